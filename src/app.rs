@@ -1,12 +1,52 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
 use ratatui::widgets::ScrollbarState;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::auth::{DeviceCode, OAuthToken};
-use crate::config::Config;
-use crate::llm::{ChatMessage, LlmClient, LlmConfig, Provider, StreamEvent};
-use crate::message::{Message, Role};
-use crate::ui::{AuthDialog, ToastLevel, ToastState};
+use crate::appearance::{Appearance, AppearanceState};
+use crate::batch::{Batch, BatchFailure, BatchLimits};
+use crate::auth::{DeviceCode, Expiring, OAuthToken, Refreshed};
+use crate::config::{
+    AutoModelConfig, BudgetConfig, ClipboardConfig, Config, ContextConfig, ContextStrategy, PreprocessConfig, ShareConfig, SyncConfig,
+    VariablesConfig,
+};
+use crate::llm::{
+    self, unsupported_hint, ChatMessage, FinishReason, InstalledModel, LlmClient, LlmConfig,
+    ModelCapabilities, ModelInfo, OllamaProvider, PullStatus, Provider, ProviderError, RateLimits, ReasoningEffort, RunningModel, StreamEvent,
+    TokenUsage, ToolCall, ToolDefinition,
+};
+use crate::commands::{self, CommandSpec, Parsed, SlashCommand, COMMANDS};
+use crate::emoji;
+use crate::history::{Edit, OperationLog};
+use crate::image::{self, Image};
+use crate::indexer::Indexer;
+use crate::paste::{self, Pasted};
+use crate::preprocess;
+use crate::profiles;
+use crate::routing::{self, Route};
+use crate::scheduler::{Activity, FrameStats};
+use crate::message::{Message, Messages, ReplyMeta, Role};
+use crate::sections::{Section, SectionState};
+use crate::session::{Session, SessionStore};
+use crate::share::{self, GuestNotice, HostNotice, ShareGuest, ShareHost, ShareMode};
+use crate::sync::{self, SyncReport};
+use crate::clipboard::{self, Copied};
+use crate::context::{self, ContextSummary};
+use crate::tokens::{self, ContextUsage};
+use crate::pricing::Pricing;
+use crate::usage::{
+    current_month, format_table, month_range, SpendCheck, UsageLedger, UsageRecord, UsageTotals,
+};
+use crate::variables::{self, Expansion};
+use crate::tools::{Dispatch, ToolDispatcher};
+use crate::ui::{
+    AttachmentsPanel, AuthDialog, ConversationSearch, DragSelection, FileTree, GlobalSearch, InputViewport, LocalModelsPanel, MessageSelection, MessageViewer, ProfilePicker, SearchAction, SectionPicker, SessionPicker, ToastLevel,
+    ToastState, ToolConfirmation,
+};
 
 /// Connection status for the LLM.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,6 +55,8 @@ pub enum ConnectionStatus {
     NotConfigured,
     /// Ready to send messages
     Ready,
+    /// Preloading a local model; messages can still be sent
+    WarmingUp,
     /// Currently streaming a response
     Streaming,
     /// An error occurred
@@ -25,15 +67,18 @@ pub enum ConnectionStatus {
 ///
 /// This enum tracks the user's progress through the connection dialog,
 /// which allows them to enter API keys or authenticate via OAuth.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum ConnectState {
     /// No connection dialog is active.
+    #[default]
     None,
     /// User has existing credentials; offer to use them or enter new ones.
     ExistingCredential {
         provider: Provider,
         masked_key: String,
         current_model: Option<String>,
+        /// Active organization/project scope for the provider, if configured
+        scope: Option<String>,
         selected: usize,
     },
     /// User is selecting how to authenticate (enter key, open browser, cancel).
@@ -81,20 +126,61 @@ pub enum ConnectState {
     ExchangingCode {
         method: crate::auth::AnthropicAuthMethod,
     },
-    /// User is selecting a model after OAuth authentication.
+    /// User is selecting a model after OAuth authentication, or for the
+    /// connected provider (Ctrl+M).
     SelectingModel {
         provider: Provider,
         selected: usize,
-        oauth_token: OAuthToken,
+        /// Token being connected with; None when switching the connected provider's model
+        oauth_token: Option<OAuthToken>,
         /// For Anthropic: which auth method was used
         anthropic_method: Option<crate::auth::AnthropicAuthMethod>,
+        /// Models to pick from, or None while the list loads
+        models: Option<Vec<ModelInfo>>,
+    },
+    /// User is typing the URL of an OpenAI-compatible local server.
+    EnteringServerUrl {
+        input: String,
+        cursor: usize,
+        error: Option<String>,
+    },
+    /// Fetching the models the local server offers.
+    FetchingServerModels {
+        /// Normalized API base, ending in `/v1`
+        api_base: String,
+    },
+    /// User is picking one of the local server's models.
+    SelectingServerModel {
+        api_base: String,
+        models: Vec<String>,
+        selected: usize,
+    },
+    /// Fetching the models installed in Ollama.
+    FetchingOllamaModels {
+        api_base: String,
+    },
+    /// User is picking one of the installed Ollama models.
+    SelectingOllamaModel {
+        api_base: String,
+        models: Vec<InstalledModel>,
+        selected: usize,
+    },
+    /// The Ollama model to use isn't installed; offer to pull it.
+    OfferingOllamaPull {
+        api_base: String,
+        /// Model that isn't installed
+        model: String,
+        /// Models that are installed, to pick from instead
+        installed: Vec<InstalledModel>,
+        selected: usize,
+    },
+    /// Downloading a model into Ollama.
+    PullingOllamaModel {
+        api_base: String,
+        model: String,
+        /// Latest progress update
+        progress: PullStatus,
     },
-}
-
-impl Default for ConnectState {
-    fn default() -> Self {
-        Self::None
-    }
 }
 
 impl ConnectState {
@@ -117,6 +203,278 @@ impl ConnectState {
             Self::SelectingAnthropicMethod { .. }
             | Self::EnteringAuthCode { .. }
             | Self::ExchangingCode { .. } => Some(Provider::Anthropic),
+            Self::EnteringServerUrl { .. }
+            | Self::FetchingServerModels { .. }
+            | Self::SelectingServerModel { .. } => Some(Provider::LocalServer),
+            Self::FetchingOllamaModels { .. }
+            | Self::SelectingOllamaModel { .. }
+            | Self::OfferingOllamaPull { .. }
+            | Self::PullingOllamaModel { .. } => Some(Provider::Ollama),
+        }
+    }
+}
+
+/// A resumed session was created with a different provider/model than the active one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelMismatch {
+    /// Provider the session was created with
+    pub session_provider: Provider,
+    /// Model the session was created with
+    pub session_model: String,
+    /// Selected option: 0 = switch back, 1 = continue with the active model
+    pub selected: usize,
+}
+
+/// Pasted paths of existing files, waiting for the user to attach the files
+/// or paste the paths as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PastedFiles {
+    /// Files the paste named
+    pub paths: Vec<PathBuf>,
+    /// The paste itself, inserted if the paths were meant as text
+    pub text: String,
+}
+
+/// Rough token estimate for a byte count (about 4 bytes per token).
+pub fn estimate_tokens(bytes: usize) -> usize {
+    bytes.div_ceil(4)
+}
+
+/// Token estimate of the conversation, redone only when it changes.
+#[derive(Debug, Clone, Default)]
+struct TokenCount {
+    /// Provider, chat message count and bytes the estimate was made for
+    key: Option<(Provider, usize, usize)>,
+    tokens: usize,
+}
+
+/// Summary of an oversized prompt awaiting confirmation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendConfirmation {
+    /// Bytes of conversation that would be sent
+    pub bytes: usize,
+    /// Estimated tokens
+    pub tokens: usize,
+}
+
+/// A request held back by a spending cap until the user confirms it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeldRequest {
+    /// A reply to the conversation as it stands
+    Reply,
+    /// More of the last reply
+    Continue,
+}
+
+/// Guards against accidental double submits, oversized prompts and overspending.
+#[derive(Debug, Clone, Default)]
+pub struct SubmitGuard {
+    /// Enter presses this soon after a submit are ignored
+    pub debounce: Duration,
+    /// Ask before sending more than this many estimated tokens (0 = never)
+    pub confirm_tokens: usize,
+    /// When the last message was sent
+    pub last_submit: Option<Instant>,
+    /// Oversized prompt waiting for confirmation
+    pub pending: Option<SendConfirmation>,
+    /// Prompt with expanded variables waiting for confirmation
+    pub expansion: Option<Expansion>,
+    /// Which variables prompts may expand
+    pub variables: VariablesConfig,
+    /// Transforms applied to the input before it is sent
+    pub preprocess: PreprocessConfig,
+    /// Spending caps per provider
+    pub budget: BudgetConfig,
+    /// Reached spending cap waiting for confirmation before sending
+    pub spend: Option<SpendCheck>,
+    /// What the confirmation sends, if not the input
+    pub held: Option<HeldRequest>,
+    /// Caps (`SpendCheck::cap`) the user chose to keep sending past this session
+    pub confirmed_caps: Vec<String>,
+    /// Caps already warned about this session, with "near" or "over"
+    pub warned_caps: Vec<String>,
+}
+
+impl SubmitGuard {
+    /// Create a guard from behavior settings.
+    pub fn new(debounce_ms: u64, confirm_tokens: usize) -> Self {
+        Self {
+            debounce: Duration::from_millis(debounce_ms),
+            confirm_tokens,
+            ..Self::default()
+        }
+    }
+
+    /// Check whether a submit now would be a bounce of the previous one.
+    pub fn is_bounce(&self) -> bool {
+        self.last_submit
+            .is_some_and(|at| at.elapsed() < self.debounce)
+    }
+}
+
+/// How often to check whether an unreachable provider is back.
+const OFFLINE_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tracks an unreachable provider so messages can wait instead of failing.
+#[derive(Debug, Default)]
+pub struct OfflineQueue {
+    /// Whether the last request could not reach the provider
+    pub offline: bool,
+    /// Connectivity check in flight
+    pub probe_rx: Option<tokio::sync::oneshot::Receiver<bool>>,
+    /// When the last check started
+    pub last_probe: Option<Instant>,
+}
+
+/// How often stored OAuth tokens are checked for expiry.
+const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most hits listed by the search across sessions.
+const GLOBAL_SEARCH_LIMIT: usize = 200;
+
+/// Mouse text selection in the chat area.
+#[derive(Debug, Default)]
+pub struct MouseState {
+    /// Where the chat text was drawn in the last frame
+    pub chat_area: Rect,
+    /// Selection being dragged, or the last one, still highlighted
+    pub drag: Option<DragSelection>,
+    /// Selected text read back at the last draw, waiting to be copied
+    pub copied: Option<String>,
+}
+
+/// Renews OAuth access tokens in the background before they expire, and
+/// warns about the ones that can't be renewed.
+#[derive(Debug, Default)]
+pub struct TokenRefresh {
+    /// Whether to check at all (off for replays, guests and tests)
+    pub enabled: bool,
+    /// Refresh in flight, then the sign-ins that still run out soon
+    pub rx: Option<tokio::sync::oneshot::Receiver<(Vec<Refreshed>, Vec<Expiring>)>>,
+    /// When the last check started
+    pub last_check: Option<Instant>,
+    /// Providers whose failed refresh was already reported
+    pub warned: Vec<Provider>,
+    /// Warn this many hours before a sign-in runs out (0 disables)
+    pub warn_hours: u64,
+    /// Sign-ins that run out within `warn_hours`, shown in the status bar
+    pub expiring: Vec<Expiring>,
+    /// Providers whose coming expiry was already announced
+    pub announced: Vec<Provider>,
+}
+
+/// A scratch fork of the conversation that is never saved.
+///
+/// Holds the conversation it was forked from, which comes back when the
+/// playground is left without keeping it.
+#[derive(Debug)]
+pub struct Playground {
+    origin: ChatState,
+    origin_session: Option<Session>,
+}
+
+/// Periodic saving of the conversation while chatting.
+#[derive(Debug, Clone, Copy)]
+pub struct Autosave {
+    /// Time between saves (zero saves only on exit)
+    pub interval: Duration,
+    /// When the last save was attempted
+    pub last: Instant,
+    /// Message count and last message length at the last save
+    saved: (usize, usize),
+    /// Time between checkpoints of a streaming reply (zero disables them)
+    pub checkpoint: Duration,
+    /// When the streaming reply was last checkpointed (or started)
+    pub checkpointed: Instant,
+    /// Whether the streaming reply has been saved unfinished
+    checkpoint_saved: bool,
+}
+
+impl Autosave {
+    /// Save every `secs` seconds; 0 disables autosave.
+    pub fn new(secs: u64) -> Self {
+        Self {
+            interval: Duration::from_secs(secs),
+            last: Instant::now(),
+            saved: (0, 0),
+            checkpoint: Duration::ZERO,
+            checkpointed: Instant::now(),
+            checkpoint_saved: false,
+        }
+    }
+
+    /// Also save streaming replies every `secs` seconds; 0 disables checkpoints.
+    pub fn with_checkpoint(mut self, secs: u64) -> Self {
+        self.checkpoint = Duration::from_secs(secs);
+        self
+    }
+}
+
+/// Follow-up prompt for providers that cannot continue a reply directly.
+const CONTINUE_PROMPT: &str =
+    "Continue exactly where your previous message stopped. Do not repeat anything or add a preamble.";
+
+/// A retry the request in flight is waiting on, for the status bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryNotice {
+    /// Which retry, from 1
+    pub attempt: u32,
+    /// Retries allowed in all
+    pub max_retries: u32,
+    /// When the request is sent again
+    pub at: Instant,
+    /// The error being retried
+    pub reason: String,
+}
+
+/// Quotas the provider last reported, and a prompt held until they reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitState {
+    /// Quotas from the headers of the last response
+    pub limits: Option<RateLimits>,
+    /// When they were received, which their reset times count from
+    pub received: Option<Instant>,
+    /// When a prompt held for the quotas to reset is sent
+    pub held_until: Option<Instant>,
+}
+
+impl RateLimitState {
+    /// How long a request of about `tokens` tokens should wait at `now`,
+    /// if a quota it needs is used up and has not reset yet.
+    pub fn wait_for(&self, tokens: u64, now: Instant) -> Option<Duration> {
+        let wait = self.limits?.wait_for(tokens)?;
+        let until = self.received? + wait;
+        until.checked_duration_since(now).filter(|wait| !wait.is_zero())
+    }
+
+    /// Drop the quotas of a provider no longer in use; a held prompt goes now.
+    fn forget(&mut self) {
+        self.limits = None;
+        self.received = None;
+        if self.held_until.is_some() {
+            self.held_until = Some(Instant::now());
+        }
+    }
+}
+
+/// Limits on automatically continuing replies cut off at the token limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoContinue {
+    /// Follow-up requests allowed per reply (0 disables)
+    pub max_follow_ups: u32,
+    /// No more follow-ups once the reply is this many estimated tokens long
+    pub max_reply_tokens: usize,
+    /// Follow-ups sent for the current reply
+    pub used: u32,
+}
+
+impl AutoContinue {
+    /// Create limits from behavior settings.
+    pub fn new(max_follow_ups: u32, max_reply_tokens: usize) -> Self {
+        Self {
+            max_follow_ups,
+            max_reply_tokens,
+            used: 0,
         }
     }
 }
@@ -149,6 +507,9 @@ pub enum InputMode {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MenuItem {
     ConnectProvider,
+    ResumeSession,
+    SearchSessions,
+    SwitchProfile,
     Exit,
 }
 
@@ -157,6 +518,9 @@ impl MenuItem {
     pub const fn all() -> &'static [MenuItem] {
         &[
             MenuItem::ConnectProvider,
+            MenuItem::ResumeSession,
+            MenuItem::SearchSessions,
+            MenuItem::SwitchProfile,
             MenuItem::Exit,
         ]
     }
@@ -165,6 +529,9 @@ impl MenuItem {
     pub const fn label(&self) -> &'static str {
         match self {
             MenuItem::ConnectProvider => "Connect Provider",
+            MenuItem::ResumeSession => "Resume Session",
+            MenuItem::SearchSessions => "Search Sessions",
+            MenuItem::SwitchProfile => "Switch Profile",
             MenuItem::Exit => "Exit",
         }
     }
@@ -179,48 +546,150 @@ impl MenuItem {
 #[derive(Debug, Default)]
 pub struct ChatState {
     /// Chat message history
-    pub messages: Vec<Message>,
+    pub messages: Messages,
     /// Current input text
     pub input: String,
     /// Cursor position in input
     pub cursor_position: usize,
+    /// Undo/redo log of conversational actions
+    pub history: OperationLog,
+    /// Expand `:shortcode:` emoji while typing
+    pub shortcodes: bool,
+    /// Show every reply as raw markdown source
+    pub raw_all: bool,
+    /// Replies flipped away from `raw_all`, by index
+    pub raw_flipped: HashSet<usize>,
+    /// Expand the reasoning streamed before replies (Ctrl+Y)
+    pub show_thinking: bool,
+    /// Show the model, timing and token usage under replies (`i` in selection)
+    pub show_info: bool,
+    /// Chat messages (prompts and replies) scrolled into view so far
+    pub read: usize,
+    /// Index of the first message that was unread when the session was opened
+    pub first_unread: Option<usize>,
+    /// Model-written summary sent in place of the oldest messages (`summarize`)
+    pub summary: Option<ContextSummary>,
+    /// Leading messages left out of the last request, to mention when it grows
+    pub dropped: usize,
+    /// System prompt set with `/system`; `None` uses `[llm] system_prompt`,
+    /// an empty one sends none
+    pub system_prompt: Option<String>,
+    /// Prompt being edited to resend (Ctrl+E), by message index
+    pub editing: Option<usize>,
+    /// Images to send with the next prompt (`/attach`)
+    pub images: Vec<Image>,
 }
 
 impl ChatState {
     /// Create a new ChatState with initial messages.
     pub fn new(messages: Vec<Message>) -> Self {
         Self {
-            messages,
+            messages: messages.into(),
             input: String::new(),
             cursor_position: 0,
+            history: OperationLog::default(),
+            shortcodes: true,
+            raw_all: false,
+            raw_flipped: HashSet::new(),
+            show_thinking: false,
+            show_info: false,
+            read: 0,
+            first_unread: None,
+            summary: None,
+            dropped: 0,
+            system_prompt: None,
+            editing: None,
+            images: Vec::new(),
         }
     }
 
+    /// Count the chat messages before `end` as read.
+    pub fn mark_read(&mut self, end: usize) {
+        let total = self.messages.iter().filter(|m| m.is_chat()).count();
+        let seen = self
+            .messages
+            .iter()
+            .take(end)
+            .filter(|m| m.is_chat())
+            .count();
+        // Undo may have removed messages that were read
+        self.read = self.read.min(total).max(seen);
+    }
+
+    /// Whether the message at `index` is shown as raw source.
+    pub fn is_raw(&self, index: usize) -> bool {
+        self.raw_all != self.raw_flipped.contains(&index)
+    }
+
     /// Handle a character input.
     pub fn handle_char(&mut self, c: char) {
         self.input.insert(self.cursor_position, c);
-        self.cursor_position += 1;
+        self.cursor_position += c.len_utf8();
+        if self.shortcodes && c == ':' {
+            emoji::expand_before_cursor(&mut self.input, &mut self.cursor_position);
+        }
+    }
+
+    /// Shortcodes matching the partial `:name` before the cursor.
+    pub fn shortcode_completions(&self) -> Vec<(&'static str, &'static str)> {
+        if !self.shortcodes {
+            return Vec::new();
+        }
+        emoji::completions(&self.input, self.cursor_position)
+    }
+
+    /// Complete the partial shortcode with the first match. Returns true if completed.
+    pub fn complete_shortcode(&mut self) -> bool {
+        match self.shortcode_completions().first() {
+            Some((code, _)) => {
+                emoji::complete(&mut self.input, &mut self.cursor_position, code)
+            }
+            None => false,
+        }
+    }
+
+    /// Slash commands matching the partial `/name` being typed.
+    pub fn command_completions(&self) -> Vec<&'static CommandSpec> {
+        commands::completions(&self.input, self.cursor_position)
+    }
+
+    /// Complete the partial command with the first match. Returns true if completed.
+    pub fn complete_command(&mut self) -> bool {
+        match self.command_completions().first() {
+            Some(spec) => {
+                self.input = format!("{} ", spec.name);
+                self.cursor_position = self.input.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+        /// Byte length of the character before the cursor.
+    fn prev_char_len(&self) -> usize {
+        self.input[..self.cursor_position]
+            .chars()
+            .next_back()
+            .map_or(0, char::len_utf8)
     }
 
     /// Handle backspace key.
     pub fn handle_backspace(&mut self) {
         if self.cursor_position > 0 {
-            self.input.remove(self.cursor_position - 1);
-            self.cursor_position -= 1;
+            self.cursor_position -= self.prev_char_len();
+            self.input.remove(self.cursor_position);
         }
     }
 
     /// Move cursor left.
     pub fn move_cursor_left(&mut self) {
-        if self.cursor_position > 0 {
-            self.cursor_position -= 1;
-        }
+        self.cursor_position -= self.prev_char_len();
     }
 
     /// Move cursor right.
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_position < self.input.len() {
-            self.cursor_position += 1;
+        if let Some(c) = self.input[self.cursor_position..].chars().next() {
+            self.cursor_position += c.len_utf8();
         }
     }
 
@@ -243,6 +712,10 @@ pub struct ScrollState {
     pub offset: usize,
     /// Scrollbar state for ratatui
     pub scrollbar: ScrollbarState,
+    /// Whether the minimap column is shown
+    pub minimap: bool,
+    /// End of the messages shown in the last frame
+    pub visible_end: usize,
 }
 
 impl ScrollState {
@@ -397,8 +870,52 @@ pub struct LlmState {
     pub status: ConnectionStatus,
     /// Receiver for streaming events
     pub stream_rx: Option<mpsc::Receiver<StreamEvent>>,
+    /// Receiver for an in-progress model warm-up
+    pub warm_up_rx: Option<tokio::sync::oneshot::Receiver<Result<(), String>>>,
     /// Current LLM configuration
     pub config: LlmConfig,
+    /// Size of the conversation sent with the request in flight, for the usage ledger
+    pub request_bytes: usize,
+    /// Length of the reply before the request in flight (non-zero when continuing)
+    pub reply_start: usize,
+    /// When the request in flight was sent
+    pub request_sent: Option<Instant>,
+    /// Tokens the provider reported for the request in flight
+    pub usage: Option<TokenUsage>,
+    /// Retry the request in flight is waiting on
+    pub retrying: Option<RetryNotice>,
+    /// Request and token quotas the provider reports
+    pub rate_limits: RateLimitState,
+    /// Follow-ups for replies cut off at the token limit
+    pub auto_continue: AutoContinue,
+    /// Pacing and rate-limit resends for `/batch`
+    pub batch_limits: BatchLimits,
+    /// Messages held back while the provider is unreachable
+    pub offline: OfflineQueue,
+    /// Background renewal of expiring OAuth tokens
+    pub token_refresh: TokenRefresh,
+    /// Models the `auto` model routes between
+    pub routing: AutoModelConfig,
+    /// Show requests instead of sending them (`--dry-run`, `/dryrun`)
+    pub dry_run: bool,
+    /// Context window from `behavior.context_window`; 0 looks it up by model
+    pub context_window: usize,
+    /// How a conversation too long for the window is shortened
+    pub context: ContextConfig,
+    /// Summary of the oldest messages being written before a request
+    pub summarizing: Option<PendingSummary>,
+    /// System prompt from `[llm] system_prompt`
+    pub system_prompt: Option<String>,
+    /// Model for the next reply only (`/retry <model>`)
+    pub chosen_model: Option<String>,
+}
+
+/// A summary of the oldest messages being written before the request it makes room for.
+pub struct PendingSummary {
+    rx: mpsc::Receiver<StreamEvent>,
+    /// Leading messages it will replace
+    covers: usize,
+    text: String,
 }
 
 impl LlmState {
@@ -407,7 +924,7 @@ impl LlmState {
         let is_configured = llm_config.is_configured();
         let client = LlmClient::new(llm_config.clone());
 
-        Self {
+        let mut state = Self {
             client: Some(client),
             status: if is_configured {
                 ConnectionStatus::Ready
@@ -415,13 +932,73 @@ impl LlmState {
                 ConnectionStatus::NotConfigured
             },
             stream_rx: None,
+            warm_up_rx: None,
             config: llm_config,
+            request_bytes: 0,
+            reply_start: 0,
+            request_sent: None,
+            usage: None,
+            retrying: None,
+            rate_limits: RateLimitState::default(),
+            auto_continue: AutoContinue::default(),
+            batch_limits: BatchLimits::default(),
+            offline: OfflineQueue::default(),
+            token_refresh: TokenRefresh::default(),
+            routing: AutoModelConfig::default(),
+            dry_run: false,
+            context_window: 0,
+            context: ContextConfig::default(),
+            summarizing: None,
+            system_prompt: None,
+            chosen_model: None,
+        };
+        state.start_warm_up();
+        state
+    }
+
+    /// Context window of the active model, in tokens.
+    pub fn context_window(&self) -> usize {
+        match self.context_window {
+            0 => tokens::context_window(self.config.provider, &self.config.model),
+            window => window,
+        }
+    }
+
+    /// Check if a model warm-up is in progress.
+    pub fn is_warming_up(&self) -> bool {
+        self.warm_up_rx.is_some()
+    }
+
+    /// Preload the model if enabled and the provider supports it.
+    fn start_warm_up(&mut self) {
+        self.warm_up_rx = None;
+        // `auto` picks its model per request, so there is nothing to preload
+        if !self.config.warm_up
+            || self.status != ConnectionStatus::Ready
+            || routing::is_auto(&self.config.model)
+        {
+            return;
+        }
+        if let Some(rx) = self.client.as_ref().and_then(|c| c.warm_up()) {
+            self.warm_up_rx = Some(rx);
+            self.status = ConnectionStatus::WarmingUp;
         }
     }
 
     /// Check if currently streaming a response.
     pub fn is_streaming(&self) -> bool {
-        self.stream_rx.is_some()
+        self.stream_rx.is_some() || self.summarizing.is_some()
+    }
+
+    /// Use an existing client, e.g. one built around a custom provider.
+    pub fn set_client(&mut self, client: LlmClient) {
+        self.status = if client.is_configured() {
+            ConnectionStatus::Ready
+        } else {
+            ConnectionStatus::NotConfigured
+        };
+        self.client = Some(client);
+        self.rate_limits.forget();
     }
 
     /// Apply the current config and recreate the client.
@@ -433,6 +1010,8 @@ impl LlmState {
         } else {
             ConnectionStatus::NotConfigured
         };
+        self.rate_limits.forget();
+        self.start_warm_up();
     }
 }
 
@@ -446,6 +1025,10 @@ pub struct App {
     pub menu: MenuState,
     /// Animation state: cursor blink, banner animation
     pub animation: AnimationState,
+    /// Event loop wake-ups and frame draw times
+    pub frames: FrameStats,
+    /// Whether the profiling overlay (F12) is shown
+    pub show_profile: bool,
     /// LLM state: client, config, status, streaming
     pub llm: LlmState,
     /// Toast notification state
@@ -454,6 +1037,17 @@ pub struct App {
     pub connect: ConnectState,
     /// Receiver for async API key validation results
     pub validation_rx: Option<tokio::sync::oneshot::Receiver<Result<(), String>>>,
+    /// Receiver for the model list shown by the model selection dialog
+    pub model_list_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<ModelInfo>, String>>>,
+    /// Session-scoped cache of provider model lists.
+    /// Maps provider storage key to the models its API reported.
+    pub model_lists: HashMap<String, Vec<ModelInfo>>,
+    /// Receiver for a local server's model list
+    pub server_models_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<String>, String>>>,
+    /// Receiver for the list of installed Ollama models
+    pub ollama_models_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<InstalledModel>, String>>>,
+    /// Receiver for the progress of an Ollama model pull
+    pub ollama_pull_rx: Option<mpsc::Receiver<Result<PullStatus, String>>>,
     /// Receiver for async OAuth polling results
     pub oauth_rx: Option<tokio::sync::oneshot::Receiver<Result<OAuthToken, String>>>,
     /// Receiver for async device code request
@@ -466,6 +1060,78 @@ pub struct App {
     /// Session-scoped cache of validated OAuth tokens (cleared on app restart).
     /// Maps provider storage key (e.g., "github_copilot") to validation status.
     pub validated_tokens: HashMap<String, bool>,
+    /// Local models panel (`/local`), when open
+    pub local_models: Option<LocalModelsPanel>,
+    /// Receiver for an in-flight `/api/ps` query or unload
+    pub local_models_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<RunningModel>, String>>>,
+    /// Pending prompt about a resumed session's original provider/model
+    pub model_mismatch: Option<ModelMismatch>,
+    /// Pasted file paths waiting to be attached or pasted as text
+    pub pasted_files: Option<PastedFiles>,
+    /// Fold state of topic sections
+    pub sections: SectionState,
+    /// Jump-to-section picker, when open
+    pub section_picker: Option<SectionPicker>,
+    /// Message selection mode (Ctrl+V), when on
+    pub selection: Option<MessageSelection>,
+    /// Saved session browser, when open
+    pub session_picker: Option<SessionPicker>,
+    /// Profile picker (Switch Profile in the menu)
+    pub profile_picker: Option<ProfilePicker>,
+    /// Mouse text selection
+    pub mouse: MouseState,
+    /// Search bar over the conversation (Ctrl+F)
+    pub search: Option<ConversationSearch>,
+    /// Search across saved sessions (Ctrl+Shift+F), when open
+    pub global_search: Option<GlobalSearch>,
+    /// Tools the model may call, guarded by `[tools]` permissions
+    pub tools: ToolDispatcher,
+    /// Tool call waiting for the user's permission
+    pub tool_confirmation: Option<ToolConfirmation>,
+    /// Double-submit and large prompt protection
+    pub submit_guard: SubmitGuard,
+    /// Full-screen message viewer, when open
+    pub viewer: Option<MessageViewer>,
+    /// Workspace file tree, when open
+    pub file_tree: Option<FileTree>,
+    /// Attachment manager, when open
+    pub attachments: Option<AttachmentsPanel>,
+    /// Rows of the draft shown in the input box
+    pub input_view: InputViewport,
+    /// Ledger completed requests are recorded in (opened by the binary, not in tests)
+    pub usage: Option<UsageLedger>,
+    /// Tokens and cost since scry started, for the status bar
+    pub session_usage: UsageTotals,
+    /// Model prices requests are costed with
+    pub pricing: Pricing,
+    /// Estimated tokens of the conversation and draft, for the context meter
+    context_tokens: TokenCount,
+    /// Where conversations are saved (opened by the binary, not in tests)
+    pub sessions: Option<Arc<dyn SessionStore>>,
+    /// Background worker indexing saved sessions; without it saves index inline
+    pub indexer: Option<Indexer>,
+    /// Saved record of the current conversation, once it has been saved
+    pub session: Option<Session>,
+    /// Scratch fork being experimented in, if any (see [`App::toggle_playground`])
+    pub playground: Option<Playground>,
+    /// Where sessions are synced to (`[sync]`)
+    pub sync_config: SyncConfig,
+    /// Receiver for a `/sync` running in the background
+    pub sync_rx: Option<tokio::sync::oneshot::Receiver<Result<SyncReport, String>>>,
+    /// Live sharing settings (`[share]`)
+    pub share_config: ShareConfig,
+    /// How copies reach the clipboard (`[clipboard]`)
+    pub clipboard_config: ClipboardConfig,
+    /// This conversation, shared live with guests (`/share`)
+    pub share: Option<ShareHost>,
+    /// Someone else's shared conversation this instance is attached to
+    pub guest: Option<ShareGuest>,
+    /// When the conversation is saved while chatting
+    pub autosave: Autosave,
+    /// Light or dark palette (`[appearance]`, `/theme`)
+    pub appearance: AppearanceState,
+    /// Prompts from a file being sent one by one (`/batch`)
+    pub batch: Option<Batch>,
 }
 
 impl App {
@@ -480,19 +1146,93 @@ impl App {
         let llm_config = LlmConfig::from_env_and_config(Some(&config.llm));
 
         Self {
-            chat: ChatState::new(vec![Message::system_banner(banner)]),
-            scroll: ScrollState::default(),
+            chat: ChatState {
+                shortcodes: config.behavior.emoji_shortcodes,
+                show_thinking: config.behavior.show_thinking,
+                ..ChatState::new(vec![Message::system_banner(banner)])
+            },
+            scroll: ScrollState {
+                minimap: config.behavior.minimap,
+                ..ScrollState::default()
+            },
             menu: MenuState::default(),
             animation: AnimationState::default(),
-            llm: LlmState::new(llm_config),
-            toasts: ToastState::default(),
+            frames: FrameStats::default(),
+            show_profile: false,
+            llm: LlmState {
+                auto_continue: AutoContinue::new(
+                    config.behavior.auto_continue,
+                    config.behavior.max_reply_tokens,
+                ),
+                batch_limits: BatchLimits::new(&config.behavior),
+                routing: config.llm.auto.clone(),
+                token_refresh: TokenRefresh {
+                    warn_hours: config.behavior.expiry_warning_hours,
+                    ..TokenRefresh::default()
+                },
+                context_window: config.behavior.context_window,
+                context: config.context.clone(),
+                system_prompt: config.llm.system_prompt.clone(),
+                ..LlmState::new(llm_config)
+            },
+            toasts: ToastState::with_config(&config.toasts),
             connect: ConnectState::default(),
             validation_rx: None,
+            model_list_rx: None,
+            model_lists: HashMap::new(),
+            server_models_rx: None,
+            appearance: AppearanceState::new(&config.appearance),
+            batch: None,
+            ollama_models_rx: None,
+            ollama_pull_rx: None,
             oauth_rx: None,
             device_code_rx: None,
             auth_code_rx: None,
             api_key_conversion_rx: None,
             validated_tokens: HashMap::new(),
+            local_models: None,
+            local_models_rx: None,
+            model_mismatch: None,
+            pasted_files: None,
+            sections: SectionState::default(),
+            section_picker: None,
+            selection: None,
+            session_picker: None,
+            profile_picker: None,
+            mouse: MouseState::default(),
+            search: None,
+            global_search: None,
+            tools: ToolDispatcher::builtin(&config.tools),
+            tool_confirmation: None,
+            submit_guard: SubmitGuard {
+                variables: config.variables.clone(),
+                preprocess: config.preprocess.clone(),
+                budget: config.budget.clone(),
+                ..SubmitGuard::new(
+                    config.behavior.submit_debounce_ms,
+                    config.behavior.confirm_send_tokens,
+                )
+            },
+            viewer: None,
+            file_tree: None,
+            attachments: None,
+            input_view: InputViewport::default(),
+            usage: None,
+            session_usage: UsageTotals::default(),
+            pricing: Pricing::new(&config.pricing),
+            context_tokens: TokenCount::default(),
+            sessions: None,
+            indexer: None,
+            playground: None,
+            sync_config: config.sync.clone(),
+            sync_rx: None,
+            share_config: config.share.clone(),
+            clipboard_config: config.clipboard.clone(),
+            share: None,
+            guest: None,
+            session: None,
+            autosave: Autosave::new(config.storage.autosave_secs)
+                .with_checkpoint(config.storage.checkpoint_secs),
         }
     }
 
@@ -508,21 +1248,95 @@ impl App {
         let llm_config = LlmConfig::from_env_and_config(Some(&config.llm));
 
         Self {
-            chat: ChatState::new(vec![Message::assistant(
-                "Welcome! Type a message and press Enter to chat. Press Ctrl+P for menu.".to_string(),
-            )]),
-            scroll: ScrollState::default(),
+            chat: ChatState {
+                shortcodes: config.behavior.emoji_shortcodes,
+                show_thinking: config.behavior.show_thinking,
+                ..ChatState::new(vec![Message::assistant(
+                    "Welcome! Type a message and press Enter to chat. Press Ctrl+P for menu.".to_string(),
+                )])
+            },
+            scroll: ScrollState {
+                minimap: config.behavior.minimap,
+                ..ScrollState::default()
+            },
             menu: MenuState::default(),
             animation: AnimationState::no_banner(),
-            llm: LlmState::new(llm_config),
-            toasts: ToastState::default(),
+            frames: FrameStats::default(),
+            show_profile: false,
+            llm: LlmState {
+                auto_continue: AutoContinue::new(
+                    config.behavior.auto_continue,
+                    config.behavior.max_reply_tokens,
+                ),
+                batch_limits: BatchLimits::new(&config.behavior),
+                routing: config.llm.auto.clone(),
+                token_refresh: TokenRefresh {
+                    warn_hours: config.behavior.expiry_warning_hours,
+                    ..TokenRefresh::default()
+                },
+                context_window: config.behavior.context_window,
+                context: config.context.clone(),
+                system_prompt: config.llm.system_prompt.clone(),
+                ..LlmState::new(llm_config)
+            },
+            toasts: ToastState::with_config(&config.toasts),
             connect: ConnectState::default(),
             validation_rx: None,
+            model_list_rx: None,
+            model_lists: HashMap::new(),
+            server_models_rx: None,
+            appearance: AppearanceState::new(&config.appearance),
+            batch: None,
+            ollama_models_rx: None,
+            ollama_pull_rx: None,
             oauth_rx: None,
             device_code_rx: None,
             auth_code_rx: None,
             api_key_conversion_rx: None,
             validated_tokens: HashMap::new(),
+            local_models: None,
+            local_models_rx: None,
+            model_mismatch: None,
+            pasted_files: None,
+            sections: SectionState::default(),
+            section_picker: None,
+            selection: None,
+            session_picker: None,
+            profile_picker: None,
+            mouse: MouseState::default(),
+            search: None,
+            global_search: None,
+            tools: ToolDispatcher::builtin(&config.tools),
+            tool_confirmation: None,
+            submit_guard: SubmitGuard {
+                variables: config.variables.clone(),
+                preprocess: config.preprocess.clone(),
+                budget: config.budget.clone(),
+                ..SubmitGuard::new(
+                    config.behavior.submit_debounce_ms,
+                    config.behavior.confirm_send_tokens,
+                )
+            },
+            viewer: None,
+            file_tree: None,
+            attachments: None,
+            input_view: InputViewport::default(),
+            usage: None,
+            session_usage: UsageTotals::default(),
+            pricing: Pricing::new(&config.pricing),
+            context_tokens: TokenCount::default(),
+            sessions: None,
+            indexer: None,
+            playground: None,
+            sync_config: config.sync.clone(),
+            sync_rx: None,
+            share_config: config.share.clone(),
+            clipboard_config: config.clipboard.clone(),
+            share: None,
+            guest: None,
+            session: None,
+            autosave: Autosave::new(config.storage.autosave_secs)
+                .with_checkpoint(config.storage.checkpoint_secs),
         }
     }
 
@@ -577,1271 +1391,7075 @@ impl App {
             return;
         }
 
-        // Add user message
-        self.chat.messages.push(Message::user(self.chat.input.clone()));
+        if let Some(parsed) = commands::parse(&self.chat.input) {
+            match parsed {
+                Parsed::Command(command, args) => {
+                    let args = args.to_string();
+                    self.run_command(command, &args);
+                }
+                Parsed::Unknown(name) => {
+                    self.toast_warning(format!("Unknown command {}; type /help for a list", name));
+                }
+            }
+            return;
+        }
 
-        // Check if LLM is configured
-        if let Some(client) = &self.llm.client {
-            if client.is_configured() {
-                // Convert message history to API format (skip system banners)
-                let api_messages: Vec<ChatMessage> = self
-                    .chat
-                    .messages
-                    .iter()
-                    .filter(|m| !m.is_system_banner())
-                    .map(|m| ChatMessage {
-                        role: match m.role {
-                            Role::User => "user".to_string(),
-                            Role::Assistant => "assistant".to_string(),
-                        },
-                        content: m.content.clone(),
-                    })
-                    .collect();
+        // A second Enter right after sending is almost always accidental
+        if self.submit_guard.is_bounce() {
+            return;
+        }
 
-                // Start streaming
-                self.llm.stream_rx = Some(client.stream_chat(api_messages));
-                self.llm.status = ConnectionStatus::Streaming;
-                
-                // Add empty assistant message that will be filled by streaming
-                self.chat.messages.push(Message::assistant(String::new()));
+        // Attached to someone else's session: the host runs the prompt
+        if let Some(guest) = &self.guest {
+            let text = self.chat.input.trim().to_string();
+            if guest.send_prompt(text) {
+                self.submit_guard.last_submit = Some(Instant::now());
+                self.chat.clear_input();
+            } else if guest.closed {
+                self.toast_warning("The shared session has ended".to_string());
             } else {
-                // Not configured - show helpful message
-                self.chat.messages.push(Message::assistant(
-                    "No API key configured. Set ANTHROPIC_API_KEY environment variable or add it to your config file.".to_string()
-                ));
+                self.toast_warning(format!("{}'s session is read-only", guest.host));
             }
-        } else {
-            // Fallback echo
-            self.chat
-                .messages
-                .push(Message::assistant(format!("You said: {}", self.chat.input)));
+            return;
         }
 
-        // Clear input
-        self.chat.clear_input();
-    }
-
-    /// Process streaming events. Call this in the event loop.
-    pub fn process_stream(&mut self) {
-        if let Some(rx) = &mut self.llm.stream_rx {
-            // Try to receive without blocking
-            match rx.try_recv() {
-                Ok(event) => match event {
-                    StreamEvent::Token(token) => {
-                        // Append token to the last message
-                        if let Some(last) = self.chat.messages.last_mut() {
-                            if last.role == Role::Assistant {
-                                last.content.push_str(&token);
-                            }
-                        }
-                    }
-                    StreamEvent::Done => {
-                        self.llm.stream_rx = None;
-                        self.llm.status = ConnectionStatus::Ready;
-                    }
-                    StreamEvent::Error(e) => {
-                        // Append error to the last message or create new one
-                        if let Some(last) = self.chat.messages.last_mut() {
-                            if last.role == Role::Assistant && last.content.is_empty() {
-                                last.content = format!("Error: {}", e);
-                            }
-                        }
-                        self.llm.stream_rx = None;
-                        self.llm.status = ConnectionStatus::Error(e);
-                    }
-                    StreamEvent::AuthError => {
-                        use crate::auth::AuthStorage;
-                        
-                        // Clear invalid credentials from storage
-                        if self.llm.config.provider == Provider::GitHubCopilot {
-                            let mut storage = AuthStorage::load().unwrap_or_default();
-                            storage.remove(Provider::GitHubCopilot.storage_key());
-                            let _ = storage.save();
-                            
-                            // Clear validation cache
-                            self.validated_tokens.remove(Provider::GitHubCopilot.storage_key());
-                        }
-                        
-                        // Show error toast but preserve chat history
-                        self.toast_error("Session expired. Please reconnect to continue chatting.".to_string());
-                        
-                        // Update status
-                        self.llm.stream_rx = None;
-                        self.llm.status = ConnectionStatus::NotConfigured;
-                    }
-                },
-                Err(mpsc::error::TryRecvError::Empty) => {
-                    // No events yet, continue
-                }
-                Err(mpsc::error::TryRecvError::Disconnected) => {
-                    // Channel closed
-                    self.llm.stream_rx = None;
-                    if self.llm.status == ConnectionStatus::Streaming {
-                        self.llm.status = ConnectionStatus::Ready;
-                    }
+        // Show what variables expand to before anything leaves the machine
+        if variables::has_placeholders(&self.chat.input) {
+            match variables::expand(&self.chat.input, &self.submit_guard.variables) {
+                Ok(expansion) => self.submit_guard.expansion = Some(expansion),
+                Err(e) => {
+                    self.toast_error(e.to_string());
                 }
             }
+            return;
         }
-    }
 
-    /// Check if currently streaming a response.
-    pub fn is_streaming(&self) -> bool {
-        self.llm.is_streaming()
+        self.check_budget_and_send();
     }
 
-    /// Handle a character input.
-    pub fn handle_char(&mut self, c: char) {
-        self.chat.handle_char(c);
+    /// Answer the variable expansion preview: send the expanded prompt, or keep editing.
+    pub fn confirm_expansion(&mut self, send: bool) {
+        let Some(expansion) = self.submit_guard.expansion.take() else {
+            return;
+        };
+        if send {
+            self.chat.input = expansion.text;
+            self.chat.cursor_position = self.chat.input.len();
+            self.check_budget_and_send();
+        }
     }
 
-    /// Handle backspace key.
-    pub fn handle_backspace(&mut self) {
-        self.chat.handle_backspace();
+    /// Send the input, asking first if the provider's spending cap is
+    /// reached and `[budget] block` is set.
+    fn check_budget_and_send(&mut self) {
+        if !self.llm.dry_run {
+            if let Some(check) = self.blocking_cap() {
+                self.submit_guard.spend = Some(check);
+                return;
+            }
+        }
+        self.check_size_and_send();
     }
 
-    /// Move cursor left.
-    pub fn move_cursor_left(&mut self) {
-        self.chat.move_cursor_left();
+    /// Hold back a request that is not the input, as a retry, continuation
+    /// or batch prompt, while a blocking cap is unconfirmed. Returns true if
+    /// `request` waits for the answer.
+    fn hold_for_budget(&mut self, request: HeldRequest) -> bool {
+        let Some(check) = self.blocking_cap() else {
+            return false;
+        };
+        self.submit_guard.spend = Some(check);
+        self.submit_guard.held = Some(request);
+        true
     }
 
-    /// Move cursor right.
-    pub fn move_cursor_right(&mut self) {
-        self.chat.move_cursor_right();
+    /// The cap stopping requests: reached by the current provider with
+    /// `[budget] block` set, and not confirmed this session.
+    fn blocking_cap(&self) -> Option<SpendCheck> {
+        if !self.submit_guard.budget.block {
+            return None;
+        }
+        self.spend_check()
+            .filter(SpendCheck::exceeded)
+            .filter(|check| !self.submit_guard.confirmed_caps.contains(&check.cap()))
     }
 
-    /// Scroll up one line.
-    pub fn scroll_up(&mut self) {
-        self.scroll.scroll_up();
+    /// Answer the spending cap confirmation: send anyway (and stop asking
+    /// about this cap), or keep the input for later.
+    pub fn confirm_spend(&mut self, send: bool) {
+        let Some(check) = self.submit_guard.spend.take() else {
+            return;
+        };
+        let held = self.submit_guard.held.take();
+        if !send {
+            // A held prompt fails instead, so a batch can move on
+            if held == Some(HeldRequest::Reply) && self.awaits_reply() {
+                let error = ProviderError::Other(format!("{}; not sent", check.message()));
+                let provider = self.llm.config.provider.display_name();
+                self.chat.messages.push(Message::error(error, provider));
+            }
+            return;
+        }
+        self.submit_guard.confirmed_caps.push(check.cap());
+        match held {
+            Some(HeldRequest::Reply) if self.awaits_reply() => self.start_request(),
+            Some(HeldRequest::Reply) => {}
+            Some(HeldRequest::Continue) => {
+                self.continue_reply();
+            }
+            None => self.check_size_and_send(),
+        }
     }
 
-    /// Scroll down one line.
-    pub fn scroll_down(&mut self, max_scroll: usize) {
-        self.scroll.scroll_down(max_scroll);
+    /// Whether the conversation ends in a prompt or tool result with no reply yet.
+    fn awaits_reply(&self) -> bool {
+        self.chat
+            .messages
+            .last()
+            .is_some_and(|m| m.is_tool_result() || (m.is_chat() && m.role == Role::User))
     }
 
-    /// Scroll up by page size.
-    pub fn scroll_page_up(&mut self, page_size: usize) {
-        self.scroll.scroll_page_up(page_size);
+    /// Where the current provider stands against its `[budget]` caps, if
+    /// close to one.
+    fn spend_check(&self) -> Option<SpendCheck> {
+        let ledger = self.usage.as_ref()?;
+        let provider = self.llm.config.provider.storage_key();
+        ledger
+            .check_budget(provider, &self.submit_guard.budget, chrono::Utc::now())
+            .ok()
+            .flatten()
     }
 
-    /// Scroll down by page size.
-    pub fn scroll_page_down(&mut self, max_scroll: usize, page_size: usize) {
-        self.scroll.scroll_page_down(max_scroll, page_size);
+    /// Toast once when the provider nears a spending cap, and once more when it reaches it.
+    fn warn_about_spending(&mut self) {
+        let Some(check) = self.spend_check() else {
+            return;
+        };
+        let level = if check.exceeded() { "over" } else { "near" };
+        let key = format!("{} {}", check.cap(), level);
+        if self.submit_guard.warned_caps.contains(&key) {
+            return;
+        }
+        self.submit_guard.warned_caps.push(key);
+        if !check.exceeded() {
+            self.toast_warning(check.message());
+        } else if self.submit_guard.budget.block {
+            self.toast_error(format!("{}; requests now ask first", check.message()));
+        } else {
+            self.toast_error(check.message());
+        }
     }
 
-    /// Scroll to top.
-    pub fn scroll_to_top(&mut self) {
-        self.scroll.scroll_to_top();
-    }
+    /// Send the input, asking first if the conversation is unusually large.
+    fn check_size_and_send(&mut self) {
+        // Ask before sending an unusually large conversation
+        if self.submit_guard.confirm_tokens > 0 && self.llm.client.is_some() && !self.llm.dry_run {
+            let bytes = self.chat.input.len()
+                + self
+                    .chat
+                    .messages
+                    .iter()
+                    .filter(|m| m.is_sent())
+                    .map(|m| m.sent_text().len())
+                    .sum::<usize>();
+            let tokens = estimate_tokens(bytes);
+            if tokens > self.submit_guard.confirm_tokens {
+                self.submit_guard.pending = Some(SendConfirmation { bytes, tokens });
+                return;
+            }
+        }
 
-    /// Scroll to bottom.
-    pub fn scroll_to_bottom(&mut self, max_scroll: usize) {
-        self.scroll.scroll_to_bottom(max_scroll);
+        self.send_input();
     }
 
-    /// Update scrollbar state.
-    pub fn update_scroll_state(&mut self, total_items: usize) {
-        self.scroll.update(total_items);
-    }
-
-    /// Toggle menu visibility.
-    pub fn toggle_menu(&mut self) {
-        self.menu.toggle();
+    /// Answer the large prompt confirmation: send the input, or keep it for editing.
+    pub fn confirm_send(&mut self, send: bool) {
+        if self.submit_guard.pending.take().is_some() && send {
+            self.send_input();
+        }
     }
 
-    /// Move menu selection up.
-    pub fn menu_up(&mut self) {
-        self.menu.up();
+    /// Add the input as a user message and request a reply.
+    fn send_input(&mut self) {
+        let author = self.share.as_ref().map(|host| host.name.clone());
+        self.send_input_from(author, true);
     }
 
-    /// Move menu selection down.
-    pub fn menu_down(&mut self, menu_items_count: usize, submenu_items_count: usize) {
-        self.menu.down(menu_items_count, submenu_items_count);
-    }
+    /// Send the input as a prompt from `author`, a participant in a shared
+    /// session. Only the host's own input (`local`) replaces the prompt being
+    /// edited and carries the staged images.
+    fn send_input_from(&mut self, author: Option<String>, local: bool) {
+        if self.llm.dry_run {
+            self.show_dry_run(local);
+            return;
+        }
+        let edited = self.chat.editing.filter(|_| local);
+        if edited.is_some() {
+            if self.is_streaming() {
+                self.toast_warning("Wait for the response to finish before resending".to_string());
+                return;
+            }
+            self.chat.editing = None;
+        }
+        if local {
+            self.submit_guard.last_submit = Some(Instant::now());
+        }
 
-    /// Get the currently selected menu item.
-    pub fn selected_menu_item(&self) -> Option<&MenuItem> {
-        App::menu_items().get(self.menu.selected)
-    }
+        // An edited prompt replaces the original and everything after it
+        let replaced: Vec<Message> = match edited.filter(|&index| self.is_prompt_at(index)) {
+            Some(index) => self.chat.messages.drain(index..).collect(),
+            None => Vec::new(),
+        };
+        if !replaced.is_empty() {
+            let kept = self.chat.messages.iter().filter(|m| m.is_sent()).count();
+            if self.chat.summary.as_ref().is_some_and(|s| s.covers >= kept) {
+                self.chat.summary = None;
+            }
+        }
 
-    /// Get the currently selected provider (when in provider submenu).
-    pub fn selected_provider(&self) -> Option<Provider> {
-        if self.menu.in_submenu {
-            Provider::all().get(self.menu.submenu_selected).copied()
-        } else {
-            None
+        // Add user message
+        let first_new = self.chat.messages.len();
+        let text = preprocess::apply(&self.chat.input, &self.submit_guard.preprocess);
+        let mut prompt = Message::user(text);
+        prompt.author = author;
+        if local {
+            prompt.images = std::mem::take(&mut self.chat.images);
         }
-    }
+        self.chat.messages.push(prompt);
 
-    /// Switch to a new provider.
-    pub fn switch_provider(&mut self, provider: Provider) {
-        self.llm.config.provider = provider;
-        self.llm.config.api_base = provider.default_api_base().to_string();
-        self.llm.config.model = provider.default_model().to_string();
-        
-        // Try to load API key from environment
-        let env_var = provider.env_var_name();
-        if !env_var.is_empty() {
-            if let Ok(key) = std::env::var(env_var) {
-                self.llm.config.api_key = key;
+        // Check if LLM is configured
+        if let Some(client) = &self.llm.client {
+            if self.llm.offline.offline && client.is_configured() {
+                // Known to be offline: wait for the connection instead of failing again
+                if let Some(last) = self.chat.messages.last_mut() {
+                    last.queued = true;
+                }
+            } else if let Some(wait) = self.rate_limit_wait().filter(|_| client.is_configured()) {
+                // The quota left would only get a 429: wait for it to reset
+                if let Some(last) = self.chat.messages.last_mut() {
+                    last.queued = true;
+                }
+                self.llm.rate_limits.held_until = Some(Instant::now() + wait);
+                self.toast_warning(format!(
+                    "Rate limit nearly used up — sending in {}s",
+                    wait.as_secs_f64().ceil()
+                ));
+            } else if client.is_configured() {
+                self.start_request();
             } else {
-                self.llm.config.api_key.clear();
+                // Not configured - show helpful message
+                self.chat.messages.push(Message::assistant(
+                    "No API key configured. Set ANTHROPIC_API_KEY environment variable or add it to your config file.".to_string()
+                ));
             }
         } else {
-            // Provider doesn't need an API key (e.g., Ollama)
-            self.llm.config.api_key.clear();
+            // Fallback echo
+            self.chat
+                .messages
+                .push(Message::assistant(format!("You said: {}", self.chat.input)));
         }
-        
-        self.llm.apply_config();
-        self.menu.close();
-        
-        // Add a message about the provider switch
-        let status = if self.llm.config.is_configured() || !provider.requires_api_key() {
-            format!("Switched to {}. Ready to chat!", provider.display_name())
+
+        let count = self.chat.messages.len() - first_new;
+        if replaced.is_empty() {
+            self.chat.history.record(
+                "send message",
+                Edit::Remove {
+                    index: first_new,
+                    count,
+                },
+            );
         } else {
-            format!(
-                "Switched to {}. Set {} to connect.",
-                provider.display_name(),
-                provider.env_var_name()
-            )
-        };
-        self.chat.messages.push(Message::assistant(status));
-    }
+            self.chat.history.record(
+                "edit and resend",
+                Edit::Splice {
+                    index: first_new,
+                    count,
+                    messages: replaced,
+                },
+            );
+        }
 
-    /// Get the list of menu items.
-    pub fn menu_items() -> &'static [MenuItem] {
-        MenuItem::all()
+        // Clear input
+        self.chat.clear_input();
     }
 
-    /// Get max scroll offset based on message count.
-    pub fn max_scroll(&self) -> usize {
-        self.chat.max_scroll()
+    /// Show the request sending the input would make, keeping the draft (`/dryrun`).
+    fn show_dry_run(&mut self, local: bool) {
+        let text = preprocess::apply(&self.chat.input, &self.submit_guard.preprocess);
+        // An edited prompt is sent without the original and what followed it
+        let tail = self
+            .chat
+            .editing
+            .filter(|&index| local && self.is_prompt_at(index))
+            .map(|index| self.chat.messages.split_off(index));
+        let mut prompt = Message::user(text);
+        if local {
+            prompt.images = self.chat.images.clone();
+        }
+        self.chat.messages.push(prompt);
+        let messages = self.api_messages();
+        let messages = self.fit_context(messages);
+        let route = self.route_request();
+        self.chat.messages.pop();
+        self.chat.messages.extend(tail.into_iter().flatten());
+
+        let model = route.as_ref().map_or(&self.llm.config.model, |route| &route.model);
+        let tools = self.offered_tools(model);
+        let preview = match (&self.llm.client, route) {
+            (Some(client), None) => client.preview_request(messages, &tools),
+            (_, route) => LlmClient::new(LlmConfig {
+                model: route.map_or_else(|| self.llm.config.model.clone(), |r| r.model),
+                ..self.llm.config.clone()
+            })
+            .preview_request(messages, &tools),
+        };
+        let note = match preview {
+            Some(preview) => format!("Dry run, not sent:\n\n```\n{}\n```", preview),
+            None => "Dry run: this provider makes no request to show".to_string(),
+        };
+        self.chat.messages.push(Message::system_note(note));
     }
 
-    // ─────────────────────────────────────────────────────────────────────────────
-    // Toast notification methods
-    // ─────────────────────────────────────────────────────────────────────────────
-
-    /// Add a toast notification.
-    pub fn toast(&mut self, message: impl Into<String>, level: ToastLevel) -> u64 {
-        self.toasts.push(message, level)
+    /// Turn dry runs on or off.
+    pub fn toggle_dry_run(&mut self) {
+        self.llm.dry_run = !self.llm.dry_run;
+        if self.llm.dry_run {
+            self.toast_info("Dry run on: submits show the request instead of sending it".to_string());
+        } else {
+            self.toast_info("Dry run off".to_string());
+        }
     }
 
-    /// Add an info toast.
-    pub fn toast_info(&mut self, message: impl Into<String>) -> u64 {
-        self.toasts.info(message)
+    /// Switch between the light and dark palette (`/theme [light|dark|auto]`).
+    /// No argument toggles; `auto` goes back to the `[appearance]` mode.
+    pub fn set_theme(&mut self, args: &str) {
+        let forced = match args {
+            "" => Some(self.appearance.current.toggled()),
+            "light" => Some(Appearance::Light),
+            "dark" => Some(Appearance::Dark),
+            "auto" => None,
+            _ => {
+                self.toast_warning("Usage: /theme [light|dark|auto]".to_string());
+                return;
+            }
+        };
+        self.appearance.set(forced);
+        let current = self.appearance.current.label();
+        let message = match forced {
+            Some(_) => format!("Theme: {}", current),
+            None => format!("Theme follows the config ({} now)", current),
+        };
+        self.toast_info(message);
+    }
+
+    /// Re-check a scheduled or system appearance; call on each tick.
+    pub fn tick_appearance(&mut self) {
+        self.appearance.tick();
+    }
+
+    /// The conversation in API format (skips banners, notes and error cards).
+    fn api_messages(&self) -> Vec<ChatMessage> {
+        // Images sent earlier are left out after switching to a text-only model
+        let vision = ModelCapabilities::for_model(self.llm.config.provider, &self.llm.config.model).vision;
+        // Calls left without a result, as when the reply was stopped, are not sent back
+        let answered: Vec<&str> = self
+            .chat
+            .messages
+            .iter()
+            .filter_map(|m| m.tool_result.as_ref())
+            .map(|result| result.call_id.as_str())
+            .collect();
+        self.chat
+            .messages
+            .iter()
+            .filter(|m| m.is_sent())
+            .map(|m| match &m.tool_result {
+                Some(result) => ChatMessage {
+                    role: "tool".to_string(),
+                    content: result.output.clone(),
+                    tool_call_id: Some(result.call_id.clone()),
+                    ..ChatMessage::default()
+                },
+                None => ChatMessage {
+                    role: match m.role {
+                        Role::User => "user".to_string(),
+                        Role::Assistant => "assistant".to_string(),
+                    },
+                    content: m.content.clone(),
+                    images: if vision {
+                        m.images.iter().map(|image| image.data.clone()).collect()
+                    } else {
+                        Vec::new()
+                    },
+                    tool_calls: m
+                        .tool_calls
+                        .iter()
+                        .filter(|call| answered.contains(&call.id.as_str()))
+                        .cloned()
+                        .collect(),
+                    tool_call_id: None,
+                },
+            })
+            .collect()
     }
 
-    /// Add a success toast.
-    pub fn toast_success(&mut self, message: impl Into<String>) -> u64 {
-        self.toasts.success(message)
+    /// The tools offered to `model`: those the `[tools]` permissions do not
+    /// deny, and none if the model cannot call tools.
+    fn offered_tools(&self, model: &str) -> Vec<ToolDefinition> {
+        if !ModelCapabilities::for_model(self.llm.config.provider, model).tools {
+            return Vec::new();
+        }
+        self.tools.definitions()
     }
 
-    /// Add a warning toast.
-    pub fn toast_warning(&mut self, message: impl Into<String>) -> u64 {
-        self.toasts.warning(message)
+    /// Start streaming a reply to `messages`, on the routed model if there is
+    /// one. Returns false without a client.
+    fn stream(&mut self, messages: Vec<ChatMessage>, route: Option<&Route>) -> bool {
+        if self.llm.client.is_none() {
+            return false;
+        }
+        let messages = self.fit_context(messages);
+        let Some(client) = &self.llm.client else {
+            return false;
+        };
+        self.llm.request_bytes = messages.iter().map(|m| m.content.len()).sum();
+        let model = route.map_or(&self.llm.config.model, |route| &route.model);
+        let tools = self.offered_tools(model);
+        let rx = match route {
+            Some(route) => LlmClient::new(LlmConfig {
+                model: route.model.clone(),
+                ..self.llm.config.clone()
+            })
+            .stream_chat_with_tools(messages, tools),
+            None => client.stream_chat_with_tools(messages, tools),
+        };
+        self.llm.stream_rx = Some(rx);
+        self.llm.status = ConnectionStatus::Streaming;
+        self.llm.request_sent = Some(Instant::now());
+        self.llm.usage = None;
+        self.llm.retrying = None;
+        self.autosave.checkpointed = Instant::now();
+        self.autosave.checkpoint_saved = false;
+        true
+    }
+
+    /// The system prompt sent ahead of this conversation, if any.
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.chat
+            .system_prompt
+            .as_deref()
+            .or(self.llm.system_prompt.as_deref())
+            .filter(|prompt| !prompt.is_empty())
+    }
+
+    /// Estimated tokens of the system prompt.
+    fn system_tokens(&self) -> usize {
+        self.system_prompt()
+            .map_or(0, |prompt| tokens::message_tokens(prompt, self.llm.config.provider))
+    }
+
+    /// Tokens the conversation may use, leaving room for the reply and the
+    /// system prompt.
+    fn context_budget(&self) -> usize {
+        context::budget(self.llm.context_window(), self.llm.context.reserve_tokens)
+            .saturating_sub(self.system_tokens())
+    }
+
+    /// Shorten `messages` to fit the context window, saying so when more
+    /// messages are left out than last time.
+    fn fit_context(&mut self, messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+        // Requests start with the messages sent, in order
+        let pinned: Vec<(usize, ChatMessage)> = self
+            .chat
+            .messages
+            .iter()
+            .filter(|m| m.is_sent())
+            .zip(&messages)
+            .enumerate()
+            .filter(|(_, (m, _))| m.pinned)
+            .map(|(i, (_, sent))| (i, sent.clone()))
+            .collect();
+        let fitted = context::fit(
+            messages,
+            &self.llm.context,
+            self.context_budget(),
+            self.llm.config.provider,
+            self.chat.summary.as_ref(),
+        );
+        if fitted.dropped > self.chat.dropped {
+            let how = if self.chat.summary.is_some() { "summarized" } else { "left out" };
+            self.toast_info(format!(
+                "Context window full: {} earlier messages {}",
+                fitted.dropped, how
+            ));
+        }
+        self.chat.dropped = fitted.dropped;
+        let mut messages = fitted.messages;
+        let left_out: Vec<ChatMessage> = pinned
+            .into_iter()
+            .filter(|(i, _)| *i < fitted.dropped)
+            .map(|(_, m)| m)
+            .collect();
+        context::restore_pinned(&mut messages, &left_out);
+        if let Some(prompt) = self.sent_system_prompt() {
+            // Anthropic sends it in `system`, the others as the first message
+            messages.insert(
+                0,
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: prompt,
+                    ..ChatMessage::default()
+                },
+            );
+        }
+        messages
+    }
+
+    /// The system prompt as sent, with its `${env:...}` and `${file:...}`
+    /// placeholders expanded as `[variables]` allows. If one may not be
+    /// expanded, the prompt goes as written and a toast says why.
+    fn sent_system_prompt(&mut self) -> Option<String> {
+        let prompt = self.system_prompt()?.to_string();
+        match variables::expand(&prompt, &self.submit_guard.variables) {
+            Ok(expansion) => Some(expansion.text),
+            Err(e) => {
+                self.toast_error(format!("System prompt not expanded: {}", e));
+                Some(prompt)
+            }
+        }
     }
 
-    /// Add an error toast.
-    pub fn toast_error(&mut self, message: impl Into<String>) -> u64 {
-        self.toasts.error(message)
+    /// Ask the model to summarize the oldest messages if the rest no longer
+    /// fit beside the current summary. Returns whether a summary was started;
+    /// the request continues once it is written.
+    fn start_summary(&mut self, messages: &[ChatMessage]) -> bool {
+        let provider = self.llm.config.provider;
+        let budget = self.context_budget();
+        let covered = self
+            .chat
+            .summary
+            .as_ref()
+            .map_or(0, |s| s.covers)
+            .min(messages.len());
+        if context::overflow(messages, budget, provider) <= covered {
+            return false;
+        }
+        // Summarize down to three quarters, leaving room for the summary and
+        // the next few messages
+        let covers = context::overflow(messages, budget * 3 / 4, provider);
+        let Some(client) = &self.llm.client else {
+            return false;
+        };
+        let request = context::summary_request(&messages[covered..covers], self.chat.summary.as_ref());
+        self.llm.summarizing = Some(PendingSummary {
+            rx: client.stream_chat(request),
+            covers,
+            text: String::new(),
+        });
+        self.llm.status = ConnectionStatus::Streaming;
+        self.toast_info(format!(
+            "Summarizing {} earlier messages to fit the context window",
+            covers - covered
+        ));
+        true
     }
 
-    /// Dismiss a toast by ID.
-    pub fn dismiss_toast(&mut self, id: u64) {
-        self.toasts.dismiss(id)
+    /// Collect the summary being written; once done, send the request that
+    /// waited for it. Call on each tick.
+    pub fn process_summary(&mut self) {
+        let Some(pending) = &mut self.llm.summarizing else {
+            return;
+        };
+        let failure = loop {
+            match pending.rx.try_recv() {
+                Ok(StreamEvent::Token(token)) => pending.text.push_str(&token),
+                Ok(StreamEvent::Done) => break None,
+                Ok(StreamEvent::Error(e)) => break Some(e),
+                Ok(StreamEvent::AuthError) => break Some("authentication failed".to_string()),
+                Ok(_) => {}
+                Err(mpsc::error::TryRecvError::Empty) => return,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    break Some("the connection closed".to_string())
+                }
+            }
+        };
+        let Some(pending) = self.llm.summarizing.take() else {
+            return;
+        };
+        self.llm.status = ConnectionStatus::Ready;
+        match failure {
+            None if !pending.text.trim().is_empty() => {
+                self.chat.summary = Some(ContextSummary {
+                    covers: pending.covers,
+                    text: pending.text.trim().to_string(),
+                });
+            }
+            None => {
+                self.toast_warning(
+                    "The summary came back empty; leaving out the oldest messages instead"
+                        .to_string(),
+                );
+            }
+            Some(e) => {
+                self.toast_warning(format!(
+                    "Could not summarize ({}); leaving out the oldest messages instead",
+                    e
+                ));
+            }
+        }
+        self.request_reply(self.api_messages());
     }
 
-    /// Tick the toast system to remove expired toasts.
-    /// Call this on each frame/tick.
-    pub fn tick_toasts(&mut self) {
-        self.toasts.tick()
+    /// The model for the next reply when one was picked for it or the `auto`
+    /// model is selected.
+    fn route_request(&self) -> Option<Route> {
+        if let Some(model) = &self.llm.chosen_model {
+            return Some(Route::chosen(model.clone()));
+        }
+        if !routing::is_auto(&self.llm.config.model) {
+            return None;
+        }
+        let prompt = self
+            .chat
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.is_chat() && m.role == Role::User)
+            .map_or("", |m| m.content.as_str());
+        Some(routing::route(
+            self.llm.config.provider,
+            prompt,
+            self.llm.config.reasoning.is_some(),
+            &self.llm.routing,
+        ))
+    }
+
+    /// Send the conversation to the LLM and stream the reply into a new message,
+    /// summarizing the oldest messages first if they no longer fit.
+    fn start_request(&mut self) {
+        if self.hold_for_budget(HeldRequest::Reply) {
+            return;
+        }
+        let messages = self.api_messages();
+        if self.llm.context.strategy == ContextStrategy::Summarize && self.start_summary(&messages) {
+            return;
+        }
+        self.request_reply(messages);
     }
 
-    // ─────────────────────────────────────────────────────────────────────────────
-    // Connection flow methods
-    // ─────────────────────────────────────────────────────────────────────────────
-
-    /// Start the connection flow for a provider.
-    ///
-    /// This checks for existing credentials and shows the appropriate dialog:
-    /// - If credentials exist: Show ExistingCredential dialog
-    /// - If OAuth provider (Copilot): Start OAuth device flow
-    /// - Otherwise: Show SelectingMethod dialog
-    pub fn start_connection(&mut self, provider: Provider) {
-        use crate::auth::AuthStorage;
+    /// Stream a reply to `messages` into a new message.
+    fn request_reply(&mut self, messages: Vec<ChatMessage>) {
+        let route = self.route_request();
+        self.llm.chosen_model = None;
+        if !self.stream(messages, route.as_ref()) {
+            return;
+        }
+        self.llm.reply_start = 0;
+        self.llm.auto_continue.used = 0;
 
-        // Close the menu first
-        self.menu.close();
+        // Add empty assistant message that will be filled by streaming
+        let mut reply = Message::assistant(String::new());
+        let model = route.as_ref().map_or(&self.llm.config.model, |route| &route.model);
+        reply.meta = Some(ReplyMeta::new(model.as_str()));
+        reply.route = route;
+        reply.reasoning = self.llm.config.reasoning;
+        self.chat.messages.push(reply);
+    }
 
-        // Check for existing credentials
-        if let Ok(storage) = AuthStorage::load() {
-            if let Some(cred) = storage.get(provider.storage_key()) {
-                if !cred.is_expired() {
-                    let masked = mask_api_key(cred.token());
-                    let current_model = cred.model().map(|s| s.to_string());
-                    self.connect = ConnectState::ExistingCredential {
-                        provider,
-                        masked_key: masked,
-                        current_model,
-                        selected: 0,
-                    };
-                    return;
-                }
+    /// Ask the model to extend the last reply, streaming into the same message.
+    ///
+    /// Providers that support prefill continue the reply directly; others get a
+    /// short follow-up asking them to pick up where they stopped. Returns
+    /// false if there is no reply to continue or it could not be requested.
+    pub fn continue_reply(&mut self) -> bool {
+        // Only a reply to a prompt, not the welcome text
+        let n = self.chat.messages.len();
+        let answers_prompt = n >= 2
+            && self.chat.messages[n - 2].is_chat()
+            && self.chat.messages[n - 2].role == Role::User;
+        let has_reply = self
+            .chat
+            .messages
+            .last()
+            .is_some_and(|m| m.is_chat() && m.role == Role::Assistant && !m.content.is_empty());
+        if !answers_prompt || !has_reply {
+            return false;
+        }
+        if self.hold_for_budget(HeldRequest::Continue) {
+            return true;
+        }
+        let Some(last) = self.chat.messages.last_mut() else {
+            return false;
+        };
+        let prefill = self.llm.config.provider.supports_prefill();
+        if prefill {
+            // A prefilled reply may not end in whitespace
+            let len = last.content.trim_end().len();
+            last.content.truncate(len);
+        }
+        let reply_start = last.content.len();
+        let finish = last.finish.take();
+        // A continuation stays on the model that started the reply
+        let route = last.route.clone();
+
+        let mut messages = self.api_messages();
+        if !prefill {
+            messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: CONTINUE_PROMPT.to_string(),
+                ..ChatMessage::default()
+            });
+        }
+        if !self.stream(messages, route.as_ref()) {
+            if let Some(last) = self.chat.messages.last_mut() {
+                last.finish = finish;
             }
+            return false;
         }
+        self.llm.reply_start = reply_start;
+        true
+    }
 
-        // No existing credentials - determine how to connect
-        if provider == Provider::Anthropic {
-            // Anthropic - show authentication method selection
-            self.connect = ConnectState::SelectingAnthropicMethod { selected: 0 };
-        } else if provider.uses_oauth() {
-            // OAuth providers (Copilot) - start device code flow
-            self.start_oauth_flow(provider);
-        } else if !provider.requires_api_key() {
-            // Provider doesn't need auth (e.g., Ollama) - connect directly
-            self.complete_connection(provider, None);
-        } else {
-            // Show method selection dialog
-            self.connect = ConnectState::SelectingMethod {
-                provider,
-                selected: 0,
-            };
+    /// Replace the reply to the last prompt with a new one (`/retry [model]`),
+    /// on `model` if one is named. The old reply can be brought back with `/undo`.
+    pub fn regenerate_reply(&mut self, model: &str) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before regenerating".to_string());
+            return;
+        }
+        if !self.llm.client.as_ref().is_some_and(|c| c.is_configured()) {
+            self.toast_warning("Connect a provider before regenerating".to_string());
+            return;
+        }
+        let Some(prompt) = self
+            .chat
+            .messages
+            .iter()
+            .rposition(|m| m.is_chat() && m.role == Role::User)
+        else {
+            self.toast_info("No prompt to regenerate a reply for".to_string());
+            return;
+        };
+        // Error cards and notes after the reply go with it
+        let start = prompt + 1;
+        let old_reply = self
+            .chat
+            .messages
+            .drain(start..)
+            .find(|m| m.is_chat() && m.role == Role::Assistant);
+        if !model.is_empty() {
+            self.llm.chosen_model = Some(model.to_string());
         }
+        self.start_request();
+        let Some(old_reply) = old_reply else {
+            return;
+        };
+        let revert = if self.chat.messages.len() > start {
+            Edit::Replace {
+                index: start,
+                message: Box::new(old_reply),
+            }
+        } else {
+            // Still summarizing; the new reply follows once that is done
+            Edit::Insert {
+                index: start,
+                messages: vec![old_reply],
+            }
+        };
+        self.chat.history.record("regenerate", revert);
     }
 
-    /// Cancel the connection flow and return to normal state.
-    pub fn cancel_connection(&mut self) {
-        self.connect = ConnectState::None;
-        self.device_code_rx = None;
-        self.oauth_rx = None;
+    /// Extend the last reply on request (`/continue`), explaining when it cannot.
+    pub fn continue_last_reply(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before continuing".to_string());
+            return;
+        }
+        if !self.llm.client.as_ref().is_some_and(|c| c.is_configured()) {
+            self.toast_warning("Connect a provider before continuing".to_string());
+            return;
+        }
+        // Resuming an interrupted reply replaces the error card that followed it
+        let n = self.chat.messages.len();
+        let card = (n >= 2
+            && self.chat.messages[n - 1].is_error()
+            && self.chat.messages[n - 2].finish == Some(FinishReason::Interrupted))
+        .then(|| self.chat.messages.pop())
+        .flatten();
+        if !self.continue_reply() {
+            self.chat.messages.extend(card);
+            self.toast_info("No reply to continue".to_string());
+        }
     }
 
-    /// Complete the connection successfully.
-    ///
-    /// Saves credentials (if provided) and switches to the provider.
-    pub fn complete_connection(&mut self, provider: Provider, api_key: Option<String>) {
-        use crate::auth::{AuthStorage, Credential};
+    /// Continue a reply that stopped at the token limit, within the configured budget.
+    fn auto_continue(&mut self) {
+        let Some(last) = self.chat.messages.last() else {
+            return;
+        };
+        if last.finish != Some(FinishReason::Length) {
+            return;
+        }
+        let reply_bytes = last.content.len();
+        let limits = self.llm.auto_continue;
+        if limits.used >= limits.max_follow_ups
+            || estimate_tokens(reply_bytes) >= limits.max_reply_tokens
+        {
+            return;
+        }
+        if self.continue_reply() {
+            self.llm.auto_continue.used += 1;
+            self.toast_info(format!(
+                "Reply hit the token limit; continuing ({}/{})",
+                self.llm.auto_continue.used, limits.max_follow_ups
+            ));
+        }
+    }
 
-        // Save credential if provided
-        if let Some(key) = &api_key {
-            if let Ok(mut storage) = AuthStorage::load() {
-                storage.set(provider.storage_key(), Credential::api_key(key));
-                if let Err(e) = storage.save() {
-                    self.toast_warning(format!("Could not save credentials: {}", e));
-                }
-            }
+    /// How much of the model's context window the conversation and draft fill.
+    pub fn context_usage(&mut self) -> ContextUsage {
+        let provider = self.llm.config.provider;
+        let chat = self.chat.messages.iter().filter(|m| m.is_sent());
+        let key = (
+            provider,
+            chat.clone().count(),
+            chat.clone().map(|m| m.sent_text().len()).sum::<usize>()
+                + self.chat.input.len()
+                + self.system_prompt().map_or(0, str::len),
+        );
+        if self.context_tokens.key != Some(key) {
+            self.context_tokens = TokenCount {
+                key: Some(key),
+                tokens: chat
+                    .map(|m| tokens::message_tokens(m.sent_text(), provider))
+                    .sum::<usize>()
+                    + tokens::estimate(&self.chat.input, provider)
+                    + self.system_tokens(),
+            };
         }
-
-        // Configure and switch to the provider
-        self.llm.config.provider = provider;
-        self.llm.config.api_base = provider.default_api_base().to_string();
-        self.llm.config.model = provider.default_model().to_string();
-
-        if let Some(key) = api_key {
-            self.llm.config.api_key = key;
-            self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
-        } else if !provider.requires_api_key() {
-            self.llm.config.api_key.clear();
-            self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+        ContextUsage {
+            tokens: self.context_tokens.tokens,
+            window: self.llm.context_window(),
         }
+    }
 
-        self.llm.apply_config();
-        self.connect = ConnectState::None;
+    /// The metadata of the reply being streamed, if there is one.
+    fn streaming_meta(&mut self) -> Option<&mut ReplyMeta> {
+        self.chat
+            .messages
+            .last_mut()
+            .filter(|m| m.is_chat() && m.role == Role::Assistant)
+            .and_then(|m| m.meta.as_mut())
+    }
 
-        self.toast_success(format!("Connected to {}", provider.display_name()));
+    /// Time the first token of the reply being streamed.
+    fn note_first_token(&mut self) {
+        let Some(sent) = self.llm.request_sent else {
+            return;
+        };
+        if let Some(meta) = self.streaming_meta() {
+            meta.first_token.get_or_insert_with(|| sent.elapsed());
+        }
     }
 
-    /// Handle a connection error.
-    ///
-    /// Shows the error in the EnteringApiKey state so the user can try again.
-    pub fn connection_error(&mut self, error: String) {
-        if let ConnectState::ValidatingKey { provider, key, .. } = &self.connect {
-            self.connect = ConnectState::EnteringApiKey {
-                provider: *provider,
-                input: key.clone(),
-                cursor: key.len(),
-                error: Some(error),
-            };
-        } else {
-            // Fallback: show toast
-            self.toast_error(error);
-            self.connect = ConnectState::None;
+    /// Add the request that just completed to its reply's duration and usage.
+    fn finish_reply_meta(&mut self) {
+        let elapsed = self.llm.request_sent.take().map(|sent| sent.elapsed());
+        let usage = self.llm.usage;
+        if let Some(meta) = self.streaming_meta() {
+            if let Some(elapsed) = elapsed {
+                meta.duration = Some(meta.duration.unwrap_or_default() + elapsed);
+            }
+            if let Some(usage) = usage {
+                meta.usage = Some(meta.usage.map_or(usage, |total| total + usage));
+            }
         }
     }
 
-    /// Use existing credentials to connect.
-    pub fn use_existing_credentials(&mut self) {
-        use crate::auth::{AuthStorage, OAuthToken};
+    /// Count the request that just completed, in the status bar and the usage ledger.
+    fn record_usage(&mut self) {
+        let last = self.chat.messages.last();
+        let reply_bytes =
+            last.map_or(0, |m| m.content.len().saturating_sub(self.llm.reply_start));
+        let model = last
+            .and_then(|m| m.route.as_ref())
+            .map_or(&self.llm.config.model, |route| &route.model);
+        let mut record = UsageRecord::estimated(
+            self.llm.config.provider,
+            model,
+            self.llm.request_bytes,
+            reply_bytes,
+        );
+        if let Some(usage) = self.llm.usage.take() {
+            record = record.with_usage(usage);
+        }
+        let record = record.priced(&self.pricing);
+        if let Some(meta) = self.chat.messages.last_mut().and_then(|m| m.meta.as_mut()) {
+            if let Some(cost) = record.cost {
+                meta.cost = Some(meta.cost.unwrap_or(0.0) + cost);
+            }
+        }
+        self.session_usage.add(&record);
+        // Bookkeeping must never interrupt the chat
+        if let Some(ledger) = &self.usage {
+            let _ = ledger.record(&record);
+        }
+        self.warn_about_spending();
+    }
 
-        if let ConnectState::ExistingCredential { provider, .. } = self.connect {
-            if let Ok(storage) = AuthStorage::load() {
-                if let Some(cred) = storage.get(provider.storage_key()) {
-                    let key = cred.token().to_string();
-                    
-                    // Special handling for Copilot
-                    if provider == Provider::GitHubCopilot {
-                        // If model is saved, check validation cache
-                        if let Some(model) = cred.model() {
-                            // Check if token was already validated this session
-                            if self.validated_tokens.get(provider.storage_key()) == Some(&true) {
-                                // Already validated - connect directly
-                                self.llm.config.provider = provider;
-                                self.llm.config.api_base = provider.default_api_base().to_string();
-                                self.llm.config.model = model.to_string();
-                                self.llm.config.api_key = key;
-                                self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
-                                self.llm.apply_config();
-                                self.connect = ConnectState::None;
-                                self.toast_success(format!("Connected to {} with {}", 
-                                    provider.display_name(), model));
-                                return;
-                            } else {
-                                // Need to validate token first
-                                self.start_copilot_validation(key.clone(), model.to_string());
-                                return;
-                            }
+    /// Open a viewer on the session's usage and the ledger's monthly and
+    /// lifetime totals per model.
+    pub fn show_usage(&mut self) {
+        let session = &self.session_usage;
+        let mut text = if session.requests == 0 {
+            "This session: no requests yet\n".to_string()
+        } else {
+            format!(
+                "This session: {} requests · {} in · {} out tokens · {}\n",
+                session.requests,
+                session.input_tokens,
+                session.output_tokens,
+                session.cost_label()
+            )
+        };
+        match &self.usage {
+            Some(ledger) => {
+                let month = current_month();
+                let tables = month_range(&month).and_then(|(from, to)| {
+                    Ok((ledger.totals_between(from, to)?, ledger.totals()?))
+                });
+                match tables {
+                    Ok((monthly, lifetime)) => {
+                        text.push('\n');
+                        text.push_str(&format_table(&month, &monthly));
+                        if !lifetime.is_empty() {
+                            text.push('\n');
+                            text.push_str(&format_table("all time", &lifetime));
                         }
-                        
-                        // No saved model - show model selection dialog
-                        let oauth_token = OAuthToken {
-                            access_token: key,
-                            token_type: "bearer".to_string(),
-                            scope: Some("read:user".to_string()),
-                            expires_in: None,
-                            refresh_token: cred.refresh_token().map(|s| s.to_string()),
-                        };
-                        
-                        self.connect = ConnectState::SelectingModel {
-                            provider,
-                            selected: 0,
-                            oauth_token,
-                            anthropic_method: None,  // Copilot doesn't use Anthropic auth
-                        };
-                        return;
                     }
-                    
-                    // For other providers, connect directly
-                    // Determine credential type from storage
-                    use crate::auth::Credential;
-                    let credential_type = match cred {
-                        Credential::OAuth { .. } => crate::llm::CredentialType::OAuth,
-                        Credential::ApiKey { .. } => crate::llm::CredentialType::ApiKey,
-                    };
-                    
-                    self.llm.config.provider = provider;
-                    self.llm.config.api_base = provider.default_api_base().to_string();
-                    if let Some(model) = cred.model() {
-                        self.llm.config.model = model.to_string();
-                    } else {
-                        self.llm.config.model = provider.default_model().to_string();
+                    Err(e) => {
+                        text.push_str(&format!("\nCould not read the usage ledger: {}\n", e));
                     }
-                    self.llm.config.api_key = key;
-                    self.llm.config.credential_type = credential_type;
-                    self.llm.apply_config();
-                    self.connect = ConnectState::None;
-                    self.toast_success(format!("Connected to {}", provider.display_name()));
-                    return;
                 }
             }
-            // Fallback if credential disappeared
-            self.toast_error("Credential not found");
-            self.connect = ConnectState::None;
+            None => {
+                text.push_str("\nThe usage ledger is not open, so only this session is counted\n")
+            }
         }
+        self.viewer = Some(MessageViewer::new("Usage", &text));
     }
 
-    /// Change the model for an existing Copilot connection.
-    pub fn change_copilot_model(&mut self) {
-        use crate::auth::{AuthStorage, OAuthToken};
+    /// Re-send the request behind the error card at the end of the conversation.
+    pub fn retry_last_request(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before retrying".to_string());
+            return;
+        }
+        if !self.chat.messages.last().is_some_and(|m| m.is_error()) {
+            self.toast_info("Nothing to retry".to_string());
+            return;
+        }
+        if !self.llm.client.as_ref().is_some_and(|c| c.is_configured()) {
+            self.toast_warning("Connect a provider before retrying".to_string());
+            return;
+        }
 
-        if let ConnectState::ExistingCredential { provider, .. } = self.connect {
-            if provider != Provider::GitHubCopilot {
-                return;
+        self.chat.messages.pop();
+        // Drop the partial reply the failed attempt left behind
+        if self
+            .chat
+            .messages
+            .last()
+            .is_some_and(|m| m.is_chat() && m.role == Role::Assistant)
+        {
+            self.chat.messages.pop();
+        }
+        self.start_request();
+    }
+
+    /// Process one streaming event, if any is ready. Call this in the event loop.
+    ///
+    /// Returns whether an event was handled, so callers can drain the channel.
+    pub fn process_stream(&mut self) -> bool {
+        let Some(rx) = &mut self.llm.stream_rx else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(event) => {
+                self.handle_stream_event(event);
+                true
             }
+            Err(mpsc::error::TryRecvError::Empty) => false,
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                self.stream_closed();
+                false
+            }
+        }
+    }
 
-            if let Ok(storage) = AuthStorage::load() {
-                if let Some(cred) = storage.get(provider.storage_key()) {
-                    // Convert credential to OAuthToken
-                    let oauth_token = OAuthToken {
-                        access_token: cred.token().to_string(),
-                        token_type: "bearer".to_string(),
-                        scope: Some("read:user".to_string()),
-                        expires_in: None,
-                        refresh_token: cred.refresh_token().map(|s| s.to_string()),
-                    };
+    /// Apply one event from the streaming reply.
+    pub fn handle_stream_event(&mut self, event: StreamEvent) {
+        if !matches!(event, StreamEvent::Retrying { .. }) {
+            self.llm.retrying = None;
+        }
+        match event {
+            StreamEvent::Token(token) => {
+                self.note_first_token();
+                // Append token to the last message
+                if let Some(last) = self.chat.messages.last_mut() {
+                    if last.role == Role::Assistant {
+                        last.content.push_str(&token);
+                    }
+                }
+            }
+            StreamEvent::Thinking(chunk) => {
+                self.note_first_token();
+                if let Some(last) = self.chat.messages.last_mut() {
+                    if last.role == Role::Assistant {
+                        last.thinking.push_str(&chunk);
+                    }
+                }
+            }
+            StreamEvent::Usage(usage) => self.llm.usage = Some(usage),
+            StreamEvent::RateLimits(limits) => {
+                self.llm.rate_limits.limits = Some(limits);
+                self.llm.rate_limits.received = Some(Instant::now());
+            }
+            StreamEvent::Retrying {
+                attempt,
+                max_retries,
+                delay,
+                reason,
+            } => {
+                self.llm.retrying = Some(RetryNotice {
+                    attempt,
+                    max_retries,
+                    at: Instant::now() + delay,
+                    reason,
+                });
+            }
+            StreamEvent::ToolCall(call) => {
+                // Run once the reply is complete, so results follow it
+                if let Some(last) = self.chat.messages.last_mut() {
+                    if last.is_chat() && last.role == Role::Assistant {
+                        last.tool_calls.push(call);
+                    }
+                }
+            }
+            StreamEvent::Finish(reason) => {
+                if let Some(last) = self.chat.messages.last_mut() {
+                    if last.role == Role::Assistant {
+                        last.finish = Some(reason);
+                    }
+                }
+            }
+            StreamEvent::Done => {
+                self.llm.stream_rx = None;
+                self.llm.status = ConnectionStatus::Ready;
+                self.finish_reply_meta();
+                // Replace the unfinished checkpoint with the whole reply
+                if std::mem::take(&mut self.autosave.checkpoint_saved) {
+                    let _ = self.save_session();
+                }
+                self.record_usage();
+                self.auto_continue();
+                self.run_tool_calls();
+            }
+            StreamEvent::Error(e) => {
+                let error = ProviderError::from_message(&e);
+                if matches!(error, ProviderError::NetworkError(_)) && self.queue_unsent() {
+                    self.llm.stream_rx = None;
+                    self.llm.status = ConnectionStatus::Error(e);
+                    return;
+                }
 
-                    // Transition to model selection
-                    self.connect = ConnectState::SelectingModel {
-                        provider,
-                        selected: 0,
-                        oauth_token,
-                        anthropic_method: None,  // Copilot doesn't use Anthropic auth
-                    };
+                // Show the failure as a card: replace the empty reply, or follow a partial one
+                let card = Message::error(error, self.llm.config.provider.display_name());
+                let mut partial = false;
+                match self.chat.messages.last_mut() {
+                    Some(last)
+                        if last.is_chat()
+                            && last.role == Role::Assistant
+                            && last.content.is_empty() =>
+                    {
+                        *last = card;
+                    }
+                    Some(last) if last.is_chat() && last.role == Role::Assistant => {
+                        last.finish = Some(FinishReason::Interrupted);
+                        partial = true;
+                        self.chat.messages.push(card);
+                    }
+                    _ => self.chat.messages.push(card),
+                }
+                self.llm.stream_rx = None;
+                self.llm.status = ConnectionStatus::Error(e);
+                // Keep what arrived, so it can be resumed even after a restart
+                if partial {
+                    if let Err(e) = self.save_session() {
+                        self.toast_error(format!("Could not save the partial reply: {}", e));
+                    }
+                }
+            }
+            StreamEvent::AuthError => {
+                use crate::auth::AuthStorage;
+                
+                // Clear invalid credentials from storage
+                if self.llm.config.provider == Provider::GitHubCopilot {
+                    let mut storage = AuthStorage::load().unwrap_or_default();
+                    storage.remove(Provider::GitHubCopilot.storage_key());
+                    let _ = storage.save();
+                    
+                    // Clear validation cache
+                    self.validated_tokens.remove(Provider::GitHubCopilot.storage_key());
                 }
+                
+                // Show error toast but preserve chat history
+                self.toast_error("Session expired. Please reconnect to continue chatting.".to_string());
+                
+                // Update status
+                self.llm.stream_rx = None;
+                self.llm.status = ConnectionStatus::NotConfigured;
             }
         }
     }
 
-    /// Enter new credentials (from ExistingCredential or SelectingMethod state).
-    pub fn enter_new_credentials(&mut self) {
-        let provider = match &self.connect {
-            ConnectState::ExistingCredential { provider, .. } => *provider,
-            ConnectState::SelectingMethod { provider, .. } => *provider,
-            _ => return,
-        };
+    /// The streaming channel closed without a final event.
+    pub fn stream_closed(&mut self) {
+        self.llm.stream_rx = None;
+        self.llm.retrying = None;
+        if self.llm.status == ConnectionStatus::Streaming {
+            self.llm.status = ConnectionStatus::Ready;
+        }
+    }
 
-        // Anthropic has its own OAuth flow with method selection
-        if provider == Provider::Anthropic {
-            self.connect = ConnectState::SelectingAnthropicMethod { selected: 0 };
-        } else if provider.uses_oauth() {
-            // Copilot uses OAuth device code flow
-            self.start_oauth_flow(provider);
-        } else {
-            self.connect = ConnectState::EnteringApiKey {
-                provider,
-                input: String::new(),
-                cursor: 0,
-                error: None,
-            };
+    /// The index of the last reply if it called tools and only their results
+    /// follow it.
+    fn tool_reply(&self) -> Option<usize> {
+        let reply = self
+            .chat
+            .messages
+            .iter()
+            .rposition(|m| !m.is_tool_result())
+            .filter(|&i| !self.chat.messages[i].tool_calls.is_empty())?;
+        self.chat.messages[reply].is_chat().then_some(reply)
+    }
+
+    /// Run the tool calls of the reply that just finished, then send their
+    /// results back so the model can continue. Stops at a call that needs
+    /// confirmation; answering it carries on from there.
+    fn run_tool_calls(&mut self) {
+        if self.is_streaming() {
+            return;
+        }
+        let Some(reply) = self.tool_reply() else {
+            return;
+        };
+        let answered: Vec<String> = self.chat.messages[reply + 1..]
+            .iter()
+            .filter_map(|m| m.tool_result.as_ref())
+            .map(|result| result.call_id.clone())
+            .collect();
+        let pending: Vec<ToolCall> = self.chat.messages[reply]
+            .tool_calls
+            .iter()
+            .filter(|call| !answered.contains(&call.id))
+            .cloned()
+            .collect();
+        for call in pending {
+            if !self.request_tool(call) {
+                return;
+            }
         }
+        self.start_request();
     }
 
-    /// Start async validation of an API key.
-    ///
-    /// Spawns a background task to validate the key and stores the receiver.
-    pub fn start_validation(&mut self, provider: Provider, key: String) {
-        use crate::llm::validate_api_key;
+    /// Run a tool call from the model, or ask first if its permission says so.
+    /// Returns false while the call waits for an answer.
+    fn request_tool(&mut self, call: ToolCall) -> bool {
+        match self.tools.dispatch(&call) {
+            Dispatch::NeedsConfirmation { summary } => {
+                self.tool_confirmation = Some(ToolConfirmation::new(call, summary));
+                false
+            }
+            outcome => {
+                self.report_tool(&call, outcome);
+                true
+            }
+        }
+    }
 
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        self.validation_rx = Some(rx);
-        self.connect = ConnectState::ValidatingKey {
-            provider,
-            key: key.clone(),
-            model: None,
+    /// Answer the tool confirmation dialog, then carry on with the reply's
+    /// other calls.
+    pub fn confirm_tool(&mut self, allow: bool, remember: bool) {
+        let Some(confirmation) = self.tool_confirmation.take() else {
+            return;
+        };
+        let outcome = self.tools.confirm(&confirmation.call, allow, remember);
+        self.report_tool(&confirmation.call, outcome);
+        self.run_tool_calls();
+    }
+
+    /// Add what became of a tool call to the conversation: a note for the
+    /// user, and the output (or the error) for the model.
+    fn report_tool(&mut self, call: &ToolCall, outcome: Dispatch) {
+        let name = &call.name;
+        let (note, output) = match outcome {
+            Dispatch::Ran(Ok(output)) => {
+                (format!("Ran {} ({} bytes of output)", name, output.len()), output)
+            }
+            Dispatch::Ran(Err(e)) => {
+                (format!("{} failed: {:#}", name, e), format!("Error: {:#}", e))
+            }
+            Dispatch::Refused(reason) => (
+                format!("Did not run {}: {}", name, reason),
+                format!("Not run: {}", reason),
+            ),
+            Dispatch::NeedsConfirmation { .. } => return,
         };
+        self.chat.messages.push(Message::tool_result(&call.id, note, output));
+    }
 
-        tokio::spawn(async move {
-            let result = validate_api_key(provider, &key).await;
-            let _ = tx.send(result);
-        });
+    /// Check if currently streaming a response.
+    pub fn is_streaming(&self) -> bool {
+        self.llm.is_streaming()
     }
 
-    /// Start async validation of a Copilot OAuth token.
+    /// What the app is busy with, which decides how long the event loop sleeps.
     ///
-    /// Spawns a background task to validate the token with the Copilot API.
-    /// On success, connects with the saved model. On failure, prompts for re-authentication.
-    fn start_copilot_validation(&mut self, key: String, model: String) {
-        use crate::llm::CopilotProvider;
-
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        self.validation_rx = Some(rx);
-        
-        self.connect = ConnectState::ValidatingKey {
-            provider: Provider::GitHubCopilot,
-            key: key.clone(),
-            model: Some(model),
-        };
-        
-        self.toast_info("Validating Copilot token...".to_string());
+    /// Streamed tokens wake the loop by themselves, so streaming alone counts
+    /// as idle.
+    pub fn activity(&self) -> Activity {
+        let oauth_dialog = matches!(
+            self.connect,
+            ConnectState::OAuthPending { .. } | ConnectState::OAuthPolling { .. }
+        );
+        if !self.animation.banner_complete {
+            Activity::Animating
+        } else if self.llm.is_warming_up()
+            || self.llm.offline.offline
+            || self.llm.rate_limits.held_until.is_some()
+            || self.llm.token_refresh.rx.is_some()
+            || self.local_models_rx.is_some()
+            || self.validation_rx.is_some()
+            || self.model_list_rx.is_some()
+            || self.server_models_rx.is_some()
+            || self.ollama_models_rx.is_some()
+            || self.ollama_pull_rx.is_some()
+            || self.oauth_rx.is_some()
+            || self.device_code_rx.is_some()
+            || self.auth_code_rx.is_some()
+            || self.api_key_conversion_rx.is_some()
+            || oauth_dialog
+            || !self.toasts.is_empty()
+            || self.indexer.as_ref().is_some_and(Indexer::is_busy)
+            || self.sync_rx.is_some()
+            || self.share.is_some()
+            || self.batch.is_some()
+            || self.llm.summarizing.is_some()
+            || self.guest.as_ref().is_some_and(|g| !g.closed)
+        {
+            Activity::Waiting
+        } else {
+            Activity::Idle
+        }
+    }
 
-        tokio::spawn(async move {
-            let provider = CopilotProvider::new();
-            // Set the OAuth token so validate_token can use it
-            *provider.oauth_token.write().await = Some(key);
-            
-            let result = provider.validate_token().await
-                .map(|_| ())
-                .map_err(|e| e.to_string());
-            let _ = tx.send(result);
-        });
+    /// Explain why the slash command being typed is unavailable for the current model.
+    pub fn input_hint(&self) -> Option<String> {
+        let word = self.chat.input.split_whitespace().next()?;
+        if !word.starts_with('/') {
+            return None;
+        }
+        let spec = COMMANDS.iter().find(|spec| spec.name.starts_with(word))?;
+        unsupported_hint(self.llm.config.provider, &self.llm.config.model, spec.requires?)
     }
 
-    /// Process async validation results.
+    /// Run a slash command typed in the input.
     ///
-    /// Call this in the event loop to check for completed validations.
-    /// Returns true if a validation completed (success or failure).
-    pub fn process_validation(&mut self) -> bool {
-        if let Some(mut rx) = self.validation_rx.take() {
-            match rx.try_recv() {
-                Ok(Ok(())) => {
-                    // Validation succeeded
-                    if let ConnectState::ValidatingKey { provider, key, model } = &self.connect {
-                        let provider = *provider;
-                        let key = key.clone();
-                        let model = model.clone();
-                        
-                        // For Copilot with model, cache validation and connect with model
-                        if provider == Provider::GitHubCopilot && model.is_some() {
-                            self.validated_tokens.insert(provider.storage_key().to_string(), true);
-                            
-                            // Connect with the validated model
-                            let model_name = model.unwrap();
-                            self.llm.config.provider = provider;
-                            self.llm.config.api_base = provider.default_api_base().to_string();
-                            self.llm.config.model = model_name.clone();
-                            self.llm.config.api_key = key;
-                            self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
-                            self.llm.apply_config();
-                            self.connect = ConnectState::None;
-                            self.toast_success(format!("Connected to {} with {}", 
-                                provider.display_name(), model_name));
-                        } else {
-                            // Regular connection flow for other providers
-                            self.complete_connection(provider, Some(key));
-                        }
-                    }
-                    return true;
-                }
-                Ok(Err(e)) => {
-                    // Validation failed - clear cache if Copilot
-                    if let ConnectState::ValidatingKey { provider, .. } = &self.connect {
-                        if *provider == Provider::GitHubCopilot {
-                            self.validated_tokens.remove(provider.storage_key());
-                        }
-                    }
-                    self.connection_error(e);
-                    return true;
+    /// The input is cleared when the command runs; it is kept for editing if
+    /// the current model does not support the command.
+    pub fn run_command(&mut self, command: SlashCommand, args: &str) {
+        if let Some(hint) = COMMANDS
+            .iter()
+            .find(|spec| spec.command == command)
+            .and_then(|spec| spec.requires)
+            .and_then(|capability| {
+                unsupported_hint(self.llm.config.provider, &self.llm.config.model, capability)
+            })
+        {
+            self.toast_warning(hint);
+            return;
+        }
+        self.chat.clear_input();
+        match command {
+            SlashCommand::Model => self.switch_model(args),
+            SlashCommand::Think => self.set_reasoning(args),
+            SlashCommand::System => self.set_system_prompt(args),
+            SlashCommand::Attach => self.attach_image(args),
+            SlashCommand::Provider => self.connect_provider(args),
+            SlashCommand::Clear => self.clear_conversation(),
+            SlashCommand::Save => match self.save_session() {
+                Ok(()) if self.sessions.is_none() => {
+                    self.toast_warning("Session storage is unavailable".to_string());
                 }
-                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
-                    // Still pending, put the receiver back
-                    self.validation_rx = Some(rx);
+                Ok(()) => {
+                    self.toast_success("Session saved");
                 }
-                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
-                    // Channel closed unexpectedly
-                    self.connection_error("Validation task failed".to_string());
-                    return true;
+                Err(e) => {
+                    self.toast_error(format!("Could not save session: {}", e));
                 }
+            },
+            SlashCommand::Continue => self.continue_last_reply(),
+            SlashCommand::Retry => self.regenerate_reply(args),
+            SlashCommand::Copy => self.copy_last_reply(),
+            SlashCommand::Undo => self.undo(),
+            SlashCommand::Redo => self.redo(),
+            SlashCommand::Raw => self.toggle_raw_all(),
+            SlashCommand::DryRun => self.toggle_dry_run(),
+            SlashCommand::Local => self.open_local_models(),
+            SlashCommand::Playground => self.toggle_playground(),
+            SlashCommand::Sync => self.start_sync(),
+            SlashCommand::Share => self.toggle_share(args),
+            SlashCommand::Theme => self.set_theme(args),
+            SlashCommand::Batch => self.start_batch(args),
+            SlashCommand::Usage => self.show_usage(),
+            SlashCommand::Help => {
+                self.chat.messages.push(Message::system_note(commands::help_text()));
+                let max_scroll = self.max_scroll();
+                self.scroll.scroll_to_bottom(max_scroll);
             }
         }
-        false
     }
 
-    /// Start the OAuth device code flow for a provider.
-    ///
-    /// Requests a device code and transitions to OAuthPending state.
-    /// Once the device code is received, we transition to OAuthPolling.
-    pub fn start_oauth_flow(&mut self, provider: Provider) {
-        use crate::auth::DeviceCodeFlow;
-
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        self.device_code_rx = Some(rx);
-
-        // Spawn task to request device code only (not polling yet)
-        tokio::spawn(async move {
-            let flow = match provider {
-                Provider::GitHubCopilot => DeviceCodeFlow::github_copilot(),
-                _ => {
-                    let _ = tx.send(Err("Provider does not support OAuth device code".to_string()));
-                    return;
-                }
-            };
-            
-            match flow.request_device_code().await {
-                Ok(device_code) => {
-                    let _ = tx.send(Ok(device_code));
-                }
+    /// Show the reasoning effort, or set it to `level` (`low`, `med`, `high` or `off`).
+    pub fn set_reasoning(&mut self, level: &str) {
+        if level.is_empty() {
+            let current = self.llm.config.reasoning.map_or("off", |e| e.label());
+            self.toast_info(format!("Thinking: {}; /think low|med|high|off to change", current));
+            return;
+        }
+        let effort = if level.eq_ignore_ascii_case("off") {
+            None
+        } else {
+            match level.parse::<ReasoningEffort>() {
+                Ok(effort) => Some(effort),
                 Err(e) => {
-                    let _ = tx.send(Err(e.to_string()));
+                    self.toast_warning(e);
+                    return;
                 }
             }
+        };
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before changing thinking".to_string());
+            return;
+        }
+        self.llm.config.reasoning = effort;
+        self.llm.apply_config();
+        self.toast_info(match effort {
+            Some(effort) => format!("Thinking: {}", effort.label()),
+            None => "Thinking off".to_string(),
         });
+    }
 
-        // Create a placeholder device code for the dialog
-        let (placeholder_uri, placeholder_full_uri) = match provider {
-            Provider::Anthropic => (
-                "https://console.anthropic.com/device".to_string(),
-                None,
-            ),
-            Provider::GitHubCopilot => (
-                "https://github.com/login/device".to_string(),
-                None,
-            ),
-            _ => (
-                "https://example.com".to_string(),
-                None,
-            ),
+    /// Show the system prompt, or set this conversation's to `prompt`: `off`
+    /// sends none, `default` goes back to `[llm] system_prompt`.
+    pub fn set_system_prompt(&mut self, prompt: &str) {
+        if prompt.is_empty() {
+            let note = match self.system_prompt() {
+                Some(current) => format!("System prompt:\n\n{}", current),
+                None => "No system prompt; /system <prompt> sets one".to_string(),
+            };
+            self.chat.messages.push(Message::system_note(note));
+            let max_scroll = self.max_scroll();
+            self.scroll.scroll_to_bottom(max_scroll);
+            return;
+        }
+        let (setting, message) = if prompt.eq_ignore_ascii_case("off") {
+            (Some(String::new()), "System prompt off")
+        } else if prompt.eq_ignore_ascii_case("default") {
+            (None, "System prompt reset to the config")
+        } else {
+            (Some(prompt.to_string()), "System prompt set for this conversation")
         };
+        self.chat.system_prompt = setting;
+        self.toast_info(message.to_string());
+    }
 
-        let placeholder_device_code = DeviceCode {
-            device_code: String::new(),
-            user_code: "Loading...".to_string(),
-            verification_uri: placeholder_uri,
-            verification_uri_complete: placeholder_full_uri,
-            expires_in: 900,
-            interval: 5,
-        };
+    /// Attach the image at `path` to the next prompt; `clear` drops the
+    /// attached images.
+    pub fn attach_image(&mut self, path: &str) {
+        if path.is_empty() {
+            self.toast_warning("Usage: /attach <image>|clear".to_string());
+            return;
+        }
+        if path.eq_ignore_ascii_case("clear") {
+            let count = std::mem::take(&mut self.chat.images).len();
+            self.toast_info(match count {
+                0 => "No images attached".to_string(),
+                1 => "Removed the attached image".to_string(),
+                n => format!("Removed {} attached images", n),
+            });
+            return;
+        }
+        match Image::load(&paste::chip_path(path)) {
+            Ok(image) => {
+                self.toast_info(format!(
+                    "Attached {} ({}) to the next prompt",
+                    image.name,
+                    image.describe()
+                ));
+                self.chat.images.push(image);
+            }
+            Err(e) => {
+                self.toast_error(format!("{:#}", e));
+            }
+        }
+    }
 
-        self.connect = ConnectState::OAuthPending {
-            provider,
-            auth_dialog: AuthDialog::new(provider.display_name(), placeholder_device_code),
-        };
+    /// Show the current model, or switch to `name` on the current provider.
+    pub fn switch_model(&mut self, name: &str) {
+        if name.is_empty() {
+            self.toast_info(format!(
+                "Using {} ({}); /model <name> to switch",
+                self.llm.config.model,
+                self.llm.config.provider.display_name()
+            ));
+            return;
+        }
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching models".to_string());
+            return;
+        }
+        self.llm.config.model = name.to_string();
+        self.llm.apply_config();
+        self.chat.messages.push(Message::system_note(format!(
+            "Switched to {} ({})",
+            name,
+            self.llm.config.provider.display_name()
+        )));
     }
 
-    /// Process device code request results.
-    ///
-    /// Call this in the event loop to check for device code.
-    /// Returns true if device code was received (success or failure).
-    pub fn process_device_code(&mut self) -> bool {
-        if let Some(mut rx) = self.device_code_rx.take() {
-            match rx.try_recv() {
-                Ok(Ok(device_code)) => {
-                    // Device code received - transition to polling
-                    if let ConnectState::OAuthPending { provider, .. } = &self.connect {
-                        let provider = *provider;
-                        self.start_oauth_polling(provider, device_code);
-                    }
-                    return true;
-                }
-                Ok(Err(e)) => {
-                    // Failed to get device code
-                    self.toast_error(format!("Failed to start authentication: {}", e));
-                    self.connect = ConnectState::None;
-                    return true;
-                }
-                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
-                    // Still pending, put the receiver back
-                    self.device_code_rx = Some(rx);
-                }
-                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
-                    // Channel closed unexpectedly
-                    self.toast_error("Device code request failed");
-                    self.connect = ConnectState::None;
-                    return true;
-                }
+    /// Connect to the named provider, or open the provider menu without a name.
+    pub fn connect_provider(&mut self, name: &str) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching providers".to_string());
+            return;
+        }
+        if name.is_empty() {
+            self.menu.toggle();
+            self.menu.enter_submenu();
+            return;
+        }
+        match name.parse::<Provider>() {
+            Ok(provider) => self.start_connection(provider),
+            Err(e) => {
+                self.toast_error(e);
             }
         }
-        false
     }
 
-    /// Start OAuth polling after device code is received.
-    ///
-    /// Call this to transition from OAuthPending to OAuthPolling with a real device code.
-    pub fn start_oauth_polling(&mut self, provider: Provider, device_code: DeviceCode) {
-        use crate::auth::DeviceCodeFlow;
+    /// Save the conversation and start a new, empty one.
+    pub fn clear_conversation(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before clearing".to_string());
+            return;
+        }
+        if let Err(e) = self.save_session() {
+            self.toast_error(format!("Could not save session: {}", e));
+            return;
+        }
+        self.chat = ChatState {
+            shortcodes: self.chat.shortcodes,
+            raw_all: self.chat.raw_all,
+            ..ChatState::new(Vec::new())
+        };
+        self.sections = SectionState::default();
+        self.session = None;
+        self.scroll.scroll_to_top();
+    }
 
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        self.oauth_rx = Some(rx);
+    /// Fork the conversation into a playground that is never saved, or leave
+    /// the playground and return to the conversation it was forked from.
+    pub fn toggle_playground(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish first".to_string());
+            return;
+        }
+        if let Some(playground) = self.playground.take() {
+            self.chat = playground.origin;
+            self.session = playground.origin_session;
+            self.sections = SectionState::default();
+            let max_scroll = self.max_scroll();
+            self.scroll.scroll_to_bottom(max_scroll);
+            self.toast_info("Playground discarded".to_string());
+            return;
+        }
+        if let Err(e) = self.save_session() {
+            self.toast_error(format!("Could not save session: {}", e));
+            return;
+        }
+        let fork = ChatState {
+            shortcodes: self.chat.shortcodes,
+            raw_all: self.chat.raw_all,
+            ..ChatState::new(self.chat.messages.to_vec())
+        };
+        self.playground = Some(Playground {
+            origin: std::mem::replace(&mut self.chat, fork),
+            origin_session: self.session.take(),
+        });
+        self.sections = SectionState::default();
+        self.toast_info("Playground: nothing here is saved; Ctrl+K keeps it".to_string());
+    }
 
-        let dc = device_code.clone();
-        tokio::spawn(async move {
-            let flow = match provider {
-                Provider::GitHubCopilot => DeviceCodeFlow::github_copilot(),
-                _ => {
-                    let _ = tx.send(Err("Provider does not support OAuth device code".to_string()));
-                    return;
-                }
-            };
-            
-            match flow.poll_for_token(&dc, || {}).await {
-                Ok(token) => {
-                    let _ = tx.send(Ok(token));
-                }
-                Err(e) => {
-                    let _ = tx.send(Err(e.to_string()));
-                }
+    /// Keep the playground as a new saved session.
+    pub fn keep_playground(&mut self) {
+        if self.playground.take().is_none() {
+            self.toast_info("Not in a playground; /playground forks one".to_string());
+            return;
+        }
+        match self.save_session() {
+            Ok(()) if self.sessions.is_none() => {
+                self.toast_warning("Session storage is unavailable".to_string());
             }
-        });
+            Ok(()) => {
+                self.toast_success("Playground kept as a new session");
+            }
+            Err(e) => {
+                self.toast_error(format!("Could not save session: {}", e));
+            }
+        }
+    }
 
-        self.connect = ConnectState::OAuthPolling {
-            provider,
-            auth_dialog: AuthDialog::new(provider.display_name(), device_code),
+    /// Revert the last conversational action.
+    pub fn undo(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before undoing".to_string());
+            return;
+        }
+        match self.chat.history.undo(&mut self.chat.messages) {
+            Some(label) => self.toast_info(format!("Undid {}", label)),
+            None => self.toast_info("Nothing to undo".to_string()),
         };
     }
 
-    /// Process async OAuth polling results.
-    ///
-    /// Call this in the event loop to check for completed OAuth flows.
-    /// Returns true if OAuth completed (success or failure).
-    pub fn process_oauth(&mut self) -> bool {
-        if let Some(mut rx) = self.oauth_rx.take() {
-            match rx.try_recv() {
-                Ok(Ok(token)) => {
-                    // OAuth succeeded
-                    if let ConnectState::OAuthPolling { provider, .. }
-                    | ConnectState::OAuthPending { provider, .. } = &self.connect
-                    {
-                        let provider = *provider;
-                        self.complete_oauth(provider, token);
-                    }
-                    return true;
+    /// Reapply the last undone action.
+    pub fn redo(&mut self) {
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before redoing".to_string());
+            return;
+        }
+        match self.chat.history.redo(&mut self.chat.messages) {
+            Some(label) => self.toast_info(format!("Redid {}", label)),
+            None => self.toast_info("Nothing to redo".to_string()),
+        };
+    }
+
+    /// Topic sections of the current conversation.
+    pub fn sections(&self) -> Arc<[Section]> {
+        self.sections.segments(&self.chat.messages)
+    }
+
+    /// Open the jump-to-section picker at the section currently in view.
+    pub fn open_section_picker(&mut self) {
+        let sections = self.sections();
+        if sections.len() < 2 {
+            self.toast_info("No sections yet; they appear once the topic changes".to_string());
+            return;
+        }
+        let current = sections
+            .iter()
+            .position(|s| s.contains(self.scroll.offset))
+            .unwrap_or(0);
+        self.section_picker = Some(SectionPicker::new(current));
+    }
+
+    /// Scroll to the start of a section and close the picker.
+    pub fn jump_to_section(&mut self, index: usize) {
+        if let Some(section) = self.sections().get(index) {
+            self.scroll.offset = section.start;
+        }
+        self.section_picker = None;
+    }
+
+    /// Fold or unfold a section.
+    pub fn toggle_section(&mut self, index: usize) {
+        if let Some(section) = self.sections().get(index) {
+            self.sections.toggle(section.start);
+        }
+    }
+
+    /// Compare a resumed session's provider/model with the active one.
+    ///
+    /// On mismatch, prompts to switch back to the session's model or continue
+    /// with the current one. Returns true if a prompt was opened.
+    pub fn check_session_model(&mut self, provider: Provider, model: &str) -> bool {
+        if self.llm.config.provider == provider && self.llm.config.model == model {
+            return false;
+        }
+        self.model_mismatch = Some(ModelMismatch {
+            session_provider: provider,
+            session_model: model.to_string(),
+            selected: 0,
+        });
+        true
+    }
+
+    /// Resolve the model mismatch prompt, recording the outcome as a system note.
+    pub fn resolve_model_mismatch(&mut self, switch_back: bool) {
+        let Some(mismatch) = self.model_mismatch.take() else {
+            return;
+        };
+        let original = format!(
+            "{} ({})",
+            mismatch.session_model,
+            mismatch.session_provider.display_name()
+        );
+
+        let note = if switch_back {
+            if self.llm.config.provider == mismatch.session_provider {
+                self.llm.config.model = mismatch.session_model.clone();
+                self.llm.apply_config();
+            } else {
+                // Switching providers may need credentials
+                self.start_connection(mismatch.session_provider);
+                if self.connect.is_active() {
+                    self.toast_info(format!(
+                        "Select {} once connected to resume with the original model",
+                        mismatch.session_model
+                    ));
+                } else {
+                    self.llm.config.model = mismatch.session_model.clone();
+                    self.llm.apply_config();
                 }
-                Ok(Err(e)) => {
-                    // OAuth failed
-                    self.toast_error(format!("Authentication failed: {}", e));
-                    self.connect = ConnectState::None;
-                    return true;
+            }
+            format!("Switched back to the session's original model, {}", original)
+        } else {
+            format!(
+                "Continued with {} ({}); this session was started with {}",
+                self.llm.config.model,
+                self.llm.config.provider.display_name(),
+                original
+            )
+        };
+        self.chat.messages.push(Message::system_note(note));
+    }
+
+    /// Open the local models panel and query Ollama for loaded models.
+    pub fn open_local_models(&mut self) {
+        self.local_models = Some(LocalModelsPanel::new());
+        self.refresh_local_models(None);
+    }
+
+    /// Re-query loaded models, optionally unloading one first.
+    pub fn refresh_local_models(&mut self, unload: Option<String>) {
+        let ollama = OllamaProvider::new(self.llm.config.clone());
+
+        if let Some(panel) = &mut self.local_models {
+            panel.loading = true;
+        }
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.local_models_rx = Some(rx);
+
+        tokio::spawn(async move {
+            let result = async {
+                if let Some(model) = unload {
+                    ollama.unload(&model).await?;
                 }
-                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
-                    // Still pending, put the receiver back
-                    self.oauth_rx = Some(rx);
+                ollama.running_models().await
+            }
+            .await;
+            let _ = tx.send(result);
+        });
+    }
+
+    /// Close the local models panel.
+    pub fn close_local_models(&mut self) {
+        self.local_models = None;
+        self.local_models_rx = None;
+    }
+
+    /// Process local model query results. Call this in the event loop.
+    pub fn process_local_models(&mut self) {
+        let Some(rx) = &mut self.local_models_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                Err("Local model query failed".to_string())
+            }
+        };
+        self.local_models_rx = None;
+        if let Some(panel) = &mut self.local_models {
+            panel.update(result);
+        }
+    }
+
+    /// Hold the prompt of a request that never reached the provider until the
+    /// connection returns. Returns false if the reply had already started.
+    fn queue_unsent(&mut self) -> bool {
+        let n = self.chat.messages.len();
+        let unanswered = n >= 2
+            && self.chat.messages[n - 1].is_chat()
+            && self.chat.messages[n - 1].role == Role::Assistant
+            && self.chat.messages[n - 1].content.is_empty()
+            && self.chat.messages[n - 2].is_chat()
+            && self.chat.messages[n - 2].role == Role::User;
+        if !unanswered {
+            return false;
+        }
+        self.chat.messages.pop();
+        if let Some(prompt) = self.chat.messages.last_mut() {
+            prompt.queued = true;
+        }
+        self.llm.offline.offline = true;
+        self.llm.offline.last_probe = Some(Instant::now());
+        self.toast_warning("Offline — will send when the connection returns".to_string());
+        true
+    }
+
+    /// Check whether an unreachable provider is back, and send queued messages
+    /// when it is. Call this in the event loop.
+    pub fn process_offline(&mut self) {
+        let offline = &mut self.llm.offline;
+        if !offline.offline {
+            return;
+        }
+        let Some(rx) = &mut offline.probe_rx else {
+            if offline
+                .last_probe
+                .is_none_or(|at| at.elapsed() >= OFFLINE_PROBE_INTERVAL)
+            {
+                offline.last_probe = Some(Instant::now());
+                offline.probe_rx = llm::probe(&self.llm.config.api_base);
+            }
+            return;
+        };
+        let reachable = match rx.try_recv() {
+            Ok(reachable) => reachable,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => false,
+        };
+        offline.probe_rx = None;
+        if !reachable {
+            return;
+        }
+
+        offline.offline = false;
+        self.llm.status = ConnectionStatus::Ready;
+        let mut queued = false;
+        for message in &mut self.chat.messages {
+            queued |= std::mem::take(&mut message.queued);
+        }
+        if queued && !self.is_streaming() {
+            self.toast_info("Back online — sending queued message".to_string());
+            self.start_request();
+        }
+    }
+
+    /// How long the conversation should wait for the provider's quotas to
+    /// reset before it is sent, if they could not cover it now.
+    fn rate_limit_wait(&self) -> Option<Duration> {
+        let provider = self.llm.config.provider;
+        let tokens: usize = self
+            .chat
+            .messages
+            .iter()
+            .filter(|m| m.is_sent())
+            .map(|m| tokens::message_tokens(m.sent_text(), provider))
+            .sum();
+        self.llm.rate_limits.wait_for(tokens as u64, Instant::now())
+    }
+
+    /// Send prompts held for the provider's quotas once they have reset.
+    /// Call this in the event loop.
+    pub fn process_rate_limit(&mut self) {
+        let Some(until) = self.llm.rate_limits.held_until else {
+            return;
+        };
+        if Instant::now() < until || self.is_streaming() {
+            return;
+        }
+        self.llm.rate_limits.held_until = None;
+        // Gone offline meanwhile: the offline queue sends them instead
+        if self.llm.offline.offline {
+            return;
+        }
+        let mut queued = false;
+        for message in &mut self.chat.messages {
+            queued |= std::mem::take(&mut message.queued);
+        }
+        if queued {
+            self.start_request();
+        }
+    }
+
+    /// Renew expiring OAuth tokens in the background, and switch the
+    /// connection to a renewed one. Call this in the event loop.
+    pub fn process_token_refresh(&mut self) {
+        let refresh = &mut self.llm.token_refresh;
+        if !refresh.enabled {
+            return;
+        }
+        let Some(rx) = &mut refresh.rx else {
+            if refresh
+                .last_check
+                .is_none_or(|at| at.elapsed() >= TOKEN_REFRESH_INTERVAL)
+            {
+                refresh.last_check = Some(Instant::now());
+                let warn_hours = refresh.warn_hours;
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                tokio::spawn(async move {
+                    let refreshed = crate::auth::refresh_due().await;
+                    let failed: Vec<Provider> = refreshed
+                        .iter()
+                        .filter(|r| r.result.is_err())
+                        .map(|r| r.provider)
+                        .collect();
+                    let until = chrono::Utc::now() + chrono::Duration::hours(warn_hours as i64);
+                    let expiring = match crate::auth::AuthStorage::load() {
+                        Ok(storage) if warn_hours > 0 => {
+                            crate::auth::expiring(&storage, until, &failed)
+                        }
+                        _ => Vec::new(),
+                    };
+                    let _ = tx.send((refreshed, expiring));
+                });
+                refresh.rx = Some(rx);
+            }
+            return;
+        };
+        let (refreshed, expiring) = match rx.try_recv() {
+            Ok(checked) => checked,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => (Vec::new(), Vec::new()),
+        };
+        refresh.rx = None;
+
+        for Refreshed {
+            provider,
+            old_token,
+            result,
+        } in refreshed
+        {
+            match result {
+                Ok(credential) => {
+                    self.llm.token_refresh.warned.retain(|p| *p != provider);
+                    let config = &mut self.llm.config;
+                    // Keys from the environment or config file are left alone
+                    if config.provider != provider
+                        || !(config.api_key == old_token || config.api_key.is_empty())
+                    {
+                        continue;
+                    }
+                    config.api_key = credential.token().to_string();
+                    config.credential_type = crate::llm::CredentialType::OAuth;
+                    // A reply streaming now finishes on the connection it started with
+                    self.llm.client = Some(LlmClient::new(config.clone()));
                 }
-                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
-                    // Channel closed unexpectedly
-                    self.toast_error("OAuth task failed unexpectedly");
-                    self.connect = ConnectState::None;
-                    return true;
+                Err(e) => {
+                    if !self.llm.token_refresh.warned.contains(&provider) {
+                        self.llm.token_refresh.warned.push(provider);
+                        self.toast_warning(format!(
+                            "Could not renew the {} sign-in ({}); press Ctrl+L to sign in again",
+                            provider.display_name(),
+                            e
+                        ));
+                    }
                 }
             }
         }
-        false
+
+        let refresh = &mut self.llm.token_refresh;
+        refresh
+            .announced
+            .retain(|p| expiring.iter().any(|e| e.provider == *p));
+        let now = chrono::Utc::now();
+        let mut notices = Vec::new();
+        for expiring in &expiring {
+            let provider = expiring.provider;
+            // A failed renewal was reported already
+            if refresh.announced.contains(&provider) || refresh.warned.contains(&provider) {
+                continue;
+            }
+            refresh.announced.push(provider);
+            notices.push(format!(
+                "The {} sign-in {}; press Ctrl+L to sign in again",
+                provider.display_name(),
+                expiring.describe(now)
+            ));
+        }
+        refresh.expiring = expiring;
+        for notice in notices {
+            self.toast_warning(notice);
+        }
     }
 
-    /// Complete OAuth authentication.
-    ///
-    /// For GitHub Copilot, transitions to model selection.
-    /// For other providers, saves the token and switches immediately.
-    fn complete_oauth(&mut self, provider: Provider, token: OAuthToken) {
-        // For Copilot, transition to model selection
-        if provider == Provider::GitHubCopilot {
-            self.connect = ConnectState::SelectingModel {
-                provider,
-                selected: 0,
-                oauth_token: token,
-                anthropic_method: None,
-            };
+    /// Sign in again to the first provider whose sign-in runs out soon (Ctrl+L).
+    pub fn reconnect_expiring(&mut self) {
+        let Some(expiring) = self.llm.token_refresh.expiring.first().copied() else {
+            return;
+        };
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before signing in".to_string());
+            return;
+        }
+        self.start_connection(expiring.provider);
+        // Skip offering the credential that is about to run out
+        if matches!(self.connect, ConnectState::ExistingCredential { .. }) {
+            self.enter_new_credentials();
+        }
+    }
+
+    /// Process the result of a model warm-up. Call this in the event loop.
+    pub fn process_warm_up(&mut self) {
+        let Some(rx) = &mut self.llm.warm_up_rx else {
             return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => Ok(()),
+        };
+        self.llm.warm_up_rx = None;
+
+        // A message sent while warming up has already moved the status on
+        if self.llm.status == ConnectionStatus::WarmingUp {
+            self.llm.status = ConnectionStatus::Ready;
         }
+        if let Err(e) = result {
+            self.toast_warning(format!("Could not preload {}: {}", self.llm.config.model, e));
+        }
+    }
 
-        // For other providers, complete immediately
-        self.finish_oauth_connection(provider, token, provider.default_model(), None);
+    /// Handle a character input.
+    pub fn handle_char(&mut self, c: char) {
+        self.chat.handle_char(c);
     }
 
-    /// Finish OAuth connection with the selected model.
+    /// Handle pasted text (bracketed paste).
     ///
-    /// Saves credentials and switches to the provider.
-    /// For Anthropic CreateApiKey flow, converts OAuth token to API key.
-    fn finish_oauth_connection(
-        &mut self,
-        provider: Provider,
-        token: OAuthToken,
-        model: &str,
-        anthropic_method: Option<crate::auth::AnthropicAuthMethod>,
-    ) {
-        use crate::auth::{AnthropicAuthMethod, AnthropicOAuth, AuthStorage, Credential};
+    /// Credential fields take the trimmed text; in the chat input, dropped
+    /// files are offered as attachments.
+    pub fn handle_paste(&mut self, text: &str) {
+        match &mut self.connect {
+            ConnectState::EnteringApiKey {
+                input,
+                cursor,
+                error,
+                ..
+            }
+            | ConnectState::EnteringAuthCode {
+                input,
+                cursor,
+                error,
+                ..
+            } => {
+                let text = text.trim();
+                input.insert_str(*cursor, text);
+                *cursor += text.len();
+                *error = None;
+                return;
+            }
+            ConnectState::None => {}
+            _ => return,
+        }
+        if self.menu.visible
+            || self.local_models.is_some()
+            || self.section_picker.is_some()
+            || self.selection.is_some()
+            || self.session_picker.is_some()
+            || self.global_search.is_some()
+            || self.profile_picker.is_some()
+            || self.tool_confirmation.is_some()
+            || self.file_tree.is_some()
+            || self.attachments.is_some()
+            || self.model_mismatch.is_some()
+            || self.pasted_files.is_some()
+            || self.submit_guard.pending.is_some()
+            || self.submit_guard.spend.is_some()
+            || self.submit_guard.expansion.is_some()
+        {
+            return;
+        }
+
+        let insert = match paste::classify(text) {
+            Pasted::Files(paths) => {
+                self.pasted_files = Some(PastedFiles {
+                    paths,
+                    text: text.trim().to_string(),
+                });
+                return;
+            }
+            Pasted::Missing(path) => {
+                self.toast_warning(format!("File not found: {}", path.display()));
+                text.trim().to_string()
+            }
+            Pasted::Text(text) => text,
+        };
+        self.chat.input.insert_str(self.chat.cursor_position, &insert);
+        self.chat.cursor_position += insert.len();
+    }
+
+    /// Answer the pasted files prompt: attach the files, paste the paths as
+    /// text, or neither. Images go to a model with image input as images
+    /// (like `/attach`); other files become `@path` chips.
+    pub fn resolve_pasted_files(&mut self, attach: Option<bool>) {
+        let Some(pasted) = self.pasted_files.take() else {
+            return;
+        };
+        match attach {
+            Some(true) => {
+                let vision = ModelCapabilities::for_model(self.llm.config.provider, &self.llm.config.model).vision;
+                let mut chips = Vec::new();
+                for path in pasted.paths {
+                    if !vision || image::media_type(&path).is_none() {
+                        chips.push(path);
+                        continue;
+                    }
+                    match Image::load(&path) {
+                        Ok(image) => self.chat.images.push(image),
+                        Err(e) => {
+                            self.toast_error(format!("{:#}", e));
+                        }
+                    }
+                }
+                self.insert_chips(&chips);
+            }
+            Some(false) => {
+                self.chat.input.insert_str(self.chat.cursor_position, &pasted.text);
+                self.chat.cursor_position += pasted.text.len();
+            }
+            None => {}
+        }
+    }
+
+    /// Insert `@path` chips for `paths` at the cursor.
+    pub fn insert_chips(&mut self, paths: &[PathBuf]) {
+        if paths.is_empty() {
+            return;
+        }
+        let chips: Vec<String> = paths.iter().map(|p| paste::chip(p)).collect();
+        // Keep chips separate from surrounding words
+        let before = &self.chat.input[..self.chat.cursor_position];
+        let lead = if before.is_empty() || before.ends_with(' ') { "" } else { " " };
+        let insert = format!("{}{} ", lead, chips.join(" "));
+        self.chat.input.insert_str(self.chat.cursor_position, &insert);
+        self.chat.cursor_position += insert.len();
+    }
+
+    /// Open the file tree on the current directory.
+    pub fn open_file_tree(&mut self) {
+        match std::env::current_dir() {
+            Ok(dir) => self.file_tree = Some(FileTree::new(dir)),
+            Err(e) => {
+                self.toast_error(format!("Cannot read current directory: {}", e));
+            }
+        }
+    }
+
+    /// Close the file tree, attaching the picked files as chips.
+    pub fn attach_files(&mut self, paths: Vec<PathBuf>) {
+        self.file_tree = None;
+        self.insert_chips(&paths);
+        let noun = if paths.len() == 1 { "file" } else { "files" };
+        self.toast_info(format!("Attached {} {}", paths.len(), noun));
+    }
+
+    /// Whether a dialog or overlay is drawn over the chat.
+    pub fn overlay_open(&self) -> bool {
+        self.connect.is_active()
+            || self.menu.visible
+            || self.local_models.is_some()
+            || self.section_picker.is_some()
+            || self.session_picker.is_some()
+            || self.global_search.is_some()
+            || self.profile_picker.is_some()
+            || self.file_tree.is_some()
+            || self.attachments.is_some()
+            || self.tool_confirmation.is_some()
+            || self.model_mismatch.is_some()
+            || self.pasted_files.is_some()
+            || self.submit_guard.pending.is_some()
+            || self.submit_guard.spend.is_some()
+            || self.submit_guard.expansion.is_some()
+            || self.viewer.is_some()
+    }
+
+    /// Open the attachment manager on the chips in the draft.
+    pub fn open_attachments(&mut self) {
+        let panel = AttachmentsPanel::new(&self.chat.input);
+        if panel.attachments.is_empty() {
+            self.toast_info("Nothing attached; Ctrl+T or dropping a file adds @path chips".to_string());
+            return;
+        }
+        self.attachments = Some(panel);
+    }
+
+    /// Close the attachment manager, writing `chips` back into the draft.
+    pub fn apply_attachments(&mut self, chips: Vec<String>) {
+        self.attachments = None;
+        self.chat.input = paste::replace_chips(&self.chat.input, &chips);
+        self.chat.cursor_position = self.chat.input.len();
+    }
+
+    /// Handle backspace key.
+    pub fn handle_backspace(&mut self) {
+        self.chat.handle_backspace();
+    }
+
+    /// Move cursor left.
+    pub fn move_cursor_left(&mut self) {
+        self.chat.move_cursor_left();
+    }
+
+    /// Move cursor right.
+    pub fn move_cursor_right(&mut self) {
+        self.chat.move_cursor_right();
+    }
+
+    /// Scroll up one line.
+    pub fn scroll_up(&mut self) {
+        self.scroll.scroll_up();
+    }
+
+    /// Scroll down one line.
+    pub fn scroll_down(&mut self, max_scroll: usize) {
+        self.scroll.scroll_down(max_scroll);
+    }
+
+    /// Scroll up by page size.
+    pub fn scroll_page_up(&mut self, page_size: usize) {
+        self.scroll.scroll_page_up(page_size);
+    }
+
+    /// Scroll down by page size.
+    pub fn scroll_page_down(&mut self, max_scroll: usize, page_size: usize) {
+        self.scroll.scroll_page_down(max_scroll, page_size);
+    }
+
+    /// Scroll to top.
+    pub fn scroll_to_top(&mut self) {
+        self.scroll.scroll_to_top();
+    }
+
+    /// Scroll to bottom.
+    pub fn scroll_to_bottom(&mut self, max_scroll: usize) {
+        self.scroll.scroll_to_bottom(max_scroll);
+    }
+
+    /// Open the full-screen viewer on the message at the top of the chat view.
+    pub fn open_viewer(&mut self) {
+        self.open_viewer_at(self.scroll.offset);
+    }
+
+    /// Open the full-screen viewer on the message at `index`.
+    pub fn open_viewer_at(&mut self, index: usize) {
+        let Some(msg) = self.chat.messages.get(index) else {
+            return;
+        };
+        let role = if msg.is_chat() {
+            msg.role.prefix().trim().trim_end_matches(':')
+        } else {
+            "Note"
+        };
+        let title = format!(
+            "{} · message {} of {}",
+            role,
+            index + 1,
+            self.chat.messages.len()
+        );
+        self.viewer = Some(MessageViewer::new(title, &msg.content));
+    }
+
+    /// Switch the reply at the top of the chat view between rendered and raw.
+    pub fn toggle_raw_message(&mut self) {
+        let index = self.scroll.offset;
+        let is_reply = self
+            .chat
+            .messages
+            .get(index)
+            .is_some_and(|m| m.is_chat() && m.role == Role::Assistant);
+        if !is_reply {
+            self.toast_info("Scroll a reply to the top to toggle it".to_string());
+            return;
+        }
+        if !self.chat.raw_flipped.remove(&index) {
+            self.chat.raw_flipped.insert(index);
+        }
+        let mode = if self.chat.is_raw(index) { "raw source" } else { "rendered" };
+        self.toast_info(format!("Showing reply as {}", mode));
+    }
+
+    /// Switch every reply between rendered and raw.
+    pub fn toggle_raw_all(&mut self) {
+        self.chat.raw_all = !self.chat.raw_all;
+        self.chat.raw_flipped.clear();
+        let mode = if self.chat.raw_all { "raw source" } else { "rendered markdown" };
+        self.toast_info(format!("Showing replies as {}", mode));
+    }
+
+    /// Expand or collapse the reasoning shown above replies.
+    pub fn toggle_thinking(&mut self) {
+        self.chat.show_thinking = !self.chat.show_thinking;
+        let mode = if self.chat.show_thinking { "expanded" } else { "collapsed" };
+        self.toast_info(format!("Thinking {}", mode));
+    }
+
+    /// Show or hide the model, timing and token usage under replies.
+    pub fn toggle_reply_info(&mut self) {
+        self.chat.show_info = !self.chat.show_info;
+    }
+
+    /// Show or hide the minimap column.
+    pub fn toggle_minimap(&mut self) {
+        self.scroll.minimap = !self.scroll.minimap;
+    }
+
+    /// Show or hide the profiling overlay.
+    pub fn toggle_profile(&mut self) {
+        self.show_profile = !self.show_profile;
+    }
+
+    /// Scroll to the previous (or next) user prompt.
+    pub fn jump_to_prompt(&mut self, forward: bool) {
+        let is_prompt = |m: &Message| m.is_chat() && m.role == Role::User;
+        let offset = self.scroll.offset;
+        let target = if forward {
+            self.chat
+                .messages
+                .iter()
+                .enumerate()
+                .skip(offset + 1)
+                .find(|(_, m)| is_prompt(m))
+        } else {
+            self.chat
+                .messages
+                .iter()
+                .enumerate()
+                .take(offset)
+                .rev()
+                .find(|(_, m)| is_prompt(m))
+        };
+        if let Some((index, _)) = target {
+            self.scroll.offset = index;
+        }
+    }
+
+    /// Whether the message at `index` is a prompt.
+    fn is_prompt_at(&self, index: usize) -> bool {
+        self.chat
+            .messages
+            .get(index)
+            .is_some_and(|m| m.is_chat() && m.role == Role::User)
+    }
+
+    /// The prompt at or above `index`, or else the first one after it.
+    fn prompt_near(&self, index: usize) -> Option<usize> {
+        let is_prompt = |m: &Message| m.is_chat() && m.role == Role::User;
+        self.chat
+            .messages
+            .iter()
+            .take(index + 1)
+            .rposition(is_prompt)
+            .or_else(|| self.chat.messages.iter().position(is_prompt))
+    }
+
+    /// Put the prompt at the top of the view, or the closest one above it,
+    /// into the input to edit and resend (Ctrl+E). Sending it drops the
+    /// original and everything after it.
+    pub fn edit_prompt(&mut self) {
+        self.edit_prompt_at(self.scroll.offset);
+    }
+
+    /// Put the prompt at `index`, or the closest one above it, into the
+    /// input to edit and resend.
+    pub fn edit_prompt_at(&mut self, index: usize) {
+        if let Some(guest) = &self.guest {
+            self.toast_warning(format!("This is {}'s session; they share it", guest.host));
+            return;
+        }
+        if self.chat.editing.is_none() && !self.chat.input.trim().is_empty() {
+            self.toast_warning("Send or clear the draft before editing a prompt".to_string());
+            return;
+        }
+        let Some(index) = self.prompt_near(index) else {
+            self.toast_info("No prompt to edit".to_string());
+            return;
+        };
+        self.selection = None;
+        self.chat.input = self.chat.messages[index].content.clone();
+        self.chat.cursor_position = self.chat.input.len();
+        self.chat.editing = Some(index);
+        self.scroll.offset = index;
+        self.toast_info("Editing: Enter resends and drops what followed, Esc cancels".to_string());
+    }
+
+    /// Stop editing a prompt, discarding the changes.
+    pub fn cancel_edit(&mut self) {
+        if self.chat.editing.take().is_some() {
+            self.chat.clear_input();
+            let max_scroll = self.max_scroll();
+            self.scroll.scroll_to_bottom(max_scroll);
+        }
+    }
+
+    /// Start selecting messages (Ctrl+V) from the one at the top of the view.
+    pub fn open_selection(&mut self) {
+        let Some(last) = self.chat.messages.len().checked_sub(1) else {
+            return;
+        };
+        self.selection = Some(MessageSelection::new(self.scroll.offset.min(last)));
+    }
+
+    /// Open the search bar (Ctrl+F), or go back to editing its query.
+    pub fn open_search(&mut self) {
+        match &mut self.search {
+            Some(search) => search.editing = true,
+            None => self.search = Some(ConversationSearch::new()),
+        }
+    }
+
+    /// Act on a key pressed in the search bar.
+    pub fn handle_search_action(&mut self, action: SearchAction) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let found = match action {
+            SearchAction::Continue => return,
+            SearchAction::Close => {
+                self.search = None;
+                return;
+            }
+            SearchAction::Changed => {
+                search.find(&self.chat.messages, self.scroll.offset);
+                search.current_match()
+            }
+            SearchAction::Next => search.step(true),
+            SearchAction::Previous => search.step(false),
+        };
+        if let Some(found) = found {
+            self.show_message(found.message);
+        }
+    }
+
+    /// Scroll so message `index` is in view, unfolding its section, and
+    /// leave the view alone if it already is. The last message drawn may be
+    /// cut off, so it counts as out of view.
+    pub fn show_message(&mut self, index: usize) {
+        let sections = self.sections();
+        if let Some(section) = sections.iter().find(|s| s.contains(index)) {
+            if self.sections.is_collapsed(section.start) {
+                self.sections.toggle(section.start);
+            }
+        }
+        let end = self.scroll.visible_end.saturating_sub(1).max(self.scroll.offset + 1);
+        if index < self.scroll.offset || index >= end {
+            self.scroll.offset = index.min(self.max_scroll());
+        }
+    }
+
+    /// Scroll as little as possible to keep the selected message in view.
+    pub fn follow_selection(&mut self) {
+        let Some(selected) = self.selection.as_ref().map(|s| s.selected) else {
+            return;
+        };
+        let end = self.scroll.visible_end.max(self.scroll.offset + 1);
+        if selected < self.scroll.offset {
+            self.scroll.offset = selected;
+        } else if selected >= end {
+            self.scroll.offset = (self.scroll.offset + selected + 1 - end).min(selected);
+        }
+    }
+
+    /// Copy `text` to the clipboard, naming `what` was copied in the toast.
+    fn copy_to_clipboard(&mut self, text: &str, what: &str) {
+        match clipboard::copy(text, self.clipboard_config.backend) {
+            Ok(Copied::Tool(_)) => self.toast_success(format!("Copied {} to the clipboard", what)),
+            Ok(Copied::Osc52) => {
+                self.toast_success(format!("Sent {} to the terminal's clipboard", what))
+            }
+            Err(e) => self.toast_error(format!("Could not copy: {}", e)),
+        };
+    }
+
+    /// Select chat text by dragging with the left button, and scroll with
+    /// the wheel. Dialogs and overlays ignore the mouse.
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.overlay_open() || self.selection.is_some() {
+            return;
+        }
+        let area = self.mouse.chat_area;
+        let (column, row) = (event.column, event.row);
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let inside = area.intersects(Rect::new(column, row, 1, 1));
+                self.mouse.drag = inside.then(|| DragSelection::start(column, row, area));
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(drag) = self.mouse.drag.as_mut().filter(|d| d.dragging) {
+                    drag.extend(column, row, area);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(drag) = &mut self.mouse.drag {
+                    if !drag.release() {
+                        self.mouse.drag = None;
+                    }
+                }
+            }
+            // What is highlighted would move under the selection
+            MouseEventKind::ScrollUp => {
+                self.mouse.drag = None;
+                self.scroll_up();
+            }
+            MouseEventKind::ScrollDown => {
+                self.mouse.drag = None;
+                self.scroll_down(self.max_scroll());
+            }
+            _ => {}
+        }
+    }
+
+    /// Copy the text the last draw read back from a finished drag.
+    pub fn copy_drag_selection(&mut self) {
+        let Some(text) = self.mouse.copied.take() else {
+            return;
+        };
+        if text.trim().is_empty() {
+            self.mouse.drag = None;
+            return;
+        }
+        self.copy_to_clipboard(&text, "the selection");
+    }
+
+    /// Copy the message at `index` to the clipboard.
+    pub fn copy_message(&mut self, index: usize) {
+        let Some(msg) = self.chat.messages.get(index) else {
+            return;
+        };
+        let text = msg.content.clone();
+        self.copy_to_clipboard(&text, "the message");
+    }
+
+    /// Copy the most recent reply to the clipboard (`/copy`, Ctrl+Shift+C).
+    pub fn copy_last_reply(&mut self) {
+        let reply = self
+            .chat
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.is_chat() && m.role == Role::Assistant && !m.content.is_empty());
+        let Some(reply) = reply else {
+            self.toast_info("No reply to copy yet".to_string());
+            return;
+        };
+        let text = reply.content.clone();
+        self.copy_to_clipboard(&text, "the last reply");
+    }
+
+    /// Delete the message at `index`; `/undo` brings it back.
+    pub fn delete_message(&mut self, index: usize) {
+        if let Some(guest) = &self.guest {
+            self.toast_warning(format!("This is {}'s session; they share it", guest.host));
+            return;
+        }
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before deleting".to_string());
+            return;
+        }
+        if index >= self.chat.messages.len() {
+            return;
+        }
+        let message = self.chat.messages.remove(index);
+        self.chat.history.record(
+            "delete message",
+            Edit::Insert {
+                index,
+                messages: vec![message],
+            },
+        );
+        self.chat.editing = match self.chat.editing {
+            Some(editing) if editing == index => None,
+            Some(editing) if editing > index => Some(editing - 1),
+            editing => editing,
+        };
+        match self.chat.messages.len().checked_sub(1) {
+            Some(last) => {
+                if let Some(selection) = &mut self.selection {
+                    selection.selected = selection.selected.min(last);
+                }
+                self.scroll.offset = self.scroll.offset.min(last);
+            }
+            None => self.selection = None,
+        }
+        self.toast_info("Deleted message; /undo brings it back".to_string());
+    }
+
+    /// Pin or unpin the message at `index`. Pinned messages are sent even
+    /// once older messages no longer fit the context window.
+    pub fn toggle_pin(&mut self, index: usize) {
+        let Some(msg) = self.chat.messages.get_mut(index).filter(|m| m.is_chat()) else {
+            self.toast_info("Only prompts and replies can be pinned".to_string());
+            return;
+        };
+        msg.pinned = !msg.pinned;
+        if msg.pinned {
+            self.toast_info("Pinned: kept in the context when older messages are left out".to_string());
+        } else {
+            self.toast_info("Unpinned".to_string());
+        }
+    }
+
+    /// Send the prompt at `index`, or the closest one above it, again.
+    pub fn rerun_prompt(&mut self, index: usize) {
+        if !self.chat.input.trim().is_empty() || self.chat.editing.is_some() {
+            self.toast_warning("Send or clear the draft before rerunning a prompt".to_string());
+            return;
+        }
+        let Some(index) = self.prompt_near(index) else {
+            self.toast_info("No prompt to rerun".to_string());
+            return;
+        };
+        self.selection = None;
+        self.chat.input = self.chat.messages[index].content.clone();
+        self.chat.cursor_position = self.chat.input.len();
+        self.submit_message();
+    }
+
+    /// Scroll to the first message that was unread when the session was opened.
+    pub fn jump_to_unread(&mut self) {
+        match self.chat.first_unread {
+            Some(index) => self.scroll.offset = index.min(self.max_scroll()),
+            None => {
+                self.toast_info("No unread messages".to_string());
+            }
+        }
+    }
+
+    /// Update scrollbar state.
+    pub fn update_scroll_state(&mut self, total_items: usize) {
+        self.scroll.update(total_items);
+    }
+
+    /// Toggle menu visibility.
+    pub fn toggle_menu(&mut self) {
+        self.menu.toggle();
+    }
+
+    /// Move menu selection up.
+    pub fn menu_up(&mut self) {
+        self.menu.up();
+    }
+
+    /// Move menu selection down.
+    pub fn menu_down(&mut self, menu_items_count: usize, submenu_items_count: usize) {
+        self.menu.down(menu_items_count, submenu_items_count);
+    }
+
+    /// Get the currently selected menu item.
+    pub fn selected_menu_item(&self) -> Option<&MenuItem> {
+        App::menu_items().get(self.menu.selected)
+    }
+
+    /// Get the currently selected provider (when in provider submenu).
+    pub fn selected_provider(&self) -> Option<Provider> {
+        if self.menu.in_submenu {
+            Provider::all().get(self.menu.submenu_selected).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Switch to a new provider.
+    pub fn switch_provider(&mut self, provider: Provider) {
+        self.llm.config.provider = provider;
+        self.llm.config.api_base = provider.default_api_base().to_string();
+        self.llm.config.model = provider.default_model().to_string();
+        
+        // Try to load API key from environment
+        let env_var = provider.env_var_name();
+        if !env_var.is_empty() {
+            if let Ok(key) = std::env::var(env_var) {
+                self.llm.config.api_key = key;
+            } else {
+                self.llm.config.api_key.clear();
+            }
+        } else {
+            // Provider doesn't need an API key (e.g., Ollama)
+            self.llm.config.api_key.clear();
+        }
+        
+        self.llm.apply_config();
+        self.menu.close();
+        
+        // Add a message about the provider switch
+        let status = if self.llm.config.is_configured() || !provider.requires_api_key() {
+            format!("Switched to {}. Ready to chat!", provider.display_name())
+        } else {
+            format!(
+                "Switched to {}. Set {} to connect.",
+                provider.display_name(),
+                provider.env_var_name()
+            )
+        };
+        self.chat.messages.push(Message::assistant(status));
+    }
+
+    /// Save the conversation to the session store, if it has been started.
+    ///
+    /// The first save creates the session record; later saves update it.
+    pub fn save_session(&mut self) -> anyhow::Result<()> {
+        let Some(store) = &self.sessions else {
+            return Ok(());
+        };
+        if self.playground.is_some() {
+            return Ok(());
+        }
+        if !self
+            .chat
+            .messages
+            .iter()
+            .any(|m| m.is_chat() && m.role == Role::User)
+        {
+            return Ok(());
+        }
+        let streaming = self.is_streaming();
+        let read = self.chat.read;
+        let system_prompt = self.chat.system_prompt.clone();
+        let config = &self.llm.config;
+        let session = self
+            .session
+            .get_or_insert_with(|| Session::new(config.provider.storage_key(), &config.model));
+        session.provider = config.provider.storage_key().to_string();
+        session.model = config.model.clone();
+        session.set_messages(&self.chat.messages);
+        session.read = Some(read.min(session.messages.len()));
+        session.system_prompt = system_prompt;
+        if streaming {
+            // A checkpoint: if it is loaded again, the reply never finished
+            if let Some(reply) = session
+                .messages
+                .last_mut()
+                .filter(|m| m.role == Role::Assistant)
+            {
+                reply.interrupted = true;
+            }
+        }
+        match &mut self.indexer {
+            Some(indexer) => {
+                store.save_record(session)?;
+                indexer.queue(session.clone());
+                Ok(())
+            }
+            None => {
+                session.derive_title();
+                store.save(session)
+            }
+        }
+    }
+
+    /// Apply what the session indexer finished. Call this in the event loop.
+    pub fn process_indexer(&mut self) {
+        let Some(indexer) = &mut self.indexer else {
+            return;
+        };
+        for result in indexer.poll() {
+            match result {
+                Ok(indexed) => {
+                    if let Some(session) = self
+                        .session
+                        .as_mut()
+                        .filter(|s| s.id == indexed.id && s.title.is_empty())
+                    {
+                        session.title = indexed.title;
+                    }
+                }
+                Err(e) => {
+                    self.toast_error(format!("Indexing failed: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Save the conversation and sync saved sessions with the `[sync]` remote
+    /// in the background.
+    pub fn start_sync(&mut self) {
+        if self.sync_rx.is_some() {
+            self.toast_info("Already syncing".to_string());
+            return;
+        }
+        if self.sync_config.backend.is_none() {
+            self.toast_warning("Sync is off; set [sync] backend in the config file".to_string());
+            return;
+        }
+        let Some(store) = self.sessions.clone() else {
+            self.toast_warning("Session storage is unavailable".to_string());
+            return;
+        };
+        if let Err(e) = self.save_session() {
+            self.toast_error(format!("Could not save session: {}", e));
+            return;
+        }
+        let config = self.sync_config.clone();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.sync_rx = Some(rx);
+        tokio::task::spawn_blocking(move || {
+            let result = sync::run(&config, store.as_ref()).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+        self.toast_info("Syncing sessions…".to_string());
+    }
+
+    /// Report a finished sync. Call this in the event loop.
+    pub fn process_sync(&mut self) {
+        let Some(rx) = &mut self.sync_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                Err("Sync stopped unexpectedly".to_string())
+            }
+        };
+        self.sync_rx = None;
+        match result {
+            Ok(report) => {
+                // Show the synced copy of the open conversation unless it moved on meanwhile
+                let chat_len = self.chat.messages.iter().filter(|m| m.is_chat()).count();
+                let pulled = self
+                    .session
+                    .as_ref()
+                    .filter(|s| report.updated.contains(&s.id) && s.messages.len() == chat_len)
+                    .and_then(|s| self.sessions.as_ref()?.load(&s.id).ok());
+                if let Some(session) = pulled.filter(|_| !self.is_streaming()) {
+                    self.load_session(session);
+                }
+                self.toast_success(format!("Synced: {}", report));
+            }
+            Err(e) => {
+                self.toast_error(format!("Sync failed: {}", e));
+            }
+        }
+    }
+
+    /// Share the conversation live (`/share` read-only, `/share write` letting
+    /// guests prompt too), or stop sharing it.
+    pub fn toggle_share(&mut self, args: &str) {
+        if let Some(guest) = &self.guest {
+            self.toast_warning(format!("This is {}'s session; they share it", guest.host));
+            return;
+        }
+        if let Some(host) = self.share.take() {
+            let guests = host.guests.len();
+            self.toast_info(format!("Stopped sharing; {} guest(s) disconnected", guests));
+            return;
+        }
+        let mode = match args {
+            "" => ShareMode::ReadOnly,
+            "write" => ShareMode::CoWrite,
+            other => {
+                self.toast_warning(format!("Unknown share mode {}; use /share or /share write", other));
+                return;
+            }
+        };
+        let name = self
+            .share_config
+            .name
+            .clone()
+            .unwrap_or_else(share::default_name);
+        match ShareHost::start(&self.share_config.bind, mode, name) {
+            Ok(host) => {
+                let note = format!(
+                    "Sharing this conversation ({}). To join from another terminal or machine:\n\n    scry attach <this machine>:{} --token {}\n\n/share again stops sharing.",
+                    mode.label(),
+                    host.addr.port(),
+                    host.token
+                );
+                self.chat.messages.push(Message::system_note(note));
+                let max_scroll = self.max_scroll();
+                self.scroll.scroll_to_bottom(max_scroll);
+                self.share = Some(host);
+            }
+            Err(e) => {
+                self.toast_error(format!("Could not share: {:#}", e));
+            }
+        }
+    }
+
+    /// Exchange the conversation with guests or the host of a shared session.
+    /// Call this in the event loop.
+    pub fn process_share(&mut self) {
+        let notices = self.share.as_mut().map(ShareHost::poll).unwrap_or_default();
+        for notice in notices {
+            match notice {
+                HostNotice::Joined(name) => {
+                    self.toast_info(format!("{} joined", name));
+                }
+                HostNotice::Left(name) => {
+                    self.toast_info(format!("{} left", name));
+                }
+                HostNotice::Prompt { author, .. } if self.llm.dry_run => {
+                    self.toast_info(format!("{}'s prompt waits until dry run is off", author));
+                }
+                HostNotice::Prompt { .. } => {}
+            }
+        }
+        // Guest prompts wait for the reply in progress and a spending cap
+        // answer, and are not spent on dry runs
+        let waiting = self.llm.is_streaming() || self.submit_guard.spend.is_some();
+        let next = match &mut self.share {
+            Some(host) if !waiting && !self.llm.dry_run => host.queued.pop_front(),
+            _ => None,
+        };
+        if let Some((author, text)) = next {
+            let draft = std::mem::take(&mut self.chat.input);
+            let cursor = self.chat.cursor_position;
+            self.chat.input = text;
+            self.send_input_from(Some(author), false);
+            self.chat.input = draft;
+            self.chat.cursor_position = cursor;
+        }
+        let streaming = self.is_streaming();
+        if let Some(host) = &mut self.share {
+            host.publish(&self.chat.messages, streaming);
+        }
+
+        let Some(guest) = &mut self.guest else {
+            return;
+        };
+        for notice in guest.poll() {
+            match notice {
+                GuestNotice::Snapshot(snapshot) => {
+                    let follow = self.scroll.offset >= self.chat.max_scroll();
+                    self.chat.messages = snapshot.messages.iter().map(|m| m.to_message()).collect();
+                    if follow {
+                        let max_scroll = self.max_scroll();
+                        self.scroll.scroll_to_bottom(max_scroll);
+                    }
+                }
+                GuestNotice::Closed(reason) => {
+                    self.chat.messages.push(Message::system_note(reason.clone()));
+                    self.toast_warning(reason);
+                }
+            }
+        }
+    }
+
+    /// Send the prompts in a file one after another (`/batch file.txt`), or
+    /// stop sending them (`/batch stop`).
+    pub fn start_batch(&mut self, args: &str) {
+        if args == "stop" {
+            match &mut self.batch {
+                Some(batch) => {
+                    batch.stop();
+                    self.toast_info("Batch stopped; no more prompts are sent".to_string());
+                }
+                None => {
+                    self.toast_info("No batch is running".to_string());
+                }
+            }
+            return;
+        }
+        if args.is_empty() {
+            self.toast_warning("Usage: /batch <file> (one prompt per line)".to_string());
+            return;
+        }
+        if self.batch.is_some() {
+            self.toast_warning("A batch is already running; /batch stop ends it".to_string());
+            return;
+        }
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before starting a batch".to_string());
+            return;
+        }
+        if self.llm.client.as_ref().is_some_and(|c| !c.is_configured()) {
+            self.toast_warning("Connect a provider before starting a batch".to_string());
+            return;
+        }
+        match Batch::load(Path::new(args)) {
+            Ok(batch) => {
+                self.chat.messages.push(Message::system_note(format!(
+                    "Sending {} prompts from {}",
+                    batch.total, batch.source
+                )));
+                self.batch = Some(batch);
+            }
+            Err(e) => {
+                self.toast_error(format!("{:#}", e));
+            }
+        }
+    }
+
+    /// Send the next batch prompt once the previous answer is in, resending
+    /// rate-limited ones after a wait. Call this in the event loop.
+    pub fn process_batch(&mut self) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        // Offline prompts wait in the queue; tools and spending caps wait for permission
+        if self.llm.is_streaming()
+            || self.tool_confirmation.is_some()
+            || self.submit_guard.spend.is_some()
+            || self.chat.messages.last().is_some_and(|m| m.queued)
+            || batch.is_paused(Instant::now())
+        {
+            return;
+        }
+        let limits = self.llm.batch_limits;
+
+        // How the last prompt went
+        if let Some(mut prompt) = batch.current.take() {
+            let error = self
+                .chat
+                .messages
+                .last()
+                .and_then(|m| m.error.as_ref())
+                .map(|card| card.error.clone());
+            match error {
+                Some(ProviderError::RateLimited { retry_after, .. })
+                    if prompt.attempts < limits.retries =>
+                {
+                    let wait = Batch::rate_limit_wait(prompt.attempts, retry_after);
+                    prompt.attempts += 1;
+                    let line = prompt.line;
+                    batch.resend = Some(prompt);
+                    batch.resume_at = Some(Instant::now() + wait);
+                    self.toast_warning(format!(
+                        "Rate limited; resending line {} in {}s",
+                        line,
+                        wait.as_secs()
+                    ));
+                    return;
+                }
+                Some(error) => batch.failures.push(BatchFailure {
+                    line: prompt.line,
+                    error: format!("{}: {}", error.title(), error.message()),
+                }),
+                None => batch.answered += 1,
+            }
+            batch.resume_at = Some(Instant::now() + limits.interval);
+            return;
+        }
+
+        if let Some(prompt) = batch.resend.take() {
+            batch.current = Some(prompt);
+            // Drop the error card and the partial reply, as Ctrl+R does
+            self.chat.messages.pop();
+            if self
+                .chat
+                .messages
+                .last()
+                .is_some_and(|m| m.is_chat() && m.role == Role::Assistant)
+            {
+                self.chat.messages.pop();
+            }
+            self.start_request();
+            return;
+        }
+
+        match batch.next_prompt() {
+            Some(prompt) => {
+                let draft = std::mem::take(&mut self.chat.input);
+                let cursor = self.chat.cursor_position;
+                self.chat.input = prompt.text.clone();
+                batch.current = Some(prompt);
+                self.send_input_from(None, false);
+                self.chat.input = draft;
+                self.chat.cursor_position = cursor;
+            }
+            None => {
+                let summary = batch.summary();
+                let failed = !batch.failures.is_empty();
+                self.batch = None;
+                self.chat.messages.push(Message::system_note(summary));
+                let max_scroll = self.max_scroll();
+                self.scroll.scroll_to_bottom(max_scroll);
+                if failed {
+                    self.toast_warning("Batch finished with failures".to_string());
+                } else {
+                    self.toast_success("Batch finished".to_string());
+                }
+                if let Err(e) = self.save_session() {
+                    self.toast_error(format!("Could not save session: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Save the conversation if the autosave interval has passed and it changed,
+    /// or checkpoint the reply while it streams. Call this in the event loop.
+    pub fn autosave(&mut self) {
+        if self.is_streaming() {
+            self.checkpoint();
+            return;
+        }
+        if self.autosave.interval.is_zero()
+            || self.sessions.is_none()
+            || self.autosave.last.elapsed() < self.autosave.interval
+        {
+            return;
+        }
+        self.autosave.last = Instant::now();
+        let fingerprint = (
+            self.chat.messages.len(),
+            self.chat.messages.last().map_or(0, |m| m.content.len()),
+        );
+        if fingerprint == self.autosave.saved {
+            return;
+        }
+        match self.save_session() {
+            Ok(()) => self.autosave.saved = fingerprint,
+            Err(e) => {
+                self.toast_error(format!("Autosave failed: {}", e));
+            }
+        }
+    }
+
+    /// Save the streaming reply so far if the checkpoint interval has passed.
+    fn checkpoint(&mut self) {
+        if self.autosave.checkpoint.is_zero()
+            || self.sessions.is_none()
+            || self.autosave.checkpointed.elapsed() < self.autosave.checkpoint
+            || self.chat.messages.last().is_none_or(|m| m.content.is_empty())
+        {
+            return;
+        }
+        self.autosave.checkpointed = Instant::now();
+        match self.save_session() {
+            Ok(()) => self.autosave.checkpoint_saved = true,
+            Err(e) => {
+                self.toast_error(format!("Checkpoint failed: {}", e));
+            }
+        }
+    }
+
+    /// Replace the conversation with a saved session.
+    pub fn load_session(&mut self, session: Session) {
+        let unread = session.unread();
+        let mut note = format!(
+            "Resumed “{}” ({} messages, {}",
+            session.title,
+            session.messages.len(),
+            session.model
+        );
+        if unread > 0 {
+            note.push_str(&format!("; {} unread, Ctrl+U jumps to the first", unread));
+        }
+        note.push(')');
+        let read = session.messages.len() - unread;
+        let mut messages = session.chat_messages();
+        messages.push(Message::system_note(note));
+        self.chat = ChatState {
+            shortcodes: self.chat.shortcodes,
+            raw_all: self.chat.raw_all,
+            read,
+            first_unread: (unread > 0).then_some(read),
+            system_prompt: session.system_prompt.clone(),
+            ..ChatState::new(messages)
+        };
+        self.sections = SectionState::default();
+        self.session = Some(session);
+        let max_scroll = self.max_scroll();
+        self.scroll.scroll_to_bottom(max_scroll);
+    }
+
+    /// Save the current conversation and open the saved session browser.
+    pub fn open_session_picker(&mut self) {
+        self.menu.close();
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching sessions".to_string());
+            return;
+        }
+        if self.playground.is_some() {
+            self.toast_warning("Leave the playground (/playground) or keep it (Ctrl+K) first".to_string());
+            return;
+        }
+        if let Err(e) = self.save_session() {
+            self.toast_error(format!("Could not save session: {}", e));
+            return;
+        }
+        let Some(store) = &self.sessions else {
+            self.toast_warning("Session storage is unavailable".to_string());
+            return;
+        };
+        let current = self.session.as_ref().map(|s| s.id.as_str());
+        match store.list() {
+            Ok(list) => {
+                let others: Vec<_> = list
+                    .into_iter()
+                    .filter(|s| Some(s.id.as_str()) != current)
+                    .collect();
+                if others.is_empty() {
+                    self.toast_info("No saved sessions to resume".to_string());
+                } else {
+                    self.session_picker = Some(SessionPicker::new(others));
+                }
+            }
+            Err(e) => {
+                self.toast_error(format!("Could not list sessions: {}", e));
+            }
+        }
+    }
+
+    /// Replace the conversation with the saved session `id` and close the browser.
+    pub fn open_session(&mut self, id: &str) {
+        self.session_picker = None;
+        let Some(store) = &self.sessions else {
+            return;
+        };
+        match store.load(id) {
+            Ok(session) => self.load_session(session),
+            Err(e) => {
+                self.toast_error(format!("Could not load session: {}", e));
+            }
+        }
+    }
+
+    /// Save the current conversation and open the search across sessions.
+    pub fn open_global_search(&mut self) {
+        self.menu.close();
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching sessions".to_string());
+            return;
+        }
+        if self.playground.is_some() {
+            self.toast_warning("Leave the playground (/playground) or keep it (Ctrl+K) first".to_string());
+            return;
+        }
+        if let Err(e) = self.save_session() {
+            self.toast_error(format!("Could not save session: {}", e));
+            return;
+        }
+        if self.sessions.is_none() {
+            self.toast_warning("Session storage is unavailable".to_string());
+            return;
+        }
+        self.global_search = Some(GlobalSearch::new());
+    }
+
+    /// Find the global search's query in every saved session.
+    pub fn run_global_search(&mut self) {
+        let (Some(search), Some(store)) = (&mut self.global_search, &self.sessions) else {
+            return;
+        };
+        let query = search.query.trim().to_string();
+        match store.search_messages(&query, GLOBAL_SEARCH_LIMIT) {
+            Ok(hits) => search.set_hits(query, hits),
+            Err(e) => {
+                self.toast_error(format!("Could not search sessions: {}", e));
+            }
+        }
+    }
+
+    /// Open the session of global search hit `index` at the matching
+    /// message, with the query in the search bar so the match is highlighted.
+    pub fn open_search_hit(&mut self, index: usize) {
+        let Some(search) = self.global_search.take() else {
+            return;
+        };
+        let Some(hit) = search.hits.get(index) else {
+            return;
+        };
+        self.open_session(&hit.session.id);
+        if self.session.as_ref().map(|s| &s.id) != Some(&hit.session.id) {
+            return;
+        }
+        let Some(message) = hit.message else {
+            return;
+        };
+        // Sessions are searched ignoring case, which a lowercase query does in the bar
+        let mut bar = ConversationSearch {
+            query: search.searched.unwrap_or_default().to_lowercase(),
+            editing: false,
+            ..ConversationSearch::new()
+        };
+        bar.find(&self.chat.messages, message);
+        self.search = Some(bar);
+        // Nothing of this session has been drawn yet
+        self.scroll.visible_end = 0;
+        self.show_message(message);
+    }
+
+    /// Open the profile picker over the main config and the named profiles.
+    pub fn open_profile_picker(&mut self) {
+        self.menu.close();
+        self.profile_picker = Some(ProfilePicker::new(profiles::list(), profiles::active()));
+    }
+
+    /// Switch to the profile `name` (`None` for the main config): its
+    /// provider, model, system prompt and credentials replace the current
+    /// connection. The conversation is kept.
+    pub fn switch_profile(&mut self, name: Option<String>) {
+        self.profile_picker = None;
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching profiles".to_string());
+            return;
+        }
+        if let Err(e) = profiles::set_active(name.as_deref()) {
+            self.toast_error(format!("{:#}", e));
+            return;
+        }
+        let config = Config::load();
+        self.use_profile_config(&config);
+        self.toast_info(format!(
+            "Switched to {} ({} · {})",
+            name.map_or("the main config".to_string(), |name| format!("the {} profile", name)),
+            self.llm.config.provider.display_name(),
+            self.llm.config.model
+        ));
+    }
+
+    /// Connect with the provider, model and credentials `config` names.
+    fn use_profile_config(&mut self, config: &Config) {
+        let provider = config.llm.default_provider().unwrap_or_default();
+        self.llm.config = LlmConfig::for_provider(provider, Some(&config.llm));
+        self.llm.system_prompt = config.llm.system_prompt.clone();
+        // Warnings were about the other profile's sign-ins
+        self.llm.token_refresh = TokenRefresh {
+            enabled: self.llm.token_refresh.enabled,
+            warn_hours: config.behavior.expiry_warning_hours,
+            ..TokenRefresh::default()
+        };
+        self.llm.apply_config();
+    }
+
+    /// Get the list of menu items.
+    pub fn menu_items() -> &'static [MenuItem] {
+        MenuItem::all()
+    }
+
+    /// Get max scroll offset based on message count.
+    pub fn max_scroll(&self) -> usize {
+        self.chat.max_scroll()
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────────
+    // Toast notification methods
+    // ─────────────────────────────────────────────────────────────────────────────
+
+    /// Add a toast notification.
+    pub fn toast(&mut self, message: impl Into<String>, level: ToastLevel) -> u64 {
+        self.toasts.push(message, level)
+    }
+
+    /// Add an info toast.
+    pub fn toast_info(&mut self, message: impl Into<String>) -> u64 {
+        self.toasts.info(message)
+    }
+
+    /// Add a success toast.
+    pub fn toast_success(&mut self, message: impl Into<String>) -> u64 {
+        self.toasts.success(message)
+    }
+
+    /// Add a warning toast.
+    pub fn toast_warning(&mut self, message: impl Into<String>) -> u64 {
+        self.toasts.warning(message)
+    }
+
+    /// Add an error toast.
+    pub fn toast_error(&mut self, message: impl Into<String>) -> u64 {
+        self.toasts.error(message)
+    }
+
+    /// Dismiss a toast by ID.
+    pub fn dismiss_toast(&mut self, id: u64) {
+        self.toasts.dismiss(id)
+    }
+
+    /// Tick the toast system to remove expired toasts.
+    /// Call this on each frame/tick.
+    pub fn tick_toasts(&mut self) {
+        self.toasts.tick()
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────────
+    // Connection flow methods
+    // ─────────────────────────────────────────────────────────────────────────────
+
+    /// Start the connection flow for a provider.
+    ///
+    /// This checks for existing credentials and shows the appropriate dialog:
+    /// - If credentials exist: Show ExistingCredential dialog
+    /// - If OAuth provider (Copilot): Start OAuth device flow
+    /// - Otherwise: Show SelectingMethod dialog
+    pub fn start_connection(&mut self, provider: Provider) {
+        use crate::auth::AuthStorage;
+
+        // Close the menu first
+        self.menu.close();
+
+        // Check for existing credentials
+        if let Ok(storage) = AuthStorage::load() {
+            if let Some(cred) = storage.get(provider.storage_key()) {
+                if !cred.is_expired() {
+                    let masked = mask_api_key(cred.token());
+                    let current_model = cred.model().map(|s| s.to_string());
+                    let scope = self
+                        .llm
+                        .config
+                        .scopes
+                        .get(provider.storage_key())
+                        .and_then(|scope| scope.summary());
+                    self.connect = ConnectState::ExistingCredential {
+                        provider,
+                        masked_key: masked,
+                        current_model,
+                        scope,
+                        selected: 0,
+                    };
+                    return;
+                }
+            }
+        }
+
+        // No existing credentials - determine how to connect
+        if provider == Provider::Anthropic {
+            // Anthropic - show authentication method selection
+            self.connect = ConnectState::SelectingAnthropicMethod { selected: 0 };
+        } else if provider.uses_oauth() {
+            // OAuth providers (Copilot) - start device code flow
+            self.start_oauth_flow(provider);
+        } else if provider == Provider::LocalServer {
+            // Ask where the server runs, then offer its models
+            let input = if self.llm.config.provider == Provider::LocalServer {
+                self.llm.config.api_base.clone()
+            } else {
+                provider.default_api_base().to_string()
+            };
+            self.connect = ConnectState::EnteringServerUrl {
+                cursor: input.len(),
+                input,
+                error: None,
+            };
+        } else if provider == Provider::Ollama {
+            // Offer the installed models rather than assuming the default is pulled
+            self.start_ollama_models();
+        } else if !provider.requires_api_key() {
+            // Provider doesn't need auth - connect directly
+            self.complete_connection(provider, None);
+        } else {
+            // Show method selection dialog
+            self.connect = ConnectState::SelectingMethod {
+                provider,
+                selected: 0,
+            };
+        }
+    }
+
+    /// Cancel the connection flow and return to normal state.
+    pub fn cancel_connection(&mut self) {
+        self.connect = ConnectState::None;
+        self.device_code_rx = None;
+        self.oauth_rx = None;
+        self.model_list_rx = None;
+        self.server_models_rx = None;
+        self.ollama_models_rx = None;
+        self.ollama_pull_rx = None;
+    }
+
+    /// Fetch the models of the local server at `url` (as typed by the user).
+    pub fn start_server_models(&mut self, url: &str) {
+        let api_base = crate::llm::normalize_base_url(url);
+        let signing = self.llm.config.signing.clone();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.server_models_rx = Some(rx);
+        self.connect = ConnectState::FetchingServerModels {
+            api_base: api_base.clone(),
+        };
+
+        tokio::spawn(async move {
+            let _ = tx.send(crate::llm::list_models(&api_base, "", None, signing.as_ref()).await);
+        });
+    }
+
+    /// Show the local server's models once fetched, or return to the URL on failure.
+    pub fn process_server_models(&mut self) {
+        let Some(rx) = &mut self.server_models_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                Err("Model list request failed".to_string())
+            }
+        };
+        self.server_models_rx = None;
+        let ConnectState::FetchingServerModels { api_base } = &self.connect else {
+            return;
+        };
+        let api_base = api_base.clone();
+        self.connect = match result {
+            Ok(models) if models.is_empty() => ConnectState::EnteringServerUrl {
+                cursor: api_base.len(),
+                input: api_base,
+                error: Some("The server has no models loaded".to_string()),
+            },
+            Ok(models) => {
+                // Keep the current model selected when reconnecting
+                let selected = models
+                    .iter()
+                    .position(|m| *m == self.llm.config.model)
+                    .unwrap_or(0);
+                ConnectState::SelectingServerModel {
+                    api_base,
+                    models,
+                    selected,
+                }
+            }
+            Err(e) => ConnectState::EnteringServerUrl {
+                cursor: api_base.len(),
+                input: api_base,
+                error: Some(e),
+            },
+        };
+    }
+
+    /// Connect to the local server with the model picked from its list.
+    pub fn select_server_model(&mut self) {
+        let ConnectState::SelectingServerModel {
+            api_base,
+            models,
+            selected,
+        } = &self.connect
+        else {
+            return;
+        };
+        let Some(model) = models.get(*selected).cloned() else {
+            return;
+        };
+        let provider = Provider::LocalServer;
+        self.llm.config.provider = provider;
+        self.llm.config.api_base = api_base.clone();
+        self.llm.config.model = model.clone();
+        // A key configured for another provider must not leak to this server
+        self.llm.config.api_key.clear();
+        self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+        self.llm.apply_config();
+        self.connect = ConnectState::None;
+        self.toast_success(format!("Connected to {} with {}", provider.display_name(), model));
+    }
+
+    /// Fetch the models installed in Ollama.
+    pub fn start_ollama_models(&mut self) {
+        // Keep a configured Ollama address when reconnecting
+        let api_base = if self.llm.config.provider == Provider::Ollama
+            && !self.llm.config.api_base.is_empty()
+        {
+            self.llm.config.api_base.clone()
+        } else {
+            Provider::Ollama.default_api_base().to_string()
+        };
+        let ollama = OllamaProvider::new(LlmConfig {
+            provider: Provider::Ollama,
+            api_base: api_base.clone(),
+            ..self.llm.config.clone()
+        });
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.ollama_models_rx = Some(rx);
+        self.connect = ConnectState::FetchingOllamaModels { api_base };
+
+        tokio::spawn(async move {
+            let _ = tx.send(ollama.installed_models().await);
+        });
+    }
+
+    /// Show the installed Ollama models once fetched.
+    pub fn process_ollama_models(&mut self) {
+        let Some(rx) = &mut self.ollama_models_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                Err("Model list request failed".to_string())
+            }
+        };
+        self.ollama_models_rx = None;
+        let ConnectState::FetchingOllamaModels { api_base } = &self.connect else {
+            return;
+        };
+        let api_base = api_base.clone();
+        let configured = self.llm.config.provider == Provider::Ollama;
+        let current = if configured {
+            self.llm.config.model.clone()
+        } else {
+            Provider::Ollama.default_model().to_string()
+        };
+        match result {
+            // Nothing to pick from, or the configured model is missing
+            Ok(models)
+                if models.is_empty()
+                    || (configured && !models.iter().any(|m| m.name == current)) =>
+            {
+                self.connect = ConnectState::OfferingOllamaPull {
+                    api_base,
+                    model: current,
+                    installed: models,
+                    selected: 0,
+                };
+            }
+            Ok(models) => {
+                // Keep the current model selected when reconnecting
+                let selected = models.iter().position(|m| m.name == current).unwrap_or(0);
+                self.connect = ConnectState::SelectingOllamaModel {
+                    api_base,
+                    models,
+                    selected,
+                };
+            }
+            Err(e) => {
+                self.connect = ConnectState::None;
+                self.toast_error(format!("Could not list Ollama models: {}", e));
+            }
+        }
+    }
+
+    /// Connect to Ollama with the model picked from the installed ones.
+    pub fn select_ollama_model(&mut self) {
+        let ConnectState::SelectingOllamaModel {
+            api_base,
+            models,
+            selected,
+        } = &self.connect
+        else {
+            return;
+        };
+        let Some(model) = models.get(*selected).map(|m| m.name.clone()) else {
+            return;
+        };
+        let api_base = api_base.clone();
+        self.connect_ollama(api_base, model);
+    }
+
+    /// Switch to Ollama at `api_base` with `model`.
+    fn connect_ollama(&mut self, api_base: String, model: String) {
+        let provider = Provider::Ollama;
+        self.llm.config.provider = provider;
+        self.llm.config.api_base = api_base;
+        self.llm.config.model = model.clone();
+        self.llm.config.api_key.clear();
+        self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+        self.llm.apply_config();
+        self.connect = ConnectState::None;
+        self.toast_success(format!("Connected to {} with {}", provider.display_name(), model));
+    }
+
+    /// Pick from the installed models instead of pulling the missing one.
+    pub fn choose_installed_ollama_model(&mut self) {
+        if let ConnectState::OfferingOllamaPull {
+            api_base, installed, ..
+        } = std::mem::take(&mut self.connect)
+        {
+            self.connect = ConnectState::SelectingOllamaModel {
+                api_base,
+                models: installed,
+                selected: 0,
+            };
+        }
+    }
+
+    /// Start pulling the model offered by the pull dialog.
+    pub fn start_ollama_pull(&mut self) {
+        let ConnectState::OfferingOllamaPull {
+            api_base, model, ..
+        } = std::mem::take(&mut self.connect)
+        else {
+            return;
+        };
+        let ollama = OllamaProvider::new(LlmConfig {
+            provider: Provider::Ollama,
+            api_base: api_base.clone(),
+            ..self.llm.config.clone()
+        });
+        self.ollama_pull_rx = Some(ollama.pull(&model));
+        self.connect = ConnectState::PullingOllamaModel {
+            api_base,
+            model,
+            progress: PullStatus::default(),
+        };
+    }
+
+    /// Show pull progress, connecting once the model is ready.
+    pub fn process_ollama_pull(&mut self) {
+        let Some(rx) = &mut self.ollama_pull_rx else {
+            return;
+        };
+        loop {
+            let result = match rx.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::error::TryRecvError::Empty) => return,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    Err("Pull stopped unexpectedly".to_string())
+                }
+            };
+            let ConnectState::PullingOllamaModel {
+                api_base,
+                model,
+                progress,
+            } = &mut self.connect
+            else {
+                self.ollama_pull_rx = None;
+                return;
+            };
+            match result {
+                Ok(status) if status.is_success() => {
+                    let (api_base, model) = (api_base.clone(), model.clone());
+                    self.ollama_pull_rx = None;
+                    self.connect_ollama(api_base, model);
+                    return;
+                }
+                Ok(status) => *progress = status,
+                Err(e) => {
+                    let model = model.clone();
+                    self.ollama_pull_rx = None;
+                    self.connect = ConnectState::None;
+                    self.toast_error(format!("Could not pull {}: {}", model, e));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Complete the connection successfully.
+    ///
+    /// Saves credentials (if provided) and switches to the provider.
+    pub fn complete_connection(&mut self, provider: Provider, api_key: Option<String>) {
+        use crate::auth::{AuthStorage, Credential};
+
+        // Save credential if provided
+        if let Some(key) = &api_key {
+            if let Ok(mut storage) = AuthStorage::load() {
+                storage.set(provider.storage_key(), Credential::api_key(key));
+                if let Err(e) = storage.save() {
+                    self.toast_warning(format!("Could not save credentials: {}", e));
+                }
+            }
+        }
+
+        // Configure and switch to the provider
+        self.llm.config.provider = provider;
+        self.llm.config.api_base = provider.default_api_base().to_string();
+        self.llm.config.model = provider.default_model().to_string();
+
+        if let Some(key) = api_key {
+            self.llm.config.api_key = key;
+            self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+        } else if !provider.requires_api_key() {
+            self.llm.config.api_key.clear();
+            self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+        }
+
+        self.llm.apply_config();
+        self.connect = ConnectState::None;
+
+        self.toast_success(format!("Connected to {}", provider.display_name()));
+    }
+
+    /// Handle a connection error.
+    ///
+    /// Shows the error in the EnteringApiKey state so the user can try again.
+    pub fn connection_error(&mut self, error: String) {
+        if let ConnectState::ValidatingKey { provider, key, .. } = &self.connect {
+            self.connect = ConnectState::EnteringApiKey {
+                provider: *provider,
+                input: key.clone(),
+                cursor: key.len(),
+                error: Some(error),
+            };
+        } else {
+            // Fallback: show toast
+            self.toast_error(error);
+            self.connect = ConnectState::None;
+        }
+    }
+
+    /// Use existing credentials to connect.
+    pub fn use_existing_credentials(&mut self) {
+        use crate::auth::{AuthStorage, OAuthToken};
+
+        if let ConnectState::ExistingCredential { provider, .. } = self.connect {
+            if let Ok(storage) = AuthStorage::load() {
+                if let Some(cred) = storage.get(provider.storage_key()) {
+                    let key = cred.token().to_string();
+                    
+                    // Special handling for Copilot
+                    if provider == Provider::GitHubCopilot {
+                        // If model is saved, check validation cache
+                        if let Some(model) = cred.model() {
+                            // Check if token was already validated this session
+                            if self.validated_tokens.get(provider.storage_key()) == Some(&true) {
+                                // Already validated - connect directly
+                                self.llm.config.provider = provider;
+                                self.llm.config.api_base = provider.default_api_base().to_string();
+                                self.llm.config.model = model.to_string();
+                                self.llm.config.api_key = key;
+                                self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
+                                self.llm.apply_config();
+                                self.connect = ConnectState::None;
+                                self.toast_success(format!("Connected to {} with {}", 
+                                    provider.display_name(), model));
+                                return;
+                            } else {
+                                // Need to validate token first
+                                self.start_copilot_validation(key.clone(), model.to_string());
+                                return;
+                            }
+                        }
+                        
+                        // No saved model - show model selection dialog
+                        let oauth_token = OAuthToken {
+                            access_token: key,
+                            token_type: "bearer".to_string(),
+                            scope: Some("read:user".to_string()),
+                            expires_in: None,
+                            refresh_token: cred.refresh_token().map(|s| s.to_string()),
+                        };
+                        
+                        // Copilot doesn't use Anthropic auth
+                        self.show_model_selection(provider, oauth_token, None);
+                        return;
+                    }
+                    
+                    // For other providers, connect directly
+                    // Determine credential type from storage
+                    use crate::auth::Credential;
+                    let credential_type = match cred {
+                        Credential::OAuth { .. } => crate::llm::CredentialType::OAuth,
+                        Credential::ApiKey { .. } => crate::llm::CredentialType::ApiKey,
+                    };
+                    
+                    self.llm.config.provider = provider;
+                    self.llm.config.api_base = provider.default_api_base().to_string();
+                    if let Some(model) = cred.model() {
+                        self.llm.config.model = model.to_string();
+                    } else {
+                        self.llm.config.model = provider.default_model().to_string();
+                    }
+                    self.llm.config.api_key = key;
+                    self.llm.config.credential_type = credential_type;
+                    self.llm.apply_config();
+                    self.connect = ConnectState::None;
+                    self.toast_success(format!("Connected to {}", provider.display_name()));
+                    return;
+                }
+            }
+            // Fallback if credential disappeared
+            self.toast_error("Credential not found");
+            self.connect = ConnectState::None;
+        }
+    }
+
+    /// Change the model for an existing Copilot connection.
+    pub fn change_copilot_model(&mut self) {
+        use crate::auth::{AuthStorage, OAuthToken};
+
+        if let ConnectState::ExistingCredential { provider, .. } = self.connect {
+            if provider != Provider::GitHubCopilot {
+                return;
+            }
+
+            if let Ok(storage) = AuthStorage::load() {
+                if let Some(cred) = storage.get(provider.storage_key()) {
+                    // Convert credential to OAuthToken
+                    let oauth_token = OAuthToken {
+                        access_token: cred.token().to_string(),
+                        token_type: "bearer".to_string(),
+                        scope: Some("read:user".to_string()),
+                        expires_in: None,
+                        refresh_token: cred.refresh_token().map(|s| s.to_string()),
+                    };
+
+                    // Transition to model selection
+                    self.show_model_selection(provider, oauth_token, None);
+                }
+            }
+        }
+    }
+
+    /// Enter new credentials (from ExistingCredential or SelectingMethod state).
+    pub fn enter_new_credentials(&mut self) {
+        let provider = match &self.connect {
+            ConnectState::ExistingCredential { provider, .. } => *provider,
+            ConnectState::SelectingMethod { provider, .. } => *provider,
+            _ => return,
+        };
+
+        // Anthropic has its own OAuth flow with method selection
+        if provider == Provider::Anthropic {
+            self.connect = ConnectState::SelectingAnthropicMethod { selected: 0 };
+        } else if provider.uses_oauth() {
+            // Copilot uses OAuth device code flow
+            self.start_oauth_flow(provider);
+        } else {
+            self.connect = ConnectState::EnteringApiKey {
+                provider,
+                input: String::new(),
+                cursor: 0,
+                error: None,
+            };
+        }
+    }
+
+    /// Start async validation of an API key.
+    ///
+    /// Spawns a background task to validate the key and stores the receiver.
+    pub fn start_validation(&mut self, provider: Provider, key: String) {
+        use crate::llm::validate_api_key;
+
+        let scope = self.llm.config.scopes.get(provider.storage_key()).cloned();
+        let signing = self.llm.config.signing.clone();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.validation_rx = Some(rx);
+        self.connect = ConnectState::ValidatingKey {
+            provider,
+            key: key.clone(),
+            model: None,
+        };
+
+        tokio::spawn(async move {
+            let result = validate_api_key(provider, &key, scope.as_ref(), signing.as_ref()).await;
+            let _ = tx.send(result);
+        });
+    }
+
+    /// Start async validation of a Copilot OAuth token.
+    ///
+    /// Spawns a background task to validate the token with the Copilot API.
+    /// On success, connects with the saved model. On failure, prompts for re-authentication.
+    fn start_copilot_validation(&mut self, key: String, model: String) {
+        use crate::llm::CopilotProvider;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.validation_rx = Some(rx);
+        
+        self.connect = ConnectState::ValidatingKey {
+            provider: Provider::GitHubCopilot,
+            key: key.clone(),
+            model: Some(model),
+        };
+        
+        self.toast_info("Validating Copilot token...".to_string());
+
+        tokio::spawn(async move {
+            let provider = CopilotProvider::new();
+            // Set the OAuth token so validate_token can use it
+            *provider.oauth_token.write().await = Some(key);
+            
+            let result = provider.validate_token().await
+                .map(|_| ())
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+
+    /// Process async validation results.
+    ///
+    /// Call this in the event loop to check for completed validations.
+    /// Returns true if a validation completed (success or failure).
+    pub fn process_validation(&mut self) -> bool {
+        if let Some(mut rx) = self.validation_rx.take() {
+            match rx.try_recv() {
+                Ok(Ok(())) => {
+                    // Validation succeeded
+                    if let ConnectState::ValidatingKey { provider, key, model } = &self.connect {
+                        let provider = *provider;
+                        let key = key.clone();
+                        let model = model.clone();
+                        
+                        // For Copilot with model, cache validation and connect with model
+                        if let (Provider::GitHubCopilot, Some(model_name)) = (provider, model) {
+                            self.validated_tokens.insert(provider.storage_key().to_string(), true);
+                            
+                            // Connect with the validated model
+                            self.llm.config.provider = provider;
+                            self.llm.config.api_base = provider.default_api_base().to_string();
+                            self.llm.config.model = model_name.clone();
+                            self.llm.config.api_key = key;
+                            self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
+                            self.llm.apply_config();
+                            self.connect = ConnectState::None;
+                            self.toast_success(format!("Connected to {} with {}", 
+                                provider.display_name(), model_name));
+                        } else {
+                            // Regular connection flow for other providers
+                            self.complete_connection(provider, Some(key));
+                        }
+                    }
+                    return true;
+                }
+                Ok(Err(e)) => {
+                    // Validation failed - clear cache if Copilot
+                    if let ConnectState::ValidatingKey { provider, .. } = &self.connect {
+                        if *provider == Provider::GitHubCopilot {
+                            self.validated_tokens.remove(provider.storage_key());
+                        }
+                    }
+                    self.connection_error(e);
+                    return true;
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                    // Still pending, put the receiver back
+                    self.validation_rx = Some(rx);
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                    // Channel closed unexpectedly
+                    self.connection_error("Validation task failed".to_string());
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Start the OAuth device code flow for a provider.
+    ///
+    /// Requests a device code and transitions to OAuthPending state.
+    /// Once the device code is received, we transition to OAuthPolling.
+    pub fn start_oauth_flow(&mut self, provider: Provider) {
+        use crate::auth::DeviceCodeFlow;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.device_code_rx = Some(rx);
+
+        // Spawn task to request device code only (not polling yet)
+        tokio::spawn(async move {
+            let flow = match provider {
+                Provider::GitHubCopilot => DeviceCodeFlow::github_copilot(),
+                _ => {
+                    let _ = tx.send(Err("Provider does not support OAuth device code".to_string()));
+                    return;
+                }
+            };
+            
+            match flow.request_device_code().await {
+                Ok(device_code) => {
+                    let _ = tx.send(Ok(device_code));
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e.to_string()));
+                }
+            }
+        });
+
+        // Create a placeholder device code for the dialog
+        let (placeholder_uri, placeholder_full_uri) = match provider {
+            Provider::Anthropic => (
+                "https://console.anthropic.com/device".to_string(),
+                None,
+            ),
+            Provider::GitHubCopilot => (
+                "https://github.com/login/device".to_string(),
+                None,
+            ),
+            _ => (
+                "https://example.com".to_string(),
+                None,
+            ),
+        };
+
+        let placeholder_device_code = DeviceCode {
+            device_code: String::new(),
+            user_code: "Loading...".to_string(),
+            verification_uri: placeholder_uri,
+            verification_uri_complete: placeholder_full_uri,
+            expires_in: 900,
+            interval: 5,
+        };
+
+        self.connect = ConnectState::OAuthPending {
+            provider,
+            auth_dialog: AuthDialog::new(provider.display_name(), placeholder_device_code),
+        };
+    }
+
+    /// Process device code request results.
+    ///
+    /// Call this in the event loop to check for device code.
+    /// Returns true if device code was received (success or failure).
+    pub fn process_device_code(&mut self) -> bool {
+        if let Some(mut rx) = self.device_code_rx.take() {
+            match rx.try_recv() {
+                Ok(Ok(device_code)) => {
+                    // Device code received - transition to polling
+                    if let ConnectState::OAuthPending { provider, .. } = &self.connect {
+                        let provider = *provider;
+                        self.start_oauth_polling(provider, device_code);
+                    }
+                    return true;
+                }
+                Ok(Err(e)) => {
+                    // Failed to get device code
+                    self.toast_error(format!("Failed to start authentication: {}", e));
+                    self.connect = ConnectState::None;
+                    return true;
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                    // Still pending, put the receiver back
+                    self.device_code_rx = Some(rx);
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                    // Channel closed unexpectedly
+                    self.toast_error("Device code request failed");
+                    self.connect = ConnectState::None;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Start OAuth polling after device code is received.
+    ///
+    /// Call this to transition from OAuthPending to OAuthPolling with a real device code.
+    pub fn start_oauth_polling(&mut self, provider: Provider, device_code: DeviceCode) {
+        use crate::auth::DeviceCodeFlow;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.oauth_rx = Some(rx);
+
+        let dc = device_code.clone();
+        tokio::spawn(async move {
+            let flow = match provider {
+                Provider::GitHubCopilot => DeviceCodeFlow::github_copilot(),
+                _ => {
+                    let _ = tx.send(Err("Provider does not support OAuth device code".to_string()));
+                    return;
+                }
+            };
+            
+            match flow.poll_for_token(&dc, || {}).await {
+                Ok(token) => {
+                    let _ = tx.send(Ok(token));
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e.to_string()));
+                }
+            }
+        });
+
+        self.connect = ConnectState::OAuthPolling {
+            provider,
+            auth_dialog: AuthDialog::new(provider.display_name(), device_code),
+        };
+    }
+
+    /// Process async OAuth polling results.
+    ///
+    /// Call this in the event loop to check for completed OAuth flows.
+    /// Returns true if OAuth completed (success or failure).
+    pub fn process_oauth(&mut self) -> bool {
+        if let Some(mut rx) = self.oauth_rx.take() {
+            match rx.try_recv() {
+                Ok(Ok(token)) => {
+                    // OAuth succeeded
+                    if let ConnectState::OAuthPolling { provider, .. }
+                    | ConnectState::OAuthPending { provider, .. } = &self.connect
+                    {
+                        let provider = *provider;
+                        self.complete_oauth(provider, token);
+                    }
+                    return true;
+                }
+                Ok(Err(e)) => {
+                    // OAuth failed
+                    self.toast_error(format!("Authentication failed: {}", e));
+                    self.connect = ConnectState::None;
+                    return true;
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                    // Still pending, put the receiver back
+                    self.oauth_rx = Some(rx);
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                    // Channel closed unexpectedly
+                    self.toast_error("OAuth task failed unexpectedly");
+                    self.connect = ConnectState::None;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Complete OAuth authentication.
+    ///
+    /// For GitHub Copilot, transitions to model selection.
+    /// For other providers, saves the token and switches immediately.
+    fn complete_oauth(&mut self, provider: Provider, token: OAuthToken) {
+        // For Copilot, transition to model selection
+        if provider == Provider::GitHubCopilot {
+            self.show_model_selection(provider, token, None);
+            return;
+        }
+
+        // For other providers, complete immediately
+        self.finish_oauth_connection(provider, token, provider.default_model(), None);
+    }
+
+    /// Finish OAuth connection with the selected model.
+    ///
+    /// Saves credentials and switches to the provider.
+    /// For Anthropic CreateApiKey flow, converts OAuth token to API key.
+    fn finish_oauth_connection(
+        &mut self,
+        provider: Provider,
+        token: OAuthToken,
+        model: &str,
+        anthropic_method: Option<crate::auth::AnthropicAuthMethod>,
+    ) {
+        use crate::auth::{AnthropicAuthMethod, AnthropicOAuth, AuthStorage, Credential};
+
+        // Special handling for Anthropic CreateApiKey method
+        if provider == Provider::Anthropic
+            && anthropic_method == Some(AnthropicAuthMethod::CreateApiKey)
+        {
+            // Convert OAuth token to API key
+            let access_token = token.access_token.clone();
+            let model_str = model.to_string();
+
+            let (tx, rx) = tokio::sync::oneshot::channel();
+
+            tokio::spawn(async move {
+                let result = AnthropicOAuth::convert_to_api_key(&access_token).await;
+                let _ = tx.send(result);
+            });
+
+            // Show converting status
+            self.toast_info("Converting OAuth token to API key...".to_string());
+
+            // Store the receiver for processing in the event loop
+            // We'll need to add a new field for this
+            self.api_key_conversion_rx = Some((rx, model_str));
+            self.connect = ConnectState::None;
+            return;
+        }
+
+        // For OAuth (Claude Pro/Max and Copilot), save the OAuth credential
+        if let Ok(mut storage) = AuthStorage::load() {
+            let expires_at = token.expires_at();
+            storage.set(
+                provider.storage_key(),
+                Credential::oauth(
+                    &token.access_token,
+                    token.refresh_token.clone(),
+                    expires_at,
+                    Some(model.to_string()),
+                ),
+            );
+            if let Err(e) = storage.save() {
+                self.toast_warning(format!("Could not save credentials: {}", e));
+            }
+        }
+
+        // Configure and switch to the provider
+        self.llm.config.provider = provider;
+        self.llm.config.api_base = provider.default_api_base().to_string();
+        self.llm.config.model = model.to_string();
+        self.llm.config.api_key = token.access_token;
+        self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
+        self.llm.apply_config();
+        self.connect = ConnectState::None;
+
+        self.toast_success(format!("Connected to {}", provider.display_name()));
+    }
+
+    /// Show the model selection dialog, loading the provider's model list
+    /// unless it was fetched earlier in this session.
+    fn show_model_selection(
+        &mut self,
+        provider: Provider,
+        oauth_token: OAuthToken,
+        anthropic_method: Option<crate::auth::AnthropicAuthMethod>,
+    ) {
+        let config = LlmConfig {
+            provider,
+            api_base: provider.default_api_base().to_string(),
+            model: provider.default_model().to_string(),
+            api_key: oauth_token.access_token.clone(),
+            credential_type: crate::llm::CredentialType::OAuth,
+            ..self.llm.config.clone()
+        };
+        self.open_model_selection(config, Some(oauth_token), anthropic_method);
+    }
+
+    /// Pick another model for the connected provider (Ctrl+M).
+    ///
+    /// Ollama and local servers list what they have installed; hosted
+    /// providers use the same dialog as after signing in.
+    pub fn open_model_switcher(&mut self) {
+        self.menu.close();
+        if self.is_streaming() {
+            self.toast_warning("Wait for the response to finish before switching models".to_string());
+            return;
+        }
+        let provider = self.llm.config.provider;
+        match provider {
+            Provider::Ollama => self.start_ollama_models(),
+            Provider::LocalServer => {
+                let url = self.llm.config.api_base.clone();
+                self.start_server_models(&url);
+            }
+            _ if !self.llm.client.as_ref().is_some_and(|c| c.is_configured()) => {
+                self.toast_warning(format!(
+                    "Connect to {} first (Ctrl+P)",
+                    provider.display_name()
+                ));
+            }
+            _ => self.open_model_selection(self.llm.config.clone(), None, None),
+        }
+    }
+
+    /// Open the model selection dialog for `config`'s provider, fetching its
+    /// models with `config` unless they are cached.
+    fn open_model_selection(
+        &mut self,
+        config: LlmConfig,
+        oauth_token: Option<OAuthToken>,
+        anthropic_method: Option<crate::auth::AnthropicAuthMethod>,
+    ) {
+        let provider = config.provider;
+        let models = self.model_lists.get(provider.storage_key()).cloned();
+        if models.is_none() {
+            self.start_model_list(config);
+        }
+        let selected = models
+            .as_deref()
+            .map_or(0, |models| self.model_position(models));
+        self.connect = ConnectState::SelectingModel {
+            provider,
+            selected,
+            oauth_token,
+            anthropic_method,
+            models,
+        };
+    }
+
+    /// Fetch the model list of `config`'s provider.
+    fn start_model_list(&mut self, config: LlmConfig) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.model_list_rx = Some(rx);
+
+        let provider = config.provider;
+        tokio::spawn(async move {
+            use crate::llm::LlmProvider;
+
+            let result = if provider == Provider::GitHubCopilot {
+                // Not saved until a model is picked, so don't read it from storage
+                let mut copilot =
+                    crate::llm::CopilotProvider::new().with_oauth_token(config.api_key.clone());
+                if let Some(enterprise) = config.scope().and_then(|s| s.organization.clone()) {
+                    copilot = copilot.with_enterprise(enterprise);
+                }
+                copilot.list_models().await
+            } else {
+                LlmClient::new(config).list_models().await
+            };
+            let _ = tx.send(result.map_err(|e| e.to_string()));
+        });
+    }
+
+    /// Fill the model selection dialog once the model list arrives.
+    ///
+    /// If the list can't be fetched, offers the provider's default models instead.
+    pub fn process_model_list(&mut self) {
+        let Some(rx) = &mut self.model_list_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                Err("Model list request failed".to_string())
+            }
+        };
+        self.model_list_rx = None;
+        let ConnectState::SelectingModel { provider, .. } = &self.connect else {
+            return;
+        };
+        let provider = *provider;
+        let models = match result {
+            Ok(models) if !models.is_empty() => {
+                self.model_lists
+                    .insert(provider.storage_key().to_string(), models.clone());
+                models
+            }
+            Ok(_) => provider.fallback_models(),
+            Err(e) => {
+                self.toast_warning(format!(
+                    "Could not load {} models: {}",
+                    provider.display_name(),
+                    e
+                ));
+                provider.fallback_models()
+            }
+        };
+        let position = self.model_position(&models);
+        if let ConnectState::SelectingModel {
+            selected,
+            models: list,
+            ..
+        } = &mut self.connect
+        {
+            *selected = position;
+            *list = Some(models);
+        }
+    }
+
+    /// Position of the current model in `models`, or 0.
+    fn model_position(&self, models: &[ModelInfo]) -> usize {
+        models
+            .iter()
+            .position(|m| m.id == self.llm.config.model)
+            .unwrap_or(0)
+    }
+
+    /// Complete model selection for Copilot.
+    ///
+    /// Called when user selects a model from the model selection dialog.
+    pub fn complete_model_selection(&mut self, model: &str) {
+        if let ConnectState::SelectingModel {
+            provider,
+            oauth_token,
+            anthropic_method,
+            ..
+        } = std::mem::take(&mut self.connect)
+        {
+            match oauth_token {
+                Some(token) => {
+                    self.finish_oauth_connection(provider, token, model, anthropic_method)
+                }
+                None => self.use_model(model),
+            }
+        }
+    }
+
+    /// Switch the connected provider to `model`, remembering it with the
+    /// provider's saved credential.
+    fn use_model(&mut self, model: &str) {
+        use crate::auth::AuthStorage;
+
+        if model != self.llm.config.model {
+            self.switch_model(model);
+        }
+        let key = self.llm.config.provider.storage_key();
+        if let Ok(mut storage) = AuthStorage::load() {
+            if let Some(mut credential) = storage.get(key).cloned() {
+                credential.set_model(model);
+                storage.set(key, credential);
+                if let Err(e) = storage.save() {
+                    self.toast_warning(format!("Could not save the model choice: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Tick the OAuth auth dialog timer.
+    ///
+    /// Call this each second to update the countdown.
+    pub fn tick_oauth_dialog(&mut self) {
+        match &mut self.connect {
+            ConnectState::OAuthPending { auth_dialog, .. }
+            | ConnectState::OAuthPolling { auth_dialog, .. } => {
+                auth_dialog.tick();
+                if auth_dialog.is_expired() {
+                    self.toast_error("Authentication timed out");
+                    self.connect = ConnectState::None;
+                    self.oauth_rx = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────────
+    // Anthropic OAuth methods
+    // ─────────────────────────────────────────────────────────────────────────────
+
+    /// Select Anthropic authentication method.
+    ///
+    /// Called when user selects an option from the Anthropic method selection dialog.
+    pub fn select_anthropic_method(&mut self, selected: usize) {
+        use crate::auth::{AnthropicAuthMethod, AnthropicOAuth};
+
+        match selected {
+            0 => {
+                // Claude Pro/Max OAuth
+                match AnthropicOAuth::new(AnthropicAuthMethod::ClaudeProMax) {
+                    Ok(oauth) => {
+                        // Open browser automatically
+                        if let Err(e) = oauth.open_browser() {
+                            self.toast_error(format!("Could not open browser: {}", e));
+                        }
+
+                        self.connect = ConnectState::EnteringAuthCode {
+                            method: AnthropicAuthMethod::ClaudeProMax,
+                            oauth_handler: oauth,
+                            input: String::new(),
+                            cursor: 0,
+                            error: None,
+                        };
+                    }
+                    Err(e) => {
+                        self.toast_error(format!("OAuth initialization failed: {}", e));
+                        self.connect = ConnectState::None;
+                    }
+                }
+            }
+            1 => {
+                // Create API Key OAuth
+                match AnthropicOAuth::new(AnthropicAuthMethod::CreateApiKey) {
+                    Ok(oauth) => {
+                        // Open browser automatically
+                        if let Err(e) = oauth.open_browser() {
+                            self.toast_error(format!("Could not open browser: {}", e));
+                        }
+
+                        self.connect = ConnectState::EnteringAuthCode {
+                            method: AnthropicAuthMethod::CreateApiKey,
+                            oauth_handler: oauth,
+                            input: String::new(),
+                            cursor: 0,
+                            error: None,
+                        };
+                    }
+                    Err(e) => {
+                        self.toast_error(format!("OAuth initialization failed: {}", e));
+                        self.connect = ConnectState::None;
+                    }
+                }
+            }
+            _ => {
+                // Manual API key entry
+                self.enter_new_credentials();
+            }
+        }
+    }
+
+    /// Submit authorization code for token exchange.
+    pub fn submit_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode {
+            method,
+            oauth_handler,
+            input,
+            ..
+        } = std::mem::replace(&mut self.connect, ConnectState::None)
+        {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            self.auth_code_rx = Some(rx);
+
+            // Start async token exchange
+            tokio::spawn(async move {
+                let result = oauth_handler.exchange_code(&input).await;
+                let _ = tx.send(result);
+            });
+
+            self.connect = ConnectState::ExchangingCode { method };
+        }
+    }
+
+    /// Process API key conversion results.
+    ///
+    /// Call this in the event loop to check for completed API key conversions.
+    pub fn process_api_key_conversion(&mut self) {
+        if let Some((rx, model)) = self.api_key_conversion_rx.as_mut() {
+            if let Ok(result) = rx.try_recv() {
+                let model = model.clone();
+                self.api_key_conversion_rx = None;
+
+                match result {
+                    Ok(api_key) => {
+                        use crate::auth::{AuthStorage, Credential};
+
+                        // Save as API key credential
+                        if let Ok(mut storage) = AuthStorage::load() {
+                            storage.set(
+                                Provider::Anthropic.storage_key(),
+                                Credential::api_key(&api_key),
+                            );
+                            if let Err(e) = storage.save() {
+                                self.toast_warning(format!("Could not save API key: {}", e));
+                            }
+                        }
+
+                        // Configure and switch to Anthropic with API key
+                        self.llm.config.provider = Provider::Anthropic;
+                        self.llm.config.api_base = Provider::Anthropic.default_api_base().to_string();
+                        self.llm.config.model = model;
+                        self.llm.config.api_key = api_key;
+                        self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
+                        self.llm.apply_config();
+
+                        self.toast_success("API key created successfully!".to_string());
+                    }
+                    Err(e) => {
+                        self.toast_error(format!("Failed to create API key: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Process authorization code exchange results.
+    ///
+    /// Call this in the event loop to check for completed code exchanges.
+    pub fn process_auth_code_exchange(&mut self) {
+        if let Some(rx) = self.auth_code_rx.as_mut() {
+            if let Ok(result) = rx.try_recv() {
+                self.auth_code_rx = None;
+
+                // Get the method from ExchangingCode state
+                let method = if let ConnectState::ExchangingCode { method } = self.connect {
+                    Some(method)
+                } else {
+                    None
+                };
+
+                match result {
+                    Ok(token) => {
+                        // Success! Now show model selection
+                        self.show_model_selection(Provider::Anthropic, token, method);
+                    }
+                    Err(e) => {
+                        self.toast_error(format!("Authentication failed: {}", e));
+                        self.connect = ConnectState::None;
+                    }
+                }
+            }
+        }
+    }
+
+    // Auth code input helpers
+    pub fn insert_char_auth_code(&mut self, c: char) {
+        if let ConnectState::EnteringAuthCode {
+            input, cursor, error, ..
+        } = &mut self.connect
+        {
+            input.insert(*cursor, c);
+            *cursor += 1;
+            *error = None;
+        }
+    }
+
+    pub fn backspace_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode {
+            input, cursor, error, ..
+        } = &mut self.connect
+        {
+            if *cursor > 0 {
+                *cursor -= 1;
+                input.remove(*cursor);
+                *error = None;
+            }
+        }
+    }
+
+    pub fn delete_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode {
+            input, cursor, error, ..
+        } = &mut self.connect
+        {
+            if *cursor < input.len() {
+                input.remove(*cursor);
+                *error = None;
+            }
+        }
+    }
+
+    pub fn move_cursor_left_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode { cursor, .. } = &mut self.connect {
+            *cursor = cursor.saturating_sub(1);
+        }
+    }
+
+    pub fn move_cursor_right_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode { input, cursor, .. } = &mut self.connect {
+            if *cursor < input.len() {
+                *cursor += 1;
+            }
+        }
+    }
+
+    pub fn move_cursor_start_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode { cursor, .. } = &mut self.connect {
+            *cursor = 0;
+        }
+    }
+
+    pub fn move_cursor_end_auth_code(&mut self) {
+        if let ConnectState::EnteringAuthCode { input, cursor, .. } = &mut self.connect {
+            *cursor = input.len();
+        }
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PreprocessStep;
+
+    #[test]
+    fn test_mask_api_key_normal() {
+        assert_eq!(mask_api_key("sk-ant-REDACTED"), "sk-a...mnop");
+    }
+
+    #[test]
+    fn test_mask_api_key_short() {
+        assert_eq!(mask_api_key("short"), "*****");
+        assert_eq!(mask_api_key("12345678"), "********");
+    }
+
+    #[test]
+    fn test_mask_api_key_empty() {
+        assert_eq!(mask_api_key(""), "");
+    }
+
+    #[test]
+    fn test_mask_api_key_exactly_nine_chars() {
+        // 9 chars should show first 4 and last 4, which overlaps but works
+        assert_eq!(mask_api_key("123456789"), "1234...6789");
+    }
+
+    #[test]
+    fn test_connect_state_default() {
+        let state = ConnectState::default();
+        assert!(!state.is_active());
+        assert!(state.provider().is_none());
+    }
+
+    #[test]
+    fn test_connect_state_is_active() {
+        let state = ConnectState::SelectingMethod {
+            provider: Provider::Anthropic,
+            selected: 0,
+        };
+        assert!(state.is_active());
+        assert_eq!(state.provider(), Some(Provider::Anthropic));
+    }
+
+    #[test]
+    fn test_connect_state_entering_api_key() {
+        let state = ConnectState::EnteringApiKey {
+            provider: Provider::OpenRouter,
+            input: "sk-or-test".to_string(),
+            cursor: 10,
+            error: None,
+        };
+        assert!(state.is_active());
+        assert_eq!(state.provider(), Some(Provider::OpenRouter));
+    }
+
+    #[test]
+    fn test_connect_state_validating() {
+        let state = ConnectState::ValidatingKey {
+            provider: Provider::Anthropic,
+            key: "sk-ant-test".to_string(),
+            model: None,
+        };
+        assert!(state.is_active());
+    }
+
+    #[test]
+    fn test_connect_state_clone() {
+        let state = ConnectState::ExistingCredential {
+            provider: Provider::Anthropic,
+            masked_key: "sk-a...xyz".to_string(),
+            current_model: None,
+            scope: None,
+            selected: 1,
+        };
+        let cloned = state.clone();
+        assert!(matches!(cloned, ConnectState::ExistingCredential { .. }));
+    }
+
+    #[test]
+    fn test_start_connection_shows_dialog() {
+        let mut app = App::new_without_banner();
+        app.start_connection(Provider::Anthropic);
+
+        // Should go to either SelectingAnthropicMethod or ExistingCredential
+        // depending on whether credentials already exist
+        assert!(
+            matches!(app.connect, ConnectState::SelectingAnthropicMethod { .. })
+                || matches!(
+                    app.connect,
+                    ConnectState::ExistingCredential {
+                        provider: Provider::Anthropic,
+                        ..
+                    }
+                )
+        );
+    }
+
+    #[test]
+    fn test_pull_missing_ollama_model() {
+        let installed = InstalledModel {
+            name: "gemma3:4b".to_string(),
+            size: 3 << 30,
+            modified_at: None,
+        };
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Ollama;
+        app.llm.config.model = "llama3.2".to_string();
+
+        // The configured model is missing: offer to pull it or pick another
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.ollama_models_rx = Some(rx);
+        app.connect = ConnectState::FetchingOllamaModels {
+            api_base: "http://localhost:11434".to_string(),
+        };
+        tx.send(Ok(vec![installed.clone()])).unwrap();
+        app.process_ollama_models();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::OfferingOllamaPull { model, installed, .. }
+                if model == "llama3.2" && installed.len() == 1
+        ));
+        app.choose_installed_ollama_model();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::SelectingOllamaModel { models, .. } if models[0] == installed
+        ));
+
+        // Progress updates the dialog; success connects with the pulled model
+        let (tx, rx) = mpsc::channel(8);
+        app.ollama_pull_rx = Some(rx);
+        app.connect = ConnectState::PullingOllamaModel {
+            api_base: "http://localhost:11434".to_string(),
+            model: "llama3.2".to_string(),
+            progress: PullStatus::default(),
+        };
+        let downloading = PullStatus {
+            status: "pulling 6a0746a1ec1a".to_string(),
+            total: Some(100),
+            completed: Some(40),
+        };
+        tx.try_send(Ok(downloading.clone())).unwrap();
+        app.process_ollama_pull();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::PullingOllamaModel { progress, .. } if *progress == downloading
+        ));
+
+        app.llm.config.model = "other".to_string();
+        tx.try_send(Ok(PullStatus {
+            status: "success".to_string(),
+            ..PullStatus::default()
+        }))
+        .unwrap();
+        app.process_ollama_pull();
+        assert!(app.ollama_pull_rx.is_none());
+        assert!(matches!(app.connect, ConnectState::None));
+        assert_eq!(app.llm.config.model, "llama3.2");
+
+        // A failed pull closes the dialog
+        let (tx, rx) = mpsc::channel(8);
+        app.ollama_pull_rx = Some(rx);
+        app.connect = ConnectState::PullingOllamaModel {
+            api_base: "http://localhost:11434".to_string(),
+            model: "nope".to_string(),
+            progress: PullStatus::default(),
+        };
+        tx.try_send(Err("file does not exist".to_string())).unwrap();
+        app.process_ollama_pull();
+        assert!(app.ollama_pull_rx.is_none());
+        assert!(matches!(app.connect, ConnectState::None));
+        assert_eq!(app.llm.config.model, "llama3.2");
+    }
+
+    #[tokio::test]
+    async fn test_start_connection_ollama() {
+        let mut app = App::new_without_banner();
+        app.start_connection(Provider::Ollama);
+
+        // Ollama doesn't need credentials, but lists the installed models first
+        assert!(matches!(
+            &app.connect,
+            ConnectState::FetchingOllamaModels { api_base } if api_base == "http://localhost:11434"
+        ));
+        assert!(app.ollama_models_rx.is_some());
+        app.cancel_connection();
+        assert!(app.ollama_models_rx.is_none());
+    }
+
+    #[test]
+    fn test_select_installed_ollama_model() {
+        let installed = |name: &str| InstalledModel {
+            name: name.to_string(),
+            size: 2 << 30,
+            modified_at: None,
+        };
+        let mut app = App::new_without_banner();
+        let fetching = ConnectState::FetchingOllamaModels {
+            api_base: "http://localhost:11434".to_string(),
+        };
+
+        // Nothing installed: offer to pull the default model
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.ollama_models_rx = Some(rx);
+        app.connect = fetching.clone();
+        tx.send(Ok(Vec::new())).unwrap();
+        app.process_ollama_models();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::OfferingOllamaPull { model, .. }
+                if model == Provider::Ollama.default_model()
+        ));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.ollama_models_rx = Some(rx);
+        app.connect = fetching;
+        tx.send(Ok(vec![installed("gemma3:4b"), installed("llama3.2:latest")]))
+            .unwrap();
+        app.process_ollama_models();
+        let ConnectState::SelectingOllamaModel { selected, .. } = &mut app.connect else {
+            panic!("expected the installed models, got {:?}", app.connect);
+        };
+        assert_eq!(*selected, 0);
+        *selected = 1;
+
+        app.llm.config.api_key = "sk-not-for-ollama".to_string();
+        app.select_ollama_model();
+        assert!(matches!(app.connect, ConnectState::None));
+        assert_eq!(app.llm.config.provider, Provider::Ollama);
+        assert_eq!(app.llm.config.model, "llama3.2:latest");
+        assert!(app.llm.config.api_key.is_empty());
+    }
+
+    #[test]
+    fn test_model_list_loads_into_selection() {
+        let mut app = App::new_without_banner();
+        let token = OAuthToken {
+            access_token: "gho_test_token".to_string(),
+            token_type: "bearer".to_string(),
+            scope: None,
+            refresh_token: None,
+            expires_in: None,
+        };
+        app.llm.config.model = "gpt-4.1".to_string();
+        app.connect = ConnectState::SelectingModel {
+            provider: Provider::GitHubCopilot,
+            selected: 0,
+            oauth_token: Some(token.clone()),
+            anthropic_method: None,
+            models: None,
+        };
+
+        // A failed fetch offers the provider's defaults and isn't cached
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.model_list_rx = Some(rx);
+        tx.send(Err("Copilot API error (500)".to_string())).unwrap();
+        app.process_model_list();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::SelectingModel { models: Some(models), .. }
+                if *models == Provider::GitHubCopilot.fallback_models()
+        ));
+        assert!(app.model_lists.is_empty());
+
+        // A fetched list selects the current model and is cached
+        let models = vec![
+            ModelInfo::from_id("claude-sonnet-4.5"),
+            ModelInfo::from_id("gpt-4.1"),
+        ];
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.model_list_rx = Some(rx);
+        tx.send(Ok(models.clone())).unwrap();
+        app.process_model_list();
+        assert!(app.model_list_rx.is_none());
+        assert!(matches!(
+            &app.connect,
+            ConnectState::SelectingModel { selected: 1, models: Some(list), .. } if *list == models
+        ));
+
+        // Reopening the dialog uses the cached list without fetching again
+        app.cancel_connection();
+        app.show_model_selection(Provider::GitHubCopilot, token, None);
+        assert!(app.model_list_rx.is_none());
+        assert!(matches!(
+            &app.connect,
+            ConnectState::SelectingModel { selected: 1, models: Some(list), .. } if *list == models
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_model_switcher_for_connected_provider() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::OpenAI;
+        app.llm.config.model = "gpt-4.1".to_string();
+        app.llm.config.api_key = String::new();
+        app.llm.apply_config();
+        app.open_model_switcher();
+        assert!(!app.connect.is_active());
+
+        app.llm.config.api_key = "sk-test".to_string();
+        app.llm.apply_config();
+        let models = vec![ModelInfo::from_id("gpt-4.1"), ModelInfo::from_id("o3")];
+        app.model_lists
+            .insert(Provider::OpenAI.storage_key().to_string(), models);
+        app.open_model_switcher();
+        assert!(app.model_list_rx.is_none());
+        assert!(matches!(
+            &app.connect,
+            ConnectState::SelectingModel { selected: 0, oauth_token: None, .. }
+        ));
+
+        app.complete_model_selection("o3");
+        assert!(matches!(app.connect, ConnectState::None));
+        assert_eq!(app.llm.config.provider, Provider::OpenAI);
+        assert_eq!(app.llm.config.model, "o3");
+        assert_eq!(app.llm.config.api_key, "sk-test");
+    }
+
+    #[test]
+    fn test_connect_local_server() {
+        let mut app = App::new_without_banner();
+        app.start_connection(Provider::LocalServer);
+        assert!(matches!(
+            &app.connect,
+            ConnectState::EnteringServerUrl { input, .. } if input == "http://localhost:1234/v1"
+        ));
+
+        // An unreachable server returns to the URL with the error
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.server_models_rx = Some(rx);
+        app.connect = ConnectState::FetchingServerModels {
+            api_base: "http://localhost:8000/v1".to_string(),
+        };
+        tx.send(Err("Could not reach".to_string())).unwrap();
+        app.process_server_models();
+        assert!(matches!(
+            &app.connect,
+            ConnectState::EnteringServerUrl { input, error: Some(_), .. }
+                if input == "http://localhost:8000/v1"
+        ));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.server_models_rx = Some(rx);
+        app.connect = ConnectState::FetchingServerModels {
+            api_base: "http://localhost:8000/v1".to_string(),
+        };
+        tx.send(Ok(vec!["llama-3.2-3b".to_string(), "qwen2.5-7b".to_string()]))
+            .unwrap();
+        app.process_server_models();
+        assert!(app.server_models_rx.is_none());
+        let ConnectState::SelectingServerModel { selected, .. } = &mut app.connect else {
+            panic!("expected the model list, got {:?}", app.connect);
+        };
+        *selected = 1;
+
+        app.llm.config.api_key = "sk-ant-not-for-local".to_string();
+        app.select_server_model();
+        assert!(matches!(app.connect, ConnectState::None));
+        assert_eq!(app.llm.config.provider, Provider::LocalServer);
+        assert_eq!(app.llm.config.api_base, "http://localhost:8000/v1");
+        assert_eq!(app.llm.config.model, "qwen2.5-7b");
+        assert!(app.llm.config.api_key.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_command_opens_panel() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Ollama;
+        let message_count = app.chat.messages.len();
+        app.chat.input = "/local".to_string();
+        app.submit_message();
+
+        assert!(app.local_models.as_ref().is_some_and(|p| p.loading));
+        assert!(app.local_models_rx.is_some());
+        assert!(app.chat.input.is_empty());
+        assert_eq!(app.chat.messages.len(), message_count);
+
+        app.close_local_models();
+        assert!(app.local_models.is_none());
+    }
+
+    #[test]
+    fn test_local_command_gated_for_hosted_providers() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.chat.input = "/lo".to_string();
+        assert!(app.input_hint().unwrap().contains("only available with Ollama"));
+
+        app.chat.input = "/local".to_string();
+        app.submit_message();
+        assert!(app.local_models.is_none());
+        assert!(!app.toasts.is_empty());
+
+        app.chat.input = "hello /local".to_string();
+        assert!(app.input_hint().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_model_command_switches_model() {
+        let mut app = App::new_without_banner();
+        let before = app.chat.messages.len();
+        app.chat.input = "/model".to_string();
+        app.submit_message();
+        assert!(!app.toasts.is_empty());
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.chat.input = "/model  custom-model ".to_string();
+        app.submit_message();
+        assert_eq!(app.llm.config.model, "custom-model");
+        assert!(app.chat.input.is_empty());
+        assert!(app.chat.messages.last().unwrap().is_system_note());
+    }
+
+    #[tokio::test]
+    async fn test_auto_model_routes_each_prompt() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.llm.config.model = "auto".to_string();
+        app.chat.input = "What time zone is Lisbon in?".to_string();
+        app.submit_message();
+        let route = app.chat.messages.last().unwrap().route.clone().unwrap();
+        assert_eq!(route.model, Provider::Anthropic.fast_model());
+
+        app.llm.stream_rx = None;
+        app.submit_guard.last_submit = None;
+        app.chat.input = "Why does this fail?\n```\nlet x: u8 = 256;\n```".to_string();
+        app.submit_message();
+        let route = app.chat.messages.last().unwrap().route.clone().unwrap();
+        assert_eq!(route.model, Provider::Anthropic.default_model());
+        assert_eq!(route.reason, "code");
+    }
+
+    #[tokio::test]
+    async fn test_think_command_sets_reasoning() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.chat.input = "/think high".to_string();
+        app.submit_message();
+        assert_eq!(app.llm.config.reasoning, Some(ReasoningEffort::High));
+
+        app.chat.input = "/think max".to_string();
+        app.submit_message();
+        assert_eq!(app.llm.config.reasoning, Some(ReasoningEffort::High));
+
+        app.chat.input = "Hello".to_string();
+        app.submit_message();
+        let reply = app.chat.messages.last().unwrap();
+        assert_eq!(reply.reasoning, Some(ReasoningEffort::High));
+
+        app.llm.stream_rx = None;
+        app.chat.input = "/think off".to_string();
+        app.submit_message();
+        assert_eq!(app.llm.config.reasoning, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_command_regenerates_the_last_reply() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.chat.messages.push(Message::user("Name a color".to_string()));
+        app.chat.messages.push(Message::assistant("Blue".to_string()));
+        app.chat.messages.push(Message::system_note("A note".to_string()));
+        let count = app.chat.messages.len();
+
+        app.run_command(SlashCommand::Retry, "claude-haiku-4-5");
+        assert!(app.llm.stream_rx.is_some());
+        assert_eq!(app.chat.messages.len(), count - 1);
+        let reply = app.chat.messages.last().unwrap();
+        assert!(reply.content.is_empty());
+        assert_eq!(reply.route, Some(Route::chosen("claude-haiku-4-5")));
+        assert_eq!(app.llm.chosen_model, None);
+
+        app.llm.stream_rx = None;
+        app.llm.status = ConnectionStatus::Ready;
+        app.undo();
+        assert_eq!(app.chat.messages.last().unwrap().content, "Blue");
+
+        // Without a model the reply comes from the configured one
+        app.run_command(SlashCommand::Retry, "");
+        assert_eq!(app.chat.messages.last().unwrap().route, None);
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_resend_truncates_and_undo_restores() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.chat.messages.push(Message::user("Name a color".to_string()));
+        app.chat.messages.push(Message::assistant("Blue".to_string()));
+        app.chat.messages.push(Message::user("And a fruit".to_string()));
+        app.chat.messages.push(Message::assistant("Apple".to_string()));
+        let before: Vec<String> = app.chat.messages.iter().map(|m| m.content.clone()).collect();
+        let first = before.len() - 4;
+
+        // The prompt at or above the top of the view is picked
+        app.scroll.offset = first + 1;
+        app.edit_prompt();
+        assert_eq!(app.chat.editing, Some(first));
+        assert_eq!(app.chat.input, "Name a color");
+
+        app.chat.input = "Name a shape".to_string();
+        app.send_input();
+        assert!(app.llm.stream_rx.is_some());
+        assert_eq!(app.chat.editing, None);
+        assert_eq!(app.chat.messages.len(), first + 2);
+        assert_eq!(app.chat.messages[first].content, "Name a shape");
+
+        app.llm.stream_rx = None;
+        app.llm.status = ConnectionStatus::Ready;
+        app.undo();
+        let after: Vec<String> = app.chat.messages.iter().map(|m| m.content.clone()).collect();
+        assert_eq!(after, before);
+
+        // Esc leaves the conversation alone
+        app.edit_prompt();
+        app.cancel_edit();
+        assert_eq!(app.chat.editing, None);
+        assert!(app.chat.input.is_empty());
+        assert_eq!(app.chat.messages.len(), before.len());
+    }
+
+    #[tokio::test]
+    async fn test_guest_prompts_leave_an_edit_alone() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.chat.messages.push(Message::user("Name a color".to_string()));
+        app.chat.messages.push(Message::assistant("Blue".to_string()));
+        let first = app.chat.messages.len() - 2;
+        app.scroll.offset = first;
+        app.edit_prompt();
+        app.chat.input = "Name a shape".to_string();
+
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::CoWrite, "ada".to_string()).unwrap();
+        host.queued.push_back(("bob".to_string(), "Check the lockfile".to_string()));
+        app.share = Some(host);
+        app.process_share();
+
+        // The guest's prompt is added after the conversation, which is otherwise untouched
+        assert_eq!(app.chat.messages[first].content, "Name a color");
+        assert_eq!(app.chat.messages[first + 1].content, "Blue");
+        assert_eq!(app.chat.messages[first + 2].content, "Check the lockfile");
+        assert_eq!(app.chat.editing, Some(first));
+        assert_eq!(app.chat.input, "Name a shape");
+
+        app.send_input();
+        assert_eq!(app.chat.editing, None);
+        assert_eq!(app.chat.messages[first].content, "Name a shape");
+        assert_eq!(app.chat.messages.len(), first + 2);
+    }
+
+    #[test]
+    fn test_unknown_and_help_commands() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let before = app.chat.messages.len();
+
+        // Unknown commands are not sent and stay in the input for fixing
+        app.chat.input = "/modle x".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), before);
+        assert_eq!(app.chat.input, "/modle x");
+        assert!(!app.toasts.is_empty());
+
+        app.chat.input = "/help".to_string();
+        app.submit_message();
+        let help = app.chat.messages.last().unwrap();
+        assert!(help.is_system_note() && help.content.contains("/provider"));
+
+        // Paths are ordinary messages
+        app.chat.input = "/usr/bin is missing".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages[before + 1].content, "/usr/bin is missing");
+    }
+
+    #[test]
+    fn test_clear_command_saves_and_starts_over() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+        app.chat.input = "keep me".to_string();
+        app.submit_message();
+
+        app.chat.input = "/clear".to_string();
+        app.submit_message();
+        assert!(app.chat.messages.is_empty());
+        assert!(app.session.is_none());
+        let saved = app.sessions.as_ref().unwrap().list().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].title, "keep me");
+    }
+
+    #[test]
+    fn test_tab_completes_command_name() {
+        let mut app = App::new_without_banner();
+        app.chat.input = "/pro".to_string();
+        app.chat.cursor_position = 4;
+        assert_eq!(app.chat.command_completions()[0].name, "/provider");
+        assert!(app.chat.complete_command());
+        assert_eq!(app.chat.input, "/provider ");
+        assert_eq!(app.chat.cursor_position, app.chat.input.len());
+        assert!(app.chat.command_completions().is_empty());
+    }
+
+    #[test]
+    fn test_tool_calls_respect_permissions() {
+        use crate::audit::AuditLog;
+        use crate::config::ToolsConfig;
+        use crate::llm::ToolDefinition;
+        use crate::tools::{PermissionPolicy, Tool};
+
+        struct Touch;
+        impl Tool for Touch {
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: "touch".to_string(),
+                    description: "Create a file".to_string(),
+                    parameters: serde_json::json!({"type": "object"}),
+                }
+            }
+            fn describe(&self, _: &serde_json::Value) -> String {
+                "touch notes.txt".to_string()
+            }
+            fn run(&self, _: &serde_json::Value) -> anyhow::Result<String> {
+                Ok("created".to_string())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ToolsConfig::default();
+        let mut app = App::new_without_banner();
+        app.tools = ToolDispatcher::new(&config)
+            .with_permissions(PermissionPolicy::new(config, dir.path()))
+            .with_audit_log(AuditLog::at(dir.path().join("audit.log")))
+            .with_tool(Touch);
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "touch".to_string(),
+            arguments: serde_json::json!({}),
+        };
+
+        // `ask` is the default: nothing runs until the user answers
+        assert!(!app.request_tool(call.clone()));
+        assert_eq!(app.tool_confirmation.as_ref().unwrap().summary, "touch notes.txt");
+        app.confirm_tool(true, true);
+        assert!(app.tool_confirmation.is_none());
+        let result = app.chat.messages.last().unwrap();
+        assert!(result.content.starts_with("Ran touch"));
+        assert_eq!(result.sent_text(), "created");
+
+        // The remembered answer applies to the next call
+        assert!(app.request_tool(call));
+        assert!(app.tool_confirmation.is_none());
+        assert_eq!(AuditLog::at(dir.path().join("audit.log")).read().unwrap().len(), 2);
+
+        app.request_tool(ToolCall {
+            id: "call_2".to_string(),
+            name: "rm".to_string(),
+            arguments: serde_json::json!({}),
+        });
+        let result = app.chat.messages.last().unwrap();
+        assert!(result.content.starts_with("Did not run rm"));
+        assert!(result.sent_text().starts_with("Not run"));
+    }
+
+    #[test]
+    fn test_denied_tools_are_not_offered_to_the_model() {
+        use crate::config::{Permission, ToolsConfig};
+        use crate::tools::{PermissionPolicy, Tool};
+
+        struct Named(&'static str);
+        impl Tool for Named {
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.0.to_string(),
+                    description: format!("The {} tool", self.0),
+                    parameters: serde_json::json!({"type": "object"}),
+                }
+            }
+            fn describe(&self, _: &serde_json::Value) -> String {
+                self.0.to_string()
+            }
+            fn run(&self, _: &serde_json::Value) -> anyhow::Result<String> {
+                Ok(String::new())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ToolsConfig {
+            permissions: [("delete_file".to_string(), Permission::Deny)].into(),
+            ..ToolsConfig::default()
+        };
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.tools = ToolDispatcher::new(&config)
+            .with_permissions(PermissionPolicy::new(config, dir.path()))
+            .with_tool(Named("read_file"))
+            .with_tool(Named("delete_file"));
+        let model = app.llm.config.model.clone();
+        let offered: Vec<String> = app.offered_tools(&model).into_iter().map(|t| t.name).collect();
+        assert_eq!(offered, ["read_file"]);
+
+        // The request itself leaves the denied tool out
+        app.llm.dry_run = true;
+        app.chat.input = "Tidy up".to_string();
+        app.submit_message();
+        let preview = &app.chat.messages.last().unwrap().content;
+        assert!(preview.contains("\"read_file\""), "{}", preview);
+        assert!(!preview.contains("delete_file"), "{}", preview);
+    }
+
+    #[tokio::test]
+    async fn test_tool_results_go_back_to_the_model() {
+        use crate::audit::AuditLog;
+        use crate::config::{Permission, ToolsConfig};
+        use crate::tools::{PermissionPolicy, Tool};
+
+        struct Notes;
+        impl Tool for Notes {
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: "read_notes".to_string(),
+                    description: "Read the user's notes".to_string(),
+                    parameters: serde_json::json!({"type": "object"}),
+                }
+            }
+            fn describe(&self, _: &serde_json::Value) -> String {
+                "read the notes".to_string()
+            }
+            fn run(&self, _: &serde_json::Value) -> anyhow::Result<String> {
+                Ok("buy milk".to_string())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ToolsConfig {
+            default: Permission::Allow,
+            ..ToolsConfig::default()
+        };
+        let mut app = offline_ollama_app();
+        app.tools = ToolDispatcher::new(&config)
+            .with_permissions(PermissionPolicy::new(config, dir.path()))
+            .with_audit_log(AuditLog::at(dir.path().join("audit.log")))
+            .with_tool(Notes);
+        app.chat.messages.push(Message::user("What do my notes say?".to_string()));
+        app.chat.messages.push(Message::assistant(String::new()));
+
+        // The model calls the tool instead of answering
+        let (tx, rx) = mpsc::channel(8);
+        app.llm.stream_rx = Some(rx);
+        app.llm.status = ConnectionStatus::Streaming;
+        tx.try_send(StreamEvent::ToolCall(ToolCall {
+            id: "call_1".to_string(),
+            name: "read_notes".to_string(),
+            arguments: serde_json::json!({}),
+        }))
+        .unwrap();
+        tx.try_send(StreamEvent::Finish(FinishReason::ToolCalls)).unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        while app.process_stream() {}
+
+        // The tool ran and a follow-up request went out with its output
+        let n = app.chat.messages.len();
+        assert!(app.chat.messages[n - 2].is_tool_result());
+        assert!(app.chat.messages[n - 2].content.starts_with("Ran read_notes"));
+        assert!(app.is_streaming());
+        let sent = app.api_messages();
+        let sent = &sent[sent.len() - 4..];
+        let roles: Vec<&str> = sent.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "tool", "assistant"]);
+        assert_eq!(sent[1].tool_calls[0].id, "call_1");
+        assert_eq!(sent[2].content, "buy milk");
+        assert_eq!(sent[2].tool_call_id.as_deref(), Some("call_1"));
+
+        // The model answers with the output in hand
+        let (tx, rx) = mpsc::channel(8);
+        app.llm.stream_rx = Some(rx);
+        tx.try_send(StreamEvent::Token("Your notes say: buy milk.".to_string()))
+            .unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        while app.process_stream() {}
+        assert_eq!(app.chat.messages.len(), n);
+        assert_eq!(app.chat.messages[n - 1].content, "Your notes say: buy milk.");
+        assert!(!app.is_streaming());
+    }
+
+    #[test]
+    fn test_undo_redo_sent_message() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let before = app.chat.messages.len();
+
+        app.chat.input = "hello".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), before + 2);
+
+        app.chat.input = "/undo".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), before);
+        assert!(app.chat.input.is_empty());
+
+        app.chat.input = "/redo".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), before + 2);
+        assert_eq!(app.chat.messages[before].content, "hello");
+    }
+
+    #[test]
+    fn test_sent_message_goes_through_preprocess() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.submit_guard.preprocess.steps.push(PreprocessStep::RedactSecrets);
+        app.chat.input = "  key: sk-proj-0123456789abcdefghij  ".to_string();
+        let before = app.chat.messages.len();
+        app.submit_message();
+        assert_eq!(app.chat.messages[before].content, "key: [REDACTED]");
+    }
+
+    #[test]
+    fn test_session_saved_and_resumed() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+
+        // Nothing to save before the first prompt
+        app.save_session().unwrap();
+        assert!(app.session.is_none());
+
+        app.chat.input = "first topic".to_string();
+        app.submit_message();
+        app.save_session().unwrap();
+        let first = app.session.clone().unwrap();
+        assert_eq!(first.title, "first topic");
+
+        // Start over and resume: the previous conversation comes back
+        app.chat = ChatState::new(Vec::new());
+        app.session = None;
+        app.submit_guard.last_submit = None;
+        app.chat.input = "second topic".to_string();
+        app.submit_message();
+        app.open_session_picker();
+        let picker = app.session_picker.as_ref().unwrap();
+        assert_eq!(picker.sessions.len(), 1);
+        assert_eq!(picker.sessions[0].provider, app.llm.config.provider.storage_key());
+        app.open_session(&first.id);
+        assert!(app.session_picker.is_none());
+        let prompt = |app: &App| {
+            let first = app.chat.messages.iter().find(|m| m.role == Role::User);
+            first.unwrap().content.clone()
+        };
+        assert_eq!(app.session.as_ref().unwrap().id, first.id);
+        assert_eq!(prompt(&app), "first topic");
+        assert!(app.chat.messages.last().unwrap().is_system_note());
+
+        // The browser now offers the other conversation, which was saved first
+        app.open_session_picker();
+        let other = app.session_picker.as_ref().unwrap().sessions[0].id.clone();
+        app.open_session(&other);
+        assert_eq!(prompt(&app), "second topic");
+        let store = app.sessions.as_ref().unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_global_search_opens_the_session_at_the_match() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let store = Arc::new(crate::session::SqliteStore::in_memory().unwrap());
+        let mut old = Session::new("ollama", "llama3");
+        let mut messages: Vec<Message> = (0..40)
+            .map(|i| Message::user(format!("filler {}", i)))
+            .collect();
+        messages[5] = Message::assistant("Raise shared_buffers first.".to_string());
+        old.set_messages(&messages);
+        old.derive_title();
+        store.save(&old).unwrap();
+        app.sessions = Some(store);
+
+        app.chat.input = "current topic".to_string();
+        app.submit_message();
+        app.open_global_search();
+        let search = app.global_search.as_mut().unwrap();
+        search.query = "Shared_Buffers".to_string();
+        app.run_global_search();
+        let hits = &app.global_search.as_ref().unwrap().hits;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message, Some(5));
+
+        app.open_search_hit(0);
+        assert!(app.global_search.is_none());
+        assert_eq!(app.session.as_ref().unwrap().id, old.id);
+        assert_eq!(app.scroll.offset, 5);
+        let bar = app.search.as_ref().unwrap();
+        assert_eq!(bar.query, "shared_buffers");
+        assert_eq!(bar.current_match().map(|m| m.message), Some(5));
+    }
+
+    #[test]
+    fn test_unread_messages_are_marked_on_resume() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+
+        // Only the greeting and prompt were in view when the echo reply arrived
+        app.chat.input = "hello".to_string();
+        app.submit_message();
+        app.chat.mark_read(2);
+        app.save_session().unwrap();
+        let id = app.session.as_ref().unwrap().id.clone();
+        app.clear_conversation();
+
+        app.jump_to_unread();
+        assert_eq!(app.toasts.toasts.back().unwrap().message, "No unread messages");
+
+        app.open_session_picker();
+        assert_eq!(app.session_picker.as_ref().unwrap().sessions[0].unread, 1);
+        app.open_session(&id);
+        assert_eq!(app.chat.first_unread, Some(2));
+        assert!(app.chat.messages.last().unwrap().content.contains("1 unread"));
+        app.jump_to_unread();
+        assert_eq!(app.scroll.offset, 2);
+
+        // Seeing the reply clears the count on the next save
+        app.chat.mark_read(3);
+        app.save_session().unwrap();
+        assert_eq!(app.session.as_ref().unwrap().unread(), 0);
+    }
+
+    #[test]
+    fn test_batch_sends_prompts_and_resends_rate_limited() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("eval.txt");
+        std::fs::write(&path, "first\n# skipped\nsecond\n").unwrap();
+
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.llm.batch_limits = BatchLimits {
+            interval: Duration::ZERO,
+            retries: 1,
+        };
+        app.run_command(SlashCommand::Batch, path.to_str().unwrap());
+        assert_eq!(app.batch.as_ref().unwrap().total, 2);
+        app.chat.input = "draft".to_string();
+
+        // The first prompt is answered, keeping the draft
+        app.process_batch();
+        assert_eq!(app.chat.input, "draft");
+        assert!(app.chat.messages.iter().any(|m| m.content == "first"));
+        app.process_batch();
+        assert_eq!(app.batch.as_ref().unwrap().answered, 1);
+
+        // The second is rate limited once, resent, then fails for good
+        app.process_batch();
+        let rate_limited = || {
+            Message::error(
+                ProviderError::RateLimited {
+                    message: "429 Too Many Requests".to_string(),
+                    retry_after: Some(Duration::ZERO),
+                },
+                "Test",
+            )
+        };
+        *app.chat.messages.last_mut().unwrap() = rate_limited();
+        app.process_batch();
+        assert!(app.batch.as_ref().unwrap().resend.is_some());
+        app.process_batch();
+        assert!(!app.chat.messages.last().unwrap().is_error());
+        app.chat.messages.push(rate_limited());
+        app.process_batch();
+        assert_eq!(app.batch.as_ref().unwrap().failures[0].line, 3);
+
+        app.process_batch();
+        assert!(app.batch.is_none());
+        let summary = &app.chat.messages.last().unwrap().content;
+        assert!(summary.contains("1 of 2 answered, 1 failed"), "{}", summary);
+        assert!(summary.contains("line 3: Rate limited"), "{}", summary);
+    }
+
+    #[test]
+    fn test_indexer_titles_saved_sessions() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let store: Arc<dyn SessionStore> =
+            Arc::new(crate::session::SqliteStore::in_memory().unwrap());
+        app.sessions = Some(store.clone());
+        app.indexer = Some(Indexer::spawn(store).unwrap());
+        app.chat.input = "index me later".to_string();
+        app.submit_message();
+        app.save_session().unwrap();
+        assert!(app.session.as_ref().unwrap().title.is_empty());
+
+        let started = Instant::now();
+        while app.indexer.as_ref().unwrap().is_busy() {
+            assert!(started.elapsed() < Duration::from_secs(10));
+            app.process_indexer();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(app.session.as_ref().unwrap().title, "index me later");
+        assert_eq!(app.activity(), Activity::Idle);
+        let hits = app.sessions.as_ref().unwrap().search("later").unwrap();
+        assert_eq!(hits[0].title, "index me later");
+    }
+
+    #[test]
+    fn test_playground_is_never_saved_unless_kept() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+        app.chat.input = "real question".to_string();
+        app.submit_message();
+
+        app.run_command(SlashCommand::Playground, "");
+        let playground = app.playground.as_ref().unwrap();
+        let origin = playground.origin_session.clone().unwrap();
+        assert!(app.session.is_none());
+        app.submit_guard.last_submit = None;
+        app.chat.input = "risky experiment".to_string();
+        app.submit_message();
+        app.save_session().unwrap();
+        assert!(app.session.is_none());
+        let store = app.sessions.clone().unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        // Leaving discards the experiment and brings the original back
+        app.toggle_playground();
+        assert!(app.playground.is_none());
+        assert_eq!(app.session.as_ref().unwrap().id, origin.id);
+        assert!(!app.chat.messages.iter().any(|m| m.content == "risky experiment"));
+
+        // Kept playgrounds become a session of their own
+        app.toggle_playground();
+        app.submit_guard.last_submit = None;
+        app.chat.input = "useful experiment".to_string();
+        app.submit_message();
+        app.keep_playground();
+        assert!(app.playground.is_none());
+        let kept = app.session.clone().unwrap();
+        assert_ne!(kept.id, origin.id);
+        assert_eq!(store.list().unwrap().len(), 2);
+        assert!(store.search("useful experiment").unwrap()[0].id == kept.id);
+    }
+
+    #[test]
+    fn test_sync_reloads_a_pulled_conversation() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.run_command(SlashCommand::Sync, "");
+        assert!(app.sync_rx.is_none(), "sync is off by default");
+
+        let store: Arc<dyn SessionStore> = Arc::new(crate::session::SqliteStore::in_memory().unwrap());
+        app.sessions = Some(store.clone());
+        app.chat.input = "laptop question".to_string();
+        app.submit_message();
+        app.save_session().unwrap();
+
+        // Another machine answered it; the sync pulled that version in
+        let mut pulled = app.session.clone().unwrap();
+        pulled.messages.push(crate::session::SessionMessage {
+            role: Role::User,
+            content: "desktop follow-up".to_string(),
+            interrupted: false,
+            pinned: false,
+        });
+        store.save(&pulled).unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.sync_rx = Some(rx);
+        tx.send(Ok(SyncReport {
+            pulled: 1,
+            updated: vec![pulled.id.clone()],
+            ..SyncReport::default()
+        }))
+        .unwrap();
+        app.process_sync();
+        assert!(app.sync_rx.is_none());
+        assert!(app.chat.messages.iter().any(|m| m.content == "desktop follow-up"));
+    }
+
+    #[tokio::test]
+    async fn test_shared_session_attributes_prompts() {
+        let mut host = App::new_without_banner();
+        host.llm.client = None;
+        host.share_config.bind = "127.0.0.1:0".to_string();
+        host.share_config.name = Some("ada".to_string());
+        host.run_command(SlashCommand::Share, "write");
+        let (addr, token) = {
+            let share = host.share.as_ref().unwrap();
+            (share.addr.to_string(), share.token.clone())
+        };
+
+        let mut guest = App::new_without_banner();
+        guest.guest = Some(ShareGuest::connect(&addr, &token, "bob").await.unwrap());
+        guest.chat.input = "what changed?".to_string();
+        guest.submit_message();
+        assert!(guest.chat.input.is_empty());
+
+        host.chat.input = "draft in progress".to_string();
+        let started = Instant::now();
+        while !guest.chat.messages.iter().any(|m| m.content == "what changed?") {
+            assert!(started.elapsed() < Duration::from_secs(5), "prompt never arrived");
+            host.process_share();
+            guest.process_share();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let prompt = host.chat.messages.iter().find(|m| m.content == "what changed?").unwrap();
+        assert_eq!(prompt.author.as_deref(), Some("bob"));
+        assert_eq!(host.chat.input, "draft in progress");
+
+        host.submit_guard.last_submit = None;
+        host.submit_message();
+        let own = host.chat.messages.iter().find(|m| m.content == "draft in progress").unwrap();
+        assert_eq!(own.author.as_deref(), Some("ada"));
+
+        // Stopping disconnects the guest
+        host.run_command(SlashCommand::Share, "");
+        assert!(host.share.is_none());
+        while !guest.guest.as_ref().unwrap().closed {
+            assert!(started.elapsed() < Duration::from_secs(5), "guest never noticed");
+            guest.process_share();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn test_autosave_only_when_due_and_changed() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+        app.chat.input = "remember me".to_string();
+        app.submit_message();
+
+        app.autosave = Autosave::new(60);
+        app.autosave();
+        assert!(app.session.is_none());
+
+        app.autosave.last -= Duration::from_secs(61);
+        app.autosave();
+        let saved_at = app.session.as_ref().unwrap().updated_at;
+
+        // Unchanged conversations are not rewritten
+        app.autosave.last -= Duration::from_secs(61);
+        app.autosave();
+        assert_eq!(app.session.as_ref().unwrap().updated_at, saved_at);
+    }
+
+    #[test]
+    fn test_replies_keep_their_model_timing_and_usage() {
+        let mut app = App::new_without_banner();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        let mut reply = Message::assistant(String::new());
+        reply.meta = Some(ReplyMeta::new("qwen3:4b"));
+        app.chat.messages.push(reply);
+        app.llm.request_sent = Some(Instant::now());
+
+        app.handle_stream_event(StreamEvent::Token("Hello".to_string()));
+        app.handle_stream_event(StreamEvent::Usage(TokenUsage {
+            prompt_tokens: 12,
+            completion_tokens: 3,
+        }));
+        app.handle_stream_event(StreamEvent::Done);
+
+        let meta = app.chat.messages.last().unwrap().meta.clone().unwrap();
+        assert_eq!(meta.model, "qwen3:4b");
+        assert!(meta.first_token.is_some() && meta.duration.is_some());
+        assert_eq!(meta.usage.map(|u| u.completion_tokens), Some(3));
+        // The reported tokens are counted, not estimates
+        assert_eq!(app.session_usage.input_tokens, 12);
+
+        app.toggle_reply_info();
+        assert!(app.chat.show_info);
+    }
+
+    #[test]
+    fn test_retries_show_until_the_reply_starts() {
+        let mut app = App::new_without_banner();
+        app.chat.messages.push(Message::assistant(String::new()));
+        app.handle_stream_event(StreamEvent::Retrying {
+            attempt: 1,
+            max_retries: 3,
+            delay: Duration::from_secs(2),
+            reason: "OpenAI API error (429): Rate limit reached".to_string(),
+        });
+        let retry = app.llm.retrying.clone().unwrap();
+        assert_eq!((retry.attempt, retry.max_retries), (1, 3));
+        assert!(retry.at > Instant::now());
+
+        app.handle_stream_event(StreamEvent::Token("Hi".to_string()));
+        assert!(app.llm.retrying.is_none());
+        assert_eq!(app.chat.messages.last().unwrap().content, "Hi");
+    }
+
+    #[test]
+    fn test_completed_requests_are_recorded() {
+        let mut app = App::new_without_banner();
+        app.usage = Some(UsageLedger::in_memory().unwrap());
+        app.llm.config.provider = Provider::Ollama;
+        app.llm.config.model = "qwen3:4b".to_string();
+        app.llm.request_bytes = 400;
+        app.chat.messages.push(Message::assistant("x".repeat(40)));
+        app.record_usage();
+
+        let (from, to) = crate::usage::month_range(&crate::usage::current_month()).unwrap();
+        let totals = app.usage.as_ref().unwrap().totals_between(from, to).unwrap();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].provider, "ollama");
+        assert_eq!((totals[0].input_tokens, totals[0].output_tokens), (100, 10));
+        assert_eq!(totals[0].unpriced, 0);
+    }
+
+    #[test]
+    fn test_replies_are_priced_and_usage_shows_the_ledger() {
+        let mut app = App::new_without_banner();
+        app.usage = Some(UsageLedger::in_memory().unwrap());
+        app.llm.config.provider = Provider::Anthropic;
+        app.llm.config.model = "claude-sonnet-4-20250514".to_string();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        let mut reply = Message::assistant(String::new());
+        reply.meta = Some(ReplyMeta::new("claude-sonnet-4-20250514"));
+        app.chat.messages.push(reply);
+
+        app.handle_stream_event(StreamEvent::Token("Hi".to_string()));
+        app.handle_stream_event(StreamEvent::Usage(TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+        }));
+        app.handle_stream_event(StreamEvent::Done);
+
+        let meta = app.chat.messages.last().unwrap().meta.clone().unwrap();
+        assert!((meta.cost.unwrap() - 4.5).abs() < 1e-9);
+        assert_eq!(app.session_usage.cost_label(), "$4.50");
+
+        app.run_command(SlashCommand::Usage, "");
+        let viewer = app.viewer.as_ref().unwrap();
+        assert!(viewer.lines[0].starts_with("This session: 1 requests"));
+        assert!(viewer.lines.iter().any(|l| l.starts_with("anthropic/claude-sonnet-4")));
+        assert!(viewer.lines.iter().any(|l| l == "Usage for all time"));
+    }
+
+    #[test]
+    fn test_raw_toggles() {
+        let mut app = App::new_without_banner();
+        *app.chat.messages = vec![
+            Message::user("one".to_string()),
+            Message::assistant("**two**".to_string()),
+            Message::assistant("`three`".to_string()),
+        ];
+
+        // Only replies can be toggled
+        app.toggle_raw_message();
+        assert!(app.chat.raw_flipped.is_empty());
 
-        // Special handling for Anthropic CreateApiKey method
-        if provider == Provider::Anthropic
-            && anthropic_method == Some(AnthropicAuthMethod::CreateApiKey)
-        {
-            // Convert OAuth token to API key
-            let access_token = token.access_token.clone();
-            let model_str = model.to_string();
+        app.scroll.offset = 1;
+        app.toggle_raw_message();
+        assert!(app.chat.is_raw(1));
+        assert!(!app.chat.is_raw(2));
 
-            let (tx, rx) = tokio::sync::oneshot::channel();
+        app.chat.input = "/raw".to_string();
+        app.submit_message();
+        assert!(app.chat.is_raw(1) && app.chat.is_raw(2));
 
-            tokio::spawn(async move {
-                let result = AnthropicOAuth::convert_to_api_key(&access_token).await;
-                let _ = tx.send(result);
-            });
+        app.toggle_raw_message();
+        assert!(!app.chat.is_raw(1));
+        assert!(app.chat.is_raw(2));
+    }
 
-            // Show converting status
-            self.toast_info("Converting OAuth token to API key...".to_string());
+    #[test]
+    fn test_thinking_streams_apart_from_the_answer() {
+        let mut app = App::new_without_banner();
+        *app.chat.messages = vec![
+            Message::user("2+2?".to_string()),
+            Message::assistant(String::new()),
+        ];
+        app.handle_stream_event(StreamEvent::Thinking("Add them.".to_string()));
+        app.handle_stream_event(StreamEvent::Token("4".to_string()));
+        let reply = app.chat.messages.last().unwrap();
+        assert_eq!((reply.thinking.as_str(), reply.content.as_str()), ("Add them.", "4"));
 
-            // Store the receiver for processing in the event loop
-            // We'll need to add a new field for this
-            self.api_key_conversion_rx = Some((rx, model_str));
-            self.connect = ConnectState::None;
-            return;
-        }
+        assert!(!app.chat.show_thinking);
+        app.toggle_thinking();
+        assert!(app.chat.show_thinking);
+    }
 
-        // For OAuth (Claude Pro/Max and Copilot), save the OAuth credential
-        if let Ok(mut storage) = AuthStorage::load() {
-            let expires_at = token.expires_at();
-            storage.set(
-                provider.storage_key(),
-                Credential::oauth(
-                    &token.access_token,
-                    token.refresh_token.clone(),
-                    expires_at,
-                    Some(model.to_string()),
-                ),
-            );
-            if let Err(e) = storage.save() {
-                self.toast_warning(format!("Could not save credentials: {}", e));
-            }
-        }
+    #[test]
+    fn test_theme_command() {
+        let mut app = App::new_without_banner_with_config(&Config::default());
+        assert_eq!(app.appearance.current, Appearance::Dark);
 
-        // Configure and switch to the provider
-        self.llm.config.provider = provider;
-        self.llm.config.api_base = provider.default_api_base().to_string();
-        self.llm.config.model = model.to_string();
-        self.llm.config.api_key = token.access_token;
-        self.llm.config.credential_type = crate::llm::CredentialType::OAuth;
-        self.llm.apply_config();
-        self.connect = ConnectState::None;
+        app.run_command(SlashCommand::Theme, "");
+        assert_eq!(app.appearance.current, Appearance::Light);
+        app.run_command(SlashCommand::Theme, "dark");
+        assert_eq!(app.appearance.current, Appearance::Dark);
+        app.run_command(SlashCommand::Theme, "light");
+        app.run_command(SlashCommand::Theme, "auto");
+        assert_eq!(app.appearance.current, Appearance::Dark);
+        assert_eq!(app.appearance.forced, None);
+    }
 
-        self.toast_success(format!("Connected to {}", provider.display_name()));
+    #[tokio::test]
+    async fn test_guest_prompts_wait_out_dry_runs() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::CoWrite, "ada".to_string()).unwrap();
+        host.queued.push_back(("bob".to_string(), "Check the lockfile".to_string()));
+        app.share = Some(host);
+        app.llm.dry_run = true;
+        let before = app.chat.messages.len();
+        app.process_share();
+        assert_eq!(app.chat.messages.len(), before);
+        assert_eq!(app.share.as_ref().unwrap().queued.len(), 1);
+
+        app.toggle_dry_run();
+        app.process_share();
+        assert!(app.share.as_ref().unwrap().queued.is_empty());
+        let prompt = &app.chat.messages[before];
+        assert_eq!(prompt.content, "Check the lockfile");
+        assert_eq!(prompt.author.as_deref(), Some("bob"));
     }
 
-    /// Complete model selection for Copilot.
-    ///
-    /// Called when user selects a model from the model selection dialog.
-    pub fn complete_model_selection(&mut self, model: &str) {
-        if let ConnectState::SelectingModel {
-            provider,
-            oauth_token,
-            anthropic_method,
-            ..
-        } = std::mem::take(&mut self.connect)
-        {
-            self.finish_oauth_connection(provider, oauth_token, model, anthropic_method);
-        }
+    #[test]
+    fn test_dry_run_shows_request_without_sending() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        *app.chat.messages = vec![
+            Message::user("Earlier".to_string()),
+            Message::assistant("Reply".to_string()),
+        ];
+        app.chat.input = "/dryrun".to_string();
+        app.submit_message();
+        assert!(app.llm.dry_run);
+
+        app.chat.input = "Hello there".to_string();
+        app.submit_message();
+        assert!(app.llm.stream_rx.is_none());
+        assert_eq!(app.chat.messages.len(), 3);
+        let note = app.chat.messages.last().unwrap();
+        assert!(note.is_system_note());
+        assert!(note.content.contains("POST https://api.anthropic.com/v1/messages"));
+        assert!(note.content.contains("Earlier") && note.content.contains("Hello there"));
+        // The draft stays so it can be adjusted or sent for real
+        assert_eq!(app.chat.input, "Hello there");
+
+        app.toggle_dry_run();
+        app.submit_guard.last_submit = None;
+        app.submit_message();
+        assert!(app.chat.messages.last().unwrap().content.starts_with("You said:"));
     }
 
-    /// Tick the OAuth auth dialog timer.
-    ///
-    /// Call this each second to update the countdown.
-    pub fn tick_oauth_dialog(&mut self) {
-        match &mut self.connect {
-            ConnectState::OAuthPending { auth_dialog, .. }
-            | ConnectState::OAuthPolling { auth_dialog, .. } => {
-                auth_dialog.tick();
-                if auth_dialog.is_expired() {
-                    self.toast_error("Authentication timed out");
-                    self.connect = ConnectState::None;
-                    self.oauth_rx = None;
-                }
-            }
-            _ => {}
-        }
+    #[test]
+    fn test_system_prompt_variables_are_expanded_when_sent() {
+        std::env::set_var("SCRY_APP_TEST_PROJECT", "scry");
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.llm.dry_run = true;
+        app.llm.system_prompt = Some("You work on ${env:SCRY_APP_TEST_PROJECT}.".to_string());
+        let request = |app: &mut App| {
+            app.submit_guard.last_submit = None;
+            app.chat.input = "Hello".to_string();
+            app.submit_message();
+            app.chat.messages.pop().unwrap().content
+        };
+
+        // Not allowlisted: sent as written, with a toast
+        let preview = request(&mut app);
+        assert!(preview.contains("\"system\": \"You work on ${env:SCRY_APP_TEST_PROJECT}.\""));
+        let toast = &app.toasts.toasts.back().unwrap().message;
+        assert!(toast.starts_with("System prompt not expanded"), "{}", toast);
+
+        app.submit_guard.variables.env = vec!["SCRY_APP_TEST_PROJECT".to_string()];
+        let preview = request(&mut app);
+        assert!(preview.contains("\"system\": \"You work on scry.\""), "{}", preview);
+        // What is kept and shown by /system is the prompt as written
+        assert_eq!(app.system_prompt(), Some("You work on ${env:SCRY_APP_TEST_PROJECT}."));
     }
 
-    // ─────────────────────────────────────────────────────────────────────────────
-    // Anthropic OAuth methods
-    // ─────────────────────────────────────────────────────────────────────────────
+    #[test]
+    fn test_system_prompt_is_sent_and_set_per_conversation() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.llm.dry_run = true;
+        app.llm.system_prompt = Some("From the config.".to_string());
+        let request = |app: &mut App| {
+            app.submit_guard.last_submit = None;
+            app.chat.input = "Hello".to_string();
+            app.submit_message();
+            app.chat.messages.pop().unwrap().content
+        };
+        assert!(request(&mut app).contains("\"system\": \"From the config.\""));
 
-    /// Select Anthropic authentication method.
-    ///
-    /// Called when user selects an option from the Anthropic method selection dialog.
-    pub fn select_anthropic_method(&mut self, selected: usize) {
-        use crate::auth::{AnthropicAuthMethod, AnthropicOAuth};
+        app.run_command(SlashCommand::System, "Answer in French.");
+        assert_eq!(app.system_prompt(), Some("Answer in French."));
+        assert!(request(&mut app).contains("\"system\": \"Answer in French.\""));
 
-        match selected {
-            0 => {
-                // Claude Pro/Max OAuth
-                match AnthropicOAuth::new(AnthropicAuthMethod::ClaudeProMax) {
-                    Ok(oauth) => {
-                        // Open browser automatically
-                        if let Err(e) = oauth.open_browser() {
-                            self.toast_error(format!("Could not open browser: {}", e));
-                        }
+        app.run_command(SlashCommand::System, "off");
+        assert_eq!(app.system_prompt(), None);
+        assert!(!request(&mut app).contains("\"system\""));
 
-                        self.connect = ConnectState::EnteringAuthCode {
-                            method: AnthropicAuthMethod::ClaudeProMax,
-                            oauth_handler: oauth,
-                            input: String::new(),
-                            cursor: 0,
-                            error: None,
-                        };
-                    }
-                    Err(e) => {
-                        self.toast_error(format!("OAuth initialization failed: {}", e));
-                        self.connect = ConnectState::None;
-                    }
-                }
-            }
-            1 => {
-                // Create API Key OAuth
-                match AnthropicOAuth::new(AnthropicAuthMethod::CreateApiKey) {
-                    Ok(oauth) => {
-                        // Open browser automatically
-                        if let Err(e) = oauth.open_browser() {
-                            self.toast_error(format!("Could not open browser: {}", e));
-                        }
+        app.run_command(SlashCommand::System, "default");
+        assert_eq!(app.system_prompt(), Some("From the config."));
+        app.clear_conversation();
+        assert_eq!(app.chat.system_prompt, None);
+    }
 
-                        self.connect = ConnectState::EnteringAuthCode {
-                            method: AnthropicAuthMethod::CreateApiKey,
-                            oauth_handler: oauth,
-                            input: String::new(),
-                            cursor: 0,
-                            error: None,
-                        };
-                    }
-                    Err(e) => {
-                        self.toast_error(format!("OAuth initialization failed: {}", e));
-                        self.connect = ConnectState::None;
-                    }
-                }
-            }
-            2 | _ => {
-                // Manual API key entry
-                self.enter_new_credentials();
-            }
+    #[test]
+    fn test_selection_deletes_and_pins_messages() {
+        let mut app = App::new_without_banner();
+        app.chat.messages.clear();
+        for i in 0..3 {
+            app.chat.messages.push(Message::user(format!("question {}", i)));
+            app.chat.messages.push(Message::assistant(format!("answer {}", i)));
         }
+
+        // The highlight stays in view as it moves past the last visible message
+        app.scroll.visible_end = 2;
+        app.open_selection();
+        app.selection.as_mut().unwrap().selected = 4;
+        app.follow_selection();
+        assert_eq!(app.scroll.offset, 3);
+
+        app.delete_message(1);
+        assert_eq!(app.chat.messages.len(), 5);
+        assert_eq!(app.chat.messages[1].content, "question 1");
+        app.undo();
+        assert_eq!(app.chat.messages[1].content, "answer 0");
+
+        // Pinned messages are sent even when the window leaves them out
+        app.toggle_pin(1);
+        assert!(app.chat.messages[1].pinned);
+        app.llm.context = ContextConfig {
+            strategy: ContextStrategy::SlidingWindow,
+            window_messages: 2,
+            ..ContextConfig::default()
+        };
+        let sent = app.fit_context(app.api_messages());
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].content.contains("Assistant: answer 0"));
+        assert!(!sent[0].content.contains("question 1"));
     }
 
-    /// Submit authorization code for token exchange.
-    pub fn submit_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode {
-            method,
-            oauth_handler,
-            input,
-            ..
-        } = std::mem::replace(&mut self.connect, ConnectState::None)
-        {
-            let (tx, rx) = tokio::sync::oneshot::channel();
-            self.auth_code_rx = Some(rx);
+    #[test]
+    fn test_open_viewer_on_top_message() {
+        let mut app = App::new_without_banner();
+        *app.chat.messages = vec![
+            Message::user("one".to_string()),
+            Message::assistant("| a | b |\n|---|---|".to_string()),
+        ];
+        app.scroll.offset = 1;
+        app.open_viewer();
+        let viewer = app.viewer.as_ref().unwrap();
+        assert_eq!(viewer.title, "Assistant · message 2 of 2");
+        assert_eq!(viewer.lines.len(), 2);
+    }
 
-            // Start async token exchange
-            tokio::spawn(async move {
-                let result = oauth_handler.exchange_code(&input).await;
-                let _ = tx.send(result);
-            });
+    #[test]
+    fn test_jump_to_prompt_skips_replies() {
+        let mut app = App::new_without_banner();
+        *app.chat.messages = vec![
+            Message::user("one".to_string()),
+            Message::assistant("1".to_string()),
+            Message::system_note("note".to_string()),
+            Message::user("two".to_string()),
+            Message::assistant("2".to_string()),
+        ];
+        app.jump_to_prompt(true);
+        assert_eq!(app.scroll.offset, 3);
+        app.jump_to_prompt(true);
+        assert_eq!(app.scroll.offset, 3);
+        app.scroll.offset = 4;
+        app.jump_to_prompt(false);
+        assert_eq!(app.scroll.offset, 3);
+        app.jump_to_prompt(false);
+        assert_eq!(app.scroll.offset, 0);
+    }
 
-            self.connect = ConnectState::ExchangingCode { method };
-        }
+    #[test]
+    fn test_section_picker_jumps_and_folds() {
+        let mut app = App::new_without_banner();
+        app.open_section_picker();
+        assert!(app.section_picker.is_none());
+
+        *app.chat.messages = vec![
+            Message::user("How do I configure tokio runtime threads?".to_string()),
+            Message::assistant("Use the builder".to_string()),
+            Message::user("Write a haiku about autumn leaves".to_string()),
+            Message::assistant("Leaves fall".to_string()),
+        ];
+        app.open_section_picker();
+        assert_eq!(app.section_picker.as_ref().map(|p| p.selected), Some(0));
+
+        app.toggle_section(1);
+        assert!(app.sections.is_collapsed(2));
+
+        app.jump_to_section(1);
+        assert_eq!(app.scroll.offset, 2);
+        assert!(app.section_picker.is_none());
     }
 
-    /// Process API key conversion results.
-    ///
-    /// Call this in the event loop to check for completed API key conversions.
-    pub fn process_api_key_conversion(&mut self) {
-        if let Some((rx, model)) = self.api_key_conversion_rx.as_mut() {
-            if let Ok(result) = rx.try_recv() {
-                let model = model.clone();
-                self.api_key_conversion_rx = None;
+    #[test]
+    fn test_check_session_model_matching_is_silent() {
+        let mut app = App::new_without_banner();
+        let provider = app.llm.config.provider;
+        let model = app.llm.config.model.clone();
+        assert!(!app.check_session_model(provider, &model));
+        assert!(app.model_mismatch.is_none());
+    }
 
-                match result {
-                    Ok(api_key) => {
-                        use crate::auth::{AuthStorage, Credential};
+    #[test]
+    fn test_resolve_model_mismatch_switch_back_same_provider() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Ollama;
+        app.llm.config.model = "qwen3:4b".to_string();
 
-                        // Save as API key credential
-                        if let Ok(mut storage) = AuthStorage::load() {
-                            storage.set(
-                                Provider::Anthropic.storage_key(),
-                                Credential::api_key(&api_key),
-                            );
-                            if let Err(e) = storage.save() {
-                                self.toast_warning(format!("Could not save API key: {}", e));
-                            }
-                        }
+        assert!(app.check_session_model(Provider::Ollama, "llama3.2"));
+        app.resolve_model_mismatch(true);
 
-                        // Configure and switch to Anthropic with API key
-                        self.llm.config.provider = Provider::Anthropic;
-                        self.llm.config.api_base = Provider::Anthropic.default_api_base().to_string();
-                        self.llm.config.model = model;
-                        self.llm.config.api_key = api_key;
-                        self.llm.config.credential_type = crate::llm::CredentialType::ApiKey;
-                        self.llm.apply_config();
+        assert!(app.model_mismatch.is_none());
+        assert_eq!(app.llm.config.model, "llama3.2");
+        let note = app.chat.messages.last().unwrap();
+        assert!(note.is_system_note());
+        assert!(note.content.contains("Switched back"));
+    }
 
-                        self.toast_success("API key created successfully!".to_string());
-                    }
-                    Err(e) => {
-                        self.toast_error(format!("Failed to create API key: {}", e));
-                    }
-                }
-            }
-        }
+    #[test]
+    fn test_resolve_model_mismatch_continue_records_note() {
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Ollama;
+        app.llm.config.model = "qwen3:4b".to_string();
+
+        app.check_session_model(Provider::Anthropic, "claude-sonnet-4-5");
+        app.resolve_model_mismatch(false);
+
+        assert_eq!(app.llm.config.model, "qwen3:4b");
+        let note = app.chat.messages.last().unwrap();
+        assert!(note.is_system_note());
+        assert!(note.content.contains("Continued with qwen3:4b"));
     }
 
-    /// Process authorization code exchange results.
-    ///
-    /// Call this in the event loop to check for completed code exchanges.
-    pub fn process_auth_code_exchange(&mut self) {
-        if let Some(rx) = self.auth_code_rx.as_mut() {
-            if let Ok(result) = rx.try_recv() {
-                self.auth_code_rx = None;
+    #[test]
+    fn test_process_warm_up_success() {
+        let mut app = App::new_without_banner();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.llm.warm_up_rx = Some(rx);
+        app.llm.status = ConnectionStatus::WarmingUp;
 
-                // Get the method from ExchangingCode state
-                let method = if let ConnectState::ExchangingCode { method } = self.connect {
-                    Some(method)
-                } else {
-                    None
-                };
+        app.process_warm_up();
+        assert!(app.llm.is_warming_up());
 
-                match result {
-                    Ok(token) => {
-                        // Success! Now show model selection
-                        self.connect = ConnectState::SelectingModel {
-                            provider: Provider::Anthropic,
-                            selected: 0,
-                            oauth_token: token,
-                            anthropic_method: method,
-                        };
-                    }
-                    Err(e) => {
-                        self.toast_error(format!("Authentication failed: {}", e));
-                        self.connect = ConnectState::None;
-                    }
-                }
-            }
-        }
+        tx.send(Ok(())).unwrap();
+        app.process_warm_up();
+        assert!(!app.llm.is_warming_up());
+        assert_eq!(app.llm.status, ConnectionStatus::Ready);
+        assert!(app.toasts.is_empty());
+    }
+
+    #[test]
+    fn test_process_warm_up_failure_keeps_streaming_status() {
+        let mut app = App::new_without_banner();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.llm.warm_up_rx = Some(rx);
+        app.llm.status = ConnectionStatus::Streaming;
+
+        tx.send(Err("model not found".to_string())).unwrap();
+        app.process_warm_up();
+        assert_eq!(app.llm.status, ConnectionStatus::Streaming);
+        assert!(!app.toasts.is_empty());
     }
 
-    // Auth code input helpers
-    pub fn insert_char_auth_code(&mut self, c: char) {
-        if let ConnectState::EnteringAuthCode {
-            input, cursor, error, ..
-        } = &mut self.connect
-        {
-            input.insert(*cursor, c);
-            *cursor += 1;
-            *error = None;
+    #[test]
+    fn test_input_expands_shortcodes_and_edits_by_char() {
+        let mut app = App::new_without_banner();
+        app.chat.shortcodes = true;
+        for c in "ok :tada: :roc".chars() {
+            app.handle_char(c);
         }
+        assert_eq!(app.chat.input, "ok 🎉 :roc");
+        assert!(app.chat.complete_shortcode());
+        assert_eq!(app.chat.input, "ok 🎉 🚀");
+
+        // Cursor movement and deletion step over whole emoji
+        app.move_cursor_left();
+        app.move_cursor_left();
+        app.handle_backspace();
+        assert_eq!(app.chat.input, "ok  🚀");
+        app.move_cursor_right();
+        assert_eq!(app.chat.cursor_position, "ok  ".len());
+
+        app.chat.shortcodes = false;
+        app.chat.clear_input();
+        for c in ":tada:".chars() {
+            app.handle_char(c);
+        }
+        assert_eq!(app.chat.input, ":tada:");
     }
 
-    pub fn backspace_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode {
-            input, cursor, error, ..
-        } = &mut self.connect
-        {
-            if *cursor > 0 {
-                *cursor -= 1;
-                input.remove(*cursor);
-                *error = None;
-            }
-        }
+    #[test]
+    fn test_paste_dropped_file_becomes_chip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("report final.pdf");
+        std::fs::write(&file, "x").unwrap();
+
+        let mut app = App::new_without_banner();
+        app.chat.input = "summarize".to_string();
+        app.chat.cursor_position = app.chat.input.len();
+        app.handle_paste(&format!("'{}'", file.display()));
+        assert_eq!(app.pasted_files.as_ref().unwrap().paths, vec![file.clone()]);
+        assert_eq!(app.chat.input, "summarize");
+        app.resolve_pasted_files(Some(true));
+        assert!(app.pasted_files.is_none());
+        assert_eq!(
+            app.chat.input,
+            format!("summarize @\"{}\" ", file.display())
+        );
+        assert_eq!(app.chat.cursor_position, app.chat.input.len());
+
+        // Or the path was meant as text
+        let plain = dir.path().join("notes.txt");
+        std::fs::write(&plain, "x").unwrap();
+        app.chat.clear_input();
+        app.handle_paste(&format!("{} ", plain.display()));
+        app.resolve_pasted_files(Some(false));
+        assert_eq!(app.chat.input, plain.display().to_string());
+
+        // Images go to a vision model as images
+        let shot = dir.path().join("shot.png");
+        std::fs::write(&shot, "x").unwrap();
+        app.chat.clear_input();
+        app.handle_paste(&shot.display().to_string());
+        app.resolve_pasted_files(Some(true));
+        assert_eq!(app.chat.images[0].name, "shot.png");
+        assert_eq!(app.chat.input, "");
+
+        app.chat.clear_input();
+        app.handle_paste("'/no/such/file.txt'");
+        assert_eq!(app.chat.input, "'/no/such/file.txt'");
+        assert!(!app.toasts.is_empty());
     }
 
-    pub fn delete_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode {
-            input, cursor, error, ..
-        } = &mut self.connect
-        {
-            if *cursor < input.len() {
-                input.remove(*cursor);
-                *error = None;
-            }
-        }
+    #[test]
+    fn test_attached_images_go_with_the_next_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chart.png");
+        std::fs::write(&path, b"hello").unwrap();
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+
+        app.chat.input = format!("/attach {}", path.display());
+        app.submit_message();
+        assert_eq!(app.chat.images.len(), 1);
+        app.chat.input = "What does this show?".to_string();
+        app.submit_message();
+        assert!(app.chat.images.is_empty());
+        let prompt = app.chat.messages.iter().rfind(|m| m.role == Role::User).unwrap();
+        assert_eq!(prompt.images[0].name, "chart.png");
+        let sent = app.api_messages();
+        let sent = sent.iter().rfind(|m| m.role == "user").unwrap();
+        assert_eq!(sent.images[0].data, "aGVsbG8=");
+
+        // Text-only models neither take attachments nor get the earlier images
+        app.llm.config.provider = Provider::DeepSeek;
+        app.llm.config.model = "deepseek-chat".to_string();
+        app.chat.input = format!("/attach {}", path.display());
+        app.submit_message();
+        assert!(app.chat.images.is_empty());
+        assert!(app.api_messages().iter().all(|m| m.images.is_empty()));
     }
 
-    pub fn move_cursor_left_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode { cursor, .. } = &mut self.connect {
-            *cursor = cursor.saturating_sub(1);
-        }
+    #[tokio::test]
+    async fn test_staged_images_stay_with_the_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chart.png");
+        std::fs::write(&path, b"hello").unwrap();
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.chat.input = format!("/attach {}", path.display());
+        app.submit_message();
+        assert_eq!(app.chat.images.len(), 1);
+
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::CoWrite, "ada".to_string()).unwrap();
+        host.queued.push_back(("bob".to_string(), "Check the lockfile".to_string()));
+        app.share = Some(host);
+        app.process_share();
+        let prompt = app.chat.messages.iter().rfind(|m| m.role == Role::User).unwrap();
+        assert_eq!(prompt.author.as_deref(), Some("bob"));
+        assert!(prompt.images.is_empty());
+        assert_eq!(app.chat.images.len(), 1);
+
+        app.chat.input = "What does this show?".to_string();
+        app.submit_message();
+        let prompt = app.chat.messages.iter().rfind(|m| m.role == Role::User).unwrap();
+        assert_eq!(prompt.images[0].name, "chart.png");
+        assert!(app.chat.images.is_empty());
     }
 
-    pub fn move_cursor_right_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode { input, cursor, .. } = &mut self.connect {
-            if *cursor < input.len() {
-                *cursor += 1;
+    #[test]
+    fn test_attachment_manager_rewrites_chips() {
+        let mut app = App::new_without_banner();
+        app.open_attachments();
+        assert!(app.attachments.is_none());
+
+        app.chat.input = "diff @a.rs and @b.rs".to_string();
+        app.open_attachments();
+        assert_eq!(app.attachments.as_ref().unwrap().attachments.len(), 2);
+        app.apply_attachments(vec!["@b.rs".to_string()]);
+        assert!(app.attachments.is_none());
+        assert_eq!(app.chat.input, "diff @b.rs and ");
+        assert_eq!(app.chat.cursor_position, app.chat.input.len());
+    }
+
+    #[test]
+    fn test_paste_into_api_key_field_is_trimmed() {
+        let mut app = App::new_without_banner();
+        app.connect = ConnectState::EnteringApiKey {
+            provider: Provider::Anthropic,
+            input: String::new(),
+            cursor: 0,
+            error: Some("bad".to_string()),
+        };
+        app.handle_paste("  sk-ant-123\n");
+        match &app.connect {
+            ConnectState::EnteringApiKey { input, cursor, error, .. } => {
+                assert_eq!(input, "sk-ant-123");
+                assert_eq!(*cursor, 10);
+                assert!(error.is_none());
             }
+            _ => panic!("dialog closed"),
         }
     }
 
-    pub fn move_cursor_start_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode { cursor, .. } = &mut self.connect {
-            *cursor = 0;
-        }
+    #[test]
+    fn test_double_submit_is_ignored() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.submit_guard = SubmitGuard::new(60_000, 0);
+        let before = app.chat.messages.len();
+
+        app.chat.input = "hello".to_string();
+        app.submit_message();
+        app.chat.input = "hello".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), before + 2);
+        assert_eq!(app.chat.input, "hello");
     }
 
-    pub fn move_cursor_end_auth_code(&mut self) {
-        if let ConnectState::EnteringAuthCode { input, cursor, .. } = &mut self.connect {
-            *cursor = input.len();
-        }
+    #[test]
+    fn test_large_prompt_needs_confirmation() {
+        let mut app = App::new_without_banner();
+        app.llm.client = Some(LlmClient::new(app.llm.config.clone()));
+        app.submit_guard = SubmitGuard::new(0, 10);
+        let before = app.chat.messages.len();
+
+        app.chat.input = "x".repeat(100);
+        app.submit_message();
+        let pending = app.submit_guard.pending.clone().unwrap();
+        assert_eq!(pending.tokens, estimate_tokens(pending.bytes));
+        assert!(pending.tokens > 10);
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.confirm_send(false);
+        assert!(app.submit_guard.pending.is_none());
+        assert_eq!(app.chat.input.len(), 100);
+        assert_eq!(app.chat.messages.len(), before);
     }
-}
 
-impl Default for App {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_spending_caps_warn_and_block_until_confirmed() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.llm.config.provider = Provider::OpenAI;
+        app.llm.config.model = "gpt-4o".to_string();
+        app.usage = Some(UsageLedger::in_memory().unwrap());
+        app.submit_guard = SubmitGuard::new(0, 0);
+        app.submit_guard.budget.providers.insert(
+            "openai".to_string(),
+            crate::config::SpendLimit {
+                daily: Some(1.0),
+                monthly: None,
+            },
+        );
+
+        // 0.9 of the cap: a warning, shown once
+        app.llm.usage = Some(TokenUsage {
+            prompt_tokens: 360_000,
+            completion_tokens: 0,
+        });
+        app.chat.messages.push(Message::assistant("a".to_string()));
+        app.record_usage();
+        assert_eq!(app.toasts.toasts.len(), 1);
+        app.llm.usage = Some(TokenUsage::default());
+        app.record_usage();
+        assert_eq!(app.toasts.toasts.len(), 1);
+
+        // Over it: an error, and with `block` the next prompt waits for a yes
+        app.llm.usage = Some(TokenUsage {
+            prompt_tokens: 80_000,
+            completion_tokens: 0,
+        });
+        app.record_usage();
+        assert_eq!(app.toasts.toasts.len(), 2);
+        app.submit_guard.budget.block = true;
+        let before = app.chat.messages.len();
+        app.chat.input = "one more".to_string();
+        app.submit_message();
+        assert_eq!(app.submit_guard.spend.as_ref().unwrap().cap(), "openai daily");
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.confirm_spend(true);
+        assert!(app.submit_guard.spend.is_none());
+        assert!(app.chat.messages.len() > before);
+        // Confirmed once, the cap stops asking
+        app.chat.input = "and another".to_string();
+        app.submit_message();
+        assert!(app.submit_guard.spend.is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_spending_caps_hold_retries_and_continuations() {
+        let mut app = offline_ollama_app();
+        let ledger = UsageLedger::in_memory().unwrap();
+        ledger
+            .record(&UsageRecord {
+                cost: Some(2.0),
+                ..UsageRecord::estimated(Provider::Ollama, "qwen3:4b", 0, 0)
+            })
+            .unwrap();
+        app.usage = Some(ledger);
+        app.submit_guard.budget.block = true;
+        app.submit_guard.budget.providers.insert(
+            "ollama".to_string(),
+            crate::config::SpendLimit {
+                daily: Some(1.0),
+                monthly: None,
+            },
+        );
+        app.chat.messages.push(Message::user("Name a color".to_string()));
+        app.chat.messages.push(Message::assistant("Blue".to_string()));
+        let before = app.chat.messages.len();
+
+        // `/retry` never reaches the provider without a yes
+        app.run_command(SlashCommand::Retry, "");
+        assert!(!app.is_streaming());
+        assert_eq!(app.submit_guard.held, Some(HeldRequest::Reply));
+        assert_eq!(app.submit_guard.spend.as_ref().unwrap().cap(), "ollama daily");
+
+        // Declining fails the prompt instead of leaving it hanging
+        app.confirm_spend(false);
+        assert!(app.submit_guard.held.is_none());
+        assert!(app.chat.messages.last().unwrap().is_error());
+        assert_eq!(app.chat.messages.len(), before);
+
+        // So does `/continue`, until confirmed
+        app.chat.messages.pop();
+        app.chat.messages.push(Message::assistant("Blue".to_string()));
+        app.continue_last_reply();
+        assert!(!app.is_streaming());
+        assert_eq!(app.submit_guard.held, Some(HeldRequest::Continue));
+        app.confirm_spend(true);
+        assert!(app.is_streaming());
+        assert_eq!(app.chat.messages.len(), before);
+    }
 
     #[test]
-    fn test_mask_api_key_normal() {
-        assert_eq!(mask_api_key("sk-ant-REDACTED"), "sk-a...mnop");
+    fn test_variables_are_previewed_before_sending() {
+        std::env::set_var("SCRY_APP_TEST_NAME", "scry");
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        app.submit_guard.variables.env = vec!["SCRY_APP_TEST_NAME".to_string()];
+        let before = app.chat.messages.len();
+
+        // Disallowed variables are refused without sending
+        app.chat.input = "hi ${env:HOME}".to_string();
+        app.submit_message();
+        assert!(app.submit_guard.expansion.is_none());
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.chat.input = "hi ${env:SCRY_APP_TEST_NAME}".to_string();
+        app.submit_message();
+        assert_eq!(app.submit_guard.expansion.as_ref().unwrap().text, "hi scry");
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.confirm_expansion(true);
+        assert!(app.submit_guard.expansion.is_none());
+        assert_eq!(app.chat.messages[before].content, "hi scry");
     }
 
     #[test]
-    fn test_mask_api_key_short() {
-        assert_eq!(mask_api_key("short"), "*****");
-        assert_eq!(mask_api_key("12345678"), "********");
+    fn test_activity_and_stream_draining() {
+        assert_eq!(App::new().activity(), Activity::Animating);
+        let mut app = App::new_without_banner();
+        app.toasts = ToastState::default();
+        assert_eq!(app.activity(), Activity::Idle);
+        app.toast_info("saved");
+        assert_eq!(app.activity(), Activity::Waiting);
+        app.toasts = ToastState::default();
+
+        // Streaming wakes the loop by itself, so it does not need polling
+        app.chat.messages.push(Message::assistant(String::new()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        app.llm.status = ConnectionStatus::Streaming;
+        assert_eq!(app.activity(), Activity::Idle);
+
+        tx.try_send(StreamEvent::Token("a".to_string())).unwrap();
+        tx.try_send(StreamEvent::Token("b".to_string())).unwrap();
+        while app.process_stream() {}
+        assert_eq!(app.chat.messages.last().unwrap().content, "ab");
+        drop(tx);
+        assert!(!app.process_stream());
+        assert!(app.llm.stream_rx.is_none());
+        assert_eq!(app.llm.status, ConnectionStatus::Ready);
     }
 
     #[test]
-    fn test_mask_api_key_empty() {
-        assert_eq!(mask_api_key(""), "");
+    fn test_stream_error_becomes_error_card() {
+        let mut app = App::new_without_banner();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::assistant(String::new()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        app.llm.status = ConnectionStatus::Streaming;
+
+        tx.try_send(StreamEvent::Error("Ollama error (404 Not Found): no model".to_string()))
+            .unwrap();
+        app.process_stream();
+
+        let last = app.chat.messages.last().unwrap();
+        assert!(last.is_error() && !last.is_chat());
+        let card = last.error.as_ref().unwrap();
+        assert_eq!(card.error.title(), "Invalid request");
+        assert!(matches!(app.llm.status, ConnectionStatus::Error(_)));
     }
 
-    #[test]
-    fn test_mask_api_key_exactly_nine_chars() {
-        // 9 chars should show first 4 and last 4, which overlaps but works
-        assert_eq!(mask_api_key("123456789"), "1234...6789");
+    #[tokio::test]
+    async fn test_network_failure_queues_message_until_online() {
+        let mut app = offline_ollama_app();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::assistant(String::new()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+
+        tx.try_send(StreamEvent::Error("Connection failed: dns error".to_string()))
+            .unwrap();
+        app.process_stream();
+        let n = app.chat.messages.len();
+        assert!(app.chat.messages[n - 1].queued);
+        assert_eq!(app.chat.messages[n - 1].content, "hi");
+        assert!(app.llm.offline.offline);
+
+        // Later messages wait too, without another failed request
+        app.chat.input = "still there?".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), n + 1);
+        assert!(app.chat.messages[n].queued);
+        assert!(app.llm.stream_rx.is_none());
+
+        let (probe_tx, probe_rx) = tokio::sync::oneshot::channel();
+        app.llm.offline.probe_rx = Some(probe_rx);
+        probe_tx.send(true).unwrap();
+        app.process_offline();
+        assert!(!app.llm.offline.offline);
+        assert!(app.chat.messages.iter().all(|m| !m.queued));
+        assert!(matches!(app.llm.status, ConnectionStatus::Streaming));
+        assert_eq!(app.chat.messages.len(), n + 2);
+    }
+
+    #[tokio::test]
+    async fn test_prompts_wait_for_exhausted_rate_limits() {
+        use crate::llm::Quota;
+
+        let mut app = offline_ollama_app();
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        let quota = |remaining| Quota {
+            limit: 50,
+            remaining,
+            reset: Some(Duration::from_secs(20)),
+        };
+        tx.try_send(StreamEvent::RateLimits(RateLimits {
+            requests: Some(quota(0)),
+            tokens: None,
+        }))
+        .unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        while app.process_stream() {}
+        assert_eq!(app.llm.rate_limits.limits.unwrap().requests, Some(quota(0)));
+
+        // No requests left: the prompt is held until the quota resets
+        app.chat.input = "hi".to_string();
+        app.submit_message();
+        assert!(app.chat.messages.last().unwrap().queued);
+        assert!(app.llm.stream_rx.is_none());
+        let until = app.llm.rate_limits.held_until.unwrap();
+        assert!(until > Instant::now() + Duration::from_secs(15));
+        assert_eq!(app.activity(), Activity::Waiting);
+
+        app.process_rate_limit();
+        assert!(app.llm.stream_rx.is_none());
+
+        app.llm.rate_limits.held_until = Some(Instant::now());
+        app.process_rate_limit();
+        assert!(app.chat.messages.iter().all(|m| !m.queued));
+        assert!(app.llm.rate_limits.held_until.is_none());
+        assert!(matches!(app.llm.status, ConnectionStatus::Streaming));
     }
 
     #[test]
-    fn test_connect_state_default() {
-        let state = ConnectState::default();
-        assert!(!state.is_active());
-        assert!(state.provider().is_none());
+    fn test_rate_limit_wait_counts_message_tokens() {
+        use crate::llm::Quota;
+
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::OpenAI;
+        app.chat.messages.clear();
+        for _ in 0..10 {
+            app.chat.messages.push(Message::user("ok".to_string()));
+            app.chat.messages.push(Message::assistant("ok".to_string()));
+        }
+        // Tool output counts, not the short note shown for it
+        let output = "x".repeat(400);
+        app.chat.messages.push(Message::tool_result("call_1", "Ran cat".to_string(), output));
+        app.llm.rate_limits = RateLimitState {
+            limits: Some(RateLimits {
+                requests: None,
+                tokens: Some(Quota {
+                    limit: 10_000,
+                    remaining: 100,
+                    reset: Some(Duration::from_secs(20)),
+                }),
+            }),
+            received: Some(Instant::now()),
+            held_until: None,
+        };
+        // 40 bytes of chat would be 10 tokens, but each message has its framing
+        assert!(app.rate_limit_wait().is_some());
+
+        app.chat.messages.truncate(4);
+        assert_eq!(app.rate_limit_wait(), None);
     }
 
     #[test]
-    fn test_connect_state_is_active() {
-        let state = ConnectState::SelectingMethod {
-            provider: Provider::Anthropic,
-            selected: 0,
+    fn test_token_refresh_switches_the_connection_to_the_new_token() {
+        use crate::auth::Credential;
+
+        let mut app = App::new_without_banner();
+        app.llm.config.provider = Provider::Anthropic;
+        app.llm.config.api_key = "old".to_string();
+        app.llm.token_refresh.enabled = true;
+        let refreshed = |result| {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let refreshed = vec![Refreshed {
+                provider: Provider::Anthropic,
+                old_token: "old".to_string(),
+                result,
+            }];
+            tx.send((refreshed, Vec::new())).unwrap();
+            rx
         };
-        assert!(state.is_active());
-        assert_eq!(state.provider(), Some(Provider::Anthropic));
+
+        // A failure is reported once, however often it is retried
+        app.llm.token_refresh.rx = Some(refreshed(Err("invalid_grant".to_string())));
+        app.process_token_refresh();
+        app.llm.token_refresh.rx = Some(refreshed(Err("invalid_grant".to_string())));
+        app.process_token_refresh();
+        assert_eq!(app.toasts.toasts.len(), 1);
+        assert_eq!(app.llm.config.api_key, "old");
+
+        let credential = Credential::oauth("new", Some("refresh".to_string()), None, None);
+        app.llm.token_refresh.rx = Some(refreshed(Ok(credential)));
+        app.process_token_refresh();
+        assert_eq!(app.llm.config.api_key, "new");
+        assert!(app.llm.token_refresh.rx.is_none());
+        assert!(app.llm.token_refresh.warned.is_empty());
     }
 
     #[test]
-    fn test_connect_state_entering_api_key() {
-        let state = ConnectState::EnteringApiKey {
-            provider: Provider::OpenRouter,
-            input: "sk-or-test".to_string(),
-            cursor: 10,
-            error: None,
+    fn test_dragging_over_the_chat_copies_the_selected_text() {
+        use crossterm::event::KeyModifiers;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new_without_banner();
+        app.chat
+            .messages
+            .push(Message::assistant("The quick brown fox".to_string()));
+        let config = Config::default();
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| crate::ui::ui(f, &mut app, &config)).unwrap();
+
+        // Find "quick" on screen
+        let buffer = terminal.backend().buffer().clone();
+        let (column, row) = (0..buffer.area.height)
+            .find_map(|row| {
+                let text: String = (0..buffer.area.width)
+                    .map(|column| buffer.get(column, row).symbol())
+                    .collect();
+                text.find("quick")
+                    .map(|at| (text[..at].chars().count() as u16, row))
+            })
+            .unwrap();
+        let mouse = |kind, column| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
         };
-        assert!(state.is_active());
-        assert_eq!(state.provider(), Some(Provider::OpenRouter));
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), column));
+        app.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), column + 10));
+        app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), column + 10));
+        terminal.draw(|f| crate::ui::ui(f, &mut app, &config)).unwrap();
+        assert_eq!(app.mouse.copied.as_deref(), Some("quick brown"));
+        assert_eq!(terminal.backend().buffer().get(column, row).bg, crate::ui::DRAG_BG);
+
+        // A click clears the highlight
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), column));
+        app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), column));
+        assert!(app.mouse.drag.is_none());
     }
 
     #[test]
-    fn test_connect_state_validating() {
-        let state = ConnectState::ValidatingKey {
+    fn test_search_jumps_to_matches_out_of_view() {
+        use crossterm::event::KeyCode;
+
+        let mut app = App::new_without_banner();
+        for i in 0..30 {
+            app.chat.messages.push(Message::user(format!("question {}", i)));
+        }
+        app.chat
+            .messages
+            .push(Message::assistant("the needle is here".to_string()));
+        let last = app.chat.messages.len() - 1;
+        app.chat.messages[5].content = "another needle".to_string();
+        app.scroll.offset = 10;
+        app.scroll.visible_end = 20;
+
+        app.open_search();
+        for c in "needle".chars() {
+            let action = app.search.as_mut().unwrap().handle_key(KeyCode::Char(c));
+            app.handle_search_action(action);
+        }
+        // The first match at or below the view
+        assert_eq!(app.scroll.offset, last);
+
+        app.scroll.visible_end = last + 1;
+        app.handle_search_action(SearchAction::Next);
+        assert_eq!(app.scroll.offset, 5);
+        assert_eq!(app.search.as_ref().unwrap().current, 0);
+
+        app.handle_search_action(SearchAction::Close);
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn test_profile_config_replaces_the_connection() {
+        let mut app = App::new_without_banner();
+        app.llm.token_refresh.expiring = vec![Expiring {
             provider: Provider::Anthropic,
-            key: "sk-ant-test".to_string(),
-            model: None,
-        };
-        assert!(state.is_active());
+            expires_at: chrono::Utc::now(),
+        }];
+        let config: Config = toml::from_str(
+            "[llm]\nprovider = \"ollama\"\nsystem_prompt = \"Work only.\"\n[llm.models]\nollama = \"qwen3:4b\"\n",
+        )
+        .unwrap();
+
+        app.use_profile_config(&config);
+        assert_eq!(app.llm.config.provider, Provider::Ollama);
+        assert_eq!(app.llm.config.model, "qwen3:4b");
+        assert_eq!(app.llm.system_prompt.as_deref(), Some("Work only."));
+        assert!(app.llm.token_refresh.expiring.is_empty());
     }
 
     #[test]
-    fn test_connect_state_clone() {
-        let state = ConnectState::ExistingCredential {
+    fn test_ctrl_l_opens_the_sign_in_for_an_expiring_provider() {
+        let mut app = App::new_without_banner();
+        app.reconnect_expiring();
+        assert!(matches!(app.connect, ConnectState::None));
+
+        app.llm.token_refresh.expiring = vec![Expiring {
             provider: Provider::Anthropic,
-            masked_key: "sk-a...xyz".to_string(),
-            current_model: None,
-            selected: 1,
+            expires_at: chrono::Utc::now(),
+        }];
+        app.reconnect_expiring();
+        assert!(matches!(
+            app.connect,
+            ConnectState::SelectingAnthropicMethod { .. }
+        ));
+    }
+
+    #[test]
+    fn test_expiring_sign_in_is_announced_once() {
+        let mut app = App::new_without_banner();
+        app.llm.token_refresh.enabled = true;
+        let expiring = Expiring {
+            provider: Provider::GitHubCopilot,
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(3),
         };
-        let cloned = state.clone();
-        assert!(matches!(cloned, ConnectState::ExistingCredential { .. }));
+        let checked = |expiring: Vec<Expiring>| {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            tx.send((Vec::new(), expiring)).unwrap();
+            rx
+        };
+
+        for _ in 0..2 {
+            app.llm.token_refresh.rx = Some(checked(vec![expiring]));
+            app.process_token_refresh();
+        }
+        assert_eq!(app.toasts.toasts.len(), 1);
+        assert_eq!(app.llm.token_refresh.expiring, vec![expiring]);
+
+        // Signed in again: the warning goes away
+        app.llm.token_refresh.rx = Some(checked(Vec::new()));
+        app.process_token_refresh();
+        assert!(app.llm.token_refresh.expiring.is_empty());
+        assert!(app.llm.token_refresh.announced.is_empty());
     }
 
     #[test]
-    fn test_start_connection_shows_dialog() {
+    fn test_stream_error_after_partial_reply_keeps_it() {
         let mut app = App::new_without_banner();
-        app.start_connection(Provider::Anthropic);
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::assistant("Hel".to_string()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
 
-        // Should go to either SelectingAnthropicMethod or ExistingCredential
-        // depending on whether credentials already exist
-        assert!(
-            matches!(app.connect, ConnectState::SelectingAnthropicMethod { .. })
-                || matches!(
-                    app.connect,
-                    ConnectState::ExistingCredential {
-                        provider: Provider::Anthropic,
-                        ..
-                    }
-                )
-        );
+        tx.try_send(StreamEvent::Error("Stream error: reset".to_string()))
+            .unwrap();
+        app.process_stream();
+
+        let n = app.chat.messages.len();
+        assert_eq!(app.chat.messages[n - 2].content, "Hel");
+        assert_eq!(app.chat.messages[n - 2].finish, Some(FinishReason::Interrupted));
+        assert!(app.chat.messages[n - 1].is_error());
     }
 
     #[test]
-    fn test_start_connection_ollama() {
+    fn test_streaming_reply_is_checkpointed() {
         let mut app = App::new_without_banner();
-        app.start_connection(Provider::Ollama);
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+        app.autosave = Autosave::new(0).with_checkpoint(5);
+        app.chat.messages.push(Message::user("tell me a story".to_string()));
+        app.chat.messages.push(Message::assistant("Once upon".to_string()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        let saved = |app: &App| {
+            let id = &app.session.as_ref().unwrap().id;
+            app.sessions.as_ref().unwrap().load(id).unwrap().messages
+        };
 
-        // Ollama doesn't need credentials, should complete immediately
-        assert!(matches!(app.connect, ConnectState::None));
-        assert_eq!(app.llm.config.provider, Provider::Ollama);
+        // Not due yet
+        app.autosave();
+        assert!(app.session.is_none());
+
+        // Saved unfinished, so a crash leaves a resumable reply
+        app.autosave.checkpointed -= Duration::from_secs(6);
+        app.autosave();
+        let reply = saved(&app).pop().unwrap();
+        assert_eq!(reply.content, "Once upon");
+        assert!(reply.interrupted);
+
+        tx.try_send(StreamEvent::Token(" a time".to_string())).unwrap();
+        app.process_stream();
+        tx.try_send(StreamEvent::Done).unwrap();
+        app.process_stream();
+        let reply = saved(&app).pop().unwrap();
+        assert_eq!(reply.content, "Once upon a time");
+        assert!(!reply.interrupted);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_reply_is_saved_and_resumed() {
+        let mut app = offline_ollama_app();
+        app.sessions = Some(Arc::new(crate::session::SqliteStore::in_memory().unwrap()));
+        app.chat.messages.push(Message::user("count".to_string()));
+        app.chat.messages.push(Message::assistant("one, two".to_string()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        tx.try_send(StreamEvent::Error("Stream error: reset".to_string()))
+            .unwrap();
+        app.process_stream();
+
+        let session = app.session.clone().unwrap();
+        let resumed = session.chat_messages().pop().unwrap();
+        assert_eq!(resumed.content, "one, two");
+        assert_eq!(resumed.finish, Some(FinishReason::Interrupted));
+
+        // /continue drops the error card and extends the reply in place
+        let n = app.chat.messages.len();
+        app.chat.input = "/continue".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), n - 1);
+        assert_eq!(app.llm.reply_start, "one, two".len());
+        assert!(matches!(app.llm.status, ConnectionStatus::Streaming));
+    }
+
+    /// An app with an Ollama client pointed at a closed port.
+    fn offline_ollama_app() -> App {
+        let mut app = App::new_without_banner();
+        app.llm.config = LlmConfig {
+            provider: Provider::Ollama,
+            api_base: "http://127.0.0.1:9".to_string(),
+            model: "qwen3:4b".to_string(),
+            warm_up: false,
+            ..LlmConfig::default()
+        };
+        app.llm.client = Some(LlmClient::new(app.llm.config.clone()));
+        app.llm.auto_continue = AutoContinue::new(1, 16_000);
+        app
+    }
+
+    #[tokio::test]
+    async fn test_summarize_strategy_summarizes_before_sending() {
+        let mut app = offline_ollama_app();
+        app.llm.context_window = 400;
+        app.llm.context = ContextConfig {
+            strategy: ContextStrategy::Summarize,
+            reserve_tokens: 0,
+            ..ContextConfig::default()
+        };
+        for i in 0..10 {
+            let text = format!("question {} {}", i, "word ".repeat(20));
+            app.chat.messages.push(Message::user(text.clone()));
+            app.chat.messages.push(Message::assistant(text));
+        }
+        app.chat.messages.push(Message::user("and now?".to_string()));
+        let before = app.chat.messages.len();
+
+        app.start_request();
+        assert!(app.is_streaming());
+        assert!(app.llm.stream_rx.is_none());
+        assert_eq!(app.chat.messages.len(), before);
+
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.summarizing.as_mut().unwrap().rx = rx;
+        tx.try_send(StreamEvent::Token("They asked ten questions.".to_string()))
+            .unwrap();
+        app.process_summary();
+        assert!(app.chat.summary.is_none());
+        tx.try_send(StreamEvent::Done).unwrap();
+        app.process_summary();
+
+        let summary = app.chat.summary.as_ref().unwrap();
+        assert_eq!(summary.text, "They asked ten questions.");
+        assert!(summary.covers > 0);
+        assert_eq!(app.api_messages()[summary.covers].role, "user");
+        assert_eq!(app.chat.dropped, summary.covers);
+        assert!(app.llm.stream_rx.is_some());
+        assert_eq!(app.chat.messages.len(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_reply_cut_off_at_length_continues_in_same_message() {
+        let mut app = offline_ollama_app();
+        app.chat.messages.push(Message::user("write a poem".to_string()));
+        app.chat.messages.push(Message::assistant(String::new()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+
+        tx.try_send(StreamEvent::Token("Roses are ".to_string())).unwrap();
+        tx.try_send(StreamEvent::Finish(FinishReason::Length)).unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        for _ in 0..3 {
+            app.process_stream();
+        }
+        assert_eq!(app.llm.auto_continue.used, 1);
+        assert_eq!(app.llm.reply_start, "Roses are ".len());
+        assert!(matches!(app.llm.status, ConnectionStatus::Streaming));
+
+        // The continuation streams into the same message
+        let n = app.chat.messages.len();
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+        tx.try_send(StreamEvent::Token("red".to_string())).unwrap();
+        tx.try_send(StreamEvent::Finish(FinishReason::Length)).unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        for _ in 0..3 {
+            app.process_stream();
+        }
+        assert_eq!(app.chat.messages.len(), n);
+        assert_eq!(app.chat.messages[n - 1].content, "Roses are red");
+        // The follow-up budget is spent, so the second cut-off is left alone
+        assert!(app.llm.stream_rx.is_none());
+        assert_eq!(app.llm.auto_continue.used, 1);
+        assert!(app.chat.messages[n - 1].is_cut_short());
+    }
+
+    #[tokio::test]
+    async fn test_continue_command_extends_last_reply() {
+        let mut app = offline_ollama_app();
+        app.chat.input = "/continue".to_string();
+        app.submit_message();
+        assert!(app.llm.stream_rx.is_none());
+        assert!(app.chat.input.is_empty());
+
+        app.chat.messages.push(Message::user("count".to_string()));
+        let mut reply = Message::assistant("one, two, ".to_string());
+        reply.finish = Some(FinishReason::Length);
+        app.chat.messages.push(reply);
+        let n = app.chat.messages.len();
+
+        app.chat.input = "/continue".to_string();
+        app.submit_message();
+        assert_eq!(app.chat.messages.len(), n);
+        assert!(app.chat.messages[n - 1].finish.is_none());
+        assert_eq!(app.llm.reply_start, "one, two, ".len());
+        assert!(matches!(app.llm.status, ConnectionStatus::Streaming));
+    }
+
+    #[test]
+    fn test_finish_reason_is_kept_on_the_reply() {
+        let mut app = App::new_without_banner();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::assistant(String::new()));
+        let (tx, rx) = mpsc::channel(4);
+        app.llm.stream_rx = Some(rx);
+
+        tx.try_send(StreamEvent::Token("I can't".to_string())).unwrap();
+        tx.try_send(StreamEvent::Finish(FinishReason::ContentFilter))
+            .unwrap();
+        tx.try_send(StreamEvent::Done).unwrap();
+        for _ in 0..3 {
+            app.process_stream();
+        }
+        let last = app.chat.messages.last().unwrap();
+        assert_eq!(last.finish, Some(FinishReason::ContentFilter));
+        assert!(last.is_cut_short());
+        // Filtered replies are not continued automatically
+        assert!(app.llm.stream_rx.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auto_continue_respects_limits() {
+        let mut app = offline_ollama_app();
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::assistant("x".repeat(400)));
+        app.llm.auto_continue = AutoContinue::new(3, 100);
+        app.chat.messages.last_mut().unwrap().finish = Some(FinishReason::Length);
+        app.auto_continue();
+        assert!(app.llm.stream_rx.is_none());
+
+        app.llm.auto_continue = AutoContinue::new(3, 16_000);
+        app.chat.messages.last_mut().unwrap().finish = Some(FinishReason::Stop);
+        app.auto_continue();
+        assert!(app.llm.stream_rx.is_none());
+    }
+
+    #[test]
+    fn test_retry_requires_error_card_and_connection() {
+        let mut app = App::new_without_banner();
+        app.llm.client = None;
+        let before = app.chat.messages.len();
+        app.retry_last_request();
+        assert_eq!(app.chat.messages.len(), before);
+
+        app.chat.messages.push(Message::user("hi".to_string()));
+        app.chat.messages.push(Message::error(
+            ProviderError::from_message("boom"),
+            "Ollama",
+        ));
+        app.retry_last_request();
+        // Without a client the card stays so the user can retry after connecting
+        assert!(app.chat.messages.last().unwrap().is_error());
     }
 
     #[test]
@@ -1883,6 +8501,7 @@ mod tests {
             provider: Provider::Anthropic,
             masked_key: "sk-ant-***test".to_string(),
             current_model: None,
+            scope: None,
             selected: 0,
         };
 
@@ -1964,6 +8583,7 @@ mod tests {
             provider: Provider::OpenRouter,
             masked_key: "sk-or...xyz".to_string(),
             current_model: None,
+            scope: None,
             selected: 1,
         };
 
@@ -1992,6 +8612,7 @@ mod tests {
             provider: Provider::GitHubCopilot,
             masked_key: "gho_...xyz".to_string(),
             current_model: Some("claude-sonnet-4.5".to_string()),
+            scope: None,
             selected: 2,
         };
 
@@ -2140,8 +8761,9 @@ mod tests {
         let state = ConnectState::SelectingModel {
             provider: Provider::GitHubCopilot,
             selected: 2,
-            oauth_token: token,
+            oauth_token: Some(token),
             anthropic_method: None,
+            models: None,
         };
         assert!(state.is_active());
         assert_eq!(state.provider(), Some(Provider::GitHubCopilot));
@@ -2160,8 +8782,9 @@ mod tests {
         app.connect = ConnectState::SelectingModel {
             provider: Provider::GitHubCopilot,
             selected: 0,
-            oauth_token: token,
+            oauth_token: Some(token),
             anthropic_method: None,
+            models: None,
         };
 
         app.complete_model_selection("claude-sonnet-4.5");
@@ -2184,8 +8807,9 @@ mod tests {
         app.connect = ConnectState::SelectingModel {
             provider: Provider::GitHubCopilot,
             selected: 1,
-            oauth_token: token,
+            oauth_token: Some(token),
             anthropic_method: None,
+            models: None,
         };
 
         app.cancel_connection();
@@ -2221,6 +8845,7 @@ mod tests {
             provider: Provider::GitHubCopilot,
             masked_key: "gho_...ken".to_string(),
             current_model: Some("claude-sonnet-4.5".to_string()),
+            scope: None,
             selected: 1,
         };
 
@@ -2268,8 +8893,9 @@ mod tests {
         app.connect = ConnectState::SelectingModel {
             provider: Provider::GitHubCopilot,
             selected: 2,
-            oauth_token: token,
+            oauth_token: Some(token),
             anthropic_method: None,
+            models: None,
         };
 
         app.complete_model_selection("claude-haiku-4.5");
@@ -2285,6 +8911,7 @@ mod tests {
             provider: Provider::GitHubCopilot,
             masked_key: "gho_...ken".to_string(),
             current_model: Some("claude-sonnet-4.5".to_string()),
+            scope: None,
             selected: 0,
         };
 
@@ -2305,6 +8932,7 @@ mod tests {
             provider: Provider::Anthropic,
             masked_key: "sk-a...xyz".to_string(),
             current_model: None,
+            scope: None,
             selected: 0,
         };
 
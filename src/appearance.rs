@@ -0,0 +1,240 @@
+//! Light and dark appearance.
+//!
+//! `[appearance] mode` picks the palette: always dark (the `[theme]`
+//! colors), always light, light during a daily window of local time, or
+//! whatever the system uses (macOS `AppleInterfaceStyle`, GNOME
+//! `color-scheme`). Scheduled and system modes are re-checked every
+//! `check_interval_secs` from the event loop; `/theme` overrides the choice
+//! until `/theme auto`.
+
+use chrono::{Local, NaiveTime};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::{AppearanceConfig, AppearanceMode};
+
+/// Which palette is in use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Appearance {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Appearance {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Appearance::Dark => "dark",
+            Appearance::Light => "light",
+        }
+    }
+
+    /// The other appearance.
+    pub fn toggled(self) -> Self {
+        match self {
+            Appearance::Dark => Appearance::Light,
+            Appearance::Light => Appearance::Dark,
+        }
+    }
+}
+
+/// The appearance in use and when to check it again.
+#[derive(Debug, Clone)]
+pub struct AppearanceState {
+    pub current: Appearance,
+    /// Set by `/theme light|dark`; wins over the configured mode
+    pub forced: Option<Appearance>,
+    config: AppearanceConfig,
+    checked_at: Option<Instant>,
+}
+
+impl AppearanceState {
+    pub fn new(config: &AppearanceConfig) -> Self {
+        let mut state = Self {
+            current: Appearance::Dark,
+            forced: None,
+            config: config.clone(),
+            checked_at: None,
+        };
+        state.tick();
+        state
+    }
+
+    /// Re-evaluate the configured mode if the check interval has passed.
+    /// Returns true if the appearance changed.
+    pub fn tick(&mut self) -> bool {
+        let interval = Duration::from_secs(self.config.check_interval_secs.max(1));
+        if self.checked_at.is_some_and(|at| at.elapsed() < interval) {
+            return false;
+        }
+        self.checked_at = Some(Instant::now());
+        self.update()
+    }
+
+    /// Force an appearance, or go back to the configured mode with None.
+    /// Returns true if the appearance changed.
+    pub fn set(&mut self, forced: Option<Appearance>) -> bool {
+        self.forced = forced;
+        self.checked_at = Some(Instant::now());
+        self.update()
+    }
+
+    fn update(&mut self) -> bool {
+        let appearance = self.forced.unwrap_or_else(|| self.configured());
+        let changed = appearance != self.current;
+        self.current = appearance;
+        changed
+    }
+
+    /// The appearance the configured mode asks for right now.
+    fn configured(&self) -> Appearance {
+        match self.config.mode {
+            AppearanceMode::Dark => Appearance::Dark,
+            AppearanceMode::Light => Appearance::Light,
+            AppearanceMode::Schedule => {
+                let from = parse_time(&self.config.light_from);
+                let until = parse_time(&self.config.light_until);
+                match (from, until) {
+                    (Some(from), Some(until)) => scheduled(Local::now().time(), from, until),
+                    _ => Appearance::Dark,
+                }
+            }
+            // Keep what we have if the system can't be asked
+            AppearanceMode::System => system_appearance().unwrap_or(self.current),
+        }
+    }
+}
+
+/// Parse `HH:MM` (or `HH:MM:SS`).
+pub fn parse_time(time: &str) -> Option<NaiveTime> {
+    let time = time.trim();
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+        .ok()
+}
+
+/// Light from `from` until `until`; the window may wrap past midnight.
+pub fn scheduled(now: NaiveTime, from: NaiveTime, until: NaiveTime) -> Appearance {
+    let light = if from <= until {
+        from <= now && now < until
+    } else {
+        now >= from || now < until
+    };
+    if light {
+        Appearance::Light
+    } else {
+        Appearance::Dark
+    }
+}
+
+/// The desktop's appearance, where it can be detected.
+pub fn system_appearance() -> Option<Appearance> {
+    if cfg!(target_os = "macos") {
+        // Only set while dark mode is on; the read fails in light mode
+        let output = Command::new("defaults")
+            .args(["read", "-g", "AppleInterfaceStyle"])
+            .output()
+            .ok()?;
+        return Some(parse_macos(
+            output.status.success(),
+            &String::from_utf8_lossy(&output.stdout),
+        ));
+    }
+    let gsettings = |key: &str| {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", key])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    gsettings("color-scheme")
+        .and_then(|scheme| parse_gnome_color_scheme(&scheme))
+        .or_else(|| gsettings("gtk-theme").map(|theme| parse_gtk_theme(&theme)))
+}
+
+fn parse_macos(success: bool, style: &str) -> Appearance {
+    if success && style.trim().eq_ignore_ascii_case("dark") {
+        Appearance::Dark
+    } else {
+        Appearance::Light
+    }
+}
+
+/// `'prefer-dark'` or `'prefer-light'`; `'default'` says nothing.
+fn parse_gnome_color_scheme(scheme: &str) -> Option<Appearance> {
+    match scheme.trim().trim_matches('\'') {
+        "prefer-dark" => Some(Appearance::Dark),
+        "prefer-light" => Some(Appearance::Light),
+        _ => None,
+    }
+}
+
+/// Older GNOME: dark themes are named like `Adwaita-dark`.
+fn parse_gtk_theme(theme: &str) -> Appearance {
+    if theme.to_lowercase().contains("dark") {
+        Appearance::Dark
+    } else {
+        Appearance::Light
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveTime {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_scheduled() {
+        let (from, until) = (at("08:00"), at("19:00"));
+        assert_eq!(scheduled(at("07:59"), from, until), Appearance::Dark);
+        assert_eq!(scheduled(at("08:00"), from, until), Appearance::Light);
+        assert_eq!(scheduled(at("18:59"), from, until), Appearance::Light);
+        assert_eq!(scheduled(at("19:00"), from, until), Appearance::Dark);
+
+        // Light overnight
+        assert_eq!(scheduled(at("23:00"), until, from), Appearance::Light);
+        assert_eq!(scheduled(at("12:00"), until, from), Appearance::Dark);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time(" 7:30 "), Some(at("07:30")));
+        assert_eq!(parse_time("19:00:30"), NaiveTime::from_hms_opt(19, 0, 30));
+        assert_eq!(parse_time("7pm"), None);
+    }
+
+    #[test]
+    fn test_parse_system_appearance() {
+        assert_eq!(parse_macos(true, "Dark\n"), Appearance::Dark);
+        assert_eq!(parse_macos(false, ""), Appearance::Light);
+        assert_eq!(
+            parse_gnome_color_scheme("'prefer-dark'\n"),
+            Some(Appearance::Dark)
+        );
+        assert_eq!(parse_gnome_color_scheme("'default'\n"), None);
+        assert_eq!(parse_gtk_theme("'Adwaita-dark'\n"), Appearance::Dark);
+        assert_eq!(parse_gtk_theme("'Yaru'\n"), Appearance::Light);
+    }
+
+    #[test]
+    fn test_forced_appearance() {
+        let config = AppearanceConfig {
+            mode: AppearanceMode::Light,
+            ..AppearanceConfig::default()
+        };
+        let mut state = AppearanceState::new(&config);
+        assert_eq!(state.current, Appearance::Light);
+        assert!(!state.tick());
+
+        assert!(state.set(Some(Appearance::Dark)));
+        assert_eq!(state.current, Appearance::Dark);
+        assert!(state.set(None));
+        assert_eq!(state.current, Appearance::Light);
+    }
+}
@@ -0,0 +1,238 @@
+//! Audit log of credential and tool actions.
+//!
+//! Records are appended as JSON lines to `audit.log` next to `auth.json`
+//! (`~/.local/share/scry-cli/audit.log`, mode 0600) and never rewritten.
+//! `scry audit` prints them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::profiles;
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+/// File name of the audit log.
+const FILE_NAME: &str = "audit.log";
+
+/// What was done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A credential was stored or replaced
+    CredentialSaved,
+    /// A stored credential was removed
+    CredentialDeleted,
+    /// OAuth tokens were replaced by refreshed ones
+    CredentialRefreshed,
+    /// A tool ran a shell command or file operation
+    ToolRun,
+}
+
+impl AuditAction {
+    /// Short label for listings.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::CredentialSaved => "credential saved",
+            AuditAction::CredentialDeleted => "credential deleted",
+            AuditAction::CredentialRefreshed => "credential refreshed",
+            AuditAction::ToolRun => "tool run",
+        }
+    }
+}
+
+/// Whether the action succeeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+/// One audited action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    /// Provider storage key, or tool name for tool runs
+    pub provider: String,
+    pub outcome: Outcome,
+    /// Error message or tool arguments; never secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditRecord {
+    /// A record of `action` on `provider` happening now, with the outcome of `result`.
+    pub fn new<T>(action: AuditAction, provider: &str, result: &Result<T>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            action,
+            provider: provider.to_string(),
+            outcome: if result.is_ok() {
+                Outcome::Success
+            } else {
+                Outcome::Failure
+            },
+            detail: result.as_ref().err().map(|e| format!("{:#}", e)),
+        }
+    }
+
+    /// Add a detail, such as the command a tool ran.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        self.detail = Some(match self.detail.take() {
+            Some(error) => format!("{}: {}", detail, error),
+            None => detail,
+        });
+        self
+    }
+}
+
+/// The append-only audit log file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Returns `~/.local/share/scry-cli/audit.log` on Linux/macOS, or the
+    /// active profile's log beside its `auth.json`.
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir =
+            dirs::data_local_dir().context("Could not determine local data directory")?;
+        Ok(profiles::scoped(data_dir.join("scry-cli")).join(FILE_NAME))
+    }
+
+    /// The log at the default path.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::at(Self::default_path()?))
+    }
+
+    /// The log at `path`.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The log kept in the same directory as `file` (e.g. `auth.json`).
+    pub fn beside(file: &Path) -> Self {
+        Self::at(file.with_file_name(FILE_NAME))
+    }
+
+    /// Append a record.
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log: {}", self.path.display()))?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write audit log: {}", self.path.display()))
+    }
+
+    /// Every record, oldest first. Lines that cannot be parsed are skipped.
+    pub fn read(&self) -> Result<Vec<AuditRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read audit log: {}", self.path.display()))?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Render records as one line each, in local time.
+pub fn format_records(records: &[AuditRecord]) -> String {
+    if records.is_empty() {
+        return "No audit records\n".to_string();
+    }
+    let mut out = String::new();
+    for record in records {
+        let _ = write!(
+            out,
+            "{}  {:<20}  {:<16}  {}",
+            record
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            record.action.label(),
+            record.provider,
+            match record.outcome {
+                Outcome::Success => "ok",
+                Outcome::Failure => "FAILED",
+            }
+        );
+        if let Some(detail) = &record.detail {
+            let _ = write!(out, "  {}", detail);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::beside(&dir.path().join("auth.json"));
+        assert!(log.read().unwrap().is_empty());
+
+        log.append(&AuditRecord::new(
+            AuditAction::CredentialSaved,
+            "anthropic",
+            &Ok(()),
+        ))
+        .unwrap();
+        let failed: Result<()> = Err(anyhow::anyhow!("disk full"));
+        log.append(&AuditRecord::new(AuditAction::ToolRun, "shell", &failed).with_detail("ls -la"))
+            .unwrap();
+
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].outcome, Outcome::Success);
+        assert_eq!(records[1].detail.as_deref(), Some("ls -la: disk full"));
+
+        let listing = format_records(&records);
+        assert!(listing.contains("credential saved"));
+        assert!(listing.contains("FAILED  ls -la: disk full"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_log_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::at(dir.path().join(FILE_NAME));
+        log.append(&AuditRecord::new(
+            AuditAction::CredentialDeleted,
+            "ollama",
+            &Ok(()),
+        ))
+        .unwrap();
+        let mode = fs::metadata(dir.path().join(FILE_NAME))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
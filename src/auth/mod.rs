@@ -6,9 +6,11 @@
 mod anthropic;
 mod oauth;
 mod pkce;
+mod refresh;
 mod storage;
 
 pub use anthropic::{AnthropicAuthMethod, AnthropicOAuth};
 pub use oauth::{DeviceCode, DeviceCodeConfig, DeviceCodeFlow, OAuthToken, PollResult};
 pub use pkce::Pkce;
+pub use refresh::{expiring, format_remaining, refresh_due, Expiring, Refreshed};
 pub use storage::{AuthStorage, Credential};
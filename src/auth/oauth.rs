@@ -216,6 +216,38 @@ impl DeviceCodeFlow {
         Err(anyhow!("Unexpected response: {}", body))
     }
 
+    /// Exchange a refresh token for a new access token.
+    ///
+    /// Only tokens that expire come with a refresh token.
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<OAuthToken> {
+        let form = [
+            ("client_id", self.config.client_id.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ];
+
+        let response = self
+            .client
+            .post(&self.config.token_url)
+            .header("Accept", "application/json")
+            .form(&form)
+            .send()
+            .await
+            .context("Failed to refresh token")?;
+
+        let body = response.text().await?;
+        if let Ok(token) = serde_json::from_str::<OAuthToken>(&body) {
+            return Ok(token);
+        }
+        match serde_json::from_str::<OAuthError>(&body) {
+            Ok(error) => Err(anyhow!(
+                "Token refresh failed: {}",
+                error.error_description.unwrap_or(error.error)
+            )),
+            Err(_) => Err(anyhow!("Unexpected response: {}", body)),
+        }
+    }
+
     /// Poll for the access token until success, error, or timeout.
     ///
     /// Returns the token on success, or an error on failure/timeout.
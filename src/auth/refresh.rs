@@ -0,0 +1,214 @@
+//! Renewing OAuth access tokens before they expire.
+//!
+//! Claude Pro/Max sign-ins, and GitHub sign-ins with expiring tokens, come
+//! with a refresh token. [`refresh_due`] trades it for a new access token
+//! for every stored credential that expires within a few minutes and saves
+//! the result, so a long chat never has to sign in again. The chat UI runs
+//! it in the background and switches its live connection to the new token.
+//! Sign-ins that can't be renewed are reported by [`expiring`] instead, so
+//! the UI can warn before they run out.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
+
+use super::{AnthropicOAuth, AuthStorage, Credential, DeviceCodeFlow, OAuthToken};
+use crate::llm::Provider;
+
+/// Outcome of refreshing one provider's credential.
+#[derive(Debug)]
+pub struct Refreshed {
+    /// Provider whose credential expired
+    pub provider: Provider,
+    /// Access token that was replaced
+    pub old_token: String,
+    /// The saved credential, or why it could not be refreshed
+    pub result: Result<Credential, String>,
+}
+
+/// A stored sign-in that runs out soon and won't be renewed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expiring {
+    /// Provider the sign-in is for
+    pub provider: Provider,
+    /// When the access token expires
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Expiring {
+    /// "expires in 3h", or "expired" once it has.
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        if self.expires_at <= now {
+            "expired".to_string()
+        } else {
+            format!("expires in {}", format_remaining(self.expires_at - now))
+        }
+    }
+}
+
+/// A time left as the largest whole unit, e.g. "3d", "5h" or "12m".
+pub fn format_remaining(left: Duration) -> String {
+    if left.num_days() > 0 {
+        format!("{}d", left.num_days())
+    } else if left.num_hours() > 0 {
+        format!("{}h", left.num_hours())
+    } else {
+        format!("{}m", left.num_minutes().max(1))
+    }
+}
+
+/// Stored sign-ins that expire before `until` and won't be renewed: those
+/// without a refresh token, and those whose renewal `failed`.
+pub fn expiring(storage: &AuthStorage, until: DateTime<Utc>, failed: &[Provider]) -> Vec<Expiring> {
+    Provider::all()
+        .iter()
+        .filter_map(|&provider| match storage.get(provider.storage_key())? {
+            Credential::OAuth {
+                expires_at: Some(expires_at),
+                refresh_token,
+                ..
+            } if *expires_at <= until
+                && (refresh_token.is_none() || failed.contains(&provider)) =>
+            {
+                Some(Expiring {
+                    provider,
+                    expires_at: *expires_at,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Refresh and save every stored credential that expires soon.
+pub async fn refresh_due() -> Vec<Refreshed> {
+    let Ok(storage) = AuthStorage::load() else {
+        return Vec::new();
+    };
+    let due: Vec<(Provider, Credential)> = Provider::all()
+        .iter()
+        .filter_map(|&provider| {
+            storage
+                .get(provider.storage_key())
+                .filter(|credential| credential.needs_refresh())
+                .map(|credential| (provider, credential.clone()))
+        })
+        .collect();
+
+    let mut refreshed = Vec::new();
+    for (provider, old) in due {
+        let result = match refresh(provider, &old).await {
+            Ok(new) => match save(provider, &old, &new) {
+                Ok(true) => Ok(new),
+                // Signed out or in again meanwhile
+                Ok(false) => continue,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        refreshed.push(Refreshed {
+            provider,
+            old_token: old.token().to_string(),
+            result: result.map_err(|e| format!("{:#}", e)),
+        });
+    }
+    refreshed
+}
+
+/// Trade `credential`'s refresh token for a new access token.
+async fn refresh(provider: Provider, credential: &Credential) -> Result<Credential> {
+    let Some(refresh_token) = credential.refresh_token() else {
+        bail!("No refresh token");
+    };
+    let token = match provider {
+        Provider::Anthropic => AnthropicOAuth::refresh_token(refresh_token).await?,
+        Provider::GitHubCopilot => {
+            DeviceCodeFlow::github_copilot()
+                .refresh_token(refresh_token)
+                .await?
+        }
+        _ => bail!("{} tokens can't be refreshed", provider.display_name()),
+    };
+    Ok(renewed(credential, token))
+}
+
+/// Replace `old` with `new` in `auth.json`, re-reading it so other changes
+/// made meanwhile are kept. Returns false if `old` is no longer stored.
+fn save(provider: Provider, old: &Credential, new: &Credential) -> Result<bool> {
+    let mut storage = AuthStorage::load()?;
+    if storage.get(provider.storage_key()).map(Credential::token) != Some(old.token()) {
+        return Ok(false);
+    }
+    storage.set(provider.storage_key(), new.clone());
+    storage.save()?;
+    Ok(true)
+}
+
+/// `old` with the access token from `token`, keeping the saved model, and the
+/// refresh token when the server doesn't issue a new one.
+fn renewed(old: &Credential, token: OAuthToken) -> Credential {
+    let expires_at = token.expires_at();
+    Credential::oauth(
+        token.access_token,
+        token
+            .refresh_token
+            .or_else(|| old.refresh_token().map(str::to_string)),
+        expires_at,
+        old.model().map(str::to_string),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(refresh_token: Option<&str>) -> OAuthToken {
+        OAuthToken {
+            access_token: "new".to_string(),
+            token_type: "bearer".to_string(),
+            scope: None,
+            expires_in: Some(3600),
+            refresh_token: refresh_token.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_renewed_keeps_model_and_refresh_token() {
+        let old = Credential::oauth(
+            "old",
+            Some("refresh-1".to_string()),
+            None,
+            Some("claude-sonnet-4-5".to_string()),
+        );
+
+        let new = renewed(&old, token(None));
+        assert_eq!(new.token(), "new");
+        assert_eq!(new.refresh_token(), Some("refresh-1"));
+        assert_eq!(new.model(), Some("claude-sonnet-4-5"));
+        assert!(!new.needs_refresh());
+
+        // Rotated refresh tokens replace the old one
+        let new = renewed(&old, token(Some("refresh-2")));
+        assert_eq!(new.refresh_token(), Some("refresh-2"));
+    }
+
+    #[test]
+    fn test_expiring_skips_sign_ins_that_renew() {
+        let now = Utc::now();
+        let mut storage = AuthStorage::default();
+        let soon = Some(now + Duration::hours(3));
+        storage.set("anthropic", Credential::oauth("a", Some("refresh".to_string()), soon, None));
+        storage.set("github_copilot", Credential::oauth("g", None, soon, None));
+        storage.set("openai", Credential::api_key("sk-123"));
+        let until = now + Duration::hours(24);
+
+        let found = expiring(&storage, until, &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].provider, Provider::GitHubCopilot);
+        assert_eq!(found[0].describe(now), "expires in 3h");
+        assert_eq!(found[0].describe(now + Duration::hours(4)), "expired");
+
+        // Unless renewing it failed
+        assert_eq!(expiring(&storage, until, &[Provider::Anthropic]).len(), 2);
+        assert!(expiring(&storage, now + Duration::hours(1), &[]).is_empty());
+    }
+}
@@ -7,12 +7,13 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use crate::audit::{AuditAction, AuditLog, AuditRecord};
+use crate::durable::{self, WriteOptions};
+use crate::profiles;
 
 /// Storage for authentication credentials.
 ///
@@ -32,6 +33,9 @@ pub enum Credential {
     ApiKey {
         /// The API key value.
         key: String,
+        /// The last selected model for this provider.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
     },
 
     /// OAuth tokens with optional refresh capability.
@@ -52,7 +56,10 @@ pub enum Credential {
 impl Credential {
     /// Create a new API key credential.
     pub fn api_key(key: impl Into<String>) -> Self {
-        Self::ApiKey { key: key.into() }
+        Self::ApiKey {
+            key: key.into(),
+            model: None,
+        }
     }
 
     /// Create a new OAuth credential.
@@ -73,7 +80,7 @@ impl Credential {
     /// Get the token/key value for API requests.
     pub fn token(&self) -> &str {
         match self {
-            Self::ApiKey { key } => key,
+            Self::ApiKey { key, .. } => key,
             Self::OAuth { access_token, .. } => access_token,
         }
     }
@@ -81,8 +88,16 @@ impl Credential {
     /// Get the saved model for this credential, if any.
     pub fn model(&self) -> Option<&str> {
         match self {
-            Self::ApiKey { .. } => None,
-            Self::OAuth { model, .. } => model.as_deref(),
+            Self::ApiKey { model, .. } | Self::OAuth { model, .. } => model.as_deref(),
+        }
+    }
+
+    /// Remember `model` as the last selected model.
+    pub fn set_model(&mut self, model: impl Into<String>) {
+        match self {
+            Self::ApiKey { model: saved, .. } | Self::OAuth { model: saved, .. } => {
+                *saved = Some(model.into());
+            }
         }
     }
 
@@ -128,11 +143,12 @@ impl Credential {
 impl AuthStorage {
     /// Get the default storage path.
     ///
-    /// Returns `~/.local/share/scry-cli/auth.json` on Linux/macOS.
+    /// Returns `~/.local/share/scry-cli/auth.json` on Linux/macOS, or the
+    /// active profile's `profiles/<name>/auth.json` beside it.
     pub fn default_path() -> Result<PathBuf> {
         let data_dir = dirs::data_local_dir()
             .context("Could not determine local data directory")?;
-        Ok(data_dir.join("scry-cli").join("auth.json"))
+        Ok(profiles::scoped(data_dir.join("scry-cli")).join("auth.json"))
     }
 
     /// Load credentials from the default storage path.
@@ -168,30 +184,54 @@ impl AuthStorage {
     /// Save credentials to a specific path.
     ///
     /// Creates parent directories if needed and sets file permissions to 0600.
-    pub fn save_to(&self, path: &PathBuf) -> Result<()> {
-        // Create parent directory if needed
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
-        }
-
-        // Write to file
-        let file = File::create(path)
-            .with_context(|| format!("Failed to create auth file: {}", path.display()))?;
-
-        // Set permissions to 0600 (owner read/write only) on Unix
-        #[cfg(unix)]
-        {
-            let mut perms = file.metadata()?.permissions();
-            perms.set_mode(0o600);
-            file.set_permissions(perms)?;
+    /// The file is replaced atomically and synced, so a crash mid-save cannot
+    /// leave a truncated auth file behind.
+    ///
+    /// Added, replaced, refreshed and removed credentials are recorded in the
+    /// audit log next to the file.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let previous = Self::load_from(&path.to_path_buf()).unwrap_or_default();
+        let changes = previous.changes_to(self);
+
+        let contents = serde_json::to_vec_pretty(self)?;
+        let result = durable::write(path, &contents, WriteOptions::CRITICAL)
+            .with_context(|| format!("Failed to write auth file: {}", path.display()));
+
+        // Auditing must never stop credentials from being saved
+        let log = AuditLog::beside(path);
+        for (action, provider) in changes {
+            let _ = log.append(&AuditRecord::new(action, &provider, &result));
         }
+        result
+    }
 
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)
-            .with_context(|| format!("Failed to write auth file: {}", path.display()))?;
-
-        Ok(())
+    /// Credential changes from `self` to `next`, ordered by provider.
+    ///
+    /// Only the secret matters: switching the saved model is not a change.
+    fn changes_to(&self, next: &Self) -> Vec<(AuditAction, String)> {
+        let mut changes: Vec<_> = next
+            .credentials
+            .iter()
+            .filter_map(|(provider, new)| {
+                let action = match self.credentials.get(provider) {
+                    None => AuditAction::CredentialSaved,
+                    Some(old) if old.token() == new.token() => return None,
+                    Some(Credential::OAuth { .. }) if matches!(new, Credential::OAuth { .. }) => {
+                        AuditAction::CredentialRefreshed
+                    }
+                    Some(_) => AuditAction::CredentialSaved,
+                };
+                Some((action, provider.clone()))
+            })
+            .chain(
+                self.credentials
+                    .keys()
+                    .filter(|provider| !next.credentials.contains_key(*provider))
+                    .map(|provider| (AuditAction::CredentialDeleted, provider.clone())),
+            )
+            .collect();
+        changes.sort_by(|a, b| a.1.cmp(&b.1));
+        changes
     }
 
     /// Get a credential for a provider.
@@ -241,6 +281,16 @@ mod tests {
         assert!(!cred.needs_refresh());
     }
 
+    #[test]
+    fn test_credential_set_model() {
+        let mut cred = Credential::api_key("sk-test-123");
+        assert_eq!(serde_json::to_string(&cred).unwrap(), r#"{"type":"api_key","key":"sk-test-123"}"#);
+        cred.set_model("gpt-4.1");
+        let json = serde_json::to_string(&cred).unwrap();
+        let cred: Credential = serde_json::from_str(&json).unwrap();
+        assert_eq!(cred.model(), Some("gpt-4.1"));
+    }
+
     #[test]
     fn test_credential_oauth() {
         let cred = Credential::oauth("access-token", Some("refresh-token".to_string()), None, None);
@@ -330,6 +380,55 @@ mod tests {
         assert_eq!(loaded.get("anthropic").unwrap().token(), "sk-ant-123");
     }
 
+    #[test]
+    fn test_save_records_audit_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("auth.json");
+        let log = AuditLog::beside(&path);
+
+        let mut storage = AuthStorage::default();
+        storage.set("anthropic", Credential::api_key("sk-ant-123"));
+        storage.set(
+            "github_copilot",
+            Credential::oauth("gho_old", Some("refresh".to_string()), None, None),
+        );
+        storage.save_to(&path).unwrap();
+
+        // A model switch alone is not audited
+        storage.set(
+            "github_copilot",
+            Credential::oauth("gho_old", Some("refresh".to_string()), None, Some("gpt-4o".to_string())),
+        );
+        storage.save_to(&path).unwrap();
+        assert_eq!(log.read().unwrap().len(), 2);
+
+        storage.set(
+            "github_copilot",
+            Credential::oauth("gho_new", Some("refresh".to_string()), None, None),
+        );
+        storage.remove("anthropic");
+        storage.save_to(&path).unwrap();
+
+        let actions: Vec<_> = log
+            .read()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.action, r.provider))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (AuditAction::CredentialSaved, "anthropic".to_string()),
+                (AuditAction::CredentialSaved, "github_copilot".to_string()),
+                (AuditAction::CredentialDeleted, "anthropic".to_string()),
+                (AuditAction::CredentialRefreshed, "github_copilot".to_string()),
+            ]
+        );
+        // Secrets never reach the log
+        let raw = std::fs::read_to_string(temp_dir.path().join("audit.log")).unwrap();
+        assert!(!raw.contains("sk-ant-123") && !raw.contains("gho_new"));
+    }
+
     #[test]
     fn test_auth_storage_load_missing_file() {
         let temp_dir = TempDir::new().unwrap();
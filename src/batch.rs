@@ -0,0 +1,221 @@
+//! Bulk prompts sent one after another (`/batch file.txt`).
+//!
+//! Each non-empty line of the file is a prompt; lines starting with `#` are
+//! comments. Prompts go out one at a time, `behavior.batch_interval_ms`
+//! apart. A rate-limited prompt is resent after the provider's `Retry-After`
+//! (or a doubling wait) up to `behavior.batch_retries` times; other failures
+//! are recorded and the batch moves on. The summary lists what failed.
+
+use anyhow::{bail, Context, Result};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::BehaviorConfig;
+
+/// Wait before the first resend of a rate-limited prompt when the provider
+/// doesn't say; doubled for each further attempt.
+pub const RATE_LIMIT_WAIT: Duration = Duration::from_secs(10);
+
+/// Pacing and retry limits for batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Pause between one answer and the next prompt
+    pub interval: Duration,
+    /// Resends of a rate-limited prompt before it counts as failed
+    pub retries: u32,
+}
+
+impl BatchLimits {
+    /// Limits from behavior settings.
+    pub fn new(behavior: &BehaviorConfig) -> Self {
+        Self {
+            interval: Duration::from_millis(behavior.batch_interval_ms),
+            retries: behavior.batch_retries,
+        }
+    }
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self::new(&BehaviorConfig::default())
+    }
+}
+
+/// A prompt from the batch file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchPrompt {
+    /// Line number in the file, from 1
+    pub line: usize,
+    pub text: String,
+    /// Times it was resent after a rate limit
+    pub attempts: u32,
+}
+
+/// A prompt that got no answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFailure {
+    pub line: usize,
+    pub error: String,
+}
+
+/// A batch in progress.
+#[derive(Debug)]
+pub struct Batch {
+    /// File name, for messages
+    pub source: String,
+    /// Prompts not sent yet
+    pending: VecDeque<BatchPrompt>,
+    /// Number of prompts in the file
+    pub total: usize,
+    /// Prompt whose answer is streaming or awaited
+    pub current: Option<BatchPrompt>,
+    /// Rate-limited prompt waiting to be resent
+    pub resend: Option<BatchPrompt>,
+    /// Nothing is sent before this
+    pub resume_at: Option<Instant>,
+    /// Prompts answered
+    pub answered: usize,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl Batch {
+    /// Read the prompts in `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let name = path
+            .file_name()
+            .map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        let batch = Self::parse(name, &text);
+        if batch.total == 0 {
+            bail!("{} has no prompts", path.display());
+        }
+        Ok(batch)
+    }
+
+    /// One prompt per non-empty line, skipping `#` comments.
+    pub fn parse(source: impl Into<String>, text: &str) -> Self {
+        let pending: VecDeque<BatchPrompt> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line, text)| BatchPrompt {
+                line,
+                text: text.to_string(),
+                attempts: 0,
+            })
+            .collect();
+        Self {
+            source: source.into(),
+            total: pending.len(),
+            pending,
+            current: None,
+            resend: None,
+            resume_at: None,
+            answered: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    /// The next prompt to send, if any are left.
+    pub fn next_prompt(&mut self) -> Option<BatchPrompt> {
+        self.pending.pop_front()
+    }
+
+    /// Send nothing more; the answer streaming now still counts.
+    pub fn stop(&mut self) {
+        self.pending.clear();
+        if let Some(prompt) = self.resend.take() {
+            self.failures.push(BatchFailure {
+                line: prompt.line,
+                error: "Stopped while waiting out a rate limit".to_string(),
+            });
+        }
+        self.resume_at = None;
+    }
+
+    /// Prompts finished, answered or not.
+    pub fn done(&self) -> usize {
+        self.answered + self.failures.len()
+    }
+
+    /// Whether nothing can be sent yet.
+    pub fn is_paused(&self, now: Instant) -> bool {
+        self.resume_at.is_some_and(|at| now < at)
+    }
+
+    /// How long to wait before resending a rate-limited prompt.
+    pub fn rate_limit_wait(attempts: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.unwrap_or(RATE_LIMIT_WAIT * 2u32.saturating_pow(attempts.min(6)))
+    }
+
+    /// What happened, with a line per failure.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Batch from {} finished: {} of {} answered",
+            self.source, self.answered, self.total
+        );
+        let stopped = self.total - self.done();
+        if stopped > 0 {
+            summary.push_str(&format!(", {} not sent", stopped));
+        }
+        if self.failures.is_empty() {
+            summary.push('.');
+        } else {
+            summary.push_str(&format!(", {} failed:", self.failures.len()));
+            for failure in &self.failures {
+                summary.push_str(&format!("\n  line {}: {}", failure.line, failure.error));
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_blank_lines_and_comments() {
+        let mut batch = Batch::parse("eval.txt", "# capitals\nFrance?\n\n  Peru?  \n");
+        assert_eq!(batch.total, 2);
+        let first = batch.next_prompt().unwrap();
+        assert_eq!((first.line, first.text.as_str()), (2, "France?"));
+        let second = batch.next_prompt().unwrap();
+        assert_eq!((second.line, second.text.as_str()), (4, "Peru?"));
+        assert!(batch.next_prompt().is_none());
+    }
+
+    #[test]
+    fn test_empty_file_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.txt");
+        std::fs::write(&path, "# nothing yet\n").unwrap();
+        assert!(Batch::load(&path).is_err());
+        assert!(Batch::load(&dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_wait_doubles_without_retry_after() {
+        assert_eq!(Batch::rate_limit_wait(0, None), RATE_LIMIT_WAIT);
+        assert_eq!(Batch::rate_limit_wait(2, None), RATE_LIMIT_WAIT * 4);
+        let given = Duration::from_secs(3);
+        assert_eq!(Batch::rate_limit_wait(2, Some(given)), given);
+    }
+
+    #[test]
+    fn test_summary_lists_failures() {
+        let mut batch = Batch::parse("eval.txt", "a\nb\nc\nd");
+        batch.answered = 2;
+        batch.failures.push(BatchFailure {
+            line: 3,
+            error: "API error 500: boom".to_string(),
+        });
+        assert_eq!(
+            batch.summary(),
+            "Batch from eval.txt finished: 2 of 4 answered, 1 not sent, 1 failed:\n  line 3: API error 500: boom"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! `scry ask` - one question, answered on stdout.
+//!
+//! Streams the reply to stdout as it arrives, without starting the chat UI,
+//! using the configured provider and model unless overridden. When stdin is
+//! piped, as in `git diff | scry ask "review this"`, it is attached to the
+//! question as a fenced context block. A reader that stops early (`| head`)
+//! ends the command quietly.
+//!
+//! With `--json` the output is JSON lines instead, for other programs: a
+//! `start` event, `delta` (and `thinking`) events as the reply streams, then
+//! `done` with the whole message, model, usage, finish reason and timing, or
+//! `error` if the request fails.
+
+use std::io::{self, ErrorKind, IsTerminal, Read, Write};
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use super::{check_connected, print_dry_run, AskArgs};
+use crate::config::Config;
+use crate::llm::{ChatMessage, FinishReason, LlmClient, StreamEvent};
+use crate::pricing::Pricing;
+use crate::tokens;
+use crate::usage::UsageRecord;
+
+/// Heading of the block holding piped stdin.
+const CONTEXT_HEADING: &str = "Piped input:";
+
+/// Run the ask subcommand.
+pub async fn run(args: AskArgs, config: &Config, dry_run: bool) -> Result<()> {
+    let question = args.prompt.join(" ");
+    if question.trim().is_empty() {
+        bail!("No question given");
+    }
+    let context = if args.no_stdin {
+        None
+    } else {
+        read_stdin()?
+    };
+    let system = args.system.or_else(|| config.llm.system_prompt.clone());
+    let messages = build_messages(&question, context.as_deref(), system.as_deref());
+
+    let llm_config = args.model.resolve(config);
+    if let Some(context) = &context {
+        let size = tokens::estimate(context, llm_config.provider);
+        let window = tokens::context_window(llm_config.provider, &llm_config.model);
+        if size > window {
+            eprintln!(
+                "Warning: stdin is about {} tokens, more than {}'s {} token context window",
+                size, llm_config.model, window
+            );
+        }
+    }
+    let client = LlmClient::new(llm_config.clone());
+    if dry_run {
+        return print_dry_run(&client, messages);
+    }
+    check_connected(&llm_config)?;
+
+    let provider = llm_config.provider.storage_key();
+    let model = llm_config.model.as_str();
+    let request_bytes = messages.iter().map(|m| m.content.len()).sum();
+    let started = Instant::now();
+    let mut rx = client.stream_chat(messages);
+    let mut stdout = io::stdout().lock();
+    if args.json && !emit(&mut stdout, &AskEvent::Start { provider, model })? {
+        return Ok(());
+    }
+    let mut reply = String::new();
+    let mut first_token = None;
+    let mut finish = None;
+    let mut reported = None;
+    while let Some(event) = rx.recv().await {
+        let failure = match event {
+            StreamEvent::Token(token) => {
+                first_token.get_or_insert_with(|| started.elapsed());
+                reply.push_str(&token);
+                let open = if args.json {
+                    emit(&mut stdout, &AskEvent::Delta { text: &token })?
+                } else {
+                    write_out(&mut stdout, &token)?
+                };
+                if !open {
+                    return Ok(());
+                }
+                continue;
+            }
+            // Reasoning is only part of the JSON output
+            StreamEvent::Thinking(text) => {
+                if args.json && !emit(&mut stdout, &AskEvent::Thinking { text: &text })? {
+                    return Ok(());
+                }
+                continue;
+            }
+            // No tools are offered
+            StreamEvent::ToolCall(_) => continue,
+            StreamEvent::Finish(reason) => {
+                finish = Some(reason);
+                continue;
+            }
+            StreamEvent::Usage(usage) => {
+                reported = Some(usage);
+                continue;
+            }
+            // A one-off request has nothing to pace
+            StreamEvent::RateLimits(_) => continue,
+            StreamEvent::Retrying {
+                attempt,
+                max_retries,
+                delay,
+                reason,
+            } => {
+                if args.json {
+                    let event = AskEvent::Retrying {
+                        attempt,
+                        max_retries,
+                        delay_ms: delay.as_millis() as u64,
+                        reason: &reason,
+                    };
+                    if !emit(&mut stdout, &event)? {
+                        return Ok(());
+                    }
+                } else {
+                    eprintln!(
+                        "{}; retry {}/{} in {:.1}s",
+                        reason,
+                        attempt,
+                        max_retries,
+                        delay.as_secs_f64()
+                    );
+                }
+                continue;
+            }
+            StreamEvent::Done => break,
+            StreamEvent::Error(e) => e,
+            StreamEvent::AuthError => format!(
+                "{} rejected the stored credentials. Reconnect with `scry auth login`.",
+                client.display_name()
+            ),
+        };
+        if args.json {
+            emit(&mut stdout, &AskEvent::Error { message: &failure })?;
+        }
+        bail!(failure);
+    }
+
+    if args.json {
+        let mut usage =
+            UsageRecord::estimated(llm_config.provider, model, request_bytes, reply.len());
+        if let Some(reported) = reported {
+            usage = usage.with_usage(reported);
+        }
+        let usage = usage.priced(&Pricing::new(&config.pricing));
+        emit(
+            &mut stdout,
+            &AskEvent::Done {
+                message: &reply,
+                provider,
+                model,
+                finish_reason: finish.as_ref().map(finish_name),
+                usage: Usage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                    estimated: reported.is_none(),
+                    cost: usage.cost,
+                },
+                timing: Timing {
+                    first_token_ms: first_token.map(|t| t.as_millis() as u64),
+                    total_ms: started.elapsed().as_millis() as u64,
+                },
+            },
+        )?;
+        return Ok(());
+    }
+    if !reply.is_empty() && !reply.ends_with('\n') && !write_out(&mut stdout, "\n")? {
+        return Ok(());
+    }
+    match finish {
+        Some(FinishReason::Length) => eprintln!("Warning: the reply was cut off at the token limit"),
+        Some(FinishReason::ContentFilter) => {
+            eprintln!("Warning: the provider's content filter stopped the reply")
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A line of `--json` output.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AskEvent<'a> {
+    /// The request was sent
+    Start { provider: &'a str, model: &'a str },
+    /// A chunk of the answer
+    Delta { text: &'a str },
+    /// A chunk of the model's reasoning
+    Thinking { text: &'a str },
+    /// The request failed with a transient error and is sent again after the delay
+    Retrying {
+        attempt: u32,
+        max_retries: u32,
+        delay_ms: u64,
+        reason: &'a str,
+    },
+    /// The complete answer
+    Done {
+        message: &'a str,
+        provider: &'a str,
+        model: &'a str,
+        finish_reason: Option<String>,
+        usage: Usage,
+        timing: Timing,
+    },
+    /// The request failed; nothing follows
+    Error { message: &'a str },
+}
+
+/// Tokens used by the request, as the provider reported them or estimated
+/// from text sizes.
+#[derive(Debug, Serialize)]
+struct Usage {
+    input_tokens: u64,
+    output_tokens: u64,
+    estimated: bool,
+    /// In USD, where the price is known
+    cost: Option<f64>,
+}
+
+/// How long the reply took, from sending the request.
+#[derive(Debug, Serialize)]
+struct Timing {
+    first_token_ms: Option<u64>,
+    total_ms: u64,
+}
+
+/// Name of a finish reason in the JSON output.
+fn finish_name(reason: &FinishReason) -> String {
+    match reason {
+        FinishReason::Stop => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ContentFilter => "content_filter",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::Interrupted => "interrupted",
+        FinishReason::Other(other) => other,
+    }
+    .to_string()
+}
+
+/// Write `event` as a line of JSON. Returns false once the reader has gone away.
+fn emit(stdout: &mut impl Write, event: &AskEvent) -> Result<bool> {
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    write_out(stdout, &line)
+}
+
+/// Read piped stdin, if stdin is not a terminal.
+fn read_stdin() -> Result<Option<String>> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    stdin
+        .lock()
+        .read_to_end(&mut bytes)
+        .context("Failed to read stdin")?;
+    let text = String::from_utf8(bytes)
+        .context("stdin is not UTF-8 text; only text can be attached")?;
+    Ok(Some(text))
+}
+
+/// Write `text` to stdout right away. Returns false once the reader has
+/// gone away, which ends the command without an error.
+fn write_out(stdout: &mut impl Write, text: &str) -> Result<bool> {
+    match stdout.write_all(text.as_bytes()).and_then(|()| stdout.flush()) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `text` in a Markdown fence longer than any backtick run inside it, tagged
+/// `diff` when it looks like one.
+fn context_block(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    let is_diff = text.starts_with("diff --git")
+        || text.starts_with("--- ")
+        || (text.starts_with("From ") && text.contains("\ndiff --git"));
+    let language = if is_diff { "diff" } else { "" };
+    format!(
+        "{}\n{}{}\n{}\n{}",
+        CONTEXT_HEADING,
+        fence,
+        language,
+        text.trim_end_matches(['\r', '\n']),
+        fence
+    )
+}
+
+/// Build the request: the optional system prompt, then the question with any
+/// piped `context` attached below it.
+fn build_messages(question: &str, context: Option<&str>, system: Option<&str>) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    if let Some(system) = system.filter(|s| !s.trim().is_empty()) {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system.to_string(),
+            ..Default::default()
+        });
+    }
+    let content = match context.filter(|c| !c.trim().is_empty()) {
+        Some(context) => format!("{}\n\n{}", question.trim(), context_block(context)),
+        None => question.trim().to_string(),
+    };
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content,
+        ..Default::default()
+    });
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_messages_with_system_prompt_and_context() {
+        let messages = build_messages(" why? ", Some("it broke\n"), Some("Be terse."));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[1].content, "why?\n\nPiped input:\n```\nit broke\n```");
+
+        // Empty stdin and an empty system prompt add nothing
+        let messages = build_messages("why?", Some(" \n"), Some(""));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "why?");
+    }
+
+    #[test]
+    fn test_json_events_are_tagged_lines() {
+        let mut out = Vec::new();
+        emit(&mut out, &AskEvent::Delta { text: "Hi" }).unwrap();
+        emit(
+            &mut out,
+            &AskEvent::Done {
+                message: "Hi",
+                provider: "ollama",
+                model: "qwen3:4b",
+                finish_reason: Some(finish_name(&FinishReason::Length)),
+                usage: Usage {
+                    input_tokens: 3,
+                    output_tokens: 1,
+                    estimated: true,
+                    cost: Some(0.0),
+                },
+                timing: Timing {
+                    first_token_ms: None,
+                    total_ms: 12,
+                },
+            },
+        )
+        .unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0], serde_json::json!({"type": "delta", "text": "Hi"}));
+        assert_eq!(lines[1]["type"], "done");
+        assert_eq!(lines[1]["finish_reason"], "length");
+        assert_eq!(lines[1]["usage"]["output_tokens"], 1);
+        assert_eq!(lines[1]["timing"]["first_token_ms"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_context_block_fence_outlasts_backticks_and_tags_diffs() {
+        let block = context_block("diff --git a/x b/x\n+```rust\n");
+        assert_eq!(block, "Piped input:\n````diff\ndiff --git a/x b/x\n+```rust\n````");
+        assert!(context_block("plain").starts_with("Piped input:\n```\n"));
+    }
+}
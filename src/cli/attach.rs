@@ -0,0 +1,32 @@
+//! `scry attach` - join a conversation shared with `/share`.
+//!
+//! The chat UI follows the host's conversation; in a `/share write` session
+//! prompts typed here are sent to the host, which runs them under this
+//! participant's name. Nothing is saved or billed on this side.
+
+use anyhow::Result;
+
+use super::AttachArgs;
+use crate::app::App;
+use crate::config::Config;
+use crate::share::{self, ShareGuest, ShareMode};
+
+/// Connect to the host named in `args`.
+pub async fn connect(args: &AttachArgs, config: &Config) -> Result<ShareGuest> {
+    let name = args
+        .name
+        .clone()
+        .or_else(|| config.share.name.clone())
+        .unwrap_or_else(share::default_name);
+    ShareGuest::connect(&args.addr, &args.token, &name).await
+}
+
+/// Hand the connection to the app.
+pub fn attach(app: &mut App, guest: ShareGuest) {
+    let hint = match guest.mode {
+        ShareMode::CoWrite => "your prompts are sent to them",
+        ShareMode::ReadOnly => "read-only",
+    };
+    app.toast_info(format!("Joined {}'s session - {}", guest.host, hint));
+    app.guest = Some(guest);
+}
@@ -0,0 +1,17 @@
+//! `scry audit` - print the most recent audit log records.
+
+use anyhow::Result;
+
+use super::AuditArgs;
+use crate::audit::{format_records, AuditLog};
+
+/// Print the last `limit` records, oldest first.
+pub fn run(args: AuditArgs) -> Result<()> {
+    let records = AuditLog::open_default()?.read()?;
+    let skip = match args.limit {
+        0 => 0,
+        limit => records.len().saturating_sub(limit),
+    };
+    print!("{}", format_records(&records[skip..]));
+    Ok(())
+}
@@ -0,0 +1,381 @@
+//! `scry auth` - manage stored credentials without the chat UI.
+//!
+//! `scry auth login` runs the same sign-in flows as the connect dialog: the
+//! GitHub device code flow for Copilot, the browser authorization flow for
+//! Anthropic, and an API key prompt for the rest. Credentials go to
+//! `auth.json` (and the audit log), so setup scripts can provision them, e.g.
+//! `echo "$KEY" | scry auth login openai --key-stdin`. `scry auth status`
+//! shows where each provider's credentials come from and when tokens expire;
+//! `scry auth logout` removes them.
+
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+
+use super::{LoginArgs, LoginMethod, LogoutArgs};
+use crate::app::mask_api_key;
+use crate::auth::{
+    format_remaining, AnthropicAuthMethod, AnthropicOAuth, AuthStorage, Credential, DeviceCodeFlow,
+};
+use crate::config::Config;
+use crate::llm::{validate_api_key, Provider};
+
+/// Run `scry auth login`.
+pub async fn login(args: LoginArgs, config: &Config) -> Result<()> {
+    let provider = args.provider;
+    let method = login_method(provider, args.method, args.key_stdin)?;
+
+    let mut credential = match method {
+        LoginMethod::Device => device_login().await?,
+        LoginMethod::Claude => anthropic_login(AnthropicAuthMethod::ClaudeProMax).await?,
+        LoginMethod::Console => anthropic_login(AnthropicAuthMethod::CreateApiKey).await?,
+        LoginMethod::Key => {
+            let key = read_api_key(provider, args.key_stdin)?;
+            if !args.no_verify {
+                eprintln!("Checking the key with {}…", provider.display_name());
+                let scope = config.llm.scopes.get(provider.storage_key());
+                validate_api_key(provider, &key, scope, config.llm.signing.as_ref())
+                    .await
+                    .map_err(anyhow::Error::msg)?;
+            }
+            Credential::api_key(key)
+        }
+    };
+    if let Some(model) = args.model {
+        credential.set_model(model);
+    }
+
+    let mut storage = AuthStorage::load()?;
+    storage.set(provider.storage_key(), credential);
+    storage.save()?;
+    println!(
+        "Logged in to {}; credentials saved to {}",
+        provider.display_name(),
+        AuthStorage::default_path()?.display()
+    );
+    Ok(())
+}
+
+/// Run `scry auth logout`.
+pub fn logout(args: LogoutArgs) -> Result<()> {
+    let provider = args.provider;
+    let mut storage = AuthStorage::load()?;
+    if storage.remove(provider.storage_key()).is_none() {
+        println!("No stored credentials for {}", provider.display_name());
+        return Ok(());
+    }
+    storage.save()?;
+    println!("Logged out of {}", provider.display_name());
+    Ok(())
+}
+
+/// Run `scry auth status`: how each provider would authenticate right now.
+pub fn status(config: &Config) -> Result<()> {
+    let storage = AuthStorage::load()?;
+    println!("Stored credentials: {}", AuthStorage::default_path()?.display());
+    let now = Utc::now();
+    let width = Provider::all()
+        .iter()
+        .map(|provider| provider.display_name().len())
+        .max()
+        .unwrap_or(0);
+    for &provider in Provider::all() {
+        let env_var = match provider.env_var_name() {
+            "" => None,
+            var => std::env::var(var).ok().filter(|key| !key.is_empty()).map(|_| var),
+        };
+        // Only Anthropic reads `[llm] api_key`
+        let in_config = provider == Provider::Anthropic
+            && config.llm.api_key.as_deref().is_some_and(|key| !key.is_empty());
+        println!(
+            "  {:<width$}  {}",
+            provider.display_name(),
+            status_line(provider, env_var, in_config, storage.get(provider.storage_key()), now)
+        );
+    }
+    Ok(())
+}
+
+/// Where `provider`'s credentials come from, in the order requests look for
+/// them: the environment, the config file, then `auth.json`.
+fn status_line(
+    provider: Provider,
+    env_var: Option<&str>,
+    in_config: bool,
+    stored: Option<&Credential>,
+    now: DateTime<Utc>,
+) -> String {
+    if matches!(provider, Provider::Ollama | Provider::LocalServer) {
+        return "runs locally, no login needed".to_string();
+    }
+    if let Some(var) = env_var {
+        return format!("API key from ${}", var);
+    }
+    if in_config {
+        return "API key from the config file".to_string();
+    }
+    let Some(credential) = stored else {
+        return format!("not connected (scry auth login {})", provider.storage_key());
+    };
+    let mut line = match credential {
+        Credential::ApiKey { key, .. } => format!("API key {}", mask_api_key(key)),
+        Credential::OAuth { expires_at: None, .. } => "signed in".to_string(),
+        Credential::OAuth {
+            expires_at: Some(expires_at),
+            refresh_token: Some(_),
+            ..
+        } if *expires_at <= now => "sign-in expired, renewed when the chat UI starts".to_string(),
+        Credential::OAuth {
+            expires_at: Some(expires_at),
+            ..
+        } if *expires_at <= now => format!(
+            "sign-in expired (scry auth login {})",
+            provider.storage_key()
+        ),
+        Credential::OAuth {
+            expires_at: Some(expires_at),
+            ..
+        } => format!("signed in, expires in {}", format_remaining(*expires_at - now)),
+    };
+    if let Some(model) = credential.model() {
+        line.push_str(&format!(" · model {}", model));
+    }
+    line
+}
+
+/// The sign-in flow for `provider`: `method` if it applies, otherwise the
+/// provider's usual one.
+fn login_method(
+    provider: Provider,
+    method: Option<LoginMethod>,
+    key_stdin: bool,
+) -> Result<LoginMethod> {
+    if matches!(provider, Provider::Ollama | Provider::LocalServer) {
+        bail!(
+            "{} runs locally and needs no login",
+            provider.display_name()
+        );
+    }
+    let method = match method {
+        Some(method) => method,
+        None if key_stdin => LoginMethod::Key,
+        None => match provider {
+            Provider::GitHubCopilot => LoginMethod::Device,
+            Provider::Anthropic => LoginMethod::Claude,
+            _ => LoginMethod::Key,
+        },
+    };
+    let fits = match method {
+        LoginMethod::Device => provider == Provider::GitHubCopilot,
+        LoginMethod::Claude | LoginMethod::Console => provider == Provider::Anthropic,
+        LoginMethod::Key => provider != Provider::GitHubCopilot,
+    };
+    if !fits {
+        bail!(
+            "{} does not support --method {}",
+            provider.display_name(),
+            method.name()
+        );
+    }
+    if key_stdin && method != LoginMethod::Key {
+        bail!("--key-stdin only applies to --method key");
+    }
+    Ok(method)
+}
+
+/// Sign in to GitHub Copilot with the device code flow.
+async fn device_login() -> Result<Credential> {
+    let flow = DeviceCodeFlow::github_copilot();
+    let code = flow.request_device_code().await?;
+    let url = code
+        .verification_uri_complete
+        .as_deref()
+        .unwrap_or(&code.verification_uri);
+    eprintln!("Open {} and enter the code {}", url, code.user_code);
+    // Best effort: the URL is printed for headless machines
+    let _ = DeviceCodeFlow::open_browser(url);
+
+    eprint!("Waiting for authorization");
+    let token = flow
+        .poll_for_token(&code, || {
+            eprint!(".");
+            let _ = io::stderr().flush();
+        })
+        .await;
+    eprintln!();
+    let token = token?;
+    Ok(Credential::oauth(
+        &token.access_token,
+        token.refresh_token.clone(),
+        token.expires_at(),
+        None,
+    ))
+}
+
+/// Sign in to Anthropic in the browser and paste back the code it shows.
+async fn anthropic_login(method: AnthropicAuthMethod) -> Result<Credential> {
+    let oauth = AnthropicOAuth::new(method)?;
+    eprintln!(
+        "Open this URL to authorize scry:\n\n{}\n",
+        oauth.build_auth_url()
+    );
+    let _ = oauth.open_browser();
+
+    eprint!("Paste the authorization code: ");
+    io::stderr().flush()?;
+    let mut code = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut code)
+        .context("Failed to read the authorization code")?;
+    let token = oauth.exchange_code(code.trim()).await?;
+
+    Ok(match method {
+        AnthropicAuthMethod::CreateApiKey => {
+            Credential::api_key(AnthropicOAuth::convert_to_api_key(&token.access_token).await?)
+        }
+        AnthropicAuthMethod::ClaudeProMax => Credential::oauth(
+            &token.access_token,
+            token.refresh_token.clone(),
+            token.expires_at(),
+            None,
+        ),
+    })
+}
+
+/// Read an API key from stdin, without echoing it when typed at a terminal.
+fn read_api_key(provider: Provider, from_stdin: bool) -> Result<String> {
+    let stdin = io::stdin();
+    let key = if from_stdin || !stdin.is_terminal() {
+        let mut key = String::new();
+        stdin
+            .lock()
+            .read_to_string(&mut key)
+            .context("Failed to read the API key from stdin")?;
+        key
+    } else {
+        eprint!("{} API key: ", provider.display_name());
+        io::stderr().flush()?;
+        enable_raw_mode()?;
+        let key = read_hidden_line();
+        disable_raw_mode()?;
+        eprintln!();
+        key?
+    };
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        bail!("No API key given");
+    }
+    Ok(key)
+}
+
+/// Collect typed characters until Enter, in raw mode so nothing is echoed.
+fn read_hidden_line() -> Result<String> {
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(line),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                bail!("Cancelled")
+            }
+            KeyCode::Char(c) => line.push(c),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Esc => bail!("Cancelled"),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_login_method_defaults_and_checks() {
+        let method = |provider, method, key_stdin| login_method(provider, method, key_stdin);
+        assert_eq!(
+            method(Provider::GitHubCopilot, None, false).unwrap(),
+            LoginMethod::Device
+        );
+        assert_eq!(
+            method(Provider::Anthropic, None, false).unwrap(),
+            LoginMethod::Claude
+        );
+        assert_eq!(
+            method(Provider::Anthropic, None, true).unwrap(),
+            LoginMethod::Key
+        );
+        assert_eq!(
+            method(Provider::OpenAI, None, false).unwrap(),
+            LoginMethod::Key
+        );
+
+        assert!(method(Provider::Ollama, None, false).is_err());
+        assert!(method(Provider::GitHubCopilot, Some(LoginMethod::Key), false).is_err());
+        assert!(method(Provider::OpenAI, Some(LoginMethod::Console), false).is_err());
+        assert!(method(Provider::Anthropic, Some(LoginMethod::Claude), true).is_err());
+    }
+
+    #[test]
+    fn test_status_line_follows_lookup_order() {
+        let now = Utc::now();
+        let key = Credential::api_key("sk-abcdefgh1234");
+        assert_eq!(
+            status_line(Provider::OpenAI, Some("OPENAI_API_KEY"), false, Some(&key), now),
+            "API key from $OPENAI_API_KEY"
+        );
+        assert_eq!(
+            status_line(Provider::OpenAI, None, false, Some(&key), now),
+            "API key sk-a...1234"
+        );
+        assert_eq!(
+            status_line(Provider::Groq, None, false, None, now),
+            "not connected (scry auth login groq)"
+        );
+        assert_eq!(
+            status_line(Provider::Ollama, None, false, None, now),
+            "runs locally, no login needed"
+        );
+
+        let token = |hours| {
+            Credential::oauth(
+                "token",
+                None,
+                Some(now + Duration::hours(hours)),
+                Some("gpt-4o".to_string()),
+            )
+        };
+        assert_eq!(
+            status_line(Provider::GitHubCopilot, None, false, Some(&token(5)), now),
+            "signed in, expires in 5h · model gpt-4o"
+        );
+        assert_eq!(
+            status_line(Provider::GitHubCopilot, None, false, Some(&token(-1)), now),
+            "sign-in expired (scry auth login github_copilot) · model gpt-4o"
+        );
+        let renewable = Credential::oauth(
+            "token",
+            Some("refresh".to_string()),
+            Some(now - Duration::hours(1)),
+            None,
+        );
+        assert_eq!(
+            status_line(Provider::Anthropic, None, false, Some(&renewable), now),
+            "sign-in expired, renewed when the chat UI starts"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! `scry filter` - use the model as a text filter.
+//!
+//! Reads the whole of stdin, asks the model to transform it according to the
+//! prompt, and writes only the transformed text to stdout. Nothing else is
+//! printed on success, so the command can be used as a vim/emacs filter.
+
+use std::io::{self, Read, Write};
+
+use anyhow::{bail, Context, Result};
+
+use super::{
+    collect_response, match_trailing_newline, print_dry_run, strip_code_fence, FilterArgs,
+};
+use crate::config::Config;
+use crate::llm::{ChatMessage, LlmClient};
+
+/// Instructions sent as the system prompt for filter requests.
+const FILTER_SYSTEM_PROMPT: &str = "You are a text filter. Apply the user's instruction to the \
+text they provide and reply with the transformed text only. Do not add explanations, \
+commentary, or surrounding quotes, and preserve the original formatting unless the \
+instruction says otherwise.";
+
+/// Run the filter subcommand.
+pub async fn run(args: FilterArgs, config: &Config, dry_run: bool) -> Result<()> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .context("Failed to read stdin")?;
+
+    let llm_config = args.model.resolve(config);
+    let client = LlmClient::new(llm_config);
+    if dry_run {
+        return print_dry_run(&client, build_messages(&args.prompt, &input));
+    }
+    if !client.is_configured() {
+        bail!(
+            "{} is not configured. Connect it from the chat UI or set {}.",
+            client.display_name(),
+            client.provider_type().env_var_name()
+        );
+    }
+
+    let response = collect_response(&client, build_messages(&args.prompt, &input)).await?;
+
+    let output = if args.keep_fences {
+        response
+    } else {
+        strip_code_fence(&response).to_string()
+    };
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(match_trailing_newline(&input, &output).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Build the request messages for a filter invocation.
+fn build_messages(prompt: &str, input: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: FILTER_SYSTEM_PROMPT.to_string(),
+            ..Default::default()
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: format!("Instruction: {}\n\nText:\n{}", prompt.trim(), input),
+            ..Default::default()
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_messages_includes_prompt_and_input() {
+        let messages = build_messages("  fix grammar ", "teh cat\n");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[1].role, "user");
+        assert!(messages[1].content.starts_with("Instruction: fix grammar\n"));
+        assert!(messages[1].content.ends_with("teh cat\n"));
+    }
+}
@@ -0,0 +1,527 @@
+//! Command-line interface for scry.
+//!
+//! Running `scry` with no subcommand starts the chat TUI. Subcommands provide
+//! non-interactive entry points that are usable from scripts and editors:
+//! - `scry ask` - Stream the answer to one question to stdout
+//! - `scry filter` - Transform stdin through the model and print the result
+//! - `scry rewrite` - Revise a file and review the changes hunk by hunk
+//! - `scry replay` - Replay a recorded response stream through the chat UI
+//! - `scry usage` - Summarize recorded usage for a month
+//! - `scry audit` - Show the audit log of credential and tool actions
+//! - `scry serve` - Serve saved sessions to a browser, read-only by default
+//! - `scry attach` - Join a conversation shared with `/share`
+//! - `scry auth` - Log in to providers, check stored credentials, or log out
+//! - `scry models` / `scry use` - List a provider's models or pick the default one
+
+pub mod ask;
+pub mod attach;
+pub mod audit;
+pub mod auth;
+pub mod filter;
+pub mod models;
+pub mod replay;
+pub mod rewrite;
+pub mod serve;
+pub mod usage;
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::config::Config;
+use crate::llm::{
+    unsupported_hint, Capability, ChatMessage, LlmClient, LlmConfig, Provider, StreamEvent,
+};
+
+/// Top-level command-line arguments.
+#[derive(Debug, Parser)]
+#[command(name = "scry", version, about = "A beautiful terminal-based chat interface")]
+pub struct Cli {
+    /// Subcommand to run (starts the chat UI when omitted)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Record raw provider response streams into this directory
+    #[arg(long, global = true, value_name = "DIR")]
+    pub record: Option<PathBuf>,
+    /// Show the provider request each submit would make instead of sending it
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Use this profile's config and credentials instead of the main ones
+    #[arg(long, global = true, env = "SCRY_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Print event loop wake-ups and frame draw times on exit
+    #[arg(long)]
+    pub frame_stats: bool,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Overrides for one run of the chat UI; the config file is left as it is.
+#[derive(Debug, Clone, Default, Args)]
+pub struct RunArgs {
+    /// Provider to chat with; it must be connected or have its API key set
+    #[arg(long)]
+    pub provider: Option<Provider>,
+    /// Model to use instead of the configured or saved one
+    #[arg(long, short)]
+    pub model: Option<String>,
+    /// System prompt to use instead of `[llm] system_prompt`
+    #[arg(long, value_name = "PROMPT")]
+    pub system: Option<String>,
+    /// Resume the saved session with this id
+    #[arg(long, value_name = "ID")]
+    pub session: Option<String>,
+}
+
+/// Available subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Ask one question and stream the answer to stdout, without the chat UI.
+    ///
+    /// Piped stdin is sent along as context, e.g. `git diff | scry ask "review this"`.
+    Ask(AskArgs),
+    /// Read stdin, transform it with the model, and write only the result to stdout.
+    ///
+    /// Designed for editor filter commands, e.g. `:%!scry filter --prompt "fix grammar"`.
+    Filter(FilterArgs),
+    /// Ask the model to revise a file, then accept or reject each hunk before writing.
+    Rewrite(RewriteArgs),
+    /// Replay a stream recorded with `--record` through the chat UI (for debugging parsers).
+    Replay(ReplayArgs),
+    /// Summarize requests, tokens and cost per model for a month.
+    Usage(UsageArgs),
+    /// Show recent credential changes and tool actions from the audit log.
+    Audit(AuditArgs),
+    /// Serve a web dashboard for browsing saved sessions (read-only by default).
+    Serve(ServeArgs),
+    /// Join a conversation another scry shares with `/share`.
+    Attach(AttachArgs),
+    /// Log in to providers, show stored credentials, or log out, without the chat UI.
+    Auth(AuthArgs),
+    /// List the models a provider offers.
+    Models(ModelsArgs),
+    /// Make a provider and model the default for the chat UI and subcommands.
+    Use(UseArgs),
+    /// Same as `scry auth login`.
+    #[command(hide = true)]
+    Login(LoginArgs),
+    /// Same as `scry auth logout`.
+    #[command(hide = true)]
+    Logout(LogoutArgs),
+}
+
+/// Model selection flags shared by the non-interactive subcommands.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ModelArgs {
+    /// Provider to use (anthropic, github_copilot, openai, groq, deepseek, openrouter, ollama, local_server)
+    #[arg(long)]
+    pub provider: Option<Provider>,
+    /// Model to use (defaults to the configured or saved model)
+    #[arg(long, short)]
+    pub model: Option<String>,
+    /// Sampling temperature
+    #[arg(long, short)]
+    pub temperature: Option<f32>,
+}
+
+/// Arguments for `scry ask`.
+#[derive(Debug, Clone, Args)]
+pub struct AskArgs {
+    /// The question (several words are joined with spaces)
+    #[arg(required = true)]
+    pub prompt: Vec<String>,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    /// System prompt to use instead of `[llm] system_prompt`
+    #[arg(long, value_name = "PROMPT")]
+    pub system: Option<String>,
+    /// Don't read stdin, even when it is not a terminal
+    #[arg(long)]
+    pub no_stdin: bool,
+    /// Print JSON lines: streamed deltas, then the message with model, usage,
+    /// finish reason and timing
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for `scry filter`.
+#[derive(Debug, Clone, Args)]
+pub struct FilterArgs {
+    /// Instruction describing how to transform the input
+    #[arg(long, short)]
+    pub prompt: String,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    /// Keep a surrounding Markdown code fence if the model adds one
+    #[arg(long)]
+    pub keep_fences: bool,
+}
+
+/// Arguments for `scry rewrite`.
+#[derive(Debug, Clone, Args)]
+pub struct RewriteArgs {
+    /// File to rewrite
+    pub file: PathBuf,
+    /// Instruction describing how to change the file
+    #[arg(long, short)]
+    pub prompt: String,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    /// Accept every hunk without the interactive review
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+/// Arguments for `scry replay`.
+#[derive(Debug, Clone, Args)]
+pub struct ReplayArgs {
+    /// Recording file to replay
+    pub file: PathBuf,
+    /// Playback speed relative to the original timing (0 replays instantly)
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f32,
+}
+
+/// Arguments for `scry usage`.
+#[derive(Debug, Clone, Args)]
+pub struct UsageArgs {
+    /// Month to report, as YYYY-MM (defaults to the current month)
+    #[arg(long)]
+    pub month: Option<String>,
+}
+
+/// Arguments for `scry audit`.
+#[derive(Debug, Clone, Args)]
+pub struct AuditArgs {
+    /// Number of most recent records to show (0 shows all)
+    #[arg(long, short = 'n', default_value_t = 50)]
+    pub limit: usize,
+}
+
+/// Arguments for `scry serve`.
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// Address to listen on; use 0.0.0.0:8765 to reach it from the LAN
+    #[arg(long, default_value = "127.0.0.1:8765")]
+    pub bind: String,
+    /// Access token for the API (a random one is generated by default)
+    #[arg(long, env = "SCRY_SERVE_TOKEN")]
+    pub token: Option<String>,
+    /// Allow deleting sessions through the API
+    #[arg(long)]
+    pub writable: bool,
+}
+
+/// Arguments for `scry attach`.
+#[derive(Debug, Clone, Args)]
+pub struct AttachArgs {
+    /// Host and port shown by `/share`, e.g. 192.168.1.20:8766
+    pub addr: String,
+    /// Token shown by `/share`
+    #[arg(long, env = "SCRY_SHARE_TOKEN")]
+    pub token: String,
+    /// Name your prompts are attributed to (defaults to `[share] name` or the login name)
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+/// Arguments for `scry models`.
+#[derive(Debug, Clone, Args)]
+pub struct ModelsArgs {
+    /// Provider to list (defaults to the configured one)
+    pub provider: Option<Provider>,
+}
+
+/// Arguments for `scry use`.
+#[derive(Debug, Clone, Args)]
+pub struct UseArgs {
+    /// Provider to start with
+    pub provider: Provider,
+    /// Model to use with it
+    pub model: String,
+    /// Save the model without checking the provider's model list
+    #[arg(long)]
+    pub no_verify: bool,
+}
+
+/// Arguments for `scry auth`.
+#[derive(Debug, Clone, Args)]
+pub struct AuthArgs {
+    #[command(subcommand)]
+    pub command: AuthCommand,
+}
+
+/// Subcommands of `scry auth`.
+#[derive(Debug, Clone, Subcommand)]
+pub enum AuthCommand {
+    /// Sign in to a provider and store its credentials.
+    Login(LoginArgs),
+    /// Show where each provider's credentials come from and when they expire.
+    Status,
+    /// Remove a provider's stored credentials.
+    Logout(LogoutArgs),
+}
+
+/// Arguments for `scry auth login`.
+#[derive(Debug, Clone, Args)]
+pub struct LoginArgs {
+    /// Provider to sign in to (anthropic, github_copilot, openai, groq, deepseek, openrouter)
+    pub provider: Provider,
+    /// How to sign in (default: device for Copilot, claude for Anthropic, key otherwise)
+    #[arg(long, value_enum)]
+    pub method: Option<LoginMethod>,
+    /// Read the API key from stdin instead of prompting for it
+    #[arg(long)]
+    pub key_stdin: bool,
+    /// Store the API key without checking it with the provider
+    #[arg(long)]
+    pub no_verify: bool,
+    /// Model to select when the provider is next connected
+    #[arg(long, short)]
+    pub model: Option<String>,
+}
+
+/// Sign-in flows for `scry auth login`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LoginMethod {
+    /// Paste an API key
+    Key,
+    /// GitHub device code (Copilot)
+    Device,
+    /// Claude Pro/Max subscription in the browser (Anthropic)
+    Claude,
+    /// Create an API key through the Anthropic Console in the browser
+    Console,
+}
+
+impl LoginMethod {
+    /// Name as written on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoginMethod::Key => "key",
+            LoginMethod::Device => "device",
+            LoginMethod::Claude => "claude",
+            LoginMethod::Console => "console",
+        }
+    }
+}
+
+/// Arguments for `scry auth logout`.
+#[derive(Debug, Clone, Args)]
+pub struct LogoutArgs {
+    /// Provider whose credentials to remove
+    pub provider: Provider,
+}
+
+impl ModelArgs {
+    /// Resolve the LLM configuration for this invocation.
+    ///
+    /// Starts from the config file and stored credentials for the chosen (or
+    /// configured) provider, then applies the `--model` and `--temperature` overrides.
+    pub fn resolve(&self, config: &Config) -> LlmConfig {
+        let provider = self
+            .provider
+            .or_else(|| config.llm.default_provider())
+            .unwrap_or_default();
+        let mut llm_config = LlmConfig::for_provider(provider, Some(&config.llm));
+        if let Some(model) = &self.model {
+            llm_config.model = model.clone();
+        }
+        if let Some(temperature) = self.temperature {
+            match unsupported_hint(provider, &llm_config.model, Capability::Temperature) {
+                Some(hint) => eprintln!("Warning: {}; --temperature is ignored", hint),
+                None => llm_config.temperature = Some(temperature),
+            }
+        }
+        llm_config
+    }
+}
+
+impl RunArgs {
+    /// The LLM configuration `--provider` and `--model` (or `[llm] provider`)
+    /// ask for, or `None` to keep the Anthropic one.
+    ///
+    /// Fails if a provider given with `--provider` has no stored credentials or
+    /// API key; a configured one that isn't connected is passed over.
+    pub fn resolve(&self, config: &Config) -> Result<Option<LlmConfig>> {
+        let configured = config
+            .llm
+            .default_provider()
+            .map(|provider| LlmConfig::for_provider(provider, Some(&config.llm)))
+            .filter(LlmConfig::is_configured);
+        let mut llm_config = match (self.provider, configured) {
+            (Some(provider), _) => LlmConfig::for_provider(provider, Some(&config.llm)),
+            (None, Some(configured)) => configured,
+            (None, None) if self.model.is_some() => {
+                LlmConfig::for_provider(Provider::default(), Some(&config.llm))
+            }
+            (None, None) => return Ok(None),
+        };
+        check_connected(&llm_config)?;
+        if let Some(model) = &self.model {
+            llm_config.model = model.clone();
+        }
+        Ok(Some(llm_config))
+    }
+}
+
+/// Fail with a hint on how to connect if `config` has no credentials.
+pub fn check_connected(config: &LlmConfig) -> Result<()> {
+    if config.is_configured() {
+        return Ok(());
+    }
+    let provider = config.provider;
+    bail!(
+        "{} is not connected. Connect it from the chat UI (Ctrl+P) or set {}.",
+        provider.display_name(),
+        provider.env_var_name()
+    )
+}
+
+/// Print the request `client` would make for `messages`, without sending it.
+pub fn print_dry_run(client: &LlmClient, messages: Vec<ChatMessage>) -> Result<()> {
+    let preview = client
+        .preview_request(messages, &[])
+        .context("This provider makes no request to show")?;
+    println!("{}", preview);
+    Ok(())
+}
+
+/// Send a request and wait for the complete response text.
+///
+/// Returns an error if the provider reports one or the credentials are rejected.
+pub async fn collect_response(client: &LlmClient, messages: Vec<ChatMessage>) -> Result<String> {
+    let mut rx = client.stream_chat(messages);
+    let mut response = String::new();
+
+    while let Some(event) = rx.recv().await {
+        match event {
+            StreamEvent::Token(token) => response.push_str(&token),
+            // Only the answer is printed
+            StreamEvent::Thinking(_) => {}
+            // No tools are offered by CLI requests
+            StreamEvent::ToolCall(_) => {}
+            StreamEvent::Finish(_) | StreamEvent::Usage(_) | StreamEvent::RateLimits(_) => {}
+            StreamEvent::Retrying {
+                attempt,
+                max_retries,
+                delay,
+                reason,
+            } => eprintln!(
+                "{}; retry {}/{} in {:.1}s",
+                reason,
+                attempt,
+                max_retries,
+                delay.as_secs_f64()
+            ),
+            StreamEvent::Done => break,
+            StreamEvent::Error(e) => bail!(e),
+            StreamEvent::AuthError => bail!(
+                "{} rejected the stored credentials. Reconnect from the chat UI menu.",
+                client.display_name()
+            ),
+        }
+    }
+
+    Ok(response)
+}
+
+/// Remove a single Markdown code fence wrapping the whole response.
+///
+/// Models often wrap their answer in ```lang ... ``` even when told not to,
+/// which would otherwise end up inside the user's buffer.
+pub(crate) fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return text;
+    };
+    let Some(body) = rest.strip_suffix("```") else {
+        return text;
+    };
+    // Skip the info string (e.g. "rust") on the opening fence line
+    match body.find('\n') {
+        Some(newline) => body[newline + 1..].trim_end_matches([' ', '\t']),
+        None => text,
+    }
+}
+
+/// Make the output end with a newline exactly when the input did.
+pub(crate) fn match_trailing_newline(input: &str, output: &str) -> String {
+    let body = output.trim_end_matches(['\r', '\n']);
+    if input.ends_with('\n') {
+        format!("{}\n", body)
+    } else {
+        body.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_code_fence_with_language() {
+        let text = "```rust\nfn main() {}\n```";
+        assert_eq!(strip_code_fence(text), "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_strip_code_fence_without_language() {
+        let text = "\n```\nhello\nworld\n```\n";
+        assert_eq!(strip_code_fence(text), "hello\nworld\n");
+    }
+
+    #[test]
+    fn test_strip_code_fence_leaves_plain_text() {
+        assert_eq!(strip_code_fence("just text"), "just text");
+        assert_eq!(strip_code_fence("```inline```"), "```inline```");
+    }
+
+    #[test]
+    fn test_strip_code_fence_leaves_inner_fences() {
+        let text = "Intro\n```\ncode\n```";
+        assert_eq!(strip_code_fence(text), text);
+    }
+
+    #[test]
+    fn test_run_args_check_credentials() {
+        let config = Config::default();
+        assert!(RunArgs::default().resolve(&config).unwrap().is_none());
+
+        // Ollama needs no key
+        let args = RunArgs {
+            provider: Some(Provider::Ollama),
+            model: Some("qwen2.5".to_string()),
+            ..RunArgs::default()
+        };
+        let llm_config = args.resolve(&config).unwrap().unwrap();
+        assert_eq!(llm_config.provider, Provider::Ollama);
+        assert_eq!(llm_config.model, "qwen2.5");
+
+        // A configured provider is used without flags
+        let mut config = Config::default();
+        config.llm.provider = Some("ollama".to_string());
+        config.llm.models.insert("ollama".to_string(), "qwen3:4b".to_string());
+        let llm_config = RunArgs::default().resolve(&config).unwrap().unwrap();
+        assert_eq!(llm_config.provider, Provider::Ollama);
+        assert_eq!(llm_config.model, "qwen3:4b");
+
+        let error = check_connected(&LlmConfig {
+            provider: Provider::OpenRouter,
+            ..LlmConfig::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "OpenRouter is not connected. Connect it from the chat UI (Ctrl+P) or set OPENROUTER_API_KEY."
+        );
+    }
+
+    #[test]
+    fn test_match_trailing_newline() {
+        assert_eq!(match_trailing_newline("a\n", "b"), "b\n");
+        assert_eq!(match_trailing_newline("a\n", "b\n\n"), "b\n");
+        assert_eq!(match_trailing_newline("a", "b\n"), "b");
+    }
+}
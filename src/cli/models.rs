@@ -0,0 +1,167 @@
+//! `scry models` and `scry use` - list a provider's models and pick the default.
+//!
+//! `scry models` asks the provider for its model list, as the model picker
+//! does, and marks the model scry would use. `scry use <provider> <model>`
+//! makes that pair the default for the chat UI and the subcommands: it sets
+//! `[llm] provider` and the provider's model in `config.toml`, and the model
+//! saved with the provider's credentials, so setup scripts can pick both.
+
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use super::{check_connected, ModelArgs, ModelsArgs, UseArgs};
+use crate::auth::AuthStorage;
+use crate::config::Config;
+use crate::durable::{self, WriteOptions};
+use crate::llm::{LlmClient, LlmConfig, ModelInfo, Provider};
+
+/// Run `scry models`.
+pub async fn list(args: ModelsArgs, config: &Config) -> Result<()> {
+    let llm_config = ModelArgs {
+        provider: args.provider,
+        ..ModelArgs::default()
+    }
+    .resolve(config);
+    check_connected(&llm_config)?;
+    let provider = llm_config.provider;
+    let mut models = fetch_models(&llm_config).await?;
+    if models.is_empty() {
+        eprintln!(
+            "{} did not list its models; showing its defaults",
+            provider.display_name()
+        );
+        models = provider.fallback_models();
+    }
+
+    let width = models.iter().map(|m| m.id.len()).max().unwrap_or(0);
+    for model in &models {
+        let marker = if model.id == llm_config.model { '*' } else { ' ' };
+        if model.name == model.id {
+            println!("{} {}", marker, model.id);
+        } else {
+            println!("{} {:<width$}  {}", marker, model.id, model.name);
+        }
+    }
+    Ok(())
+}
+
+/// Run `scry use`.
+pub async fn use_model(args: UseArgs, config: &Config) -> Result<()> {
+    let provider = args.provider;
+    if !args.no_verify {
+        let llm_config = LlmConfig::for_provider(provider, Some(&config.llm));
+        check_connected(&llm_config)?;
+        let models = fetch_models(&llm_config).await?;
+        // Some servers list nothing; there's nothing to check against then
+        if !models.is_empty() && !models.iter().any(|m| m.id == args.model) {
+            bail!(
+                "{} has no model '{}'. List them with `scry models {}`, or pass --no-verify.",
+                provider.display_name(),
+                args.model,
+                provider.storage_key()
+            );
+        }
+    }
+
+    let path = Config::default_path().context("Could not determine the config directory")?;
+    save_default(&path, provider, &args.model)?;
+    // A model saved with the credentials wins over the config file
+    let mut storage = AuthStorage::load()?;
+    if let Some(mut credential) = storage.get(provider.storage_key()).cloned() {
+        credential.set_model(&args.model);
+        storage.set(provider.storage_key(), credential);
+        storage.save()?;
+    }
+    println!(
+        "Using {} with {}; saved to {}",
+        args.model,
+        provider.display_name(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// The models `config`'s provider lists.
+async fn fetch_models(config: &LlmConfig) -> Result<Vec<ModelInfo>> {
+    LlmClient::new(config.clone())
+        .list_models()
+        .await
+        .with_context(|| format!("Could not list {} models", config.provider.display_name()))
+}
+
+/// Make `provider` and `model` the defaults in the config file at `path`.
+///
+/// Only `[llm] provider` and the model are touched; the file is rewritten, so
+/// other settings are kept but comments are not. Anthropic's model is
+/// `[llm] model`, the others' go under `[llm.models]`.
+fn save_default(path: &Path, provider: Provider, model: &str) -> Result<()> {
+    let mut table = match std::fs::read_to_string(path) {
+        Ok(text) => text
+            .parse::<toml::Table>()
+            .with_context(|| format!("Could not parse {}", path.display()))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    let llm = table
+        .entry("llm")
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .context("`llm` in the config file is not a table")?;
+    llm.insert("provider".to_string(), provider.storage_key().into());
+    if provider == Provider::Anthropic {
+        llm.insert("model".to_string(), model.into());
+    } else {
+        llm.entry("models")
+            .or_insert_with(|| toml::Table::new().into())
+            .as_table_mut()
+            .context("`llm.models` in the config file is not a table")?
+            .insert(provider.storage_key().to_string(), model.into());
+    }
+
+    let contents = toml::to_string_pretty(&table)?;
+    // Don't leave a config behind that scry can't load
+    toml::from_str::<Config>(&contents)
+        .with_context(|| format!("{} would no longer load", path.display()))?;
+    durable::write(path, contents.as_bytes(), WriteOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_default_keeps_other_settings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        save_default(&path, Provider::Ollama, "qwen3:4b").unwrap();
+        std::fs::write(
+            &path,
+            format!("{}\n[behavior]\nminimap = true\n", std::fs::read_to_string(&path).unwrap()),
+        )
+        .unwrap();
+        save_default(&path, Provider::OpenAI, "gpt-4o").unwrap();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(config.llm.default_provider(), Some(Provider::OpenAI));
+        assert_eq!(config.llm.models["openai"], "gpt-4o");
+        assert_eq!(config.llm.models["ollama"], "qwen3:4b");
+        assert!(config.behavior.minimap);
+
+        save_default(&path, Provider::Anthropic, "claude-haiku-4-5").unwrap();
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(config.llm.default_provider(), Some(Provider::Anthropic));
+        assert_eq!(config.llm.model, "claude-haiku-4-5");
+    }
+
+    #[test]
+    fn test_save_default_refuses_a_broken_config() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "llm = 3\n").unwrap();
+        assert!(save_default(&path, Provider::OpenAI, "gpt-4o").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "llm = 3\n");
+    }
+}
@@ -0,0 +1,30 @@
+//! `scry replay` - push a recorded response stream through the chat UI.
+//!
+//! Recordings are made with `scry --record <dir>` (or `SCRY_RECORD_DIR`). The
+//! replayed stream goes through the same provider parser as a live response,
+//! so parsing bugs can be reproduced without network access.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use super::ReplayArgs;
+use crate::app::App;
+use crate::llm::recording::{Recording, ReplayProvider};
+use crate::llm::LlmClient;
+
+/// Load the recording and build a client that replays it on every message.
+pub fn load(args: &ReplayArgs) -> Result<LlmClient> {
+    let recording = Recording::load(&args.file)?;
+    Ok(LlmClient::from_provider(Arc::new(ReplayProvider::new(
+        recording, args.speed,
+    ))))
+}
+
+/// Swap the app's client for the replay client.
+pub fn attach(app: &mut App, client: LlmClient) {
+    app.llm.config.provider = client.provider_type();
+    app.llm.config.model = client.model().to_string();
+    app.llm.set_client(client);
+    app.toast_info("Replay loaded - send any message to play it");
+}
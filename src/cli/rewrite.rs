@@ -0,0 +1,165 @@
+//! `scry rewrite` - model-assisted file edits with hunk-by-hunk review.
+//!
+//! Sends a file and an instruction to the model, diffs the revised file
+//! against the original, and lets the user accept or reject each hunk in a
+//! small TUI before writing the result back.
+
+use std::fs;
+use std::io;
+
+use anyhow::{bail, Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+
+use super::{
+    collect_response, match_trailing_newline, print_dry_run, strip_code_fence, RewriteArgs,
+};
+use crate::config::Config;
+use crate::diff::FileDiff;
+use crate::llm::{ChatMessage, LlmClient};
+use crate::ui::{render_diff_review, DiffReview, ReviewAction};
+
+/// Instructions sent as the system prompt for rewrite requests.
+const REWRITE_SYSTEM_PROMPT: &str = "You are editing a file. Apply the user's instruction and \
+reply with the complete revised file contents only. Do not add explanations or commentary, \
+and leave everything the instruction does not ask to change exactly as it is.";
+
+/// Run the rewrite subcommand.
+pub async fn run(args: RewriteArgs, config: &Config, dry_run: bool) -> Result<()> {
+    let original = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+
+    let llm_config = args.model.resolve(config);
+    let client = LlmClient::new(llm_config);
+    let title = args.file.display().to_string();
+    if dry_run {
+        return print_dry_run(&client, build_messages(&args.prompt, &title, &original));
+    }
+    if !client.is_configured() {
+        bail!(
+            "{} is not configured. Connect it from the chat UI or set {}.",
+            client.display_name(),
+            client.provider_type().env_var_name()
+        );
+    }
+
+    eprintln!(
+        "Asking {} ({}) to rewrite {}...",
+        client.display_name(),
+        client.model(),
+        title
+    );
+
+    let messages = build_messages(&args.prompt, &title, &original);
+    let response = collect_response(&client, messages).await?;
+    let revised = match_trailing_newline(&original, strip_code_fence(&response));
+
+    let diff = FileDiff::new(&original, &revised);
+    if diff.is_empty() {
+        eprintln!("No changes suggested.");
+        return Ok(());
+    }
+
+    let total = diff.hunks().len();
+    let accepted = if args.yes {
+        vec![true; total]
+    } else {
+        match review(DiffReview::new(title.clone(), diff.clone()))? {
+            Some(accepted) => accepted,
+            None => {
+                eprintln!("Aborted, {} left unchanged.", title);
+                return Ok(());
+            }
+        }
+    };
+
+    let applied = accepted.iter().filter(|a| **a).count();
+    if applied == 0 {
+        eprintln!("No hunks accepted, {} left unchanged.", title);
+        return Ok(());
+    }
+
+    fs::write(&args.file, diff.apply(&accepted))
+        .with_context(|| format!("Failed to write {}", title))?;
+    eprintln!("Applied {} of {} hunks to {}.", applied, total, title);
+    Ok(())
+}
+
+/// Build the request messages for a rewrite.
+fn build_messages(prompt: &str, path: &str, contents: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: REWRITE_SYSTEM_PROMPT.to_string(),
+            ..Default::default()
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: format!(
+                "Instruction: {}\n\nFile: {}\n\n{}",
+                prompt.trim(),
+                path,
+                contents
+            ),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Run the interactive review, returning the accepted flags or `None` if aborted.
+fn review(mut state: DiffReview) -> Result<Option<Vec<bool>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = review_loop(&mut terminal, &mut state);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(match result? {
+        ReviewAction::Finish => Some(state.accepted()),
+        _ => None,
+    })
+}
+
+fn review_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    state: &mut DiffReview,
+) -> io::Result<ReviewAction> {
+    loop {
+        terminal.draw(|f| render_diff_review(f, state))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(ReviewAction::Abort);
+            }
+            match state.handle_key(key.code) {
+                ReviewAction::Continue => {}
+                action => return Ok(action),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_messages_includes_file() {
+        let messages = build_messages("rename foo", "src/lib.rs", "fn foo() {}\n");
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[1].content.contains("File: src/lib.rs"));
+        assert!(messages[1].content.ends_with("fn foo() {}\n"));
+    }
+}
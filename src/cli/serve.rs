@@ -0,0 +1,359 @@
+//! `scry serve` - a small daemon serving saved sessions over HTTP.
+//!
+//! Serves a read-only web dashboard for browsing sessions and reading
+//! transcripts from a browser or phone on the LAN, backed by a JSON API:
+//! - `GET /` - The dashboard page (embedded, no data of its own)
+//! - `GET /api/sessions[?q=text]` - Session summaries, or search results
+//! - `GET /api/sessions/<id>` - One session with its messages
+//! - `DELETE /api/sessions/<id>` - Delete a session (only with `--writable`)
+//!
+//! Every API request must carry `Authorization: Bearer <token>`. The token is
+//! printed at startup in the dashboard URL's fragment, which browsers never
+//! send to the server.
+
+use anyhow::{Context, Result};
+use rand::Rng;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::ServeArgs;
+use crate::config::Config;
+use crate::session::{self, SessionStore, SessionSummary};
+
+/// The dashboard page.
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Largest request head read before giving up.
+const MAX_REQUEST: usize = 16 * 1024;
+
+/// What requests are served from.
+struct Server {
+    store: Arc<dyn SessionStore>,
+    token: String,
+    writable: bool,
+}
+
+/// A parsed HTTP request; bodies are never needed.
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: Option<String>,
+    authorization: Option<String>,
+}
+
+/// A response ready to be written.
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            204 => "No Content",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        let mut bytes = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(self.body.as_bytes());
+        bytes
+    }
+}
+
+/// Serve sessions until interrupted.
+pub async fn run(args: ServeArgs, config: &Config) -> Result<()> {
+    let store = session::open_store(&config.storage)?;
+    let token = args.token.unwrap_or_else(new_token);
+    let listener = TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| format!("Failed to listen on {}", args.bind))?;
+    let addr = listener.local_addr()?;
+    eprintln!(
+        "Serving sessions{} at http://{}/#token={}",
+        if args.writable { "" } else { " (read-only)" },
+        addr,
+        token
+    );
+
+    let server = Arc::new(Server {
+        store,
+        token,
+        writable: args.writable,
+    });
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_connection(stream, server).await {
+                eprintln!("Request failed: {:#}", e);
+            }
+        });
+    }
+}
+
+/// A random token for sessions started without `--token`.
+pub(crate) fn new_token() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn serve_connection(mut stream: TcpStream, server: Arc<Server>) -> Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 2048];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST {
+            let response = Response::error(400, "Request too large");
+            stream.write_all(&response.to_bytes()).await?;
+            return Ok(());
+        }
+    }
+    let response = match parse_request(&String::from_utf8_lossy(&head)) {
+        // Store calls block (SQLite), so keep them off the async workers
+        Some(request) => tokio::task::spawn_blocking(move || server.respond(&request)).await?,
+        None => Response::error(400, "Malformed request"),
+    };
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Parse the request line and the headers that matter.
+fn parse_request(head: &str) -> Option<Request> {
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next()?.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+    let authorization = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .map(|(_, value)| value.trim().to_string());
+    Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        authorization,
+    })
+}
+
+/// Value of `key` in a query string, percent-decoded.
+fn query_param(query: Option<&str>, key: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, v)| {
+            let v = v.replace('+', " ");
+            urlencoding::decode(&v).ok().map(|v| v.into_owned())
+        })
+}
+
+/// Compare without leaking how much of the token matched.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn summary_json(summary: &SessionSummary) -> serde_json::Value {
+    json!({
+        "id": summary.id,
+        "title": summary.title,
+        "provider": summary.provider,
+        "model": summary.model,
+        "updated_at": summary.updated_at.to_rfc3339(),
+        "message_count": summary.message_count,
+        "tags": summary.tags,
+    })
+}
+
+impl Server {
+    fn authorized(&self, request: &Request) -> bool {
+        request
+            .authorization
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        if request.path == "/" {
+            return match request.method.as_str() {
+                "GET" => Response {
+                    status: 200,
+                    content_type: "text/html; charset=utf-8",
+                    body: DASHBOARD.to_string(),
+                },
+                _ => Response::error(405, "Method not allowed"),
+            };
+        }
+        let Some(route) = request.path.strip_prefix("/api/sessions") else {
+            return Response::error(404, "Not found");
+        };
+        if !self.authorized(request) {
+            return Response::error(401, "Missing or wrong token");
+        }
+        let result = match (request.method.as_str(), route.strip_prefix('/')) {
+            ("GET", None) if route.is_empty() => {
+                let summaries = match query_param(request.query.as_deref(), "q") {
+                    Some(q) if !q.trim().is_empty() => self.store.search(q.trim()),
+                    _ => self.store.list(),
+                };
+                summaries.map(|list| Response::json(200, list.iter().map(summary_json).collect()))
+            }
+            ("GET", Some(id)) => match self.store.load(id) {
+                Ok(session) => Ok(Response::json(200, json!(session))),
+                Err(_) => Ok(Response::error(404, "No such session")),
+            },
+            ("DELETE", Some(_)) if !self.writable => {
+                Ok(Response::error(403, "Read-only; restart with --writable"))
+            }
+            ("DELETE", Some(id)) => self.store.delete(id).map(|()| Response {
+                status: 204,
+                content_type: "text/plain",
+                body: String::new(),
+            }),
+            (_, None) if !route.is_empty() => Ok(Response::error(404, "Not found")),
+            _ => Ok(Response::error(405, "Method not allowed")),
+        };
+        result.unwrap_or_else(|e| Response::error(500, &format!("{:#}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::session::{Session, SqliteStore};
+
+    fn server(writable: bool) -> (Server, Session) {
+        let store = SqliteStore::in_memory().unwrap();
+        let mut session = Session::new("ollama", "llama3");
+        session.set_messages(&[
+            Message::user("How do lighthouses rotate?".to_string()),
+            Message::assistant("On a bath of mercury.".to_string()),
+        ]);
+        session.derive_title();
+        store.save(&session).unwrap();
+        let server = Server {
+            store: Arc::new(store),
+            token: "secret".to_string(),
+            writable,
+        };
+        (server, session)
+    }
+
+    fn request(head: &str) -> Request {
+        parse_request(head).unwrap()
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let parsed = request(
+            "GET /api/sessions?q=a+b HTTP/1.1\r\nHost: x\r\nauthorization: Bearer t\r\n\r\n",
+        );
+        assert_eq!(parsed.method, "GET");
+        assert_eq!(parsed.path, "/api/sessions");
+        assert_eq!(query_param(parsed.query.as_deref(), "q").unwrap(), "a b");
+        assert_eq!(parsed.authorization.as_deref(), Some("Bearer t"));
+        assert!(parse_request("").is_none());
+    }
+
+    #[test]
+    fn test_api_requires_the_token() {
+        let (server, _) = server(false);
+        assert_eq!(
+            server.respond(&request("GET / HTTP/1.1\r\n\r\n")).status,
+            200
+        );
+        let anonymous = request("GET /api/sessions HTTP/1.1\r\n\r\n");
+        assert_eq!(server.respond(&anonymous).status, 401);
+        let wrong = request("GET /api/sessions HTTP/1.1\r\nAuthorization: Bearer nope\r\n\r\n");
+        assert_eq!(server.respond(&wrong).status, 401);
+    }
+
+    #[test]
+    fn test_sessions_are_listed_searched_and_read() {
+        let (server, session) = server(false);
+        let auth = "Authorization: Bearer secret\r\n\r\n";
+        let get = |target: &str| {
+            let response =
+                server.respond(&request(&format!("GET {} HTTP/1.1\r\n{}", target, auth)));
+            let body = serde_json::from_str::<serde_json::Value>(&response.body).unwrap();
+            (response.status, body)
+        };
+
+        let (status, list) = get("/api/sessions");
+        assert_eq!(status, 200);
+        assert_eq!(list[0]["title"], "How do lighthouses rotate?");
+        assert_eq!(
+            get("/api/sessions?q=mercury").1.as_array().unwrap().len(),
+            1
+        );
+        assert!(get("/api/sessions?q=sails")
+            .1
+            .as_array()
+            .unwrap()
+            .is_empty());
+
+        let (status, full) = get(&format!("/api/sessions/{}", session.id));
+        assert_eq!(status, 200);
+        assert_eq!(full["messages"][1]["content"], "On a bath of mercury.");
+        assert_eq!(get("/api/sessions/missing").0, 404);
+    }
+
+    #[test]
+    fn test_deleting_needs_writable() {
+        let delete = |server: &Server, id: &str| {
+            let head = format!(
+                "DELETE /api/sessions/{} HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+                id
+            );
+            server.respond(&request(&head)).status
+        };
+        let (read_only, session) = server(false);
+        assert_eq!(delete(&read_only, &session.id), 403);
+        assert_eq!(read_only.store.list().unwrap().len(), 1);
+
+        let (writable, session) = server(true);
+        assert_eq!(delete(&writable, &session.id), 204);
+        assert!(writable.store.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,18 @@
+//! `scry usage` - print a monthly summary from the usage ledger.
+
+use anyhow::Result;
+
+use super::UsageArgs;
+use crate::usage::{current_month, format_table, month_range, UsageLedger};
+
+/// Print per-model usage for the requested month.
+pub fn run(args: UsageArgs) -> Result<()> {
+    let month = args.month.unwrap_or_else(current_month);
+    let (from, to) = month_range(&month)?;
+    let ledger = UsageLedger::open_default()?;
+    print!(
+        "{}",
+        format_table(&month, &ledger.totals_between(from, to)?)
+    );
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! Copying text to the system clipboard (`[clipboard]`).
+//!
+//! There is no clipboard API common to every platform, so text is piped to
+//! the tool each desktop ships with: `pbcopy` on macOS, `clip.exe` on
+//! Windows (and WSL), `wl-copy` under Wayland, `xclip` or `xsel` under X11.
+//! Over SSH, or where no tool is installed, the text is handed to the
+//! terminal instead in an OSC 52 escape sequence, which most terminals put
+//! on the clipboard of the machine they run on. Inside tmux or screen the
+//! sequence is wrapped so the multiplexer passes it on to that terminal.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::config::ClipboardBackend;
+
+/// Largest chunk of an escape sequence screen passes through at once.
+const SCREEN_CHUNK: usize = 76;
+
+/// How copied text reached the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Copied {
+    /// Piped to this clipboard tool
+    Tool(&'static str),
+    /// Sent to the terminal as OSC 52, which it may ignore
+    Osc52,
+}
+
+/// Terminal multiplexer scry runs in, which escape sequences must get through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// The multiplexer this process runs in, from its environment.
+    pub fn detect() -> Self {
+        if std::env::var_os("TMUX").is_some() {
+            Multiplexer::Tmux
+        } else if std::env::var_os("STY").is_some() {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+
+    /// `sequence` wrapped so the multiplexer passes it on to the terminal.
+    pub fn passthrough(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::None => sequence.to_string(),
+            Multiplexer::Tmux => {
+                format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+            }
+            // The sequence is ASCII, so any byte is a character boundary
+            Multiplexer::Screen => sequence
+                .as_bytes()
+                .chunks(SCREEN_CHUNK)
+                .map(|chunk| format!("\x1bP{}\x1b\\", String::from_utf8_lossy(chunk)))
+                .collect(),
+        }
+    }
+}
+
+/// A clipboard tool: program and arguments that read the text from stdin.
+type Tool = (&'static str, &'static [&'static str]);
+
+/// Whether `program` is an executable on `PATH`.
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
+
+/// Clipboard tools installed for this session's desktop, most specific first.
+fn native_tools() -> Vec<Tool> {
+    let mut tools: Vec<Tool> = Vec::new();
+    if cfg!(target_os = "macos") {
+        tools.push(("pbcopy", &[]));
+    }
+    if cfg!(windows) || std::env::var_os("WSL_DISTRO_NAME").is_some() {
+        tools.push(("clip.exe", &[]));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        tools.push(("xclip", &["-selection", "clipboard"]));
+        tools.push(("xsel", &["--clipboard", "--input"]));
+    }
+    if std::env::var_os("TERMUX_VERSION").is_some() {
+        tools.push(("termux-clipboard-set", &[]));
+    }
+    tools.retain(|(program, _)| on_path(program));
+    tools
+}
+
+/// Pipe `text` to `tool`.
+fn run_tool((program, args): Tool, text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .context("no stdin")?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Copy `text` with the first clipboard tool that works.
+fn copy_native(text: &str) -> Option<Copied> {
+    native_tools()
+        .into_iter()
+        .find(|tool| run_tool(*tool, text).is_ok())
+        .map(|(program, _)| Copied::Tool(program))
+}
+
+/// The OSC 52 sequence that sets the clipboard to `text`.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+/// Send `text` to the terminal's clipboard.
+fn copy_osc52(text: &str) -> Result<Copied> {
+    let sequence = Multiplexer::detect().passthrough(&osc52(text));
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(Copied::Osc52)
+}
+
+/// Whether scry runs in an SSH session, where clipboard tools would copy
+/// to the remote machine rather than the user's.
+fn over_ssh() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some()
+}
+
+/// Copy `text` to the clipboard the way `backend` says.
+pub fn copy(text: &str, backend: ClipboardBackend) -> Result<Copied> {
+    match backend {
+        ClipboardBackend::Auto if over_ssh() => copy_osc52(text),
+        ClipboardBackend::Auto => copy_native(text).map_or_else(|| copy_osc52(text), Ok),
+        ClipboardBackend::Native => copy_native(text).context(
+            "No clipboard tool found (install wl-clipboard, xclip or xsel, or set [clipboard] backend = \"osc52\")",
+        ),
+        ClipboardBackend::Osc52 => copy_osc52(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52(""), "\x1b]52;c;\x07");
+    }
+
+    #[test]
+    fn test_multiplexers_pass_the_sequence_through() {
+        let sequence = osc52("hi");
+        assert_eq!(Multiplexer::None.passthrough(&sequence), sequence);
+        assert_eq!(
+            Multiplexer::Tmux.passthrough(&sequence),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+
+        let long = osc52(&"x".repeat(100));
+        let wrapped = Multiplexer::Screen.passthrough(&long);
+        assert_eq!(wrapped.matches("\x1bP").count(), long.len().div_ceil(SCREEN_CHUNK));
+        assert_eq!(wrapped.replace("\x1bP", "").replace("\x1b\\", ""), long);
+    }
+}
@@ -0,0 +1,336 @@
+//! Slash commands typed in the chat input.
+//!
+//! Input whose first word is a registered `/name` runs the command instead of
+//! being sent to the model. While a command name is being typed,
+//! [`completions`] lists candidates for the popup above the input.
+
+use std::ops::Range;
+
+use crate::llm::Capability;
+
+/// Maximum number of completions shown at once.
+const MAX_COMPLETIONS: usize = 10;
+
+/// Action run by a slash command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlashCommand {
+    Model,
+    Think,
+    System,
+    Attach,
+    Provider,
+    Clear,
+    Save,
+    Continue,
+    Retry,
+    Copy,
+    Undo,
+    Redo,
+    Raw,
+    DryRun,
+    Local,
+    Playground,
+    Sync,
+    Share,
+    Theme,
+    Batch,
+    Usage,
+    Help,
+}
+
+/// A registered slash command.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    /// Name including the slash, e.g. `/model`
+    pub name: &'static str,
+    /// Argument synopsis shown in help, empty if none
+    pub args: &'static str,
+    /// One-line description
+    pub description: &'static str,
+    /// Capability the current model needs, if any
+    pub requires: Option<Capability>,
+    pub command: SlashCommand,
+}
+
+/// Registered commands, in completion order.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "/model",
+        args: "[name]",
+        description: "Show or switch the model",
+        requires: None,
+        command: SlashCommand::Model,
+    },
+    CommandSpec {
+        name: "/think",
+        args: "[low|med|high|off]",
+        description: "Set the reasoning effort",
+        requires: Some(Capability::Reasoning),
+        command: SlashCommand::Think,
+    },
+    CommandSpec {
+        name: "/system",
+        args: "[prompt|off|default]",
+        description: "Show or set this conversation's system prompt",
+        requires: None,
+        command: SlashCommand::System,
+    },
+    CommandSpec {
+        name: "/attach",
+        args: "<image>|clear",
+        description: "Send an image with the next prompt",
+        requires: Some(Capability::Vision),
+        command: SlashCommand::Attach,
+    },
+    CommandSpec {
+        name: "/provider",
+        args: "[name]",
+        description: "Connect to another provider",
+        requires: None,
+        command: SlashCommand::Provider,
+    },
+    CommandSpec {
+        name: "/clear",
+        args: "",
+        description: "Save and start a new conversation",
+        requires: None,
+        command: SlashCommand::Clear,
+    },
+    CommandSpec {
+        name: "/save",
+        args: "",
+        description: "Save the conversation now",
+        requires: None,
+        command: SlashCommand::Save,
+    },
+    CommandSpec {
+        name: "/continue",
+        args: "",
+        description: "Continue a reply that was cut short",
+        requires: None,
+        command: SlashCommand::Continue,
+    },
+    CommandSpec {
+        name: "/retry",
+        args: "[model]",
+        description: "Replace the last reply with a new one, optionally from another model",
+        requires: None,
+        command: SlashCommand::Retry,
+    },
+    CommandSpec {
+        name: "/copy",
+        args: "",
+        description: "Copy the last reply to the clipboard",
+        requires: None,
+        command: SlashCommand::Copy,
+    },
+    CommandSpec {
+        name: "/undo",
+        args: "",
+        description: "Revert the last conversation edit",
+        requires: None,
+        command: SlashCommand::Undo,
+    },
+    CommandSpec {
+        name: "/redo",
+        args: "",
+        description: "Reapply the last undone edit",
+        requires: None,
+        command: SlashCommand::Redo,
+    },
+    CommandSpec {
+        name: "/raw",
+        args: "",
+        description: "Toggle raw Markdown for all messages",
+        requires: None,
+        command: SlashCommand::Raw,
+    },
+    CommandSpec {
+        name: "/dryrun",
+        args: "",
+        description: "Toggle showing requests instead of sending them",
+        requires: None,
+        command: SlashCommand::DryRun,
+    },
+    CommandSpec {
+        name: "/local",
+        args: "",
+        description: "Manage loaded Ollama models",
+        requires: Some(Capability::LocalModels),
+        command: SlashCommand::Local,
+    },
+    CommandSpec {
+        name: "/playground",
+        args: "",
+        description: "Fork into an unsaved scratch conversation, or leave it",
+        requires: None,
+        command: SlashCommand::Playground,
+    },
+    CommandSpec {
+        name: "/sync",
+        args: "",
+        description: "Sync saved sessions with the [sync] remote",
+        requires: None,
+        command: SlashCommand::Sync,
+    },
+    CommandSpec {
+        name: "/share",
+        args: "[write]",
+        description: "Share the conversation live on the network, or stop",
+        requires: None,
+        command: SlashCommand::Share,
+    },
+    CommandSpec {
+        name: "/theme",
+        args: "[light|dark|auto]",
+        description: "Switch between the light and dark theme",
+        requires: None,
+        command: SlashCommand::Theme,
+    },
+    CommandSpec {
+        name: "/batch",
+        args: "<file>|stop",
+        description: "Send each line of a file as a prompt",
+        requires: None,
+        command: SlashCommand::Batch,
+    },
+    CommandSpec {
+        name: "/usage",
+        args: "",
+        description: "Show tokens and cost this session, this month and overall",
+        requires: None,
+        command: SlashCommand::Usage,
+    },
+    CommandSpec {
+        name: "/help",
+        args: "",
+        description: "List slash commands",
+        requires: None,
+        command: SlashCommand::Help,
+    },
+];
+
+/// Result of parsing the chat input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parsed<'a> {
+    /// A registered command and its (trimmed) arguments.
+    Command(SlashCommand, &'a str),
+    /// A `/word` that is not a registered command.
+    Unknown(&'a str),
+}
+
+/// Look up a command by name, including the slash.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
+
+/// Parse `input` as a slash command.
+///
+/// Returns `None` for ordinary text, including text that merely starts with
+/// a path such as `/usr/bin`, so it can be sent as usual.
+pub fn parse(input: &str) -> Option<Parsed<'_>> {
+    let input = input.trim();
+    let (name, args) = input
+        .split_once(char::is_whitespace)
+        .map_or((input, ""), |(name, args)| (name, args.trim()));
+    let word = name.strip_prefix('/')?;
+    if word.is_empty() || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(match lookup(name) {
+        Some(spec) => Parsed::Command(spec.command, args),
+        None => Parsed::Unknown(name),
+    })
+}
+
+/// Byte range of the command name in `input`, if it parses as a registered command.
+pub fn command_range(input: &str) -> Option<Range<usize>> {
+    let Some(Parsed::Command(..)) = parse(input) else {
+        return None;
+    };
+    let start = input.len() - input.trim_start().len();
+    let len = input[start..]
+        .find(char::is_whitespace)
+        .unwrap_or(input.len() - start);
+    Some(start..start + len)
+}
+
+/// Commands starting with the partial name when the input is only `/name`
+/// with the cursor at its end.
+pub fn completions(input: &str, cursor: usize) -> Vec<&'static CommandSpec> {
+    if cursor != input.len() || !input.starts_with('/') || input.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    COMMANDS
+        .iter()
+        .filter(|spec| spec.name.starts_with(input) && spec.name != input)
+        .take(MAX_COMPLETIONS)
+        .collect()
+}
+
+/// One line per command, for `/help`.
+pub fn help_text() -> String {
+    let lines: Vec<String> = COMMANDS
+        .iter()
+        .map(|spec| {
+            let usage = if spec.args.is_empty() {
+                spec.name.to_string()
+            } else {
+                format!("{} {}", spec.name, spec.args)
+            };
+            format!("{:<18} {}", usage, spec.description)
+        })
+        .collect();
+    format!("Slash commands:\n{}", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands_and_arguments() {
+        assert_eq!(
+            parse("  /model  llama3.2 "),
+            Some(Parsed::Command(SlashCommand::Model, "llama3.2"))
+        );
+        assert_eq!(parse("/clear"), Some(Parsed::Command(SlashCommand::Clear, "")));
+        assert_eq!(parse("/modle"), Some(Parsed::Unknown("/modle")));
+    }
+
+    #[test]
+    fn test_ordinary_text_is_not_a_command() {
+        assert_eq!(parse("hello /model"), None);
+        assert_eq!(parse("/usr/bin is on my PATH"), None);
+        assert_eq!(parse("/"), None);
+        assert_eq!(parse("/2 is half"), None);
+    }
+
+    #[test]
+    fn test_command_range() {
+        assert_eq!(command_range("  /model llama3.2"), Some(2..8));
+        assert_eq!(command_range("/raw"), Some(0..4));
+        // Only what submitting would run as a command
+        assert_eq!(command_range("/modle x"), None);
+        assert_eq!(command_range("hello /model"), None);
+    }
+
+    #[test]
+    fn test_completions() {
+        let names: Vec<_> = completions("/r", 2).iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["/retry", "/redo", "/raw"]);
+        // Not offered once the name is complete or arguments follow
+        assert!(completions("/raw", 4).is_empty());
+        assert!(completions("/model x", 8).is_empty());
+        // Or when the cursor is elsewhere
+        assert!(completions("/r", 1).is_empty());
+        assert_eq!(completions("/", 1).len(), COMMANDS.len().min(MAX_COMPLETIONS));
+    }
+
+    #[test]
+    fn test_help_lists_every_command() {
+        let help = help_text();
+        assert!(COMMANDS.iter().all(|spec| help.contains(spec.name)));
+        assert!(help.contains("/model [name]"));
+    }
+}
@@ -1,6 +1,13 @@
+use anyhow::{bail, Context};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+use crate::appearance::Appearance;
+use crate::llm::{Provider, ReasoningEffort};
+use crate::message::Role;
+use crate::profiles;
+
 /// RGB color represented as a 3-element array.
 pub type Rgb = [u8; 3];
 
@@ -14,10 +21,214 @@ pub struct LlmConfigFile {
     pub api_key: Option<String>,
     /// Model name
     pub model: String,
+    /// Provider to start with and ask by default (set by `scry use`); defaults to anthropic
+    pub provider: Option<String>,
+    /// Model per provider for the others, keyed by provider (e.g. `[llm.models] openai = "gpt-4o"`)
+    pub models: BTreeMap<String, String>,
     /// Temperature for generation
     pub temperature: Option<f32>,
     /// Max tokens for generation
     pub max_tokens: Option<u32>,
+    /// Organization/project scoping, keyed by provider (e.g. `[llm.scopes.github_copilot]`)
+    pub scopes: BTreeMap<String, ProviderScope>,
+    /// Request signing for authenticated gateways (`[llm.signing]`)
+    pub signing: Option<SigningConfig>,
+    /// Record raw response streams into this directory (also `SCRY_RECORD_DIR`)
+    pub record_dir: Option<PathBuf>,
+    /// Streaming channel settings (`[llm.stream]`)
+    pub stream: StreamConfig,
+    /// Retrying failed requests (`[llm.retry]`)
+    pub retry: RetryConfig,
+    /// Connect, read and stall timeouts (`[llm.timeouts]`)
+    pub timeouts: TimeoutConfig,
+    /// Preload local models when switching to them, so the first message is fast
+    pub warm_up: bool,
+    /// How long local models stay loaded after a request (Ollama `keep_alive`, e.g. "10m")
+    pub keep_alive: Option<String>,
+    /// Models picked by `model = "auto"` (`[llm.auto]`)
+    pub auto: AutoModelConfig,
+    /// Reasoning effort to start with: "low", "med" or "high" (change with `/think`)
+    pub reasoning: Option<ReasoningEffort>,
+    /// Instructions sent ahead of every conversation (change per session with `/system`)
+    pub system_prompt: Option<String>,
+}
+
+/// Routing for the `auto` model.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AutoModelConfig {
+    /// Model for short, simple prompts (defaults to the provider's fast model)
+    pub cheap: Option<String>,
+    /// Model for long prompts, code and thinking (defaults to the provider's default model)
+    pub strong: Option<String>,
+    /// Prompts estimated above this many tokens go to the strong model
+    pub simple_max_tokens: usize,
+}
+
+impl Default for AutoModelConfig {
+    fn default() -> Self {
+        Self {
+            cheap: None,
+            strong: None,
+            simple_max_tokens: 300,
+        }
+    }
+}
+
+/// What to do when the UI falls behind a streaming response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressurePolicy {
+    /// Wait for the UI, pausing the provider's reader task
+    #[default]
+    Block,
+    /// Keep reading and merge queued tokens into one event until there is room
+    Coalesce,
+}
+
+/// Streaming channel between providers and the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    /// Number of events buffered before backpressure applies
+    pub channel_size: usize,
+    /// Behavior when the channel is full
+    pub backpressure: BackpressurePolicy,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            channel_size: 100,
+            backpressure: BackpressurePolicy::Block,
+        }
+    }
+}
+
+/// Retrying requests that fail before the reply starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry in ms, doubled for each one after
+    pub base_delay_ms: u64,
+    /// Longest delay between attempts in ms
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+/// How long requests may wait on the network before they fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Seconds to wait for a connection to the provider (0 waits forever)
+    pub connect_secs: u64,
+    /// Seconds a response may send nothing at all before it fails (0 waits forever)
+    pub read_secs: u64,
+    /// Seconds a streaming reply may go without an event before it fails
+    /// (0 waits forever). Unlike `read_secs`, keep-alive pings don't count.
+    pub stall_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect_secs: 10,
+            read_secs: 300,
+            stall_secs: 180,
+        }
+    }
+}
+
+/// How to sign provider requests before they are sent.
+///
+/// Needed when traffic goes through an internal gateway that authenticates
+/// each request on top of the provider's own credentials.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SigningConfig {
+    /// HMAC-SHA256 over the timestamp, method, path and body.
+    Hmac {
+        /// Environment variable holding the shared secret
+        secret_env: String,
+        /// Header carrying the hex-encoded signature
+        #[serde(default = "default_signature_header")]
+        header: String,
+        /// Header carrying the Unix timestamp included in the signature
+        #[serde(default = "default_timestamp_header")]
+        timestamp_header: String,
+    },
+    /// External program that prints `Name: value` header lines.
+    ///
+    /// The request body is passed on stdin and the method and URL in the
+    /// `SCRY_REQUEST_METHOD` and `SCRY_REQUEST_URL` environment variables.
+    Command {
+        /// Program to run
+        command: String,
+        /// Arguments passed to the program
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+fn default_signature_header() -> String {
+    "X-Signature".to_string()
+}
+
+fn default_timestamp_header() -> String {
+    "X-Signature-Timestamp".to_string()
+}
+
+/// Organization or project a provider's requests are scoped to.
+///
+/// The meaning of `organization` depends on the provider: an OpenAI organization
+/// ID, an Anthropic workspace, or a GitHub Copilot enterprise slug.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProviderScope {
+    /// Organization, workspace, or enterprise identifier
+    pub organization: Option<String>,
+    /// Project identifier (OpenAI projects)
+    pub project: Option<String>,
+}
+
+impl ProviderScope {
+    /// Check if no scoping is configured.
+    pub fn is_empty(&self) -> bool {
+        self.organization.is_none() && self.project.is_none()
+    }
+
+    /// Short description for display, e.g. `acme / billing`.
+    pub fn summary(&self) -> Option<String> {
+        match (&self.organization, &self.project) {
+            (Some(org), Some(project)) => Some(format!("{} / {}", org, project)),
+            (Some(org), None) => Some(org.clone()),
+            (None, Some(project)) => Some(format!("project {}", project)),
+            (None, None) => None,
+        }
+    }
+
+    /// Headers for OpenAI-compatible APIs (`OpenAI-Organization`, `OpenAI-Project`).
+    pub fn openai_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(org) = &self.organization {
+            headers.push(("OpenAI-Organization", org.clone()));
+        }
+        if let Some(project) = &self.project {
+            headers.push(("OpenAI-Project", project.clone()));
+        }
+        headers
+    }
 }
 
 impl Default for LlmConfigFile {
@@ -26,12 +237,32 @@ impl Default for LlmConfigFile {
             api_base: "https://api.anthropic.com/v1".to_string(),
             api_key: None,
             model: "claude-sonnet-4-5".to_string(),
+            provider: None,
+            models: BTreeMap::new(),
             temperature: Some(0.7),
             max_tokens: Some(4096),
+            scopes: BTreeMap::new(),
+            signing: None,
+            record_dir: None,
+            stream: StreamConfig::default(),
+            retry: RetryConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            warm_up: true,
+            keep_alive: None,
+            auto: AutoModelConfig::default(),
+            reasoning: None,
+            system_prompt: None,
         }
     }
 }
 
+impl LlmConfigFile {
+    /// The provider `provider` names, if it names one.
+    pub fn default_provider(&self) -> Option<Provider> {
+        self.provider.as_deref().and_then(|name| name.parse().ok())
+    }
+}
+
 /// Color configuration for the UI.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
@@ -77,10 +308,36 @@ pub struct BehaviorConfig {
     pub scroll_page_size: usize,
     /// Characters revealed per animation frame
     pub animation_chars_per_frame: usize,
-    /// Animation frame duration in milliseconds
+    /// Animation frame duration in milliseconds, also the shortest time between redraws
     pub animation_frame_ms: u64,
-    /// Idle polling interval in milliseconds
+    /// Polling interval in milliseconds while waiting on background work
+    /// (sign-in, model warm-up, reconnecting); fully idle, the loop sleeps longer
     pub idle_poll_ms: u64,
+    /// Show the conversation minimap at startup (toggle with Ctrl+B)
+    pub minimap: bool,
+    /// Ignore Enter presses this soon after sending, in milliseconds
+    pub submit_debounce_ms: u64,
+    /// Ask for confirmation before sending more than this many estimated tokens (0 disables)
+    pub confirm_send_tokens: usize,
+    /// Expand `:shortcode:` emoji while typing
+    pub emoji_shortcodes: bool,
+    /// Follow-up requests sent when a reply stops at the token limit (0 disables)
+    pub auto_continue: u32,
+    /// Stop continuing once a reply reaches this many estimated tokens
+    pub max_reply_tokens: usize,
+    /// Start with the reasoning models stream before their answer expanded
+    pub show_thinking: bool,
+    /// Pause between `/batch` prompts, in milliseconds
+    pub batch_interval_ms: u64,
+    /// Resends of a rate-limited `/batch` prompt before it counts as failed
+    pub batch_retries: u32,
+    /// Context window for the meter, in tokens; 0 uses the model's known window
+    pub context_window: usize,
+    /// Warn this many hours before a sign-in that can't be renewed expires (0 disables)
+    pub expiry_warning_hours: u64,
+    /// Capture the mouse: drag to select and copy chat text, wheel to scroll
+    /// (off leaves selection to the terminal)
+    pub mouse: bool,
 }
 
 impl Default for BehaviorConfig {
@@ -90,6 +347,18 @@ impl Default for BehaviorConfig {
             animation_chars_per_frame: 3,
             animation_frame_ms: 16,  // ~60 FPS
             idle_poll_ms: 100,
+            minimap: false,
+            submit_debounce_ms: 300,
+            confirm_send_tokens: 32_000,
+            emoji_shortcodes: true,
+            auto_continue: 2,
+            max_reply_tokens: 16_000,
+            show_thinking: false,
+            batch_interval_ms: 1000,
+            batch_retries: 3,
+            context_window: 0,
+            expiry_warning_hours: 24,
+            mouse: true,
         }
     }
 }
@@ -186,6 +455,42 @@ pub struct ThemeConfig {
     pub bg_primary: Rgb,
     /// Secondary background color
     pub bg_secondary: Rgb,
+    /// Input text color
+    pub fg_primary: Rgb,
+
+    // Chat messages
+    /// Prefix shown before user messages
+    pub user_prefix: String,
+    /// Prefix shown before assistant messages
+    pub assistant_prefix: String,
+    /// User message color (terminal cyan if unset)
+    pub user_color: Option<Rgb>,
+    /// Assistant message color (terminal green if unset)
+    pub assistant_color: Option<Rgb>,
+    /// Which side user messages are aligned to
+    pub user_align: MessageAlign,
+    /// How messages are drawn
+    pub message_style: MessageStyle,
+}
+
+/// Horizontal placement of a message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageAlign {
+    #[default]
+    Left,
+    Right,
+}
+
+/// How chat messages are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStyle {
+    /// Role prefix followed by the text
+    #[default]
+    Plain,
+    /// Each message in its own rounded box, labelled with the role
+    Bubbles,
 }
 
 impl Default for ThemeConfig {
@@ -210,10 +515,390 @@ impl Default for ThemeConfig {
             // General UI colors
             bg_primary: [20, 20, 25],
             bg_secondary: [30, 30, 35],
+            fg_primary: [255, 255, 255],
+
+            // Chat messages
+            user_prefix: "You: ".to_string(),
+            assistant_prefix: "Assistant: ".to_string(),
+            user_color: None,
+            assistant_color: None,
+            user_align: MessageAlign::Left,
+            message_style: MessageStyle::Plain,
+        }
+    }
+}
+
+/// Which backend saved sessions are stored in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// One JSON file per session
+    #[default]
+    Json,
+    /// A single SQLite database with full-text search
+    Sqlite,
+}
+
+/// Which saves are flushed to disk before continuing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// Only critical files such as credentials
+    #[default]
+    Critical,
+    /// Sessions as well
+    Always,
+}
+
+/// Session storage configuration (`[storage]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Storage backend
+    pub backend: StorageBackend,
+    /// Directory (json) or database file (sqlite); defaults under the local data dir
+    pub path: Option<PathBuf>,
+    /// Save the conversation this often while chatting (0 = only on exit)
+    pub autosave_secs: u64,
+    /// Save a reply this often while it streams, so a dropped connection keeps it (0 = never)
+    pub checkpoint_secs: u64,
+    /// Write session files to a temporary file and rename them into place
+    pub atomic_writes: bool,
+    /// Which saves wait for the data to reach the disk
+    pub fsync: FsyncPolicy,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::default(),
+            path: None,
+            autosave_secs: 60,
+            checkpoint_secs: 10,
+            atomic_writes: true,
+            fsync: FsyncPolicy::default(),
+        }
+    }
+}
+
+/// Where sessions are synced to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncBackend {
+    /// A git repository, pushed to and pulled from with the `git` command
+    Git,
+    /// A WebDAV collection (also S3 and others through `rclone serve webdav`)
+    Webdav,
+}
+
+/// Session sync configuration (`[sync]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Sync backend; sync is off while unset
+    pub backend: Option<SyncBackend>,
+    /// Git remote URL, or WebDAV collection URL
+    pub remote: String,
+    /// Branch sessions are kept on (git)
+    pub branch: String,
+    /// User name for the WebDAV server
+    pub username: Option<String>,
+    /// Environment variable holding the WebDAV password
+    pub password_env: String,
+    /// Sync when scry exits
+    pub on_exit: bool,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            remote: String::new(),
+            branch: "main".to_string(),
+            username: None,
+            password_env: "SCRY_SYNC_PASSWORD".to_string(),
+            on_exit: false,
+        }
+    }
+}
+
+/// Whether the UI uses the dark or the light palette.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppearanceMode {
+    /// Always dark (the `[theme]` colors)
+    #[default]
+    Dark,
+    /// Always light
+    Light,
+    /// Light between `light_from` and `light_until` local time
+    Schedule,
+    /// Follow the macOS or GNOME system appearance
+    System,
+}
+
+/// Light/dark switching (`[appearance]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AppearanceConfig {
+    pub mode: AppearanceMode,
+    /// Local time the light theme starts (`HH:MM`, schedule mode)
+    pub light_from: String,
+    /// Local time the dark theme starts again (`HH:MM`, schedule mode)
+    pub light_until: String,
+    /// How often the schedule or system appearance is checked
+    pub check_interval_secs: u64,
+}
+
+impl Default for AppearanceConfig {
+    fn default() -> Self {
+        Self {
+            mode: AppearanceMode::Dark,
+            light_from: "08:00".to_string(),
+            light_until: "19:00".to_string(),
+            check_interval_secs: 60,
         }
     }
 }
 
+/// Corner toasts are stacked in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToastPosition {
+    #[default]
+    TopRight,
+    BottomRight,
+    BottomCenter,
+}
+
+/// How toasts appear and leave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToastAnimation {
+    #[default]
+    None,
+    /// Slide in from the edge and back out
+    Slide,
+    /// Type the message in and erase it again
+    Fade,
+}
+
+/// Toast placement (`[toasts]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ToastConfig {
+    pub position: ToastPosition,
+    /// Width in columns, borders included
+    pub width: u16,
+    /// Toasts shown at once; older ones are dropped
+    pub max_visible: usize,
+    pub animation: ToastAnimation,
+}
+
+impl Default for ToastConfig {
+    fn default() -> Self {
+        Self {
+            position: ToastPosition::default(),
+            width: 40,
+            max_visible: 3,
+            animation: ToastAnimation::default(),
+        }
+    }
+}
+
+/// Live session sharing (`[share]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShareConfig {
+    /// Address `/share` listens on
+    pub bind: String,
+    /// Name your prompts are attributed to; defaults to the login name
+    pub name: Option<String>,
+}
+
+impl Default for ShareConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:8766".to_string(),
+            name: None,
+        }
+    }
+}
+
+/// How copied text reaches the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardBackend {
+    /// A clipboard tool on a local machine, the terminal (OSC 52) over SSH
+    /// or without one
+    #[default]
+    Auto,
+    /// Only a clipboard tool such as `pbcopy`, `wl-copy` or `xclip`
+    Native,
+    /// Only the terminal, with an OSC 52 escape sequence
+    Osc52,
+}
+
+/// Copying to the clipboard (`[clipboard]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub backend: ClipboardBackend,
+}
+
+/// Allowlist for `${env:...}` and `${file:...}` prompt variables (`[variables]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct VariablesConfig {
+    /// Environment variables that may be read; a trailing `*` matches a prefix
+    pub env: Vec<String>,
+    /// Files or directories whose contents may be read
+    pub files: Vec<PathBuf>,
+}
+
+/// One transform in the outgoing message pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreprocessStep {
+    /// Strip surrounding whitespace and trailing spaces
+    Trim,
+    /// Replace `{{name}}` with `[preprocess.templates]` entries
+    ExpandTemplates,
+    /// Replace tokens that look like API keys with `[REDACTED]`
+    RedactSecrets,
+    /// Append `signature` to every message
+    AppendSignature,
+}
+
+/// Transforms applied to messages before sending (`[preprocess]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PreprocessConfig {
+    /// Steps to run, in order
+    pub steps: Vec<PreprocessStep>,
+    /// Snippets for `{{name}}` placeholders
+    pub templates: BTreeMap<String, String>,
+    /// Text appended by the `append_signature` step
+    pub signature: String,
+}
+
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        Self {
+            steps: vec![PreprocessStep::Trim],
+            templates: BTreeMap::new(),
+            signature: String::new(),
+        }
+    }
+}
+
+/// How a conversation too long for the context window is shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Send everything and let the provider refuse or cut it
+    Off,
+    /// Drop the oldest messages that do not fit
+    #[default]
+    Truncate,
+    /// Send only the last `window_messages` messages (fewer if they do not fit)
+    SlidingWindow,
+    /// Replace the messages that do not fit with a summary the model writes
+    Summarize,
+}
+
+/// Fitting the conversation into the model's context window (`[context]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ContextConfig {
+    pub strategy: ContextStrategy,
+    /// Messages kept by the `sliding_window` strategy
+    pub window_messages: usize,
+    /// Tokens left free for the reply
+    pub reserve_tokens: usize,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ContextStrategy::default(),
+            window_messages: 20,
+            reserve_tokens: 1024,
+        }
+    }
+}
+
+/// Whether a tool may run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Run without asking
+    Allow,
+    /// Ask before every run (unless a choice was remembered)
+    #[default]
+    Ask,
+    /// Never run
+    Deny,
+}
+
+/// Tool permissions (`[tools]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// Level for tools not listed in `permissions`
+    pub default: Permission,
+    /// Level per tool name (`[tools.permissions]`)
+    pub permissions: BTreeMap<String, Permission>,
+    /// Per-project levels, keyed by directory (`[tools.projects."~/code/app"]`)
+    pub projects: BTreeMap<PathBuf, BTreeMap<String, Permission>>,
+    /// Directory the file tools are confined to (default: the working directory)
+    pub root: Option<PathBuf>,
+    /// Files or directories under `root` the file tools may use; empty allows all of it
+    pub paths: Vec<PathBuf>,
+}
+
+/// Price of a model (`[pricing."model"]`), in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ModelPrice {
+    /// Per million input (prompt) tokens
+    pub input: f64,
+    /// Per million output (completion) tokens
+    pub output: f64,
+}
+
+/// Spending caps (`[budget]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Warn once spending reaches this fraction of a cap
+    pub warn_at: f64,
+    /// Ask before sending once a cap is reached, instead of only warning
+    pub block: bool,
+    /// Caps per provider storage key (`[budget.providers.anthropic]`)
+    pub providers: BTreeMap<String, SpendLimit>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            warn_at: 0.8,
+            block: false,
+            providers: BTreeMap::new(),
+        }
+    }
+}
+
+/// What a provider may spend, in USD (`[budget.providers.<name>]`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpendLimit {
+    /// Per UTC day
+    pub daily: Option<f64>,
+    /// Per UTC calendar month
+    pub monthly: Option<f64>,
+}
+
 /// Main application configuration.
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(default)]
@@ -223,19 +908,124 @@ pub struct Config {
     pub welcome: WelcomeConfig,
     pub llm: LlmConfigFile,
     pub theme: ThemeConfig,
+    pub appearance: AppearanceConfig,
+    pub toasts: ToastConfig,
+    pub storage: StorageConfig,
+    pub sync: SyncConfig,
+    pub share: ShareConfig,
+    pub clipboard: ClipboardConfig,
+    pub variables: VariablesConfig,
+    pub preprocess: PreprocessConfig,
+    pub context: ContextConfig,
+    pub tools: ToolsConfig,
+    /// Prices per model name or prefix, over the built-in table (`[pricing]`)
+    pub pricing: BTreeMap<String, ModelPrice>,
+    pub budget: BudgetConfig,
 }
 
+/// Name of the per-project config file, looked up from the current directory.
+pub const PROJECT_FILE: &str = ".scry.toml";
+
+/// `[llm]` settings a project's `.scry.toml` may set.
+pub const PROJECT_LLM_KEYS: &[&str] = &[
+    "provider",
+    "model",
+    "models",
+    "system_prompt",
+    "temperature",
+    "max_tokens",
+    "reasoning",
+];
+
 impl Config {
-    /// Returns the default config file path: ~/.config/scry-cli/config.toml
+    /// Returns the default config file path: ~/.config/scry-cli/config.toml,
+    /// or the active profile's `profiles/<name>/config.toml` beside it.
     pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|p| profiles::scoped(p.join("scry-cli")).join("config.toml"))
+    }
+
+    /// Returns the main config file path, which profiles are layered over.
+    pub fn main_path() -> Option<PathBuf> {
         dirs::config_dir().map(|p| p.join("scry-cli").join("config.toml"))
     }
 
-    /// Load configuration from the default path, falling back to defaults.
+    /// Load configuration from the default path, with the project's
+    /// `.scry.toml` over it, falling back to defaults. A project file that
+    /// can't be used is left out (see [`Config::load_project`]).
     pub fn load() -> Self {
-        Self::default_path()
-            .and_then(|path| Self::load_from_path(&path).ok())
-            .unwrap_or_default()
+        let Some(main) = Self::main_path() else {
+            return Self::default();
+        };
+        let profile = Self::default_path().filter(|path| *path != main);
+        let project = Self::project_path().and_then(|path| Self::load_project(&path).ok());
+        Self::load_layered(&main, profile.as_deref(), project).unwrap_or_default()
+    }
+
+    /// Load the config file at `main` with the profile's file at `profile`
+    /// and then the `project` settings layered over it. Either file may be
+    /// missing.
+    pub fn load_layered(
+        main: &Path,
+        profile: Option<&Path>,
+        project: Option<toml::Table>,
+    ) -> anyhow::Result<Self> {
+        let mut table = toml::Table::new();
+        for path in std::iter::once(main).chain(profile) {
+            match std::fs::read_to_string(path) {
+                Ok(contents) => profiles::merge(&mut table, toml::from_str(&contents)?),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if let Some(project) = project {
+            profiles::merge(&mut table, project);
+        }
+        Ok(table.try_into()?)
+    }
+
+    /// The nearest `.scry.toml` in the current directory or its parents.
+    pub fn project_path() -> Option<PathBuf> {
+        let dir = std::env::current_dir().ok()?;
+        Self::find_project_file(&dir)
+    }
+
+    /// The nearest `.scry.toml` in `dir` or its parents.
+    pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Read the project settings in the `.scry.toml` at `path`.
+    ///
+    /// A repository can pin the model choice for everyone working in it, but
+    /// nothing that could send a user's credentials or files elsewhere, so
+    /// only the `[llm]` keys in [`PROJECT_LLM_KEYS`] are accepted.
+    pub fn load_project(path: &Path) -> anyhow::Result<toml::Table> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&contents)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        for (section, value) in &table {
+            let keys = match (section.as_str(), value) {
+                ("llm", toml::Value::Table(llm)) => llm.keys(),
+                _ => bail!("{} can only set [llm] settings, not `{}`", path.display(), section),
+            };
+            for key in keys {
+                if !PROJECT_LLM_KEYS.contains(&key.as_str()) {
+                    bail!(
+                        "{} can't set `llm.{}`; a project may set {}",
+                        path.display(),
+                        key,
+                        PROJECT_LLM_KEYS.join(", ")
+                    );
+                }
+            }
+        }
+        // Check the values too, so a bad file can't break the whole config
+        toml::Table::try_into::<Config>(table.clone())
+            .with_context(|| format!("Invalid settings in {}", path.display()))?;
+        Ok(table)
     }
 
     /// Load configuration from a specific path.
@@ -266,6 +1056,16 @@ impl Config {
         std::fs::write(path, contents)?;
         Ok(())
     }
+
+    /// This config with the palette for `appearance`; prefixes, layout and
+    /// everything else are kept.
+    pub fn with_appearance(&self, appearance: Appearance) -> Config {
+        let mut config = self.clone();
+        if appearance == Appearance::Light {
+            config.theme = config.theme.light();
+        }
+        config
+    }
 }
 
 impl ColorConfig {
@@ -386,4 +1186,55 @@ impl ThemeConfig {
     pub fn bg_secondary(&self) -> ratatui::style::Color {
         Self::to_color(&self.bg_secondary)
     }
+
+    /// Get input text color.
+    pub fn fg_primary(&self) -> ratatui::style::Color {
+        Self::to_color(&self.fg_primary)
+    }
+
+    /// The built-in light palette, keeping this theme's prefixes and layout.
+    pub fn light(&self) -> Self {
+        Self {
+            menu_bg: [245, 245, 248],
+            menu_shadow: [200, 200, 210],
+            menu_selected_bg: [210, 220, 240],
+            menu_selected_fg: [0, 90, 160],
+            menu_unselected_fg: [90, 90, 110],
+            menu_separator: [200, 200, 210],
+            menu_border: [160, 160, 180],
+            menu_input_bg: [230, 230, 236],
+            status_ready: [0, 140, 60],
+            status_streaming: [0, 100, 200],
+            status_error: [200, 40, 40],
+            status_not_configured: [200, 40, 40],
+            bg_primary: [250, 250, 252],
+            bg_secondary: [236, 236, 242],
+            fg_primary: [30, 30, 35],
+            user_color: Some([0, 110, 160]),
+            assistant_color: Some([20, 120, 50]),
+            ..self.clone()
+        }
+    }
+
+    /// Get the message color for a role.
+    pub fn role_color(&self, role: Role) -> ratatui::style::Color {
+        match role {
+            Role::User => self
+                .user_color
+                .as_ref()
+                .map_or(ratatui::style::Color::Cyan, Self::to_color),
+            Role::Assistant => self
+                .assistant_color
+                .as_ref()
+                .map_or(ratatui::style::Color::Green, Self::to_color),
+        }
+    }
+
+    /// Get the prefix for a role.
+    pub fn role_prefix(&self, role: Role) -> &str {
+        match role {
+            Role::User => &self.user_prefix,
+            Role::Assistant => &self.assistant_prefix,
+        }
+    }
 }
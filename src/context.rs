@@ -0,0 +1,254 @@
+//! Fitting the conversation into the model's context window (`[context]`).
+//!
+//! Before a request goes out, the oldest messages that no longer fit are
+//! dropped (`truncate`), only the most recent ones are sent at all
+//! (`sliding_window`), or the model is first asked to summarize the ones
+//! that do not fit, and the summary is sent in their place (`summarize`).
+//! The newest message is always sent, and what is sent starts with a prompt.
+
+use crate::config::{ContextConfig, ContextStrategy};
+use crate::llm::{ChatMessage, Provider};
+use crate::tokens;
+
+/// Request for a summary of the oldest part of the conversation.
+pub const SUMMARY_PROMPT: &str = "Summarize the conversation below so that it can be continued \
+without it. Keep facts, decisions, names, numbers, code and open questions; leave out \
+pleasantries. Reply with the summary only.";
+
+/// Heading the summary is sent under, ahead of the first message kept.
+pub const SUMMARY_HEADING: &str = "Summary of the earlier conversation:";
+
+/// Heading pinned messages are sent under when they would be left out.
+pub const PINNED_HEADING: &str = "Pinned from earlier in the conversation:";
+
+/// A summary standing in for the oldest messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextSummary {
+    /// Number of leading messages it replaces
+    pub covers: usize,
+    pub text: String,
+}
+
+/// The messages to send, and how many were left out.
+#[derive(Debug, Clone)]
+pub struct Fitted {
+    pub messages: Vec<ChatMessage>,
+    /// Leading messages not sent as they are (dropped or summarized)
+    pub dropped: usize,
+}
+
+/// Tokens the conversation may use in a `window`, leaving room for the reply.
+pub fn budget(window: usize, reserve: usize) -> usize {
+    // Never reserve more than half, or small windows would fit nothing
+    window.saturating_sub(reserve.min(window / 2))
+}
+
+/// First message at or after `start` that is a prompt; the last message if none is.
+fn prompt_from(messages: &[ChatMessage], mut start: usize) -> usize {
+    while start + 1 < messages.len() && messages[start].role != "user" {
+        start += 1;
+    }
+    start
+}
+
+/// Number of leading messages to leave out so the rest fits in `budget`.
+pub fn overflow(messages: &[ChatMessage], budget: usize, provider: Provider) -> usize {
+    let sizes: Vec<usize> = messages
+        .iter()
+        .map(|m| tokens::message_tokens(&m.content, provider))
+        .collect();
+    let mut total: usize = sizes.iter().sum();
+    let mut drop = 0;
+    while drop + 1 < messages.len() && total > budget {
+        total -= sizes[drop];
+        drop += 1;
+    }
+    prompt_from(messages, drop)
+}
+
+/// Shorten `messages` to fit `budget` the way `config` says.
+pub fn fit(
+    mut messages: Vec<ChatMessage>,
+    config: &ContextConfig,
+    budget: usize,
+    provider: Provider,
+    summary: Option<&ContextSummary>,
+) -> Fitted {
+    let mut dropped = match (config.strategy, summary) {
+        (ContextStrategy::Off, _) => {
+            return Fitted {
+                messages,
+                dropped: 0,
+            }
+        }
+        (ContextStrategy::SlidingWindow, _) => prompt_from(
+            &messages,
+            messages.len().saturating_sub(config.window_messages.max(1)),
+        ),
+        (ContextStrategy::Summarize, Some(summary)) if summary.covers < messages.len() => {
+            let start = prompt_from(&messages, summary.covers);
+            let first = &mut messages[start];
+            first.content = format!("{}\n{}\n\n{}", SUMMARY_HEADING, summary.text, first.content);
+            start
+        }
+        _ => 0,
+    };
+    messages.drain(..dropped);
+    let more = overflow(&messages, budget, provider);
+    messages.drain(..more);
+    dropped += more;
+    Fitted { messages, dropped }
+}
+
+/// `messages` as a `Speaker: text` transcript.
+fn transcript(messages: &[ChatMessage]) -> String {
+    let mut text = String::new();
+    for message in messages {
+        let speaker = match message.role.as_str() {
+            "user" => "User",
+            "tool" => "Tool",
+            _ => "Assistant",
+        };
+        text.push_str(&format!("{}: {}\n\n", speaker, message.content));
+    }
+    text
+}
+
+/// Send the `pinned` messages that were left out ahead of the first message
+/// that is sent (a prompt), so they stay in the model's view.
+pub fn restore_pinned(messages: &mut [ChatMessage], pinned: &[ChatMessage]) {
+    let Some(first) = messages.first_mut() else {
+        return;
+    };
+    if pinned.is_empty() {
+        return;
+    }
+    first.content = format!("{}\n{}{}", PINNED_HEADING, transcript(pinned), first.content);
+}
+
+/// The request for a summary of `messages`, folding in the `previous` summary
+/// of what came before them.
+pub fn summary_request(
+    messages: &[ChatMessage],
+    previous: Option<&ContextSummary>,
+) -> Vec<ChatMessage> {
+    let mut text = format!("{}\n\n", SUMMARY_PROMPT);
+    if let Some(previous) = previous {
+        text.push_str(&format!("{}\n{}\n\n", SUMMARY_HEADING, previous.text));
+    }
+    text.push_str(&transcript(messages));
+    vec![ChatMessage {
+        role: "user".to_string(),
+        content: text.trim_end().to_string(),
+        ..ChatMessage::default()
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(count: usize) -> Vec<ChatMessage> {
+        (0..count)
+            .map(|i| ChatMessage {
+                role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                content: format!("message {} {}", i, "word ".repeat(20)),
+                ..ChatMessage::default()
+            })
+            .collect()
+    }
+
+    fn config(strategy: ContextStrategy) -> ContextConfig {
+        ContextConfig {
+            strategy,
+            window_messages: 3,
+            ..ContextConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_budget_keeps_room_for_the_reply() {
+        assert_eq!(budget(200_000, 1024), 198_976);
+        assert_eq!(budget(1000, 1024), 500);
+    }
+
+    #[test]
+    fn test_truncate_drops_oldest_and_starts_with_a_prompt() {
+        let messages = chat(5);
+        let each = tokens::message_tokens(&messages[0].content, Provider::OpenAI);
+        let fitted = fit(
+            messages.clone(),
+            &config(ContextStrategy::Truncate),
+            each * 2,
+            Provider::OpenAI,
+            None,
+        );
+        // Two would fit, but the second to last is a reply
+        assert_eq!(fitted.dropped, 4);
+        assert_eq!(fitted.messages.len(), 1);
+        assert_eq!(fitted.messages[0].content, messages[4].content);
+
+        let all = fit(messages, &config(ContextStrategy::Truncate), usize::MAX, Provider::OpenAI, None);
+        assert_eq!(all.dropped, 0);
+    }
+
+    #[test]
+    fn test_off_and_sliding_window() {
+        let off = fit(chat(5), &config(ContextStrategy::Off), 1, Provider::OpenAI, None);
+        assert_eq!(off.messages.len(), 5);
+
+        let window = fit(
+            chat(6),
+            &config(ContextStrategy::SlidingWindow),
+            usize::MAX,
+            Provider::OpenAI,
+            None,
+        );
+        // The last three start with a reply, so the window starts at the next prompt
+        assert_eq!(window.dropped, 4);
+        assert_eq!(window.messages[0].role, "user");
+    }
+
+    #[test]
+    fn test_summary_replaces_covered_messages() {
+        let summary = ContextSummary {
+            covers: 2,
+            text: "They said hello.".to_string(),
+        };
+        let fitted = fit(
+            chat(4),
+            &config(ContextStrategy::Summarize),
+            usize::MAX,
+            Provider::OpenAI,
+            Some(&summary),
+        );
+        assert_eq!(fitted.dropped, 2);
+        assert!(fitted.messages[0]
+            .content
+            .starts_with("Summary of the earlier conversation:\nThey said hello.\n\nmessage 2"));
+
+        let request = summary_request(&chat(2), Some(&summary));
+        assert_eq!(request.len(), 1);
+        assert!(request[0].content.contains("They said hello."));
+        assert!(request[0].content.contains("\n\nAssistant: message 1"));
+    }
+
+    #[test]
+    fn test_pinned_messages_are_sent_ahead_of_the_first_kept_prompt() {
+        let messages = chat(5);
+        let mut fitted = fit(
+            messages.clone(),
+            &config(ContextStrategy::SlidingWindow),
+            usize::MAX,
+            Provider::OpenAI,
+            None,
+        );
+        assert_eq!(fitted.dropped, 2);
+        restore_pinned(&mut fitted.messages, &messages[1..2]);
+        assert_eq!(fitted.messages.len(), 3);
+        assert!(fitted.messages[0]
+            .content
+            .starts_with("Pinned from earlier in the conversation:\nAssistant: message 1"));
+        assert!(fitted.messages[0].content.ends_with(&messages[2].content));
+    }
+}
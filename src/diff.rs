@@ -0,0 +1,254 @@
+//! Line-based diffs with per-hunk accept/reject.
+//!
+//! A [`FileDiff`] splits the difference between two texts into independent
+//! hunks. Each hunk can be accepted or rejected on its own and the result is
+//! rebuilt with [`FileDiff::apply`].
+
+use similar::{DiffTag, TextDiff};
+
+/// Number of unchanged lines shown around each hunk.
+pub const CONTEXT_LINES: usize = 3;
+
+/// Kind of a line within a hunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    /// Unchanged line shown for context
+    Context,
+    /// Line present only in the new text
+    Added,
+    /// Line present only in the old text
+    Removed,
+}
+
+impl DiffLineKind {
+    /// Get the unified-diff prefix for this kind.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            DiffLineKind::Context => " ",
+            DiffLineKind::Added => "+",
+            DiffLineKind::Removed => "-",
+        }
+    }
+}
+
+/// A single displayed line of a hunk (without its line ending).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// A contiguous change between the old and new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line number of the first displayed line in the old text
+    pub old_start: usize,
+    /// 1-based line number of the first displayed line in the new text
+    pub new_start: usize,
+    /// Lines to display, including surrounding context
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    /// Number of added lines in this hunk.
+    pub fn added(&self) -> usize {
+        self.count(DiffLineKind::Added)
+    }
+
+    /// Number of removed lines in this hunk.
+    pub fn removed(&self) -> usize {
+        self.count(DiffLineKind::Removed)
+    }
+
+    /// Unified-diff style header, e.g. `@@ -1,3 +1,4 @@`.
+    pub fn header(&self) -> String {
+        let context = self.count(DiffLineKind::Context);
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start,
+            context + self.removed(),
+            self.new_start,
+            context + self.added()
+        )
+    }
+
+    fn count(&self, kind: DiffLineKind) -> usize {
+        self.lines.iter().filter(|l| l.kind == kind).count()
+    }
+}
+
+/// Piece of the old text: either unchanged or replaced by a hunk.
+#[derive(Debug, Clone)]
+enum Segment {
+    Equal(Vec<String>),
+    Change {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+/// Difference between two texts, split into hunks.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    segments: Vec<Segment>,
+    hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// Compute the line diff between `old` and `new`.
+    pub fn new(old: &str, new: &str) -> Self {
+        let diff = TextDiff::from_lines(old, new);
+        let old_lines = diff.old_slices();
+        let new_lines = diff.new_slices();
+        let owned = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        // Merge adjacent non-equal ops so each change region becomes one segment
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut positions = Vec::new();
+        for op in diff.ops() {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if tag == DiffTag::Equal {
+                segments.push(Segment::Equal(owned(&old_lines[old_range])));
+                continue;
+            }
+            match segments.last_mut() {
+                Some(Segment::Change { old, new }) => {
+                    old.extend(owned(&old_lines[old_range]));
+                    new.extend(owned(&new_lines[new_range]));
+                }
+                _ => {
+                    positions.push((old_range.start, new_range.start));
+                    segments.push(Segment::Change {
+                        old: owned(&old_lines[old_range]),
+                        new: owned(&new_lines[new_range]),
+                    });
+                }
+            }
+        }
+
+        let mut hunks = Vec::new();
+        let mut change_index = 0;
+        for (i, segment) in segments.iter().enumerate() {
+            let Segment::Change { old, new } = segment else {
+                continue;
+            };
+            let (old_pos, new_pos) = positions[change_index];
+            change_index += 1;
+
+            let before = match i.checked_sub(1).map(|p| &segments[p]) {
+                Some(Segment::Equal(lines)) => &lines[lines.len().saturating_sub(CONTEXT_LINES)..],
+                _ => &[][..],
+            };
+            let after = match segments.get(i + 1) {
+                Some(Segment::Equal(lines)) => &lines[..lines.len().min(CONTEXT_LINES)],
+                _ => &[][..],
+            };
+
+            let line = |kind, text: &String| DiffLine {
+                kind,
+                text: text.trim_end_matches(['\r', '\n']).to_string(),
+            };
+            let mut lines = Vec::new();
+            lines.extend(before.iter().map(|t| line(DiffLineKind::Context, t)));
+            lines.extend(old.iter().map(|t| line(DiffLineKind::Removed, t)));
+            lines.extend(new.iter().map(|t| line(DiffLineKind::Added, t)));
+            lines.extend(after.iter().map(|t| line(DiffLineKind::Context, t)));
+
+            hunks.push(Hunk {
+                old_start: old_pos + 1 - before.len(),
+                new_start: new_pos + 1 - before.len(),
+                lines,
+            });
+        }
+
+        Self { segments, hunks }
+    }
+
+    /// Get the hunks of this diff.
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// Check if the two texts are identical.
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Rebuild the text, taking the new side of each hunk marked as accepted.
+    ///
+    /// Hunks without a corresponding entry in `accepted` are rejected.
+    pub fn apply(&self, accepted: &[bool]) -> String {
+        let mut output = String::new();
+        let mut hunk = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Equal(lines) => output.extend(lines.iter().map(String::as_str)),
+                Segment::Change { old, new } => {
+                    let lines = if accepted.get(hunk).copied().unwrap_or(false) {
+                        new
+                    } else {
+                        old
+                    };
+                    output.extend(lines.iter().map(String::as_str));
+                    hunk += 1;
+                }
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    const NEW: &str = "one\nTWO\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\n";
+
+    #[test]
+    fn test_identical_texts_have_no_hunks() {
+        let diff = FileDiff::new(OLD, OLD);
+        assert!(diff.is_empty());
+        assert_eq!(diff.apply(&[]), OLD);
+    }
+
+    #[test]
+    fn test_hunks_are_split_by_unchanged_lines() {
+        let diff = FileDiff::new(OLD, NEW);
+        assert_eq!(diff.hunks().len(), 2);
+
+        let first = &diff.hunks()[0];
+        assert_eq!(first.removed(), 1);
+        assert_eq!(first.added(), 1);
+        assert_eq!(first.old_start, 1);
+        assert_eq!(first.header(), "@@ -1,5 +1,5 @@");
+
+        let second = &diff.hunks()[1];
+        assert_eq!(second.removed(), 0);
+        assert_eq!(second.added(), 1);
+        assert_eq!(second.lines.last().unwrap().text, "eleven");
+    }
+
+    #[test]
+    fn test_apply_all_and_none() {
+        let diff = FileDiff::new(OLD, NEW);
+        assert_eq!(diff.apply(&[true, true]), NEW);
+        assert_eq!(diff.apply(&[false, false]), OLD);
+        assert_eq!(diff.apply(&[]), OLD);
+    }
+
+    #[test]
+    fn test_apply_partial() {
+        let diff = FileDiff::new(OLD, NEW);
+        let result = diff.apply(&[false, true]);
+        assert!(result.starts_with("one\ntwo\n"));
+        assert!(result.ends_with("ten\neleven\n"));
+    }
+
+    #[test]
+    fn test_diff_line_prefix() {
+        assert_eq!(DiffLineKind::Added.prefix(), "+");
+        assert_eq!(DiffLineKind::Removed.prefix(), "-");
+        assert_eq!(DiffLineKind::Context.prefix(), " ");
+    }
+}
@@ -0,0 +1,182 @@
+//! Crash-safe file writes.
+//!
+//! Files are written to a temporary sibling and renamed into place, so a
+//! crash mid-write leaves either the old contents or the new ones, never a
+//! truncated mix. With `fsync` the data (and the rename) reach the disk
+//! before the call returns.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+/// How a file is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Write a temporary file and rename it over the target
+    pub atomic: bool,
+    /// Flush the file and its directory to disk before returning
+    pub fsync: bool,
+    /// Unix permissions for a newly written file
+    pub mode: Option<u32>,
+}
+
+/// Numbers temporary files, so concurrent writes in one process never share one.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl WriteOptions {
+    /// Atomic, synced and owner-only: for credentials.
+    pub const CRITICAL: Self = Self {
+        atomic: true,
+        fsync: true,
+        mode: Some(0o600),
+    };
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            atomic: true,
+            fsync: false,
+            mode: None,
+        }
+    }
+}
+
+/// Write `contents` to `path`, creating parent directories if needed.
+pub fn write(path: &Path, contents: &[u8], options: WriteOptions) -> Result<()> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    if !options.atomic {
+        return write_file(path, contents, options);
+    }
+
+    let name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    replace(path, &temp, contents, options)?;
+    if options.fsync {
+        // The rename is only durable once the directory entry is
+        #[cfg(unix)]
+        if let Some(parent) = parent {
+            File::open(parent)
+                .and_then(|dir| dir.sync_all())
+                .with_context(|| format!("Failed to sync directory: {}", parent.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `contents` to `temp` and rename it over `path`, removing `temp` on failure.
+fn replace(path: &Path, temp: &Path, contents: &[u8], options: WriteOptions) -> Result<()> {
+    if let Err(e) = write_file(temp, contents, options) {
+        let _ = fs::remove_file(temp);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(temp, path) {
+        let _ = fs::remove_file(temp);
+        return Err(e).with_context(|| format!("Failed to replace {}", path.display()));
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, contents: &[u8], options: WriteOptions) -> Result<()> {
+    let mut open = OpenOptions::new();
+    open.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if let Some(mode) = options.mode {
+        open.mode(mode);
+    }
+    let mut file = open
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+    // `mode` only applies to new files; tighten existing ones too
+    #[cfg(unix)]
+    if let Some(mode) = options.mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    file.write_all(contents)
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+    if options.fsync {
+        file.sync_all()
+            .with_context(|| format!("Failed to sync file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_write_replaces_and_leaves_no_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.json");
+        write(&path, b"old", WriteOptions::default()).unwrap();
+        write(&path, b"new", WriteOptions::CRITICAL).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let entries = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mode_is_applied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret");
+        write(&path, b"x", WriteOptions::CRITICAL).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+    }
+
+    #[test]
+    fn test_failed_write_keeps_old_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state");
+        write(&path, b"old", WriteOptions::default()).unwrap();
+        // A directory in the way of the temp file makes the write fail
+        let temp = dir.path().join(".state.tmp");
+        fs::create_dir(&temp).unwrap();
+        assert!(replace(&path, &temp, b"new", WriteOptions::default()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_concurrent_writes_use_their_own_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state");
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        write(&path, format!("writer {}", i).as_bytes(), WriteOptions::default())
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(fs::read_to_string(&path).unwrap().starts_with("writer "));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
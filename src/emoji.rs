@@ -0,0 +1,175 @@
+//! Emoji shortcode expansion for text inputs.
+//!
+//! Typing the closing colon of a known `:shortcode:` replaces it with the
+//! emoji. While a shortcode is being typed, [`completions`] lists candidates
+//! for the completion popup. Everything works on a plain `String` and byte
+//! cursor, so any text field can opt in.
+
+/// Known shortcodes, in completion order.
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("bug", "🐛"),
+    ("check", "✅"),
+    ("clap", "👏"),
+    ("confused", "😕"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fire", "🔥"),
+    ("grin", "😁"),
+    ("heart", "❤️"),
+    ("hourglass", "⏳"),
+    ("joy", "😂"),
+    ("laughing", "😆"),
+    ("lock", "🔒"),
+    ("memo", "📝"),
+    ("ok_hand", "👌"),
+    ("party", "🥳"),
+    ("pray", "🙏"),
+    ("question", "❓"),
+    ("rocket", "🚀"),
+    ("shrug", "🤷"),
+    ("smile", "😄"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunglasses", "😎"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("zap", "⚡"),
+];
+
+/// Maximum number of completions offered at once.
+const MAX_COMPLETIONS: usize = 5;
+
+/// Look up the emoji for a shortcode name (without colons).
+pub fn lookup(name: &str) -> Option<&'static str> {
+    SHORTCODES
+        .iter()
+        .find(|(code, _)| *code == name)
+        .map(|(_, emoji)| *emoji)
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')
+}
+
+/// The partial shortcode name being typed before `cursor`, if any.
+///
+/// Requires the opening colon to start a word so times like `10:30` and
+/// URLs are left alone.
+pub fn partial_before(text: &str, cursor: usize) -> Option<&str> {
+    let before = &text[..cursor];
+    let colon = before.rfind(':')?;
+    let name = &before[colon + 1..];
+    let starts_word = before[..colon]
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace());
+    (starts_word && name.chars().all(is_shortcode_char)).then_some(name)
+}
+
+/// Shortcodes starting with the partial name before the cursor.
+pub fn completions(text: &str, cursor: usize) -> Vec<(&'static str, &'static str)> {
+    match partial_before(text, cursor) {
+        Some(name) if !name.is_empty() => SHORTCODES
+            .iter()
+            .filter(|(code, _)| code.starts_with(name))
+            .take(MAX_COMPLETIONS)
+            .copied()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Replace a complete `:shortcode:` ending at `cursor` with its emoji.
+///
+/// Returns true if the text changed; the cursor then sits after the emoji.
+pub fn expand_before_cursor(text: &mut String, cursor: &mut usize) -> bool {
+    let Some(without_colon) = cursor.checked_sub(1) else {
+        return false;
+    };
+    if !text[..*cursor].ends_with(':') {
+        return false;
+    }
+    let Some(name) = partial_before(text, without_colon) else {
+        return false;
+    };
+    let Some(emoji) = lookup(name) else {
+        return false;
+    };
+    let start = without_colon - name.len() - 1;
+    text.replace_range(start..*cursor, emoji);
+    *cursor = start + emoji.len();
+    true
+}
+
+/// Complete the partial shortcode before `cursor` with `code`'s emoji.
+pub fn complete(text: &mut String, cursor: &mut usize, code: &str) -> bool {
+    let (Some(name), Some(emoji)) = (partial_before(text, *cursor), lookup(code)) else {
+        return false;
+    };
+    let start = *cursor - name.len() - 1;
+    text.replace_range(start..*cursor, emoji);
+    *cursor = start + emoji.len();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_str(s: &str) -> (String, usize) {
+        let mut text = String::new();
+        let mut cursor = 0;
+        for c in s.chars() {
+            text.insert(cursor, c);
+            cursor += c.len_utf8();
+            if c == ':' {
+                expand_before_cursor(&mut text, &mut cursor);
+            }
+        }
+        (text, cursor)
+    }
+
+    #[test]
+    fn test_expands_known_shortcodes_while_typing() {
+        let (text, cursor) = type_str("well :shrug: ok");
+        assert_eq!(text, "well 🤷 ok");
+        assert_eq!(cursor, text.len());
+    }
+
+    #[test]
+    fn test_leaves_unknown_and_embedded_colons() {
+        assert_eq!(type_str(":nope:").0, ":nope:");
+        assert_eq!(type_str("at 10:30: done").0, "at 10:30: done");
+        assert_eq!(type_str("http://x.io:tada:").0, "http://x.io:tada:");
+    }
+
+    #[test]
+    fn test_completions_for_partial_name() {
+        let codes: Vec<&str> = completions("hi :th", 6).iter().map(|(c, _)| *c).collect();
+        assert_eq!(codes, vec!["thinking", "thumbsdown", "thumbsup"]);
+        assert!(completions("hi :", 4).is_empty());
+        assert!(completions("hi there", 8).is_empty());
+    }
+
+    #[test]
+    fn test_complete_replaces_partial() {
+        let mut text = "go :roc".to_string();
+        let mut cursor = text.len();
+        assert!(complete(&mut text, &mut cursor, "rocket"));
+        assert_eq!(text, "go 🚀");
+        assert_eq!(cursor, text.len());
+    }
+}
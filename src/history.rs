@@ -0,0 +1,258 @@
+//! Undo/redo log for conversation edits.
+//!
+//! Every conversational action (sending a message, deleting messages,
+//! regenerating a reply, resending an edited prompt) is recorded as the [`Edit`] that reverts it. Undoing
+//! applies that edit and records its own inverse for redo, so the two stacks
+//! always hold exactly what is needed to move back and forth.
+
+use crate::message::Message;
+
+/// Maximum number of actions kept for undo.
+const MAX_UNDO: usize = 100;
+
+/// A reversible change to the message list.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Insert messages at `index`.
+    Insert {
+        index: usize,
+        messages: Vec<Message>,
+    },
+    /// Remove `count` messages starting at `index`.
+    Remove { index: usize, count: usize },
+    /// Replace the message at `index`.
+    Replace { index: usize, message: Box<Message> },
+    /// Replace `count` messages starting at `index` with `messages`.
+    Splice {
+        index: usize,
+        count: usize,
+        messages: Vec<Message>,
+    },
+}
+
+impl Edit {
+    /// Apply the edit, returning the edit that reverts it.
+    ///
+    /// Returns `None` if the edit no longer fits the message list.
+    fn apply(self, messages: &mut Vec<Message>) -> Option<Edit> {
+        match self {
+            Edit::Insert {
+                index,
+                messages: inserted,
+            } => {
+                if index > messages.len() {
+                    return None;
+                }
+                let count = inserted.len();
+                messages.splice(index..index, inserted);
+                Some(Edit::Remove { index, count })
+            }
+            Edit::Remove { index, count } => {
+                if index + count > messages.len() {
+                    return None;
+                }
+                let removed = messages.drain(index..index + count).collect();
+                Some(Edit::Insert {
+                    index,
+                    messages: removed,
+                })
+            }
+            Edit::Replace { index, message } => {
+                let slot = messages.get_mut(index)?;
+                let previous = std::mem::replace(slot, *message);
+                Some(Edit::Replace {
+                    index,
+                    message: Box::new(previous),
+                })
+            }
+            Edit::Splice {
+                index,
+                count,
+                messages: inserted,
+            } => {
+                if index + count > messages.len() {
+                    return None;
+                }
+                let inserted_count = inserted.len();
+                let removed = messages.splice(index..index + count, inserted).collect();
+                Some(Edit::Splice {
+                    index,
+                    count: inserted_count,
+                    messages: removed,
+                })
+            }
+        }
+    }
+}
+
+/// A recorded action: what it was and how to revert it.
+#[derive(Debug, Clone)]
+struct Entry {
+    label: String,
+    revert: Edit,
+}
+
+/// Undo and redo stacks for the conversation.
+#[derive(Debug, Clone, Default)]
+pub struct OperationLog {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl OperationLog {
+    /// Record an action that has just been performed, given the edit that reverts it.
+    ///
+    /// Clears the redo stack, since it no longer applies.
+    pub fn record(&mut self, label: impl Into<String>, revert: Edit) {
+        self.redo.clear();
+        self.undo.push(Entry {
+            label: label.into(),
+            revert,
+        });
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Revert the last action. Returns its label if something was undone.
+    pub fn undo(&mut self, messages: &mut Vec<Message>) -> Option<String> {
+        Self::step(&mut self.undo, &mut self.redo, messages)
+    }
+
+    /// Reapply the last undone action. Returns its label if something was redone.
+    pub fn redo(&mut self, messages: &mut Vec<Message>) -> Option<String> {
+        Self::step(&mut self.redo, &mut self.undo, messages)
+    }
+
+    /// Check whether there is anything to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Check whether there is anything to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget all recorded actions, e.g. when another conversation is loaded.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn step(
+        from: &mut Vec<Entry>,
+        to: &mut Vec<Entry>,
+        messages: &mut Vec<Message>,
+    ) -> Option<String> {
+        let entry = from.pop()?;
+        match entry.revert.apply(messages) {
+            Some(inverse) => {
+                to.push(Entry {
+                    label: entry.label.clone(),
+                    revert: inverse,
+                });
+                Some(entry.label)
+            }
+            // The list changed underneath the log; the remaining entries are stale too
+            None => {
+                from.clear();
+                to.clear();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_undo_and_redo_send() {
+        let mut messages = vec![Message::assistant("welcome".to_string())];
+        let mut log = OperationLog::default();
+
+        messages.push(Message::user("hi".to_string()));
+        messages.push(Message::assistant("hello".to_string()));
+        log.record("send message", Edit::Remove { index: 1, count: 2 });
+
+        assert_eq!(log.undo(&mut messages).as_deref(), Some("send message"));
+        assert_eq!(contents(&messages), vec!["welcome"]);
+        assert!(log.can_redo());
+
+        assert_eq!(log.redo(&mut messages).as_deref(), Some("send message"));
+        assert_eq!(contents(&messages), vec!["welcome", "hi", "hello"]);
+        assert!(log.can_undo());
+    }
+
+    #[test]
+    fn test_replace_round_trip() {
+        let mut messages = vec![Message::assistant("first".to_string())];
+        let mut log = OperationLog::default();
+
+        let old = std::mem::replace(&mut messages[0], Message::assistant("second".to_string()));
+        log.record(
+            "regenerate",
+            Edit::Replace {
+                index: 0,
+                message: Box::new(old),
+            },
+        );
+
+        log.undo(&mut messages);
+        assert_eq!(contents(&messages), vec!["first"]);
+        log.redo(&mut messages);
+        assert_eq!(contents(&messages), vec!["second"]);
+    }
+
+    #[test]
+    fn test_splice_round_trip() {
+        let mut messages = vec![
+            Message::user("a".to_string()),
+            Message::assistant("b".to_string()),
+        ];
+        let mut log = OperationLog::default();
+
+        let old = std::mem::take(&mut messages);
+        messages.push(Message::user("a2".to_string()));
+        log.record(
+            "edit and resend",
+            Edit::Splice {
+                index: 0,
+                count: 1,
+                messages: old,
+            },
+        );
+
+        log.undo(&mut messages);
+        assert_eq!(contents(&messages), vec!["a", "b"]);
+        log.redo(&mut messages);
+        assert_eq!(contents(&messages), vec!["a2"]);
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut messages = vec![Message::user("a".to_string())];
+        let mut log = OperationLog::default();
+        log.record("send message", Edit::Remove { index: 0, count: 1 });
+        log.undo(&mut messages);
+
+        messages.push(Message::user("b".to_string()));
+        log.record("send message", Edit::Remove { index: 0, count: 1 });
+        assert!(!log.can_redo());
+    }
+
+    #[test]
+    fn test_stale_edit_clears_log() {
+        let mut messages = vec![];
+        let mut log = OperationLog::default();
+        log.record("send message", Edit::Remove { index: 0, count: 2 });
+        assert!(log.undo(&mut messages).is_none());
+        assert!(!log.can_undo());
+    }
+}
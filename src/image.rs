@@ -0,0 +1,122 @@
+//! Images attached to prompts (`/attach photo.png`).
+//!
+//! An attached image is read and base64-encoded once, kept with the prompt
+//! it was sent with, and sent again with the rest of the conversation to
+//! models that take image input. The chat shows a placeholder in its place.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::llm::ImageData;
+use crate::ui::format_size;
+
+/// Largest image accepted, the lowest per-image limit of the providers.
+pub const MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// File extensions of the formats every vision API accepts, with their MIME types.
+const FORMATS: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+/// An image attached to a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// File name, for the placeholder
+    pub name: String,
+    /// Size of the file in bytes
+    pub bytes: u64,
+    /// What is sent to the model
+    pub data: ImageData,
+}
+
+impl Image {
+    /// Read the image at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let Some(media_type) = media_type(path) else {
+            bail!(
+                "{} is not a PNG, JPEG, GIF or WebP image",
+                path.display()
+            );
+        };
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read image: {}", path.display()))?;
+        let size = bytes.len() as u64;
+        if size > MAX_BYTES {
+            bail!(
+                "{} is {}; images can be at most {}",
+                path.display(),
+                format_size(size),
+                format_size(MAX_BYTES)
+            );
+        }
+        Ok(Self {
+            name: path
+                .file_name()
+                .map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned()),
+            bytes: size,
+            data: ImageData {
+                media_type: media_type.to_string(),
+                data: STANDARD.encode(&bytes),
+            },
+        })
+    }
+
+    /// Format and size, e.g. "PNG · 1.5 MB".
+    pub fn describe(&self) -> String {
+        let format = self
+            .data
+            .media_type
+            .trim_start_matches("image/")
+            .to_uppercase();
+        format!("{} · {}", format, format_size(self.bytes))
+    }
+}
+
+/// MIME type of an image file, judged by its extension.
+pub fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    FORMATS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, media_type)| *media_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_loads_and_encodes_images() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("Chart.PNG");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let image = Image::load(&path).unwrap();
+        assert_eq!(image.name, "Chart.PNG");
+        assert_eq!(image.data.media_type, "image/png");
+        assert_eq!(image.data.data, "aGVsbG8=");
+        assert_eq!(image.describe(), "PNG · 5 B");
+    }
+
+    #[test]
+    fn test_rejects_other_files_and_large_images() {
+        let dir = TempDir::new().unwrap();
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"text").unwrap();
+        assert!(Image::load(&notes).is_err());
+        assert!(Image::load(&dir.path().join("missing.jpg")).is_err());
+
+        let large = dir.path().join("large.webp");
+        std::fs::write(&large, vec![0u8; MAX_BYTES as usize + 1]).unwrap();
+        let error = Image::load(&large).unwrap_err().to_string();
+        assert!(error.contains("5.0 MB"), "{}", error);
+    }
+}
@@ -0,0 +1,235 @@
+//! Background worker for the slow half of saving a session.
+//!
+//! Saving writes the session record on the calling thread; deriving its
+//! title, counting its tokens and rebuilding its search index happen on a
+//! dedicated thread so none of it holds up the event loop, however long the
+//! history. The worker runs at low priority: it coalesces repeated saves of
+//! the same session, indexes in small chunks and yields between them. The
+//! app polls [`Indexer::poll`] for progress and results.
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::app::estimate_tokens;
+use crate::session::{Session, SessionStore};
+
+/// Messages indexed between progress reports.
+const PROGRESS_EVERY: usize = 64;
+
+/// Reported by the worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexEvent {
+    /// `done` of `total` messages of the current session are indexed
+    Progress { done: usize, total: usize },
+    /// A session was indexed
+    Indexed(IndexResult),
+    /// A queued session was dropped for a newer save of it
+    Superseded,
+    /// Indexing a session failed
+    Failed(String),
+}
+
+/// What the worker worked out for a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexResult {
+    pub id: String,
+    pub title: String,
+    /// Estimated tokens in the whole conversation
+    pub tokens: usize,
+}
+
+/// Handle to the indexing thread.
+pub struct Indexer {
+    jobs: Sender<Session>,
+    events: Receiver<IndexEvent>,
+    /// Sessions queued but not yet indexed
+    pending: usize,
+    /// Progress through the session being indexed
+    pub progress: Option<(usize, usize)>,
+    /// Result for the most recently indexed session
+    pub last: Option<IndexResult>,
+}
+
+impl Indexer {
+    /// Start the worker thread for `store`.
+    pub fn spawn(store: Arc<dyn SessionStore>) -> anyhow::Result<Self> {
+        let (jobs, job_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        thread::Builder::new()
+            .name("scry-indexer".to_string())
+            .spawn(move || run(store.as_ref(), &job_rx, &event_tx))?;
+        Ok(Self {
+            jobs,
+            events,
+            pending: 0,
+            progress: None,
+            last: None,
+        })
+    }
+
+    /// Queue a saved session for indexing.
+    pub fn queue(&mut self, session: Session) {
+        if self.jobs.send(session).is_ok() {
+            self.pending += 1;
+        }
+    }
+
+    /// Whether the worker has sessions left to index.
+    pub fn is_busy(&self) -> bool {
+        self.pending > 0
+    }
+
+    /// Collect what the worker reported since the last call.
+    ///
+    /// Returns the results of sessions indexed meanwhile, and errors.
+    pub fn poll(&mut self) -> Vec<Result<IndexResult, String>> {
+        let mut finished = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(IndexEvent::Progress { done, total }) => self.progress = Some((done, total)),
+                Ok(IndexEvent::Indexed(result)) => {
+                    self.pending = self.pending.saturating_sub(1);
+                    self.progress = None;
+                    self.last = Some(result.clone());
+                    finished.push(Ok(result));
+                }
+                Ok(IndexEvent::Superseded) => self.pending = self.pending.saturating_sub(1),
+                Ok(IndexEvent::Failed(error)) => {
+                    self.pending = self.pending.saturating_sub(1);
+                    self.progress = None;
+                    finished.push(Err(error));
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.pending = 0;
+                    self.progress = None;
+                    break;
+                }
+            }
+        }
+        finished
+    }
+}
+
+/// Worker loop: index queued sessions until the app goes away.
+fn run(store: &dyn SessionStore, jobs: &Receiver<Session>, events: &Sender<IndexEvent>) {
+    while let Ok(first) = jobs.recv() {
+        // Only the newest copy of a session saved several times is worth indexing
+        let mut batch = vec![first];
+        for session in jobs.try_iter() {
+            if let Some(stale) = batch.iter_mut().find(|s| s.id == session.id) {
+                *stale = session;
+                let _ = events.send(IndexEvent::Superseded);
+            } else {
+                batch.push(session);
+            }
+        }
+        for mut session in batch {
+            let event = match index(store, &mut session, events) {
+                Ok(result) => IndexEvent::Indexed(result),
+                Err(e) => IndexEvent::Failed(format!("{:#}", e)),
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+            thread::yield_now();
+        }
+    }
+}
+
+/// Title, token count and search index for one session.
+fn index(
+    store: &dyn SessionStore,
+    session: &mut Session,
+    events: &Sender<IndexEvent>,
+) -> anyhow::Result<IndexResult> {
+    session.derive_title();
+    let total = session.messages.len();
+    let mut reported = 0;
+    store.index(session, &mut |done| {
+        if done - reported >= PROGRESS_EVERY || done == total {
+            reported = done;
+            let _ = events.send(IndexEvent::Progress { done, total });
+        }
+        thread::yield_now();
+    })?;
+    Ok(summarize(session))
+}
+
+/// Result for a session whose title has been derived.
+fn summarize(session: &Session) -> IndexResult {
+    IndexResult {
+        id: session.id.clone(),
+        title: session.title.clone(),
+        tokens: session
+            .messages
+            .iter()
+            .map(|m| estimate_tokens(m.content.len()))
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::session::SqliteStore;
+    use std::time::{Duration, Instant};
+
+    /// Poll until the worker is idle, collecting results.
+    fn wait(indexer: &mut Indexer) -> Vec<Result<IndexResult, String>> {
+        let started = Instant::now();
+        let mut results = Vec::new();
+        while indexer.is_busy() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "indexer stalled"
+            );
+            results.extend(indexer.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        results
+    }
+
+    #[test]
+    fn test_sessions_are_indexed_in_the_background() {
+        let store: Arc<dyn SessionStore> = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut indexer = Indexer::spawn(store.clone()).unwrap();
+
+        let mut session = Session::new("ollama", "llama3");
+        session.set_messages(&[
+            Message::user("Where do otters sleep?".to_string()),
+            Message::assistant("Holding hands, in rafts.".to_string()),
+        ]);
+        store.save_record(&session).unwrap();
+        assert!(store.search("rafts").unwrap().is_empty());
+
+        indexer.queue(session.clone());
+        indexer.queue(session.clone());
+        assert!(indexer.is_busy());
+        let results = wait(&mut indexer);
+        assert!(!results.is_empty());
+        let last = indexer.last.clone().unwrap();
+        assert_eq!(last.id, session.id);
+        assert_eq!(last.title, "Where do otters sleep?");
+        assert_eq!(last.tokens, 6 + 6);
+
+        let hits = store.search("rafts").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title, "Where do otters sleep?");
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn SessionStore> =
+            Arc::new(crate::session::JsonStore::open(dir.path()).unwrap());
+        let mut indexer = Indexer::spawn(store).unwrap();
+        // Never saved, so there is nothing to index
+        indexer.queue(Session::new("ollama", "llama3"));
+        let results = wait(&mut indexer);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+}
@@ -1,14 +1,22 @@
 use std::io;
 use std::time::{Duration, Instant};
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
 use ratatui::{backend::Backend, Terminal};
+use tokio::sync::mpsc;
 
 use crate::app::{App, ConnectState, MenuItem};
+use crate::appearance::Appearance;
 use crate::config::Config;
-use crate::llm::{Provider, ANTHROPIC_MODELS, COPILOT_MODELS};
+use crate::llm::{Provider, StreamEvent};
+use crate::profiling;
+use crate::scheduler::Scheduler;
 use crate::ui;
-use crate::ui::AuthDialogResult;
+use crate::ui::{
+    AttachmentsAction, AuthDialogResult, FileTreeAction, LocalModelsAction, SectionPickerAction, SelectionAction,
+    GlobalSearchAction, ProfilePickerAction, SessionPickerAction, ToolConfirmAction, ViewerAction,
+};
 
 /// Result of handling a key event.
 pub enum HandleResult {
@@ -24,20 +32,39 @@ const CURSOR_BLINK_MS: u64 = 530;
 /// OAuth timer tick interval in milliseconds.
 const OAUTH_TICK_MS: u64 = 1000;
 
+/// Most stream events applied before the next frame is drawn.
+const MAX_EVENTS_PER_FRAME: usize = 1024;
+
 /// Run the main application loop.
-pub fn run_app<B: Backend>(
+///
+/// Sleeps until a key press, a streamed token, or the next timer or poll
+/// that can change the screen (see [`Scheduler`]), instead of polling at a
+/// fixed rate.
+pub async fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     config: &Config,
 ) -> io::Result<()> {
-    let behavior = &config.behavior;
+    let scheduler = Scheduler::new(&config.behavior);
+    let light_config = config.with_appearance(Appearance::Light);
+    let mut events = EventStream::new();
     let mut last_cursor_toggle = Instant::now();
     let mut last_oauth_tick = Instant::now();
+    let mut last_draw: Option<Instant> = None;
 
     loop {
-        // Process any streaming events first
-        app.process_stream();
+        app.frames.record_wakeup();
+
+        // Apply stream events that are already waiting
+        for _ in 0..MAX_EVENTS_PER_FRAME {
+            if !app.process_stream() {
+                break;
+            }
+        }
         
+        // Send a request once the summary it waited for is written
+        app.process_summary();
+
         // Process async validation results
         app.process_validation();
 
@@ -53,6 +80,27 @@ pub fn run_app<B: Backend>(
         // Process API key conversion (Anthropic CreateApiKey flow)
         app.process_api_key_conversion();
 
+        // Process local model warm-up
+        app.process_warm_up();
+
+        // Check whether an unreachable provider is back
+        app.process_offline();
+
+        // Send prompts held until the provider's rate limits reset
+        app.process_rate_limit();
+
+        // Renew OAuth tokens that are about to expire
+        app.process_token_refresh();
+
+        // Process local models panel queries
+        app.process_local_models();
+
+        // Process model lists for the model selection dialogs
+        app.process_model_list();
+        app.process_server_models();
+        app.process_ollama_models();
+        app.process_ollama_pull();
+
         // Tick OAuth dialog timer
         if last_oauth_tick.elapsed() >= Duration::from_millis(OAUTH_TICK_MS) {
             app.tick_oauth_dialog();
@@ -61,8 +109,24 @@ pub fn run_app<B: Backend>(
         
         // Tick toast notifications to expire old ones
         app.tick_toasts();
-        
-        terminal.draw(|f| ui::ui(f, app, config))?;
+
+        // Follow the light/dark schedule or the system appearance
+        app.tick_appearance();
+
+        // Save the conversation periodically
+        app.autosave();
+
+        // Pick up titles and errors from the session indexer
+        app.process_indexer();
+
+        // Report a finished /sync
+        app.process_sync();
+
+        // Trade the conversation with share guests or the host
+        app.process_share();
+
+        // Send the next /batch prompt
+        app.process_batch();
 
         // Toggle cursor blink
         if last_cursor_toggle.elapsed() >= Duration::from_millis(CURSOR_BLINK_MS) {
@@ -70,30 +134,70 @@ pub fn run_app<B: Backend>(
             last_cursor_toggle = Instant::now();
         }
 
-        // Use timeout for animation: fast polling during animation/streaming/validation/oauth, slower when idle
-        let timeout = if !app.animation.banner_complete || app.is_streaming() || app.validation_rx.is_some() || app.oauth_rx.is_some() || app.device_code_rx.is_some() || app.auth_code_rx.is_some() {
-            Duration::from_millis(behavior.animation_frame_ms)
-        } else {
-            // Use shorter timeout to keep cursor blinking smooth
-            Duration::from_millis(50)
-        };
+        // Redraw at most once per frame, so a fast stream is batched
+        let now = Instant::now();
+        let next_frame = last_draw.map_or(now, |at| at + scheduler.frame);
+        if next_frame <= now {
+            let started = Instant::now();
+            let allocations = profiling::allocations();
+            let config = match app.appearance.current {
+                Appearance::Dark => config,
+                Appearance::Light => &light_config,
+            };
+            terminal.draw(|f| ui::ui(f, app, config))?;
+            let elapsed = started.elapsed();
+            let last = &mut app.frames.last;
+            last.render = elapsed.saturating_sub(last.layout + last.wrap);
+            last.allocations = profiling::allocations() - allocations;
+            app.frames.record_frame(elapsed);
+            last_draw = Some(started);
+            app.copy_drag_selection();
+        }
+
+        // Sleep until the next timer, or until a skipped frame is due
+        let now = Instant::now();
+        let frame_due = last_draw.map_or(now, |at| at + scheduler.frame);
+        let next_blink = last_cursor_toggle + Duration::from_millis(CURSOR_BLINK_MS);
+        let mut timeout = scheduler.timeout(app.activity(), Some(next_blink), now);
+        let frame_ready = frame_due <= now;
+        if !frame_ready {
+            timeout = timeout.min(frame_due - now);
+        }
 
-        // Poll for events with timeout
-        if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
                     // Reset cursor to visible on any keypress
                     app.animation.cursor_visible = true;
                     last_cursor_toggle = Instant::now();
-                    
+                    app.mouse.drag = None;
+
                     match handle_key_event(app, key.code, key.modifiers, config) {
                         HandleResult::Exit => return Ok(()),
                         HandleResult::Continue => {}
                     }
                 }
-            }
+                Some(Ok(Event::Paste(text))) => app.handle_paste(&text),
+                Some(Ok(Event::Mouse(mouse))) => app.handle_mouse(mouse),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                // The terminal closed its input
+                None => return Ok(()),
+            },
+            event = next_stream_event(&mut app.llm.stream_rx), if frame_ready => match event {
+                Some(event) => app.handle_stream_event(event),
+                None => app.stream_closed(),
+            },
+            _ = tokio::time::sleep(timeout) => {}
         }
-        // If no event, loop continues and redraws (for animation/cursor blink/streaming)
+    }
+}
+
+/// Wait for the next stream event; never resolves when nothing is streaming.
+async fn next_stream_event(rx: &mut Option<mpsc::Receiver<StreamEvent>>) -> Option<StreamEvent> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
     }
 }
 
@@ -108,6 +212,16 @@ fn handle_key_event(
 
     // Global shortcuts (work in all modes)
     match code {
+        // Ctrl+Shift+C arrives as a capital C, with or without the shift flag
+        KeyCode::Char('c') | KeyCode::Char('C')
+            if modifiers.contains(KeyModifiers::CONTROL)
+                && (code == KeyCode::Char('C') || modifiers.contains(KeyModifiers::SHIFT)) =>
+        {
+            if !app.connect.is_active() {
+                app.copy_last_reply();
+            }
+            return HandleResult::Continue;
+        }
         KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
             return HandleResult::Exit;
         }
@@ -121,6 +235,32 @@ fn handle_key_event(
             }
             return HandleResult::Continue;
         }
+        KeyCode::Char('b') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.toggle_minimap();
+            return HandleResult::Continue;
+        }
+        KeyCode::F(12) => {
+            app.toggle_profile();
+            return HandleResult::Continue;
+        }
+        KeyCode::Char('g') if modifiers.contains(KeyModifiers::CONTROL) => {
+            if !app.connect.is_active() && !app.menu.visible {
+                app.open_section_picker();
+            }
+            return HandleResult::Continue;
+        }
+        KeyCode::Char('t') if modifiers.contains(KeyModifiers::CONTROL) => {
+            if !app.connect.is_active() && !app.menu.visible {
+                app.open_file_tree();
+            }
+            return HandleResult::Continue;
+        }
+        KeyCode::Char('m') if modifiers.contains(KeyModifiers::CONTROL) => {
+            if !app.connect.is_active() {
+                app.open_model_switcher();
+            }
+            return HandleResult::Continue;
+        }
         _ => {}
     }
 
@@ -129,11 +269,167 @@ fn handle_key_event(
         return handle_connect_keys(app, code);
     }
 
+    // Message viewer captures keys while open
+    if let Some(viewer) = &mut app.viewer {
+        if viewer.handle_key(code) == ViewerAction::Close {
+            app.viewer = None;
+        }
+        return HandleResult::Continue;
+    }
+
+    // Tool permission prompt
+    if let Some(confirmation) = &mut app.tool_confirmation {
+        if let ToolConfirmAction::Answer { allow, remember } = confirmation.handle_key(code) {
+            app.confirm_tool(allow, remember);
+        }
+        return HandleResult::Continue;
+    }
+
+    // Variable expansion preview
+    if app.submit_guard.expansion.is_some() {
+        match code {
+            KeyCode::Enter | KeyCode::Char('y') => app.confirm_expansion(true),
+            KeyCode::Esc | KeyCode::Char('n') => app.confirm_expansion(false),
+            _ => {}
+        }
+        return HandleResult::Continue;
+    }
+
+    // Pasted file paths: attach or paste as text
+    if app.pasted_files.is_some() {
+        match code {
+            KeyCode::Enter | KeyCode::Char('y') => app.resolve_pasted_files(Some(true)),
+            KeyCode::Char('t') => app.resolve_pasted_files(Some(false)),
+            KeyCode::Esc | KeyCode::Char('n') => app.resolve_pasted_files(None),
+            _ => {}
+        }
+        return HandleResult::Continue;
+    }
+
+    // Large prompt confirmation
+    if app.submit_guard.pending.is_some() {
+        match code {
+            KeyCode::Enter | KeyCode::Char('y') => app.confirm_send(true),
+            KeyCode::Esc | KeyCode::Char('n') => app.confirm_send(false),
+            _ => {}
+        }
+        return HandleResult::Continue;
+    }
+
+    // Spending cap confirmation
+    if app.submit_guard.spend.is_some() {
+        match code {
+            KeyCode::Enter | KeyCode::Char('y') => app.confirm_spend(true),
+            KeyCode::Esc | KeyCode::Char('n') => app.confirm_spend(false),
+            _ => {}
+        }
+        return HandleResult::Continue;
+    }
+
+    // Resumed session model prompt
+    if let Some(mismatch) = &mut app.model_mismatch {
+        match code {
+            KeyCode::Up | KeyCode::Down | KeyCode::Tab => {
+                mismatch.selected = 1 - mismatch.selected;
+            }
+            KeyCode::Enter => {
+                let switch_back = mismatch.selected == 0;
+                app.resolve_model_mismatch(switch_back);
+            }
+            KeyCode::Esc => app.resolve_model_mismatch(false),
+            _ => {}
+        }
+        return HandleResult::Continue;
+    }
+
+    // Local models panel captures keys while open
+    if let Some(panel) = &mut app.local_models {
+        match panel.handle_key(code) {
+            LocalModelsAction::Continue => {}
+            LocalModelsAction::Close => app.close_local_models(),
+            LocalModelsAction::Refresh => app.refresh_local_models(None),
+            LocalModelsAction::Unload(model) => app.refresh_local_models(Some(model)),
+        }
+        return HandleResult::Continue;
+    }
+
+    // Section picker captures keys while open
+    if let Some(picker) = &mut app.section_picker {
+        let count = app.sections.segments(&app.chat.messages).len();
+        match picker.handle_key(code, count) {
+            SectionPickerAction::Continue => {}
+            SectionPickerAction::Close => app.section_picker = None,
+            SectionPickerAction::Jump(index) => app.jump_to_section(index),
+            SectionPickerAction::Toggle(index) => app.toggle_section(index),
+        }
+        return HandleResult::Continue;
+    }
+
+    // Session browser captures keys while open
+    if let Some(picker) = &mut app.session_picker {
+        match picker.handle_key(code) {
+            SessionPickerAction::Continue => {}
+            SessionPickerAction::Close => app.session_picker = None,
+            SessionPickerAction::Open(id) => app.open_session(&id),
+        }
+        return HandleResult::Continue;
+    }
+
+    // Search across sessions captures keys while open
+    if let Some(search) = &mut app.global_search {
+        match search.handle_key(code) {
+            GlobalSearchAction::Continue => {}
+            GlobalSearchAction::Close => app.global_search = None,
+            GlobalSearchAction::Search => app.run_global_search(),
+            GlobalSearchAction::Open(index) => app.open_search_hit(index),
+        }
+        return HandleResult::Continue;
+    }
+
+    // Search bar captures keys while open
+    if let Some(search) = &mut app.search {
+        let action = search.handle_key(code);
+        app.handle_search_action(action);
+        return HandleResult::Continue;
+    }
+
+    // Profile picker captures keys while open
+    if let Some(picker) = &mut app.profile_picker {
+        match picker.handle_key(code) {
+            ProfilePickerAction::Continue => {}
+            ProfilePickerAction::Close => app.profile_picker = None,
+            ProfilePickerAction::Switch(name) => app.switch_profile(name),
+        }
+        return HandleResult::Continue;
+    }
+
+    // File tree captures keys while open
+    if let Some(tree) = &mut app.file_tree {
+        match tree.handle_key(code) {
+            FileTreeAction::Continue => {}
+            FileTreeAction::Close => app.file_tree = None,
+            FileTreeAction::Attach(paths) => app.attach_files(paths),
+        }
+        return HandleResult::Continue;
+    }
+
+    // Attachment manager captures keys while open
+    if let Some(panel) = &mut app.attachments {
+        match panel.handle_key(code) {
+            AttachmentsAction::Continue => {}
+            AttachmentsAction::Close => app.attachments = None,
+            AttachmentsAction::Apply(chips) => app.apply_attachments(chips),
+        }
+        return HandleResult::Continue;
+    }
+
     // Handle menu-specific or normal-mode keys
     if app.menu.visible {
         handle_menu_keys(app, code)
+    } else if app.selection.is_some() {
+        handle_selection_keys(app, code)
     } else {
-        handle_normal_keys(app, code, page_size)
+        handle_normal_keys(app, code, modifiers, page_size)
     }
 }
 
@@ -165,6 +461,15 @@ fn handle_main_menu_keys(app: &mut App, code: KeyCode) -> HandleResult {
                         // Enter the provider submenu
                         app.menu.enter_submenu();
                     }
+                    MenuItem::ResumeSession => {
+                        app.open_session_picker();
+                    }
+                    MenuItem::SearchSessions => {
+                        app.open_global_search();
+                    }
+                    MenuItem::SwitchProfile => {
+                        app.open_profile_picker();
+                    }
                     MenuItem::Exit => {
                         return HandleResult::Exit;
                     }
@@ -204,11 +509,87 @@ fn handle_submenu_keys(app: &mut App, code: KeyCode) -> HandleResult {
     HandleResult::Continue
 }
 
+/// Handle key events in message selection mode.
+fn handle_selection_keys(app: &mut App, code: KeyCode) -> HandleResult {
+    let count = app.chat.messages.len();
+    let Some(selection) = &mut app.selection else {
+        return HandleResult::Continue;
+    };
+    match selection.handle_key(code, count) {
+        SelectionAction::Continue => app.follow_selection(),
+        SelectionAction::Close => app.selection = None,
+        SelectionAction::Open(index) => app.open_viewer_at(index),
+        SelectionAction::Copy(index) => app.copy_message(index),
+        SelectionAction::Delete(index) => app.delete_message(index),
+        SelectionAction::Edit(index) => app.edit_prompt_at(index),
+        SelectionAction::Pin(index) => app.toggle_pin(index),
+        SelectionAction::Rerun(index) => app.rerun_prompt(index),
+        SelectionAction::Info => app.toggle_reply_info(),
+    }
+    HandleResult::Continue
+}
+
 /// Handle key events in normal (non-menu) mode.
-fn handle_normal_keys(app: &mut App, code: KeyCode, page_size: usize) -> HandleResult {
+fn handle_normal_keys(
+    app: &mut App,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+    page_size: usize,
+) -> HandleResult {
     let max_scroll = app.max_scroll();
 
     match code {
+        // Enter on an empty input opens the message at the top of the view
+        KeyCode::Enter if app.chat.input.trim().is_empty() => {
+            app.open_viewer();
+        }
+        KeyCode::Up if modifiers.contains(KeyModifiers::SHIFT) => {
+            app.jump_to_prompt(false);
+        }
+        KeyCode::Down if modifiers.contains(KeyModifiers::SHIFT) => {
+            app.jump_to_prompt(true);
+        }
+        KeyCode::Char('r') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.retry_last_request();
+        }
+        KeyCode::Char('o') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.toggle_raw_message();
+        }
+        KeyCode::Char('y') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.toggle_thinking();
+        }
+        KeyCode::Char('k') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.keep_playground();
+        }
+        KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.jump_to_unread();
+        }
+        KeyCode::Char('a') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.open_attachments();
+        }
+        KeyCode::Char('e') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.edit_prompt();
+        }
+        KeyCode::Char('v') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.open_selection();
+        }
+        KeyCode::Char('l') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.reconnect_expiring();
+        }
+        // Ctrl+Shift+F arrives as a capital F, with or without the shift flag
+        KeyCode::Char('f') | KeyCode::Char('F')
+            if modifiers.contains(KeyModifiers::CONTROL)
+                && (code == KeyCode::Char('F') || modifiers.contains(KeyModifiers::SHIFT)) =>
+        {
+            app.open_global_search();
+        }
+        KeyCode::Char('f') if modifiers.contains(KeyModifiers::CONTROL) => {
+            app.open_search();
+        }
+        KeyCode::Tab if app.chat.complete_command() => {}
+        KeyCode::Tab => {
+            app.chat.complete_shortcode();
+        }
         KeyCode::Enter => {
             app.submit_message();
         }
@@ -242,6 +623,9 @@ fn handle_normal_keys(app: &mut App, code: KeyCode, page_size: usize) -> HandleR
         KeyCode::End => {
             app.scroll_to_bottom(max_scroll);
         }
+        KeyCode::Esc if app.chat.editing.is_some() => {
+            app.cancel_edit();
+        }
         KeyCode::Esc => {
             return HandleResult::Exit;
         }
@@ -264,11 +648,7 @@ fn handle_connect_keys(app: &mut App, code: KeyCode) -> HandleResult {
         ConnectState::SelectingMethod { selected, .. } => {
             handle_selecting_method_keys(app, code, *selected)
         }
-        ConnectState::EnteringApiKey { input, cursor, .. } => {
-            let input = input.clone();
-            let cursor = *cursor;
-            handle_entering_api_key_keys(app, code, &input, cursor)
-        }
+        ConnectState::EnteringApiKey { .. } => handle_entering_api_key_keys(app, code),
         ConnectState::ValidatingKey { .. } => {
             // No input during validation, but allow Esc to cancel
             if code == KeyCode::Esc {
@@ -276,14 +656,17 @@ fn handle_connect_keys(app: &mut App, code: KeyCode) -> HandleResult {
             }
             HandleResult::Continue
         }
-        ConnectState::OAuthPending { auth_dialog, .. } | ConnectState::OAuthPolling { auth_dialog, .. } => {
-            // Handle OAuth dialog keys
-            let mut dialog = auth_dialog.clone();
-            match dialog.handle_key(code) {
+        ConnectState::OAuthPending { .. } | ConnectState::OAuthPolling { .. } => {
+            // Handle OAuth dialog keys in place
+            let (ConnectState::OAuthPending { auth_dialog, .. }
+            | ConnectState::OAuthPolling { auth_dialog, .. }) = &mut app.connect
+            else {
+                return HandleResult::Continue;
+            };
+            match auth_dialog.handle_key(code) {
                 AuthDialogResult::OpenBrowser => {
                     // Open browser to the verification URL
-                    let url = dialog.verification_url().to_string();
-                    if open::that(&url).is_err() {
+                    if open::that(auth_dialog.verification_url()).is_err() {
                         app.toast_error("Could not open browser");
                     }
                 }
@@ -297,11 +680,7 @@ fn handle_connect_keys(app: &mut App, code: KeyCode) -> HandleResult {
         ConnectState::SelectingAnthropicMethod { selected } => {
             handle_selecting_anthropic_method_keys(app, code, *selected)
         }
-        ConnectState::EnteringAuthCode { input, cursor, .. } => {
-            let input = input.clone();
-            let cursor = *cursor;
-            handle_entering_auth_code_keys(app, code, &input, cursor)
-        }
+        ConnectState::EnteringAuthCode { .. } => handle_entering_auth_code_keys(app, code),
         ConnectState::ExchangingCode { .. } => {
             // No input during code exchange, but allow Esc to cancel
             if code == KeyCode::Esc {
@@ -312,6 +691,24 @@ fn handle_connect_keys(app: &mut App, code: KeyCode) -> HandleResult {
         ConnectState::SelectingModel { selected, .. } => {
             handle_model_selection_keys(app, code, *selected)
         }
+        ConnectState::EnteringServerUrl { .. } => handle_entering_server_url_keys(app, code),
+        ConnectState::FetchingServerModels { .. } | ConnectState::FetchingOllamaModels { .. } => {
+            // No input while the model list loads, but allow Esc to cancel
+            if code == KeyCode::Esc {
+                app.cancel_connection();
+            }
+            HandleResult::Continue
+        }
+        ConnectState::SelectingServerModel { .. } => handle_server_model_keys(app, code),
+        ConnectState::SelectingOllamaModel { .. } => handle_ollama_model_keys(app, code),
+        ConnectState::OfferingOllamaPull { .. } => handle_ollama_pull_offer_keys(app, code),
+        ConnectState::PullingOllamaModel { .. } => {
+            // Esc stops the download
+            if code == KeyCode::Esc {
+                app.cancel_connection();
+            }
+            HandleResult::Continue
+        }
     }
 }
 
@@ -354,7 +751,7 @@ fn handle_existing_credential_keys(app: &mut App, code: KeyCode, selected: usize
                 1 if has_saved_model => app.change_copilot_model(),
                 1 => app.enter_new_credentials(),
                 2 if has_saved_model => app.enter_new_credentials(),
-                2 | 3 | _ => app.cancel_connection(),
+                _ => app.cancel_connection(),
             }
         }
         KeyCode::Esc => {
@@ -400,7 +797,7 @@ fn handle_selecting_method_keys(app: &mut App, code: KeyCode, selected: usize) -
                         }
                     }
                 }
-                2 | _ => {
+                _ => {
                     app.cancel_connection();
                 }
             }
@@ -413,13 +810,8 @@ fn handle_selecting_method_keys(app: &mut App, code: KeyCode, selected: usize) -
     HandleResult::Continue
 }
 
-/// Handle keys in EnteringApiKey state.
-fn handle_entering_api_key_keys(
-    app: &mut App,
-    code: KeyCode,
-    input: &str,
-    _cursor: usize,
-) -> HandleResult {
+/// Handle keys in EnteringApiKey state, editing the key in place.
+fn handle_entering_api_key_keys(app: &mut App, code: KeyCode) -> HandleResult {
     match code {
         KeyCode::Char(c) => {
             if let ConnectState::EnteringApiKey {
@@ -466,20 +858,24 @@ fn handle_entering_api_key_keys(
         }
         KeyCode::Enter => {
             // Validate and submit
-            if !input.is_empty() {
-                if let ConnectState::EnteringApiKey { provider, input, .. } = &app.connect {
+            if let ConnectState::EnteringApiKey {
+                provider,
+                input,
+                error,
+                ..
+            } = &mut app.connect
+            {
+                if input.is_empty() {
+                    return HandleResult::Continue;
+                }
+                // Check format first
+                if let Err(e) = provider.validate_api_key_format(input) {
+                    *error = Some(e.to_string());
+                } else {
+                    // Format is valid - start async validation
                     let provider = *provider;
-                    let key = input.clone();
-
-                    // Check format first
-                    if let Err(e) = provider.validate_api_key_format(&key) {
-                        if let ConnectState::EnteringApiKey { error, .. } = &mut app.connect {
-                            *error = Some(e.to_string());
-                        }
-                    } else {
-                        // Format is valid - start async validation
-                        app.start_validation(provider, key);
-                    }
+                    let key = std::mem::take(input);
+                    app.start_validation(provider, key);
                 }
             }
         }
@@ -520,12 +916,7 @@ fn handle_selecting_anthropic_method_keys(app: &mut App, code: KeyCode, selected
 }
 
 /// Handle keys in EnteringAuthCode state.
-fn handle_entering_auth_code_keys(
-    app: &mut App,
-    code: KeyCode,
-    input: &str,
-    _cursor: usize,
-) -> HandleResult {
+fn handle_entering_auth_code_keys(app: &mut App, code: KeyCode) -> HandleResult {
     match code {
         KeyCode::Left => {
             app.move_cursor_left_auth_code();
@@ -550,7 +941,7 @@ fn handle_entering_auth_code_keys(
         }
         KeyCode::Enter => {
             // Submit the authorization code
-            if !input.is_empty() {
+            if matches!(&app.connect, ConnectState::EnteringAuthCode { input, .. } if !input.is_empty()) {
                 app.submit_auth_code();
             }
         }
@@ -562,22 +953,132 @@ fn handle_entering_auth_code_keys(
     HandleResult::Continue
 }
 
-/// Handle keys in SelectingModel state.
-fn handle_model_selection_keys(app: &mut App, code: KeyCode, selected: usize) -> HandleResult {
-    // Get provider to determine which model list to use
-    let provider = if let ConnectState::SelectingModel { provider, .. } = &app.connect {
-        *provider
-    } else {
+/// Handle keys in EnteringServerUrl state.
+fn handle_entering_server_url_keys(app: &mut App, code: KeyCode) -> HandleResult {
+    if code == KeyCode::Esc {
+        app.cancel_connection();
+        return HandleResult::Continue;
+    }
+    let ConnectState::EnteringServerUrl {
+        input,
+        cursor,
+        error,
+    } = &mut app.connect
+    else {
         return HandleResult::Continue;
     };
+    match code {
+        KeyCode::Char(c) => {
+            input.insert(*cursor, c);
+            *cursor += c.len_utf8();
+            *error = None;
+        }
+        KeyCode::Backspace => {
+            if let Some(c) = input[..*cursor].chars().next_back() {
+                *cursor -= c.len_utf8();
+                input.remove(*cursor);
+                *error = None;
+            }
+        }
+        KeyCode::Left => {
+            if let Some(c) = input[..*cursor].chars().next_back() {
+                *cursor -= c.len_utf8();
+            }
+        }
+        KeyCode::Right => {
+            if let Some(c) = input[*cursor..].chars().next() {
+                *cursor += c.len_utf8();
+            }
+        }
+        KeyCode::Home => *cursor = 0,
+        KeyCode::End => *cursor = input.len(),
+        KeyCode::Enter if !input.trim().is_empty() => {
+            let url = input.clone();
+            app.start_server_models(&url);
+        }
+        _ => {}
+    }
+    HandleResult::Continue
+}
 
-    let models: &[(&str, &str)] = match provider {
-        Provider::Anthropic => ANTHROPIC_MODELS,
-        Provider::GitHubCopilot => COPILOT_MODELS,
-        _ => return HandleResult::Continue,
+/// Handle keys in SelectingServerModel state.
+fn handle_server_model_keys(app: &mut App, code: KeyCode) -> HandleResult {
+    match code {
+        KeyCode::Up => {
+            if let ConnectState::SelectingServerModel { selected, .. } = &mut app.connect {
+                *selected = selected.saturating_sub(1);
+            }
+        }
+        KeyCode::Down => {
+            if let ConnectState::SelectingServerModel {
+                models, selected, ..
+            } = &mut app.connect
+            {
+                if *selected + 1 < models.len() {
+                    *selected += 1;
+                }
+            }
+        }
+        KeyCode::Enter => app.select_server_model(),
+        KeyCode::Esc => app.cancel_connection(),
+        _ => {}
+    }
+    HandleResult::Continue
+}
+
+/// Handle keys while picking an installed Ollama model.
+fn handle_ollama_model_keys(app: &mut App, code: KeyCode) -> HandleResult {
+    match code {
+        KeyCode::Up => {
+            if let ConnectState::SelectingOllamaModel { selected, .. } = &mut app.connect {
+                *selected = selected.saturating_sub(1);
+            }
+        }
+        KeyCode::Down => {
+            if let ConnectState::SelectingOllamaModel {
+                models, selected, ..
+            } = &mut app.connect
+            {
+                if *selected + 1 < models.len() {
+                    *selected += 1;
+                }
+            }
+        }
+        KeyCode::Enter => app.select_ollama_model(),
+        KeyCode::Esc => app.cancel_connection(),
+        _ => {}
+    }
+    HandleResult::Continue
+}
+
+/// Handle keys in the dialog offering to pull a missing Ollama model.
+fn handle_ollama_pull_offer_keys(app: &mut App, code: KeyCode) -> HandleResult {
+    // Options: pull the model, or pick an installed one when there are any
+    let ConnectState::OfferingOllamaPull {
+        installed, selected, ..
+    } = &mut app.connect
+    else {
+        return HandleResult::Continue;
     };
+    let option_count = if installed.is_empty() { 1 } else { 2 };
+    match code {
+        KeyCode::Up => *selected = selected.saturating_sub(1),
+        KeyCode::Down if *selected + 1 < option_count => *selected += 1,
+        KeyCode::Enter if *selected == 0 => app.start_ollama_pull(),
+        KeyCode::Enter => app.choose_installed_ollama_model(),
+        KeyCode::Esc => app.cancel_connection(),
+        _ => {}
+    }
+    HandleResult::Continue
+}
 
-    let model_count = models.len();
+/// Handle keys in SelectingModel state.
+fn handle_model_selection_keys(app: &mut App, code: KeyCode, selected: usize) -> HandleResult {
+    // No model list yet while it loads
+    let model_count = match &app.connect {
+        ConnectState::SelectingModel { models, .. } => models.as_ref().map_or(0, Vec::len),
+        _ => return HandleResult::Continue,
+    };
 
     match code {
         KeyCode::Up => {
@@ -587,15 +1088,22 @@ fn handle_model_selection_keys(app: &mut App, code: KeyCode, selected: usize) ->
         }
         KeyCode::Down => {
             if let ConnectState::SelectingModel { selected, .. } = &mut app.connect {
-                if *selected < model_count - 1 {
+                if *selected + 1 < model_count {
                     *selected += 1;
                 }
             }
         }
         KeyCode::Enter => {
             // Get the API model ID for the selected model
-            if let Some((_display_name, api_id)) = models.get(selected) {
-                app.complete_model_selection(api_id);
+            let model = match &app.connect {
+                ConnectState::SelectingModel {
+                    models: Some(models),
+                    ..
+                } => models.get(selected).map(|m| m.id.clone()),
+                _ => None,
+            };
+            if let Some(model) = model {
+                app.complete_model_selection(&model);
             }
         }
         KeyCode::Esc => {
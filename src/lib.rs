@@ -3,10 +3,38 @@
 //! This library exposes the core modules for testing and reuse.
 
 pub mod app;
+pub mod appearance;
+pub mod audit;
+pub mod batch;
 pub mod auth;
+pub mod cli;
+pub mod clipboard;
+pub mod commands;
 pub mod config;
+pub mod context;
+pub mod diff;
+pub mod durable;
+pub mod emoji;
+pub mod history;
+pub mod image;
+pub mod indexer;
 pub mod input;
 pub mod llm;
 pub mod message;
+pub mod paste;
+pub mod preprocess;
+pub mod pricing;
+pub mod profiles;
+pub mod profiling;
+pub mod routing;
+pub mod scheduler;
+pub mod sections;
+pub mod session;
+pub mod share;
+pub mod sync;
+pub mod tokens;
+pub mod tools;
 pub mod ui;
+pub mod usage;
+pub mod variables;
 pub mod welcome;
@@ -5,15 +5,19 @@
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::StreamExt;
-use reqwest::Client;
+use futures::{Stream, StreamExt};
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 
 use crate::llm::CredentialType;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
-use super::{ChatMessage, LlmConfig, LlmProvider, Provider, StreamEvent};
+use super::{
+    backpressure, recording, signing, sse, ChatMessage, FinishReason, ImageData, LlmConfig,
+    LlmProvider, ModelInfo, Provider, ProviderError, ProviderResult, RateLimits, RequestPreview,
+    StreamEvent, TokenUsage, ToolCall, ToolDefinition,
+};
 
 /// Anthropic API version header value.
 const ANTHROPIC_VERSION: &str = "2023-06-01";
@@ -23,12 +27,32 @@ const ANTHROPIC_VERSION: &str = "2023-06-01";
 struct AnthropicRequest {
     model: String,
     messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
     max_tokens: u32,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<Thinking>,
+}
+
+/// A tool offered to the model.
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+/// Extended thinking settings.
+#[derive(Debug, Serialize)]
+struct Thinking {
+    #[serde(rename = "type")]
+    thinking_type: &'static str,
+    budget_tokens: u32,
 }
 
 /// Message format for Anthropic API.
@@ -37,7 +61,81 @@ struct AnthropicRequest {
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: AnthropicContent,
+}
+
+/// Message content: a plain string, or image and text blocks when images
+/// are attached.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+enum AnthropicContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl AnthropicContent {
+    /// `text` alone, or after `images`, which Anthropic recommends putting first.
+    fn new(text: String, images: Vec<ImageData>) -> Self {
+        if images.is_empty() {
+            return AnthropicContent::Text(text);
+        }
+        let mut blocks: Vec<ContentBlock> = images
+            .into_iter()
+            .map(|image| ContentBlock::Image {
+                source: ImageSource {
+                    source_type: "base64",
+                    media_type: image.media_type,
+                    data: image.data,
+                },
+            })
+            .collect();
+        blocks.push(ContentBlock::Text { text });
+        AnthropicContent::Blocks(blocks)
+    }
+}
+
+/// One block of a message with images or tool calls.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    Image { source: ImageSource },
+    /// A call the model made, sent back with its reply
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// A tool's output, sent in a user message
+    ToolResult { tool_use_id: String, content: String },
+}
+
+/// Inline image data of an image block.
+#[derive(Debug, PartialEq, Serialize)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    source_type: &'static str,
+    media_type: String,
+    data: String,
+}
+
+/// SSE event data for content_block_start; a tool call's block opens with
+/// its id and name.
+#[derive(Debug, Deserialize)]
+struct ContentBlockStart {
+    index: usize,
+    content_block: StartedBlock,
+}
+
+/// The block opened by content_block_start.
+#[derive(Debug, Deserialize)]
+struct StartedBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
 }
 
 /// SSE event data for content_block_delta.
@@ -46,18 +144,67 @@ struct ContentBlockDelta {
     #[serde(rename = "type")]
     #[allow(dead_code)]
     event_type: String,
-    #[allow(dead_code)]
     index: usize,
     delta: TextDelta,
 }
 
-/// Delta containing text content.
+/// Delta containing text (or, with extended thinking, reasoning) content,
+/// or part of a tool call's input.
 #[derive(Debug, Deserialize)]
 struct TextDelta {
     #[serde(rename = "type")]
     delta_type: String,
     #[serde(default)]
     text: String,
+    #[serde(default)]
+    thinking: String,
+    /// Tool input JSON, streamed in pieces
+    #[serde(default)]
+    partial_json: String,
+}
+
+/// SSE event data for content_block_stop.
+#[derive(Debug, Deserialize)]
+struct ContentBlockStop {
+    index: usize,
+}
+
+/// SSE event data for message_start, which carries the prompt's token count.
+#[derive(Debug, Deserialize)]
+struct MessageStart {
+    message: MessageStartBody,
+}
+
+/// The message opened by message_start.
+#[derive(Debug, Deserialize)]
+struct MessageStartBody {
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+/// Token counts as Anthropic reports them.
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+/// SSE event data for message_delta, which carries the stop reason and the
+/// reply's token count.
+#[derive(Debug, Deserialize)]
+struct MessageDelta {
+    delta: MessageDeltaBody,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+/// Message-level changes in a message_delta event.
+#[derive(Debug, Deserialize)]
+struct MessageDeltaBody {
+    #[serde(default)]
+    stop_reason: Option<String>,
 }
 
 /// Error response from Anthropic API.
@@ -76,6 +223,20 @@ struct AnthropicErrorDetail {
     message: String,
 }
 
+/// `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+/// One entry of a `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
 /// Anthropic API client.
 #[derive(Clone)]
 pub struct AnthropicClient {
@@ -87,7 +248,7 @@ impl AnthropicClient {
     /// Create a new Anthropic client with the given configuration.
     pub fn new(config: LlmConfig) -> Self {
         Self {
-            client: Client::new(),
+            client: super::http_client(&config.timeouts),
             config: Arc::new(config),
         }
     }
@@ -113,13 +274,33 @@ impl LlmProvider for AnthropicClient {
         self.config.is_configured()
     }
 
-    fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent> {
-        let (tx, rx) = mpsc::channel(100);
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        let url = format!("{}/messages", self.config.api_base);
+        Some(RequestPreview::new(url, &build_request(&self.config, messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        fetch_models(&self.client, &self.config)
+            .await
+            .map_err(|e| ProviderError::from_message(&e.to_string()))
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.config.stream);
         let client = self.client.clone();
         let config = self.config.clone();
+        let request = build_request(&config, messages, tools);
 
         tokio::spawn(async move {
-            if let Err(e) = stream_chat_inner(&client, &config, messages, tx.clone()).await {
+            if let Err(e) = stream_chat_inner(&client, &config, request, tx.clone()).await {
                 let _ = tx.send(StreamEvent::Error(e.to_string())).await;
             }
         });
@@ -130,18 +311,53 @@ impl LlmProvider for AnthropicClient {
 
 /// Convert generic ChatMessages to Anthropic format.
 /// Extracts system messages into a separate field.
+///
+/// Tool calls become `tool_use` blocks of the reply that made them, and tool
+/// output goes back as `tool_result` blocks of a user message.
 fn convert_messages(messages: Vec<ChatMessage>) -> (Option<String>, Vec<AnthropicMessage>) {
     let mut system = None;
-    let mut anthropic_messages = Vec::new();
+    let mut anthropic_messages: Vec<AnthropicMessage> = Vec::new();
 
     for msg in messages {
         if msg.role == "system" {
             // Anthropic uses a separate system field, not in messages array
             system = Some(msg.content);
+        } else if let Some(tool_use_id) = msg.tool_call_id {
+            let result = ContentBlock::ToolResult {
+                tool_use_id,
+                content: msg.content,
+            };
+            // The results of one reply's calls share a message
+            match anthropic_messages.last_mut() {
+                Some(AnthropicMessage {
+                    content: AnthropicContent::Blocks(blocks),
+                    ..
+                }) if matches!(blocks.first(), Some(ContentBlock::ToolResult { .. })) => {
+                    blocks.push(result)
+                }
+                _ => anthropic_messages.push(AnthropicMessage {
+                    role: "user".to_string(),
+                    content: AnthropicContent::Blocks(vec![result]),
+                }),
+            }
+        } else if !msg.tool_calls.is_empty() {
+            let mut blocks = Vec::new();
+            if !msg.content.is_empty() {
+                blocks.push(ContentBlock::Text { text: msg.content });
+            }
+            blocks.extend(msg.tool_calls.into_iter().map(|call| ContentBlock::ToolUse {
+                id: call.id,
+                name: call.name,
+                input: call.arguments,
+            }));
+            anthropic_messages.push(AnthropicMessage {
+                role: msg.role,
+                content: AnthropicContent::Blocks(blocks),
+            });
         } else {
             anthropic_messages.push(AnthropicMessage {
                 role: msg.role,
-                content: msg.content,
+                content: AnthropicContent::new(msg.content, msg.images),
             });
         }
     }
@@ -149,47 +365,124 @@ fn convert_messages(messages: Vec<ChatMessage>) -> (Option<String>, Vec<Anthropi
     (system, anthropic_messages)
 }
 
-/// Internal streaming implementation.
-async fn stream_chat_inner(
-    client: &Client,
+/// Build the request body for `messages`, offering `tools`.
+fn build_request(
     config: &LlmConfig,
     messages: Vec<ChatMessage>,
-    tx: mpsc::Sender<StreamEvent>,
-) -> Result<()> {
-    let url = format!("{}/messages", config.api_base);
+    tools: &[ToolDefinition],
+) -> AnthropicRequest {
     let (system, anthropic_messages) = convert_messages(messages);
 
     // Anthropic requires max_tokens
-    let max_tokens = config.max_tokens.unwrap_or(4096);
+    let mut max_tokens = config.max_tokens.unwrap_or(4096);
+    let mut temperature = config.temperature;
+    let thinking = config.reasoning.map(|effort| {
+        // The budget comes out of max_tokens, and thinking only runs at the
+        // default temperature
+        let budget_tokens = effort.budget_tokens();
+        max_tokens = max_tokens.max(budget_tokens + 4096);
+        temperature = None;
+        Thinking {
+            thinking_type: "enabled",
+            budget_tokens,
+        }
+    });
 
-    let request_body = AnthropicRequest {
+    AnthropicRequest {
         model: config.model.clone(),
         messages: anthropic_messages,
+        tools: tools
+            .iter()
+            .map(|tool| AnthropicTool {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                input_schema: tool.parameters.clone(),
+            })
+            .collect(),
         max_tokens,
         stream: true,
-        temperature: config.temperature,
+        temperature,
         system,
-    };
+        thinking,
+    }
+}
 
-    // Build request with appropriate authentication headers
-    // OAuth tokens use Authorization header, API keys use x-api-key header
-    let mut request = client
-        .post(&format!("{}/messages", config.api_base))
-        .header("anthropic-version", ANTHROPIC_VERSION)
-        .header("Content-Type", "application/json");
+/// Add the version and authentication headers for `config`'s credential.
+fn authorize(request: RequestBuilder, config: &LlmConfig) -> RequestBuilder {
+    let request = request.header("anthropic-version", ANTHROPIC_VERSION);
 
     // Use credential_type from config to determine auth method
     if config.credential_type == CredentialType::OAuth {
         // OAuth token - use Authorization header with all required beta headers
-        request = request
+        request
             .header("Authorization", format!("Bearer {}", config.api_key))
-            .header("anthropic-beta", "oauth-2025-04-20,claude-code-20250219,interleaved-thinking-2025-05-14,fine-grained-tool-streaming-2025-05-14");
+            .header("anthropic-beta", "oauth-2025-04-20,claude-code-20250219,interleaved-thinking-2025-05-14,fine-grained-tool-streaming-2025-05-14")
     } else {
         // API key - use x-api-key header
-        request = request.header("x-api-key", &config.api_key);
+        request.header("x-api-key", &config.api_key)
+    }
+}
+
+/// Fetch the models the credential in `config` can use (`GET /models`).
+async fn fetch_models(client: &Client, config: &LlmConfig) -> Result<Vec<ModelInfo>> {
+    let url = format!("{}/models?limit=1000", config.api_base);
+    let request = authorize(client.get(&url), config);
+    let response = signing::sign(request, config.signing.as_ref())
+        .await?
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if let Ok(error) = serde_json::from_str::<AnthropicError>(&body) {
+            return Err(anyhow!(
+                "Anthropic API error ({}): {}",
+                status,
+                error.error.message
+            ));
+        }
+        return Err(anyhow!("Anthropic API error {}: {}", status, body));
     }
 
-    let response = request.json(&request_body).send().await?;
+    parse_models(&response.text().await?)
+}
+
+/// Models from a `/models` response body, newest first as the API lists them.
+fn parse_models(body: &str) -> Result<Vec<ModelInfo>> {
+    let list: ModelList = serde_json::from_str(body)?;
+    Ok(list
+        .data
+        .into_iter()
+        .map(|m| ModelInfo {
+            name: m.display_name.unwrap_or_else(|| m.id.clone()),
+            id: m.id,
+        })
+        .collect())
+}
+
+/// Internal streaming implementation.
+async fn stream_chat_inner(
+    client: &Client,
+    config: &LlmConfig,
+    request_body: AnthropicRequest,
+    tx: mpsc::Sender<StreamEvent>,
+) -> Result<()> {
+    let url = format!("{}/messages", config.api_base);
+
+    let request = authorize(
+        client.post(&url).header("Content-Type", "application/json"),
+        config,
+    );
+
+    let response = signing::json_body(request, &request_body, config.signing.as_ref())
+        .await?
+        .send()
+        .await?;
+
+    if let Some(limits) = RateLimits::from_headers(response.headers(), chrono::Utc::now()) {
+        tx.send(StreamEvent::RateLimits(limits)).await.ok();
+    }
 
     if !response.status().is_success() {
         let status = response.status();
@@ -208,64 +501,115 @@ async fn stream_chat_inner(
         return Err(anyhow!("Anthropic API error {}: {}", status, body));
     }
 
-    let mut stream = response.bytes_stream();
-
-    // Buffer for incomplete SSE lines
-    let mut buffer = String::new();
-    // Track current event type (Anthropic uses named events)
-    let mut current_event = String::new();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
-
-        // Process complete lines
-        while let Some(newline_pos) = buffer.find('\n') {
-            let line = buffer[..newline_pos].trim().to_string();
-            buffer = buffer[newline_pos + 1..].to_string();
-
-            if line.is_empty() {
-                continue;
-            }
+    let stream = recording::tap(response.bytes_stream(), recording::recorder_for(config));
+    parse_stream(stream, &tx).await
+}
 
-            // Anthropic SSE format uses "event:" lines to identify event type
-            if let Some(event_name) = line.strip_prefix("event: ") {
-                current_event = event_name.to_string();
-                continue;
+/// Parse an Anthropic SSE byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(stream: S, tx: &mpsc::Sender<StreamEvent>) -> Result<()>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut events = std::pin::pin!(sse::events(stream));
+    let mut prompt_tokens = 0;
+    // The tool call being streamed: its block index, call and input so far
+    let mut tool_use: Option<(usize, ToolCall, String)> = None;
+
+    while let Some(event) = events.next().await {
+        let event = event?;
+
+        // Anthropic SSE format uses "event:" lines to identify event type
+        match event.event.as_deref().unwrap_or_default() {
+            "content_block_start" => {
+                if let Ok(start) = serde_json::from_str::<ContentBlockStart>(&event.data) {
+                    if start.content_block.block_type == "tool_use" {
+                        let call = ToolCall {
+                            id: start.content_block.id,
+                            name: start.content_block.name,
+                            arguments: serde_json::json!({}),
+                        };
+                        tool_use = Some((start.index, call, String::new()));
+                    }
+                }
             }
-
-            // Process data lines based on current event type
-            if let Some(json_str) = line.strip_prefix("data: ") {
-                match current_event.as_str() {
-                    "content_block_delta" => {
-                        if let Ok(delta) = serde_json::from_str::<ContentBlockDelta>(json_str) {
-                            // Only process text deltas
-                            if delta.delta.delta_type == "text_delta" && !delta.delta.text.is_empty()
-                            {
-                                tx.send(StreamEvent::Token(delta.delta.text)).await.ok();
+            "content_block_delta" => {
+                if let Ok(delta) = serde_json::from_str::<ContentBlockDelta>(&event.data) {
+                    // Text and thinking deltas and tool input; signatures are skipped
+                    let event = match delta.delta.delta_type.as_str() {
+                        "text_delta" if !delta.delta.text.is_empty() => {
+                            StreamEvent::Token(delta.delta.text)
+                        }
+                        "thinking_delta" if !delta.delta.thinking.is_empty() => {
+                            StreamEvent::Thinking(delta.delta.thinking)
+                        }
+                        "input_json_delta" => {
+                            if let Some((index, _, input)) = &mut tool_use {
+                                if *index == delta.index {
+                                    input.push_str(&delta.delta.partial_json);
+                                }
                             }
+                            continue;
                         }
+                        _ => continue,
+                    };
+                    tx.send(event).await.ok();
+                }
+            }
+            "content_block_stop" => {
+                let Ok(stop) = serde_json::from_str::<ContentBlockStop>(&event.data) else {
+                    continue;
+                };
+                if let Some((_, mut call, input)) =
+                    tool_use.take_if(|(index, _, _)| *index == stop.index)
+                {
+                    if !input.trim().is_empty() {
+                        call.arguments = serde_json::from_str(&input)
+                            .unwrap_or(serde_json::Value::String(input));
                     }
-                    "message_stop" => {
-                        tx.send(StreamEvent::Done).await.ok();
-                        return Ok(());
+                    tx.send(StreamEvent::ToolCall(call)).await.ok();
+                }
+            }
+            "message_start" => {
+                if let Ok(start) = serde_json::from_str::<MessageStart>(&event.data) {
+                    prompt_tokens = start.message.usage.input_tokens;
+                }
+            }
+            "message_delta" => {
+                if let Ok(delta) = serde_json::from_str::<MessageDelta>(&event.data) {
+                    if let Some(reason) = delta.delta.stop_reason {
+                        tx.send(StreamEvent::Finish(FinishReason::parse(&reason)))
+                            .await
+                            .ok();
                     }
-                    "error" => {
-                        // Handle streaming errors
-                        if let Ok(error) = serde_json::from_str::<AnthropicError>(json_str) {
-                            return Err(anyhow!(
-                                "Stream error: {} - {}",
-                                error.error.error_type,
-                                error.error.message
-                            ));
-                        }
+                    if let Some(usage) = delta.usage {
+                        let usage = TokenUsage {
+                            prompt_tokens,
+                            completion_tokens: usage.output_tokens,
+                        };
+                        tx.send(StreamEvent::Usage(usage)).await.ok();
                     }
-                    // Ignore other events: message_start, content_block_start,
-                    // content_block_stop, message_delta, ping
-                    _ => {}
                 }
             }
+            "message_stop" => {
+                tx.send(StreamEvent::Done).await.ok();
+                return Ok(());
+            }
+            "error" => {
+                // Handle streaming errors
+                if let Ok(error) = serde_json::from_str::<AnthropicError>(&event.data) {
+                    return Err(anyhow!(
+                        "Stream error: {} - {}",
+                        error.error.error_type,
+                        error.error.message
+                    ));
+                }
+            }
+            // Ignore other events, such as ping
+            _ => {}
         }
     }
 
@@ -278,16 +622,20 @@ async fn stream_chat_inner(
 mod tests {
     use super::*;
 
+    use crate::llm::ReasoningEffort;
+
     #[test]
     fn test_convert_messages_basic() {
         let messages = vec![
             ChatMessage {
                 role: "user".to_string(),
                 content: "Hello".to_string(),
+                ..Default::default()
             },
             ChatMessage {
                 role: "assistant".to_string(),
                 content: "Hi there!".to_string(),
+                ..Default::default()
             },
         ];
 
@@ -296,9 +644,84 @@ mod tests {
         assert!(system.is_none());
         assert_eq!(anthropic_msgs.len(), 2);
         assert_eq!(anthropic_msgs[0].role, "user");
-        assert_eq!(anthropic_msgs[0].content, "Hello");
+        assert_eq!(anthropic_msgs[0].content, AnthropicContent::Text("Hello".to_string()));
         assert_eq!(anthropic_msgs[1].role, "assistant");
-        assert_eq!(anthropic_msgs[1].content, "Hi there!");
+        assert_eq!(
+            anthropic_msgs[1].content,
+            AnthropicContent::Text("Hi there!".to_string())
+        );
+    }
+
+    #[test]
+    fn test_images_come_before_the_text() {
+        let (_, messages) = convert_messages(vec![ChatMessage {
+            role: "user".to_string(),
+            content: "What is this?".to_string(),
+            images: vec![ImageData {
+                media_type: "image/jpeg".to_string(),
+                data: "aGVsbG8=".to_string(),
+            }],
+            ..Default::default()
+        }]);
+        let json = serde_json::to_value(&messages[0]).unwrap();
+        assert_eq!(json["content"][0]["type"], "image");
+        assert_eq!(json["content"][0]["source"]["type"], "base64");
+        assert_eq!(json["content"][0]["source"]["media_type"], "image/jpeg");
+        assert_eq!(json["content"][0]["source"]["data"], "aGVsbG8=");
+        assert_eq!(json["content"][1]["type"], "text");
+        assert_eq!(json["content"][1]["text"], "What is this?");
+    }
+
+    #[test]
+    fn test_tool_calls_and_results_become_blocks() {
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({"path": "notes.txt"}),
+        };
+        let result = |id: &str| ChatMessage {
+            role: "tool".to_string(),
+            content: "hello".to_string(),
+            tool_call_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        let tools = [ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: serde_json::json!({"type": "object"}),
+        }];
+        let messages = vec![
+            ChatMessage {
+                role: "user".to_string(),
+                content: "Compare the notes".to_string(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "assistant".to_string(),
+                content: "Reading both.".to_string(),
+                tool_calls: vec![call("toolu_1"), call("toolu_2")],
+                ..Default::default()
+            },
+            result("toolu_1"),
+            result("toolu_2"),
+        ];
+        let json = serde_json::to_value(build_request(&LlmConfig::default(), messages, &tools))
+            .unwrap();
+
+        assert_eq!(json["tools"][0]["name"], "read_file");
+        assert_eq!(json["tools"][0]["input_schema"]["type"], "object");
+        let reply = &json["messages"][1]["content"];
+        assert_eq!(reply[0]["type"], "text");
+        assert_eq!(reply[1]["type"], "tool_use");
+        assert_eq!(reply[1]["id"], "toolu_1");
+        assert_eq!(reply[1]["input"]["path"], "notes.txt");
+        // Both results go back in one user message
+        let messages = json["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["type"], "tool_result");
+        assert_eq!(messages[2]["content"][1]["tool_use_id"], "toolu_2");
+        assert_eq!(messages[2]["content"][1]["content"], "hello");
     }
 
     #[test]
@@ -307,10 +730,12 @@ mod tests {
             ChatMessage {
                 role: "system".to_string(),
                 content: "You are a helpful assistant.".to_string(),
+                ..Default::default()
             },
             ChatMessage {
                 role: "user".to_string(),
                 content: "Hello".to_string(),
+                ..Default::default()
             },
         ];
 
@@ -327,12 +752,14 @@ mod tests {
             model: "claude-sonnet-4-5".to_string(),
             messages: vec![AnthropicMessage {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: AnthropicContent::Text("Hello".to_string()),
             }],
+            tools: Vec::new(),
             max_tokens: 1024,
             stream: true,
             temperature: Some(0.7),
             system: None,
+            thinking: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -349,12 +776,14 @@ mod tests {
             model: "claude-sonnet-4-5".to_string(),
             messages: vec![AnthropicMessage {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: AnthropicContent::Text("Hello".to_string()),
             }],
+            tools: Vec::new(),
             max_tokens: 1024,
             stream: true,
             temperature: None,
             system: Some("Be helpful.".to_string()),
+            thinking: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -363,6 +792,26 @@ mod tests {
         assert!(!json.contains("\"temperature\""));
     }
 
+    #[test]
+    fn test_request_with_thinking_budget() {
+        let config = LlmConfig {
+            max_tokens: Some(1024),
+            reasoning: Some(ReasoningEffort::Medium),
+            ..LlmConfig::default()
+        };
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            images: Vec::new(),
+            ..Default::default()
+        }];
+        let json = serde_json::to_value(build_request(&config, messages, &[])).unwrap();
+        assert_eq!(json["thinking"]["type"], "enabled");
+        assert_eq!(json["thinking"]["budget_tokens"], 8192);
+        assert!(json["max_tokens"].as_u64().unwrap() > 8192);
+        assert!(json.get("temperature").is_none());
+    }
+
     #[test]
     fn test_parse_content_block_delta() {
         let json = r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#;
@@ -371,6 +820,78 @@ mod tests {
         assert_eq!(delta.delta.text, "Hello");
     }
 
+    #[tokio::test]
+    async fn test_parse_stream_separates_thinking_and_reports_usage() {
+        let body = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Check the units.\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"abc\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"42 m\"}}\n\n",
+            "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":40}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::io::Error>(body.as_bytes().to_vec())]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(format!("{:?}", event));
+        }
+        assert_eq!(
+            events,
+            [
+                "Thinking(\"Check the units.\")",
+                "Token(\"42 m\")",
+                "Finish(Stop)",
+                "Usage(TokenUsage { prompt_tokens: 25, completion_tokens: 40 })",
+                "Done"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_assembles_tool_calls() {
+        let body = concat!(
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Reading it.\"}}\n\n",
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"read_file\",\"input\":{}}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"path\\\": \"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"notes.txt\\\"}\"}}\n\n",
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":1}\n\n",
+            "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::io::Error>(body.as_bytes().to_vec())]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Token(t)) if t == "Reading it."));
+        match rx.recv().await {
+            Some(StreamEvent::ToolCall(call)) => {
+                assert_eq!(call.id, "toolu_1");
+                assert_eq!(call.name, "read_file");
+                assert_eq!(call.arguments, serde_json::json!({"path": "notes.txt"}));
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+        assert!(matches!(
+            rx.recv().await,
+            Some(StreamEvent::Finish(FinishReason::ToolCalls))
+        ));
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Done)));
+    }
+
+    #[test]
+    fn test_parse_message_delta_stop_reason() {
+        let json = r#"{"type":"message_delta","delta":{"stop_reason":"max_tokens","stop_sequence":null},"usage":{"output_tokens":1024}}"#;
+        let delta: MessageDelta = serde_json::from_str(json).unwrap();
+        assert_eq!(delta.delta.stop_reason.as_deref(), Some("max_tokens"));
+    }
+
     #[test]
     fn test_parse_error_response() {
         let json = r#"{"type":"error","error":{"type":"invalid_api_key","message":"Invalid API key provided"}}"#;
@@ -379,6 +900,21 @@ mod tests {
         assert_eq!(error.error.message, "Invalid API key provided");
     }
 
+    #[test]
+    fn test_parse_models() {
+        let json = r#"{"data":[
+            {"type":"model","id":"claude-sonnet-4-5-20250929","display_name":"Claude Sonnet 4.5"},
+            {"type":"model","id":"claude-new"}
+        ],"has_more":false}"#;
+        let models = parse_models(json).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].id, "claude-sonnet-4-5-20250929");
+        assert_eq!(models[0].name, "Claude Sonnet 4.5");
+        // Entries without a display name fall back to the id
+        assert_eq!(models[1].name, "claude-new");
+        assert!(parse_models("not json").is_err());
+    }
+
     #[test]
     fn test_client_not_configured() {
         let config = LlmConfig::default();
@@ -390,16 +926,20 @@ mod tests {
 
     #[test]
     fn test_client_configured() {
-        let mut config = LlmConfig::default();
-        config.api_key = "test-oauth-token".to_string(); // Now an OAuth token, not API key
+        let config = LlmConfig {
+            api_key: "test-oauth-token".to_string(), // Now an OAuth token, not API key
+            ..LlmConfig::default()
+        };
         let client = AnthropicClient::new(config);
         assert!(client.is_configured());
     }
 
     #[test]
     fn test_model_name() {
-        let mut config = LlmConfig::default();
-        config.model = "claude-opus-4".to_string();
+        let config = LlmConfig {
+            model: "claude-opus-4".to_string(),
+            ..LlmConfig::default()
+        };
         let client = AnthropicClient::new(config);
         assert_eq!(client.model(), "claude-opus-4");
     }
@@ -0,0 +1,274 @@
+//! Backpressure between provider streams and the UI.
+//!
+//! Providers send their events into a [`channel`] sized by
+//! [`StreamConfig::channel_size`], and the stages between them and the UI
+//! hand events on through single-slot channels, so the configured size is
+//! what bounds buffering. With [`BackpressurePolicy::Block`] a full provider
+//! channel pauses the provider's reader until the UI catches up. With
+//! [`BackpressurePolicy::Coalesce`] the relay keeps draining the provider into
+//! a channel of the configured size and merges tokens into a single pending
+//! event while that one is full, which keeps very fast local models from
+//! stalling their reader task.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use super::StreamEvent;
+use crate::config::{BackpressurePolicy, StreamConfig};
+
+/// Channel fullness counters, shared by all streams of a client.
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    events: AtomicU64,
+    full: AtomicU64,
+    coalesced: AtomicU64,
+    max_depth: AtomicUsize,
+}
+
+/// Point-in-time copy of [`StreamStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStatsSnapshot {
+    /// Events delivered to the UI
+    pub events: u64,
+    /// Times an event found the channel full
+    pub full: u64,
+    /// Tokens merged into an earlier pending token
+    pub coalesced: u64,
+    /// Deepest queue observed
+    pub max_depth: usize,
+}
+
+impl StreamStats {
+    /// Take a snapshot of the counters.
+    pub fn snapshot(&self) -> StreamStatsSnapshot {
+        StreamStatsSnapshot {
+            events: self.events.load(Ordering::Relaxed),
+            full: self.full.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+        }
+    }
+
+    fn delivered(&self, tx: &mpsc::Sender<StreamEvent>) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        let depth = tx.max_capacity() - tx.capacity();
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+}
+
+/// Create the channel a provider sends a response's events into.
+pub fn channel(config: StreamConfig) -> (mpsc::Sender<StreamEvent>, mpsc::Receiver<StreamEvent>) {
+    mpsc::channel(config.channel_size.max(1))
+}
+
+/// Create a single-slot channel for a stage that passes events on.
+pub fn handoff() -> (mpsc::Sender<StreamEvent>, mpsc::Receiver<StreamEvent>) {
+    mpsc::channel(1)
+}
+
+/// Relay events from `source`, a provider's [`channel`], applying `config`'s policy.
+pub fn relay(
+    source: mpsc::Receiver<StreamEvent>,
+    config: StreamConfig,
+    stats: Arc<StreamStats>,
+) -> mpsc::Receiver<StreamEvent> {
+    match config.backpressure {
+        BackpressurePolicy::Block => {
+            let (tx, rx) = handoff();
+            tokio::spawn(relay_blocking(source, tx, stats));
+            rx
+        }
+        BackpressurePolicy::Coalesce => {
+            let (tx, rx) = channel(config);
+            tokio::spawn(relay_coalescing(source, tx, stats));
+            rx
+        }
+    }
+}
+
+async fn relay_blocking(
+    mut source: mpsc::Receiver<StreamEvent>,
+    tx: mpsc::Sender<StreamEvent>,
+    stats: Arc<StreamStats>,
+) {
+    loop {
+        // The provider's channel is the buffer here, so measure it before taking an event
+        let depth = source.len();
+        let Some(event) = source.recv().await else { return };
+        if depth == source.max_capacity() {
+            stats.full.fetch_add(1, Ordering::Relaxed);
+        }
+        if tx.send(event).await.is_err() {
+            return;
+        }
+        stats.events.fetch_add(1, Ordering::Relaxed);
+        stats.max_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+}
+
+async fn relay_coalescing(
+    mut source: mpsc::Receiver<StreamEvent>,
+    tx: mpsc::Sender<StreamEvent>,
+    stats: Arc<StreamStats>,
+) {
+    // Tokens that arrived while the channel was full
+    let mut pending = String::new();
+
+    loop {
+        let event = if pending.is_empty() {
+            source.recv().await
+        } else {
+            tokio::select! {
+                event = source.recv() => event,
+                permit = tx.reserve() => {
+                    let Ok(permit) = permit else { return };
+                    permit.send(StreamEvent::Token(std::mem::take(&mut pending)));
+                    stats.delivered(&tx);
+                    continue;
+                }
+            }
+        };
+
+        match event {
+            Some(StreamEvent::Token(token)) if pending.is_empty() => {
+                match tx.try_send(StreamEvent::Token(token)) {
+                    Ok(()) => stats.delivered(&tx),
+                    Err(TrySendError::Full(StreamEvent::Token(token))) => {
+                        stats.full.fetch_add(1, Ordering::Relaxed);
+                        pending = token;
+                    }
+                    Err(_) => return,
+                }
+            }
+            Some(StreamEvent::Token(token)) => {
+                stats.coalesced.fetch_add(1, Ordering::Relaxed);
+                pending.push_str(&token);
+            }
+            // Control events and end of stream flush pending text first, in order
+            other => {
+                if !pending.is_empty() {
+                    if tx
+                        .send(StreamEvent::Token(std::mem::take(&mut pending)))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    stats.delivered(&tx);
+                }
+                let Some(event) = other else { return };
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+                stats.delivered(&tx);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(mut rx: mpsc::Receiver<StreamEvent>) -> (String, usize, bool) {
+        let mut text = String::new();
+        let mut tokens = 0;
+        let mut done = false;
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Token(t) => {
+                    tokens += 1;
+                    text.push_str(&t);
+                }
+                StreamEvent::Done => done = true,
+                _ => {}
+            }
+        }
+        (text, tokens, done)
+    }
+
+    async fn send_tokens(n: usize) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = mpsc::channel(n + 1);
+        for i in 0..n {
+            tx.send(StreamEvent::Token(i.to_string())).await.unwrap();
+        }
+        tx.send(StreamEvent::Done).await.unwrap();
+        rx
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_delivers_every_event() {
+        let stats = Arc::new(StreamStats::default());
+        let config = StreamConfig {
+            channel_size: 2,
+            backpressure: BackpressurePolicy::Block,
+        };
+        let rx = relay(send_tokens(10).await, config, Arc::clone(&stats));
+
+        let (text, tokens, done) = collect(rx).await;
+        assert_eq!(text, "0123456789");
+        assert_eq!(tokens, 10);
+        assert!(done);
+        assert_eq!(stats.snapshot().events, 11);
+        assert_eq!(stats.snapshot().coalesced, 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_buffers_only_the_provider_channel() {
+        let stats = Arc::new(StreamStats::default());
+        let config = StreamConfig {
+            channel_size: 4,
+            backpressure: BackpressurePolicy::Block,
+        };
+        let (tx, source) = channel(config);
+        let _rx = relay(source, config, Arc::clone(&stats));
+
+        // Nobody reads, so the provider is held back once the buffers fill
+        let mut accepted = 0;
+        for i in 0..100 {
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            if tx.try_send(StreamEvent::Token(i.to_string())).is_err() {
+                break;
+            }
+            accepted += 1;
+        }
+        // The provider channel, plus the relay's event in hand and the hand-off slot
+        assert_eq!(accepted, 4 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_policy_merges_tokens_when_full() {
+        let stats = Arc::new(StreamStats::default());
+        let config = StreamConfig {
+            channel_size: 2,
+            backpressure: BackpressurePolicy::Coalesce,
+        };
+        let (tx, source) = mpsc::channel(51);
+        for i in 0..50 {
+            tx.send(StreamEvent::Token(i.to_string())).await.unwrap();
+        }
+        tx.send(StreamEvent::Done).await.unwrap();
+        let rx = relay(source, config, Arc::clone(&stats));
+
+        // Let the relay take every event before the UI starts reading
+        while tx.capacity() < tx.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+        drop(tx);
+
+        let expected: String = (0..50).map(|i| i.to_string()).collect();
+        let (text, tokens, done) = collect(rx).await;
+        assert_eq!(text, expected);
+        assert!(tokens < 50);
+        assert!(done);
+
+        let snapshot = stats.snapshot();
+        assert!(snapshot.full >= 1);
+        assert!(snapshot.coalesced >= 1);
+        assert_eq!(snapshot.max_depth, 2);
+    }
+}
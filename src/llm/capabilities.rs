@@ -0,0 +1,215 @@
+//! Model capability registry.
+//!
+//! Maps a provider and model name to the features the model supports, so the
+//! UI can hide or explain affordances that don't apply instead of letting
+//! requests fail. Models are matched by name pattern; unknown hosted models
+//! are assumed to support everything, while unknown local models are assumed
+//! to be text-only.
+
+use super::Provider;
+
+/// A feature that may or may not apply to the active model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Image input
+    Vision,
+    /// Tool / function calling
+    Tools,
+    /// Sampling temperature is honored
+    Temperature,
+    /// Reasoning effort can be set (`/think`)
+    Reasoning,
+    /// Models run on a local runtime that can be inspected (`/local`)
+    LocalModels,
+}
+
+impl Capability {
+    /// Short description used in hints.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Capability::Vision => "image input",
+            Capability::Tools => "tool calling",
+            Capability::Temperature => "temperature",
+            Capability::Reasoning => "reasoning effort",
+            Capability::LocalModels => "local model management",
+        }
+    }
+}
+
+/// Features supported by a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    pub vision: bool,
+    pub tools: bool,
+    pub temperature: bool,
+    pub reasoning: bool,
+    pub local_models: bool,
+}
+
+/// Ollama model families known to accept images.
+const OLLAMA_VISION: &[&str] = &[
+    "llava",
+    "bakllava",
+    "moondream",
+    "vision",
+    "gemma3",
+    "qwen2.5vl",
+    "qwen3-vl",
+    "minicpm-v",
+    "granite3.2-vision",
+];
+
+/// Ollama model families known to support tool calling.
+const OLLAMA_TOOLS: &[&str] = &[
+    "llama3.1",
+    "llama3.2",
+    "llama3.3",
+    "qwen2.5",
+    "qwen3",
+    "mistral",
+    "mixtral",
+    "command-r",
+    "firefunction",
+    "granite3",
+    "gpt-oss",
+];
+
+/// OpenAI reasoning models, which only accept the default temperature.
+const FIXED_TEMPERATURE: &[&str] = &["o1", "o3", "o4", "gpt-5"];
+
+impl ModelCapabilities {
+    /// Look up the capabilities of `model` served by `provider`.
+    pub fn for_model(provider: Provider, model: &str) -> Self {
+        let model = model.to_lowercase();
+        // OpenRouter ids are prefixed with the vendor, e.g. "openai/o3"
+        let name = model.rsplit('/').next().unwrap_or(&model);
+
+        let temperature = !FIXED_TEMPERATURE.iter().any(|p| name.starts_with(p));
+
+        match provider {
+            Provider::Ollama => Self {
+                vision: OLLAMA_VISION.iter().any(|p| name.contains(p)),
+                tools: OLLAMA_TOOLS.iter().any(|p| name.starts_with(p)),
+                temperature,
+                reasoning: false,
+                local_models: true,
+            },
+            // Only OpenAI's reasoning models accept `reasoning_effort`
+            Provider::OpenAI => Self {
+                vision: true,
+                tools: true,
+                temperature,
+                reasoning: !temperature,
+                local_models: false,
+            },
+            // Groq serves open models; only Llama 4 takes images
+            Provider::Groq => Self {
+                vision: name.contains("llama-4"),
+                tools: true,
+                temperature,
+                reasoning: false,
+                local_models: false,
+            },
+            // The reasoner thinks on its own and ignores sampling settings and tools
+            Provider::DeepSeek => {
+                let reasoner = name.contains("reasoner");
+                Self {
+                    vision: false,
+                    tools: !reasoner,
+                    temperature: !reasoner,
+                    reasoning: false,
+                    local_models: false,
+                }
+            }
+            // Whatever the server loaded; assume a plain chat model that may call tools
+            Provider::LocalServer => Self {
+                vision: false,
+                tools: true,
+                temperature,
+                reasoning: false,
+                local_models: false,
+            },
+            Provider::Anthropic | Provider::GitHubCopilot | Provider::OpenRouter => Self {
+                vision: true,
+                tools: true,
+                temperature,
+                reasoning: true,
+                local_models: false,
+            },
+        }
+    }
+
+    /// Check whether a capability is supported.
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Vision => self.vision,
+            Capability::Tools => self.tools,
+            Capability::Temperature => self.temperature,
+            Capability::Reasoning => self.reasoning,
+            Capability::LocalModels => self.local_models,
+        }
+    }
+}
+
+/// Explain why `capability` is unavailable for the model, or `None` if it is available.
+pub fn unsupported_hint(provider: Provider, model: &str, capability: Capability) -> Option<String> {
+    if ModelCapabilities::for_model(provider, model).supports(capability) {
+        return None;
+    }
+    Some(match capability {
+        Capability::LocalModels => format!(
+            "{} is only available with Ollama (current provider: {})",
+            capability.label(),
+            provider.display_name()
+        ),
+        Capability::Temperature => format!("{} ignores custom temperature", model),
+        _ => format!("{} does not support {}", model, capability.label()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosted_models_support_everything_but_local() {
+        let caps = ModelCapabilities::for_model(Provider::Anthropic, "claude-sonnet-4-5");
+        assert!(caps.vision && caps.tools && caps.temperature);
+        assert!(!caps.local_models);
+    }
+
+    #[test]
+    fn test_reasoning_models_ignore_temperature() {
+        assert!(!ModelCapabilities::for_model(Provider::GitHubCopilot, "gpt-5-mini").temperature);
+        assert!(!ModelCapabilities::for_model(Provider::OpenRouter, "openai/o3").temperature);
+        assert!(ModelCapabilities::for_model(Provider::GitHubCopilot, "gpt-4.1").temperature);
+    }
+
+    #[test]
+    fn test_openai_reasoning_only_on_reasoning_models() {
+        let o3 = ModelCapabilities::for_model(Provider::OpenAI, "o3-mini");
+        assert!(o3.reasoning && !o3.temperature);
+        let gpt = ModelCapabilities::for_model(Provider::OpenAI, "gpt-4.1");
+        assert!(!gpt.reasoning && gpt.temperature);
+    }
+
+    #[test]
+    fn test_ollama_patterns() {
+        let qwen = ModelCapabilities::for_model(Provider::Ollama, "qwen3:4b");
+        assert!(qwen.tools && !qwen.vision && qwen.local_models);
+        assert!(!qwen.reasoning);
+
+        let llava = ModelCapabilities::for_model(Provider::Ollama, "llava:13b");
+        assert!(llava.vision && !llava.tools);
+    }
+
+    #[test]
+    fn test_unsupported_hint() {
+        assert!(unsupported_hint(Provider::Ollama, "llava", Capability::Vision).is_none());
+        let hint = unsupported_hint(Provider::Ollama, "phi3", Capability::Vision).unwrap();
+        assert_eq!(hint, "phi3 does not support image input");
+        let hint =
+            unsupported_hint(Provider::Anthropic, "claude", Capability::LocalModels).unwrap();
+        assert!(hint.contains("only available with Ollama"));
+    }
+}
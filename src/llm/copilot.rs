@@ -6,14 +6,23 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 
-use super::{ChatMessage, LlmProvider, Provider, StreamEvent};
+use super::openai::{
+    FunctionTool, MessageContent, ToolCallBuffer, ToolCallDelta, WireToolCall,
+};
+use super::{
+    backpressure, recording, signing, sse, ChatMessage, FinishReason, LlmProvider, ModelInfo,
+    Provider, ProviderError, ProviderResult, ReasoningEffort, RequestPreview, StreamEvent,
+    ToolDefinition,
+};
 use crate::auth::{AuthStorage, Credential, DeviceCodeFlow, OAuthToken};
+use crate::config::{SigningConfig, StreamConfig, TimeoutConfig};
 
 /// GitHub Copilot token response.
 #[derive(Debug, Clone, Deserialize)]
@@ -27,18 +36,26 @@ struct CopilotToken {
 struct CopilotRequest {
     model: String,
     messages: Vec<CopilotMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
 }
 
 /// Message format for Copilot API.
 #[derive(Debug, Serialize)]
 struct CopilotMessage {
     role: String,
-    content: String,
+    content: MessageContent,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
 }
 
 /// SSE delta for streaming responses.
@@ -58,6 +75,29 @@ struct StreamChoice {
 struct DeltaContent {
     #[serde(default)]
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+/// One entry of a `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+    /// Whether Copilot Chat offers the model in its picker
+    #[serde(default = "default_true")]
+    model_picker_enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 /// Copilot token state for caching.
@@ -87,10 +127,20 @@ pub struct CopilotProvider {
     model: String,
     temperature: Option<f32>,
     max_tokens: Option<u32>,
+    /// Reasoning effort sent as `reasoning_effort`.
+    reasoning: Option<ReasoningEffort>,
     /// Cached OAuth token from GitHub.
     pub oauth_token: Arc<RwLock<Option<String>>>,
     /// Cached Copilot API token.
     copilot_token: Arc<RwLock<Option<TokenState>>>,
+    /// GitHub Enterprise Cloud slug (`<slug>.ghe.com`), if scoped to an enterprise.
+    enterprise: Option<String>,
+    /// Request signing for gateways in front of the chat API.
+    signing: Option<SigningConfig>,
+    /// Directory to record raw response streams into, if recording is enabled.
+    record_dir: Option<PathBuf>,
+    /// Size of the channel responses are streamed through.
+    stream: StreamConfig,
 }
 
 impl Clone for CopilotProvider {
@@ -100,8 +150,13 @@ impl Clone for CopilotProvider {
             model: self.model.clone(),
             temperature: self.temperature,
             max_tokens: self.max_tokens,
+            reasoning: self.reasoning,
             oauth_token: Arc::clone(&self.oauth_token),
             copilot_token: Arc::clone(&self.copilot_token),
+            enterprise: self.enterprise.clone(),
+            signing: self.signing.clone(),
+            record_dir: self.record_dir.clone(),
+            stream: self.stream,
         }
     }
 }
@@ -114,8 +169,13 @@ impl CopilotProvider {
             model: "claude-sonnet-4.5".to_string(),
             temperature: Some(0.7),
             max_tokens: Some(4096),
+            reasoning: None,
             oauth_token: Arc::new(RwLock::new(None)),
             copilot_token: Arc::new(RwLock::new(None)),
+            enterprise: None,
+            signing: None,
+            record_dir: None,
+            stream: StreamConfig::default(),
         }
     }
 
@@ -137,6 +197,118 @@ impl CopilotProvider {
         self
     }
 
+    /// Create a provider that asks for a reasoning effort.
+    pub fn with_reasoning(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning = Some(effort);
+        self
+    }
+
+    /// Create a provider using `token` instead of the stored GitHub OAuth token.
+    pub fn with_oauth_token(mut self, token: impl Into<String>) -> Self {
+        self.oauth_token = Arc::new(RwLock::new(Some(token.into())));
+        self
+    }
+
+    /// Create a provider scoped to a GitHub Enterprise Cloud slug.
+    pub fn with_enterprise(mut self, slug: impl Into<String>) -> Self {
+        self.enterprise = Some(slug.into());
+        self
+    }
+
+    /// Create a provider that signs chat requests.
+    pub fn with_signing(mut self, signing: SigningConfig) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    /// Create a provider that streams responses through a channel configured by `stream`.
+    pub fn with_stream(mut self, stream: StreamConfig) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Create a provider that records raw response streams into `dir`.
+    pub fn with_record_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.record_dir = Some(dir.into());
+        self
+    }
+
+    /// Create a provider whose requests use the connect and read `timeouts`.
+    pub fn with_timeouts(mut self, timeouts: &TimeoutConfig) -> Self {
+        self.client = super::http_client(timeouts);
+        self
+    }
+
+    /// URL for exchanging the GitHub OAuth token for a Copilot token.
+    fn token_url(&self) -> String {
+        match &self.enterprise {
+            Some(slug) => format!("https://api.{}.ghe.com/copilot_internal/v2/token", slug),
+            None => "https://api.github.com/copilot_internal/v2/token".to_string(),
+        }
+    }
+
+    /// Build the request body for `messages`, offering `tools`.
+    fn build_request(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+    ) -> CopilotRequest {
+        CopilotRequest {
+            model: self.model.clone(),
+            messages: messages
+                .iter()
+                .map(|m| CopilotMessage {
+                    role: m.role.clone(),
+                    content: MessageContent::new(m.content.clone(), &m.images),
+                    tool_calls: WireToolCall::all(m.tool_calls.clone()),
+                    tool_call_id: m.tool_call_id.clone(),
+                })
+                .collect(),
+            tools: FunctionTool::all(tools),
+            stream: true,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            reasoning_effort: self.reasoning.map(|effort| effort.effort()),
+        }
+    }
+
+    /// Base URL of the Copilot API.
+    fn api_base(&self) -> String {
+        match &self.enterprise {
+            Some(slug) => format!("https://copilot-api.{}.ghe.com", slug),
+            None => "https://api.githubcopilot.com".to_string(),
+        }
+    }
+
+    /// URL for chat completions.
+    fn chat_url(&self) -> String {
+        format!("{}/chat/completions", self.api_base())
+    }
+
+    /// Fetch the models offered in Copilot's model picker.
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>> {
+        let copilot_token = self.get_copilot_token().await?;
+        let request = self
+            .client
+            .get(format!("{}/models", self.api_base()))
+            .header("Authorization", format!("Bearer {}", copilot_token))
+            .header("Copilot-Integration-Id", "vscode-chat")
+            .header("Editor-Version", "scry-cli/0.1.0");
+        let response = signing::sign(request, self.signing.as_ref())
+            .await?
+            .send()
+            .await
+            .context("Failed to list Copilot models")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Copilot API error ({}): {}", status, body));
+        }
+
+        parse_models(&response.text().await?)
+    }
+
     /// Load credentials from storage.
     pub async fn load_credentials(&self) -> Result<bool> {
         let storage = AuthStorage::load()?;
@@ -201,21 +373,26 @@ impl CopilotProvider {
             model: self.model.clone(),
             messages: vec![CopilotMessage {
                 role: "user".to_string(),
-                content: "Hi".to_string(),
+                content: MessageContent::Text("Hi".to_string()),
+                tool_calls: Vec::new(),
+                tool_call_id: None,
             }],
+            tools: Vec::new(),
             stream: false,
             temperature: Some(0.0),
             max_tokens: Some(1),
+            reasoning_effort: None,
         };
 
-        let response = self
+        let request = self
             .client
-            .post("https://api.githubcopilot.com/chat/completions")
+            .post(self.chat_url())
             .header("Authorization", format!("Bearer {}", copilot_token))
             .header("Content-Type", "application/json")
             .header("Copilot-Integration-Id", "vscode-chat")
-            .header("Editor-Version", "scry-cli/0.1.0")
-            .json(&request_body)
+            .header("Editor-Version", "scry-cli/0.1.0");
+        let response = signing::json_body(request, &request_body, self.signing.as_ref())
+            .await?
             .send()
             .await
             .context("Failed to validate token")?;
@@ -263,12 +440,14 @@ impl CopilotProvider {
             .clone()
             .ok_or_else(|| anyhow!("Not authenticated - run OAuth flow first"))?;
 
-        let response = self
+        let request = self
             .client
-            .get("https://api.github.com/copilot_internal/v2/token")
+            .get(self.token_url())
             .header("Authorization", format!("Bearer {}", oauth_token))
             .header("User-Agent", "scry-cli/0.1.0")
-            .header("Accept", "application/json")
+            .header("Accept", "application/json");
+        let response = signing::sign(request, self.signing.as_ref())
+            .await?
             .send()
             .await
             .context("Failed to get Copilot token")?;
@@ -300,46 +479,35 @@ impl CopilotProvider {
     async fn stream_chat_inner(
         &self,
         messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
         tx: mpsc::Sender<StreamEvent>,
     ) -> Result<()> {
-        self.stream_chat_with_retry(messages, tx, 0).await
+        self.stream_chat_with_retry(messages, tools, tx, 0).await
     }
 
     /// Send a streaming chat request with exponential backoff retry.
     fn stream_chat_with_retry(
         &self,
         messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
         tx: mpsc::Sender<StreamEvent>,
         retry_count: u32,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
         Box::pin(async move {
             let copilot_token = self.get_copilot_token().await?;
 
-            let copilot_messages: Vec<CopilotMessage> = messages
-                .iter()
-                .map(|m| CopilotMessage {
-                    role: m.role.clone(),
-                    content: m.content.clone(),
-                })
-                .collect();
-
-            let request_body = CopilotRequest {
-                model: self.model.clone(),
-                messages: copilot_messages,
-                stream: true,
-                temperature: self.temperature,
-                max_tokens: self.max_tokens,
-            };
+            let request_body = self.build_request(&messages, &tools);
 
-            let response = self
+            let request = self
                 .client
-                .post("https://api.githubcopilot.com/chat/completions")
+                .post(self.chat_url())
                 .header("Authorization", format!("Bearer {}", copilot_token))
                 .header("Content-Type", "application/json")
                 .header("Accept", "text/event-stream")
                 .header("Copilot-Integration-Id", "vscode-chat")
-                .header("Editor-Version", "scry-cli/0.1.0")
-                .json(&request_body)
+                .header("Editor-Version", "scry-cli/0.1.0");
+            let response = signing::json_body(request, &request_body, self.signing.as_ref())
+                .await?
                 .send()
                 .await
                 .context("Failed to send chat request")?;
@@ -356,7 +524,9 @@ impl CopilotProvider {
                     // Clear cached token and retry
                     *self.copilot_token.write().await = None;
                     
-                    return self.stream_chat_with_retry(messages, tx, retry_count + 1).await;
+                    return self
+                        .stream_chat_with_retry(messages, tools, tx, retry_count + 1)
+                        .await;
                 } else {
                     // Max retries exceeded - send AuthError event
                     tx.send(StreamEvent::AuthError).await.ok();
@@ -369,50 +539,79 @@ impl CopilotProvider {
                 return Err(anyhow!("Copilot API error ({}): {}", status, body));
             }
 
-            let mut stream = response.bytes_stream();
-            let mut buffer = String::new();
-
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk.context("Error reading stream")?;
-                let text = String::from_utf8_lossy(&chunk);
-                buffer.push_str(&text);
+            let recorder = recording::start(
+                self.record_dir.as_deref(),
+                Provider::GitHubCopilot,
+                &self.model,
+            );
+            let stream = recording::tap(response.bytes_stream(), recorder);
+            parse_stream(stream, &tx).await
+        })
+    }
+}
 
-                // Process complete lines
-                while let Some(newline_pos) = buffer.find('\n') {
-                    let line = buffer[..newline_pos].trim().to_string();
-                    buffer = buffer[newline_pos + 1..].to_string();
+/// Models from a `/models` response body, skipping those hidden from the picker.
+fn parse_models(body: &str) -> Result<Vec<ModelInfo>> {
+    let list: ModelList =
+        serde_json::from_str(body).context("Failed to parse Copilot model list")?;
+    let mut models: Vec<ModelInfo> = Vec::new();
+    for entry in list.data {
+        // The same model can be listed once per version
+        if entry.model_picker_enabled && !models.iter().any(|m| m.id == entry.id) {
+            models.push(ModelInfo {
+                name: entry.name.unwrap_or_else(|| entry.id.clone()),
+                id: entry.id,
+            });
+        }
+    }
+    Ok(models)
+}
 
-                    if line.is_empty() {
-                        continue;
-                    }
+/// Parse a Copilot SSE byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(stream: S, tx: &mpsc::Sender<StreamEvent>) -> Result<()>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut events = std::pin::pin!(sse::events(stream));
+    let mut tool_calls = ToolCallBuffer::default();
+
+    while let Some(event) = events.next().await {
+        let event = event.context("Error reading stream")?;
+        if event.data == "[DONE]" {
+            tool_calls.send(tx).await;
+            tx.send(StreamEvent::Done).await.ok();
+            return Ok(());
+        }
 
-                    if let Some(data) = line.strip_prefix("data: ") {
-                        if data == "[DONE]" {
-                            tx.send(StreamEvent::Done).await.ok();
-                            return Ok(());
-                        }
-
-                        if let Ok(delta) = serde_json::from_str::<StreamDelta>(data) {
-                            for choice in delta.choices {
-                                if let Some(content) = choice.delta.content {
-                                    if !content.is_empty() {
-                                        tx.send(StreamEvent::Token(content)).await.ok();
-                                    }
-                                }
-                                if choice.finish_reason.is_some() {
-                                    tx.send(StreamEvent::Done).await.ok();
-                                    return Ok(());
-                                }
-                            }
-                        }
+        if let Ok(delta) = serde_json::from_str::<StreamDelta>(&event.data) {
+            for choice in delta.choices {
+                if let Some(content) = choice.delta.content {
+                    if !content.is_empty() {
+                        tx.send(StreamEvent::Token(content)).await.ok();
                     }
                 }
+                for call in choice.delta.tool_calls {
+                    tool_calls.push(call);
+                }
+                if let Some(reason) = choice.finish_reason {
+                    tool_calls.send(tx).await;
+                    tx.send(StreamEvent::Finish(FinishReason::parse(&reason)))
+                        .await
+                        .ok();
+                    tx.send(StreamEvent::Done).await.ok();
+                    return Ok(());
+                }
             }
-
-            tx.send(StreamEvent::Done).await.ok();
-            Ok(())
-        })
+        }
     }
+
+    tool_calls.send(tx).await;
+    tx.send(StreamEvent::Done).await.ok();
+    Ok(())
 }
 
 impl Default for CopilotProvider {
@@ -437,12 +636,31 @@ impl LlmProvider for CopilotProvider {
         true // Assume configured if provider exists; actual check happens at runtime
     }
 
-    fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent> {
-        let (tx, rx) = mpsc::channel(100);
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        Some(RequestPreview::new(self.chat_url(), &self.build_request(&messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        self.fetch_models()
+            .await
+            .map_err(|e| ProviderError::from_message(&e.to_string()))
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.stream);
         let provider = self.clone();
+        let tools = tools.to_vec();
 
         tokio::spawn(async move {
-            if let Err(e) = provider.stream_chat_inner(messages, tx.clone()).await {
+            if let Err(e) = provider.stream_chat_inner(messages, tools, tx.clone()).await {
                 let _ = tx.send(StreamEvent::Error(e.to_string())).await;
             }
         });
@@ -480,6 +698,40 @@ mod tests {
         assert_eq!(provider.max_tokens, Some(2048));
     }
 
+    #[tokio::test]
+    async fn test_copilot_provider_with_oauth_token() {
+        let provider = CopilotProvider::new().with_oauth_token("gho_test");
+        assert!(provider.has_oauth_token().await);
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let json = r#"{"data":[
+            {"id":"gpt-4.1","name":"GPT-4.1","model_picker_enabled":true},
+            {"id":"gpt-4.1","name":"GPT-4.1","model_picker_enabled":true},
+            {"id":"text-embedding-3-small","name":"Embedding V3 small","model_picker_enabled":false},
+            {"id":"claude-sonnet-4.5"}
+        ]}"#;
+        let models = parse_models(json).unwrap();
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["gpt-4.1", "claude-sonnet-4.5"]);
+        assert_eq!(models[0].name, "GPT-4.1");
+        assert_eq!(models[1].name, "claude-sonnet-4.5");
+    }
+
+    #[test]
+    fn test_copilot_provider_enterprise_urls() {
+        let provider = CopilotProvider::new();
+        assert_eq!(provider.chat_url(), "https://api.githubcopilot.com/chat/completions");
+
+        let provider = provider.with_enterprise("acme");
+        assert_eq!(
+            provider.token_url(),
+            "https://api.acme.ghe.com/copilot_internal/v2/token"
+        );
+        assert_eq!(provider.chat_url(), "https://copilot-api.acme.ghe.com/chat/completions");
+    }
+
     #[test]
     fn test_copilot_provider_display_name() {
         let provider = CopilotProvider::new();
@@ -492,11 +744,15 @@ mod tests {
             model: "claude-sonnet-4.5".to_string(),
             messages: vec![CopilotMessage {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: MessageContent::Text("Hello".to_string()),
+                tool_calls: Vec::new(),
+                tool_call_id: None,
             }],
+            tools: Vec::new(),
             stream: true,
             temperature: Some(0.7),
             max_tokens: None,
+            reasoning_effort: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -510,7 +766,9 @@ mod tests {
     fn test_copilot_message_serialization() {
         let msg = CopilotMessage {
             role: "user".to_string(),
-            content: "Hello world".to_string(),
+            content: MessageContent::Text("Hello world".to_string()),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
         };
 
         let json = serde_json::to_string(&msg).unwrap();
@@ -565,9 +823,8 @@ mod tests {
 
     #[test]
     fn test_device_code_flow() {
-        let _flow = CopilotProvider::device_code_flow();
         // Just verify it creates without panicking
-        assert!(true, "Device code flow created successfully");
+        let _flow = CopilotProvider::device_code_flow();
     }
 
     #[tokio::test]
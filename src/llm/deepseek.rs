@@ -0,0 +1,387 @@
+//! DeepSeek provider using its OpenAI-style chat completions API.
+//!
+//! Streams `deepseek-chat` and `deepseek-reasoner` replies from
+//! `api.deepseek.com`. The reasoner streams its chain of thought in a
+//! separate `reasoning_content` field before the answer; it arrives as
+//! [`StreamEvent::Thinking`] so the UI can show or hide it, and is never sent
+//! back in later requests (the API rejects it).
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use super::openai::{FunctionTool, ToolCallBuffer, ToolCallDelta, WireToolCall};
+use super::{
+    backpressure, openai, recording, signing, sse, ChatMessage, FinishReason, LlmConfig,
+    LlmProvider, ModelInfo, Provider, ProviderError, ProviderResult, RequestPreview, StreamEvent,
+    TokenUsage, ToolDefinition,
+};
+
+/// Default DeepSeek API base URL.
+const DEFAULT_API_BASE: &str = "https://api.deepseek.com";
+
+/// DeepSeek provider.
+pub struct DeepSeekProvider {
+    client: Client,
+    config: Arc<LlmConfig>,
+}
+
+impl DeepSeekProvider {
+    /// Create a new DeepSeek provider with the given configuration.
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            client: super::http_client(&config.timeouts),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Get the API base URL.
+    fn api_base(&self) -> &str {
+        if self.config.api_base.is_empty() {
+            DEFAULT_API_BASE
+        } else {
+            &self.config.api_base
+        }
+    }
+
+    /// Chat completions endpoint.
+    fn chat_url(&self) -> String {
+        format!("{}/chat/completions", self.api_base().trim_end_matches('/'))
+    }
+
+    /// Build the request body for `messages`, offering `tools`.
+    fn build_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> DeepSeekRequest {
+        DeepSeekRequest {
+            model: self.config.model.clone(),
+            messages: messages
+                .into_iter()
+                .map(|m| DeepSeekMessage {
+                    role: m.role,
+                    content: m.content,
+                    tool_calls: WireToolCall::all(m.tool_calls),
+                    tool_call_id: m.tool_call_id,
+                })
+                .collect(),
+            tools: FunctionTool::all(tools),
+            stream: true,
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for DeepSeekProvider {
+    fn provider(&self) -> Provider {
+        Provider::DeepSeek
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.config.api_key.is_empty()
+    }
+
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        Some(RequestPreview::new(self.chat_url(), &self.build_request(messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let signing = self.config.signing.as_ref();
+        let ids = openai::list_models(self.api_base(), &self.config.api_key, None, signing)
+            .await
+            .map_err(ProviderError::Other)?;
+        Ok(ids.into_iter().map(ModelInfo::from_id).collect())
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.config.stream);
+
+        let client = self.client.clone();
+        let url = self.chat_url();
+        let api_key = self.config.api_key.clone();
+        let signing = self.config.signing.clone();
+        let recorder = recording::recorder_for(&self.config);
+        let request = self.build_request(messages, tools);
+
+        tokio::spawn(async move {
+            if api_key.is_empty() {
+                let _ = tx
+                    .send(StreamEvent::Error(
+                        "DeepSeek API key not configured. Set DEEPSEEK_API_KEY environment variable."
+                            .to_string(),
+                    ))
+                    .await;
+                return;
+            }
+
+            let builder = client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", api_key));
+            let response = match signing::json_body(builder, &request, signing.as_ref()).await {
+                Ok(builder) => builder
+                    .send()
+                    .await
+                    .map_err(|e| format!("Request failed: {}", e)),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            let result = match response {
+                Ok(response) if response.status().is_success() => {
+                    let stream = recording::tap(response.bytes_stream(), recorder);
+                    parse_stream(stream, &tx).await
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    Err(match serde_json::from_str::<DeepSeekErrorResponse>(&body) {
+                        Ok(error) => {
+                            format!("DeepSeek error ({}): {}", status, error.error.message)
+                        }
+                        Err(_) => format!("DeepSeek error ({}): {}", status, body),
+                    })
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                let _ = tx.send(StreamEvent::Error(e)).await;
+            }
+        });
+
+        rx
+    }
+}
+
+/// DeepSeek chat completions request.
+#[derive(Debug, Serialize)]
+struct DeepSeekRequest {
+    model: String,
+    messages: Vec<DeepSeekMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+}
+
+/// DeepSeek message format.
+#[derive(Debug, Serialize)]
+struct DeepSeekMessage {
+    role: String,
+    content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// Streaming response chunk.
+#[derive(Debug, Deserialize)]
+struct DeepSeekStreamChunk {
+    #[serde(default)]
+    choices: Vec<DeepSeekChoice>,
+    /// Sent with the last chunk
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+    #[serde(default)]
+    error: Option<DeepSeekError>,
+}
+
+/// Choice in a streaming chunk.
+#[derive(Debug, Deserialize)]
+struct DeepSeekChoice {
+    #[serde(default)]
+    delta: DeepSeekDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+/// Incremental message content.
+#[derive(Debug, Default, Deserialize)]
+struct DeepSeekDelta {
+    #[serde(default)]
+    content: Option<String>,
+    /// Chain of thought, streamed by `deepseek-reasoner` before the answer
+    #[serde(default)]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// DeepSeek error details.
+#[derive(Debug, Deserialize)]
+struct DeepSeekError {
+    message: String,
+}
+
+/// DeepSeek error response wrapper.
+#[derive(Debug, Deserialize)]
+struct DeepSeekErrorResponse {
+    error: DeepSeekError,
+}
+
+/// Parse a DeepSeek SSE byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(
+    stream: S,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut events = std::pin::pin!(sse::events(stream));
+    let mut tool_calls = ToolCallBuffer::default();
+
+    while let Some(event) = events.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        let data = event.data.as_str();
+        if data == "[DONE]" {
+            if tool_calls.send(tx).await {
+                let _ = tx.send(StreamEvent::Done).await;
+            }
+            return Ok(());
+        }
+
+        let chunk = match serde_json::from_str::<DeepSeekStreamChunk>(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                eprintln!("DeepSeek parse warning: {} for data: {}", e, data);
+                continue;
+            }
+        };
+        if let Some(error) = chunk.error {
+            return Err(error.message);
+        }
+        for choice in chunk.choices {
+            let delta = choice.delta;
+            let events = [
+                delta
+                    .reasoning_content
+                    .filter(|c| !c.is_empty())
+                    .map(StreamEvent::Thinking),
+                delta
+                    .content
+                    .filter(|c| !c.is_empty())
+                    .map(StreamEvent::Token),
+            ];
+            for event in events.into_iter().flatten() {
+                if tx.send(event).await.is_err() {
+                    return Ok(()); // Receiver dropped
+                }
+            }
+            for call in delta.tool_calls {
+                tool_calls.push(call);
+            }
+            if let Some(reason) = choice.finish_reason {
+                if !tool_calls.send(tx).await {
+                    return Ok(()); // Receiver dropped
+                }
+                let _ = tx
+                    .send(StreamEvent::Finish(FinishReason::parse(&reason)))
+                    .await;
+            }
+        }
+        if let Some(usage) = chunk.usage {
+            let _ = tx.send(StreamEvent::Usage(usage)).await;
+        }
+    }
+
+    if tool_calls.send(tx).await {
+        let _ = tx.send(StreamEvent::Done).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LlmConfig {
+        LlmConfig {
+            provider: Provider::DeepSeek,
+            api_base: String::new(),
+            api_key: "sk-test".to_string(),
+            model: "deepseek-reasoner".to_string(),
+            ..LlmConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_deepseek_provider_basics() {
+        let provider = DeepSeekProvider::new(config());
+        assert_eq!(provider.provider(), Provider::DeepSeek);
+        assert_eq!(provider.model(), "deepseek-reasoner");
+        assert_eq!(provider.display_name(), "DeepSeek");
+        assert!(provider.is_configured());
+        assert_eq!(provider.api_base(), DEFAULT_API_BASE);
+
+        let request = provider.build_request(vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            ..ChatMessage::default()
+        }], &[]);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "deepseek-reasoner");
+        assert_eq!(json["stream"], true);
+        assert_eq!(json["messages"][0]["content"], "Hello");
+        assert_eq!(json["max_tokens"], 4096);
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_separates_reasoning() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":null,\"reasoning_content\":\"\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":null,\"reasoning_content\":\"Two plus \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":null,\"reasoning_content\":\"two.\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"4\",\"reasoning_content\":null}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::convert::Infallible>(
+            body.as_bytes().to_vec(),
+        )]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        let mut thinking = String::new();
+        let mut text = String::new();
+        let mut finish = None;
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Thinking(chunk) => {
+                    assert!(text.is_empty(), "reasoning arrives before the answer");
+                    thinking.push_str(&chunk);
+                }
+                StreamEvent::Token(token) => text.push_str(&token),
+                StreamEvent::Finish(reason) => finish = Some(reason),
+                StreamEvent::Done => {}
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(thinking, "Two plus two.");
+        assert_eq!(text, "4");
+        assert_eq!(finish, Some(FinishReason::Stop));
+    }
+}
@@ -3,67 +3,52 @@
 //! This module provides a unified interface for interacting with LLM providers.
 //! Currently supports:
 //! - Anthropic (Claude)
+//! - DeepSeek
 //! - GitHub Copilot
+//! - Groq (fast inference)
+//! - Local OpenAI-compatible servers (LM Studio, vLLM, llama.cpp, LocalAI)
 //! - Ollama (local models)
+//! - OpenAI
 //! - OpenRouter (multi-model access)
 
 mod anthropic;
+mod backpressure;
+mod capabilities;
 mod copilot;
+mod deepseek;
 mod ollama;
+mod openai;
 mod openrouter;
 mod provider;
+mod ratelimit;
+mod reasoning;
+pub mod recording;
+mod retry;
+mod signing;
+pub mod sse;
+mod watchdog;
 
-pub use provider::{LlmProvider, ProviderError, ProviderResult};
+pub use provider::{LlmProvider, ModelInfo, ProviderError, ProviderResult, RequestPreview};
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::config::{ProviderScope, RetryConfig, SigningConfig, StreamConfig, TimeoutConfig};
 
 pub use anthropic::AnthropicClient;
+pub use backpressure::{StreamStats, StreamStatsSnapshot};
+pub use capabilities::{unsupported_hint, Capability, ModelCapabilities};
 pub use copilot::CopilotProvider;
-pub use ollama::OllamaProvider;
+pub use deepseek::DeepSeekProvider;
+pub use ollama::{InstalledModel, OllamaProvider, PullStatus, RunningModel};
+pub use openai::{list_models, normalize_base_url, OpenAIProvider};
 pub use openrouter::OpenRouterProvider;
-
-/// Available models for GitHub Copilot.
-///
-/// Each tuple contains (display_name, api_model_id).
-/// These are the models available through GitHub Copilot Chat API.
-pub const COPILOT_MODELS: &[(&str, &str)] = &[
-    // Claude models (Anthropic)
-    ("Claude Sonnet 4.5", "claude-sonnet-4.5"),
-    ("Claude Sonnet 4", "claude-sonnet-4"),
-    ("Claude Opus 4.5", "claude-opus-4.5"),
-    ("Claude Opus 4.1", "claude-opus-4.1"),
-    ("Claude Haiku 4.5", "claude-haiku-4.5"),
-    // GPT models (OpenAI)
-    ("GPT-5.2", "gpt-5.2"),
-    ("GPT-5.1-Codex-Max", "gpt-5.1-codex-max"),
-    ("GPT-5.1-Codex", "gpt-5.1-codex"),
-    ("GPT-5.1-Codex-Mini", "gpt-5.1-codex-mini"),
-    ("GPT-5.1", "gpt-5.1"),
-    ("GPT-5-Codex", "gpt-5-codex"),
-    ("GPT-5 mini", "gpt-5-mini"),
-    ("GPT-5", "gpt-5"),
-    ("GPT-4.1", "gpt-4.1"),
-    // Gemini models (Google)
-    ("Gemini 3 Pro", "gemini-3-pro"),
-    ("Gemini 3 Flash", "gemini-3-flash"),
-    ("Gemini 2.5 Pro", "gemini-2.5-pro"),
-    // Other
-    ("Grok Code Fast 1", "grok-code-fast-1"),
-    ("Raptor mini", "raptor-mini"),
-];
-
-/// Available models for Anthropic (Claude).
-///
-/// Each tuple contains (display_name, api_model_id).
-/// These are the models available through Anthropic's API.
-pub const ANTHROPIC_MODELS: &[(&str, &str)] = &[
-    ("Claude Sonnet 4 (2025-05-14)", "claude-sonnet-4-20250514"),
-    ("Claude 3.5 Sonnet (2024-10-22)", "claude-3-5-sonnet-20241022"),
-    ("Claude 3.5 Haiku (2024-10-22)", "claude-3-5-haiku-20241022"),
-    ("Claude 3 Opus (2024-02-29)", "claude-3-opus-20240229"),
-];
+pub use ratelimit::{Quota, RateLimits};
+pub use reasoning::ReasoningEffort;
 
 /// Supported LLM providers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -74,6 +59,10 @@ pub enum Provider {
     Ollama,
     OpenRouter,
     GitHubCopilot,
+    OpenAI,
+    Groq,
+    DeepSeek,
+    LocalServer,
 }
 
 impl Provider {
@@ -82,8 +71,12 @@ impl Provider {
         &[
             Provider::Anthropic,
             Provider::GitHubCopilot,
+            Provider::OpenAI,
+            Provider::Groq,
+            Provider::DeepSeek,
             Provider::OpenRouter,
             Provider::Ollama,
+            Provider::LocalServer,
         ]
     }
 
@@ -93,7 +86,11 @@ impl Provider {
             Provider::Anthropic => "Anthropic",
             Provider::GitHubCopilot => "GitHub Copilot",
             Provider::OpenRouter => "OpenRouter",
+            Provider::OpenAI => "OpenAI",
+            Provider::Groq => "Groq",
+            Provider::DeepSeek => "DeepSeek",
             Provider::Ollama => "Ollama (Local)",
+            Provider::LocalServer => "OpenAI-compatible (Local)",
         }
     }
 
@@ -104,6 +101,11 @@ impl Provider {
             Provider::Ollama => "http://localhost:11434",
             Provider::OpenRouter => "https://openrouter.ai/api/v1",
             Provider::GitHubCopilot => "https://api.githubcopilot.com",
+            Provider::OpenAI => "https://api.openai.com/v1",
+            Provider::Groq => "https://api.groq.com/openai/v1",
+            Provider::DeepSeek => "https://api.deepseek.com",
+            // LM Studio's default; the connect dialog asks for the actual URL
+            Provider::LocalServer => "http://localhost:1234/v1",
         }
     }
 
@@ -114,9 +116,37 @@ impl Provider {
             Provider::Ollama => "qwen3:4b",
             Provider::OpenRouter => "anthropic/claude-sonnet-4-5",
             Provider::GitHubCopilot => "claude-sonnet-4.5",
+            Provider::OpenAI => "gpt-4.1",
+            Provider::Groq => "llama-3.3-70b-versatile",
+            Provider::DeepSeek => "deepseek-chat",
+            // Servers ignore or reject unknown names; the connect dialog picks a served one
+            Provider::LocalServer => "local-model",
+        }
+    }
+
+    /// Get a fast, inexpensive model for this provider, used by `auto` for simple prompts.
+    pub fn fast_model(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "claude-haiku-4-5",
+            Provider::Ollama => "qwen3:1.7b",
+            Provider::OpenRouter => "anthropic/claude-haiku-4.5",
+            Provider::GitHubCopilot => "gpt-5-mini",
+            Provider::OpenAI => "gpt-4.1-mini",
+            Provider::Groq => "llama-3.1-8b-instant",
+            Provider::DeepSeek => "deepseek-chat",
+            Provider::LocalServer => "local-model",
         }
     }
 
+    /// Models offered when the provider's model list can't be fetched.
+    pub fn fallback_models(&self) -> Vec<ModelInfo> {
+        let mut models = vec![ModelInfo::from_id(self.default_model())];
+        if self.fast_model() != self.default_model() {
+            models.push(ModelInfo::from_id(self.fast_model()));
+        }
+        models
+    }
+
     /// Get the environment variable name for the API key.
     pub fn env_var_name(&self) -> &'static str {
         match self {
@@ -124,6 +154,10 @@ impl Provider {
             Provider::Ollama => "", // No API key needed for local Ollama
             Provider::OpenRouter => "OPENROUTER_API_KEY",
             Provider::GitHubCopilot => "GITHUB_COPILOT_TOKEN",
+            Provider::OpenAI => "OPENAI_API_KEY",
+            Provider::Groq => "GROQ_API_KEY",
+            Provider::DeepSeek => "DEEPSEEK_API_KEY",
+            Provider::LocalServer => "", // Local servers rarely check keys
         }
     }
 
@@ -134,9 +168,20 @@ impl Provider {
             Provider::Ollama => false,
             Provider::OpenRouter => true,
             Provider::GitHubCopilot => false, // Uses OAuth
+            Provider::OpenAI => true,
+            Provider::Groq => true,
+            Provider::DeepSeek => true,
+            Provider::LocalServer => false,
         }
     }
 
+    /// Whether a trailing assistant message is continued rather than answered.
+    ///
+    /// Used to extend a cut-off reply seamlessly instead of asking for more.
+    pub const fn supports_prefill(&self) -> bool {
+        matches!(self, Provider::Anthropic)
+    }
+
     /// Check if this provider uses OAuth device flow.
     pub const fn uses_oauth(&self) -> bool {
         matches!(self, Provider::Anthropic | Provider::GitHubCopilot)
@@ -151,6 +196,10 @@ impl Provider {
             Provider::Ollama => "ollama",
             Provider::OpenRouter => "openrouter",
             Provider::GitHubCopilot => "github_copilot",
+            Provider::OpenAI => "openai",
+            Provider::Groq => "groq",
+            Provider::DeepSeek => "deepseek",
+            Provider::LocalServer => "local_server",
         }
     }
 
@@ -161,11 +210,29 @@ impl Provider {
         match self {
             Provider::Anthropic => None,    // Uses OAuth, not API keys
             Provider::OpenRouter => Some("https://openrouter.ai/keys"),
+            Provider::OpenAI => Some("https://platform.openai.com/api-keys"),
+            Provider::Groq => Some("https://console.groq.com/keys"),
+            Provider::DeepSeek => Some("https://platform.deepseek.com/api_keys"),
             Provider::Ollama => None,       // Local, no API key needed
+            Provider::LocalServer => None,
             Provider::GitHubCopilot => None, // Uses OAuth, not API keys
         }
     }
 
+    /// Label for this provider's organization scope in the UI.
+    pub const fn scope_label(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "Workspace",
+            Provider::GitHubCopilot => "Enterprise",
+            Provider::OpenAI
+            | Provider::Groq
+            | Provider::DeepSeek
+            | Provider::OpenRouter
+            | Provider::Ollama
+            | Provider::LocalServer => "Organization",
+        }
+    }
+
     /// Validate the format of an API key for this provider.
     ///
     /// Returns `Ok(())` if the format is valid, or an error message describing the issue.
@@ -196,10 +263,44 @@ impl Provider {
                 }
                 Ok(())
             }
+            Provider::OpenAI => {
+                // OpenAI keys start with "sk-" ("sk-proj-" for project keys)
+                if !key.starts_with("sk-") || key.starts_with("sk-ant-") || key.starts_with("sk-or-") {
+                    return Err("OpenAI keys must start with 'sk-'");
+                }
+                if key.len() < 20 {
+                    return Err("API key is too short");
+                }
+                Ok(())
+            }
+            Provider::Groq => {
+                // Groq keys start with "gsk_"
+                if !key.starts_with("gsk_") {
+                    return Err("Groq keys must start with 'gsk_'");
+                }
+                if key.len() < 20 {
+                    return Err("API key is too short");
+                }
+                Ok(())
+            }
+            Provider::DeepSeek => {
+                // DeepSeek keys start with "sk-", like OpenAI's
+                if !key.starts_with("sk-") || key.starts_with("sk-ant-") || key.starts_with("sk-or-") {
+                    return Err("DeepSeek keys must start with 'sk-'");
+                }
+                if key.len() < 20 {
+                    return Err("API key is too short");
+                }
+                Ok(())
+            }
             Provider::Ollama => {
                 // Ollama doesn't need an API key
                 Err("Ollama does not require an API key")
             }
+            Provider::LocalServer => {
+                // The server's URL is entered instead of a key
+                Err("Local servers do not require an API key")
+            }
             Provider::GitHubCopilot => {
                 // Copilot uses OAuth, not API keys
                 Err("GitHub Copilot uses OAuth, not API keys")
@@ -208,21 +309,52 @@ impl Provider {
     }
 }
 
+impl std::str::FromStr for Provider {
+    type Err = String;
+
+    /// Parse a provider from its storage key or a common alias (case-insensitive).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace('-', "_");
+        Provider::all()
+            .iter()
+            .copied()
+            .find(|p| p.storage_key() == name)
+            .or(match name.as_str() {
+                "copilot" | "github" => Some(Provider::GitHubCopilot),
+                "claude" => Some(Provider::Anthropic),
+                "local" | "lmstudio" | "lm_studio" | "vllm" | "llamacpp" | "llama_cpp" | "localai" => {
+                    Some(Provider::LocalServer)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| {
+                let known: Vec<&str> = Provider::all().iter().map(|p| p.storage_key()).collect();
+                format!("Unknown provider '{}' (expected one of: {})", s, known.join(", "))
+            })
+    }
+}
+
 /// Validate an API key by making a minimal request to the provider.
 ///
-/// This function makes a real API request to verify the key is valid.
+/// This function makes a real API request to verify the key is valid, sent
+/// with the organization/project headers of `scope` where the provider uses them.
 /// Returns `Ok(())` if the key is valid (including rate limited - 429).
 /// Returns an error message if the key is invalid or the request fails.
 ///
 /// # Note
 /// This may incur minimal API costs (~1 token for most providers).
-pub async fn validate_api_key(provider: Provider, api_key: &str) -> Result<(), String> {
+pub async fn validate_api_key(
+    provider: Provider,
+    api_key: &str,
+    scope: Option<&ProviderScope>,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
     use reqwest::Client;
 
     // Handle providers that don't use API keys first
     match provider {
-        Provider::Ollama => {
-            // Ollama doesn't need API key validation
+        Provider::Ollama | Provider::LocalServer => {
+            // Local providers don't need API key validation
             return Ok(());
         }
         Provider::GitHubCopilot => {
@@ -240,28 +372,70 @@ pub async fn validate_api_key(provider: Provider, api_key: &str) -> Result<(), S
     let client = Client::new();
 
     match provider {
-        Provider::Anthropic => validate_anthropic_key(&client, api_key).await,
-        Provider::OpenRouter => validate_openrouter_key(&client, api_key).await,
-        Provider::Ollama | Provider::GitHubCopilot => {
+        Provider::Anthropic => validate_anthropic_key(&client, api_key, signing).await,
+        Provider::OpenRouter => validate_openrouter_key(&client, api_key, signing).await,
+        Provider::OpenAI => validate_openai_key(&client, api_key, scope, signing).await,
+        Provider::Groq => validate_groq_key(&client, api_key, signing).await,
+        Provider::DeepSeek => validate_deepseek_key(&client, api_key, signing).await,
+        Provider::Ollama | Provider::LocalServer | Provider::GitHubCopilot => {
             // Already handled above
             unreachable!()
         }
     }
 }
 
+/// HTTP client for provider requests, with the connect and read timeouts
+/// of `timeouts`.
+pub(crate) fn http_client(timeouts: &TimeoutConfig) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    if timeouts.connect_secs > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(timeouts.connect_secs));
+    }
+    if timeouts.read_secs > 0 {
+        builder = builder.read_timeout(Duration::from_secs(timeouts.read_secs));
+    }
+    builder.build().unwrap_or_default()
+}
+
+/// How long a connectivity probe waits for any answer.
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Check in the background whether `url` answers at all (any HTTP status counts).
+///
+/// Returns None outside a Tokio runtime.
+pub fn probe(url: &str) -> Option<oneshot::Receiver<bool>> {
+    let handle = tokio::runtime::Handle::try_current().ok()?;
+    let url = url.to_string();
+    let (tx, rx) = oneshot::channel();
+    handle.spawn(async move {
+        let reachable = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+            Ok(client) => client.head(&url).send().await.is_ok(),
+            Err(_) => false,
+        };
+        let _ = tx.send(reachable);
+    });
+    Some(rx)
+}
+
 /// Validate an Anthropic API key by making a minimal request.
-async fn validate_anthropic_key(client: &reqwest::Client, api_key: &str) -> Result<(), String> {
+async fn validate_anthropic_key(
+    client: &reqwest::Client,
+    api_key: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
     // Use the messages endpoint with max_tokens=1 for minimal cost
-    let response = client
+    let request = client
         .post("https://api.anthropic.com/v1/messages")
         .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "model": "claude-sonnet-4-5",
-            "max_tokens": 1,
-            "messages": [{"role": "user", "content": "Hi"}]
-        }))
+        .header("anthropic-version", "2023-06-01");
+    let body = serde_json::json!({
+        "model": "claude-sonnet-4-5",
+        "max_tokens": 1,
+        "messages": [{"role": "user", "content": "Hi"}]
+    });
+    let response = signing::json_body(request, &body, signing)
+        .await
+        .map_err(|e| e.to_string())?
         .send()
         .await
         .map_err(|e| format!("Connection failed: {}", e))?;
@@ -291,17 +465,23 @@ async fn validate_anthropic_key(client: &reqwest::Client, api_key: &str) -> Resu
 }
 
 /// Validate an OpenRouter API key by making a minimal request.
-async fn validate_openrouter_key(client: &reqwest::Client, api_key: &str) -> Result<(), String> {
+async fn validate_openrouter_key(
+    client: &reqwest::Client,
+    api_key: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
     // OpenRouter uses OpenAI-compatible API format
-    let response = client
+    let request = client
         .post("https://openrouter.ai/api/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "model": "openai/claude-haiku-4.5",
-            "max_tokens": 1,
-            "messages": [{"role": "user", "content": "Hi"}]
-        }))
+        .header("Authorization", format!("Bearer {}", api_key));
+    let body = serde_json::json!({
+        "model": "openai/claude-haiku-4.5",
+        "max_tokens": 1,
+        "messages": [{"role": "user", "content": "Hi"}]
+    });
+    let response = signing::json_body(request, &body, signing)
+        .await
+        .map_err(|e| e.to_string())?
         .send()
         .await
         .map_err(|e| format!("Connection failed: {}", e))?;
@@ -330,11 +510,216 @@ async fn validate_openrouter_key(client: &reqwest::Client, api_key: &str) -> Res
     Err(format!("API error ({}): {}", status, body))
 }
 
+/// Validate an OpenAI API key by listing models (free, no tokens used).
+async fn validate_openai_key(
+    client: &reqwest::Client,
+    api_key: &str,
+    scope: Option<&ProviderScope>,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
+    let mut request = client
+        .get("https://api.openai.com/v1/models")
+        .header("Authorization", format!("Bearer {}", api_key));
+    for (name, value) in scope.map(ProviderScope::openai_headers).unwrap_or_default() {
+        request = request.header(name, value);
+    }
+    let response = signing::sign(request, signing)
+        .await
+        .map_err(|e| e.to_string())?
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    let status = response.status();
+
+    // 429 = valid key, rate limited (treat as valid)
+    if status.is_success() || status.as_u16() == 429 {
+        return Ok(());
+    }
+
+    // 401 = invalid key
+    if status.as_u16() == 401 {
+        return Err("Invalid API key".to_string());
+    }
+
+    // 403 = forbidden (e.g. key restricted to other endpoints or regions)
+    if status.as_u16() == 403 {
+        return Err("API key forbidden - check permissions".to_string());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("API error ({}): {}", status, body))
+}
+
+/// Validate a Groq API key by listing models (free, no tokens used).
+async fn validate_groq_key(
+    client: &reqwest::Client,
+    api_key: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
+    let request = client
+        .get("https://api.groq.com/openai/v1/models")
+        .header("Authorization", format!("Bearer {}", api_key));
+    let response = signing::sign(request, signing)
+        .await
+        .map_err(|e| e.to_string())?
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    let status = response.status();
+
+    // 429 = valid key, rate limited (treat as valid)
+    if status.is_success() || status.as_u16() == 429 {
+        return Ok(());
+    }
+
+    // 401 = invalid key
+    if status.as_u16() == 401 {
+        return Err("Invalid API key".to_string());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("API error ({}): {}", status, body))
+}
+
+/// Validate a DeepSeek API key by listing models (free, no tokens used).
+async fn validate_deepseek_key(
+    client: &reqwest::Client,
+    api_key: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<(), String> {
+    let request = client
+        .get("https://api.deepseek.com/models")
+        .header("Authorization", format!("Bearer {}", api_key));
+    let response = signing::sign(request, signing)
+        .await
+        .map_err(|e| e.to_string())?
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    let status = response.status();
+
+    // 429 = valid key, rate limited (treat as valid)
+    if status.is_success() || status.as_u16() == 429 {
+        return Ok(());
+    }
+
+    // 401 = invalid key
+    if status.as_u16() == 401 {
+        return Err("Invalid API key".to_string());
+    }
+
+    // 402 = valid key, but the account has no balance left
+    if status.as_u16() == 402 {
+        return Err("DeepSeek account balance is empty".to_string());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("API error ({}): {}", status, body))
+}
+
 /// Chat message for API requests.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// Images attached to the message (models with image input only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageData>,
+    /// Tools the model called in this reply (`assistant` messages)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call this message answers (`tool` messages, which carry a tool's output)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+/// An image sent to a vision model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageData {
+    /// MIME type, e.g. `image/png`
+    pub media_type: String,
+    /// Base64-encoded bytes
+    pub data: String,
+}
+
+impl ImageData {
+    /// The image as a `data:` URL, as OpenAI-style APIs take it.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+/// A function the model may call, described by a JSON schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments object
+    pub parameters: serde_json::Value,
+}
+
+/// A tool invocation requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Id the result is sent back under
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// Tokens a provider reports a request used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Tokens in the request, the whole conversation sent
+    pub prompt_tokens: u64,
+    /// Tokens in the reply
+    pub completion_tokens: u64,
+}
+
+/// Usage of two requests together, such as a reply and its continuation.
+impl std::ops::Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: TokenUsage) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+        }
+    }
+}
+
+/// Why the model stopped generating.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    /// Natural end of the answer (or a stop sequence)
+    Stop,
+    /// Cut off by the max tokens limit
+    Length,
+    /// Withheld or cut short by the provider's content filter
+    ContentFilter,
+    /// Stopped to call a tool
+    ToolCalls,
+    /// The stream broke off before the provider finished
+    Interrupted,
+    /// A reason this client doesn't know
+    Other(String),
+}
+
+impl FinishReason {
+    /// Map a provider's reason string (OpenAI, Anthropic or Ollama style).
+    pub fn parse(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" => FinishReason::Length,
+            "content_filter" | "refusal" => FinishReason::ContentFilter,
+            "tool_calls" | "tool_use" | "function_call" => FinishReason::ToolCalls,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
 }
 
 /// Events sent during streaming.
@@ -342,6 +727,26 @@ pub struct ChatMessage {
 pub enum StreamEvent {
     /// A chunk of text was received.
     Token(String),
+    /// A chunk of the model's reasoning, streamed apart from the answer.
+    Thinking(String),
+    /// The model asked to call a tool.
+    ToolCall(ToolCall),
+    /// Why generation stopped, sent just before `Done` by providers that report it.
+    Finish(FinishReason),
+    /// Tokens the request used, sent before `Done` by providers that report it.
+    Usage(TokenUsage),
+    /// Quotas left, from the response headers of providers that send them.
+    RateLimits(RateLimits),
+    /// The request failed with a transient error and is sent again after `delay`.
+    Retrying {
+        /// Which retry this is, from 1
+        attempt: u32,
+        /// Retries allowed in all
+        max_retries: u32,
+        delay: Duration,
+        /// The error that is being retried
+        reason: String,
+    },
     /// Stream completed successfully.
     Done,
     /// An error occurred.
@@ -369,6 +774,24 @@ pub struct LlmConfig {
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub credential_type: CredentialType,
+    /// Organization/project scoping per provider, keyed by storage key
+    pub scopes: BTreeMap<String, ProviderScope>,
+    /// Optional request signing applied before requests are sent
+    pub signing: Option<SigningConfig>,
+    /// Directory to record raw response streams into (for debugging parsers)
+    pub record_dir: Option<PathBuf>,
+    /// Channel size and backpressure policy for streamed responses
+    pub stream: StreamConfig,
+    /// How requests that fail before the reply starts are retried
+    pub retry: RetryConfig,
+    /// Connect, read and stall timeouts
+    pub timeouts: TimeoutConfig,
+    /// Preload local models when the client is created
+    pub warm_up: bool,
+    /// How long local models stay loaded after a request
+    pub keep_alive: Option<String>,
+    /// Reasoning effort requested with each message (`/think`)
+    pub reasoning: Option<ReasoningEffort>,
 }
 
 impl Default for LlmConfig {
@@ -382,6 +805,15 @@ impl Default for LlmConfig {
             temperature: Some(0.7),
             max_tokens: Some(4096),
             credential_type: CredentialType::default(),
+            scopes: BTreeMap::new(),
+            signing: None,
+            record_dir: None,
+            stream: StreamConfig::default(),
+            retry: RetryConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            warm_up: true,
+            keep_alive: None,
+            reasoning: None,
         }
     }
 }
@@ -392,22 +824,24 @@ impl LlmConfig {
         !self.provider.requires_api_key() || !self.api_key.is_empty()
     }
 
+    /// Get the organization/project scope configured for the active provider.
+    pub fn scope(&self) -> Option<&ProviderScope> {
+        self.scopes
+            .get(self.provider.storage_key())
+            .filter(|scope| !scope.is_empty())
+    }
+
+    /// Capabilities of the configured model.
+    pub fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::for_model(self.provider, &self.model)
+    }
+
     /// Load from environment variables, with file config as fallback.
     pub fn from_env_and_config(file_config: Option<&crate::config::LlmConfigFile>) -> Self {
         let mut config = Self::default();
 
         // First apply file config if present
         if let Some(fc) = file_config {
-            // Check for deprecated OpenAI config and warn
-            if fc.api_base.contains("openai.com") || fc.model.starts_with("gpt-") {
-                eprintln!(
-                    "\x1b[33mWarning: OpenAI configuration detected but is no longer supported.\x1b[0m"
-                );
-                eprintln!(
-                    "\x1b[33mPlease update your config to use Anthropic. See docs for migration.\x1b[0m"
-                );
-            }
-
             config.api_base = fc.api_base.clone();
             if let Some(ref key) = fc.api_key {
                 config.api_key = key.clone();
@@ -415,6 +849,15 @@ impl LlmConfig {
             config.model = fc.model.clone();
             config.temperature = fc.temperature;
             config.max_tokens = fc.max_tokens;
+            config.scopes = fc.scopes.clone();
+            config.signing = fc.signing.clone();
+            config.record_dir = fc.record_dir.clone();
+            config.stream = fc.stream;
+            config.retry = fc.retry;
+            config.timeouts = fc.timeouts;
+            config.warm_up = fc.warm_up;
+            config.keep_alive = fc.keep_alive.clone();
+            config.reasoning = fc.reasoning;
         }
 
         // Environment variable overrides file config
@@ -422,6 +865,10 @@ impl LlmConfig {
             config.api_key = key;
         }
 
+        if let Ok(dir) = std::env::var("SCRY_RECORD_DIR") {
+            config.record_dir = Some(PathBuf::from(dir));
+        }
+
         // Also check for ANTHROPIC_MODEL
         if let Ok(model) = std::env::var("ANTHROPIC_MODEL") {
             config.model = model;
@@ -429,6 +876,52 @@ impl LlmConfig {
 
         config
     }
+
+    /// Build a config for `provider`, falling back to credentials saved in `auth.json`.
+    ///
+    /// Used by the non-interactive subcommands, which can't run the connect dialog.
+    /// Environment variables still take precedence over stored credentials.
+    pub fn for_provider(
+        provider: Provider,
+        file_config: Option<&crate::config::LlmConfigFile>,
+    ) -> Self {
+        use crate::auth::{AuthStorage, Credential};
+
+        let mut config = Self::from_env_and_config(file_config);
+
+        // The file config only describes Anthropic; other providers start from their defaults
+        if provider != Provider::Anthropic {
+            config.provider = provider;
+            config.api_base = provider.default_api_base().to_string();
+            config.model = provider.default_model().to_string();
+            config.api_key = match provider.env_var_name() {
+                "" => String::new(),
+                var => std::env::var(var).unwrap_or_default(),
+            };
+            if let Some(model) = file_config.and_then(|fc| fc.models.get(provider.storage_key())) {
+                config.model = model.clone();
+            }
+        }
+
+        if config.api_key.is_empty() {
+            if let Ok(storage) = AuthStorage::load() {
+                if let Some(cred) = storage.get(provider.storage_key()) {
+                    if !cred.is_expired() {
+                        config.api_key = cred.token().to_string();
+                        config.credential_type = match cred {
+                            Credential::OAuth { .. } => CredentialType::OAuth,
+                            Credential::ApiKey { .. } => CredentialType::ApiKey,
+                        };
+                        if let Some(model) = cred.model() {
+                            config.model = model.to_string();
+                        }
+                    }
+                }
+            }
+        }
+
+        config
+    }
 }
 
 /// LLM client for making API calls.
@@ -438,22 +931,48 @@ impl LlmConfig {
 #[derive(Clone)]
 pub struct LlmClient {
     inner: Arc<dyn LlmProvider>,
+    stream: StreamConfig,
+    retry: RetryConfig,
+    /// How long a stream may go without an event; zero never gives up
+    stall: Duration,
+    stats: Arc<StreamStats>,
 }
 
 impl LlmClient {
     /// Create a new LLM client with the given configuration.
     ///
     /// This will create the appropriate provider based on the config.
-    pub fn new(config: LlmConfig) -> Self {
+    /// Settings the model doesn't support (see [`ModelCapabilities`]) are dropped.
+    pub fn new(mut config: LlmConfig) -> Self {
+        let (stream, retry) = (config.stream, config.retry);
+        let stall = Duration::from_secs(config.timeouts.stall_secs);
+        // Models with a fixed temperature reject requests that set one
+        if !config.capabilities().temperature {
+            config.temperature = None;
+        }
         let provider: Arc<dyn LlmProvider> = match config.provider {
             Provider::Anthropic => Arc::new(AnthropicClient::new(config)),
             Provider::GitHubCopilot => {
                 // Copilot provider - will load credentials on first use
                 let mut copilot = CopilotProvider::new();
+                if let Some(enterprise) = config.scope().and_then(|s| s.organization.clone()) {
+                    copilot = copilot.with_enterprise(enterprise);
+                }
+                if let Some(signing) = config.signing.clone() {
+                    copilot = copilot.with_signing(signing);
+                }
+                if let Some(dir) = config.record_dir.clone() {
+                    copilot = copilot.with_record_dir(dir);
+                }
+                copilot = copilot.with_timeouts(&config.timeouts);
+                copilot = copilot.with_stream(config.stream);
                 copilot = copilot.with_model(config.model);
                 if let Some(temp) = config.temperature {
                     copilot = copilot.with_temperature(temp);
                 }
+                if let Some(effort) = config.reasoning {
+                    copilot = copilot.with_reasoning(effort);
+                }
                 if let Some(max) = config.max_tokens {
                     copilot = copilot.with_max_tokens(max);
                 }
@@ -461,16 +980,54 @@ impl LlmClient {
             }
             Provider::Ollama => Arc::new(OllamaProvider::new(config)),
             Provider::OpenRouter => Arc::new(OpenRouterProvider::new(config)),
+            Provider::OpenAI => Arc::new(OpenAIProvider::new(config)),
+            Provider::Groq => Arc::new(OpenAIProvider::compatible(Provider::Groq, config)),
+            Provider::DeepSeek => Arc::new(DeepSeekProvider::new(config)),
+            Provider::LocalServer => {
+                Arc::new(OpenAIProvider::compatible(Provider::LocalServer, config))
+            }
         };
 
-        Self { inner: provider }
+        Self::from_provider(provider)
+            .with_stream_config(stream)
+            .with_retry_config(retry)
+            .with_stall_timeout(stall)
     }
 
     /// Create a new LLM client from an existing provider.
     ///
     /// Use this when you have a custom or pre-configured provider.
     pub fn from_provider(provider: Arc<dyn LlmProvider>) -> Self {
-        Self { inner: provider }
+        Self {
+            inner: provider,
+            stream: StreamConfig::default(),
+            retry: RetryConfig::default(),
+            stall: Duration::from_secs(TimeoutConfig::default().stall_secs),
+            stats: Arc::new(StreamStats::default()),
+        }
+    }
+
+    /// Use different streaming channel settings.
+    pub fn with_stream_config(mut self, stream: StreamConfig) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Use different retry settings.
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Fail streams that go `stall` without an event; zero waits forever.
+    pub fn with_stall_timeout(mut self, stall: Duration) -> Self {
+        self.stall = stall;
+        self
+    }
+
+    /// Channel statistics accumulated over all streamed responses.
+    pub fn stream_stats(&self) -> StreamStatsSnapshot {
+        self.stats.snapshot()
     }
 
     /// Get a reference to the underlying provider.
@@ -499,10 +1056,51 @@ impl LlmClient {
         self.inner.display_name()
     }
 
+    /// Ask the provider to preload its model.
+    ///
+    /// Returns `None` if the provider has nothing to warm up.
+    pub fn warm_up(&self) -> Option<oneshot::Receiver<Result<(), String>>> {
+        self.inner.warm_up()
+    }
+
+    /// The request `stream_chat_with_tools` would send, without sending it (`--dry-run`).
+    pub fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        self.inner.preview_request(messages, tools)
+    }
+
+    /// The models the provider offers, as reported by its API.
+    pub async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
     /// Send a streaming chat completion request.
     /// Returns a channel receiver that yields StreamEvents.
+    ///
+    /// Transient failures are retried as the client's [`RetryConfig`] says,
+    /// a stream that stalls ends with an error, and events are relayed
+    /// through a channel sized and managed according to its [`StreamConfig`].
     pub fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent> {
-        self.inner.stream_chat(messages)
+        self.stream_chat_with_tools(messages, Vec::new())
+    }
+
+    /// Send a streaming chat completion request offering `tools`, which the
+    /// model may answer with [`StreamEvent::ToolCall`]s.
+    pub fn stream_chat_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+    ) -> mpsc::Receiver<StreamEvent> {
+        let attempts =
+            retry::stream_with_retries(Arc::clone(&self.inner), messages, tools, self.retry);
+        backpressure::relay(
+            watchdog::watch(attempts, self.stall),
+            self.stream,
+            Arc::clone(&self.stats),
+        )
     }
 }
 
@@ -520,24 +1118,59 @@ mod tests {
 
     #[test]
     fn test_llm_client_model() {
-        let mut config = LlmConfig::default();
-        config.model = "claude-3-opus-20240229".to_string();
+        let config = LlmConfig {
+            model: "claude-3-opus-20240229".to_string(),
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert_eq!(client.model(), "claude-3-opus-20240229");
     }
 
+    #[test]
+    fn test_preview_request() {
+        let client = LlmClient::new(LlmConfig {
+            api_key: "sk-ant-secret".to_string(),
+            ..LlmConfig::default()
+        });
+        let preview = client
+            .preview_request(vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: "Be brief.".to_string(),
+                    ..ChatMessage::default()
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: "Hi".to_string(),
+                    ..ChatMessage::default()
+                },
+            ], &[])
+            .unwrap();
+        assert_eq!(preview.url, "https://api.anthropic.com/v1/messages");
+        assert_eq!(preview.body["system"], "Be brief.");
+        assert_eq!(preview.body["messages"][0]["content"], "Hi");
+
+        let shown = preview.to_string();
+        assert!(shown.starts_with("POST https://api.anthropic.com/v1/messages\n{"));
+        assert!(!shown.contains("sk-ant-secret"));
+    }
+
     #[test]
     fn test_llm_client_not_configured_without_key() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter; // OpenRouter requires API key
+        let config = LlmConfig {
+            provider: Provider::OpenRouter, // OpenRouter requires API key
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert!(!client.is_configured());
     }
 
     #[test]
     fn test_llm_client_configured_with_key() {
-        let mut config = LlmConfig::default();
-        config.api_key = "test-key".to_string();
+        let config = LlmConfig {
+            api_key: "test-key".to_string(),
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert!(client.is_configured());
     }
@@ -552,10 +1185,12 @@ mod tests {
 
     #[test]
     fn test_openrouter_provider() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_base = Provider::OpenRouter.default_api_base().to_string();
-        config.model = Provider::OpenRouter.default_model().to_string();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_base: Provider::OpenRouter.default_api_base().to_string(),
+            model: Provider::OpenRouter.default_model().to_string(),
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert_eq!(client.provider_type(), Provider::OpenRouter);
         // OpenRouter requires an API key, so it's not configured without one
@@ -564,9 +1199,11 @@ mod tests {
 
     #[test]
     fn test_openrouter_provider_configured() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_key = "test-key".to_string();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_key: "test-key".to_string(),
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert_eq!(client.provider_type(), Provider::OpenRouter);
         assert!(client.is_configured());
@@ -574,10 +1211,12 @@ mod tests {
 
     #[test]
     fn test_ollama_provider() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::Ollama;
-        config.api_base = Provider::Ollama.default_api_base().to_string();
-        config.model = Provider::Ollama.default_model().to_string();
+        let config = LlmConfig {
+            provider: Provider::Ollama,
+            api_base: Provider::Ollama.default_api_base().to_string(),
+            model: Provider::Ollama.default_model().to_string(),
+            ..LlmConfig::default()
+        };
         let client = LlmClient::new(config);
         assert_eq!(client.provider_type(), Provider::Ollama);
         // Ollama doesn't need an API key, so it's always configured
@@ -590,6 +1229,7 @@ mod tests {
         assert_eq!(Provider::OpenRouter.storage_key(), "openrouter");
         assert_eq!(Provider::Ollama.storage_key(), "ollama");
         assert_eq!(Provider::GitHubCopilot.storage_key(), "github_copilot");
+        assert_eq!(Provider::OpenAI.storage_key(), "openai");
     }
 
     #[test]
@@ -644,6 +1284,93 @@ mod tests {
         assert!(Provider::OpenRouter.validate_api_key_format("").is_err());
     }
 
+    #[test]
+    fn test_provider_validate_api_key_format_openai() {
+        assert!(Provider::OpenAI
+            .validate_api_key_format("sk-proj-abcdefghijklmnopqrstuvwxyz")
+            .is_ok());
+        // Keys for other providers are rejected before any request
+        assert!(Provider::OpenAI
+            .validate_api_key_format("sk-or-v1-abcdefghijklmnopqrstuvwxyz")
+            .is_err());
+        assert!(Provider::OpenAI.validate_api_key_format("sk-abc").is_err());
+    }
+
+    #[test]
+    fn test_openai_provider() {
+        let config = LlmConfig {
+            provider: Provider::OpenAI,
+            ..LlmConfig::default()
+        };
+        let client = LlmClient::new(config);
+        assert_eq!(client.provider_type(), Provider::OpenAI);
+        assert!(!client.is_configured());
+        assert_eq!(Provider::OpenAI.env_var_name(), "OPENAI_API_KEY");
+        assert!(Provider::OpenAI.api_key_url().is_some());
+    }
+
+    #[test]
+    fn test_groq_provider() {
+        assert!(Provider::Groq
+            .validate_api_key_format("gsk_abcdefghijklmnopqrstuvwxyz")
+            .is_ok());
+        assert!(Provider::Groq
+            .validate_api_key_format("sk-abcdefghijklmnopqrstuvwxyz")
+            .is_err());
+        assert!(Provider::Groq.validate_api_key_format("gsk_abc").is_err());
+
+        let client = LlmClient::new(LlmConfig {
+            provider: Provider::Groq,
+            api_key: "gsk_test".to_string(),
+            ..LlmConfig::default()
+        });
+        assert_eq!(client.provider_type(), Provider::Groq);
+        assert!(client.is_configured());
+        assert_eq!(Provider::Groq.env_var_name(), "GROQ_API_KEY");
+        assert!(Provider::Groq.default_model().starts_with("llama-3"));
+        assert_eq!("groq".parse::<Provider>(), Ok(Provider::Groq));
+    }
+
+    #[test]
+    fn test_deepseek_provider() {
+        assert!(Provider::DeepSeek
+            .validate_api_key_format("sk-0123456789abcdef0123456789abcdef")
+            .is_ok());
+        assert!(Provider::DeepSeek.validate_api_key_format("sk-abc").is_err());
+        assert!(Provider::DeepSeek
+            .validate_api_key_format("gsk_abcdefghijklmnopqrstuvwxyz")
+            .is_err());
+
+        let client = LlmClient::new(LlmConfig {
+            provider: Provider::DeepSeek,
+            ..LlmConfig::default()
+        });
+        assert_eq!(client.provider_type(), Provider::DeepSeek);
+        assert!(!client.is_configured());
+        assert_eq!(Provider::DeepSeek.env_var_name(), "DEEPSEEK_API_KEY");
+        assert_eq!("deepseek".parse::<Provider>(), Ok(Provider::DeepSeek));
+    }
+
+    #[test]
+    fn test_local_server_provider() {
+        assert!(Provider::LocalServer
+            .validate_api_key_format("sk-anything")
+            .is_err());
+
+        // Keyless, so it is ready as soon as a base URL is set
+        let client = LlmClient::new(LlmConfig {
+            provider: Provider::LocalServer,
+            api_base: "http://localhost:8000/v1".to_string(),
+            ..LlmConfig::default()
+        });
+        assert_eq!(client.provider_type(), Provider::LocalServer);
+        assert!(client.is_configured());
+        assert!(!Provider::LocalServer.requires_api_key());
+        assert_eq!("local_server".parse::<Provider>(), Ok(Provider::LocalServer));
+        assert_eq!("lmstudio".parse::<Provider>(), Ok(Provider::LocalServer));
+        assert_eq!("vllm".parse::<Provider>(), Ok(Provider::LocalServer));
+    }
+
     #[test]
     fn test_provider_validate_api_key_format_no_key_providers() {
         // Ollama doesn't need API keys
@@ -664,7 +1391,7 @@ mod tests {
     async fn test_validate_api_key_format_check_anthropic() {
         // Anthropic now supports both OAuth and API keys
         // Invalid format should fail before network request
-        let result = validate_api_key(Provider::Anthropic, "invalid-key").await;
+        let result = validate_api_key(Provider::Anthropic, "invalid-key", None, None).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("sk-ant-"));
     }
@@ -672,7 +1399,7 @@ mod tests {
     #[tokio::test]
     async fn test_validate_api_key_format_check_openrouter() {
         // Invalid format should fail before network request
-        let result = validate_api_key(Provider::OpenRouter, "invalid-key").await;
+        let result = validate_api_key(Provider::OpenRouter, "invalid-key", None, None).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("sk-or-"));
     }
@@ -680,14 +1407,14 @@ mod tests {
     #[tokio::test]
     async fn test_validate_api_key_ollama() {
         // Ollama doesn't need validation - should always succeed
-        let result = validate_api_key(Provider::Ollama, "anything").await;
+        let result = validate_api_key(Provider::Ollama, "anything", None, None).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_validate_api_key_copilot_fails() {
         // Copilot uses OAuth, not API keys
-        let result = validate_api_key(Provider::GitHubCopilot, "anything").await;
+        let result = validate_api_key(Provider::GitHubCopilot, "anything", None, None).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("OAuth"));
     }
@@ -695,29 +1422,56 @@ mod tests {
     #[tokio::test]
     async fn test_validate_api_key_empty() {
         // Empty key should fail - use OpenRouter since Anthropic now uses OAuth
-        let result = validate_api_key(Provider::OpenRouter, "").await;
+        let result = validate_api_key(Provider::OpenRouter, "", None, None).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("empty"));
     }
 
     #[test]
-    fn test_copilot_models_not_empty() {
-        assert!(!COPILOT_MODELS.is_empty());
+    fn test_llm_config_scope_follows_provider() {
+        let mut config = LlmConfig::default();
+        config.scopes.insert(
+            "github_copilot".to_string(),
+            ProviderScope {
+                organization: Some("acme".to_string()),
+                project: None,
+            },
+        );
+        assert!(config.scope().is_none());
+
+        config.provider = Provider::GitHubCopilot;
+        assert_eq!(config.scope().and_then(|s| s.organization.as_deref()), Some("acme"));
     }
 
     #[test]
-    fn test_copilot_models_have_display_and_api_names() {
-        for (display_name, api_id) in COPILOT_MODELS {
-            assert!(!display_name.is_empty(), "Display name should not be empty");
-            assert!(!api_id.is_empty(), "API ID should not be empty");
-        }
+    fn test_finish_reason_parse() {
+        assert_eq!(FinishReason::parse("length"), FinishReason::Length);
+        assert_eq!(FinishReason::parse("max_tokens"), FinishReason::Length);
+        assert_eq!(FinishReason::parse("end_turn"), FinishReason::Stop);
+        assert_eq!(FinishReason::parse("content_filter"), FinishReason::ContentFilter);
+        assert_eq!(
+            FinishReason::parse("load"),
+            FinishReason::Other("load".to_string())
+        );
+    }
+
+    #[test]
+    fn test_provider_from_str() {
+        assert_eq!("anthropic".parse::<Provider>(), Ok(Provider::Anthropic));
+        assert_eq!("OpenRouter".parse::<Provider>(), Ok(Provider::OpenRouter));
+        assert_eq!("github-copilot".parse::<Provider>(), Ok(Provider::GitHubCopilot));
+        assert_eq!("copilot".parse::<Provider>(), Ok(Provider::GitHubCopilot));
+        assert_eq!("ollama".parse::<Provider>(), Ok(Provider::Ollama));
+        assert_eq!("OpenAI".parse::<Provider>(), Ok(Provider::OpenAI));
+        assert!("nope".parse::<Provider>().unwrap_err().contains("anthropic"));
     }
 
     #[test]
-    fn test_copilot_models_first_is_default() {
-        // First model should match the default
-        let (_, first_api_id) = COPILOT_MODELS[0];
-        assert_eq!(first_api_id, "claude-sonnet-4.5");
-        assert_eq!(first_api_id, Provider::GitHubCopilot.default_model());
+    fn test_fallback_models() {
+        let models = Provider::GitHubCopilot.fallback_models();
+        assert_eq!(models[0].id, Provider::GitHubCopilot.default_model());
+        assert_eq!(models[1].id, Provider::GitHubCopilot.fast_model());
+        // Providers whose fast model is the default list it once
+        assert_eq!(Provider::LocalServer.fallback_models().len(), 1);
     }
 }
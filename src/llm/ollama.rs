@@ -1,15 +1,23 @@
 //! Ollama provider for local LLM models.
 //!
-//! Ollama runs locally and provides an OpenAI-compatible API.
-//! No authentication is required.
+//! Ollama runs locally and is reached through its native `/api/chat`
+//! endpoint, which supports images for multimodal models, tool calling and
+//! structured (`format`) output. No authentication is required.
 
-use futures::StreamExt;
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use futures::{Stream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
-use super::{ChatMessage, LlmConfig, LlmProvider, Provider, StreamEvent};
+use super::sse::Utf8Decoder;
+use super::{
+    backpressure, recording, signing, ChatMessage, FinishReason, LlmConfig, LlmProvider, ModelInfo,
+    Provider, ProviderError, ProviderResult, RequestPreview, StreamEvent, TokenUsage, ToolCall,
+    ToolDefinition,
+};
 
 /// Default Ollama API base URL.
 const DEFAULT_API_BASE: &str = "http://localhost:11434";
@@ -18,26 +26,174 @@ const DEFAULT_API_BASE: &str = "http://localhost:11434";
 pub struct OllamaProvider {
     client: Client,
     config: Arc<LlmConfig>,
+    /// Output format: `"json"` or a JSON schema
+    format: Option<serde_json::Value>,
 }
 
 impl OllamaProvider {
     /// Create a new Ollama provider with the given configuration.
     pub fn new(config: LlmConfig) -> Self {
         Self {
-            client: Client::new(),
+            client: super::http_client(&config.timeouts),
             config: Arc::new(config),
+            format: None,
         }
     }
 
+    /// Constrain output to JSON (`"json"`) or to a JSON schema.
+    pub fn with_format(mut self, format: serde_json::Value) -> Self {
+        self.format = Some(format);
+        self
+    }
+
     /// Create a new Ollama provider with default settings.
     pub fn with_defaults() -> Self {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::Ollama;
-        config.api_base = DEFAULT_API_BASE.to_string();
-        config.model = Provider::Ollama.default_model().to_string();
+        let config = LlmConfig {
+            provider: Provider::Ollama,
+            api_base: DEFAULT_API_BASE.to_string(),
+            model: Provider::Ollama.default_model().to_string(),
+            ..LlmConfig::default()
+        };
         Self::new(config)
     }
 
+    /// Build the request body for a conversation, offering `tools`.
+    fn build_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> OllamaChatRequest {
+        OllamaChatRequest {
+            model: self.config.model.clone(),
+            messages: messages.into_iter().map(OllamaMessage::from).collect(),
+            stream: true,
+            options: self.config.temperature.map(|t| OllamaOptions {
+                temperature: Some(t),
+            }),
+            tools: tools
+                .iter()
+                .cloned()
+                .map(|function| OllamaTool {
+                    tool_type: "function",
+                    function,
+                })
+                .collect(),
+            format: self.format.clone(),
+            keep_alive: self.config.keep_alive.clone(),
+        }
+    }
+
+    /// List the models Ollama currently has loaded (`/api/ps`).
+    pub async fn running_models(&self) -> Result<Vec<RunningModel>, String> {
+        let url = format!("{}/api/ps", self.api_base().trim_end_matches('/'));
+        let response = signing::sign(self.client.get(&url), self.config.signing.as_ref())
+            .await
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| connect_error(&e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Ollama error ({}): {}", status, body));
+        }
+
+        let ps: PsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid /api/ps response: {}", e))?;
+        Ok(ps.models)
+    }
+
+    /// List the models installed in Ollama (`/api/tags`), sorted by name.
+    pub async fn installed_models(&self) -> Result<Vec<InstalledModel>, String> {
+        let url = format!("{}/api/tags", self.api_base().trim_end_matches('/'));
+        let response = signing::sign(self.client.get(&url), self.config.signing.as_ref())
+            .await
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| connect_error(&e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Ollama error ({}): {}", status, body));
+        }
+
+        let tags: TagsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid /api/tags response: {}", e))?;
+        let mut models = tags.models;
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(models)
+    }
+
+    /// Download `model` into Ollama (`/api/pull`), streaming its progress.
+    ///
+    /// The receiver yields a status for each stage, ending with one whose
+    /// status is `success`, or an error. Dropping it stops the download.
+    pub fn pull(&self, model: &str) -> mpsc::Receiver<Result<PullStatus, String>> {
+        let (tx, rx) = mpsc::channel(32);
+
+        let client = self.client.clone();
+        let config = self.config.clone();
+        let url = format!("{}/api/pull", self.api_base().trim_end_matches('/'));
+        let body = serde_json::json!({ "model": model, "stream": true });
+
+        tokio::spawn(async move {
+            let result = async {
+                let request = client.post(&url);
+                let response = signing::json_body(request, &body, config.signing.as_ref())
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .send()
+                    .await
+                    .map_err(|e| connect_error(&e))?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(format!("Ollama error ({}): {}", status, body));
+                }
+                parse_pull_stream(response.bytes_stream(), &tx).await
+            }
+            .await;
+            if let Err(e) = result {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+
+        rx
+    }
+
+    /// Unload a model immediately to free its memory.
+    pub async fn unload(&self, model: &str) -> Result<(), String> {
+        let url = format!("{}/api/chat", self.api_base().trim_end_matches('/'));
+        // An empty chat with keep_alive 0 evicts the model without generating
+        let body = serde_json::json!({
+            "model": model,
+            "messages": [],
+            "keep_alive": 0,
+            "stream": false,
+        });
+        let request = self.client.post(&url);
+        let response = signing::json_body(request, &body, self.config.signing.as_ref())
+            .await
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| connect_error(&e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Ollama error ({}): {}", status, body));
+        }
+        Ok(())
+    }
+
     /// Get the API base URL.
     fn api_base(&self) -> &str {
         if self.config.api_base.is_empty() {
@@ -48,6 +204,7 @@ impl OllamaProvider {
     }
 }
 
+#[async_trait]
 impl LlmProvider for OllamaProvider {
     fn provider(&self) -> Provider {
         Provider::Ollama
@@ -63,16 +220,76 @@ impl LlmProvider for OllamaProvider {
         true
     }
 
-    fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent> {
-        let (tx, rx) = mpsc::channel(32);
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        let url = format!("{}/api/chat", self.api_base().trim_end_matches('/'));
+        Some(RequestPreview::new(url, &self.build_request(messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let models = self
+            .installed_models()
+            .await
+            .map_err(|e| ProviderError::from_message(&e))?;
+        Ok(models
+            .into_iter()
+            .map(|m| ModelInfo::from_id(m.name))
+            .collect())
+    }
+
+    fn warm_up(&self) -> Option<oneshot::Receiver<Result<(), String>>> {
+        // Warm-up is a convenience; without a runtime (e.g. in sync tests) skip it
+        let handle = tokio::runtime::Handle::try_current().ok()?;
+        let (tx, rx) = oneshot::channel();
+
+        let client = self.client.clone();
+        let url = format!("{}/api/chat", self.api_base().trim_end_matches('/'));
+        let config = self.config.clone();
+        // An empty message list makes Ollama load the model without generating
+        let request = OllamaChatRequest {
+            stream: false,
+            ..self.build_request(Vec::new(), &[])
+        };
+
+        handle.spawn(async move {
+            let result = async {
+                let builder =
+                    signing::json_body(client.post(&url), &request, config.signing.as_ref())
+                        .await
+                        .map_err(|e| format!("{:#}", e))?;
+                let response = builder.send().await.map_err(|e| connect_error(&e))?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(format!("Ollama error ({}): {}", status, body));
+                }
+                Ok(())
+            }
+            .await;
+            let _ = tx.send(result);
+        });
+
+        Some(rx)
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.config.stream);
 
         let client = self.client.clone();
         let api_base = self.api_base().to_string();
-        let model = self.config.model.clone();
-        let temperature = self.config.temperature;
+        let config = self.config.clone();
+        let request = self.build_request(messages, tools);
 
         tokio::spawn(async move {
-            if let Err(e) = stream_ollama_chat(client, api_base, model, temperature, messages, tx.clone()).await {
+            if let Err(e) = stream_ollama_chat(client, api_base, &config, request, tx.clone()).await
+            {
                 let _ = tx.send(StreamEvent::Error(e)).await;
             }
         });
@@ -81,6 +298,98 @@ impl LlmProvider for OllamaProvider {
     }
 }
 
+/// A model loaded in Ollama's memory, as reported by `/api/ps`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RunningModel {
+    pub name: String,
+    /// Total size in memory, in bytes
+    #[serde(default)]
+    pub size: u64,
+    /// Portion of `size` held in GPU memory, in bytes
+    #[serde(default)]
+    pub size_vram: u64,
+    /// When Ollama will unload the model if it stays idle
+    #[serde(default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+}
+
+impl RunningModel {
+    /// Share of the model held in GPU memory, from 0 to 100.
+    pub fn gpu_percent(&self) -> u8 {
+        if self.size == 0 {
+            return 0;
+        }
+        (self.size_vram.min(self.size) * 100 / self.size) as u8
+    }
+}
+
+/// A model installed in Ollama, as reported by `/api/tags`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InstalledModel {
+    pub name: String,
+    /// Size on disk, in bytes
+    #[serde(default)]
+    pub size: u64,
+    /// When the model was last pulled or changed
+    #[serde(default)]
+    pub modified_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A progress update streamed by `/api/pull`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PullStatus {
+    /// Stage, such as `pulling manifest`, `pulling <digest>`,
+    /// `verifying sha256 digest` or `success`
+    #[serde(default)]
+    pub status: String,
+    /// Size of the layer being downloaded, in bytes
+    #[serde(default)]
+    pub total: Option<u64>,
+    /// Bytes of the layer downloaded so far
+    #[serde(default)]
+    pub completed: Option<u64>,
+}
+
+impl PullStatus {
+    /// Whether the pull has finished.
+    pub fn is_success(&self) -> bool {
+        self.status == "success"
+    }
+
+    /// Share of the current layer downloaded, from 0.0 to 1.0, when known.
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => {
+                Some((completed.min(total) as f64) / total as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One line of the `/api/pull` stream.
+#[derive(Debug, Deserialize)]
+struct PullLine {
+    #[serde(flatten)]
+    status: PullStatus,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Response body of `/api/tags`.
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<InstalledModel>,
+}
+
+/// Response body of `/api/ps`.
+#[derive(Debug, Deserialize)]
+struct PsResponse {
+    #[serde(default)]
+    models: Vec<RunningModel>,
+}
+
 /// Ollama chat request format.
 #[derive(Debug, Serialize)]
 struct OllamaChatRequest {
@@ -89,6 +398,12 @@ struct OllamaChatRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OllamaTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
 }
 
 /// Ollama message format.
@@ -96,6 +411,42 @@ struct OllamaChatRequest {
 struct OllamaMessage {
     role: String,
     content: String,
+    /// Base64-encoded images
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    /// Calls the model made in this reply
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+impl From<ChatMessage> for OllamaMessage {
+    fn from(message: ChatMessage) -> Self {
+        Self {
+            role: message.role,
+            content: message.content,
+            // Ollama takes the bytes alone and sniffs the format
+            images: message.images.into_iter().map(|image| image.data).collect(),
+            // Calls have no ids here; results answer them in order
+            tool_calls: message
+                .tool_calls
+                .into_iter()
+                .map(|call| OllamaToolCall {
+                    function: OllamaFunction {
+                        name: call.name,
+                        arguments: call.arguments,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Tool entry in a chat request.
+#[derive(Debug, Serialize)]
+struct OllamaTool {
+    #[serde(rename = "type")]
+    tool_type: &'static str,
+    function: ToolDefinition,
 }
 
 /// Ollama options for generation.
@@ -113,6 +464,14 @@ struct OllamaStreamChunk {
     #[serde(default)]
     done: bool,
     #[serde(default)]
+    done_reason: Option<String>,
+    /// Prompt tokens, in the final chunk
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    /// Reply tokens, in the final chunk
+    #[serde(default)]
+    eval_count: Option<u64>,
+    #[serde(default)]
     error: Option<String>,
 }
 
@@ -121,52 +480,43 @@ struct OllamaStreamChunk {
 struct OllamaResponseMessage {
     #[allow(dead_code)]
     role: String,
+    #[serde(default)]
     content: String,
+    #[serde(default)]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+/// Tool call in a message.
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunction,
+}
+
+/// The function a tool call invokes.
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
 }
 
 /// Perform streaming chat with Ollama.
 async fn stream_ollama_chat(
     client: Client,
     api_base: String,
-    model: String,
-    temperature: Option<f32>,
-    messages: Vec<ChatMessage>,
+    config: &LlmConfig,
+    request: OllamaChatRequest,
     tx: mpsc::Sender<StreamEvent>,
 ) -> Result<(), String> {
     let url = format!("{}/api/chat", api_base.trim_end_matches('/'));
 
-    // Convert messages to Ollama format
-    let ollama_messages: Vec<OllamaMessage> = messages
-        .into_iter()
-        .map(|m| OllamaMessage {
-            role: m.role,
-            content: m.content,
-        })
-        .collect();
-
-    let options = temperature.map(|t| OllamaOptions {
-        temperature: Some(t),
-    });
-
-    let request = OllamaChatRequest {
-        model,
-        messages: ollama_messages,
-        stream: true,
-        options,
-    };
-
-    let response = client
-        .post(&url)
-        .json(&request)
+    let builder = signing::json_body(client.post(&url), &request, config.signing.as_ref())
+        .await
+        .map_err(|e| format!("{:#}", e))?;
+    let response = builder
         .send()
         .await
-        .map_err(|e| {
-            if e.is_connect() {
-                "Failed to connect to Ollama. Is it running? Start with: ollama serve".to_string()
-            } else {
-                format!("Request failed: {}", e)
-            }
-        })?;
+        .map_err(|e| connect_error(&e))?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -174,15 +524,84 @@ async fn stream_ollama_chat(
         return Err(format!("Ollama error ({}): {}", status, body));
     }
 
-    // Process streaming response (newline-delimited JSON)
-    let mut stream = response.bytes_stream();
+    let stream = recording::tap(response.bytes_stream(), recording::recorder_for(config));
+    parse_stream(stream, &tx).await
+}
+
+/// Describe a failed request, with a hint when Ollama isn't running.
+fn connect_error(e: &reqwest::Error) -> String {
+    if e.is_connect() {
+        "Failed to connect to Ollama. Is it running? Start with: ollama serve".to_string()
+    } else {
+        format!("Request failed: {}", e)
+    }
+}
 
+/// Forward the statuses of an `/api/pull` NDJSON stream.
+///
+/// Fails on an error line or if the stream ends before reporting success.
+async fn parse_pull_stream<S, B, E>(
+    mut stream: S,
+    tx: &mpsc::Sender<Result<PullStatus, String>>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
     let mut buffer = String::new();
+    let mut decoder = Utf8Decoder::new();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
+        buffer.push_str(&decoder.push(chunk.as_ref()));
+
+        while let Some(newline_pos) = buffer.find('\n') {
+            let line: String = buffer.drain(..=newline_pos).collect();
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line: PullLine = serde_json::from_str(line)
+                .map_err(|e| format!("Invalid /api/pull response: {}", e))?;
+            if let Some(error) = line.error {
+                return Err(error);
+            }
+            let success = line.status.is_success();
+            if tx.send(Ok(line.status)).await.is_err() {
+                // Receiver dropped: the pull was cancelled
+                return Ok(());
+            }
+            if success {
+                return Ok(());
+            }
+        }
+    }
+
+    Err("Pull ended before the model was ready".to_string())
+}
+
+/// Parse an Ollama NDJSON byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(
+    mut stream: S,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+
+    let mut buffer = String::new();
+    let mut decoder = Utf8Decoder::new();
+    // Ollama does not number tool calls, so they get ids here
+    let mut calls = 0;
 
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
+        buffer.push_str(&decoder.push(chunk.as_ref()));
 
         // Process complete lines
         while let Some(newline_pos) = buffer.find('\n') {
@@ -197,17 +616,42 @@ async fn stream_ollama_chat(
                             return Ok(());
                         }
 
-                        // Send content if present
+                        // Send content and tool calls if present
                         if let Some(message) = chunk.message {
-                            if !message.content.is_empty() {
-                                if tx.send(StreamEvent::Token(message.content)).await.is_err() {
-                                    return Ok(()); // Receiver dropped
+                            if !message.content.is_empty()
+                                && tx.send(StreamEvent::Token(message.content)).await.is_err()
+                            {
+                                return Ok(()); // Receiver dropped
+                            }
+                            for call in message.tool_calls {
+                                calls += 1;
+                                let call = ToolCall {
+                                    id: format!("call_{}", calls),
+                                    name: call.function.name,
+                                    arguments: call.function.arguments,
+                                };
+                                if tx.send(StreamEvent::ToolCall(call)).await.is_err() {
+                                    return Ok(());
                                 }
                             }
                         }
 
                         // Check if done
                         if chunk.done {
+                            if let Some(reason) = chunk.done_reason {
+                                let _ = tx
+                                    .send(StreamEvent::Finish(FinishReason::parse(&reason)))
+                                    .await;
+                            }
+                            if let (Some(prompt), Some(reply)) =
+                                (chunk.prompt_eval_count, chunk.eval_count)
+                            {
+                                let usage = TokenUsage {
+                                    prompt_tokens: prompt,
+                                    completion_tokens: reply,
+                                };
+                                let _ = tx.send(StreamEvent::Usage(usage)).await;
+                            }
                             let _ = tx.send(StreamEvent::Done).await;
                             return Ok(());
                         }
@@ -231,6 +675,28 @@ async fn stream_ollama_chat(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::ImageData;
+
+    #[tokio::test]
+    async fn test_parse_stream_keeps_split_multibyte_characters() {
+        let line = "{\"message\":{\"role\":\"assistant\",\"content\":\"こんにちは 🦀\"},\"done\":true}\n";
+        let bytes = line.as_bytes();
+        // Split every 5 bytes, which lands inside several multi-byte characters
+        let chunks: Vec<Result<Vec<u8>, std::convert::Infallible>> =
+            bytes.chunks(5).map(|c| Ok(c.to_vec())).collect();
+
+        let (tx, mut rx) = mpsc::channel(10);
+        parse_stream(futures::stream::iter(chunks), &tx).await.unwrap();
+        drop(tx);
+
+        let mut text = String::new();
+        while let Some(event) = rx.recv().await {
+            if let StreamEvent::Token(t) = event {
+                text.push_str(&t);
+            }
+        }
+        assert_eq!(text, "こんにちは 🦀");
+    }
 
     #[test]
     fn test_ollama_provider_new() {
@@ -242,6 +708,7 @@ mod tests {
             temperature: Some(0.7),
             max_tokens: None,
             credential_type: crate::llm::CredentialType::ApiKey,
+            ..LlmConfig::default()
         };
         let provider = OllamaProvider::new(config);
         assert_eq!(provider.provider(), Provider::Ollama);
@@ -271,18 +738,22 @@ mod tests {
 
     #[test]
     fn test_ollama_api_base_default() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::Ollama;
-        config.api_base = String::new(); // Empty
+        let config = LlmConfig {
+            provider: Provider::Ollama,
+            api_base: String::new(), // Empty
+            ..LlmConfig::default()
+        };
         let provider = OllamaProvider::new(config);
         assert_eq!(provider.api_base(), DEFAULT_API_BASE);
     }
 
     #[test]
     fn test_ollama_api_base_custom() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::Ollama;
-        config.api_base = "http://192.168.1.100:11434".to_string();
+        let config = LlmConfig {
+            provider: Provider::Ollama,
+            api_base: "http://192.168.1.100:11434".to_string(),
+            ..LlmConfig::default()
+        };
         let provider = OllamaProvider::new(config);
         assert_eq!(provider.api_base(), "http://192.168.1.100:11434");
     }
@@ -292,10 +763,198 @@ mod tests {
         let msg = OllamaMessage {
             role: "user".to_string(),
             content: "Hello!".to_string(),
+            images: vec![],
+            tool_calls: vec![],
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"role\":\"user\""));
         assert!(json.contains("\"content\":\"Hello!\""));
+        assert!(!json.contains("images"));
+    }
+
+    #[test]
+    fn test_ollama_message_with_images() {
+        let msg = OllamaMessage::from(ChatMessage {
+            role: "user".to_string(),
+            content: "What is this?".to_string(),
+            images: vec![ImageData {
+                media_type: "image/png".to_string(),
+                data: "aGVsbG8=".to_string(),
+            }],
+            ..ChatMessage::default()
+        });
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"images\":[\"aGVsbG8=\"]"));
+    }
+
+    #[test]
+    fn test_build_request_with_tools_and_format() {
+        let provider = OllamaProvider::with_defaults().with_format(serde_json::json!("json"));
+        let tools = [ToolDefinition {
+            name: "get_weather".to_string(),
+            description: "Current weather for a city".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"]
+            }),
+        }];
+
+        let request = provider.build_request(
+            vec![
+                ChatMessage {
+                    role: "assistant".to_string(),
+                    tool_calls: vec![ToolCall {
+                        id: "call_1".to_string(),
+                        name: "get_weather".to_string(),
+                        arguments: serde_json::json!({"city": "Oslo"}),
+                    }],
+                    ..ChatMessage::default()
+                },
+                ChatMessage {
+                    role: "tool".to_string(),
+                    content: "4°C".to_string(),
+                    tool_call_id: Some("call_1".to_string()),
+                    ..ChatMessage::default()
+                },
+            ],
+            &tools,
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["format"], "json");
+        assert_eq!(json["tools"][0]["type"], "function");
+        assert_eq!(json["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(
+            json["tools"][0]["function"]["parameters"]["required"][0],
+            "city"
+        );
+        let call = &json["messages"][0]["tool_calls"][0]["function"];
+        assert_eq!(call["name"], "get_weather");
+        assert_eq!(call["arguments"]["city"], "Oslo");
+        assert_eq!(json["messages"][1]["role"], "tool");
+        assert_eq!(json["messages"][1]["content"], "4°C");
+    }
+
+    #[test]
+    fn test_ps_response_deserialization() {
+        let json = r#"{"models":[{"name":"qwen3:4b","model":"qwen3:4b","size":5137025024,
+            "digest":"abc","details":{"family":"qwen3"},
+            "expires_at":"2024-06-04T14:38:31.83753-07:00","size_vram":2568512512}]}"#;
+        let ps: PsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(ps.models.len(), 1);
+        let model = &ps.models[0];
+        assert_eq!(model.name, "qwen3:4b");
+        assert_eq!(model.gpu_percent(), 50);
+        assert!(model.expires_at.is_some());
+
+        let empty: PsResponse = serde_json::from_str("{}").unwrap();
+        assert!(empty.models.is_empty());
+    }
+
+    #[test]
+    fn test_tags_response_deserialization() {
+        let json = r#"{"models":[{"name":"llama3.2:latest","model":"llama3.2:latest",
+            "modified_at":"2024-10-30T13:29:22.253717863-07:00","size":2019393189,
+            "digest":"a80c4f17","details":{"family":"llama","parameter_size":"3.2B"}}]}"#;
+        let tags: TagsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(tags.models.len(), 1);
+        let model = &tags.models[0];
+        assert_eq!(model.name, "llama3.2:latest");
+        assert_eq!(model.size, 2019393189);
+        assert!(model.modified_at.is_some());
+
+        let empty: TagsResponse = serde_json::from_str("{}").unwrap();
+        assert!(empty.models.is_empty());
+    }
+
+    #[test]
+    fn test_warm_up_needs_runtime() {
+        assert!(OllamaProvider::with_defaults().warm_up().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_request_has_no_messages() {
+        let provider = OllamaProvider::new(LlmConfig {
+            provider: Provider::Ollama,
+            keep_alive: Some("10m".to_string()),
+            ..LlmConfig::default()
+        });
+        let request = OllamaChatRequest {
+            stream: false,
+            ..provider.build_request(Vec::new(), &[])
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["messages"], serde_json::json!([]));
+        assert_eq!(json["keep_alive"], "10m");
+        assert_eq!(json["stream"], false);
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_tool_calls() {
+        let body = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\",",
+            "\"tool_calls\":[{\"function\":{\"name\":\"get_weather\",",
+            "\"arguments\":{\"city\":\"Oslo\"}}}]},\"done\":false}\n",
+            "{\"done\":true}\n"
+        );
+        let chunks = vec![Ok::<_, std::convert::Infallible>(body.as_bytes().to_vec())];
+
+        let (tx, mut rx) = mpsc::channel(10);
+        parse_stream(futures::stream::iter(chunks), &tx).await.unwrap();
+        drop(tx);
+
+        match rx.recv().await {
+            Some(StreamEvent::ToolCall(call)) => {
+                assert_eq!(call.id, "call_1");
+                assert_eq!(call.name, "get_weather");
+                assert_eq!(call.arguments["city"], "Oslo");
+            }
+            other => panic!("expected tool call, got {:?}", other),
+        }
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Done)));
+    }
+
+    #[tokio::test]
+    async fn test_parse_pull_stream() {
+        let body = concat!(
+            "{\"status\":\"pulling manifest\"}\n",
+            "{\"status\":\"pulling 6a0746a1ec1a\",\"digest\":\"sha256:6a07\",",
+            "\"total\":2000,\"completed\":500}\n",
+            "{\"status\":\"verifying sha256 digest\"}\n",
+            "{\"status\":\"success\"}\n"
+        );
+        let chunks = vec![Ok::<_, std::convert::Infallible>(body.as_bytes().to_vec())];
+
+        let (tx, mut rx) = mpsc::channel(10);
+        parse_pull_stream(futures::stream::iter(chunks), &tx).await.unwrap();
+        drop(tx);
+
+        let mut statuses = Vec::new();
+        while let Some(status) = rx.recv().await {
+            statuses.push(status.unwrap());
+        }
+        assert_eq!(statuses.len(), 4);
+        assert_eq!(statuses[1].fraction(), Some(0.25));
+        assert_eq!(statuses[2].fraction(), None);
+        assert!(statuses[3].is_success());
+    }
+
+    #[tokio::test]
+    async fn test_parse_pull_stream_errors() {
+        let (tx, _rx) = mpsc::channel(10);
+        let error = vec![Ok::<_, std::convert::Infallible>(
+            b"{\"error\":\"pull model manifest: file does not exist\"}\n".to_vec(),
+        )];
+        assert_eq!(
+            parse_pull_stream(futures::stream::iter(error), &tx).await,
+            Err("pull model manifest: file does not exist".to_string())
+        );
+
+        // A stream that stops early never reports success
+        let cut = vec![Ok::<_, std::convert::Infallible>(
+            b"{\"status\":\"pulling manifest\"}\n".to_vec(),
+        )];
+        assert!(parse_pull_stream(futures::stream::iter(cut), &tx).await.is_err());
     }
 
     #[test]
@@ -305,11 +964,16 @@ mod tests {
             messages: vec![OllamaMessage {
                 role: "user".to_string(),
                 content: "Hello".to_string(),
+                images: vec![],
+                tool_calls: vec![],
             }],
             stream: true,
             options: Some(OllamaOptions {
                 temperature: Some(0.7),
             }),
+            tools: vec![],
+            format: None,
+            keep_alive: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("\"model\":\"qwen3:4b\""));
@@ -324,9 +988,15 @@ mod tests {
             messages: vec![],
             stream: true,
             options: None,
+            tools: vec![],
+            format: None,
+            keep_alive: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(!json.contains("options"));
+        assert!(!json.contains("tools"));
+        assert!(!json.contains("format"));
+        assert!(!json.contains("keep_alive"));
     }
 
     #[test]
@@ -344,6 +1014,10 @@ mod tests {
         let chunk: OllamaStreamChunk = serde_json::from_str(json).unwrap();
         assert!(chunk.done);
         assert!(chunk.message.is_none());
+
+        let json = r#"{"done":true,"done_reason":"stop","prompt_eval_count":26,"eval_count":290}"#;
+        let chunk: OllamaStreamChunk = serde_json::from_str(json).unwrap();
+        assert_eq!((chunk.prompt_eval_count, chunk.eval_count), (Some(26), Some(290)));
     }
 
     #[test]
@@ -0,0 +1,907 @@
+//! OpenAI provider using the chat completions API.
+//!
+//! Streams replies from `api.openai.com` over server-sent events. Requests
+//! are scoped to an organization and project when `[llm.scopes.openai]` is
+//! set, and `/think` maps to `reasoning_effort`. Providers with an
+//! OpenAI-compatible API (Groq, local servers such as LM Studio or vLLM) use
+//! the same implementation with their own base URL.
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use super::{
+    backpressure, recording, signing, sse, ChatMessage, FinishReason, ImageData, LlmConfig,
+    LlmProvider, ModelInfo, Provider, ProviderError, ProviderResult, RateLimits, RequestPreview,
+    StreamEvent, TokenUsage, ToolCall, ToolDefinition,
+};
+use crate::config::{ProviderScope, SigningConfig};
+
+/// OpenAI provider, or another provider speaking the same API.
+pub struct OpenAIProvider {
+    client: Client,
+    config: Arc<LlmConfig>,
+    provider: Provider,
+}
+
+impl OpenAIProvider {
+    /// Create a new OpenAI provider with the given configuration.
+    pub fn new(config: LlmConfig) -> Self {
+        Self::compatible(Provider::OpenAI, config)
+    }
+
+    /// Create a provider for `provider`'s OpenAI-compatible API.
+    pub fn compatible(provider: Provider, config: LlmConfig) -> Self {
+        Self {
+            client: super::http_client(&config.timeouts),
+            config: Arc::new(config),
+            provider,
+        }
+    }
+
+    /// Get the API base URL.
+    fn api_base(&self) -> &str {
+        if self.config.api_base.is_empty() {
+            self.provider.default_api_base()
+        } else {
+            &self.config.api_base
+        }
+    }
+
+    /// Chat completions endpoint.
+    fn chat_url(&self) -> String {
+        format!("{}/chat/completions", self.api_base().trim_end_matches('/'))
+    }
+
+    /// Build the request body for `messages`, offering `tools`.
+    fn build_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> OpenAIRequest {
+        OpenAIRequest {
+            model: self.config.model.clone(),
+            messages: messages
+                .into_iter()
+                .map(|m| OpenAIMessage {
+                    content: MessageContent::new(m.content, &m.images),
+                    role: m.role,
+                    tool_calls: WireToolCall::all(m.tool_calls),
+                    tool_call_id: m.tool_call_id,
+                })
+                .collect(),
+            tools: FunctionTool::all(tools),
+            stream: true,
+            temperature: self.config.temperature,
+            max_completion_tokens: self.config.max_tokens,
+            reasoning_effort: self.config.reasoning.map(|effort| effort.effort()),
+            // Other compatible servers may reject options they don't know
+            stream_options: matches!(self.provider, Provider::OpenAI | Provider::Groq)
+                .then_some(StreamOptions { include_usage: true }),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAIProvider {
+    fn provider(&self) -> Provider {
+        self.provider
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.provider.requires_api_key() || !self.config.api_key.is_empty()
+    }
+
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        Some(RequestPreview::new(self.chat_url(), &self.build_request(messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let scope = self.config.scope().filter(|_| self.provider == Provider::OpenAI);
+        let signing = self.config.signing.as_ref();
+        let ids = list_models(self.api_base(), &self.config.api_key, scope, signing)
+            .await
+            .map_err(ProviderError::Other)?;
+        Ok(ids.into_iter().map(ModelInfo::from_id).collect())
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.config.stream);
+
+        let client = self.client.clone();
+        let url = self.chat_url();
+        let api_key = self.config.api_key.clone();
+        let name = self.provider.display_name();
+        let env_var = self.provider.env_var_name();
+        let requires_key = self.provider.requires_api_key();
+        // Organization and project headers mean nothing to other providers
+        let scope_headers = self
+            .config
+            .scope()
+            .filter(|_| self.provider == Provider::OpenAI)
+            .map(ProviderScope::openai_headers)
+            .unwrap_or_default();
+        let signing = self.config.signing.clone();
+        let recorder = recording::recorder_for(&self.config);
+        let request = self.build_request(messages, tools);
+
+        tokio::spawn(async move {
+            if requires_key && api_key.is_empty() {
+                let _ = tx
+                    .send(StreamEvent::Error(format!(
+                        "{} API key not configured. Set {} environment variable.",
+                        name, env_var
+                    )))
+                    .await;
+                return;
+            }
+
+            let mut builder = client.post(&url);
+            if !api_key.is_empty() {
+                builder = builder.header("Authorization", format!("Bearer {}", api_key));
+            }
+            for (name, value) in scope_headers {
+                builder = builder.header(name, value);
+            }
+            let response = match signing::json_body(builder, &request, signing.as_ref()).await {
+                Ok(builder) => builder.send().await.map_err(|e| format!("Request failed: {}", e)),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            if let Ok(response) = &response {
+                if let Some(limits) =
+                    RateLimits::from_headers(response.headers(), chrono::Utc::now())
+                {
+                    let _ = tx.send(StreamEvent::RateLimits(limits)).await;
+                }
+            }
+            let result = match response {
+                Ok(response) if response.status().is_success() => {
+                    let stream = recording::tap(response.bytes_stream(), recorder);
+                    parse_stream(stream, &tx).await
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    Err(match serde_json::from_str::<OpenAIErrorResponse>(&body) {
+                        Ok(error) => format!("{} error ({}): {}", name, status, error.error.message),
+                        Err(_) => format!("{} error ({}): {}", name, status, body),
+                    })
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                let _ = tx.send(StreamEvent::Error(e)).await;
+            }
+        });
+
+        rx
+    }
+}
+
+/// Normalize a server URL typed by the user into an API base ending in `/v1`.
+///
+/// Accepts `localhost:1234`, `http://host:8000/` or `http://host:8080/v1`.
+pub fn normalize_base_url(input: &str) -> String {
+    let url = input.trim().trim_end_matches('/');
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    if url.ends_with("/v1") {
+        url
+    } else {
+        format!("{}/v1", url)
+    }
+}
+
+/// List the ids of the models an OpenAI-compatible server offers (`GET /models`).
+///
+/// `scope` adds OpenAI's organization/project headers; other servers pass `None`.
+pub async fn list_models(
+    api_base: &str,
+    api_key: &str,
+    scope: Option<&ProviderScope>,
+    signing: Option<&SigningConfig>,
+) -> Result<Vec<String>, String> {
+    let url = format!("{}/models", api_base.trim_end_matches('/'));
+    let mut request = Client::new().get(&url);
+    if !api_key.is_empty() {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+    for (name, value) in scope.map(ProviderScope::openai_headers).unwrap_or_default() {
+        request = request.header(name, value);
+    }
+    let response = signing::sign(request, signing)
+        .await
+        .map_err(|e| e.to_string())?
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {}: {}", api_base, e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Server error ({}): {}", status, body));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read /models response: {}", e))?;
+    parse_models(&body)
+}
+
+/// Model ids from a `/models` response body, sorted.
+fn parse_models(body: &str) -> Result<Vec<String>, String> {
+    let list: ModelList =
+        serde_json::from_str(body).map_err(|e| format!("Invalid /models response: {}", e))?;
+    let mut ids: Vec<String> = list.data.into_iter().map(|m| m.id).collect();
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+/// One entry of a `/models` response.
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// OpenAI chat completions request.
+#[derive(Debug, Serialize)]
+struct OpenAIRequest {
+    model: String,
+    messages: Vec<OpenAIMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    /// Replaces the deprecated `max_tokens`, which reasoning models reject
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
+    /// Asks for a final chunk with the token usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+/// Streaming options, for the APIs known to accept them.
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+/// OpenAI message format.
+#[derive(Debug, Serialize)]
+struct OpenAIMessage {
+    role: String,
+    content: MessageContent,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// Message content in the chat completions format: a plain string, or text
+/// and image parts when images are attached. Shared by the providers that
+/// speak this API.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// `text` alone, or with `images` as parts before it.
+    pub(crate) fn new(text: String, images: &[ImageData]) -> Self {
+        if images.is_empty() {
+            return MessageContent::Text(text);
+        }
+        let mut parts: Vec<ContentPart> = images
+            .iter()
+            .map(|image| ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: image.data_url(),
+                },
+            })
+            .collect();
+        parts.push(ContentPart::Text { text });
+        MessageContent::Parts(parts)
+    }
+}
+
+/// One part of a multimodal message.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+/// An image part's source, here always a `data:` URL.
+#[derive(Debug, Serialize)]
+pub(crate) struct ImageUrl {
+    url: String,
+}
+
+/// A function offered to the model. Shared by the providers that speak
+/// this API, like the tool call types below.
+#[derive(Debug, Serialize)]
+pub(crate) struct FunctionTool {
+    #[serde(rename = "type")]
+    tool_type: &'static str,
+    function: ToolDefinition,
+}
+
+impl FunctionTool {
+    /// `tools` in the chat completions format.
+    pub(crate) fn all(tools: &[ToolDefinition]) -> Vec<Self> {
+        tools
+            .iter()
+            .map(|tool| FunctionTool {
+                tool_type: "function",
+                function: tool.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A call the model made, sent back with the reply that made it.
+#[derive(Debug, Serialize)]
+pub(crate) struct WireToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    call_type: &'static str,
+    function: WireFunction,
+}
+
+/// The function a call invoked; the API takes the arguments JSON-encoded.
+#[derive(Debug, Serialize)]
+pub(crate) struct WireFunction {
+    name: String,
+    arguments: String,
+}
+
+impl WireToolCall {
+    /// `calls` in the chat completions format.
+    pub(crate) fn all(calls: Vec<ToolCall>) -> Vec<Self> {
+        calls
+            .into_iter()
+            .map(|call| WireToolCall {
+                id: call.id,
+                call_type: "function",
+                function: WireFunction {
+                    name: call.name,
+                    arguments: call.arguments.to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// A streamed piece of a tool call; the first names it, the rest extend
+/// its arguments.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: FunctionDelta,
+}
+
+/// Name and arguments text in a [`ToolCallDelta`].
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+/// Tool calls put together from their streamed pieces.
+#[derive(Debug, Default)]
+pub(crate) struct ToolCallBuffer {
+    calls: Vec<(String, String, String)>,
+}
+
+impl ToolCallBuffer {
+    /// Add a streamed piece.
+    pub(crate) fn push(&mut self, delta: ToolCallDelta) {
+        if self.calls.len() <= delta.index {
+            self.calls.resize_with(delta.index + 1, Default::default);
+        }
+        let (id, name, arguments) = &mut self.calls[delta.index];
+        if let Some(part) = delta.id {
+            id.push_str(&part);
+        }
+        if let Some(part) = delta.function.name {
+            name.push_str(&part);
+        }
+        if let Some(part) = delta.function.arguments {
+            arguments.push_str(&part);
+        }
+    }
+
+    /// The calls streamed so far, leaving the buffer empty.
+    pub(crate) fn take(&mut self) -> Vec<ToolCall> {
+        std::mem::take(&mut self.calls)
+            .into_iter()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, arguments)| ToolCall {
+                id,
+                name,
+                // Arguments that are not valid JSON reach the tool as a
+                // string, which it rejects with a message the model can read
+                arguments: if arguments.trim().is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&arguments)
+                        .unwrap_or(serde_json::Value::String(arguments))
+                },
+            })
+            .collect()
+    }
+
+    /// Send the calls streamed so far. Returns false if the receiver is gone.
+    pub(crate) async fn send(&mut self, tx: &mpsc::Sender<StreamEvent>) -> bool {
+        for call in self.take() {
+            if tx.send(StreamEvent::ToolCall(call)).await.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Streaming response chunk.
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIChoice>,
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+    #[serde(default)]
+    error: Option<OpenAIError>,
+}
+
+/// Choice in a streaming chunk.
+#[derive(Debug, Deserialize)]
+struct OpenAIChoice {
+    #[serde(default)]
+    delta: OpenAIDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+/// Incremental message content.
+#[derive(Debug, Default, Deserialize)]
+struct OpenAIDelta {
+    #[serde(default)]
+    content: Option<String>,
+    /// Reasoning streamed by compatible servers (vLLM, llama.cpp, Groq)
+    #[serde(default, alias = "reasoning")]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// OpenAI error details.
+#[derive(Debug, Deserialize)]
+struct OpenAIError {
+    message: String,
+}
+
+/// OpenAI error response wrapper.
+#[derive(Debug, Deserialize)]
+struct OpenAIErrorResponse {
+    error: OpenAIError,
+}
+
+/// Parse an OpenAI SSE byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(
+    stream: S,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut events = std::pin::pin!(sse::events(stream));
+    let mut tool_calls = ToolCallBuffer::default();
+
+    while let Some(event) = events.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        let data = event.data.as_str();
+        if data == "[DONE]" {
+            if tool_calls.send(tx).await {
+                let _ = tx.send(StreamEvent::Done).await;
+            }
+            return Ok(());
+        }
+
+        let chunk = match serde_json::from_str::<OpenAIStreamChunk>(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                eprintln!("OpenAI parse warning: {} for data: {}", e, data);
+                continue;
+            }
+        };
+        if let Some(error) = chunk.error {
+            return Err(error.message);
+        }
+        for choice in chunk.choices {
+            if let Some(thinking) = choice.delta.reasoning_content.filter(|c| !c.is_empty()) {
+                if tx.send(StreamEvent::Thinking(thinking)).await.is_err() {
+                    return Ok(()); // Receiver dropped
+                }
+            }
+            if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                if tx.send(StreamEvent::Token(content)).await.is_err() {
+                    return Ok(()); // Receiver dropped
+                }
+            }
+            for delta in choice.delta.tool_calls {
+                tool_calls.push(delta);
+            }
+            if let Some(reason) = choice.finish_reason {
+                if !tool_calls.send(tx).await {
+                    return Ok(()); // Receiver dropped
+                }
+                let _ = tx.send(StreamEvent::Finish(FinishReason::parse(&reason))).await;
+            }
+        }
+        if let Some(usage) = chunk.usage {
+            let _ = tx.send(StreamEvent::Usage(usage)).await;
+        }
+    }
+
+    if tool_calls.send(tx).await {
+        let _ = tx.send(StreamEvent::Done).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ReasoningEffort;
+
+    fn config() -> LlmConfig {
+        LlmConfig {
+            provider: Provider::OpenAI,
+            api_base: Provider::OpenAI.default_api_base().to_string(),
+            api_key: "sk-test".to_string(),
+            model: "gpt-4.1".to_string(),
+            ..LlmConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_openai_provider_basics() {
+        let provider = OpenAIProvider::new(config());
+        assert_eq!(provider.provider(), Provider::OpenAI);
+        assert_eq!(provider.model(), "gpt-4.1");
+        assert_eq!(provider.display_name(), "OpenAI");
+        assert!(provider.is_configured());
+        assert_eq!(provider.api_base(), "https://api.openai.com/v1");
+
+        let unconfigured = OpenAIProvider::new(LlmConfig {
+            api_key: String::new(),
+            api_base: String::new(),
+            ..config()
+        });
+        assert!(!unconfigured.is_configured());
+        assert_eq!(unconfigured.api_base(), "https://api.openai.com/v1");
+    }
+
+    #[test]
+    fn test_compatible_provider() {
+        let groq = OpenAIProvider::compatible(
+            Provider::Groq,
+            LlmConfig {
+                api_base: String::new(),
+                model: "llama-3.3-70b-versatile".to_string(),
+                ..config()
+            },
+        );
+        assert_eq!(groq.provider(), Provider::Groq);
+        assert_eq!(groq.display_name(), "Groq");
+        assert_eq!(groq.api_base(), "https://api.groq.com/openai/v1");
+        let json = serde_json::to_value(groq.build_request(Vec::new(), &[])).unwrap();
+        assert_eq!(json["model"], "llama-3.3-70b-versatile");
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let provider = OpenAIProvider::new(LlmConfig {
+            reasoning: Some(ReasoningEffort::Medium),
+            ..config()
+        });
+        let request = provider.build_request(vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            ..ChatMessage::default()
+        }], &[]);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "gpt-4.1");
+        assert_eq!(json["stream"], true);
+        assert_eq!(json["messages"][0]["content"], "Hello");
+        assert_eq!(json["max_completion_tokens"], 4096);
+        assert_eq!(json["reasoning_effort"], "medium");
+        assert!(json.get("max_tokens").is_none());
+        assert_eq!(json["stream_options"]["include_usage"], true);
+
+        let plain = OpenAIProvider::new(LlmConfig {
+            temperature: None,
+            max_tokens: None,
+            ..config()
+        })
+        .build_request(Vec::new(), &[]);
+        let json = serde_json::to_string(&plain).unwrap();
+        assert!(!json.contains("temperature"));
+        assert!(!json.contains("max_completion_tokens"));
+        assert!(!json.contains("reasoning_effort"));
+
+        // Local servers are not sent options they may not know
+        let local = OpenAIProvider::compatible(Provider::LocalServer, config())
+            .build_request(Vec::new(), &[]);
+        assert!(!serde_json::to_string(&local).unwrap().contains("stream_options"));
+    }
+
+    #[test]
+    fn test_images_become_content_parts() {
+        let request = OpenAIProvider::new(config()).build_request(vec![ChatMessage {
+            role: "user".to_string(),
+            content: "What is this?".to_string(),
+            images: vec![ImageData {
+                media_type: "image/png".to_string(),
+                data: "aGVsbG8=".to_string(),
+            }],
+            ..ChatMessage::default()
+        }], &[]);
+        let json = serde_json::to_value(&request).unwrap();
+        let parts = &json["messages"][0]["content"];
+        assert_eq!(parts[0]["type"], "image_url");
+        assert_eq!(parts[0]["image_url"]["url"], "data:image/png;base64,aGVsbG8=");
+        assert_eq!(parts[1]["type"], "text");
+        assert_eq!(parts[1]["text"], "What is this?");
+    }
+
+    #[test]
+    fn test_tools_and_their_results_in_requests() {
+        let tools = [ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: serde_json::json!({"type": "object"}),
+        }];
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({"path": "notes.txt"}),
+        };
+        let request = OpenAIProvider::new(config()).build_request(
+            vec![
+                ChatMessage {
+                    role: "assistant".to_string(),
+                    tool_calls: vec![call],
+                    ..ChatMessage::default()
+                },
+                ChatMessage {
+                    role: "tool".to_string(),
+                    content: "hello".to_string(),
+                    tool_call_id: Some("call_1".to_string()),
+                    ..ChatMessage::default()
+                },
+            ],
+            &tools,
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["tools"][0]["type"], "function");
+        assert_eq!(json["tools"][0]["function"]["name"], "read_file");
+        let sent = &json["messages"][0]["tool_calls"][0];
+        assert_eq!(sent["id"], "call_1");
+        assert_eq!(sent["function"]["arguments"], r#"{"path":"notes.txt"}"#);
+        assert_eq!(json["messages"][1]["tool_call_id"], "call_1");
+        assert_eq!(json["messages"][1]["content"], "hello");
+
+        // Without tools the request says nothing about them
+        let plain = OpenAIProvider::new(config()).build_request(Vec::new(), &[]);
+        assert!(!serde_json::to_string(&plain).unwrap().contains("tool"));
+    }
+
+    #[test]
+    fn test_local_server_without_key() {
+        let local = OpenAIProvider::compatible(
+            Provider::LocalServer,
+            LlmConfig {
+                api_key: String::new(),
+                api_base: "http://localhost:8000/v1".to_string(),
+                ..config()
+            },
+        );
+        assert!(local.is_configured());
+        assert_eq!(local.api_base(), "http://localhost:8000/v1");
+    }
+
+    #[test]
+    fn test_normalize_base_url() {
+        assert_eq!(normalize_base_url("localhost:1234"), "http://localhost:1234/v1");
+        assert_eq!(normalize_base_url(" http://gpu:8000/ "), "http://gpu:8000/v1");
+        assert_eq!(normalize_base_url("https://llm.lan/v1/"), "https://llm.lan/v1");
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let body = r#"{"object":"list","data":[
+            {"id":"qwen2.5-7b-instruct","object":"model","owned_by":"organization_owner"},
+            {"id":"llama-3.2-3b","object":"model"},
+            {"id":"qwen2.5-7b-instruct","object":"model"}
+        ]}"#;
+        assert_eq!(
+            parse_models(body).unwrap(),
+            vec!["llama-3.2-3b".to_string(), "qwen2.5-7b-instruct".to_string()]
+        );
+        assert!(parse_models("<html>").is_err());
+    }
+
+    #[test]
+    fn test_scope_headers() {
+        assert!(ProviderScope::default().openai_headers().is_empty());
+        let scope = ProviderScope {
+            organization: Some("org-123".to_string()),
+            project: Some("proj_abc".to_string()),
+        };
+        assert_eq!(
+            scope.openai_headers(),
+            vec![
+                ("OpenAI-Organization", "org-123".to_string()),
+                ("OpenAI-Project", "proj_abc".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::convert::Infallible>(
+            body.as_bytes().to_vec(),
+        )]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        let mut text = String::new();
+        let mut finish = None;
+        let mut usage = None;
+        let mut done = false;
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Token(token) => text.push_str(&token),
+                StreamEvent::Finish(reason) => finish = Some(reason),
+                StreamEvent::Usage(reported) => usage = Some(reported),
+                StreamEvent::Done => done = true,
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(text, "Hello");
+        assert_eq!(finish, Some(FinishReason::Length));
+        assert_eq!(
+            usage,
+            Some(TokenUsage {
+                prompt_tokens: 9,
+                completion_tokens: 2
+            })
+        );
+        assert!(done);
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_separates_reasoning() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"reasoning\":\"Short \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"answer.\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Yes\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::convert::Infallible>(
+            body.as_bytes().to_vec(),
+        )]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        let mut thinking = String::new();
+        let mut text = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Thinking(chunk) => thinking.push_str(&chunk),
+                StreamEvent::Token(token) => text.push_str(&token),
+                StreamEvent::Done => {}
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(thinking, "Short answer.");
+        assert_eq!(text, "Yes");
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_assembles_tool_calls() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"read_file\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"path\\\": \"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"notes.txt\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let stream = futures::stream::iter(vec![Ok::<_, std::convert::Infallible>(
+            body.as_bytes().to_vec(),
+        )]);
+        let (tx, mut rx) = mpsc::channel(16);
+        parse_stream(stream, &tx).await.unwrap();
+        drop(tx);
+
+        match rx.recv().await {
+            Some(StreamEvent::ToolCall(call)) => {
+                assert_eq!(call.id, "call_1");
+                assert_eq!(call.name, "read_file");
+                assert_eq!(call.arguments, serde_json::json!({"path": "notes.txt"}));
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+        assert!(matches!(
+            rx.recv().await,
+            Some(StreamEvent::Finish(FinishReason::ToolCalls))
+        ));
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Done)));
+    }
+
+    #[tokio::test]
+    async fn test_parse_stream_error() {
+        let body = "data: {\"error\":{\"message\":\"Rate limit reached\"}}\n\n";
+        let stream = futures::stream::iter(vec![Ok::<_, std::convert::Infallible>(
+            body.as_bytes().to_vec(),
+        )]);
+        let (tx, _rx) = mpsc::channel(16);
+        assert_eq!(
+            parse_stream(stream, &tx).await.unwrap_err(),
+            "Rate limit reached"
+        );
+    }
+}
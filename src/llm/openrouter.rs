@@ -3,13 +3,22 @@
 //! OpenRouter provides access to multiple LLM models through a single API.
 //! Uses the OpenAI-compatible API format.
 
-use futures::StreamExt;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
-use super::{ChatMessage, LlmConfig, LlmProvider, Provider, StreamEvent};
+use super::openai::{
+    self, FunctionTool, MessageContent, ToolCallBuffer, ToolCallDelta, WireToolCall,
+};
+use super::{
+    backpressure, recording, signing, sse, ChatMessage, FinishReason, LlmConfig, LlmProvider,
+    ModelInfo, Provider, ProviderError, ProviderResult, RequestPreview, StreamEvent, TokenUsage,
+    ToolDefinition,
+};
+use crate::config::SigningConfig;
 
 /// Default OpenRouter API base URL.
 const DEFAULT_API_BASE: &str = "https://openrouter.ai/api/v1";
@@ -24,7 +33,7 @@ impl OpenRouterProvider {
     /// Create a new OpenRouter provider with the given configuration.
     pub fn new(config: LlmConfig) -> Self {
         Self {
-            client: Client::new(),
+            client: super::http_client(&config.timeouts),
             config: Arc::new(config),
         }
     }
@@ -32,10 +41,12 @@ impl OpenRouterProvider {
     /// Create a new OpenRouter provider with default settings.
     #[allow(dead_code)]
     pub fn with_defaults() -> Self {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_base = DEFAULT_API_BASE.to_string();
-        config.model = Provider::OpenRouter.default_model().to_string();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_base: DEFAULT_API_BASE.to_string(),
+            model: Provider::OpenRouter.default_model().to_string(),
+            ..LlmConfig::default()
+        };
         Self::new(config)
     }
 
@@ -47,8 +58,41 @@ impl OpenRouterProvider {
             &self.config.api_base
         }
     }
+
+    /// Chat completions endpoint.
+    fn chat_url(&self) -> String {
+        format!("{}/chat/completions", self.api_base().trim_end_matches('/'))
+    }
+
+    /// Build the request body for `messages`, offering `tools`.
+    fn build_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> OpenRouterRequest {
+        OpenRouterRequest {
+            model: self.config.model.clone(),
+            messages: messages
+                .into_iter()
+                .map(|m| OpenRouterMessage {
+                    content: MessageContent::new(m.content, &m.images),
+                    role: m.role,
+                    tool_calls: WireToolCall::all(m.tool_calls),
+                    tool_call_id: m.tool_call_id,
+                })
+                .collect(),
+            tools: FunctionTool::all(tools),
+            stream: true,
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+            reasoning: self.config.reasoning.map(|effort| OpenRouterReasoning {
+                effort: effort.effort(),
+            }),
+        }
+    }
 }
 
+#[async_trait]
 impl LlmProvider for OpenRouterProvider {
     fn provider(&self) -> Provider {
         Provider::OpenRouter
@@ -62,15 +106,35 @@ impl LlmProvider for OpenRouterProvider {
         !self.config.api_key.is_empty()
     }
 
-    fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent> {
-        let (tx, rx) = mpsc::channel(32);
+    fn preview_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        Some(RequestPreview::new(self.chat_url(), &self.build_request(messages, tools)))
+    }
+
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let signing = self.config.signing.as_ref();
+        let ids = openai::list_models(self.api_base(), &self.config.api_key, None, signing)
+            .await
+            .map_err(ProviderError::Other)?;
+        Ok(ids.into_iter().map(ModelInfo::from_id).collect())
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = backpressure::channel(self.config.stream);
 
         let client = self.client.clone();
-        let api_base = self.api_base().to_string();
+        let url = self.chat_url();
         let api_key = self.config.api_key.clone();
-        let model = self.config.model.clone();
-        let temperature = self.config.temperature;
-        let max_tokens = self.config.max_tokens;
+        let request = self.build_request(messages, tools);
+        let signing = self.config.signing.clone();
+        let recorder = recording::recorder_for(&self.config);
 
         tokio::spawn(async move {
             if api_key.is_empty() {
@@ -82,17 +146,9 @@ impl LlmProvider for OpenRouterProvider {
                 return;
             }
 
-            if let Err(e) = stream_openrouter_chat(
-                client,
-                api_base,
-                api_key,
-                model,
-                temperature,
-                max_tokens,
-                messages,
-                tx.clone(),
-            )
-            .await
+            if let Err(e) =
+                stream_openrouter_chat(client, url, api_key, request, signing, recorder, tx.clone())
+                    .await
             {
                 let _ = tx.send(StreamEvent::Error(e)).await;
             }
@@ -107,18 +163,32 @@ impl LlmProvider for OpenRouterProvider {
 struct OpenRouterRequest {
     model: String,
     messages: Vec<OpenRouterMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<OpenRouterReasoning>,
+}
+
+/// OpenRouter's unified reasoning settings.
+#[derive(Debug, Serialize)]
+struct OpenRouterReasoning {
+    effort: &'static str,
 }
 
 /// OpenRouter message format.
 #[derive(Debug, Serialize)]
 struct OpenRouterMessage {
     role: String,
-    content: String,
+    content: MessageContent,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
 }
 
 /// OpenRouter streaming response chunk (OpenAI SSE format).
@@ -126,6 +196,9 @@ struct OpenRouterMessage {
 struct OpenRouterStreamChunk {
     #[serde(default)]
     choices: Vec<OpenRouterChoice>,
+    /// Sent with the last chunk
+    #[serde(default)]
+    usage: Option<TokenUsage>,
     #[serde(default)]
     error: Option<OpenRouterError>,
 }
@@ -144,6 +217,11 @@ struct OpenRouterChoice {
 struct OpenRouterDelta {
     #[serde(default)]
     content: Option<String>,
+    /// Reasoning tokens of thinking models
+    #[serde(default)]
+    reasoning: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
 }
 
 /// OpenRouter error response.
@@ -158,39 +236,21 @@ struct OpenRouterError {
 /// Perform streaming chat with OpenRouter.
 async fn stream_openrouter_chat(
     client: Client,
-    api_base: String,
+    url: String,
     api_key: String,
-    model: String,
-    temperature: Option<f32>,
-    max_tokens: Option<u32>,
-    messages: Vec<ChatMessage>,
+    request: OpenRouterRequest,
+    signing: Option<SigningConfig>,
+    recorder: Option<recording::StreamRecorder>,
     tx: mpsc::Sender<StreamEvent>,
 ) -> Result<(), String> {
-    let url = format!("{}/chat/completions", api_base.trim_end_matches('/'));
-
-    // Convert messages to OpenRouter format
-    let openrouter_messages: Vec<OpenRouterMessage> = messages
-        .into_iter()
-        .map(|m| OpenRouterMessage {
-            role: m.role,
-            content: m.content,
-        })
-        .collect();
-
-    let request = OpenRouterRequest {
-        model,
-        messages: openrouter_messages,
-        stream: true,
-        temperature,
-        max_tokens,
-    };
-
-    let response = client
+    let builder = client
         .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
         .header("HTTP-Referer", "https://github.com/mnesler/scry-cli")
-        .header("X-Title", "scry-cli")
-        .json(&request)
+        .header("X-Title", "scry-cli");
+    let response = signing::json_body(builder, &request, signing.as_ref())
+        .await
+        .map_err(|e| format!("{:#}", e))?
         .send()
         .await
         .map_err(|e| format!("Request failed: {}", e))?;
@@ -207,67 +267,92 @@ async fn stream_openrouter_chat(
         return Err(format!("OpenRouter error ({}): {}", status, body));
     }
 
-    // Process SSE stream
-    let mut stream = response.bytes_stream();
-    let mut buffer = String::new();
-
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
-
-        // Process complete lines (SSE format: "data: {...}")
-        while let Some(newline_pos) = buffer.find('\n') {
-            let line = buffer[..newline_pos].trim();
-
-            if line.starts_with("data: ") {
-                let data = &line[6..];
-                
-                // Check for stream end
-                if data == "[DONE]" {
-                    let _ = tx.send(StreamEvent::Done).await;
+    let stream = recording::tap(response.bytes_stream(), recorder);
+    parse_stream(stream, &tx).await
+}
+
+/// Parse an OpenRouter SSE byte stream into stream events.
+///
+/// Shared by live requests and recorded stream replay.
+pub(super) async fn parse_stream<S, B, E>(
+    stream: S,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut events = std::pin::pin!(sse::events(stream));
+    let mut tool_calls = ToolCallBuffer::default();
+
+    while let Some(event) = events.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        let data = event.data.as_str();
+
+        // Check for stream end
+        if data == "[DONE]" {
+            if tool_calls.send(tx).await {
+                let _ = tx.send(StreamEvent::Done).await;
+            }
+            return Ok(());
+        }
+
+        // Parse JSON chunk
+        match serde_json::from_str::<OpenRouterStreamChunk>(data) {
+            Ok(chunk) => {
+                // Check for error
+                if let Some(error) = chunk.error {
+                    let _ = tx.send(StreamEvent::Error(error.message)).await;
                     return Ok(());
                 }
 
-                // Parse JSON chunk
-                match serde_json::from_str::<OpenRouterStreamChunk>(data) {
-                    Ok(chunk) => {
-                        // Check for error
-                        if let Some(error) = chunk.error {
-                            let _ = tx.send(StreamEvent::Error(error.message)).await;
-                            return Ok(());
-                        }
+                if let Some(usage) = chunk.usage {
+                    let _ = tx.send(StreamEvent::Usage(usage)).await;
+                }
 
-                        // Send content if present
-                        for choice in chunk.choices {
-                            if let Some(content) = choice.delta.content {
-                                if !content.is_empty() {
-                                    if tx.send(StreamEvent::Token(content)).await.is_err() {
-                                        return Ok(()); // Receiver dropped
-                                    }
-                                }
-                            }
-
-                            // Check for finish
-                            if choice.finish_reason.is_some() {
-                                let _ = tx.send(StreamEvent::Done).await;
-                                return Ok(());
-                            }
+                // Send content if present
+                for choice in chunk.choices {
+                    if let Some(reasoning) = choice.delta.reasoning {
+                        if !reasoning.is_empty()
+                            && tx.send(StreamEvent::Thinking(reasoning)).await.is_err()
+                        {
+                            return Ok(()); // Receiver dropped
                         }
                     }
-                    Err(e) => {
-                        // Log parse error but continue
-                        eprintln!("OpenRouter parse warning: {} for data: {}", e, data);
+                    if let Some(content) = choice.delta.content {
+                        if !content.is_empty()
+                            && tx.send(StreamEvent::Token(content)).await.is_err()
+                        {
+                            return Ok(()); // Receiver dropped
+                        }
+                    }
+                    for delta in choice.delta.tool_calls {
+                        tool_calls.push(delta);
+                    }
+
+                    // Check for finish
+                    if let Some(reason) = choice.finish_reason {
+                        if !tool_calls.send(tx).await {
+                            return Ok(()); // Receiver dropped
+                        }
+                        let _ = tx.send(StreamEvent::Finish(FinishReason::parse(&reason))).await;
+                        let _ = tx.send(StreamEvent::Done).await;
+                        return Ok(());
                     }
                 }
             }
-
-            buffer = buffer[newline_pos + 1..].to_string();
+            Err(e) => {
+                // Log parse error but continue
+                eprintln!("OpenRouter parse warning: {} for data: {}", e, data);
+            }
         }
     }
 
     // Send done if we haven't already
-    let _ = tx.send(StreamEvent::Done).await;
+    if tool_calls.send(tx).await {
+        let _ = tx.send(StreamEvent::Done).await;
+    }
     Ok(())
 }
 
@@ -291,6 +376,7 @@ mod tests {
             temperature: Some(0.7),
             max_tokens: Some(4096),
             credential_type: crate::llm::CredentialType::ApiKey,
+            ..LlmConfig::default()
         };
         let provider = OpenRouterProvider::new(config);
         assert_eq!(provider.provider(), Provider::OpenRouter);
@@ -299,18 +385,22 @@ mod tests {
 
     #[test]
     fn test_openrouter_provider_is_configured() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_key = "test-key".to_string();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_key: "test-key".to_string(),
+            ..LlmConfig::default()
+        };
         let provider = OpenRouterProvider::new(config);
         assert!(provider.is_configured());
     }
 
     #[test]
     fn test_openrouter_provider_not_configured() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_key = String::new();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_key: String::new(),
+            ..LlmConfig::default()
+        };
         let provider = OpenRouterProvider::new(config);
         assert!(!provider.is_configured());
     }
@@ -325,6 +415,7 @@ mod tests {
             temperature: None,
             max_tokens: None,
             credential_type: crate::llm::CredentialType::ApiKey,
+            ..LlmConfig::default()
         };
         let provider = OpenRouterProvider::new(config);
         assert_eq!(provider.display_name(), "OpenRouter");
@@ -332,18 +423,22 @@ mod tests {
 
     #[test]
     fn test_openrouter_api_base_default() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_base = String::new();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_base: String::new(),
+            ..LlmConfig::default()
+        };
         let provider = OpenRouterProvider::new(config);
         assert_eq!(provider.api_base(), DEFAULT_API_BASE);
     }
 
     #[test]
     fn test_openrouter_api_base_custom() {
-        let mut config = LlmConfig::default();
-        config.provider = Provider::OpenRouter;
-        config.api_base = "https://custom.openrouter.ai/api".to_string();
+        let config = LlmConfig {
+            provider: Provider::OpenRouter,
+            api_base: "https://custom.openrouter.ai/api".to_string(),
+            ..LlmConfig::default()
+        };
         let provider = OpenRouterProvider::new(config);
         assert_eq!(provider.api_base(), "https://custom.openrouter.ai/api");
     }
@@ -352,7 +447,9 @@ mod tests {
     fn test_openrouter_message_serialization() {
         let msg = OpenRouterMessage {
             role: "user".to_string(),
-            content: "Hello!".to_string(),
+            content: MessageContent::Text("Hello!".to_string()),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"role\":\"user\""));
@@ -365,17 +462,22 @@ mod tests {
             model: "anthropic/claude-sonnet-4-5".to_string(),
             messages: vec![OpenRouterMessage {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: MessageContent::Text("Hello".to_string()),
+                tool_calls: Vec::new(),
+                tool_call_id: None,
             }],
+            tools: Vec::new(),
             stream: true,
             temperature: Some(0.7),
             max_tokens: Some(4096),
+            reasoning: Some(OpenRouterReasoning { effort: "high" }),
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("\"model\":\"anthropic/claude-sonnet-4-5\""));
         assert!(json.contains("\"stream\":true"));
         assert!(json.contains("\"temperature\":0.7"));
         assert!(json.contains("\"max_tokens\":4096"));
+        assert!(json.contains("\"reasoning\":{\"effort\":\"high\"}"));
     }
 
     #[test]
@@ -383,13 +485,17 @@ mod tests {
         let request = OpenRouterRequest {
             model: "test".to_string(),
             messages: vec![],
+            tools: Vec::new(),
             stream: true,
             temperature: None,
             max_tokens: None,
+            reasoning: None,
         };
         let json = serde_json::to_string(&request).unwrap();
+        assert!(!json.contains("tools"));
         assert!(!json.contains("temperature"));
         assert!(!json.contains("max_tokens"));
+        assert!(!json.contains("reasoning"));
     }
 
     #[test]
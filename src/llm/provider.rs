@@ -4,9 +4,9 @@
 //! It enables a unified interface for interacting with different LLM backends.
 
 use async_trait::async_trait;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
-use super::{ChatMessage, Provider, StreamEvent};
+use super::{ChatMessage, Provider, StreamEvent, ToolDefinition};
 
 /// Trait for LLM providers.
 ///
@@ -22,9 +22,10 @@ use super::{ChatMessage, Provider, StreamEvent};
 ///     let messages = vec![ChatMessage {
 ///         role: "user".to_string(),
 ///         content: "Hello!".to_string(),
+///         ..Default::default()
 ///     }];
 ///     
-///     let mut rx = provider.stream_chat(messages);
+///     let mut rx = provider.stream_chat(messages, &[]);
 ///     while let Some(event) = rx.recv().await {
 ///         // Handle events...
 ///     }
@@ -56,11 +57,50 @@ pub trait LlmProvider: Send + Sync {
     ///
     /// Returns a channel receiver that yields `StreamEvent`s:
     /// - `StreamEvent::Token(String)` - A chunk of generated text
+    /// - `StreamEvent::ToolCall(ToolCall)` - A call to one of `tools`
     /// - `StreamEvent::Done` - Stream completed successfully
     /// - `StreamEvent::Error(String)` - An error occurred
     ///
-    /// The returned receiver should be polled until `Done` or `Error` is received.
-    fn stream_chat(&self, messages: Vec<ChatMessage>) -> mpsc::Receiver<StreamEvent>;
+    /// The model is offered `tools` when there are any. The returned receiver
+    /// should be polled until `Done` or `Error` is received.
+    fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent>;
+
+    /// Build the request `stream_chat` would send for `messages`, without sending it.
+    ///
+    /// Used by dry runs. Returns `None` for providers that don't make HTTP
+    /// requests, such as replays.
+    fn preview_request(
+        &self,
+        _messages: Vec<ChatMessage>,
+        _tools: &[ToolDefinition],
+    ) -> Option<RequestPreview> {
+        None
+    }
+
+    /// List the models this provider offers, as reported by its API.
+    ///
+    /// Used to fill the model picker. The default reports that the
+    /// provider has no models endpoint.
+    async fn list_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        Err(ProviderError::Other(format!(
+            "{} does not list its models",
+            self.display_name()
+        )))
+    }
+
+    /// Load the model ahead of the first request.
+    ///
+    /// Local providers can take several seconds to load a model on first use.
+    /// Providers that support preloading start it in the background and
+    /// return a receiver that resolves once the model is ready. The default
+    /// returns `None`, meaning there is nothing to warm up.
+    fn warm_up(&self) -> Option<oneshot::Receiver<Result<(), String>>> {
+        None
+    }
 
     /// Cancel any ongoing request.
     ///
@@ -71,6 +111,52 @@ pub trait LlmProvider: Send + Sync {
     }
 }
 
+/// A model offered by a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// Id sent in requests
+    pub id: String,
+    /// Name shown in the model picker
+    pub name: String,
+}
+
+impl ModelInfo {
+    /// A model whose name is its id, for APIs that only report ids.
+    pub fn from_id(id: impl Into<String>) -> Self {
+        let id = id.into();
+        Self {
+            name: id.clone(),
+            id,
+        }
+    }
+}
+
+/// A provider request as it would be sent, shown by dry runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestPreview {
+    /// Endpoint the request is posted to
+    pub url: String,
+    /// JSON body; credentials travel in headers and are never included
+    pub body: serde_json::Value,
+}
+
+impl RequestPreview {
+    /// Preview of posting `body` to `url`.
+    pub fn new(url: String, body: &impl serde::Serialize) -> Self {
+        Self {
+            url,
+            body: serde_json::to_value(body).unwrap_or_default(),
+        }
+    }
+}
+
+impl std::fmt::Display for RequestPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let body = serde_json::to_string_pretty(&self.body).map_err(|_| std::fmt::Error)?;
+        write!(f, "POST {}\n{}", self.url, body)
+    }
+}
+
 /// Result type for provider operations.
 pub type ProviderResult<T> = Result<T, ProviderError>;
 
@@ -129,6 +215,90 @@ impl std::fmt::Display for ProviderError {
 
 impl std::error::Error for ProviderError {}
 
+impl ProviderError {
+    /// Classify an error message reported by a stream.
+    ///
+    /// Providers report stream failures as plain strings; this recovers the
+    /// error class from the HTTP status or wording so it can be shown apart
+    /// from the message itself.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_string();
+        let lower = message.to_lowercase();
+        match http_status(&message) {
+            Some(401 | 403) => Self::AuthenticationFailed(message),
+            Some(429) => Self::RateLimited {
+                message,
+                retry_after: None,
+            },
+            Some(400 | 404 | 413 | 422) => Self::InvalidRequest(message),
+            Some(status) => Self::ApiError { status, message },
+            None if lower.contains("rate limit") => Self::RateLimited {
+                message,
+                retry_after: None,
+            },
+            None if ["connect", "timed out", "request failed", "stream error", "dns"]
+                .iter()
+                .any(|p| lower.contains(p)) =>
+            {
+                Self::NetworkError(message)
+            }
+            None if lower.contains("not configured") => Self::NotConfigured(message),
+            None => Self::Other(message),
+        }
+    }
+
+    /// Whether sending the request again may succeed: rate limits, server
+    /// errors and dropped connections. A server that can't be reached at
+    /// all is not retried; the app waits for it to come back instead.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } => true,
+            Self::ApiError { status, .. } => *status >= 500,
+            Self::NetworkError(message) => {
+                let lower = message.to_lowercase();
+                ["reset", "connection closed", "broken pipe", "unexpected eof"]
+                    .iter()
+                    .any(|p| lower.contains(p))
+            }
+            _ => false,
+        }
+    }
+
+    /// Short name of the error class.
+    pub fn title(&self) -> String {
+        match self {
+            Self::NotConfigured(_) => "Not configured".to_string(),
+            Self::AuthenticationFailed(_) => "Authentication failed".to_string(),
+            Self::RateLimited { .. } => "Rate limited".to_string(),
+            Self::NetworkError(_) => "Network error".to_string(),
+            Self::ApiError { status, .. } => format!("API error {}", status),
+            Self::InvalidRequest(_) => "Invalid request".to_string(),
+            Self::Other(_) => "Request failed".to_string(),
+        }
+    }
+
+    /// The error message without its class prefix.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotConfigured(msg)
+            | Self::AuthenticationFailed(msg)
+            | Self::NetworkError(msg)
+            | Self::InvalidRequest(msg)
+            | Self::Other(msg) => msg,
+            Self::RateLimited { message, .. } | Self::ApiError { message, .. } => message,
+        }
+    }
+}
+
+/// Find an HTTP error status (4xx/5xx) mentioned in an error message.
+fn http_status(message: &str) -> Option<u16> {
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|word| word.len() == 3)
+        .filter_map(|word| word.parse().ok())
+        .find(|status| (400..600).contains(status))
+}
+
 impl From<reqwest::Error> for ProviderError {
     fn from(err: reqwest::Error) -> Self {
         if err.is_timeout() {
@@ -167,6 +337,37 @@ mod tests {
         assert_eq!(err.to_string(), "API error (400): bad request");
     }
 
+    #[test]
+    fn test_provider_error_from_message() {
+        let err = ProviderError::from_message("Ollama error (404): model 'x' not found");
+        assert!(matches!(err, ProviderError::InvalidRequest(_)));
+        assert_eq!(err.message(), "Ollama error (404): model 'x' not found");
+
+        let err = ProviderError::from_message("Anthropic API error 529 Overloaded: {}");
+        assert_eq!(err.title(), "API error 529");
+
+        let err = ProviderError::from_message("Copilot API error (401): bad token");
+        assert!(matches!(err, ProviderError::AuthenticationFailed(_)));
+
+        let err = ProviderError::from_message(
+            "Cannot connect to Ollama at http://localhost:11434. Is Ollama running?",
+        );
+        assert_eq!(err.title(), "Network error");
+
+        assert_eq!(ProviderError::from_message("boom").title(), "Request failed");
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let transient = |message: &str| ProviderError::from_message(message).is_transient();
+        assert!(transient("OpenAI API error (429): Rate limit reached"));
+        assert!(transient("Anthropic API error 529 Overloaded: {}"));
+        assert!(transient("Request failed: connection reset by peer"));
+        assert!(!transient("Cannot connect to Ollama at http://localhost:11434. Is Ollama running?"));
+        assert!(!transient("OpenAI API error (400): bad request"));
+        assert!(!transient("OpenAI API error (401): invalid key"));
+    }
+
     #[test]
     fn test_provider_error_from_reqwest() {
         // We can't easily create reqwest errors, but we can test the From impl exists
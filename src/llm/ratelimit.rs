@@ -0,0 +1,174 @@
+//! Rate-limit quotas reported in response headers.
+//!
+//! Anthropic sends `anthropic-ratelimit-{requests,tokens}-{limit,remaining,reset}`
+//! with the reset as an RFC 3339 time; OpenAI and compatible APIs send
+//! `x-ratelimit-{limit,remaining,reset}-{requests,tokens}` with the reset as
+//! a duration such as `6m0s`. Both are read into [`RateLimits`], so the app
+//! can show what is left and hold a prompt the quota could not cover.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+
+/// One quota: how much of it is left and when it fills up again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Time from the response until the quota is full again, if reported
+    pub reset: Option<Duration>,
+}
+
+/// Request and token quotas from one response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests: Option<Quota>,
+    pub tokens: Option<Quota>,
+}
+
+impl RateLimits {
+    /// The quotas in `headers`, received at `now`, or `None` without any.
+    pub fn from_headers(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+        let quota = |kind: &str| {
+            // Anthropic's names, then OpenAI's
+            let anthropic =
+                |field: &str| header(&format!("anthropic-ratelimit-{}-{}", kind, field));
+            let openai = |field: &str| header(&format!("x-ratelimit-{}-{}", field, kind));
+            let limit = anthropic("limit").or_else(|| openai("limit"))?.parse().ok()?;
+            let remaining = anthropic("remaining")
+                .or_else(|| openai("remaining"))?
+                .parse()
+                .ok()?;
+            let reset = match anthropic("reset") {
+                Some(at) => DateTime::parse_from_rfc3339(at)
+                    .ok()
+                    .and_then(|at| (at.with_timezone(&Utc) - now).to_std().ok()),
+                None => openai("reset").and_then(parse_duration),
+            };
+            Some(Quota {
+                limit,
+                remaining,
+                reset,
+            })
+        };
+        let limits = Self {
+            requests: quota("requests"),
+            tokens: quota("tokens"),
+        };
+        (limits != Self::default()).then_some(limits)
+    }
+
+    /// How long to wait before sending a request of about `tokens` tokens:
+    /// until the reset of a quota that is used up or could not cover it.
+    /// `None` if it can go now.
+    pub fn wait_for(&self, tokens: u64) -> Option<Duration> {
+        let requests = self.requests.filter(|q| q.remaining == 0);
+        let tokens = self.tokens.filter(|q| q.remaining < tokens);
+        [requests, tokens]
+            .into_iter()
+            .flatten()
+            .filter_map(|q| q.reset)
+            .max()
+    }
+}
+
+/// A Go-style duration as OpenAI writes them: `1s`, `20ms`, `6m0s`, `1h2m3.5s`.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        total += value
+            * match &rest[..unit] {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                "ms" => 0.001,
+                _ => return None,
+            };
+        rest = &rest[unit..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (HeaderName::from_static(name), HeaderValue::from_str(value).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reads_anthropic_headers() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        let limits = RateLimits::from_headers(
+            &headers(&[
+                ("anthropic-ratelimit-requests-limit", "50"),
+                ("anthropic-ratelimit-requests-remaining", "49"),
+                ("anthropic-ratelimit-requests-reset", "2025-03-01T12:00:01Z"),
+                ("anthropic-ratelimit-tokens-limit", "40000"),
+                ("anthropic-ratelimit-tokens-remaining", "1200"),
+                ("anthropic-ratelimit-tokens-reset", "2025-03-01T12:00:30Z"),
+            ]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            limits.requests,
+            Some(Quota {
+                limit: 50,
+                remaining: 49,
+                reset: Some(Duration::from_secs(1)),
+            })
+        );
+        assert_eq!(limits.tokens.unwrap().remaining, 1200);
+
+        // Enough for a small prompt, not for a large one
+        assert_eq!(limits.wait_for(1000), None);
+        assert_eq!(limits.wait_for(5000), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_reads_openai_headers() {
+        let limits = RateLimits::from_headers(
+            &headers(&[
+                ("x-ratelimit-limit-requests", "500"),
+                ("x-ratelimit-remaining-requests", "0"),
+                ("x-ratelimit-reset-requests", "1m30.5s"),
+                ("x-ratelimit-limit-tokens", "30000"),
+                ("x-ratelimit-remaining-tokens", "29000"),
+            ]),
+            Utc::now(),
+        )
+        .unwrap();
+        assert_eq!(limits.tokens.unwrap().reset, None);
+        assert_eq!(limits.wait_for(10), Some(Duration::from_millis(90_500)));
+
+        assert_eq!(RateLimits::from_headers(&HeaderMap::new(), Utc::now()), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("soon"), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Reasoning effort (`/think`) and how each provider expresses it.
+//!
+//! Anthropic takes a thinking token budget, OpenAI-compatible APIs take a
+//! `reasoning_effort` level, and OpenRouter takes a `reasoning` object.
+
+use serde::{Deserialize, Serialize};
+
+/// How hard the model should think before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    #[serde(alias = "med")]
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// Short name shown in the status bar and under replies.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "med",
+            ReasoningEffort::High => "high",
+        }
+    }
+
+    /// Level name for OpenAI's `reasoning_effort` and OpenRouter's `reasoning.effort`.
+    pub const fn effort(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+
+    /// Anthropic extended thinking budget, in tokens.
+    pub const fn budget_tokens(&self) -> u32 {
+        match self {
+            ReasoningEffort::Low => 2_048,
+            ReasoningEffort::Medium => 8_192,
+            ReasoningEffort::High => 24_576,
+        }
+    }
+}
+
+impl std::str::FromStr for ReasoningEffort {
+    type Err = String;
+
+    /// Parse `low`, `med`/`medium` or `high` (case-insensitive).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(ReasoningEffort::Low),
+            "med" | "medium" => Ok(ReasoningEffort::Medium),
+            "high" => Ok(ReasoningEffort::High),
+            _ => Err(format!(
+                "Unknown reasoning effort '{}' (expected low, med, high or off)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_effort() {
+        assert_eq!("med".parse(), Ok(ReasoningEffort::Medium));
+        assert_eq!(" HIGH ".parse(), Ok(ReasoningEffort::High));
+        assert!("max".parse::<ReasoningEffort>().is_err());
+        assert!(ReasoningEffort::Low.budget_tokens() < ReasoningEffort::High.budget_tokens());
+    }
+}
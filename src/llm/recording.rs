@@ -0,0 +1,378 @@
+//! Recording and replay of raw provider response streams.
+//!
+//! When `record_dir` is set, every response body is written chunk by chunk to a
+//! `.jsonl` file in that directory, preserving chunk boundaries and timing. A
+//! recording can later be fed back through the provider's parser with
+//! [`replay`] or [`ReplayProvider`], which makes malformed-chunk bugs
+//! reproducible without a live API.
+//!
+//! File format: a header line `{"provider":..,"model":..,"recorded_at":..}`
+//! followed by one `{"ms":..,"data":"<base64>"}` line per chunk.
+
+use std::convert::Infallible;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::{anthropic, copilot, deepseek, ollama, openai, openrouter};
+use super::{ChatMessage, LlmConfig, LlmProvider, Provider, StreamEvent, ToolDefinition};
+
+/// First line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordingHeader {
+    provider: Provider,
+    model: String,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A single chunk as received from the network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedChunk {
+    /// Milliseconds since the first byte of the response
+    pub ms: u64,
+    /// Raw bytes (base64 in the file)
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// Writes response chunks to a recording file.
+pub struct StreamRecorder {
+    file: File,
+    started: Instant,
+}
+
+impl StreamRecorder {
+    /// Create a new recording file in `dir`.
+    pub fn create(dir: &Path, provider: Provider, model: &str) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create recording directory {}", dir.display()))?;
+
+        let recorded_at = chrono::Utc::now();
+        let stem = format!(
+            "{}-{}",
+            provider.storage_key(),
+            recorded_at.format("%Y%m%d-%H%M%S%.3f")
+        );
+        // Responses started in the same millisecond get numbered names instead
+        // of overwriting each other
+        let mut suffix = 1;
+        let mut path = dir.join(format!("{}.jsonl", stem));
+        let mut file = loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    suffix += 1;
+                    path = dir.join(format!("{}-{}.jsonl", stem, suffix));
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to create recording {}", path.display()))
+                }
+            }
+        };
+
+        let header = RecordingHeader {
+            provider,
+            model: model.to_string(),
+            recorded_at,
+        };
+        writeln!(file, "{}", serde_json::to_string(&header)?)?;
+
+        Ok(Self {
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    /// Append a chunk to the recording.
+    pub fn record(&mut self, data: &[u8]) {
+        let chunk = RecordedChunk {
+            ms: self.started.elapsed().as_millis() as u64,
+            data: data.to_vec(),
+        };
+        // Recording is best effort and must never break the live stream
+        if let Ok(line) = serde_json::to_string(&chunk) {
+            let _ = writeln!(self.file, "{}", line);
+        }
+    }
+}
+
+/// Start a recorder if `dir` is set.
+///
+/// Failures to create the file are ignored so recording never blocks a request.
+pub fn start(dir: Option<&Path>, provider: Provider, model: &str) -> Option<StreamRecorder> {
+    dir.and_then(|dir| StreamRecorder::create(dir, provider, model).ok())
+}
+
+/// Start a recorder for a request made with `config`.
+pub fn recorder_for(config: &LlmConfig) -> Option<StreamRecorder> {
+    start(config.record_dir.as_deref(), config.provider, &config.model)
+}
+
+/// Pass a byte stream through unchanged, recording each chunk.
+pub fn tap<S, B, E>(stream: S, mut recorder: Option<StreamRecorder>) -> impl Stream<Item = S::Item>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    stream.inspect(move |item| {
+        if let (Some(recorder), Ok(bytes)) = (recorder.as_mut(), item) {
+            recorder.record(bytes.as_ref());
+        }
+    })
+}
+
+/// A recording loaded from disk.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub provider: Provider,
+    pub model: String,
+    pub chunks: Vec<RecordedChunk>,
+}
+
+impl Recording {
+    /// Load a recording file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open recording {}", path.display()))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header_line = lines.next().context("Recording is empty")??;
+        let header: RecordingHeader =
+            serde_json::from_str(&header_line).context("Invalid recording header")?;
+
+        let mut chunks = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let chunk = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid recording chunk on line {}", i + 2))?;
+            chunks.push(chunk);
+        }
+
+        Ok(Self {
+            provider: header.provider,
+            model: header.model,
+            chunks,
+        })
+    }
+}
+
+/// Feed a recording through its provider's stream parser.
+///
+/// With `speed` of 1.0 chunks arrive with their original timing; larger values
+/// replay faster and 0.0 replays instantly.
+pub fn replay(recording: Recording, speed: f32) -> mpsc::Receiver<StreamEvent> {
+    let (tx, rx) = mpsc::channel(100);
+
+    tokio::spawn(async move {
+        let chunks = recording.chunks;
+        let stream = Box::pin(
+            futures::stream::iter(chunks.into_iter().scan(0u64, move |last_ms, chunk| {
+                let delay = chunk.ms.saturating_sub(*last_ms);
+                *last_ms = chunk.ms;
+                Some((delay, chunk.data))
+            }))
+            .then(move |(delay, data)| async move {
+                if speed > 0.0 && delay > 0 {
+                    tokio::time::sleep(Duration::from_millis(delay).div_f32(speed)).await;
+                }
+                Ok::<_, Infallible>(data)
+            }),
+        );
+
+        let result = match recording.provider {
+            Provider::Anthropic => anthropic::parse_stream(stream, &tx)
+                .await
+                .map_err(|e| e.to_string()),
+            Provider::GitHubCopilot => copilot::parse_stream(stream, &tx)
+                .await
+                .map_err(|e| e.to_string()),
+            Provider::OpenRouter => openrouter::parse_stream(stream, &tx).await,
+            Provider::OpenAI | Provider::Groq | Provider::LocalServer => {
+                openai::parse_stream(stream, &tx).await
+            }
+            Provider::DeepSeek => deepseek::parse_stream(stream, &tx).await,
+            Provider::Ollama => ollama::parse_stream(stream, &tx).await,
+        };
+        if let Err(e) = result {
+            let _ = tx.send(StreamEvent::Error(e)).await;
+        }
+    });
+
+    rx
+}
+
+/// Provider that answers every request by replaying a recording.
+///
+/// Used by `scry replay` to push a recorded stream through the chat UI.
+pub struct ReplayProvider {
+    recording: Recording,
+    speed: f32,
+}
+
+impl ReplayProvider {
+    /// Create a replay provider for a loaded recording.
+    pub fn new(recording: Recording, speed: f32) -> Self {
+        Self { recording, speed }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    fn provider(&self) -> Provider {
+        self.recording.provider
+    }
+
+    fn model(&self) -> &str {
+        &self.recording.model
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn stream_chat(
+        &self,
+        _messages: Vec<ChatMessage>,
+        _tools: &[ToolDefinition],
+    ) -> mpsc::Receiver<StreamEvent> {
+        replay(self.recording.clone(), self.speed)
+    }
+}
+
+/// Serde helper storing bytes as base64 strings.
+mod base64_bytes {
+    use super::{Engine, STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn chunks(parts: &[&str]) -> Vec<RecordedChunk> {
+        parts
+            .iter()
+            .map(|p| RecordedChunk {
+                ms: 0,
+                data: p.as_bytes().to_vec(),
+            })
+            .collect()
+    }
+
+    async fn collect(mut rx: mpsc::Receiver<StreamEvent>) -> (String, bool) {
+        let mut text = String::new();
+        let mut done = false;
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Token(t) => text.push_str(&t),
+                StreamEvent::Done => done = true,
+                _ => {}
+            }
+        }
+        (text, done)
+    }
+
+    #[tokio::test]
+    async fn test_record_and_load_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let mut recorder =
+            StreamRecorder::create(dir.path(), Provider::Ollama, "qwen3:4b").unwrap();
+        recorder.record(b"first");
+        recorder.record(b"second\n");
+        drop(recorder);
+
+        let path = fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(path.to_string_lossy().contains("ollama-"));
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.provider, Provider::Ollama);
+        assert_eq!(recording.model, "qwen3:4b");
+        assert_eq!(recording.chunks.len(), 2);
+        assert_eq!(recording.chunks[1].data, b"second\n");
+    }
+
+    #[test]
+    fn test_recordings_never_overwrite_each_other() {
+        let dir = TempDir::new().unwrap();
+        let recorders: Vec<StreamRecorder> = (0..20)
+            .map(|_| StreamRecorder::create(dir.path(), Provider::Ollama, "qwen3:4b").unwrap())
+            .collect();
+        drop(recorders);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_replay_anthropic_split_chunks() {
+        let recording = Recording {
+            provider: Provider::Anthropic,
+            model: "claude".to_string(),
+            chunks: chunks(&[
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"te",
+                "xt\":\"Hello\"}}\n\nevent: message_stop\ndata: {}\n\n",
+            ]),
+        };
+        let (text, done) = collect(replay(recording, 0.0)).await;
+        assert_eq!(text, "Hello");
+        assert!(done);
+    }
+
+    #[tokio::test]
+    async fn test_replay_openai_style() {
+        let recording = Recording {
+            provider: Provider::OpenRouter,
+            model: "test".to_string(),
+            chunks: chunks(&[
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\" there\"}}]}\n\ndata: [DONE]\n\n",
+            ]),
+        };
+        let (text, done) = collect(replay(recording, 0.0)).await;
+        assert_eq!(text, "Hi there");
+        assert!(done);
+    }
+
+    #[tokio::test]
+    async fn test_tap_records_chunks() {
+        let dir = TempDir::new().unwrap();
+        let recorder = StreamRecorder::create(dir.path(), Provider::Anthropic, "m").ok();
+        let stream = futures::stream::iter(vec![Ok::<_, Infallible>(b"abc".to_vec())]);
+        let items: Vec<_> = tap(stream, recorder).collect().await;
+        assert_eq!(items.len(), 1);
+
+        let path = fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.chunks[0].data, b"abc");
+    }
+}
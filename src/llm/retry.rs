@@ -0,0 +1,223 @@
+//! Retrying requests that fail before the reply starts.
+//!
+//! Rate limits (429), server errors (5xx) and dropped connections usually
+//! pass. When a stream fails with one of those before any of the reply has
+//! arrived, the request is sent again after a jittered, exponentially growing
+//! delay, and a [`StreamEvent::Retrying`] says so. Once output has streamed
+//! errors are passed on as they are: retrying would repeat the reply.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::sync::mpsc;
+
+use super::{backpressure, ChatMessage, LlmProvider, ProviderError, StreamEvent, ToolDefinition};
+use crate::config::RetryConfig;
+
+/// Stream `messages` from `provider`, offering `tools`, and retry transient
+/// failures as `config` allows.
+pub fn stream_with_retries(
+    provider: Arc<dyn LlmProvider>,
+    messages: Vec<ChatMessage>,
+    tools: Vec<ToolDefinition>,
+    config: RetryConfig,
+) -> mpsc::Receiver<StreamEvent> {
+    if config.max_retries == 0 {
+        return provider.stream_chat(messages, &tools);
+    }
+    let (tx, rx) = backpressure::handoff();
+    tokio::spawn(async move {
+        let mut attempt = 0;
+        loop {
+            let mut source = provider.stream_chat(messages.clone(), &tools);
+            let mut started = false;
+            let mut failure = None;
+            while let Some(event) = source.recv().await {
+                match event {
+                    StreamEvent::Error(message)
+                        if !started
+                            && attempt < config.max_retries
+                            && ProviderError::from_message(&message).is_transient() =>
+                    {
+                        failure = Some(message);
+                        break;
+                    }
+                    event => {
+                        started |= matches!(
+                            event,
+                            StreamEvent::Token(_)
+                                | StreamEvent::Thinking(_)
+                                | StreamEvent::ToolCall(_)
+                        );
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let Some(reason) = failure else {
+                return;
+            };
+
+            attempt += 1;
+            let delay = backoff(&config, attempt, rand::thread_rng().gen());
+            let retrying = StreamEvent::Retrying {
+                attempt,
+                max_retries: config.max_retries,
+                delay,
+                reason,
+            };
+            if tx.send(retrying).await.is_err() {
+                return;
+            }
+            // Stop waiting if the reply is cancelled meanwhile
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = tx.closed() => return,
+            }
+        }
+    });
+    rx
+}
+
+/// Delay before retry `attempt` (from 1): the base delay doubled for each
+/// retry before it, capped, then jittered down by up to half so clients
+/// that failed together don't retry together. `jitter` is in `[0, 1)`.
+fn backoff(config: &RetryConfig, attempt: u32, jitter: f64) -> Duration {
+    let exponential = config
+        .base_delay_ms
+        .saturating_mul(1u64 << (attempt - 1).min(32))
+        .min(config.max_delay_ms);
+    let half = exponential / 2;
+    Duration::from_millis(half + (half as f64 * jitter) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Provider;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Answers each request with the next scripted list of events.
+    struct Scripted {
+        responses: Mutex<Vec<Vec<StreamEvent>>>,
+        requests: Mutex<usize>,
+    }
+
+    impl Scripted {
+        fn new(mut responses: Vec<Vec<StreamEvent>>) -> Arc<Self> {
+            responses.reverse();
+            Arc::new(Self {
+                responses: Mutex::new(responses),
+                requests: Mutex::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for Scripted {
+        fn provider(&self) -> Provider {
+            Provider::OpenAI
+        }
+
+        fn model(&self) -> &str {
+            "gpt-4o"
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        fn stream_chat(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: &[ToolDefinition],
+        ) -> mpsc::Receiver<StreamEvent> {
+            *self.requests.lock().unwrap() += 1;
+            let events = self.responses.lock().unwrap().pop().unwrap_or_default();
+            let (tx, rx) = mpsc::channel(events.len().max(1));
+            for event in events {
+                let _ = tx.try_send(event);
+            }
+            rx
+        }
+    }
+
+    const FAST: RetryConfig = RetryConfig {
+        max_retries: 2,
+        base_delay_ms: 1,
+        max_delay_ms: 5,
+    };
+
+    async fn collect(mut rx: mpsc::Receiver<StreamEvent>) -> Vec<String> {
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(match event {
+                StreamEvent::Token(text) => text,
+                StreamEvent::Retrying { attempt, .. } => format!("retry {}", attempt),
+                StreamEvent::Error(message) => format!("error: {}", message),
+                StreamEvent::Done => "done".to_string(),
+                other => format!("{:?}", other),
+            });
+        }
+        events
+    }
+
+    fn error(message: &str) -> Vec<StreamEvent> {
+        vec![StreamEvent::Error(message.to_string())]
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let provider = Scripted::new(vec![
+            error("OpenAI API error (429): Rate limit reached"),
+            error("OpenAI API error (503): unavailable"),
+            vec![StreamEvent::Token("Hi".to_string()), StreamEvent::Done],
+        ]);
+        let rx = stream_with_retries(provider.clone(), Vec::new(), Vec::new(), FAST);
+        assert_eq!(collect(rx).await, ["retry 1", "retry 2", "Hi", "done"]);
+        assert_eq!(*provider.requests.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries_and_on_other_errors() {
+        let busy = "OpenAI API error (500): oops";
+        let provider = Scripted::new(vec![error(busy), error(busy), error(busy)]);
+        let rx = stream_with_retries(provider, Vec::new(), Vec::new(), FAST);
+        let events = collect(rx).await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2], format!("error: {}", busy));
+
+        let provider = Scripted::new(vec![error("OpenAI API error (401): bad key")]);
+        let rx = stream_with_retries(provider.clone(), Vec::new(), Vec::new(), FAST);
+        assert_eq!(collect(rx).await, ["error: OpenAI API error (401): bad key"]);
+        assert_eq!(*provider.requests.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failures_after_output_are_not_retried() {
+        let dropped = "Request failed: connection reset by peer";
+        let provider = Scripted::new(vec![vec![
+            StreamEvent::Token("Hal".to_string()),
+            StreamEvent::Error(dropped.to_string()),
+        ]]);
+        let rx = stream_with_retries(provider, Vec::new(), Vec::new(), FAST);
+        assert_eq!(collect(rx).await, ["Hal".to_string(), format!("error: {}", dropped)]);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap_with_jitter() {
+        let config = RetryConfig {
+            max_retries: 10,
+            base_delay_ms: 1000,
+            max_delay_ms: 5000,
+        };
+        assert_eq!(backoff(&config, 1, 0.0), Duration::from_millis(500));
+        assert_eq!(backoff(&config, 1, 0.999), Duration::from_millis(999));
+        assert_eq!(backoff(&config, 3, 1.0), Duration::from_millis(4000));
+        assert_eq!(backoff(&config, 9, 1.0), Duration::from_millis(5000));
+        assert_eq!(backoff(&config, 40, 0.0), Duration::from_millis(2500));
+    }
+}
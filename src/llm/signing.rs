@@ -0,0 +1,327 @@
+//! Request signing for authenticated LLM gateways.
+//!
+//! Providers attach their JSON bodies through [`json_body`], which serializes
+//! the body once and adds the signature headers described by the configured
+//! [`SigningConfig`] before the request is sent. Requests without a body, such
+//! as model listings, go through [`sign`] instead.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, bail, Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{RequestBuilder, Url};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::config::SigningConfig;
+
+/// Attach `body` as JSON, signing the request if `signing` is set.
+pub async fn json_body<T: Serialize>(
+    request: RequestBuilder,
+    body: &T,
+    signing: Option<&SigningConfig>,
+) -> Result<RequestBuilder> {
+    let Some(signing) = signing else {
+        return Ok(request.json(body));
+    };
+    let bytes = serde_json::to_vec(body).context("Failed to serialize request body")?;
+    add_signature(request, signing, Some(bytes)).await
+}
+
+/// Sign a request without a body, if `signing` is set. The signature covers
+/// the method and path with an empty body.
+pub async fn sign(request: RequestBuilder, signing: Option<&SigningConfig>) -> Result<RequestBuilder> {
+    match signing {
+        Some(signing) => add_signature(request, signing, None).await,
+        None => Ok(request),
+    }
+}
+
+/// Add the headers `signing` computes for `request`, and `body` as its JSON body.
+async fn add_signature(
+    request: RequestBuilder,
+    signing: &SigningConfig,
+    body: Option<Vec<u8>>,
+) -> Result<RequestBuilder> {
+    let (client, request) = request.build_split();
+    let mut request = request.context("Failed to build request")?;
+
+    let headers = signature_headers(
+        signing.clone(),
+        request.method().as_str().to_string(),
+        request.url().clone(),
+        body.clone().unwrap_or_default(),
+    )
+    .await?;
+
+    let map = request.headers_mut();
+    if body.is_some() {
+        map.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid signing header name: {}", name))?;
+        let value = HeaderValue::from_str(&value)
+            .with_context(|| format!("Invalid value for signing header {}", name))?;
+        map.insert(name, value);
+    }
+    if let Some(body) = body {
+        *request.body_mut() = Some(body.into());
+    }
+
+    Ok(RequestBuilder::from_parts(client, request))
+}
+
+/// Compute the headers to add for a request.
+async fn signature_headers(
+    signing: SigningConfig,
+    method: String,
+    url: Url,
+    body: Vec<u8>,
+) -> Result<Vec<(String, String)>> {
+    match signing {
+        SigningConfig::Hmac {
+            secret_env,
+            header,
+            timestamp_header,
+        } => {
+            let secret = std::env::var(&secret_env)
+                .map_err(|_| anyhow!("Request signing secret {} is not set", secret_env))?;
+            let timestamp = chrono::Utc::now().timestamp();
+            let signature = hmac_signature(&secret, timestamp, &method, &url, &body);
+            Ok(vec![
+                (header, signature),
+                (timestamp_header, timestamp.to_string()),
+            ])
+        }
+        SigningConfig::Command { command, args } => tokio::task::spawn_blocking(move || {
+            run_signing_command(&command, &args, &method, &url, &body)
+        })
+        .await
+        .context("Request signing command panicked")?,
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of `timestamp\nMETHOD\npath?query\nbody`.
+fn hmac_signature(secret: &str, timestamp: i64, method: &str, url: &Url, body: &[u8]) -> String {
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key");
+    mac.update(format!("{}\n{}\n{}\n", timestamp, method, path).as_bytes());
+    mac.update(body);
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Run an external signing program and parse its `Name: value` output lines.
+fn run_signing_command(
+    command: &str,
+    args: &[String],
+    method: &str,
+    url: &Url,
+    body: &[u8],
+) -> Result<Vec<(String, String)>> {
+    let mut child = Command::new(command)
+        .args(args)
+        .env("SCRY_REQUEST_METHOD", method)
+        .env("SCRY_REQUEST_URL", url.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run request signing command {}", command))?;
+
+    // Write the body while the output is read: a program that prints before
+    // it has read all of a large body would otherwise block both sides
+    let stdin = child.stdin.take();
+    let (written, output) = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.map_or(Ok(()), |mut stdin| stdin.write_all(body)));
+        let output = child.wait_with_output();
+        (writer.join().expect("signing body writer panicked"), output)
+    });
+    // Programs that don't need the body may exit without reading it
+    if let Err(e) = written {
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(e).context("Failed to pass request body to signing command");
+        }
+    }
+
+    let output = output?;
+    if !output.status.success() {
+        bail!(
+            "Request signing command failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    parse_header_lines(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `Name: value` lines, ignoring blank lines.
+fn parse_header_lines(text: &str) -> Result<Vec<(String, String)>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, value) = line.split_once(':').ok_or_else(|| {
+                anyhow!("Expected 'Name: value' from signing command, got: {}", line)
+            })?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_signature_is_stable() {
+        let url = Url::parse("https://gateway.internal/v1/messages?beta=1").unwrap();
+        let a = hmac_signature("secret", 1700000000, "POST", &url, b"{}");
+        let b = hmac_signature("secret", 1700000000, "POST", &url, b"{}");
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 64);
+
+        let other = hmac_signature("secret", 1700000001, "POST", &url, b"{}");
+        assert_ne!(a, other);
+    }
+
+    #[test]
+    fn test_parse_header_lines() {
+        let headers = parse_header_lines("X-Sig: abc\n\nX-Key-Id : 42 \n").unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                ("X-Sig".to_string(), "abc".to_string()),
+                ("X-Key-Id".to_string(), "42".to_string()),
+            ]
+        );
+        assert!(parse_header_lines("not a header").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_body_without_signing() {
+        let request = reqwest::Client::new().post("http://localhost/test");
+        let request = json_body(request, &serde_json::json!({"a": 1}), None)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.body().and_then(|b| b.as_bytes()),
+            Some(&b"{\"a\":1}"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_body_with_hmac() {
+        std::env::set_var("SCRY_TEST_SIGNING_SECRET", "secret");
+        let signing = SigningConfig::Hmac {
+            secret_env: "SCRY_TEST_SIGNING_SECRET".to_string(),
+            header: "X-Signature".to_string(),
+            timestamp_header: "X-Signature-Timestamp".to_string(),
+        };
+        let request = reqwest::Client::new().post("http://localhost/test");
+        let request = json_body(request, &serde_json::json!({"a": 1}), Some(&signing))
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let headers = request.headers();
+        assert_eq!(headers[CONTENT_TYPE], "application/json");
+        assert_eq!(headers["X-Signature"].len(), 64);
+        assert!(headers.contains_key("X-Signature-Timestamp"));
+    }
+
+    #[tokio::test]
+    async fn test_get_is_signed_over_an_empty_body() {
+        std::env::set_var("SCRY_TEST_SIGNING_SECRET", "secret");
+        let signing = SigningConfig::Hmac {
+            secret_env: "SCRY_TEST_SIGNING_SECRET".to_string(),
+            header: "X-Signature".to_string(),
+            timestamp_header: "X-Signature-Timestamp".to_string(),
+        };
+        let request = reqwest::Client::new().get("http://localhost/v1/models?limit=5");
+        let request = sign(request, Some(&signing)).await.unwrap().build().unwrap();
+
+        let headers = request.headers();
+        let timestamp: i64 = headers["X-Signature-Timestamp"].to_str().unwrap().parse().unwrap();
+        let expected = hmac_signature("secret", timestamp, "GET", request.url(), b"");
+        assert_eq!(headers["X-Signature"], expected.as_str());
+        assert!(!headers.contains_key(CONTENT_TYPE));
+        assert!(request.body().is_none());
+
+        // Unsigned requests are left as they are
+        let request = reqwest::Client::new().get("http://localhost/v1/models");
+        let request = sign(request, None).await.unwrap().build().unwrap();
+        assert!(request.headers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_json_body_missing_secret_fails() {
+        let signing = SigningConfig::Hmac {
+            secret_env: "SCRY_TEST_SIGNING_SECRET_UNSET".to_string(),
+            header: "X-Signature".to_string(),
+            timestamp_header: "X-Signature-Timestamp".to_string(),
+        };
+        let request = reqwest::Client::new().post("http://localhost/test");
+        assert!(json_body(request, &serde_json::json!({}), Some(&signing))
+            .await
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_json_body_with_command() {
+        let signing = SigningConfig::Command {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "echo \"X-Method: $SCRY_REQUEST_METHOD\"".to_string(),
+            ],
+        };
+        let request = reqwest::Client::new().post("http://localhost/test");
+        let request = json_body(request, &serde_json::json!({}), Some(&signing))
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["X-Method"], "POST");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_may_print_before_reading_a_large_body() {
+        // Fills the output pipe before reading stdin
+        let signing = SigningConfig::Command {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "head -c 200000 /dev/zero | tr '\\0' '\\n'; echo \"X-Body-Bytes: $(wc -c)\""
+                    .to_string(),
+            ],
+        };
+        let body = serde_json::json!({ "text": "x".repeat(200_000) });
+        let request = reqwest::Client::new().post("http://localhost/test");
+        let request = json_body(request, &body, Some(&signing))
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        let bytes = serde_json::to_vec(&body).unwrap().len();
+        assert_eq!(request.headers()["X-Body-Bytes"], bytes.to_string().as_str());
+    }
+}
@@ -0,0 +1,339 @@
+//! Incremental Server-Sent Events parser shared by the streaming providers.
+//!
+//! Implements the parts of the SSE spec the providers rely on:
+//! - `event:`, `data:` and `id:` fields, with or without a space after the colon
+//! - multiple `data:` lines joined with `\n`
+//! - comment lines starting with `:` (keep-alives)
+//! - `\n`, `\r\n` and `\r` line endings
+//! - chunks that split lines, or UTF-8 characters, at arbitrary byte offsets
+//!
+//! [`Utf8Decoder`] provides the same boundary-safe decoding for providers that
+//! stream newline-delimited JSON instead of SSE.
+
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt};
+
+/// A dispatched SSE event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type from the `event:` field, if any
+    pub event: Option<String>,
+    /// Data lines joined with `\n`
+    pub data: String,
+    /// Last event ID from the `id:` field, if any
+    pub id: Option<String>,
+}
+
+/// Incremental SSE parser.
+///
+/// Feed raw bytes with [`SseParser::push`]; complete events are returned as
+/// soon as their terminating blank line arrives.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// Bytes of the current incomplete line
+    line: Vec<u8>,
+    /// Whether the previous chunk ended with `\r` (a following `\n` is part of it)
+    after_cr: bool,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseParser {
+    /// Create a new parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes, returning any events it completes.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if self.after_cr {
+                self.after_cr = false;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'\r' => {
+                    self.after_cr = true;
+                    self.end_line(&mut events);
+                }
+                b'\n' => self.end_line(&mut events),
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    /// Flush at end of stream.
+    ///
+    /// Providers don't always terminate the final event with a blank line, so
+    /// any pending line and event are dispatched rather than dropped.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut events = Vec::new();
+        if !self.line.is_empty() {
+            self.end_line(&mut events);
+        }
+        self.dispatch(&mut events);
+        events.pop()
+    }
+
+    fn end_line(&mut self, events: &mut Vec<SseEvent>) {
+        // Lines are only decoded once complete, so split characters are never garbled
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            // `retry:` and unknown fields are ignored
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return;
+        }
+        events.push(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        });
+    }
+}
+
+/// Incremental UTF-8 decoder for byte streams.
+///
+/// Multi-byte characters split across chunks are held back until complete
+/// instead of being replaced, so emoji and CJK text survive arbitrary chunking.
+/// Genuinely invalid bytes still become U+FFFD.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a chunk, returning all text that is complete so far.
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut out = String::new();
+
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(text) => {
+                    out.push_str(text);
+                    self.pending.clear();
+                    return out;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    out.push_str(&String::from_utf8_lossy(&self.pending[..valid]));
+                    match e.error_len() {
+                        // Invalid sequence: replace it and keep going
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + len);
+                        }
+                        // Incomplete character at the end: wait for more bytes
+                        None => {
+                            self.pending.drain(..valid);
+                            return out;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Flush any bytes left at end of stream.
+    pub fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        rest
+    }
+}
+
+/// Turn a byte stream into a stream of SSE events.
+///
+/// Errors from the underlying stream are passed through unchanged.
+pub fn events<S, B, E>(stream: S) -> impl Stream<Item = Result<SseEvent, E>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let state = (stream, SseParser::new(), VecDeque::new(), false);
+    futures::stream::unfold(state, |(mut stream, mut parser, mut pending, mut ended)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((Ok(event), (stream, parser, pending, ended)));
+            }
+            if ended {
+                return None;
+            }
+            match stream.next().await {
+                Some(Ok(chunk)) => pending.extend(parser.push(chunk.as_ref())),
+                Some(Err(e)) => return Some((Err(e), (stream, parser, pending, true))),
+                None => {
+                    ended = true;
+                    pending.extend(parser.finish());
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut parser = SseParser::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| parser.push(c)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
+    fn data(events: &[SseEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.data.as_str()).collect()
+    }
+
+    #[test]
+    fn test_named_events() {
+        let events = parse(&[b"event: ping\ndata: {}\n\nevent: message_stop\ndata: {\"a\":1}\n\n"]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("ping"));
+        assert_eq!(events[1].event.as_deref(), Some("message_stop"));
+        assert_eq!(events[1].data, "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_event_type_resets_between_events() {
+        let events = parse(&[b"event: a\ndata: 1\n\ndata: 2\n\n"]);
+        assert_eq!(events[1].event, None);
+    }
+
+    #[test]
+    fn test_multiline_data() {
+        let events = parse(&[b"data: first\ndata: second\n\n"]);
+        assert_eq!(data(&events), vec!["first\nsecond"]);
+    }
+
+    #[test]
+    fn test_crlf_and_cr_line_endings() {
+        let events = parse(&[b"data: a\r\n\r\ndata: b\r\rdata: c\n\n"]);
+        assert_eq!(data(&events), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_crlf_split_across_chunks() {
+        let events = parse(&[b"data: a\r", b"\n\r", b"\n"]);
+        assert_eq!(data(&events), vec!["a"]);
+    }
+
+    #[test]
+    fn test_comments_are_ignored() {
+        let events = parse(&[b": keep-alive\n\ndata: x\n\n: OPENROUTER PROCESSING\n\n"]);
+        assert_eq!(data(&events), vec!["x"]);
+    }
+
+    #[test]
+    fn test_no_space_after_colon() {
+        let events = parse(&[b"event:delta\ndata:{}\nid:7\n\n"]);
+        assert_eq!(events[0].event.as_deref(), Some("delta"));
+        assert_eq!(events[0].data, "{}");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let events = parse(&[b"da", b"ta: hel", b"lo\n", b"\n"]);
+        assert_eq!(data(&events), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_utf8_split_across_chunks() {
+        let bytes = "data: héllo 🦀\n\n".as_bytes();
+        // Split inside both multi-byte characters
+        let e_pos = bytes.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let crab_pos = bytes.iter().position(|&b| b == 0xF0).unwrap() + 2;
+        let events = parse(&[&bytes[..e_pos], &bytes[e_pos..crab_pos], &bytes[crab_pos..]]);
+        assert_eq!(data(&events), vec!["héllo 🦀"]);
+    }
+
+    #[test]
+    fn test_finish_flushes_unterminated_event() {
+        let events = parse(&[b"data: [DONE]"]);
+        assert_eq!(data(&events), vec!["[DONE]"]);
+    }
+
+    #[test]
+    fn test_empty_data_events_are_dropped() {
+        let events = parse(&[b"event: ping\n\n\n"]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_utf8_decoder_holds_back_split_characters() {
+        let bytes = "日本🦀".as_bytes();
+        let mut decoder = Utf8Decoder::new();
+        let mut text = String::new();
+        for byte in bytes {
+            text.push_str(&decoder.push(std::slice::from_ref(byte)));
+        }
+        text.push_str(&decoder.finish());
+        assert_eq!(text, "日本🦀");
+    }
+
+    #[test]
+    fn test_utf8_decoder_partial_output() {
+        let mut decoder = Utf8Decoder::new();
+        let crab = "🦀".as_bytes();
+        assert_eq!(decoder.push(b"ok "), "ok ");
+        assert_eq!(decoder.push(&crab[..2]), "");
+        assert_eq!(decoder.push(&crab[2..]), "🦀");
+    }
+
+    #[test]
+    fn test_utf8_decoder_replaces_invalid_bytes() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.push(b"a\xFFb"), "a\u{FFFD}b");
+        assert_eq!(decoder.push(&[0xF0, 0x9F]), "");
+        assert_eq!(decoder.finish(), "\u{FFFD}");
+    }
+
+    #[tokio::test]
+    async fn test_events_stream() {
+        let chunks = vec![
+            Ok::<_, std::convert::Infallible>(b"data: 1\n".to_vec()),
+            Ok(b"\ndata: 2".to_vec()),
+        ];
+        let events: Vec<_> = events(futures::stream::iter(chunks))
+            .map(|e| e.unwrap().data)
+            .collect()
+            .await;
+        assert_eq!(events, vec!["1", "2"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Ending streams that stall.
+//!
+//! A connection that dies without being closed leaves a streaming reply
+//! waiting for events that never come, and SSE keep-alive pings can keep the
+//! read timeout from ever firing. The watchdog relays a stream and ends it
+//! with an error once no event has arrived for the stall timeout, so the
+//! reply fails where it stands instead of streaming forever.
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use super::{backpressure, StreamEvent};
+
+/// Relay `source`, ending it with an error if it goes `stall` without an
+/// event. A zero `stall` passes `source` through unwatched.
+pub fn watch(
+    mut source: mpsc::Receiver<StreamEvent>,
+    stall: Duration,
+) -> mpsc::Receiver<StreamEvent> {
+    if stall.is_zero() {
+        return source;
+    }
+    let (tx, rx) = backpressure::handoff();
+    tokio::spawn(async move {
+        let mut limit = stall;
+        loop {
+            let next = tokio::select! {
+                next = tokio::time::timeout(limit, source.recv()) => next,
+                _ = tx.closed() => return,
+            };
+            let event = match next {
+                Ok(Some(event)) => event,
+                Ok(None) => return,
+                Err(_) => {
+                    let message = format!(
+                        "Stream stalled: nothing received for {}s",
+                        stall.as_secs_f64().round()
+                    );
+                    let _ = tx.send(StreamEvent::Error(message)).await;
+                    return;
+                }
+            };
+            // Waiting out a retry's delay is not a stall
+            limit = match &event {
+                StreamEvent::Retrying { delay, .. } => stall + *delay,
+                _ => stall,
+            };
+            if tx.send(event).await.is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STALL: Duration = Duration::from_millis(200);
+
+    #[tokio::test]
+    async fn test_a_silent_stream_ends_with_an_error() {
+        let (tx, source) = mpsc::channel(4);
+        let mut rx = watch(source, STALL);
+        tx.send(StreamEvent::Token("Hel".to_string())).await.unwrap();
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Token(t)) if t == "Hel"));
+
+        // The provider is still connected but sends nothing more
+        match rx.recv().await {
+            Some(StreamEvent::Error(message)) => {
+                assert!(message.starts_with("Stream stalled"), "{}", message)
+            }
+            other => panic!("expected a stall error, got {:?}", other),
+        }
+        assert!(rx.recv().await.is_none());
+        assert!(tx.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_steady_streams_and_retry_delays_pass() {
+        let (tx, source) = mpsc::channel(4);
+        let mut rx = watch(source, STALL);
+        tokio::spawn(async move {
+            tx.send(StreamEvent::Retrying {
+                attempt: 1,
+                max_retries: 3,
+                delay: STALL * 2,
+                reason: "429".to_string(),
+            })
+            .await
+            .unwrap();
+            tokio::time::sleep(STALL * 2).await;
+            for token in ["a", "b", "c"] {
+                tx.send(StreamEvent::Token(token.to_string())).await.unwrap();
+                tokio::time::sleep(STALL / 5).await;
+            }
+            tx.send(StreamEvent::Done).await.unwrap();
+        });
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 5, "{:?}", events);
+        assert!(matches!(events[4], StreamEvent::Done));
+    }
+
+    #[tokio::test]
+    async fn test_zero_disables_the_watchdog() {
+        let (tx, source) = mpsc::channel(1);
+        let mut rx = watch(source, Duration::ZERO);
+        tokio::time::sleep(STALL * 2).await;
+        tx.send(StreamEvent::Done).await.unwrap();
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Done)));
+    }
+}
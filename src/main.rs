@@ -4,44 +4,170 @@
 
 use std::io;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::Parser;
 use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 use scry_cli::app::App;
+use scry_cli::cli::{self, AuthCommand, Cli, Command};
 use scry_cli::config::Config;
+use scry_cli::indexer::Indexer;
 use scry_cli::input;
+use scry_cli::profiles;
+use scry_cli::profiling::CountingAllocator;
+use scry_cli::session;
+use scry_cli::sync;
+use scry_cli::usage::UsageLedger;
 use scry_cli::welcome;
 
+/// Counts allocations for the profiling overlay (F12).
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Cli::parse();
+    profiles::set_active(args.profile.as_deref())?;
+
     // Load configuration
-    let config = Config::load();
+    let mut config = Config::load();
+    if let Some(dir) = args.record {
+        config.llm.record_dir = Some(dir);
+    }
+    let project = Config::project_path().filter(|path| match Config::load_project(path) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Warning: ignoring project settings: {:#}", e);
+            false
+        }
+    });
+
+    // Non-interactive subcommands never touch the terminal UI
+    let mut guest = None;
+    let replay = match args.command {
+        Some(Command::Ask(ask_args)) => {
+            return cli::ask::run(ask_args, &config, args.dry_run).await
+        }
+        Some(Command::Filter(filter_args)) => {
+            return cli::filter::run(filter_args, &config, args.dry_run).await
+        }
+        Some(Command::Rewrite(rewrite_args)) => {
+            return cli::rewrite::run(rewrite_args, &config, args.dry_run).await
+        }
+        Some(Command::Usage(usage_args)) => return cli::usage::run(usage_args),
+        Some(Command::Audit(audit_args)) => return cli::audit::run(audit_args),
+        Some(Command::Auth(auth_args)) => {
+            return match auth_args.command {
+                AuthCommand::Login(login_args) => cli::auth::login(login_args, &config).await,
+                AuthCommand::Status => cli::auth::status(&config),
+                AuthCommand::Logout(logout_args) => cli::auth::logout(logout_args),
+            }
+        }
+        Some(Command::Models(models_args)) => return cli::models::list(models_args, &config).await,
+        Some(Command::Use(use_args)) => return cli::models::use_model(use_args, &config).await,
+        Some(Command::Login(login_args)) => return cli::auth::login(login_args, &config).await,
+        Some(Command::Logout(logout_args)) => return cli::auth::logout(logout_args),
+        Some(Command::Serve(serve_args)) => return cli::serve::run(serve_args, &config).await,
+        Some(Command::Replay(replay_args)) => Some(cli::replay::load(&replay_args)?),
+        Some(Command::Attach(attach_args)) => {
+            guest = Some(cli::attach::connect(&attach_args, &config).await?);
+            None
+        }
+        None => None,
+    };
+
+    // Check the overrides for this run before the terminal is taken over
+    let llm_override = args.run.resolve(&config)?;
+    let resumed = match &args.run.session {
+        Some(id) => Some(
+            session::open_store(&config.storage)
+                .and_then(|store| store.load(id))
+                .with_context(|| format!("Could not resume session {}", id))?,
+        ),
+        None => None,
+    };
 
     // Show welcome screen with TTE effects (if available)
-    if let Err(e) = welcome::show_welcome(&config.welcome) {
-        eprintln!("Warning: Welcome screen failed: {}", e);
+    if replay.is_none() && guest.is_none() {
+        if let Err(e) = welcome::show_welcome(&config.welcome) {
+            eprintln!("Warning: Welcome screen failed: {}", e);
+        }
     }
 
     // Setup terminal for TUI
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    if config.behavior.mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+    // Tell Ctrl+M apart from Enter where the terminal can
+    let enhanced_keys = supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app (without the old banner since we showed TTE welcome)
     let mut app = App::new_without_banner_with_config(&config);
+    app.llm.dry_run = args.dry_run;
+    if let Some(llm_config) = llm_override {
+        app.llm.config = llm_config;
+        app.llm.apply_config();
+    }
+    if let Some(system) = args.run.system {
+        app.llm.system_prompt = Some(system);
+    }
+    if let Some(path) = project {
+        app.toast_info(format!("Using project settings from {}", path.display()));
+    }
+    if let Some(client) = replay {
+        cli::replay::attach(&mut app, client);
+    } else if let Some(guest) = guest {
+        // The host saves and pays for the shared conversation
+        cli::attach::attach(&mut app, guest);
+    } else {
+        // Replays are not real requests, so they stay out of the ledger
+        app.usage = UsageLedger::open_default().ok();
+        app.llm.token_refresh.enabled = true;
+        app.sessions = session::open_store(&config.storage).ok();
+        app.indexer = app.sessions.clone().and_then(|store| Indexer::spawn(store).ok());
+        if let Some(session) = resumed {
+            app.load_session(session);
+        }
+    }
 
     // Run app
-    let res = input::run_app(&mut terminal, &mut app, &config);
+    let res = input::run_app(&mut terminal, &mut app, &config).await;
 
     // Restore terminal
+    if enhanced_keys {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    if config.behavior.mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableBracketedPaste
+    )?;
     terminal.show_cursor()?;
 
     // Handle any errors
@@ -49,5 +175,25 @@ async fn main() -> Result<()> {
         eprintln!("Error: {:?}", err);
     }
 
+    if args.frame_stats {
+        eprintln!("{}", app.frames.summary());
+    }
+
+    // Keep the conversation so it can be resumed next time
+    if let Err(err) = app.save_session() {
+        eprintln!("Warning: Could not save session: {}", err);
+    }
+
+    if config.sync.on_exit && config.sync.backend.is_some() {
+        if let Some(store) = app.sessions.clone() {
+            eprintln!("Syncing sessions…");
+            let sync_config = config.sync.clone();
+            match tokio::task::spawn_blocking(move || sync::run(&sync_config, store.as_ref())).await? {
+                Ok(report) => eprintln!("Synced: {}", report),
+                Err(err) => eprintln!("Warning: Could not sync sessions: {:#}", err),
+            }
+        }
+    }
+
     Ok(())
 }
@@ -1,5 +1,14 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::image::Image;
+use crate::llm::{FinishReason, ProviderError, ReasoningEffort, TokenUsage, ToolCall};
+use crate::routing::Route;
+
 /// Represents who sent a message in the chat.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Role {
     User,
     Assistant,
@@ -23,6 +32,55 @@ pub enum MessageType {
     Chat,
     /// System banner (welcome message, not sent to LLM)
     SystemBanner,
+    /// Note about the conversation itself, e.g. a model change (not sent to LLM)
+    SystemNote,
+    /// A failed request, shown as an error card (not sent to LLM)
+    Error,
+    /// What a tool call returned (sent to LLM, shown as a note)
+    ToolResult,
+}
+
+/// Details of a failed request shown in an error card.
+#[derive(Clone, Debug)]
+pub struct ErrorCard {
+    /// Classified error
+    pub error: ProviderError,
+    /// Display name of the provider that failed
+    pub provider: String,
+}
+
+/// The answer to one of the model's tool calls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolResult {
+    /// Id of the call it answers
+    pub call_id: String,
+    /// The tool's output, or why it did not run
+    pub output: String,
+}
+
+/// How a reply was produced: its model, timing and token usage.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplyMeta {
+    /// Model that wrote the reply
+    pub model: String,
+    /// Time from sending the request to the first streamed token
+    pub first_token: Option<Duration>,
+    /// Time from sending the request to the end of the reply, continuations included
+    pub duration: Option<Duration>,
+    /// Tokens the provider reported, continuations included
+    pub usage: Option<TokenUsage>,
+    /// Cost in USD, if the model's price is known
+    pub cost: Option<f64>,
+}
+
+impl ReplyMeta {
+    /// Metadata for a reply about to be requested from `model`.
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            ..Self::default()
+        }
+    }
 }
 
 /// A single message in the chat history.
@@ -31,6 +89,30 @@ pub struct Message {
     pub role: Role,
     pub content: String,
     pub message_type: MessageType,
+    /// Error details, for error cards
+    pub error: Option<ErrorCard>,
+    /// Why the provider stopped generating this reply, if it said
+    pub finish: Option<FinishReason>,
+    /// Waiting to be sent until the connection returns
+    pub queued: bool,
+    /// Model the `auto` model picked for this reply
+    pub route: Option<Route>,
+    /// Reasoning effort the reply was requested with
+    pub reasoning: Option<ReasoningEffort>,
+    /// Reasoning the model streamed before its answer (never sent back)
+    pub thinking: String,
+    /// Participant who sent the prompt, in a shared session
+    pub author: Option<String>,
+    /// Kept in the request when older messages no longer fit the context
+    pub pinned: bool,
+    /// Images sent with the prompt (`/attach`)
+    pub images: Vec<Image>,
+    /// Model, timing and token usage of a reply
+    pub meta: Option<ReplyMeta>,
+    /// Tools the model called in this reply
+    pub tool_calls: Vec<ToolCall>,
+    /// Output sent back for a tool call, for tool results
+    pub tool_result: Option<ToolResult>,
 }
 
 impl Message {
@@ -40,6 +122,18 @@ impl Message {
             role,
             content,
             message_type: MessageType::Chat,
+            error: None,
+            finish: None,
+            queued: false,
+            route: None,
+            reasoning: None,
+            thinking: String::new(),
+            author: None,
+            pinned: false,
+            images: Vec::new(),
+            meta: None,
+            tool_calls: Vec::new(),
+            tool_result: None,
         }
     }
 
@@ -59,6 +153,76 @@ impl Message {
             role: Role::Assistant,
             content,
             message_type: MessageType::SystemBanner,
+            error: None,
+            finish: None,
+            queued: false,
+            route: None,
+            reasoning: None,
+            thinking: String::new(),
+            author: None,
+            pinned: false,
+            images: Vec::new(),
+            meta: None,
+            tool_calls: Vec::new(),
+            tool_result: None,
+        }
+    }
+
+    /// Create a system note (not sent to LLM).
+    pub fn system_note(content: String) -> Self {
+        Self {
+            role: Role::Assistant,
+            content,
+            message_type: MessageType::SystemNote,
+            error: None,
+            finish: None,
+            queued: false,
+            route: None,
+            reasoning: None,
+            thinking: String::new(),
+            author: None,
+            pinned: false,
+            images: Vec::new(),
+            meta: None,
+            tool_calls: Vec::new(),
+            tool_result: None,
+        }
+    }
+
+    /// Create an error card for a failed request (not sent to LLM).
+    pub fn error(error: ProviderError, provider: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: error.message().to_string(),
+            message_type: MessageType::Error,
+            error: Some(ErrorCard {
+                error,
+                provider: provider.into(),
+            }),
+            finish: None,
+            queued: false,
+            route: None,
+            reasoning: None,
+            thinking: String::new(),
+            author: None,
+            pinned: false,
+            images: Vec::new(),
+            meta: None,
+            tool_calls: Vec::new(),
+            tool_result: None,
+        }
+    }
+
+    /// Record what a tool call returned: `note` is shown in the conversation,
+    /// `output` is sent back to the model.
+    pub fn tool_result(call_id: impl Into<String>, note: String, output: String) -> Self {
+        Self {
+            message_type: MessageType::ToolResult,
+            tool_result: Some(ToolResult {
+                call_id: call_id.into(),
+                output,
+            }),
+            ..Self::system_note(note)
         }
     }
 
@@ -66,4 +230,125 @@ impl Message {
     pub fn is_system_banner(&self) -> bool {
         self.message_type == MessageType::SystemBanner
     }
+
+    /// Returns true if this is a system note.
+    pub fn is_system_note(&self) -> bool {
+        self.message_type == MessageType::SystemNote
+    }
+
+    /// Returns true if this is an error card.
+    pub fn is_error(&self) -> bool {
+        self.message_type == MessageType::Error
+    }
+
+    /// Returns true if the reply stopped early, at the token limit, a content
+    /// filter or a dropped connection.
+    pub fn is_cut_short(&self) -> bool {
+        matches!(
+            self.finish,
+            Some(FinishReason::Length | FinishReason::ContentFilter | FinishReason::Interrupted)
+        )
+    }
+
+    /// Returns true if this message is part of the conversation sent to the LLM.
+    pub fn is_chat(&self) -> bool {
+        self.message_type == MessageType::Chat
+    }
+
+    /// Returns true if this is a tool's output for the model.
+    pub fn is_tool_result(&self) -> bool {
+        self.message_type == MessageType::ToolResult
+    }
+
+    /// Returns true if this message is sent to the LLM: a chat message or a
+    /// tool result.
+    pub fn is_sent(&self) -> bool {
+        self.is_chat() || self.is_tool_result()
+    }
+
+    /// The text sent to the LLM: a tool's output rather than the note shown for it.
+    pub fn sent_text(&self) -> &str {
+        self.tool_result
+            .as_ref()
+            .map_or(&self.content, |result| &result.output)
+    }
+}
+
+/// Source of [`Messages`] revisions, unique across the process.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The conversation's messages, with a revision that changes whenever they may have.
+///
+/// Reads go through `Deref`; every mutable borrow counts as a change, so
+/// watchers such as live sharing can compare revisions instead of contents.
+/// Revisions are never reused, so a replaced list never looks unchanged.
+#[derive(Clone, Debug)]
+pub struct Messages {
+    list: Vec<Message>,
+    revision: u64,
+}
+
+impl Messages {
+    /// Equal revisions mean the messages are unchanged.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+impl From<Vec<Message>> for Messages {
+    fn from(list: Vec<Message>) -> Self {
+        Self {
+            list,
+            revision: next_revision(),
+        }
+    }
+}
+
+impl FromIterator<Message> for Messages {
+    fn from_iter<I: IntoIterator<Item = Message>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<'a> IntoIterator for &'a Messages {
+    type Item = &'a Message;
+    type IntoIter = std::slice::Iter<'a, Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Messages {
+    type Item = &'a mut Message;
+    type IntoIter = std::slice::IterMut<'a, Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl std::ops::Deref for Messages {
+    type Target = Vec<Message>;
+
+    fn deref(&self) -> &Vec<Message> {
+        &self.list
+    }
+}
+
+impl std::ops::DerefMut for Messages {
+    fn deref_mut(&mut self) -> &mut Vec<Message> {
+        self.revision = next_revision();
+        &mut self.list
+    }
 }
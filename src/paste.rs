@@ -0,0 +1,251 @@
+//! Pasted text handling: dropped files become `@path` chips.
+//!
+//! Most terminals turn a file dropped onto the window into a pasted path,
+//! quoted or shell-escaped (`'/tmp/my file.txt'`, `/tmp/my\ file.txt`) or as
+//! a `file://` URL. Such pastes, and pasted paths like `src/main.rs`, can be
+//! attached as `@path` chips in the input once every path is confirmed to
+//! exist; the chat asks first, since a path may be meant as text.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// What a paste turned out to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pasted {
+    /// Dropped files, converted to chips
+    Files(Vec<PathBuf>),
+    /// Looked like dropped files, but this path does not exist
+    Missing(PathBuf),
+    /// Ordinary text
+    Text(String),
+}
+
+/// Split pasted text into shell-style words, honoring quotes and backslash escapes.
+fn shell_words(text: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => word.push(chars.next()?),
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                word.push(chars.next()?);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+/// Turn a dropped or pasted word into a path, or `None` if it does not look
+/// like one. Relative paths need a directory part, so plain words never are.
+fn as_path(word: &str) -> Option<PathBuf> {
+    if let Some(rest) = word.strip_prefix("file://") {
+        let decoded = urlencoding::decode(rest).ok()?;
+        return Some(PathBuf::from(decoded.into_owned()));
+    }
+    if let Some(rest) = word.strip_prefix("~/") {
+        return dirs::home_dir().map(|home| home.join(rest));
+    }
+    let path = Path::new(word);
+    (path.is_absolute() || path.components().count() > 1).then(|| PathBuf::from(word))
+}
+
+/// Classify pasted text.
+pub fn classify(text: &str) -> Pasted {
+    let text_only = || Pasted::Text(text.replace('\r', ""));
+    if text.trim().is_empty() || text.trim().contains('\n') {
+        return text_only();
+    }
+    let Some(words) = shell_words(text.trim()) else {
+        return text_only();
+    };
+    let Some(paths) = words.iter().map(|w| as_path(w)).collect::<Option<Vec<_>>>() else {
+        return text_only();
+    };
+    if paths.is_empty() {
+        return text_only();
+    }
+    // Only quoting or a URL marks a paste as a drop; a bare `/word` may just be text
+    let looks_dropped = text.contains(['\'', '"', '\\']) || text.contains("file://");
+    match paths.iter().find(|p| !p.is_file()) {
+        Some(missing) if looks_dropped => Pasted::Missing(missing.clone()),
+        Some(_) => text_only(),
+        None => Pasted::Files(paths),
+    }
+}
+
+/// Format a path as an `@path` chip, quoting it if it contains whitespace.
+pub fn chip(path: &Path) -> String {
+    let path = path.display().to_string();
+    if path.contains(char::is_whitespace) {
+        format!("@\"{}\"", path)
+    } else {
+        format!("@{}", path)
+    }
+}
+
+/// Byte ranges of `@path` chips in the input, for highlighting.
+pub fn chip_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut rest = 0;
+    while let Some(offset) = text[rest..].find('@') {
+        let start = rest + offset;
+        let starts_word = text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let body = &text[start + 1..];
+        let len = if let Some(quoted) = body.strip_prefix('"') {
+            quoted.find('"').map(|end| end + 2)
+        } else {
+            Some(body.find(char::is_whitespace).unwrap_or(body.len()))
+        };
+        match len {
+            Some(len) if starts_word && len > 0 => {
+                ranges.push(start..start + 1 + len);
+                rest = start + 1 + len;
+            }
+            _ => rest = start + 1,
+        }
+    }
+    ranges
+}
+
+/// The path an `@path` chip refers to.
+pub fn chip_path(chip: &str) -> PathBuf {
+    let body = chip.strip_prefix('@').unwrap_or(chip);
+    let body = body
+        .strip_prefix('"')
+        .and_then(|b| b.strip_suffix('"'))
+        .unwrap_or(body);
+    PathBuf::from(body)
+}
+
+/// Put `chips` in place of the chips in `text`, in order; chips beyond the
+/// new list are removed along with the space after them.
+pub fn replace_chips(text: &str, chips: &[String]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (i, range) in chip_ranges(text).into_iter().enumerate() {
+        out.push_str(&text[last..range.start]);
+        last = range.end;
+        match chips.get(i) {
+            Some(chip) => out.push_str(chip),
+            None if text[last..].starts_with(' ') => last += 1,
+            None => {}
+        }
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_quoted_and_escaped_drops_become_chips() {
+        let dir = tempfile::tempdir().unwrap();
+        let spaced = dir.path().join("my notes.txt");
+        let plain = dir.path().join("plain.rs");
+        fs::write(&spaced, "x").unwrap();
+        fs::write(&plain, "x").unwrap();
+
+        let quoted = format!("'{}'", spaced.display());
+        assert_eq!(classify(&quoted), Pasted::Files(vec![spaced.clone()]));
+
+        let escaped = spaced.display().to_string().replace(' ', "\\ ");
+        assert_eq!(classify(&escaped), Pasted::Files(vec![spaced.clone()]));
+
+        let url = format!(
+            "file://{}",
+            spaced.display().to_string().replace(' ', "%20")
+        );
+        assert_eq!(classify(&url), Pasted::Files(vec![spaced.clone()]));
+
+        let both = format!("{} '{}' ", plain.display(), spaced.display());
+        assert_eq!(classify(&both), Pasted::Files(vec![plain, spaced]));
+    }
+
+    #[test]
+    fn test_relative_paths_of_existing_files() {
+        // Tests run from the crate root
+        assert_eq!(
+            classify("src/paste.rs"),
+            Pasted::Files(vec![PathBuf::from("src/paste.rs")])
+        );
+        assert_eq!(classify("and/or"), Pasted::Text("and/or".to_string()));
+        assert_eq!(classify("Cargo.toml"), Pasted::Text("Cargo.toml".to_string()));
+    }
+
+    #[test]
+    fn test_missing_and_ordinary_text() {
+        assert_eq!(
+            classify("'/definitely/not/here.txt'"),
+            Pasted::Missing(PathBuf::from("/definitely/not/here.txt"))
+        );
+        assert_eq!(
+            classify("hello world"),
+            Pasted::Text("hello world".to_string())
+        );
+        assert_eq!(classify("it's"), Pasted::Text("it's".to_string()));
+        assert_eq!(classify("/local"), Pasted::Text("/local".to_string()));
+        assert_eq!(classify("/a\r\n/b"), Pasted::Text("/a\n/b".to_string()));
+    }
+
+    #[test]
+    fn test_chips_round_trip_through_ranges() {
+        let input = format!(
+            "see {} and {} ok a@b",
+            chip(Path::new("/tmp/a.rs")),
+            chip(Path::new("/tmp/my file.txt"))
+        );
+        assert_eq!(input, "see @/tmp/a.rs and @\"/tmp/my file.txt\" ok a@b");
+        let chips: Vec<&str> = chip_ranges(&input).into_iter().map(|r| &input[r]).collect();
+        assert_eq!(chips, vec!["@/tmp/a.rs", "@\"/tmp/my file.txt\""]);
+    }
+
+    #[test]
+    fn test_replace_chips_reorders_and_removes() {
+        let text = "compare @a.rs with @\"b c.rs\" and @d.rs please";
+        let chips: Vec<String> = chip_ranges(text)
+            .into_iter()
+            .map(|r| text[r].to_string())
+            .collect();
+        assert_eq!(chip_path(&chips[1]), PathBuf::from("b c.rs"));
+
+        let reordered = vec![chips[2].clone(), chips[0].clone()];
+        assert_eq!(
+            replace_chips(text, &reordered),
+            "compare @d.rs with @a.rs and please"
+        );
+        assert_eq!(replace_chips(text, &[]), "compare with and please");
+    }
+}
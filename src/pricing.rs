@@ -0,0 +1,202 @@
+//! Model prices, for the cost of each request.
+//!
+//! Prices are per million input and output tokens, in USD, from the
+//! providers' published rates. A model is priced by the longest name in the
+//! table it starts with, so dated snapshots (`claude-sonnet-4-20250514`)
+//! share their family's price, and OpenRouter's `vendor/` prefix is ignored.
+//! Dots in versions match dashes, as OpenRouter writes `claude-sonnet-4.5`
+//! for Anthropic's `claude-sonnet-4-5`.
+//! `[pricing]` in the config adds models or overrides these prices. Local
+//! models are free; anything else not listed has no known cost.
+
+use std::collections::BTreeMap;
+
+use crate::config::ModelPrice;
+use crate::llm::{Provider, TokenUsage};
+
+/// Built-in prices: model name prefix, input and output USD per million tokens.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-5-pro", 15.0, 120.0),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("o1", 15.0, 60.0),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o3-pro", 20.0, 80.0),
+    ("o4-mini", 1.1, 4.4),
+    ("deepseek-chat", 0.28, 0.42),
+    ("deepseek-reasoner", 0.28, 0.42),
+    ("llama-3.3-70b-versatile", 0.59, 0.79),
+    ("llama-3.1-8b-instant", 0.05, 0.08),
+];
+
+/// The built-in prices with the config's `[pricing]` over them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pricing {
+    overrides: BTreeMap<String, ModelPrice>,
+}
+
+impl Pricing {
+    /// Prices with `overrides` (keyed by model name or prefix) taking precedence.
+    pub fn new(overrides: &BTreeMap<String, ModelPrice>) -> Self {
+        Self {
+            overrides: overrides
+                .iter()
+                .map(|(model, price)| (normalize(model), *price))
+                .collect(),
+        }
+    }
+
+    /// Price of `model` on `provider`, if known. The longest matching name
+    /// wins, an override over a built-in name of the same length.
+    pub fn price(&self, provider: Provider, model: &str) -> Option<ModelPrice> {
+        let model = normalize(model);
+        let id = model.rsplit('/').next().unwrap_or(&model);
+        // An override may name the model with or without its vendor prefix
+        let overridden = self
+            .overrides
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()) || id.starts_with(name.as_str()))
+            .map(|(name, price)| (name.len(), *price))
+            .max_by_key(|(len, _)| *len);
+        let builtin = match provider {
+            Provider::Ollama | Provider::LocalServer => Some((0, ModelPrice::default())),
+            // Copilot is a subscription; requests have no per-token price
+            Provider::GitHubCopilot => None,
+            _ => PRICES
+                .iter()
+                .filter(|(name, _, _)| id.starts_with(&normalize(name)))
+                .map(|&(name, input, output)| (name.len(), ModelPrice { input, output }))
+                .max_by_key(|(len, _)| *len),
+        };
+        match (overridden, builtin) {
+            (Some(o), Some(b)) if b.0 > o.0 => Some(b.1),
+            (Some(o), _) => Some(o.1),
+            (None, b) => b.map(|(_, price)| price),
+        }
+    }
+
+    /// Cost in USD of a request that used `usage`, if the model's price is known.
+    pub fn cost(&self, provider: Provider, model: &str, usage: TokenUsage) -> Option<f64> {
+        self.price(provider, model).map(|price| {
+            (usage.prompt_tokens as f64 * price.input
+                + usage.completion_tokens as f64 * price.output)
+                / 1_000_000.0
+        })
+    }
+}
+
+/// `name` lowercased with dots as dashes, the form names are compared in.
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace('.', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix_prices_a_model() {
+        let pricing = Pricing::default();
+        let price = |provider, model| pricing.price(provider, model).map(|p| (p.input, p.output));
+        assert_eq!(
+            price(Provider::Anthropic, "claude-sonnet-4-20250514"),
+            Some((3.0, 15.0))
+        );
+        assert_eq!(price(Provider::Anthropic, "claude-opus-4-5"), Some((5.0, 25.0)));
+        assert_eq!(price(Provider::Anthropic, "claude-opus-4-1"), Some((15.0, 75.0)));
+        assert_eq!(price(Provider::OpenAI, "gpt-4o-mini-2024-07-18"), Some((0.15, 0.6)));
+        assert_eq!(price(Provider::OpenRouter, "openai/gpt-4o"), Some((2.5, 10.0)));
+        assert_eq!(price(Provider::OpenRouter, "mistralai/mistral-large"), None);
+        assert_eq!(price(Provider::GitHubCopilot, "gpt-4o"), None);
+        assert_eq!(price(Provider::Ollama, "llama3"), Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_pro_models_and_dotted_versions() {
+        let pricing = Pricing::default();
+        let price = |provider, model| pricing.price(provider, model).map(|p| (p.input, p.output));
+        assert_eq!(price(Provider::OpenAI, "o3-pro-2025-06-10"), Some((20.0, 80.0)));
+        assert_eq!(price(Provider::OpenAI, "o3-2025-04-16"), Some((2.0, 8.0)));
+        assert_eq!(price(Provider::OpenAI, "gpt-5-pro"), Some((15.0, 120.0)));
+        assert_eq!(price(Provider::OpenRouter, "openai/gpt-5-pro"), Some((15.0, 120.0)));
+
+        // OpenRouter writes versions with dots
+        assert_eq!(
+            price(Provider::OpenRouter, "anthropic/claude-sonnet-4.5"),
+            Some((3.0, 15.0))
+        );
+        assert_eq!(
+            price(Provider::OpenRouter, "anthropic/claude-opus-4.5"),
+            Some((5.0, 25.0))
+        );
+        assert_eq!(
+            price(Provider::OpenRouter, "anthropic/claude-3.5-sonnet"),
+            Some((3.0, 15.0))
+        );
+        assert_eq!(
+            price(Provider::OpenRouter, "anthropic/claude-3.5-haiku"),
+            Some((0.8, 4.0))
+        );
+        assert_eq!(price(Provider::OpenAI, "gpt-4.1-mini"), Some((0.4, 1.6)));
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("gemini-2.5-pro".to_string(), ModelPrice { input: 1.25, output: 10.0 });
+        let pricing = Pricing::new(&overrides);
+        assert_eq!(
+            pricing.price(Provider::OpenRouter, "google/gemini-2.5-pro").map(|p| p.output),
+            Some(10.0)
+        );
+    }
+
+    #[test]
+    fn test_costs_and_overrides() {
+        let pricing = Pricing::default();
+        let cost = pricing
+            .cost(Provider::Anthropic, "claude-sonnet-4", usage(1_000_000, 100_000))
+            .unwrap();
+        assert!((cost - 4.5).abs() < 1e-9, "{}", cost);
+        assert_eq!(pricing.cost(Provider::LocalServer, "phi", usage(500, 500)), Some(0.0));
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("Mistral-Large".to_string(), ModelPrice { input: 2.0, output: 6.0 });
+        overrides.insert("gpt-4o".to_string(), ModelPrice { input: 1.0, output: 1.0 });
+        overrides.insert("llama3".to_string(), ModelPrice { input: 0.1, output: 0.1 });
+        let pricing = Pricing::new(&overrides);
+        assert_eq!(
+            pricing.cost(Provider::OpenRouter, "mistralai/mistral-large", usage(1_000_000, 0)),
+            Some(2.0)
+        );
+        assert_eq!(
+            pricing.cost(Provider::OpenAI, "gpt-4o-2024-11-20", usage(0, 1_000_000)),
+            Some(1.0)
+        );
+        // A longer built-in name is more specific than the override
+        assert_eq!(
+            pricing.cost(Provider::OpenAI, "gpt-4o-mini", usage(0, 1_000_000)),
+            Some(0.6)
+        );
+        assert_eq!(pricing.cost(Provider::Ollama, "llama3:8b", usage(0, 1_000_000)), Some(0.1));
+    }
+}
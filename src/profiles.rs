@@ -0,0 +1,138 @@
+//! Named profiles (`scry --profile work`).
+//!
+//! A profile keeps its own config file and credentials, so work and personal
+//! accounts can use different providers, models, system prompts and sign-ins.
+//! Its files live in a `profiles/<name>/` directory next to the main ones:
+//! `config.toml` under the config directory, `auth.json` (and its audit log)
+//! under the data directory. The profile's config file is layered over the
+//! main one, so it only needs the settings that differ; its credentials are
+//! its own. Without a profile scry uses the main files. Sessions and the
+//! usage ledger are shared by all profiles.
+
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::{bail, Result};
+
+/// Name of the directory holding the profiles.
+const PROFILES_DIR: &str = "profiles";
+
+/// The profile this process uses, if any.
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+/// The profile in use, or `None` for the main files.
+pub fn active() -> Option<String> {
+    ACTIVE.read().ok().and_then(|active| active.clone())
+}
+
+/// Use the profile `name` from now on, or the main files for `None`.
+pub fn set_active(name: Option<&str>) -> Result<()> {
+    if let Some(name) = name {
+        validate(name)?;
+    }
+    if let Ok(mut active) = ACTIVE.write() {
+        *active = name.map(str::to_string);
+    }
+    Ok(())
+}
+
+/// Check that `name` can name a profile directory.
+pub fn validate(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// `dir`, scry's config or data directory, for the active profile.
+pub fn scoped(dir: PathBuf) -> PathBuf {
+    match active() {
+        Some(name) => dir.join(PROFILES_DIR).join(name),
+        None => dir,
+    }
+}
+
+/// Profiles with files in any of `dirs` (scry's config and data
+/// directories), sorted by name.
+pub fn list_in(dirs: &[PathBuf]) -> Vec<String> {
+    let mut names: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir.join(PROFILES_DIR)).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate(name).is_ok())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Profiles that have a config file or credentials.
+pub fn list() -> Vec<String> {
+    let dirs: Vec<PathBuf> = [dirs::config_dir(), dirs::data_local_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("scry-cli"))
+        .collect();
+    list_in(&dirs)
+}
+
+/// Layer `overlay` over `base`: tables are merged key by key, anything else
+/// in `overlay` replaces what `base` has.
+pub fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_merge_layers_tables() {
+        let mut base: toml::Table =
+            toml::from_str("[llm]\nmodel = \"a\"\ntemperature = 0.5\n[behavior]\nminimap = true\n")
+                .unwrap();
+        let overlay: toml::Table =
+            toml::from_str("[llm]\nmodel = \"b\"\n[llm.models]\nopenai = \"gpt-4o\"\n").unwrap();
+        merge(&mut base, overlay);
+
+        assert_eq!(base["llm"]["model"].as_str(), Some("b"));
+        assert_eq!(base["llm"]["temperature"].as_float(), Some(0.5));
+        assert_eq!(base["llm"]["models"]["openai"].as_str(), Some("gpt-4o"));
+        assert_eq!(base["behavior"]["minimap"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_names_and_listing() {
+        assert!(validate("work").is_ok());
+        assert!(validate("side-project_2").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("../etc").is_err());
+
+        let config = TempDir::new().unwrap();
+        let data = TempDir::new().unwrap();
+        std::fs::create_dir_all(config.path().join("profiles/work")).unwrap();
+        std::fs::create_dir_all(data.path().join("profiles/work")).unwrap();
+        std::fs::create_dir_all(data.path().join("profiles/personal")).unwrap();
+        std::fs::write(config.path().join("profiles/notes.txt"), "").unwrap();
+        let dirs = [config.path().to_path_buf(), data.path().to_path_buf()];
+        assert_eq!(list_in(&dirs), ["personal", "work"]);
+    }
+}
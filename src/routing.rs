@@ -0,0 +1,153 @@
+//! The `auto` model: pick a cheap or strong model for each prompt.
+//!
+//! Short, plain prompts go to the provider's fast model; long prompts, prompts
+//! with code, and prompts sent with `/think` on go to the strong one.
+//! Both models can be overridden in `[llm.auto]`.
+
+use crate::app::estimate_tokens;
+use crate::config::AutoModelConfig;
+use crate::llm::Provider;
+
+/// Model name that turns on routing.
+pub const AUTO_MODEL: &str = "auto";
+
+/// Which kind of model a prompt needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// Fast and inexpensive
+    Cheap,
+    /// The provider's most capable default
+    Strong,
+    /// Picked by hand for one reply (`/retry <model>`)
+    Chosen,
+}
+
+/// The model chosen for one request, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub model: String,
+    pub tier: Tier,
+    pub reason: &'static str,
+}
+
+impl Route {
+    /// A reply on `model`, whatever the configured model.
+    pub fn chosen(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            tier: Tier::Chosen,
+            reason: "chosen",
+        }
+    }
+}
+
+/// Whether `model` asks for routing.
+pub fn is_auto(model: &str) -> bool {
+    model.eq_ignore_ascii_case(AUTO_MODEL)
+}
+
+/// Whether `text` contains a fenced block or lines that read like code.
+fn has_code(text: &str) -> bool {
+    if text.contains("```") {
+        return true;
+    }
+    let code_lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            line.ends_with(';')
+                || line.ends_with('{')
+                || line.starts_with("fn ")
+                || line.starts_with("def ")
+                || line.starts_with("import ")
+                || line.starts_with("#include")
+        })
+        .count();
+    code_lines >= 2
+}
+
+/// Decide which tier `prompt` needs, and why.
+pub fn classify(prompt: &str, thinking: bool, config: &AutoModelConfig) -> (Tier, &'static str) {
+    if thinking {
+        (Tier::Strong, "thinking")
+    } else if has_code(prompt) {
+        (Tier::Strong, "code")
+    } else if estimate_tokens(prompt.len()) > config.simple_max_tokens {
+        (Tier::Strong, "long prompt")
+    } else {
+        (Tier::Cheap, "short prompt")
+    }
+}
+
+/// Pick the model for `prompt` on `provider`.
+pub fn route(provider: Provider, prompt: &str, thinking: bool, config: &AutoModelConfig) -> Route {
+    let (tier, reason) = classify(prompt, thinking, config);
+    let model = match tier {
+        Tier::Cheap => config.cheap.as_deref().unwrap_or(provider.fast_model()),
+        Tier::Strong | Tier::Chosen => {
+            config.strong.as_deref().unwrap_or(provider.default_model())
+        }
+    };
+    Route {
+        model: model.to_string(),
+        tier,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_prompts_are_cheap() {
+        let config = AutoModelConfig::default();
+        let route = route(
+            Provider::Anthropic,
+            "What is the capital of France?",
+            false,
+            &config,
+        );
+        assert_eq!(route.tier, Tier::Cheap);
+        assert_eq!(route.model, Provider::Anthropic.fast_model());
+    }
+
+    #[test]
+    fn test_code_length_and_thinking_are_strong() {
+        let config = AutoModelConfig::default();
+        let strong = |prompt: &str, thinking| classify(prompt, thinking, &config);
+        assert_eq!(
+            strong("Why?\n```rust\nfn main() {}\n```", false),
+            (Tier::Strong, "code")
+        );
+        assert_eq!(
+            strong("fix this:\nlet x = 1;\nif x {", false),
+            (Tier::Strong, "code")
+        );
+        assert_eq!(
+            strong(&"word ".repeat(400), false),
+            (Tier::Strong, "long prompt")
+        );
+        assert_eq!(strong("hi", true), (Tier::Strong, "thinking"));
+        // A semicolon in prose is not code
+        assert_eq!(strong("Explain this; briefly.", false).0, Tier::Cheap);
+    }
+
+    #[test]
+    fn test_configured_models_override_defaults() {
+        let config = AutoModelConfig {
+            cheap: Some("llama3.2:1b".to_string()),
+            strong: Some("llama3.1:70b".to_string()),
+            simple_max_tokens: 10,
+        };
+        assert_eq!(
+            route(Provider::Ollama, "hi", false, &config).model,
+            "llama3.2:1b"
+        );
+        let long = "a fairly long question about many things at once";
+        assert_eq!(
+            route(Provider::Ollama, long, false, &config).model,
+            "llama3.1:70b"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! When the event loop wakes up, and how long its frames take.
+//!
+//! Instead of polling at a fixed rate, the loop sleeps for as long as nothing
+//! on screen can change: one frame while something animates, the poll
+//! interval while a background task may finish, and otherwise until the next
+//! timer (such as the cursor blink). Key presses and streamed tokens wake it
+//! at once. [`FrameStats`] measures the result, and [`FrameProfile`] breaks
+//! down the last frame for the profiling overlay (F12).
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::config::BehaviorConfig;
+
+/// Longest sleep when nothing is happening at all.
+const MAX_IDLE_SLEEP: Duration = Duration::from_secs(1);
+
+/// What the app is doing, from busiest to idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    /// Something moves every frame (the banner reveal)
+    Animating,
+    /// A background task may finish at any moment, or a countdown is shown
+    Waiting,
+    /// Only timers and input can change the screen
+    Idle,
+}
+
+/// Picks how long the event loop may sleep.
+#[derive(Debug, Clone, Copy)]
+pub struct Scheduler {
+    /// Frame interval while animating, and the shortest time between redraws
+    pub frame: Duration,
+    /// Polling interval while waiting on background work
+    pub poll: Duration,
+}
+
+impl Scheduler {
+    /// Frame and poll intervals from behavior settings.
+    pub fn new(behavior: &BehaviorConfig) -> Self {
+        Self {
+            frame: Duration::from_millis(behavior.animation_frame_ms.max(1)),
+            poll: Duration::from_millis(behavior.idle_poll_ms.max(1)),
+        }
+    }
+
+    /// Longest the loop may sleep at `now`, doing `activity`, with the next
+    /// timer due at `next_timer`.
+    pub fn timeout(
+        &self,
+        activity: Activity,
+        next_timer: Option<Instant>,
+        now: Instant,
+    ) -> Duration {
+        let limit = match activity {
+            Activity::Animating => self.frame,
+            Activity::Waiting => self.poll,
+            Activity::Idle => MAX_IDLE_SLEEP,
+        };
+        next_timer.map_or(limit, |at| at.saturating_duration_since(now).min(limit))
+    }
+}
+
+/// Where the time of one frame went.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameProfile {
+    /// Splitting the screen and updating scroll state
+    pub layout: Duration,
+    /// Wrapping and styling messages into lines
+    pub wrap: Duration,
+    /// Rendering widgets and flushing them to the terminal
+    pub render: Duration,
+    /// Heap allocations made while drawing
+    pub allocations: u64,
+}
+
+/// Frame-time metric: how often the loop wakes and how long drawing takes.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    /// When measuring started
+    pub started: Instant,
+    /// Times the loop woke up
+    pub wakeups: u64,
+    /// Frames drawn
+    pub frames: u64,
+    /// Time spent drawing, in total
+    pub draw_time: Duration,
+    /// Slowest frame
+    pub max_draw: Duration,
+    /// Breakdown of the last frame
+    pub last: FrameProfile,
+    /// When each frame of the last second was drawn
+    recent: VecDeque<Instant>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            wakeups: 0,
+            frames: 0,
+            draw_time: Duration::ZERO,
+            max_draw: Duration::ZERO,
+            last: FrameProfile::default(),
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl FrameStats {
+    /// Count a wake-up of the event loop.
+    pub fn record_wakeup(&mut self) {
+        self.wakeups += 1;
+    }
+
+    /// Count a frame that took `elapsed` to draw.
+    pub fn record_frame(&mut self, elapsed: Duration) {
+        self.frames += 1;
+        self.draw_time += elapsed;
+        self.max_draw = self.max_draw.max(elapsed);
+        let now = Instant::now();
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > Duration::from_secs(1))
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Frames drawn in the last second.
+    pub fn fps(&self) -> usize {
+        self.recent
+            .iter()
+            .filter(|at| at.elapsed() <= Duration::from_secs(1))
+            .count()
+    }
+
+    /// Average time to draw a frame.
+    pub fn average_draw(&self) -> Duration {
+        let frames = u32::try_from(self.frames).unwrap_or(u32::MAX);
+        self.draw_time.checked_div(frames).unwrap_or_default()
+    }
+
+    /// Wake-ups per second since measuring started.
+    pub fn wakeups_per_sec(&self) -> f64 {
+        let secs = self.started.elapsed().as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.wakeups as f64 / secs
+        }
+    }
+
+    /// One-line summary, e.g. for printing on exit.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} frames in {:.1}s, {:.1} wake-ups/s, draw avg {:.2}ms max {:.2}ms",
+            self.frames,
+            self.started.elapsed().as_secs_f64(),
+            self.wakeups_per_sec(),
+            self.average_draw().as_secs_f64() * 1000.0,
+            self.max_draw.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_follows_activity_and_timers() {
+        let scheduler = Scheduler::new(&BehaviorConfig::default());
+        let now = Instant::now();
+        assert_eq!(
+            scheduler.timeout(Activity::Animating, None, now),
+            scheduler.frame
+        );
+        assert_eq!(
+            scheduler.timeout(Activity::Waiting, None, now),
+            scheduler.poll
+        );
+        assert_eq!(scheduler.timeout(Activity::Idle, None, now), MAX_IDLE_SLEEP);
+
+        // Idle sleeps only until the next timer, and not at all once it is due
+        let blink = now + Duration::from_millis(300);
+        assert_eq!(
+            scheduler.timeout(Activity::Idle, Some(blink), now),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            scheduler.timeout(Activity::Idle, Some(now), now + Duration::from_millis(5)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_frame_stats() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.average_draw(), Duration::ZERO);
+        stats.record_frame(Duration::from_millis(2));
+        stats.record_frame(Duration::from_millis(4));
+        stats.record_wakeup();
+        assert_eq!(stats.average_draw(), Duration::from_millis(3));
+        assert_eq!(stats.max_draw, Duration::from_millis(4));
+        assert_eq!(stats.fps(), 2);
+        assert!(stats.summary().starts_with("2 frames"));
+    }
+}
@@ -0,0 +1,271 @@
+//! Topic sections for long conversations.
+//!
+//! A conversation is split into sections at user messages that change the
+//! subject, detected heuristically by comparing the significant words of each
+//! prompt with those of the section so far. Sections are derived from the
+//! messages on demand, so they stay correct across undo, edits and reloads;
+//! only the fold state is kept separately. The last result is cached until a
+//! prompt changes, so redrawing a long conversation does not re-split it.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::message::{Message, Role};
+
+/// Word overlap below which a user message starts a new section.
+const NEW_TOPIC_THRESHOLD: f32 = 0.15;
+
+/// Maximum length of a section title, in characters.
+const TITLE_LEN: usize = 48;
+
+/// A run of messages about one topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Index of the first message
+    pub start: usize,
+    /// Index one past the last message
+    pub end: usize,
+    /// Title derived from the opening prompt
+    pub title: String,
+}
+
+impl Section {
+    /// Number of messages in the section.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check whether the section has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Check whether a message index falls inside the section.
+    pub fn contains(&self, index: usize) -> bool {
+        (self.start..self.end).contains(&index)
+    }
+}
+
+/// Lowercased words of four or more letters, ignoring short filler words.
+fn keywords(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Build a short title from the first line of a prompt.
+fn title_for(text: &str) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let line = line.trim().trim_start_matches('#').trim();
+    if line.chars().count() > TITLE_LEN {
+        let mut title: String = line.chars().take(TITLE_LEN - 1).collect();
+        title.push('…');
+        title
+    } else {
+        line.to_string()
+    }
+}
+
+/// Split messages into topic sections.
+///
+/// Messages before the first user prompt (banners, greetings) belong to the
+/// first section. Returns an empty list if there are no user messages.
+pub fn segment(messages: &[Message]) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut topic = BTreeSet::new();
+
+    for (i, msg) in messages.iter().enumerate() {
+        if msg.role != Role::User || !msg.is_chat() {
+            continue;
+        }
+        let words = keywords(&msg.content);
+        let overlap = if topic.is_empty() || words.is_empty() {
+            0.0
+        } else {
+            let shared = words.intersection(&topic).count() as f32;
+            shared / words.len().min(topic.len()) as f32
+        };
+
+        if sections.is_empty() || overlap < NEW_TOPIC_THRESHOLD && !words.is_empty() {
+            if let Some(last) = sections.last_mut() {
+                last.end = i;
+            }
+            sections.push(Section {
+                start: if sections.is_empty() { 0 } else { i },
+                end: messages.len(),
+                title: title_for(&msg.content),
+            });
+            topic = words;
+        } else {
+            topic.extend(words);
+        }
+    }
+
+    sections
+}
+
+/// Fingerprint of everything [`segment`] looks at: the message count and
+/// the user prompts.
+fn fingerprint(messages: &[Message]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    messages.len().hash(&mut hasher);
+    for (i, msg) in messages.iter().enumerate() {
+        if msg.role == Role::User && msg.is_chat() {
+            (i, &msg.content).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Which sections are folded, keyed by their first message index.
+#[derive(Debug, Clone, Default)]
+pub struct SectionState {
+    /// Start indices of collapsed sections
+    pub collapsed: HashSet<usize>,
+    /// Last segmentation and the fingerprint of the messages it came from
+    cache: RefCell<Option<(u64, Arc<[Section]>)>>,
+}
+
+impl SectionState {
+    /// Sections of `messages`, split again only when a prompt has changed.
+    pub fn segments(&self, messages: &[Message]) -> Arc<[Section]> {
+        let key = fingerprint(messages);
+        let mut cache = self.cache.borrow_mut();
+        if let Some((cached, sections)) = &*cache {
+            if *cached == key {
+                return Arc::clone(sections);
+            }
+        }
+        let sections: Arc<[Section]> = segment(messages).into();
+        *cache = Some((key, Arc::clone(&sections)));
+        sections
+    }
+
+    /// Toggle whether the section starting at `start` is collapsed.
+    pub fn toggle(&mut self, start: usize) {
+        if !self.collapsed.remove(&start) {
+            self.collapsed.insert(start);
+        }
+    }
+
+    /// Check whether the section starting at `start` is collapsed.
+    pub fn is_collapsed(&self, start: usize) -> bool {
+        self.collapsed.contains(&start)
+    }
+
+    /// Check whether a message is hidden inside a collapsed section.
+    pub fn is_hidden(&self, sections: &[Section], index: usize) -> bool {
+        sections
+            .iter()
+            .any(|s| s.contains(index) && self.is_collapsed(s.start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(turns: &[&str]) -> Vec<Message> {
+        turns
+            .iter()
+            .flat_map(|t| {
+                [
+                    Message::user(t.to_string()),
+                    Message::assistant("ok".to_string()),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_related_prompts_share_a_section() {
+        let messages = chat(&[
+            "How do I configure tokio runtime threads?",
+            "Can the tokio runtime use fewer threads on startup?",
+        ]);
+        let sections = segment(&messages);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].len(), 4);
+    }
+
+    #[test]
+    fn test_segments_are_cached_until_a_prompt_changes() {
+        let state = SectionState::default();
+        let mut messages = chat(&["How do I configure tokio runtime threads?"]);
+        let first = state.segments(&messages);
+        assert!(Arc::ptr_eq(&first, &state.segments(&messages)));
+
+        // Streaming into a reply keeps the cached split
+        messages[1].content.push_str(" more");
+        assert!(Arc::ptr_eq(&first, &state.segments(&messages)));
+
+        messages[0].content = "Write a haiku about autumn leaves".to_string();
+        let changed = state.segments(&messages);
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert_eq!(changed[0].title, "Write a haiku about autumn leaves");
+    }
+
+    #[test]
+    fn test_topic_change_starts_section() {
+        let mut messages = vec![Message::system_banner("welcome".to_string())];
+        messages.extend(chat(&[
+            "How do I configure tokio runtime threads?",
+            "Write a haiku about autumn leaves",
+        ]));
+        let sections = segment(&messages);
+        assert_eq!(sections.len(), 2);
+        // The banner belongs to the first section
+        assert_eq!(sections[0].start, 0);
+        assert_eq!(sections[0].end, 3);
+        assert_eq!(sections[1].start, 3);
+        assert_eq!(sections[1].end, 5);
+        assert_eq!(sections[1].title, "Write a haiku about autumn leaves");
+    }
+
+    #[test]
+    fn test_short_follow_ups_stay_in_section() {
+        let messages = chat(&["Explain borrow checker lifetimes", "why?"]);
+        assert_eq!(segment(&messages).len(), 1);
+    }
+
+    #[test]
+    fn test_long_titles_are_truncated() {
+        let title = title_for(&"word ".repeat(30));
+        assert_eq!(title.chars().count(), TITLE_LEN);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn test_toggle_collapsed() {
+        let mut state = SectionState::default();
+        state.toggle(3);
+        assert!(state.is_collapsed(3));
+        state.toggle(3);
+        assert!(!state.is_collapsed(3));
+    }
+
+    #[test]
+    fn test_collapsed_section_hides_its_messages() {
+        let sections = vec![
+            Section {
+                start: 0,
+                end: 2,
+                title: "a".to_string(),
+            },
+            Section {
+                start: 2,
+                end: 4,
+                title: "b".to_string(),
+            },
+        ];
+        let mut state = SectionState::default();
+        state.toggle(2);
+        assert!(!state.is_hidden(&sections, 1));
+        assert!(state.is_hidden(&sections, 2));
+        assert!(state.is_hidden(&sections, 3));
+    }
+}
@@ -0,0 +1,130 @@
+//! Session store keeping one JSON file per session in a directory.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use super::{Session, SessionStore, SessionSummary};
+use crate::durable::{self, WriteOptions};
+
+/// Sessions as `<id>.json` files in a directory.
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    dir: PathBuf,
+    options: WriteOptions,
+}
+
+impl JsonStore {
+    /// Use `dir` for session files, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            options: WriteOptions::default(),
+        })
+    }
+
+    /// Set how session files are written (atomic by default).
+    pub fn with_write_options(mut self, options: WriteOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        // Ids become file names, so never let one escape the directory
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            bail!("Invalid session id: {}", id);
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    /// Every readable session in the directory, newest first.
+    fn all(&self) -> Result<Vec<Session>> {
+        let entries = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read directory: {}", self.dir.display()))?;
+        let mut sessions: Vec<Session> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            // Skip files that are not sessions rather than failing the whole listing
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|contents| serde_json::from_str(&contents).ok())
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(sessions)
+    }
+}
+
+impl SessionStore for JsonStore {
+    fn save_record(&self, session: &Session) -> Result<()> {
+        let path = self.path(&session.id)?;
+        let contents = serde_json::to_string_pretty(session)?;
+        durable::write(&path, contents.as_bytes(), self.options)
+            .with_context(|| format!("Failed to write session: {}", path.display()))
+    }
+
+    /// Searches read the files directly, so only the title needs storing.
+    fn index(&self, session: &Session, progress: &mut dyn FnMut(usize)) -> Result<()> {
+        let mut stored = self.load(&session.id)?;
+        if stored.title != session.title {
+            stored.title = session.title.clone();
+            self.save_record(&stored)?;
+        }
+        progress(session.messages.len());
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Session> {
+        let path = self.path(id)?;
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read session: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse session: {}", path.display()))
+    }
+
+    fn list(&self) -> Result<Vec<SessionSummary>> {
+        Ok(self.all()?.iter().map(Session::summary).collect())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        let path = self.path(id)?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete session: {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SessionSummary>> {
+        Ok(self
+            .all()?
+            .iter()
+            .filter(|s| s.contains(query))
+            .map(Session::summary)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::tests::exercise;
+
+    #[test]
+    fn test_json_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonStore::open(dir.path().join("sessions")).unwrap();
+        exercise(&store);
+    }
+
+    #[test]
+    fn test_rejects_ids_outside_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonStore::open(dir.path()).unwrap();
+        assert!(store.load("../secrets").is_err());
+        assert!(store.delete("").is_err());
+    }
+}
@@ -0,0 +1,490 @@
+//! Saved chat sessions and where they are stored.
+//!
+//! Sessions are persisted through the [`SessionStore`] trait so the storage
+//! backend can be chosen in config (`[storage] backend`):
+//! - `json` - One pretty-printed file per session; easy to read, diff and sync
+//! - `sqlite` - A single database with full-text search, suited to thousands
+//!   of sessions
+
+mod json;
+mod sqlite;
+
+pub use json::JsonStore;
+pub use sqlite::SqliteStore;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::{FsyncPolicy, StorageBackend, StorageConfig};
+use crate::durable::WriteOptions;
+use crate::llm::FinishReason;
+use crate::message::{Message, Role};
+
+/// Maximum length of a title derived from the first prompt.
+const TITLE_LEN: usize = 60;
+
+/// Characters of context kept before a match in a search snippet.
+const SNIPPET_BEFORE: usize = 30;
+
+/// Characters of context kept after a match in a search snippet.
+const SNIPPET_AFTER: usize = 50;
+
+/// Most hits taken from one session, so one long chat can't crowd out the rest.
+const HITS_PER_SESSION: usize = 5;
+
+/// One message of a saved session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMessage {
+    pub role: Role,
+    pub content: String,
+    /// A reply whose stream broke off (or was still streaming when saved)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// Kept in the context window when older messages are left out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+/// A saved conversation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Unique id, sortable by creation time
+    pub id: String,
+    /// Short title, by default the start of the first prompt
+    pub title: String,
+    /// Provider the conversation was held with
+    pub provider: String,
+    /// Model the conversation was held with
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Free-form labels for filtering
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub messages: Vec<SessionMessage>,
+    /// How many messages have been seen; sessions saved before read
+    /// markers have none and count as read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<usize>,
+    /// System prompt set with `/system`; `None` uses the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+impl Session {
+    /// Start an empty session with a fresh id.
+    pub fn new(provider: impl Into<String>, model: impl Into<String>) -> Self {
+        let now = Utc::now();
+        let suffix: u16 = rand::thread_rng().gen();
+        Self {
+            id: format!("{}-{:04x}", now.format("%Y%m%d-%H%M%S"), suffix),
+            title: String::new(),
+            provider: provider.into(),
+            model: model.into(),
+            created_at: now,
+            updated_at: now,
+            tags: Vec::new(),
+            messages: Vec::new(),
+            read: Some(0),
+            system_prompt: None,
+        }
+    }
+
+    /// Replace the saved messages with the chat messages of a conversation.
+    ///
+    /// Banners, notes and error cards are display-only and are not saved.
+    /// The title is left alone; see [`Session::derive_title`].
+    pub fn set_messages(&mut self, messages: &[Message]) {
+        self.messages = messages
+            .iter()
+            .filter(|m| m.is_chat())
+            .map(|m| SessionMessage {
+                role: m.role,
+                content: m.content.clone(),
+                interrupted: m.finish == Some(FinishReason::Interrupted),
+                pinned: m.pinned,
+            })
+            .collect();
+        self.updated_at = Utc::now();
+    }
+
+    /// Take the title from the first prompt unless one was already set.
+    pub fn derive_title(&mut self) {
+        if self.title.is_empty() {
+            if let Some(first) = self.messages.iter().find(|m| m.role == Role::User) {
+                self.title = title_from(&first.content);
+            }
+        }
+    }
+
+    /// A copy of this session under a fresh id.
+    pub fn duplicate(&self) -> Session {
+        Session {
+            id: Session::new("", "").id,
+            ..self.clone()
+        }
+    }
+
+    /// The saved messages as chat messages.
+    pub fn chat_messages(&self) -> Vec<Message> {
+        self.messages
+            .iter()
+            .map(|m| {
+                let mut message = Message::new(m.role, m.content.clone());
+                if m.interrupted {
+                    message.finish = Some(FinishReason::Interrupted);
+                }
+                message.pinned = m.pinned;
+                message
+            })
+            .collect()
+    }
+
+    /// Messages saved since the user last looked at this session.
+    pub fn unread(&self) -> usize {
+        unread(self.messages.len(), self.read)
+    }
+
+    /// Summary of this session for listings.
+    pub fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            updated_at: self.updated_at,
+            message_count: self.messages.len(),
+            unread: self.unread(),
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Whether any message contains `query`, ignoring case.
+    pub fn contains(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.title.to_lowercase().contains(&query)
+            || self
+                .messages
+                .iter()
+                .any(|m| m.content.to_lowercase().contains(&query))
+    }
+
+    /// Where `query` occurs in this session, ignoring case: one hit per
+    /// matching message, or a single title hit if only the title matches.
+    pub fn hits(&self, query: &str) -> Vec<SearchHit> {
+        let needle: Vec<char> = fold(query);
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let summary = self.summary();
+        let mut hits: Vec<SearchHit> = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, m)| {
+                let (snippet, highlight) = snippet(&m.content, &needle)?;
+                Some(SearchHit {
+                    session: summary.clone(),
+                    message: Some(index),
+                    snippet,
+                    highlight,
+                })
+            })
+            .take(HITS_PER_SESSION)
+            .collect();
+        if hits.is_empty() {
+            if let Some((snippet, highlight)) = snippet(&self.title, &needle) {
+                hits.push(SearchHit {
+                    session: summary,
+                    message: None,
+                    snippet,
+                    highlight,
+                });
+            }
+        }
+        hits
+    }
+}
+
+/// `text` lowercased, one char per char so positions carry over.
+fn fold(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// The first occurrence of `needle` (already folded) in `text` with some
+/// context around it, on one line, and where the match sits in it.
+fn snippet(text: &str, needle: &[char]) -> Option<(String, (usize, usize))> {
+    let haystack = fold(text);
+    let at = haystack
+        .windows(needle.len())
+        .position(|window| window == needle)?;
+    let from = at.saturating_sub(SNIPPET_BEFORE);
+    let to = (at + needle.len() + SNIPPET_AFTER).min(haystack.len());
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let start = snippet.chars().count() + at - from;
+    snippet.extend(
+        text.chars()
+            .skip(from)
+            .take(to - from)
+            .map(|c| if c.is_whitespace() { ' ' } else { c }),
+    );
+    if to < haystack.len() {
+        snippet.push('…');
+    }
+    Some((snippet, (start, needle.len())))
+}
+
+/// First line of a prompt, shortened for use as a title.
+fn title_from(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or("").trim();
+    if line.chars().count() > TITLE_LEN {
+        let cut: String = line.chars().take(TITLE_LEN - 1).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Messages past the read marker; no marker means all read.
+pub(crate) fn unread(message_count: usize, read: Option<usize>) -> usize {
+    read.map_or(0, |read| message_count.saturating_sub(read))
+}
+
+/// A session as shown in listings, without its messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    pub provider: String,
+    pub model: String,
+    pub updated_at: DateTime<Utc>,
+    pub message_count: usize,
+    /// Messages saved since the session was last looked at
+    pub unread: usize,
+    pub tags: Vec<String>,
+}
+
+/// A place in a saved session where a search query was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub session: SessionSummary,
+    /// Index of the matching message; `None` when only the title matches
+    pub message: Option<usize>,
+    /// The text around the match, on one line
+    pub snippet: String,
+    /// Where the match is in `snippet`, as (first char, length in chars)
+    pub highlight: (usize, usize),
+}
+
+/// Persistence for saved sessions.
+///
+/// Listings and search results are ordered most recently updated first.
+/// Saving is split in two so the slow half, [`SessionStore::index`], can run
+/// on the background indexer.
+pub trait SessionStore: Send + Sync {
+    /// Insert or replace a session, including its title and search index.
+    fn save(&self, session: &Session) -> Result<()> {
+        self.save_record(session)?;
+        self.index(session, &mut |_| {})
+    }
+
+    /// Insert or replace a session without updating its search index.
+    fn save_record(&self, session: &Session) -> Result<()>;
+
+    /// Store the title of a saved session and rebuild its search index,
+    /// calling `progress` with the number of messages indexed so far.
+    fn index(&self, session: &Session, progress: &mut dyn FnMut(usize)) -> Result<()>;
+
+    /// Load a session by id.
+    fn load(&self, id: &str) -> Result<Session>;
+
+    /// Summaries of all sessions.
+    fn list(&self) -> Result<Vec<SessionSummary>>;
+
+    /// Delete a session; deleting a missing session is not an error.
+    fn delete(&self, id: &str) -> Result<()>;
+
+    /// Sessions whose title or messages contain `query`.
+    fn search(&self, query: &str) -> Result<Vec<SessionSummary>>;
+
+    /// Hits for `query` across all sessions, most recently updated first,
+    /// at most `limit` of them. Sessions are narrowed down with
+    /// [`SessionStore::search`] and only those are read in full.
+    fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let mut hits = Vec::new();
+        for summary in self.search(query)? {
+            hits.extend(self.load(&summary.id)?.hits(query));
+            if hits.len() >= limit {
+                hits.truncate(limit);
+                break;
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Sessions carrying `tag`.
+    fn tagged(&self, tag: &str) -> Result<Vec<SessionSummary>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|s| s.tags.iter().any(|t| t == tag))
+            .collect())
+    }
+}
+
+impl StorageConfig {
+    /// Where sessions are stored: the configured path or the default for the backend.
+    pub fn resolved_path(&self) -> Result<PathBuf> {
+        if let Some(path) = &self.path {
+            return Ok(path.clone());
+        }
+        let dir = dirs::data_local_dir()
+            .context("Could not determine local data directory")?
+            .join("scry-cli");
+        Ok(match self.backend {
+            StorageBackend::Json => dir.join("sessions"),
+            StorageBackend::Sqlite => dir.join("sessions.db"),
+        })
+    }
+
+    /// How session files are written.
+    pub fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            atomic: self.atomic_writes,
+            fsync: self.fsync == FsyncPolicy::Always,
+            mode: None,
+        }
+    }
+}
+
+/// Open the session store selected in config.
+pub fn open_store(config: &StorageConfig) -> Result<Arc<dyn SessionStore>> {
+    let path = config.resolved_path()?;
+    Ok(match config.backend {
+        StorageBackend::Json => {
+            Arc::new(JsonStore::open(path)?.with_write_options(config.write_options()))
+        }
+        StorageBackend::Sqlite => Arc::new(SqliteStore::open(path)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session with a few messages, saved `age_secs` seconds ago.
+    pub(super) fn sample(title: &str, reply: &str, age_secs: i64) -> Session {
+        let mut session = Session::new("ollama", "llama3");
+        session.set_messages(&[
+            Message::system_banner("banner".to_string()),
+            Message::user(title.to_string()),
+            Message::assistant(reply.to_string()),
+        ]);
+        session.derive_title();
+        session.updated_at = Utc::now() - chrono::Duration::seconds(age_secs);
+        session
+    }
+
+    /// Behavior every backend must share.
+    pub(super) fn exercise(store: &dyn SessionStore) {
+        let mut old = sample("Tuning postgres", "Raise shared_buffers first.", 60);
+        old.tags = vec!["db".to_string()];
+        old.system_prompt = Some("Answer as a DBA.".to_string());
+        let new = sample("Borrow checker help", "Use a scoped block.", 0);
+        store.save(&old).unwrap();
+        store.save(&new).unwrap();
+
+        let ids: Vec<String> = store.list().unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![new.id.clone(), old.id.clone()]);
+        assert_eq!(store.load(&old.id).unwrap(), old);
+
+        let hits = store.search("shared_buffers").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, old.id);
+        assert_eq!(hits[0].message_count, 2);
+        assert!(store.search("nothing like this").unwrap().is_empty());
+
+        let found = store.search_messages("SHARED_buffers", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].session.id, old.id);
+        assert_eq!(found[0].message, Some(1));
+        assert_eq!(found[0].snippet, "Raise shared_buffers first.");
+        assert_eq!(found[0].highlight, (6, 14));
+
+        let tagged = store.tagged("db").unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].tags, vec!["db"]);
+
+        // Saving again replaces the session
+        let mut renamed = old.clone();
+        renamed.title = "Postgres".to_string();
+        store.save(&renamed).unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+        assert_eq!(store.load(&old.id).unwrap().title, "Postgres");
+
+        store.delete(&old.id).unwrap();
+        store.delete(&old.id).unwrap();
+        assert!(store.load(&old.id).is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_only_chat_messages_are_saved() {
+        let session = sample("Hello there", "Hi!", 0);
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.title, "Hello there");
+        assert_eq!(session.chat_messages()[1].content, "Hi!");
+    }
+
+    #[test]
+    fn test_unread_counts_messages_past_the_marker() {
+        let mut session = sample("Hello there", "Hi!", 0);
+        session.read = Some(1);
+        assert_eq!(session.unread(), 1);
+        assert_eq!(session.summary().unread, 1);
+
+        // Saved before read markers: everything counts as read
+        session.read = None;
+        assert_eq!(session.unread(), 0);
+        let json = serde_json::to_string(&session).unwrap();
+        assert!(!json.contains("\"read\""));
+        let old: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(old.read, None);
+    }
+
+    #[test]
+    fn test_hits_show_the_match_in_context() {
+        let long = format!("{} needle in the {}", "hay ".repeat(20), "stack\n".repeat(20));
+        let mut session = sample("Where is it?", &long, 0);
+        let hits = session.hits("Needle");
+        assert_eq!(hits.len(), 1);
+        let (start, len) = hits[0].highlight;
+        let shown: String = hits[0].snippet.chars().skip(start).take(len).collect();
+        assert_eq!(shown, "needle");
+        assert!(hits[0].snippet.starts_with('…'));
+        assert!(hits[0].snippet.ends_with('…'));
+        assert!(!hits[0].snippet.contains('\n'));
+
+        // Only the title matches
+        session.title = "Needle hunt".to_string();
+        session.messages.clear();
+        assert_eq!(session.hits("hunt")[0].message, None);
+        assert!(session.hits("").is_empty());
+    }
+
+    #[test]
+    fn test_long_titles_are_shortened() {
+        let title = title_from(&format!("{}\nsecond line", "word ".repeat(30)));
+        assert!(title.chars().count() <= TITLE_LEN);
+        assert!(title.ends_with('…'));
+    }
+}
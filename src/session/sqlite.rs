@@ -0,0 +1,454 @@
+//! Session store backed by a single SQLite database.
+//!
+//! Messages are mirrored into an FTS5 table so searching thousands of
+//! sessions stays fast; tags live in their own table for filtering.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{unread, Session, SessionMessage, SessionStore, SessionSummary};
+use crate::message::Role;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        read INTEGER,
+        system_prompt TEXT
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        seq INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        interrupted INTEGER NOT NULL DEFAULT 0,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (session_id, seq)
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (session_id, tag)
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+        content, session_id UNINDEXED
+    );
+    CREATE INDEX IF NOT EXISTS sessions_updated ON sessions(updated_at);
+";
+
+/// Messages added to the search index per transaction.
+const INDEX_CHUNK: usize = 64;
+
+/// Columns selected for a [`SessionSummary`], from `sessions s`.
+const SUMMARY_COLUMNS: &str = "s.id, s.title, s.model, s.updated_at,
+    (SELECT COUNT(*) FROM messages m WHERE m.session_id = s.id),
+    (SELECT group_concat(tag, char(31)) FROM tags t WHERE t.session_id = s.id),
+    s.provider, s.read";
+
+/// Sessions in a SQLite database file.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open session database: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// A database that lives only in memory, for tests and dry runs.
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create session tables")?;
+        migrate(&conn).context("Failed to upgrade session tables")?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Run `f` with the connection.
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session database lock poisoned"))?;
+        f(&conn)
+    }
+
+    fn summaries(&self, sql: &str, query: Option<&str>) -> Result<Vec<SessionSummary>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = match query {
+                Some(query) => stmt.query_map([query], summary_from_row)?,
+                None => stmt.query_map([], summary_from_row)?,
+            };
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+    }
+}
+
+/// Add columns introduced after a database was created.
+fn migrate(conn: &Connection) -> Result<()> {
+    let has_interrupted: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('messages') WHERE name = 'interrupted'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_interrupted == 0 {
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN interrupted INTEGER NOT NULL DEFAULT 0;",
+        )?;
+    }
+    let has_pinned: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('messages') WHERE name = 'pinned'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_pinned == 0 {
+        conn.execute_batch("ALTER TABLE messages ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;")?;
+    }
+    let has_read: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('sessions') WHERE name = 'read'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_read == 0 {
+        conn.execute_batch("ALTER TABLE sessions ADD COLUMN read INTEGER;")?;
+    }
+    let has_system_prompt: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('sessions') WHERE name = 'system_prompt'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_system_prompt == 0 {
+        conn.execute_batch("ALTER TABLE sessions ADD COLUMN system_prompt TEXT;")?;
+    }
+    Ok(())
+}
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<SessionSummary> {
+    let tags: Option<String> = row.get(5)?;
+    let message_count = row.get::<_, i64>(4)? as usize;
+    let read: Option<i64> = row.get(7)?;
+    Ok(SessionSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        provider: row.get(6)?,
+        model: row.get(2)?,
+        updated_at: parse_time(row.get(3)?),
+        message_count,
+        unread: unread(message_count, read.map(|r| r as usize)),
+        tags: tags
+            .map(|t| t.split('\u{1f}').map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn parse_time(text: String) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(&text)
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+/// Quote a search string as an FTS5 phrase so punctuation is matched literally.
+fn fts_phrase(query: &str) -> String {
+    format!("\"{}\"", query.replace('"', "\"\""))
+}
+
+impl SessionStore for SqliteStore {
+    fn save_record(&self, session: &Session) -> Result<()> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session database lock poisoned"))?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", [&session.id])?;
+        tx.execute(
+            "INSERT INTO sessions
+                (id, title, provider, model, created_at, updated_at, read, system_prompt)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session.id,
+                session.title,
+                session.provider,
+                session.model,
+                session.created_at.to_rfc3339(),
+                session.updated_at.to_rfc3339(),
+                session.read.map(|r| r as i64),
+                session.system_prompt,
+            ],
+        )?;
+        for (seq, msg) in session.messages.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO messages (session_id, seq, role, content, interrupted, pinned)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    session.id,
+                    seq as i64,
+                    role_name(msg.role),
+                    msg.content,
+                    msg.interrupted,
+                    msg.pinned
+                ],
+            )?;
+        }
+        for tag in &session.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (session_id, tag) VALUES (?1, ?2)",
+                params![session.id, tag],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Indexes in chunks of [`INDEX_CHUNK`] messages, releasing the database
+    /// between them so saves and searches are not held up by huge sessions.
+    fn index(&self, session: &Session, progress: &mut dyn FnMut(usize)) -> Result<()> {
+        self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE sessions SET title = ?1 WHERE id = ?2",
+                params![session.title, session.id],
+            )?;
+            tx.execute(
+                "DELETE FROM messages_fts WHERE session_id = ?1",
+                [&session.id],
+            )?;
+            tx.execute(
+                "INSERT INTO messages_fts (content, session_id) VALUES (?1, ?2)",
+                params![session.title, session.id],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })?;
+        let mut done = 0;
+        for chunk in session.messages.chunks(INDEX_CHUNK) {
+            self.with_conn(|conn| {
+                let tx = conn.unchecked_transaction()?;
+                for msg in chunk {
+                    tx.execute(
+                        "INSERT INTO messages_fts (content, session_id) VALUES (?1, ?2)",
+                        params![msg.content, session.id],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })?;
+            done += chunk.len();
+            progress(done);
+        }
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Session> {
+        self.with_conn(|conn| {
+            let mut session = conn
+                .query_row(
+                    "SELECT id, title, provider, model, created_at, updated_at, read,
+                        system_prompt
+                     FROM sessions WHERE id = ?1",
+                    [id],
+                    |row| {
+                        Ok(Session {
+                            id: row.get(0)?,
+                            title: row.get(1)?,
+                            provider: row.get(2)?,
+                            model: row.get(3)?,
+                            created_at: parse_time(row.get(4)?),
+                            updated_at: parse_time(row.get(5)?),
+                            tags: Vec::new(),
+                            messages: Vec::new(),
+                            read: row.get::<_, Option<i64>>(6)?.map(|r| r as usize),
+                            system_prompt: row.get(7)?,
+                        })
+                    },
+                )
+                .optional()?
+                .with_context(|| format!("No saved session with id {}", id))?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT role, content, interrupted, pinned FROM messages
+                     WHERE session_id = ?1 ORDER BY seq",
+                )?;
+            session.messages = stmt
+                .query_map([id], |row| {
+                    let role: String = row.get(0)?;
+                    Ok(SessionMessage {
+                        role: if role == "user" {
+                            Role::User
+                        } else {
+                            Role::Assistant
+                        },
+                        content: row.get(1)?,
+                        interrupted: row.get(2)?,
+                        pinned: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let mut stmt =
+                conn.prepare("SELECT tag FROM tags WHERE session_id = ?1 ORDER BY rowid")?;
+            session.tags = stmt
+                .query_map([id], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(session)
+        })
+    }
+
+    fn list(&self) -> Result<Vec<SessionSummary>> {
+        self.summaries(
+            &format!(
+                "SELECT {} FROM sessions s ORDER BY s.updated_at DESC",
+                SUMMARY_COLUMNS
+            ),
+            None,
+        )
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM messages_fts WHERE session_id = ?1", [id])?;
+            conn.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+            Ok(())
+        })
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SessionSummary>> {
+        self.summaries(
+            &format!(
+                "SELECT {} FROM sessions s WHERE s.id IN (
+                    SELECT session_id FROM messages_fts WHERE messages_fts MATCH ?1
+                 ) ORDER BY s.updated_at DESC",
+                SUMMARY_COLUMNS
+            ),
+            Some(&fts_phrase(query)),
+        )
+    }
+
+    fn tagged(&self, tag: &str) -> Result<Vec<SessionSummary>> {
+        self.summaries(
+            &format!(
+                "SELECT {} FROM sessions s
+                 WHERE EXISTS (SELECT 1 FROM tags t WHERE t.session_id = s.id AND t.tag = ?1)
+                 ORDER BY s.updated_at DESC",
+                SUMMARY_COLUMNS
+            ),
+            Some(tag),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::tests::{exercise, sample};
+
+    #[test]
+    fn test_sqlite_store() {
+        exercise(&SqliteStore::in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_store_persists_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("sessions.db");
+        let session = sample("Persist me", "Done.", 0);
+        SqliteStore::open(&path).unwrap().save(&session).unwrap();
+        assert_eq!(
+            SqliteStore::open(&path).unwrap().load(&session.id).unwrap(),
+            session
+        );
+    }
+
+    #[test]
+    fn test_older_databases_are_upgraded() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE messages (
+                session_id TEXT NOT NULL,
+                seq INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY (session_id, seq)
+            );",
+        )
+        .unwrap();
+        let store = SqliteStore::with_connection(conn).unwrap();
+        let mut session = sample("Interrupted", "Half a", 0);
+        session.messages[1].interrupted = true;
+        session.messages[0].pinned = true;
+        session.read = Some(1);
+        session.system_prompt = Some("Be terse.".to_string());
+        store.save(&session).unwrap();
+        assert_eq!(store.load(&session.id).unwrap(), session);
+        assert_eq!(store.list().unwrap()[0].unread, 1);
+    }
+
+    #[test]
+    fn test_search_treats_punctuation_literally() {
+        let store = SqliteStore::in_memory().unwrap();
+        let session = sample("What does foo() do?", "It returns \"bar\".", 0);
+        store.save(&session).unwrap();
+        assert_eq!(store.search("foo()").unwrap().len(), 1);
+        assert_eq!(store.search("\"bar\"").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_index_is_built_separately_from_the_record() {
+        let store = SqliteStore::in_memory().unwrap();
+        let mut session = sample("Untitled", "A long answer", 0);
+        session.messages = (0..150)
+            .map(|i| SessionMessage {
+                role: Role::User,
+                content: format!("message {}", i),
+                interrupted: false,
+                pinned: false,
+            })
+            .collect();
+        store.save_record(&session).unwrap();
+        assert!(store.search("message 149").unwrap().is_empty());
+
+        session.title = "Counting".to_string();
+        let mut reported = Vec::new();
+        store.index(&session, &mut |done| reported.push(done)).unwrap();
+        assert_eq!(reported, vec![64, 128, 150]);
+        assert_eq!(store.search("message 149").unwrap().len(), 1);
+        assert_eq!(store.list().unwrap()[0].title, "Counting");
+    }
+}
@@ -0,0 +1,582 @@
+//! Live session sharing over the local network.
+//!
+//! `/share` makes this instance a host: other scry instances join with
+//! `scry attach <host:port> --token <token>` and follow the conversation as it
+//! happens. Guests of a `/share write` session can also send prompts; the host
+//! runs them as if typed, attributed to the guest, so the host's provider and
+//! key answer for everyone. Read-only guests only watch.
+//!
+//! The protocol is newline-delimited JSON over TCP. A guest opens with
+//! [`GuestEvent::Hello`]; the host answers [`HostEvent::Welcome`] (or
+//! [`HostEvent::Rejected`]) and then sends a [`HostEvent::Snapshot`] of the
+//! conversation whenever it changes. Traffic is not encrypted, so share only
+//! on networks you trust.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::cli::serve::{constant_time_eq, new_token};
+use crate::message::{Message, Messages, Role};
+
+/// Longest line a guest may send.
+const MAX_GUEST_LINE: usize = 64 * 1024;
+
+/// Longest line a host may send (a snapshot of the whole conversation).
+const MAX_HOST_LINE: usize = 64 * 1024 * 1024;
+
+/// How long a new connection has to say hello.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Snapshots of a streaming reply are sent at most this often.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest participant name kept.
+const MAX_NAME: usize = 32;
+
+/// What guests may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareMode {
+    /// Guests watch
+    ReadOnly,
+    /// Guests may also send prompts
+    CoWrite,
+}
+
+impl ShareMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShareMode::ReadOnly => "read-only",
+            ShareMode::CoWrite => "co-write",
+        }
+    }
+}
+
+/// A message as guests see it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedMessage {
+    pub role: Role,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// A note or error card rather than part of the conversation
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub note: bool,
+}
+
+impl SharedMessage {
+    fn from_message(message: &Message) -> Self {
+        Self {
+            role: message.role,
+            content: message.content.clone(),
+            author: message.author.clone(),
+            note: !message.is_chat(),
+        }
+    }
+
+    /// The message for the guest's chat view.
+    pub fn to_message(&self) -> Message {
+        if self.note {
+            return Message::system_note(self.content.clone());
+        }
+        let mut message = Message::new(self.role, self.content.clone());
+        message.author = self.author.clone();
+        message
+    }
+}
+
+/// The conversation at one point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub messages: Vec<SharedMessage>,
+    /// Whether the last reply is still streaming
+    pub streaming: bool,
+}
+
+/// Sent by the host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostEvent {
+    Welcome { mode: ShareMode, host: String },
+    Rejected { reason: String },
+    Snapshot(Snapshot),
+}
+
+/// Sent by a guest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GuestEvent {
+    Hello { token: String, name: String },
+    Prompt { text: String },
+}
+
+/// Something the host app should know about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostNotice {
+    Joined(String),
+    Left(String),
+    /// A co-write guest sent a prompt
+    Prompt {
+        author: String,
+        text: String,
+    },
+}
+
+/// What every guest connection needs from the host.
+struct HostInfo {
+    token: String,
+    mode: ShareMode,
+    name: String,
+}
+
+/// The hosting side of a shared session.
+pub struct ShareHost {
+    pub addr: SocketAddr,
+    pub token: String,
+    pub mode: ShareMode,
+    /// Name the host's own prompts are attributed to
+    pub name: String,
+    /// Names of the attached guests
+    pub guests: Vec<String>,
+    /// Guest prompts waiting for the current reply to finish
+    pub queued: VecDeque<(String, String)>,
+    snapshot: watch::Sender<Snapshot>,
+    notices: mpsc::UnboundedReceiver<HostNotice>,
+    /// Revision of the messages and streaming flag last published
+    published: Option<(u64, bool)>,
+    published_at: Instant,
+    accept: JoinHandle<()>,
+}
+
+impl ShareHost {
+    /// Listen on `bind`; call from within the Tokio runtime.
+    pub fn start(bind: &str, mode: ShareMode, name: String) -> Result<Self> {
+        tokio::runtime::Handle::try_current().context("Sharing needs the async runtime")?;
+        let listener = std::net::TcpListener::bind(bind)
+            .with_context(|| format!("Failed to listen on {}", bind))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+        let addr = listener.local_addr()?;
+        let token = new_token();
+        let (snapshot, snapshots) = watch::channel(Snapshot::default());
+        let (notice_tx, notices) = mpsc::unbounded_channel();
+        let info = Arc::new(HostInfo {
+            token: token.clone(),
+            mode,
+            name: name.clone(),
+        });
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let guest = serve_guest(stream, info.clone(), snapshots.clone(), notice_tx.clone());
+                tokio::spawn(async move {
+                    // A guest that goes away mid-write is not the host's problem
+                    let _ = guest.await;
+                });
+            }
+        });
+        Ok(Self {
+            addr,
+            token,
+            mode,
+            name,
+            guests: Vec::new(),
+            queued: VecDeque::new(),
+            snapshot,
+            notices,
+            published: None,
+            published_at: Instant::now(),
+            accept,
+        })
+    }
+
+    /// Send the conversation to guests if it changed since the last call.
+    pub fn publish(&mut self, messages: &Messages, streaming: bool) {
+        if streaming && self.published_at.elapsed() < PUBLISH_INTERVAL {
+            return;
+        }
+        // Edits and deletions anywhere in the conversation change the revision
+        let marker = (messages.revision(), streaming);
+        if self.published == Some(marker) {
+            return;
+        }
+        self.published = Some(marker);
+        self.published_at = Instant::now();
+        self.snapshot.send_replace(Snapshot {
+            messages: messages
+                .iter()
+                .filter(|m| !m.is_system_banner())
+                .map(SharedMessage::from_message)
+                .collect(),
+            streaming,
+        });
+    }
+
+    /// Collect guest arrivals, departures and prompts since the last call;
+    /// prompts are also added to [`ShareHost::queued`].
+    pub fn poll(&mut self) -> Vec<HostNotice> {
+        let mut notices = Vec::new();
+        while let Ok(notice) = self.notices.try_recv() {
+            match &notice {
+                HostNotice::Joined(name) => self.guests.push(name.clone()),
+                HostNotice::Left(name) => {
+                    if let Some(i) = self.guests.iter().position(|g| g == name) {
+                        self.guests.remove(i);
+                    }
+                }
+                HostNotice::Prompt { author, text } => {
+                    self.queued.push_back((author.clone(), text.clone()))
+                }
+            }
+            notices.push(notice);
+        }
+        notices
+    }
+}
+
+impl Drop for ShareHost {
+    fn drop(&mut self) {
+        // Dropping the snapshot sender also ends every guest connection
+        self.accept.abort();
+    }
+}
+
+/// Talk to one guest until either side goes away.
+async fn serve_guest(
+    stream: TcpStream,
+    info: Arc<HostInfo>,
+    mut snapshots: watch::Receiver<Snapshot>,
+    notices: mpsc::UnboundedSender<HostNotice>,
+) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let hello = tokio::time::timeout(HELLO_TIMEOUT, read_event(&mut reader, MAX_GUEST_LINE))
+        .await
+        .context("No hello")??;
+    let name = match hello {
+        Some(GuestEvent::Hello { token, name })
+            if constant_time_eq(token.as_bytes(), info.token.as_bytes()) =>
+        {
+            clean_name(&name)
+        }
+        _ => {
+            let reason = "Missing or wrong token".to_string();
+            return send(&mut write, &HostEvent::Rejected { reason }).await;
+        }
+    };
+    send(
+        &mut write,
+        &HostEvent::Welcome {
+            mode: info.mode,
+            host: info.name.clone(),
+        },
+    )
+    .await?;
+    let _ = notices.send(HostNotice::Joined(name.clone()));
+
+    // Read on a task of its own: a half-read line would be lost if the read
+    // were cancelled by a snapshot arriving
+    let (prompt_tx, mut prompts) = mpsc::unbounded_channel();
+    let reading = tokio::spawn(async move {
+        while let Ok(Some(event)) = read_event::<_, GuestEvent>(&mut reader, MAX_GUEST_LINE).await {
+            if prompt_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let current = snapshots.borrow_and_update().clone();
+    let mut result = send(&mut write, &HostEvent::Snapshot(current)).await;
+    while result.is_ok() {
+        tokio::select! {
+            changed = snapshots.changed() => {
+                if changed.is_err() {
+                    break; // Sharing stopped
+                }
+                let snapshot = snapshots.borrow_and_update().clone();
+                result = send(&mut write, &HostEvent::Snapshot(snapshot)).await;
+            }
+            event = prompts.recv() => match event {
+                Some(GuestEvent::Prompt { text }) if info.mode == ShareMode::CoWrite => {
+                    let author = name.clone();
+                    let _ = notices.send(HostNotice::Prompt { author, text });
+                }
+                Some(_) => {} // Read-only guests can't send
+                None => break,
+            },
+        }
+    }
+    reading.abort();
+    let _ = notices.send(HostNotice::Left(name));
+    result
+}
+
+/// Something the guest app should know about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuestNotice {
+    Snapshot(Snapshot),
+    /// The connection ended, and why
+    Closed(String),
+}
+
+/// The guest side of a shared session.
+pub struct ShareGuest {
+    /// Name of the host
+    pub host: String,
+    pub mode: ShareMode,
+    /// Whether the connection has ended
+    pub closed: bool,
+    notices: mpsc::UnboundedReceiver<GuestNotice>,
+    prompts: mpsc::UnboundedSender<String>,
+}
+
+impl ShareGuest {
+    /// Join the session shared at `addr` as `name`.
+    pub async fn connect(addr: &str, token: &str, name: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .with_context(|| format!("Could not connect to {}", addr))?;
+        let (read, mut write) = stream.into_split();
+        let mut reader = BufReader::new(read);
+        let hello = GuestEvent::Hello {
+            token: token.to_string(),
+            name: name.to_string(),
+        };
+        send(&mut write, &hello).await?;
+        let (mode, host) = match read_event(&mut reader, MAX_HOST_LINE).await? {
+            Some(HostEvent::Welcome { mode, host }) => (mode, host),
+            Some(HostEvent::Rejected { reason }) => bail!("The host refused: {}", reason),
+            _ => bail!("The host closed the connection"),
+        };
+
+        let (notice_tx, notices) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let reason = loop {
+                match read_event(&mut reader, MAX_HOST_LINE).await {
+                    Ok(Some(HostEvent::Snapshot(snapshot))) => {
+                        if notice_tx.send(GuestNotice::Snapshot(snapshot)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => break "The host stopped sharing".to_string(),
+                    Err(e) => break format!("Connection lost: {:#}", e),
+                }
+            };
+            let _ = notice_tx.send(GuestNotice::Closed(reason));
+        });
+        let (prompts, mut prompt_rx) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(text) = prompt_rx.recv().await {
+                if send(&mut write, &GuestEvent::Prompt { text })
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            host,
+            mode,
+            closed: false,
+            notices,
+            prompts,
+        })
+    }
+
+    /// Send a prompt for the host to run. Returns false if the session is
+    /// read-only or over.
+    pub fn send_prompt(&self, text: String) -> bool {
+        self.mode == ShareMode::CoWrite && !self.closed && self.prompts.send(text).is_ok()
+    }
+
+    /// Collect snapshots since the last call, and the end of the connection.
+    pub fn poll(&mut self) -> Vec<GuestNotice> {
+        let mut notices = Vec::new();
+        while let Ok(notice) = self.notices.try_recv() {
+            if matches!(notice, GuestNotice::Closed(_)) {
+                self.closed = true;
+            }
+            notices.push(notice);
+        }
+        notices
+    }
+}
+
+/// Participant names are shown in the chat; keep them short and printable.
+fn clean_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME)
+        .collect();
+    match name.trim() {
+        "" => "guest".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Name to attribute this machine's prompts to.
+pub fn default_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|name| clean_name(&name))
+        .unwrap_or_else(|_| "host".to_string())
+}
+
+async fn send<W, T>(writer: &mut W, event: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(event)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one event; None at the end of the stream.
+async fn read_event<R, T>(reader: &mut R, max: usize) -> Result<Option<T>>
+where
+    R: AsyncBufRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut line = Vec::new();
+    (&mut *reader)
+        .take(max as u64)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        bail!("Line too long or cut off");
+    }
+    Ok(Some(serde_json::from_slice(&line)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Poll until `f` returns something, failing after a few seconds.
+    async fn wait_for<T>(mut f: impl FnMut() -> Option<T>) -> T {
+        let started = Instant::now();
+        loop {
+            if let Some(value) = f() {
+                return value;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guests_follow_and_prompt() {
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::CoWrite, "ada".to_string()).unwrap();
+        let addr = host.addr.to_string();
+        assert!(ShareGuest::connect(&addr, "wrong", "bob").await.is_err());
+
+        let mut guest = ShareGuest::connect(&addr, &host.token, "bob")
+            .await
+            .unwrap();
+        assert_eq!(guest.host, "ada");
+        assert_eq!(guest.mode, ShareMode::CoWrite);
+        wait_for(|| (!host.poll().is_empty()).then_some(())).await;
+        assert_eq!(host.guests, vec!["bob".to_string()]);
+
+        let mut prompt = Message::user("Why is the build red?".to_string());
+        prompt.author = Some("ada".to_string());
+        host.publish(
+            &vec![prompt, Message::system_note("Switched model".to_string())].into(),
+            false,
+        );
+        let snapshot = wait_for(|| {
+            guest.poll().into_iter().find_map(|n| match n {
+                GuestNotice::Snapshot(s) if !s.messages.is_empty() => Some(s),
+                _ => None,
+            })
+        })
+        .await;
+        assert_eq!(snapshot.messages[0].author.as_deref(), Some("ada"));
+        assert!(snapshot.messages[1].to_message().is_system_note());
+
+        assert!(guest.send_prompt("Check the lockfile".to_string()));
+        let notice = wait_for(|| host.poll().pop()).await;
+        assert_eq!(
+            notice,
+            HostNotice::Prompt {
+                author: "bob".to_string(),
+                text: "Check the lockfile".to_string()
+            }
+        );
+        assert_eq!(host.queued.len(), 1);
+
+        drop(host);
+        wait_for(|| {
+            guest
+                .poll()
+                .pop()
+                .filter(|n| matches!(n, GuestNotice::Closed(_)))
+        })
+        .await;
+        assert!(guest.closed);
+    }
+
+    #[tokio::test]
+    async fn test_edits_anywhere_are_published() {
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::ReadOnly, "ada".to_string()).unwrap();
+        let mut messages = Messages::from(vec![
+            Message::user("Name a color".to_string()),
+            Message::assistant("Blue".to_string()),
+            Message::user("And a fruit".to_string()),
+            Message::assistant("Apple".to_string()),
+        ]);
+        host.publish(&messages, false);
+        assert_eq!(host.snapshot.borrow().messages[1].content, "Blue");
+
+        // Reading the messages is not a change
+        let revision = messages.revision();
+        assert_eq!(messages.iter().filter(|m| m.is_chat()).count(), 4);
+        assert_eq!(messages.revision(), revision);
+
+        // Same length, same count, same last message
+        messages[1].content = "Gray".to_string();
+        host.publish(&messages, false);
+        assert_eq!(host.snapshot.borrow().messages[1].content, "Gray");
+
+        // A deletion, then a reply as long as the old last one
+        messages.remove(1);
+        messages.push(Message::assistant("Peach".to_string()));
+        host.publish(&messages, false);
+        let contents: Vec<String> =
+            host.snapshot.borrow().messages.iter().map(|m| m.content.clone()).collect();
+        assert_eq!(contents, ["Name a color", "And a fruit", "Apple", "Peach"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_guests_cannot_prompt() {
+        let mut host =
+            ShareHost::start("127.0.0.1:0", ShareMode::ReadOnly, "ada".to_string()).unwrap();
+        let guest = ShareGuest::connect(&host.addr.to_string(), &host.token, "\u{1b}[31m")
+            .await
+            .unwrap();
+        assert!(!guest.send_prompt("rm -rf".to_string()));
+        wait_for(|| (!host.poll().is_empty()).then_some(())).await;
+        assert_eq!(host.guests, vec!["[31m".to_string()]);
+    }
+}
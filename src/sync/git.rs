@@ -0,0 +1,134 @@
+//! Git remote: a working copy of the sessions repository, driven through the
+//! `git` command so the user's credentials, SSH keys and helpers apply.
+//!
+//! The working copy is only a cache of the remote branch. Every fetch resets
+//! it to the branch, discarding anything a failed push left behind; the
+//! sessions themselves live in the session store.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::{valid_id, Remote, Versions};
+use crate::session::Session;
+
+/// A git repository holding one `<id>.json` file per session.
+pub struct GitRemote {
+    dir: PathBuf,
+    branch: String,
+}
+
+impl GitRemote {
+    /// Use (creating if needed) the working copy at `dir` for `url`.
+    pub fn open(dir: impl Into<PathBuf>, url: &str, branch: &str) -> Result<Self> {
+        let remote = Self {
+            dir: dir.into(),
+            branch: branch.to_string(),
+        };
+        if !remote.dir.join(".git").exists() {
+            std::fs::create_dir_all(&remote.dir)
+                .with_context(|| format!("Failed to create directory: {}", remote.dir.display()))?;
+            remote.git(&["init", "-q"])?;
+            // Sync commits are machine-made; don't depend on a global identity
+            remote.git(&["config", "user.name", "scry"])?;
+            remote.git(&["config", "user.email", "scry@localhost"])?;
+        }
+        if remote.git(&["remote", "set-url", "origin", url]).is_err() {
+            remote.git(&["remote", "add", "origin", url])?;
+        }
+        Ok(remote)
+    }
+
+    /// Run git in the working copy, returning its output.
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .context("Failed to run git; is it installed?")?;
+        if !output.status.success() {
+            // Some failures, like "nothing to commit", are reported on stdout
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let reason = if stderr.trim().is_empty() {
+                stdout
+            } else {
+                stderr
+            };
+            bail!("git {} failed: {}", args[0], reason.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        if !valid_id(id) {
+            bail!("Invalid session id: {}", id);
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+/// Read a session file.
+fn read(path: &Path) -> Result<Session> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+impl Remote for GitRemote {
+    fn fetch(&mut self) -> Result<()> {
+        self.git(&["fetch", "-q", "origin"])?;
+        let tracking = format!("refs/remotes/origin/{}", self.branch);
+        if self
+            .git(&["rev-parse", "--verify", "-q", &tracking])
+            .is_ok()
+        {
+            self.git(&["checkout", "-q", "-f", "-B", &self.branch, &tracking])?;
+        } else {
+            // Nothing pushed yet: start the branch from scratch
+            let head = format!("refs/heads/{}", self.branch);
+            self.git(&["symbolic-ref", "HEAD", &head])?;
+            let _ = self.git(&["update-ref", "-d", &head]);
+            self.git(&["read-tree", "--empty"])?;
+        }
+        self.git(&["clean", "-q", "-f", "-d", "-x"])?;
+        Ok(())
+    }
+
+    fn versions(&self) -> Result<Versions> {
+        let mut versions = Versions::new();
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                let session = read(&path)?;
+                // Files are looked up by id, so skip any saved under another name
+                if valid_id(&session.id) && path.file_stem().is_some_and(|s| *s == *session.id) {
+                    versions.insert(session.id, session.updated_at);
+                }
+            }
+        }
+        Ok(versions)
+    }
+
+    fn get(&self, id: &str) -> Result<Session> {
+        read(&self.path(id)?)
+    }
+
+    fn put(&mut self, session: &Session) -> Result<()> {
+        let path = self.path(&session.id)?;
+        let json = serde_json::to_string_pretty(session)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn publish(&mut self, message: &str) -> Result<()> {
+        self.git(&["add", "-A"])?;
+        self.git(&["commit", "-q", "-m", message])?;
+        let refspec = format!("HEAD:refs/heads/{}", self.branch);
+        self.git(&["push", "-q", "origin", &refspec])
+            .context("Push failed; sync again to merge the other changes")?;
+        Ok(())
+    }
+}
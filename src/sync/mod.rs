@@ -0,0 +1,409 @@
+//! Syncing saved sessions with a remote copy.
+//!
+//! Sessions are mirrored as `<id>.json` files in a remote chosen in config
+//! (`[sync] backend`):
+//! - `git` - A git repository, driven through the `git` command
+//! - `webdav` - A WebDAV collection; S3, Dropbox and friends are reachable
+//!   through `rclone serve webdav`
+//!
+//! A sync compares each session's `updated_at` on both sides with the version
+//! both agreed on at the last sync (kept in `sync-state.json`). A session
+//! changed on one side is copied to the other; one changed on both is a
+//! conflict: the newer version wins (last writer wins) and the older one is
+//! kept as a "(conflict copy)" under a new id on both sides. Deletions are not
+//! synced, so a session deleted on one side comes back from the other.
+//!
+//! Syncing blocks on the network and the store, so run it off the event loop.
+
+mod git;
+mod webdav;
+
+pub use git::GitRemote;
+pub use webdav::WebDavRemote;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::{SyncBackend, SyncConfig};
+use crate::durable::{self, WriteOptions};
+use crate::session::{Session, SessionStore};
+
+/// Update time of each session, by id.
+pub type Versions = BTreeMap<String, DateTime<Utc>>;
+
+/// Where sessions are synced to.
+pub trait Remote {
+    /// Bring the local view of the remote up to date.
+    fn fetch(&mut self) -> Result<()>;
+
+    /// Sessions on the remote, as of the last fetch plus puts since.
+    fn versions(&self) -> Result<Versions>;
+
+    /// Read a session from the remote.
+    fn get(&self, id: &str) -> Result<Session>;
+
+    /// Write a session to the remote; it may only be visible after [`Remote::publish`].
+    fn put(&mut self, session: &Session) -> Result<()>;
+
+    /// Make the sessions put since the last fetch visible to other machines.
+    fn publish(&mut self, message: &str) -> Result<()>;
+}
+
+/// What to do with one session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Copy the local version to the remote
+    Push,
+    /// Copy the remote version to the local store
+    Pull,
+    /// Both sides changed since the last sync
+    Conflict,
+}
+
+/// What a sync did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub conflicts: usize,
+    /// Sessions rewritten in the local store
+    pub updated: Vec<String>,
+}
+
+impl SyncReport {
+    /// Whether anything was copied.
+    pub fn changed(&self) -> bool {
+        self.pushed + self.pulled + self.conflicts > 0
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.changed() {
+            return write!(f, "already up to date");
+        }
+        write!(f, "{} up, {} down", self.pushed, self.pulled)?;
+        match self.conflicts {
+            0 => Ok(()),
+            1 => write!(f, ", 1 conflict copied"),
+            n => write!(f, ", {} conflicts copied", n),
+        }
+    }
+}
+
+/// Decide what to do with every session, given the local and remote
+/// versions and those agreed on at the last sync.
+pub fn plan(local: &Versions, remote: &Versions, base: &Versions) -> Vec<(String, Action)> {
+    let ids: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    ids.into_iter()
+        .filter_map(|id| {
+            let action = match (local.get(id), remote.get(id)) {
+                (Some(_), None) => Action::Push,
+                (None, Some(_)) => Action::Pull,
+                (Some(mine), Some(theirs)) if mine == theirs => return None,
+                (Some(mine), Some(theirs)) => {
+                    let agreed = base.get(id);
+                    match (Some(mine) != agreed, Some(theirs) != agreed) {
+                        (true, false) => Action::Push,
+                        (false, true) => Action::Pull,
+                        _ => Action::Conflict,
+                    }
+                }
+                (None, None) => return None,
+            };
+            Some((id.clone(), action))
+        })
+        .collect()
+}
+
+/// Sync `store` with `remote`, starting from the versions in `base`.
+///
+/// `base` is only updated if the whole sync succeeds.
+pub fn sync(
+    store: &dyn SessionStore,
+    remote: &mut dyn Remote,
+    base: &mut Versions,
+) -> Result<SyncReport> {
+    remote.fetch()?;
+    let local: Versions = store
+        .list()?
+        .into_iter()
+        .map(|s| (s.id, s.updated_at))
+        .collect();
+    let theirs = remote.versions()?;
+
+    let mut agreed: Versions = local
+        .iter()
+        .filter(|(id, at)| theirs.get(*id) == Some(at))
+        .map(|(id, at)| (id.clone(), *at))
+        .collect();
+    let mut report = SyncReport::default();
+    for (id, action) in plan(&local, &theirs, base) {
+        match action {
+            Action::Push => {
+                let session = store.load(&id)?;
+                remote.put(&session)?;
+                agreed.insert(id, session.updated_at);
+                report.pushed += 1;
+            }
+            Action::Pull => {
+                let mut session = remote.get(&id)?;
+                // Saved before the indexer got to it on the other machine
+                session.derive_title();
+                store.save(&session)?;
+                agreed.insert(id.clone(), session.updated_at);
+                report.updated.push(id);
+                report.pulled += 1;
+            }
+            Action::Conflict => {
+                let mine = store.load(&id)?;
+                let theirs = remote.get(&id)?;
+                let (winner, loser) = if mine.updated_at >= theirs.updated_at {
+                    remote.put(&mine)?;
+                    (mine, theirs)
+                } else {
+                    store.save(&theirs)?;
+                    report.updated.push(id.clone());
+                    (theirs, mine)
+                };
+                let mut copy = loser.duplicate();
+                copy.title = format!(
+                    "{} (conflict copy)",
+                    if loser.title.is_empty() {
+                        "Untitled"
+                    } else {
+                        &loser.title
+                    }
+                );
+                store.save(&copy)?;
+                remote.put(&copy)?;
+                agreed.insert(id, winner.updated_at);
+                agreed.insert(copy.id.clone(), copy.updated_at);
+                report.updated.push(copy.id);
+                report.conflicts += 1;
+            }
+        }
+    }
+    if report.pushed + report.conflicts > 0 {
+        remote.publish(&format!("scry sync: {}", report))?;
+    }
+    *base = agreed;
+    Ok(report)
+}
+
+/// Whether `id` is safe to use as a file name on the remote.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Versions agreed on at the last sync, per remote.
+#[derive(Debug, Default)]
+pub struct SyncState {
+    path: PathBuf,
+    remotes: BTreeMap<String, Versions>,
+}
+
+impl SyncState {
+    /// Returns `~/.local/share/scry-cli/sync-state.json` on Linux/macOS.
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir =
+            dirs::data_local_dir().context("Could not determine local data directory")?;
+        Ok(data_dir.join("scry-cli").join("sync-state.json"))
+    }
+
+    /// Load the state at `path`; a missing file is an empty state.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let remotes = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, remotes })
+    }
+
+    /// Versions agreed on with `remote`.
+    pub fn versions(&mut self, remote: &str) -> &mut Versions {
+        self.remotes.entry(remote.to_string()).or_default()
+    }
+
+    /// Write the state back.
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.remotes)?;
+        durable::write(&self.path, json.as_bytes(), WriteOptions::default())
+    }
+}
+
+/// Open the remote configured in `[sync]`.
+pub fn open_remote(config: &SyncConfig) -> Result<Box<dyn Remote>> {
+    if config.remote.is_empty() {
+        bail!("Set [sync] remote in the config file");
+    }
+    Ok(match config.backend {
+        None => bail!("Sync is off; set [sync] backend in the config file"),
+        Some(SyncBackend::Git) => {
+            let dir = dirs::data_local_dir()
+                .context("Could not determine local data directory")?
+                .join("scry-cli")
+                .join("sync-git");
+            Box::new(GitRemote::open(dir, &config.remote, &config.branch)?)
+        }
+        Some(SyncBackend::Webdav) => {
+            let password = std::env::var(&config.password_env).ok();
+            Box::new(WebDavRemote::new(
+                &config.remote,
+                config.username.clone(),
+                password,
+            )?)
+        }
+    })
+}
+
+/// Sync `store` with the remote configured in `[sync]`.
+///
+/// Blocks; WebDAV additionally needs to be called from within the Tokio
+/// runtime (e.g. in `spawn_blocking`).
+pub fn run(config: &SyncConfig, store: &dyn SessionStore) -> Result<SyncReport> {
+    let mut remote = open_remote(config)?;
+    let mut state = SyncState::load(SyncState::default_path()?)?;
+    let report = sync(store, remote.as_mut(), state.versions(&config.remote))?;
+    state.save()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::session::SqliteStore;
+    use std::process::Command;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn versions(entries: &[(&str, i64)]) -> Versions {
+        entries
+            .iter()
+            .map(|(id, secs)| (id.to_string(), at(*secs)))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let base = versions(&[("same", 1), ("mine", 1), ("theirs", 1), ("both", 1)]);
+        let local = versions(&[
+            ("same", 1),
+            ("mine", 2),
+            ("theirs", 1),
+            ("both", 2),
+            ("new", 5),
+        ]);
+        let remote = versions(&[
+            ("same", 1),
+            ("mine", 1),
+            ("theirs", 3),
+            ("both", 3),
+            ("far", 5),
+        ]);
+        let actions: BTreeMap<_, _> = plan(&local, &remote, &base).into_iter().collect();
+        assert_eq!(actions.get("same"), None);
+        assert_eq!(actions["mine"], Action::Push);
+        assert_eq!(actions["theirs"], Action::Pull);
+        assert_eq!(actions["both"], Action::Conflict);
+        assert_eq!(actions["new"], Action::Push);
+        assert_eq!(actions["far"], Action::Pull);
+
+        // Never synced before: a session on both sides with different versions conflicts
+        let actions = plan(&local, &remote, &Versions::new());
+        assert_eq!(
+            actions
+                .iter()
+                .filter(|(_, a)| *a == Action::Conflict)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_valid_id() {
+        assert!(valid_id("20250101-120000-00ff"));
+        assert!(!valid_id("../secrets"));
+        assert!(!valid_id(""));
+    }
+
+    fn git(dir: &std::path::Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    fn session(prompt: &str) -> Session {
+        let mut session = Session::new("ollama", "llama3");
+        session.set_messages(&[Message::user(prompt.to_string())]);
+        session.derive_title();
+        session
+    }
+
+    #[test]
+    fn test_sync_through_git() {
+        let dir = tempfile::tempdir().unwrap();
+        if !git(dir.path(), &["init", "-q", "--bare", "remote.git"]) {
+            return; // git is not installed
+        }
+        let url = dir.path().join("remote.git").display().to_string();
+        let laptop = SqliteStore::in_memory().unwrap();
+        let desktop = SqliteStore::in_memory().unwrap();
+        let mut laptop_remote = GitRemote::open(dir.path().join("laptop"), &url, "main").unwrap();
+        let mut desktop_remote = GitRemote::open(dir.path().join("desktop"), &url, "main").unwrap();
+        let (mut laptop_base, mut desktop_base) = (Versions::new(), Versions::new());
+
+        let mut shared = session("Plan the garden");
+        laptop.save(&shared).unwrap();
+        let report = sync(&laptop, &mut laptop_remote, &mut laptop_base).unwrap();
+        assert_eq!((report.pushed, report.pulled), (1, 0));
+        let report = sync(&desktop, &mut desktop_remote, &mut desktop_base).unwrap();
+        assert_eq!((report.pushed, report.pulled), (0, 1));
+        assert_eq!(desktop.load(&shared.id).unwrap(), shared);
+        assert!(!sync(&laptop, &mut laptop_remote, &mut laptop_base)
+            .unwrap()
+            .changed());
+
+        // Both edit the same session; the desktop's later edit wins
+        shared.updated_at = at(10);
+        shared.messages[0].content = "Plan the garden (laptop)".to_string();
+        laptop.save(&shared).unwrap();
+        shared.updated_at = at(20);
+        shared.messages[0].content = "Plan the garden (desktop)".to_string();
+        desktop.save(&shared).unwrap();
+        sync(&desktop, &mut desktop_remote, &mut desktop_base).unwrap();
+        let report = sync(&laptop, &mut laptop_remote, &mut laptop_base).unwrap();
+        assert_eq!(report.conflicts, 1);
+        assert_eq!(report.to_string(), "0 up, 0 down, 1 conflict copied");
+
+        let won = laptop.load(&shared.id).unwrap();
+        assert_eq!(won.messages[0].content, "Plan the garden (desktop)");
+        let copy = laptop
+            .list()
+            .unwrap()
+            .into_iter()
+            .find(|s| s.id != shared.id)
+            .unwrap();
+        assert_eq!(copy.title, "Plan the garden (conflict copy)");
+
+        // The copy reaches the desktop too
+        let report = sync(&desktop, &mut desktop_remote, &mut desktop_base).unwrap();
+        assert_eq!(report.pulled, 1);
+        let copy = desktop.load(&copy.id).unwrap();
+        assert_eq!(copy.messages[0].content, "Plan the garden (laptop)");
+    }
+}
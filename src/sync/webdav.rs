@@ -0,0 +1,131 @@
+//! WebDAV remote: session files in a WebDAV collection.
+//!
+//! Next to the `<id>.json` files the collection holds `index.json`, mapping
+//! each id to its `updated_at`, so a sync costs one request plus one per
+//! changed session. Requests run on the Tokio runtime the sync was started
+//! from.
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use tokio::runtime::Handle;
+
+use super::{valid_id, Remote, Versions};
+use crate::session::Session;
+
+/// Name of the index file in the collection.
+const INDEX: &str = "index.json";
+
+/// A WebDAV collection holding one `<id>.json` file per session.
+pub struct WebDavRemote {
+    client: Client,
+    runtime: Handle,
+    /// Collection URL, without a trailing slash
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    /// Index as of the last fetch, plus puts since
+    index: Versions,
+}
+
+impl WebDavRemote {
+    /// A remote for the collection at `url`; call from within the runtime.
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        let runtime = Handle::try_current().context("WebDAV sync needs the async runtime")?;
+        Ok(Self {
+            client: Client::new(),
+            runtime,
+            url: url.trim_end_matches('/').to_string(),
+            username,
+            password,
+            index: Versions::new(),
+        })
+    }
+
+    fn request(&self, method: Method, name: &str) -> RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}/{}", self.url, name));
+        match &self.username {
+            Some(username) => builder.basic_auth(username, self.password.as_ref()),
+            None => builder,
+        }
+    }
+
+    /// Send a request, blocking until the response arrives.
+    fn send(&self, builder: RequestBuilder) -> Result<Response> {
+        self.runtime
+            .block_on(builder.send())
+            .with_context(|| format!("Could not reach {}", self.url))
+    }
+
+    /// GET `name`, or None if it does not exist.
+    fn get_text(&self, name: &str) -> Result<Option<String>> {
+        let response = self.send(self.request(Method::GET, name))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(self.runtime.block_on(response.text())?)),
+            status => bail!("WebDAV GET {} failed ({})", name, status),
+        }
+    }
+
+    /// PUT `body` as `name`, creating the collection if it is missing.
+    fn put_text(&self, name: &str, body: String) -> Result<()> {
+        let put = || self.send(self.request(Method::PUT, name).body(body.clone()));
+        let mut response = put()?;
+        if matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::CONFLICT
+        ) {
+            let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+            self.send(self.request(mkcol, ""))?;
+            response = put()?;
+        }
+        if !response.status().is_success() {
+            bail!("WebDAV PUT {} failed ({})", name, response.status());
+        }
+        Ok(())
+    }
+
+    fn file(id: &str) -> Result<String> {
+        if !valid_id(id) {
+            bail!("Invalid session id: {}", id);
+        }
+        Ok(format!("{}.json", id))
+    }
+}
+
+impl Remote for WebDavRemote {
+    fn fetch(&mut self) -> Result<()> {
+        self.index = match self.get_text(INDEX)? {
+            Some(json) => {
+                serde_json::from_str(&json).context("Failed to parse the remote index")?
+            }
+            None => Versions::new(),
+        };
+        self.index.retain(|id, _| valid_id(id));
+        Ok(())
+    }
+
+    fn versions(&self) -> Result<Versions> {
+        Ok(self.index.clone())
+    }
+
+    fn get(&self, id: &str) -> Result<Session> {
+        let name = Self::file(id)?;
+        let json = self
+            .get_text(&name)?
+            .with_context(|| format!("{} is in the index but not on the server", name))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", name))
+    }
+
+    fn put(&mut self, session: &Session) -> Result<()> {
+        let name = Self::file(&session.id)?;
+        self.put_text(&name, serde_json::to_string_pretty(session)?)?;
+        self.index.insert(session.id.clone(), session.updated_at);
+        Ok(())
+    }
+
+    fn publish(&mut self, _message: &str) -> Result<()> {
+        self.put_text(INDEX, serde_json::to_string_pretty(&self.index)?)
+    }
+}
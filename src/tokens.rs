@@ -0,0 +1,177 @@
+//! Token estimates and model context windows.
+//!
+//! Counting exactly would need each provider's tokenizer, so text is split
+//! the way BPE tokenizers mostly split it: a token per short word, longer
+//! words and digit runs in pieces, a token per symbol and per CJK character.
+//! It is an estimate, but close enough to see a conversation approach the
+//! model's context window.
+
+use crate::llm::Provider;
+
+/// Tokens each message adds for its role and framing.
+pub const MESSAGE_OVERHEAD: usize = 4;
+
+/// Context window assumed for models not listed in [`context_window`].
+pub const DEFAULT_CONTEXT_WINDOW: usize = 32_768;
+
+/// Ollama's default `num_ctx`: the context it runs a model with unless the
+/// Modelfile raises it, whatever the model itself supports.
+pub const OLLAMA_CONTEXT_WINDOW: usize = 4_096;
+
+/// Estimated tokens in `text` for `provider`'s tokenizer.
+pub fn estimate(text: &str, provider: Provider) -> usize {
+    let mut tokens = 0;
+    // Length of the word or digit run being read, in bytes
+    let mut word = 0usize;
+    let mut digits = 0usize;
+    let mut spaces = 0;
+
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            tokens += word.div_ceil(6);
+            word = 0;
+            digits += 1;
+            spaces = 0;
+            continue;
+        }
+        tokens += digits.div_ceil(3);
+        digits = 0;
+        if c.is_alphabetic() && c.len_utf8() <= 2 {
+            word += c.len_utf8();
+            spaces = 0;
+            continue;
+        }
+        tokens += word.div_ceil(6);
+        word = 0;
+        if c == ' ' {
+            // A single space joins the next word; indentation is its own token
+            spaces += 1;
+            if spaces == 2 {
+                tokens += 1;
+            }
+        } else {
+            spaces = 0;
+            // Symbols, newlines and CJK characters
+            tokens += 1;
+        }
+    }
+    tokens += word.div_ceil(6) + digits.div_ceil(3);
+
+    match provider {
+        // Claude's tokenizer splits English a little finer than tiktoken
+        Provider::Anthropic => tokens * 11 / 10,
+        _ => tokens,
+    }
+}
+
+/// Estimated tokens a chat message takes up, framing included.
+pub fn message_tokens(content: &str, provider: Provider) -> usize {
+    estimate(content, provider) + MESSAGE_OVERHEAD
+}
+
+/// Context window of `model`, in tokens.
+pub fn context_window(provider: Provider, model: &str) -> usize {
+    if provider == Provider::Ollama {
+        return OLLAMA_CONTEXT_WINDOW;
+    }
+    let model = model.to_lowercase();
+    let known: &[(&str, usize)] = &[
+        ("claude", 200_000),
+        ("gpt-5", 400_000),
+        ("gpt-4.1", 1_047_576),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4", 8_192),
+        ("gpt-3.5", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4", 200_000),
+        ("gemini", 1_048_576),
+        ("deepseek", 128_000),
+        ("llama-3", 131_072),
+        ("llama3", 131_072),
+        ("qwen", 32_768),
+        ("mixtral", 32_768),
+        ("gemma", 8_192),
+    ];
+    // Checked in order, so `gpt-4o` is matched before `gpt-4`
+    known
+        .iter()
+        .find(|(name, _)| {
+            model
+                .split('/')
+                .next_back()
+                .is_some_and(|id| id.starts_with(name) || id.contains(&format!("-{}", name)))
+        })
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, window)| *window)
+}
+
+/// How much of a context window a conversation fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextUsage {
+    pub tokens: usize,
+    pub window: usize,
+}
+
+impl ContextUsage {
+    /// Share of the window in use, from 0.0 (may exceed 1.0).
+    pub fn fraction(&self) -> f64 {
+        self.tokens as f64 / self.window.max(1) as f64
+    }
+
+    /// Whole percent of the window in use.
+    pub fn percent(&self) -> usize {
+        (self.fraction() * 100.0).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_words_symbols_and_digits() {
+        assert_eq!(estimate("", Provider::OpenAI), 0);
+        // hello , world !
+        assert_eq!(estimate("hello, world!", Provider::OpenAI), 4);
+        // 2025 splits into 2 digit groups
+        assert_eq!(estimate("in 2025", Provider::OpenAI), 3);
+        // Long words are several tokens; indentation counts once
+        assert_eq!(estimate("    internationalization", Provider::OpenAI), 5);
+        // CJK characters are a token each
+        assert_eq!(estimate("你好", Provider::OpenAI), 2);
+    }
+
+    #[test]
+    fn test_estimate_is_near_four_bytes_per_token_on_prose() {
+        let prose = "The quick brown fox jumps over the lazy dog while the cat \
+                     watches from the window, wondering why anyone would run.";
+        let tokens = estimate(prose, Provider::OpenAI);
+        let bytes_based = prose.len() / 4;
+        assert!(tokens.abs_diff(bytes_based) * 5 < bytes_based, "{} vs {}", tokens, bytes_based);
+        assert!(estimate(prose, Provider::Anthropic) > tokens);
+    }
+
+    #[test]
+    fn test_context_windows() {
+        assert_eq!(context_window(Provider::Anthropic, "claude-sonnet-4-5"), 200_000);
+        assert_eq!(context_window(Provider::OpenAI, "gpt-4o-mini"), 128_000);
+        assert_eq!(context_window(Provider::OpenAI, "gpt-4"), 8_192);
+        assert_eq!(
+            context_window(Provider::OpenRouter, "meta-llama/llama-3.1-70b-instruct"),
+            131_072
+        );
+        assert_eq!(context_window(Provider::Ollama, "llama3.2"), OLLAMA_CONTEXT_WINDOW);
+        assert_eq!(context_window(Provider::LocalServer, "my-model"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_context_usage_percent() {
+        let usage = ContextUsage {
+            tokens: 50_000,
+            window: 200_000,
+        };
+        assert_eq!(usage.percent(), 25);
+        assert_eq!(ContextUsage { tokens: 1, window: 0 }.percent(), 100);
+    }
+}
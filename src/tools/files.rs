@@ -0,0 +1,390 @@
+//! Built-in file tools: `read_file`, `write_file` and `list_dir`.
+//!
+//! All three work inside a [`Sandbox`]: a root directory (the project by
+//! default, or `[tools] root`) and, optionally, an allowlist of files and
+//! directories under it (`[tools] paths`). Paths from the model are taken
+//! relative to the root, and are checked after `..` and symlinks are
+//! resolved, so no path can reach outside what the sandbox allows.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::Tool;
+use crate::config::ToolsConfig;
+use crate::durable::{self, WriteOptions};
+use crate::llm::ToolDefinition;
+use crate::variables;
+
+/// Largest file `read_file` returns.
+pub const MAX_READ_BYTES: u64 = 256 * 1024;
+
+/// Most entries `list_dir` returns.
+const MAX_ENTRIES: usize = 500;
+
+/// Where the file tools may read and write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    /// Canonical root directory
+    root: PathBuf,
+    /// Canonical files or directories under the root; empty allows the whole root
+    allowed: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// A sandbox at `root` allowing `paths` (relative to the root), or all
+    /// of it when `paths` is empty.
+    pub fn new(root: impl Into<PathBuf>, paths: &[PathBuf]) -> Self {
+        let root = root.into();
+        let root = root.canonicalize().unwrap_or(root);
+        let allowed = paths
+            .iter()
+            .filter_map(|path| normalize(&root.join(variables::resolve(path))))
+            .collect();
+        Self { root, allowed }
+    }
+
+    /// The sandbox `[tools]` configures, rooted at the working directory
+    /// unless `root` is set.
+    pub fn from_config(config: &ToolsConfig) -> Self {
+        let root = match &config.root {
+            Some(root) => variables::resolve(root),
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+        Self::new(root, &config.paths)
+    }
+
+    /// The root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve `path` from the model to a path the tools may use, or say
+    /// why it may not be used.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let joined = self.root.join(variables::resolve(Path::new(path)));
+        let Some(resolved) = normalize(&joined) else {
+            bail!("{} cannot be resolved", path);
+        };
+        if !resolved.starts_with(&self.root) {
+            bail!("{} is outside {}", path, self.root.display());
+        }
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|a| resolved.starts_with(a)) {
+            bail!("{} is not allowed; add it to [tools] paths", path);
+        }
+        Ok(resolved)
+    }
+
+    /// `path` relative to the root, for descriptions.
+    fn display(&self, path: &Path) -> String {
+        match path.strip_prefix(&self.root) {
+            Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+            Ok(rel) => rel.display().to_string(),
+            Err(_) => path.display().to_string(),
+        }
+    }
+}
+
+/// `path` with symlinks and `..` resolved. A path that does not exist yet
+/// (a file about to be written) resolves through its nearest existing
+/// ancestor; `..` is not allowed in the part that does not exist.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    if let Ok(canonical) = path.canonicalize() {
+        return Some(canonical);
+    }
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        let name = existing.file_name()?;
+        missing.push(name.to_os_string());
+        existing = existing.parent()?;
+        if let Ok(canonical) = existing.canonicalize() {
+            // `file_name` is `None` for a trailing `..`, so these are plain names
+            let mut resolved = canonical;
+            for name in missing.iter().rev() {
+                resolved.push(name);
+            }
+            return Some(resolved);
+        }
+    }
+}
+
+/// The `path` argument of a call.
+fn path_argument(arguments: &Value) -> Result<&str> {
+    arguments["path"]
+        .as_str()
+        .context("Missing the \"path\" argument")
+}
+
+/// Unix permissions of the file at `path`, if it exists.
+#[cfg(unix)]
+fn existing_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).ok().map(|meta| meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn existing_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Reads a text file.
+pub struct ReadFile {
+    sandbox: Arc<Sandbox>,
+}
+
+impl ReadFile {
+    pub fn new(sandbox: Arc<Sandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for ReadFile {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a text file in the project".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Path relative to the project root"}
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn describe(&self, arguments: &Value) -> String {
+        format!("read {}", arguments["path"].as_str().unwrap_or("?"))
+    }
+
+    fn run(&self, arguments: &Value) -> Result<String> {
+        let path = self.sandbox.resolve(path_argument(arguments)?)?;
+        let size = fs::metadata(&path)
+            .with_context(|| format!("Failed to read {}", self.sandbox.display(&path)))?
+            .len();
+        if size > MAX_READ_BYTES {
+            bail!(
+                "{} is {} bytes; read_file returns at most {}",
+                self.sandbox.display(&path),
+                size,
+                MAX_READ_BYTES
+            );
+        }
+        fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {} as text", self.sandbox.display(&path)))
+    }
+}
+
+/// Writes a file, creating it and its directories if needed.
+pub struct WriteFile {
+    sandbox: Arc<Sandbox>,
+}
+
+impl WriteFile {
+    pub fn new(sandbox: Arc<Sandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for WriteFile {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "write_file".to_string(),
+            description: "Write a file in the project, replacing its contents".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Path relative to the project root"},
+                    "content": {"type": "string", "description": "The whole new contents"}
+                },
+                "required": ["path", "content"]
+            }),
+        }
+    }
+
+    fn describe(&self, arguments: &Value) -> String {
+        let bytes = arguments["content"].as_str().map_or(0, str::len);
+        format!(
+            "write {} bytes to {}",
+            bytes,
+            arguments["path"].as_str().unwrap_or("?")
+        )
+    }
+
+    fn run(&self, arguments: &Value) -> Result<String> {
+        let path = self.sandbox.resolve(path_argument(arguments)?)?;
+        let content = arguments["content"]
+            .as_str()
+            .context("Missing the \"content\" argument")?;
+        if path.is_dir() {
+            bail!("{} is a directory", self.sandbox.display(&path));
+        }
+        // The file is replaced through a temporary one, which would reset its mode
+        let options = WriteOptions {
+            mode: existing_mode(&path),
+            ..WriteOptions::default()
+        };
+        durable::write(&path, content.as_bytes(), options)
+            .with_context(|| format!("Failed to write {}", self.sandbox.display(&path)))?;
+        Ok(format!(
+            "Wrote {} bytes to {}",
+            content.len(),
+            self.sandbox.display(&path)
+        ))
+    }
+}
+
+/// Lists a directory: one entry per line, directories ending in `/`.
+pub struct ListDir {
+    sandbox: Arc<Sandbox>,
+}
+
+impl ListDir {
+    pub fn new(sandbox: Arc<Sandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for ListDir {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_dir".to_string(),
+            description: "List the files and directories in a project directory".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Path relative to the project root (default: the root)"}
+                }
+            }),
+        }
+    }
+
+    fn describe(&self, arguments: &Value) -> String {
+        format!("list {}", arguments["path"].as_str().unwrap_or("."))
+    }
+
+    fn run(&self, arguments: &Value) -> Result<String> {
+        let path = self
+            .sandbox
+            .resolve(arguments["path"].as_str().unwrap_or("."))?;
+        let mut entries: Vec<String> = fs::read_dir(&path)
+            .with_context(|| format!("Failed to list {}", self.sandbox.display(&path)))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().is_dir() {
+                    format!("{}/", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        entries.sort();
+        let total = entries.len();
+        if total > MAX_ENTRIES {
+            entries.truncate(MAX_ENTRIES);
+            entries.push(format!("... and {} more", total - MAX_ENTRIES));
+        }
+        Ok(entries.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/ui")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("secret.env"), "TOKEN=x\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_paths_stay_inside_the_sandbox() {
+        let dir = project();
+        let sandbox = Sandbox::new(dir.path(), &[]);
+        let root = sandbox.root().to_path_buf();
+        assert_eq!(sandbox.resolve("src/main.rs").unwrap(), root.join("src/main.rs"));
+        assert_eq!(sandbox.resolve("src/../new.txt").unwrap(), root.join("new.txt"));
+        assert!(sandbox.resolve("../outside.txt").is_err());
+        assert!(sandbox.resolve("src/new/../../../x").is_err());
+        assert!(sandbox.resolve("/etc/passwd").is_err());
+        // An absolute path inside the root is fine
+        assert!(sandbox.resolve(&root.join("secret.env").display().to_string()).is_ok());
+
+        // With an allowlist, only the listed paths can be used
+        let sandbox = Sandbox::new(dir.path(), &[PathBuf::from("src")]);
+        assert!(sandbox.resolve("src/ui/new.rs").is_ok());
+        let error = sandbox.resolve("secret.env").unwrap_err().to_string();
+        assert!(error.contains("[tools] paths"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_cannot_escape() {
+        let dir = project();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let sandbox = Sandbox::new(dir.path(), &[]);
+        assert!(sandbox.resolve("link/file.txt").is_err());
+    }
+
+    #[test]
+    fn test_read_write_and_list() {
+        let dir = project();
+        let sandbox = Arc::new(Sandbox::new(dir.path(), &[]));
+        let (read, write, list) = (
+            ReadFile::new(sandbox.clone()),
+            WriteFile::new(sandbox.clone()),
+            ListDir::new(sandbox),
+        );
+
+        let args = json!({"path": "src/main.rs"});
+        assert_eq!(read.run(&args).unwrap(), "fn main() {}\n");
+        assert_eq!(read.describe(&args), "read src/main.rs");
+
+        let args = json!({"path": "src/ui/mod.rs", "content": "pub mod chat;\n"});
+        assert_eq!(write.describe(&args), "write 14 bytes to src/ui/mod.rs");
+        assert_eq!(write.run(&args).unwrap(), "Wrote 14 bytes to src/ui/mod.rs");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/ui/mod.rs")).unwrap(),
+            "pub mod chat;\n"
+        );
+        assert!(write.run(&json!({"path": "src", "content": ""})).is_err());
+
+        assert_eq!(list.run(&json!({"path": "src"})).unwrap(), "main.rs\nui/");
+        assert_eq!(list.run(&json!({})).unwrap(), "secret.env\nsrc/");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_the_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = project();
+        let script = dir.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let write = WriteFile::new(Arc::new(Sandbox::new(dir.path(), &[])));
+        write.run(&json!({"path": "run.sh", "content": "#!/bin/sh\necho hi\n"})).unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(), "#!/bin/sh\necho hi\n");
+        let mode = fs::metadata(&script).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o750);
+    }
+
+    #[test]
+    fn test_large_files_are_not_read() {
+        let dir = project();
+        fs::write(dir.path().join("big.log"), vec![b'x'; MAX_READ_BYTES as usize + 1]).unwrap();
+        let read = ReadFile::new(Arc::new(Sandbox::new(dir.path(), &[])));
+        assert!(read.run(&json!({"path": "big.log"})).is_err());
+    }
+}
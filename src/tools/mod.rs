@@ -0,0 +1,247 @@
+//! Tools the model can call, and the dispatcher that runs them.
+//!
+//! Every call goes through [`ToolDispatcher::dispatch`], which enforces the
+//! [`PermissionPolicy`]: allowed tools run at once, denied ones never run,
+//! and the rest wait for [`ToolDispatcher::confirm`]. Each run is recorded in
+//! the audit log. The built-in file tools live in [`files`].
+
+pub mod files;
+pub mod permission;
+
+pub use files::Sandbox;
+pub use permission::PermissionPolicy;
+
+use anyhow::Result;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::audit::{AuditAction, AuditLog, AuditRecord};
+use crate::config::{Permission, ToolsConfig};
+use crate::llm::{ToolCall, ToolDefinition};
+
+/// A function the model may call.
+pub trait Tool: Send + Sync {
+    /// Name, description and argument schema offered to the model.
+    fn definition(&self) -> ToolDefinition;
+
+    /// What a call will do, for the confirmation dialog and the audit log.
+    fn describe(&self, arguments: &Value) -> String;
+
+    /// Run the call and return its output for the model.
+    fn run(&self, arguments: &Value) -> Result<String>;
+}
+
+/// What became of a tool call.
+#[derive(Debug)]
+pub enum Dispatch {
+    /// The tool ran, successfully or not.
+    Ran(Result<String>),
+    /// The tool needs the user's permission; answer with [`ToolDispatcher::confirm`].
+    NeedsConfirmation {
+        /// What the call will do
+        summary: String,
+    },
+    /// The tool was not run.
+    Refused(String),
+}
+
+/// Registered tools and the permission policy that guards them.
+pub struct ToolDispatcher {
+    tools: Vec<Box<dyn Tool>>,
+    permissions: PermissionPolicy,
+    audit: Option<AuditLog>,
+}
+
+impl ToolDispatcher {
+    /// A dispatcher with no tools, guarded by `config` for the working
+    /// directory and auditing to the default log.
+    pub fn new(config: &ToolsConfig) -> Self {
+        let project = std::env::current_dir().unwrap_or_default();
+        let mut permissions = PermissionPolicy::new(config.clone(), project);
+        if let Ok(path) = PermissionPolicy::default_path() {
+            permissions = permissions.with_store(path);
+        }
+        Self {
+            tools: Vec::new(),
+            permissions,
+            audit: AuditLog::open_default().ok(),
+        }
+    }
+
+    /// A dispatcher with the built-in file tools, confined to the sandbox
+    /// `config` sets up.
+    pub fn builtin(config: &ToolsConfig) -> Self {
+        let sandbox = Arc::new(Sandbox::from_config(config));
+        Self::new(config)
+            .with_tool(files::ReadFile::new(sandbox.clone()))
+            .with_tool(files::WriteFile::new(sandbox.clone()))
+            .with_tool(files::ListDir::new(sandbox))
+    }
+
+    /// Replace the permission policy.
+    pub fn with_permissions(mut self, permissions: PermissionPolicy) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Audit runs to `log` instead of the default log.
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Register a tool.
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    /// Definitions of the tools that are not denied, to offer the model.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|tool| tool.definition())
+            .filter(|def| self.permissions.check(&def.name) != Permission::Deny)
+            .collect()
+    }
+
+    fn find(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools
+            .iter()
+            .find(|tool| tool.definition().name == name)
+            .map(|tool| tool.as_ref())
+    }
+
+    /// Run `call` if its permission allows, or say what is needed first.
+    pub fn dispatch(&self, call: &ToolCall) -> Dispatch {
+        let Some(tool) = self.find(&call.name) else {
+            return Dispatch::Refused(format!("Unknown tool '{}'", call.name));
+        };
+        match self.permissions.check(&call.name) {
+            Permission::Allow => Dispatch::Ran(self.execute(tool, call)),
+            Permission::Ask => Dispatch::NeedsConfirmation {
+                summary: tool.describe(&call.arguments),
+            },
+            Permission::Deny => {
+                Dispatch::Refused(format!("'{}' is denied by [tools] permissions", call.name))
+            }
+        }
+    }
+
+    /// Answer a confirmation: run `call` or refuse it, optionally remembering
+    /// the answer for this project.
+    pub fn confirm(&mut self, call: &ToolCall, allow: bool, remember: bool) -> Dispatch {
+        if remember {
+            if let Err(e) = self.permissions.remember(&call.name, allow) {
+                return Dispatch::Refused(format!("{:#}", e));
+            }
+        }
+        match self.find(&call.name) {
+            Some(tool) if allow => Dispatch::Ran(self.execute(tool, call)),
+            Some(_) => Dispatch::Refused(format!("'{}' was not allowed", call.name)),
+            None => Dispatch::Refused(format!("Unknown tool '{}'", call.name)),
+        }
+    }
+
+    fn execute(&self, tool: &dyn Tool, call: &ToolCall) -> Result<String> {
+        let result = tool.run(&call.arguments);
+        if let Some(log) = &self.audit {
+            let record = AuditRecord::new(AuditAction::ToolRun, &call.name, &result)
+                .with_detail(tool.describe(&call.arguments));
+            // Auditing must never change what the tool did
+            let _ = log.append(&record);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    struct Echo;
+
+    impl Tool for Echo {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "echo".to_string(),
+                description: "Echo the text".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        fn describe(&self, arguments: &Value) -> String {
+            format!("echo {}", arguments["text"])
+        }
+
+        fn run(&self, arguments: &Value) -> Result<String> {
+            Ok(arguments["text"].as_str().unwrap_or_default().to_string())
+        }
+    }
+
+    fn dispatcher(level: Permission, dir: &std::path::Path) -> ToolDispatcher {
+        let config = ToolsConfig {
+            permissions: BTreeMap::from([("echo".to_string(), level)]),
+            ..ToolsConfig::default()
+        };
+        ToolDispatcher::new(&config)
+            .with_permissions(PermissionPolicy::new(config, dir))
+            .with_audit_log(AuditLog::at(dir.join("audit.log")))
+            .with_tool(Echo)
+    }
+
+    fn call() -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: "echo".to_string(),
+            arguments: serde_json::json!({"text": "hi"}),
+        }
+    }
+
+    #[test]
+    fn test_levels_are_enforced() {
+        let dir = tempfile::tempdir().unwrap();
+        let allowed = dispatcher(Permission::Allow, dir.path());
+        assert!(matches!(allowed.dispatch(&call()), Dispatch::Ran(Ok(out)) if out == "hi"));
+
+        let denied = dispatcher(Permission::Deny, dir.path());
+        assert!(matches!(denied.dispatch(&call()), Dispatch::Refused(_)));
+        assert!(denied.definitions().is_empty());
+
+        let unknown = ToolCall {
+            name: "rm".to_string(),
+            ..call()
+        };
+        assert!(matches!(allowed.dispatch(&unknown), Dispatch::Refused(_)));
+
+        // Only the allowed run was audited
+        let records = AuditLog::at(dir.path().join("audit.log")).read().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].detail.as_deref(), Some("echo \"hi\""));
+    }
+
+    #[test]
+    fn test_ask_waits_for_confirmation_and_remembers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tools = dispatcher(Permission::Ask, dir.path());
+        match tools.dispatch(&call()) {
+            Dispatch::NeedsConfirmation { summary } => assert_eq!(summary, "echo \"hi\""),
+            other => panic!("expected a confirmation, got {:?}", other),
+        }
+        assert!(matches!(
+            tools.confirm(&call(), false, false),
+            Dispatch::Refused(_)
+        ));
+        assert!(matches!(
+            tools.dispatch(&call()),
+            Dispatch::NeedsConfirmation { .. }
+        ));
+
+        assert!(matches!(
+            tools.confirm(&call(), true, true),
+            Dispatch::Ran(Ok(_))
+        ));
+        assert!(matches!(tools.dispatch(&call()), Dispatch::Ran(Ok(_))));
+    }
+}
@@ -0,0 +1,183 @@
+//! Permission levels for tools: configured and remembered.
+//!
+//! `[tools]` sets a level per tool, globally and per project directory. When a
+//! tool is set to `ask`, the answer in the confirmation dialog can be
+//! remembered for the current project in `permissions.json` next to
+//! `auth.json`. Remembered answers never override `allow` or `deny` from the
+//! config, so a tool locked down there stays locked down.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Permission, ToolsConfig};
+use crate::durable::{self, WriteOptions};
+use crate::variables;
+
+impl ToolsConfig {
+    /// Configured level of `tool` when working in `project`.
+    ///
+    /// The most specific project entry containing `project` wins, then
+    /// `[tools.permissions]`, then `default`.
+    pub fn level(&self, tool: &str, project: &Path) -> Permission {
+        self.projects
+            .iter()
+            .filter(|(dir, _)| project.starts_with(variables::resolve(dir)))
+            .max_by_key(|(dir, _)| variables::resolve(dir).components().count())
+            .and_then(|(_, levels)| levels.get(tool))
+            .or_else(|| self.permissions.get(tool))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Answers remembered from the confirmation dialog, per project.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Remembered {
+    projects: BTreeMap<PathBuf, BTreeMap<String, Permission>>,
+}
+
+/// Decides whether a tool may run in the current project.
+#[derive(Debug, Clone)]
+pub struct PermissionPolicy {
+    config: ToolsConfig,
+    project: PathBuf,
+    remembered: Remembered,
+    /// Where remembered answers are saved; `None` keeps them for this run only
+    store: Option<PathBuf>,
+}
+
+impl PermissionPolicy {
+    /// Returns `~/.local/share/scry-cli/permissions.json` on Linux/macOS.
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir =
+            dirs::data_local_dir().context("Could not determine local data directory")?;
+        Ok(data_dir.join("scry-cli").join("permissions.json"))
+    }
+
+    /// Policy for `project` with answers kept in memory only.
+    pub fn new(config: ToolsConfig, project: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            project: project.into(),
+            remembered: Remembered::default(),
+            store: None,
+        }
+    }
+
+    /// Load and save remembered answers at `path`.
+    ///
+    /// A missing or unreadable file starts with no remembered answers.
+    pub fn with_store(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.remembered = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        self.store = Some(path);
+        self
+    }
+
+    /// Level that applies to `tool` right now.
+    pub fn check(&self, tool: &str) -> Permission {
+        match self.config.level(tool, &self.project) {
+            Permission::Ask => self
+                .remembered
+                .projects
+                .get(&self.project)
+                .and_then(|levels| levels.get(tool))
+                .copied()
+                .unwrap_or(Permission::Ask),
+            level => level,
+        }
+    }
+
+    /// Remember an answer for `tool` in the current project.
+    pub fn remember(&mut self, tool: &str, allow: bool) -> Result<()> {
+        let level = if allow {
+            Permission::Allow
+        } else {
+            Permission::Deny
+        };
+        self.remembered
+            .projects
+            .entry(self.project.clone())
+            .or_default()
+            .insert(tool.to_string(), level);
+        let Some(path) = &self.store else {
+            return Ok(());
+        };
+        let contents = serde_json::to_vec_pretty(&self.remembered)?;
+        durable::write(path, &contents, WriteOptions::default())
+            .with_context(|| format!("Failed to save tool permissions: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ToolsConfig {
+        ToolsConfig {
+            default: Permission::Ask,
+            permissions: BTreeMap::from([
+                ("read_file".to_string(), Permission::Allow),
+                ("shell".to_string(), Permission::Deny),
+            ]),
+            projects: BTreeMap::from([
+                (
+                    PathBuf::from("/work"),
+                    BTreeMap::from([("shell".to_string(), Permission::Ask)]),
+                ),
+                (
+                    PathBuf::from("/work/scratch"),
+                    BTreeMap::from([("shell".to_string(), Permission::Allow)]),
+                ),
+            ]),
+            ..ToolsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_most_specific_level_wins() {
+        let config = config();
+        assert_eq!(config.level("shell", Path::new("/home")), Permission::Deny);
+        assert_eq!(
+            config.level("shell", Path::new("/work/app")),
+            Permission::Ask
+        );
+        assert_eq!(
+            config.level("shell", Path::new("/work/scratch/x")),
+            Permission::Allow
+        );
+        assert_eq!(
+            config.level("read_file", Path::new("/work")),
+            Permission::Allow
+        );
+        assert_eq!(
+            config.level("write_file", Path::new("/work")),
+            Permission::Ask
+        );
+    }
+
+    #[test]
+    fn test_remembered_answers_only_replace_ask() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("permissions.json");
+        let mut policy = PermissionPolicy::new(config(), "/work/app").with_store(&store);
+        assert_eq!(policy.check("write_file"), Permission::Ask);
+        policy.remember("write_file", true).unwrap();
+
+        let reloaded = PermissionPolicy::new(config(), "/work/app").with_store(&store);
+        assert_eq!(reloaded.check("write_file"), Permission::Allow);
+        // Answers belong to the project they were given in
+        let mut home = PermissionPolicy::new(config(), "/home").with_store(&store);
+        assert_eq!(home.check("write_file"), Permission::Ask);
+
+        // A remembered answer cannot unlock a denied tool
+        home.remember("shell", true).unwrap();
+        assert_eq!(home.check("shell"), Permission::Deny);
+    }
+}
@@ -39,7 +39,7 @@ pub fn render_anthropic_method_dialog(f: &mut Frame, selected: usize) {
     f.render_widget(title_text, chunks[0]);
 
     // Options
-    let options = vec![
+    let options = [
         ("Claude Pro/Max (OAuth)", "Sign in with Claude Pro or Max subscription"),
         ("Create API Key (OAuth)", "Create a new API key via OAuth"),
         ("Enter API Key", "Enter an existing API key manually"),
@@ -0,0 +1,228 @@
+//! Attachment manager (Ctrl+A).
+//!
+//! Lists the `@path` chips in the draft with their size and an estimate of
+//! the tokens they add, so it is clear what the next prompt will carry.
+//! Attachments can be removed or reordered; Enter writes the new list back
+//! into the draft, Esc leaves it as it was.
+
+use std::path::PathBuf;
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::file_tree::format_size;
+use super::render::centered_rect;
+use super::status_bar::format_tokens;
+use crate::app::estimate_tokens;
+use crate::paste::{chip_path, chip_ranges};
+
+/// A file attached to the draft.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// The chip as written in the input
+    pub chip: String,
+    pub path: PathBuf,
+    /// Size in bytes; `None` if the file is missing
+    pub size: Option<u64>,
+}
+
+impl Attachment {
+    /// Look up the file behind `chip`.
+    pub fn new(chip: &str) -> Self {
+        let path = chip_path(chip);
+        let size = std::fs::metadata(&path).ok().map(|m| m.len());
+        Self {
+            chip: chip.to_string(),
+            path,
+            size,
+        }
+    }
+
+    /// Estimated tokens the file adds to the prompt.
+    pub fn tokens(&self) -> u64 {
+        estimate_tokens(self.size.unwrap_or(0) as usize) as u64
+    }
+}
+
+/// Result of handling a key event in the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentsAction {
+    /// Keep the panel open.
+    Continue,
+    /// Close without changing the draft.
+    Close,
+    /// Close, writing these chips back into the draft in this order.
+    Apply(Vec<String>),
+}
+
+/// State of the attachment manager.
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentsPanel {
+    pub attachments: Vec<Attachment>,
+    /// Index of the highlighted attachment
+    pub selected: usize,
+    /// Whether anything was removed or moved
+    pub changed: bool,
+}
+
+impl AttachmentsPanel {
+    /// A panel over the chips in `input`.
+    pub fn new(input: &str) -> Self {
+        Self {
+            attachments: chip_ranges(input)
+                .into_iter()
+                .map(|range| Attachment::new(&input[range]))
+                .collect(),
+            selected: 0,
+            changed: false,
+        }
+    }
+
+    /// Estimated tokens of all attachments.
+    pub fn total_tokens(&self) -> u64 {
+        self.attachments.iter().map(Attachment::tokens).sum()
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> AttachmentsAction {
+        let len = self.attachments.len();
+        match key {
+            KeyCode::Esc => return AttachmentsAction::Close,
+            KeyCode::Enter if !self.changed => return AttachmentsAction::Close,
+            KeyCode::Enter => {
+                return AttachmentsAction::Apply(
+                    self.attachments.iter().map(|a| a.chip.clone()).collect(),
+                )
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < len => self.selected += 1,
+            KeyCode::Char('K') if self.selected > 0 => {
+                self.attachments.swap(self.selected, self.selected - 1);
+                self.selected -= 1;
+                self.changed = true;
+            }
+            KeyCode::Char('J') if self.selected + 1 < len => {
+                self.attachments.swap(self.selected, self.selected + 1);
+                self.selected += 1;
+                self.changed = true;
+            }
+            KeyCode::Delete | KeyCode::Char('d') if self.selected < len => {
+                self.attachments.remove(self.selected);
+                self.selected = self.selected.min(self.attachments.len().saturating_sub(1));
+                self.changed = true;
+            }
+            _ => {}
+        }
+        AttachmentsAction::Continue
+    }
+}
+
+/// Render the attachment manager.
+pub fn render_attachments(f: &mut Frame, panel: &AttachmentsPanel) {
+    let area = centered_rect(70, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let title = format!(
+        " Attachments · {} · ≈{} tokens ",
+        panel.attachments.len(),
+        format_tokens(panel.total_tokens())
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Attachment list
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    // Keep the selection visible
+    let visible = (chunks[0].height as usize).max(1);
+    let first = (panel.selected + 1).saturating_sub(visible);
+
+    let mut lines = Vec::new();
+    if panel.attachments.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  All attachments removed; Enter updates the draft",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (i, attachment) in panel.attachments.iter().enumerate().skip(first).take(visible) {
+        let selected = i == panel.selected;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let meta = match attachment.size {
+            Some(size) => Span::styled(
+                format!(
+                    "  {} · ≈{} tokens",
+                    format_size(size),
+                    format_tokens(attachment.tokens())
+                ),
+                Style::default().fg(Color::DarkGray),
+            ),
+            None => Span::styled("  missing", Style::default().fg(Color::Red)),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} {}", if selected { ">" } else { " " }, attachment.path.display()),
+                style,
+            ),
+            meta,
+        ]));
+    }
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Paragraph::new("↑↓ select · J/K move · d remove · Enter apply · Esc cancel")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        std::fs::write(&notes, "x".repeat(400)).unwrap();
+        let input = format!("read @{} and @missing.rs", notes.display());
+
+        let mut panel = AttachmentsPanel::new(&input);
+        assert_eq!(panel.attachments.len(), 2);
+        assert_eq!(panel.attachments[0].size, Some(400));
+        assert_eq!(panel.total_tokens(), 100);
+        assert_eq!(panel.attachments[1].size, None);
+        assert_eq!(panel.handle_key(KeyCode::Enter), AttachmentsAction::Close);
+
+        panel.handle_key(KeyCode::Down);
+        panel.handle_key(KeyCode::Char('K'));
+        assert_eq!(panel.selected, 0);
+        panel.handle_key(KeyCode::Down);
+        panel.handle_key(KeyCode::Char('d'));
+        assert_eq!(panel.selected, 0);
+        assert_eq!(
+            panel.handle_key(KeyCode::Enter),
+            AttachmentsAction::Apply(vec!["@missing.rs".to_string()])
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Chat message layout: plain prefixed lines or rounded bubbles.
+//!
+//! The role prefix, color, alignment and style all come from the theme, so
+//! this module only turns a message's text into lines for the chat list.
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_width::UnicodeWidthStr;
+
+use super::markdown;
+use super::text::wrap_text;
+use crate::config::{MessageAlign, MessageStyle, ThemeConfig};
+use crate::message::Role;
+
+/// Bubbles never take more than this share of the chat width, in percent.
+const BUBBLE_MAX_PERCENT: usize = 80;
+
+/// Lay out a chat message for a chat area `width` columns wide.
+///
+/// Assistant messages are rendered as markdown unless `raw` is set. Prompts
+/// with an `author` (in shared sessions) are labeled with it instead of the
+/// role prefix.
+pub fn message_lines(
+    theme: &ThemeConfig,
+    role: Role,
+    author: Option<&str>,
+    content: &str,
+    width: usize,
+    raw: bool,
+) -> Vec<Line<'static>> {
+    let style = Style::default().fg(theme.role_color(role));
+    let align = match role {
+        Role::User => theme.user_align,
+        Role::Assistant => MessageAlign::Left,
+    };
+    let body = |width: usize| -> Vec<Line<'static>> {
+        if role == Role::Assistant && !raw {
+            markdown::render(content, width, style)
+        } else {
+            wrap_text(content, width)
+                .into_iter()
+                .map(|text| Line::from(Span::styled(text, style)))
+                .collect()
+        }
+    };
+    let prefix = match author {
+        Some(author) => format!("{}: ", author),
+        None => theme.role_prefix(role).to_string(),
+    };
+    match theme.message_style {
+        MessageStyle::Plain => {
+            plain_lines(&prefix, body(width.saturating_sub(prefix.width())), style, width, align)
+        }
+        MessageStyle::Bubbles => {
+            let label = prefix.trim().trim_end_matches(':');
+            let max_outer = (width * BUBBLE_MAX_PERCENT / 100).max(8);
+            bubble_lines(label, body(max_outer.saturating_sub(4)), style, width, align)
+        }
+    }
+}
+
+/// Prefix on the first line, continuation lines indented under the text.
+fn plain_lines(
+    prefix: &str,
+    body: Vec<Line<'static>>,
+    style: Style,
+    width: usize,
+    align: MessageAlign,
+) -> Vec<Line<'static>> {
+    let indent = prefix.width();
+    body.into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let lead = if i == 0 {
+                Span::styled(prefix.to_string(), style.add_modifier(Modifier::BOLD))
+            } else {
+                Span::raw(" ".repeat(indent))
+            };
+            let used = indent + line.width();
+            let mut spans = vec![lead];
+            spans.extend(line.spans);
+            if align == MessageAlign::Right {
+                spans.insert(0, Span::raw(" ".repeat(width.saturating_sub(used))));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// The message in a rounded box with the role in its top border.
+fn bubble_lines(
+    label: &str,
+    body: Vec<Line<'static>>,
+    style: Style,
+    width: usize,
+    align: MessageAlign,
+) -> Vec<Line<'static>> {
+    let text_width = body
+        .iter()
+        .map(Line::width)
+        .max()
+        .unwrap_or(0)
+        .max(label.width() + 2);
+    let outer = text_width + 4;
+    let pad = match align {
+        MessageAlign::Left => String::new(),
+        MessageAlign::Right => " ".repeat(width.saturating_sub(outer)),
+    };
+
+    let top = format!(
+        "╭─ {} {}╮",
+        label,
+        "─".repeat(outer.saturating_sub(label.width() + 5))
+    );
+    let mut lines = vec![Line::from(vec![
+        Span::raw(pad.clone()),
+        Span::styled(top, style.add_modifier(Modifier::BOLD)),
+    ])];
+    for line in body {
+        let fill = " ".repeat(text_width - line.width());
+        let mut spans = vec![Span::raw(pad.clone()), Span::styled("│ ", style)];
+        spans.extend(line.spans);
+        spans.push(Span::raw(fill));
+        spans.push(Span::styled(" │", style));
+        lines.push(Line::from(spans));
+    }
+    lines.push(Line::from(vec![
+        Span::raw(pad),
+        Span::styled(format!("╰{}╯", "─".repeat(outer - 2)), style),
+    ]));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_plain_lines_indent_under_prefix() {
+        let theme = ThemeConfig::default();
+        let lines = message_lines(&theme, Role::User, None, "one two three", 12, false);
+        assert_eq!(text(&lines[0]), "You: one two");
+        assert_eq!(text(&lines[1]), "     three");
+    }
+
+    #[test]
+    fn test_author_replaces_the_prefix() {
+        let theme = ThemeConfig::default();
+        let lines = message_lines(&theme, Role::User, Some("ada"), "hi", 20, false);
+        assert_eq!(text(&lines[0]), "ada: hi");
+    }
+
+    #[test]
+    fn test_right_aligned_user_lines() {
+        let theme = ThemeConfig {
+            user_align: MessageAlign::Right,
+            ..ThemeConfig::default()
+        };
+        let lines = message_lines(&theme, Role::User, None, "hi", 20, false);
+        assert_eq!(text(&lines[0]).width(), 20);
+        assert!(text(&lines[0]).ends_with("You: hi"));
+
+        // Assistant replies stay on the left
+        let lines = message_lines(&theme, Role::Assistant, None, "hi", 20, false);
+        assert_eq!(text(&lines[0]), "Assistant: hi");
+    }
+
+    #[test]
+    fn test_bubbles_have_matching_borders() {
+        let theme = ThemeConfig {
+            message_style: MessageStyle::Bubbles,
+            user_align: MessageAlign::Right,
+            ..ThemeConfig::default()
+        };
+        let lines = message_lines(&theme, Role::User, None, "hello there", 40, false);
+        let rendered: Vec<String> = lines.iter().map(text).collect();
+        assert_eq!(rendered.len(), 3);
+        assert!(rendered[0].trim_start().starts_with("╭─ You "));
+        assert!(rendered[1].trim_start().starts_with("│ hello there"));
+        assert!(rendered[2].trim_start().starts_with("╰"));
+        // Every row is the same width and flush right
+        assert!(rendered.iter().all(|l| l.width() == 40));
+    }
+
+    #[test]
+    fn test_raw_shows_assistant_markdown_source() {
+        let theme = ThemeConfig::default();
+        let rendered = message_lines(&theme, Role::Assistant, None, "**hi**", 40, false);
+        assert_eq!(text(&rendered[0]), "Assistant: hi");
+        let raw = message_lines(&theme, Role::Assistant, None, "**hi**", 40, true);
+        assert_eq!(text(&raw[0]), "Assistant: **hi**");
+    }
+}
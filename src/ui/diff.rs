@@ -0,0 +1,274 @@
+//! Diff rendering and the hunk-by-hunk review screen.
+//!
+//! Used by `scry rewrite` to let the user accept or reject each hunk of a
+//! model-proposed change before it is written back to disk.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::diff::{DiffLineKind, FileDiff, Hunk};
+
+/// Result of handling a key event in the review screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewAction {
+    /// Keep reviewing.
+    Continue,
+    /// Finish and apply the accepted hunks.
+    Finish,
+    /// Quit without applying anything.
+    Abort,
+}
+
+/// State of an interactive hunk review.
+#[derive(Debug, Clone)]
+pub struct DiffReview {
+    /// Title shown in the header (usually the file path)
+    pub title: String,
+    /// The diff under review
+    pub diff: FileDiff,
+    /// Decision per hunk: `Some(true)` accepted, `Some(false)` rejected
+    pub decisions: Vec<Option<bool>>,
+    /// Index of the hunk being shown
+    pub current: usize,
+    /// Vertical scroll offset within the current hunk
+    pub scroll: u16,
+}
+
+impl DiffReview {
+    /// Start reviewing a diff with every hunk undecided.
+    pub fn new(title: impl Into<String>, diff: FileDiff) -> Self {
+        let decisions = vec![None; diff.hunks().len()];
+        Self {
+            title: title.into(),
+            diff,
+            decisions,
+            current: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Get the hunk currently shown.
+    pub fn current_hunk(&self) -> Option<&Hunk> {
+        self.diff.hunks().get(self.current)
+    }
+
+    /// Accepted flag per hunk; undecided hunks count as rejected.
+    pub fn accepted(&self) -> Vec<bool> {
+        self.decisions.iter().map(|d| d.unwrap_or(false)).collect()
+    }
+
+    /// Number of accepted hunks.
+    pub fn accepted_count(&self) -> usize {
+        self.decisions.iter().filter(|d| **d == Some(true)).count()
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> ReviewAction {
+        match key {
+            KeyCode::Char('y') => self.decide(true),
+            KeyCode::Char('n') => self.decide(false),
+            KeyCode::Char('a') => self.decide_remaining(true),
+            KeyCode::Char('d') => self.decide_remaining(false),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                self.select(self.current + 1);
+                ReviewAction::Continue
+            }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => {
+                self.select(self.current.saturating_sub(1));
+                ReviewAction::Continue
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.scroll = self.scroll.saturating_add(1);
+                ReviewAction::Continue
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.scroll = self.scroll.saturating_sub(1);
+                ReviewAction::Continue
+            }
+            KeyCode::Enter | KeyCode::Char('w') => ReviewAction::Finish,
+            KeyCode::Esc | KeyCode::Char('q') => ReviewAction::Abort,
+            _ => ReviewAction::Continue,
+        }
+    }
+
+    /// Record a decision for the current hunk and move to the next undecided one.
+    fn decide(&mut self, accept: bool) -> ReviewAction {
+        if let Some(decision) = self.decisions.get_mut(self.current) {
+            *decision = Some(accept);
+        }
+        let count = self.decisions.len();
+        let next = (1..=count)
+            .map(|offset| (self.current + offset) % count)
+            .find(|&i| self.decisions[i].is_none());
+        match next {
+            Some(i) => {
+                self.select(i);
+                ReviewAction::Continue
+            }
+            None => ReviewAction::Finish,
+        }
+    }
+
+    /// Apply a decision to the current hunk and every undecided one, then finish.
+    fn decide_remaining(&mut self, accept: bool) -> ReviewAction {
+        for (i, decision) in self.decisions.iter_mut().enumerate() {
+            if decision.is_none() || i == self.current {
+                *decision = Some(accept);
+            }
+        }
+        ReviewAction::Finish
+    }
+
+    fn select(&mut self, index: usize) {
+        if index < self.decisions.len() {
+            self.current = index;
+            self.scroll = 0;
+        }
+    }
+}
+
+/// Convert a hunk into styled lines with `+`/`-` markers.
+pub fn hunk_lines(hunk: &Hunk) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(Span::styled(
+        hunk.header(),
+        Style::default().fg(Color::Cyan),
+    ))];
+    lines.extend(hunk.lines.iter().map(|line| {
+        let style = match line.kind {
+            DiffLineKind::Context => Style::default().fg(Color::Gray),
+            DiffLineKind::Added => Style::default().fg(Color::Green),
+            DiffLineKind::Removed => Style::default().fg(Color::Red),
+        };
+        Line::from(Span::styled(
+            format!("{}{}", line.kind.prefix(), line.text),
+            style,
+        ))
+    }));
+    lines
+}
+
+/// Render the review screen.
+pub fn render_diff_review(f: &mut Frame, review: &DiffReview) {
+    let chunks = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .split(f.size());
+
+    // Header: file, position and decision summary
+    let total = review.decisions.len();
+    let (status, status_color) = match review.decisions.get(review.current) {
+        Some(Some(true)) => ("accepted", Color::Green),
+        Some(Some(false)) => ("rejected", Color::Red),
+        _ => ("undecided", Color::Yellow),
+    };
+    let header = Line::from(vec![
+        Span::styled(
+            review.title.clone(),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  hunk {}/{}  ", review.current + 1, total)),
+        Span::styled(status, Style::default().fg(status_color)),
+        Span::styled(
+            format!("  ({} of {} accepted)", review.accepted_count(), total),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    f.render_widget(Paragraph::new(header), chunks[0]);
+
+    // Body: the current hunk
+    let body = review.current_hunk().map(hunk_lines).unwrap_or_default();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(status_color));
+    f.render_widget(
+        Paragraph::new(body).block(block).scroll((review.scroll, 0)),
+        chunks[1],
+    );
+
+    // Footer: key hints
+    let key = |k: &'static str| Span::styled(k, Style::default().fg(Color::Yellow));
+    let hints = Line::from(vec![
+        key("[y]"),
+        Span::raw(" accept  "),
+        key("[n]"),
+        Span::raw(" reject  "),
+        key("[a/d]"),
+        Span::raw(" accept/reject rest  "),
+        key("[←→]"),
+        Span::raw(" hunk  "),
+        key("[↑↓]"),
+        Span::raw(" scroll  "),
+        key("[Enter]"),
+        Span::raw(" write  "),
+        key("[q]"),
+        Span::raw(" quit"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[2],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review() -> DiffReview {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+        DiffReview::new("test.txt", FileDiff::new(old, new))
+    }
+
+    #[test]
+    fn test_decide_moves_to_next_and_finishes() {
+        let mut review = review();
+        assert_eq!(review.decisions.len(), 2);
+        assert_eq!(review.handle_key(KeyCode::Char('y')), ReviewAction::Continue);
+        assert_eq!(review.current, 1);
+        assert_eq!(review.handle_key(KeyCode::Char('n')), ReviewAction::Finish);
+        assert_eq!(review.accepted(), vec![true, false]);
+    }
+
+    #[test]
+    fn test_accept_remaining() {
+        let mut review = review();
+        review.handle_key(KeyCode::Char('n'));
+        assert_eq!(review.handle_key(KeyCode::Char('a')), ReviewAction::Finish);
+        assert_eq!(review.accepted(), vec![false, true]);
+    }
+
+    #[test]
+    fn test_navigation_is_clamped() {
+        let mut review = review();
+        review.handle_key(KeyCode::Left);
+        assert_eq!(review.current, 0);
+        review.handle_key(KeyCode::Right);
+        review.handle_key(KeyCode::Right);
+        assert_eq!(review.current, 1);
+    }
+
+    #[test]
+    fn test_undecided_hunks_are_rejected() {
+        let mut review = review();
+        assert_eq!(review.handle_key(KeyCode::Enter), ReviewAction::Finish);
+        assert_eq!(review.accepted(), vec![false, false]);
+        assert_eq!(review.handle_key(KeyCode::Char('q')), ReviewAction::Abort);
+    }
+
+    #[test]
+    fn test_hunk_lines_have_markers() {
+        let review = review();
+        let lines = hunk_lines(review.current_hunk().unwrap());
+        assert!(lines[0].spans[0].content.starts_with("@@"));
+        assert_eq!(lines[1].spans[0].content, "-a");
+        assert_eq!(lines[2].spans[0].content, "+A");
+    }
+}
@@ -0,0 +1,156 @@
+//! Selecting chat text with the mouse.
+//!
+//! Dragging with the left button selects the text under the pointer in the
+//! chat area, from where the drag started to where it is now, in reading
+//! order: the rest of the first row, every row in between, and the last row
+//! up to the pointer, so a selection runs across wrapped lines as text does.
+//! The selection is kept in screen cells and read back from what was drawn,
+//! so it copies exactly what is shown. Releasing the button copies it.
+
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+use unicode_width::UnicodeWidthStr;
+
+/// Background of selected cells.
+pub const DRAG_BG: Color = Color::Rgb(70, 70, 110);
+
+/// A selection being dragged (or just released) in the chat area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragSelection {
+    /// Cell where the drag started, as (column, row)
+    pub anchor: (u16, u16),
+    /// Cell under the pointer now
+    pub head: (u16, u16),
+    /// Whether the button is still held
+    pub dragging: bool,
+    /// Whether the text should be copied at the next draw
+    pub copy: bool,
+}
+
+impl DragSelection {
+    /// Start a selection at a cell of `area`.
+    pub fn start(column: u16, row: u16, area: Rect) -> Self {
+        let cell = clamp(column, row, area);
+        Self {
+            anchor: cell,
+            head: cell,
+            dragging: true,
+            copy: false,
+        }
+    }
+
+    /// Move the end of the selection to the pointer, kept inside `area`.
+    pub fn extend(&mut self, column: u16, row: u16, area: Rect) {
+        self.head = clamp(column, row, area);
+    }
+
+    /// Let go of the button: the selection is copied at the next draw.
+    /// Returns false for a click that selected nothing.
+    pub fn release(&mut self) -> bool {
+        self.dragging = false;
+        self.copy = self.anchor != self.head;
+        self.copy
+    }
+
+    /// Start and end of the selection in reading order.
+    fn bounds(&self) -> ((u16, u16), (u16, u16)) {
+        let (a, h) = (self.anchor, self.head);
+        if (a.1, a.0) <= (h.1, h.0) {
+            (a, h)
+        } else {
+            (h, a)
+        }
+    }
+
+    /// Columns selected in `row`, end exclusive, within `area`.
+    fn columns(&self, row: u16, area: Rect) -> Option<(u16, u16)> {
+        let (start, end) = self.bounds();
+        if row < start.1 || row > end.1 {
+            return None;
+        }
+        let from = if row == start.1 { start.0 } else { area.left() };
+        let to = if row == end.1 { end.0 + 1 } else { area.right() };
+        (from < to).then_some((from, to))
+    }
+
+    /// Highlight the selected cells of `area` in `buf`.
+    pub fn highlight(&self, buf: &mut Buffer, area: Rect) {
+        for row in area.top()..area.bottom() {
+            if let Some((from, to)) = self.columns(row, area) {
+                for column in from..to {
+                    buf.get_mut(column, row).set_bg(DRAG_BG);
+                }
+            }
+        }
+    }
+
+    /// The selected text of `area` in `buf`: one line per row, without
+    /// trailing blanks.
+    pub fn text(&self, buf: &Buffer, area: Rect) -> String {
+        let mut lines = Vec::new();
+        for row in area.top()..area.bottom() {
+            if let Some((from, to)) = self.columns(row, area) {
+                let mut line = String::new();
+                let mut column = from;
+                while column < to {
+                    let symbol = buf.get(column, row).symbol();
+                    line.push_str(symbol);
+                    // Wide characters cover the next cells too
+                    column += symbol.width().max(1) as u16;
+                }
+                lines.push(line.trim_end().to_string());
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// `(column, row)` moved inside `area`.
+fn clamp(column: u16, row: u16, area: Rect) -> (u16, u16) {
+    (
+        column.clamp(area.left(), area.right().saturating_sub(1)),
+        row.clamp(area.top(), area.bottom().saturating_sub(1)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::text::Line;
+
+    fn screen() -> (Buffer, Rect) {
+        let area = Rect::new(0, 0, 12, 3);
+        let mut buf = Buffer::empty(area);
+        buf.set_line(0, 0, &Line::from("you> hello"), 12);
+        buf.set_line(0, 1, &Line::from("     there"), 12);
+        buf.set_line(0, 2, &Line::from("bot> 日本"), 12);
+        (buf, area)
+    }
+
+    #[test]
+    fn test_selection_runs_across_rows_in_reading_order() {
+        let (mut buf, area) = screen();
+        // Dragged upwards from "the|re" to "he|llo"
+        let mut selection = DragSelection::start(7, 1, area);
+        selection.extend(6, 0, area);
+        assert!(selection.release());
+        assert_eq!(selection.text(&buf, area), "ello\n     the");
+
+        selection.highlight(&mut buf, area);
+        assert_eq!(buf.get(6, 0).bg, DRAG_BG);
+        assert_eq!(buf.get(5, 0).bg, Color::Reset);
+        assert_eq!(buf.get(0, 1).bg, DRAG_BG);
+        assert_eq!(buf.get(8, 1).bg, Color::Reset);
+    }
+
+    #[test]
+    fn test_drags_outside_the_area_are_clamped_and_clicks_copy_nothing() {
+        let (buf, area) = screen();
+        let mut selection = DragSelection::start(0, 2, area);
+        selection.extend(40, 9, area);
+        assert_eq!(selection.head, (11, 2));
+        assert_eq!(selection.text(&buf, area), "bot> 日本");
+
+        let mut click = DragSelection::start(3, 0, area);
+        assert!(!click.release());
+    }
+}
@@ -0,0 +1,417 @@
+//! Workspace file tree (Ctrl+T).
+//!
+//! Browses the current directory, skipping files excluded by `.gitignore`
+//! and hidden files, so files can be picked as `@path` attachments instead
+//! of typing their paths. Several files can be marked at once, and the
+//! highlighted file is previewed beside the tree.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::render::centered_rect;
+
+/// Bytes read from a file for its preview.
+const PREVIEW_BYTES: usize = 16 * 1024;
+
+/// Result of handling a key event in the file tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileTreeAction {
+    /// Keep the tree open.
+    Continue,
+    /// Close the tree without attaching anything.
+    Close,
+    /// Attach these files and close.
+    Attach(Vec<PathBuf>),
+}
+
+/// One visible row of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub path: PathBuf,
+    /// Nesting level below the root
+    pub depth: usize,
+    pub is_dir: bool,
+    /// File size in bytes (0 for directories)
+    pub size: u64,
+}
+
+/// State of the file tree overlay.
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    /// Directory being browsed
+    pub root: PathBuf,
+    /// Visible rows, in display order
+    pub entries: Vec<TreeEntry>,
+    /// Directories whose children are shown
+    pub expanded: HashSet<PathBuf>,
+    /// Index of the highlighted row
+    pub selected: usize,
+    /// Files marked for attaching
+    pub marked: BTreeSet<PathBuf>,
+    /// Preview lines of the highlighted file
+    pub preview: Vec<String>,
+}
+
+/// Children of `dir` that are not ignored: directories first, then by name.
+fn children(dir: &Path, depth: usize) -> Vec<TreeEntry> {
+    let mut entries: Vec<TreeEntry> = ignore::WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        // Honor .gitignore even outside a git checkout
+        .require_git(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let size = if is_dir {
+                0
+            } else {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+            TreeEntry {
+                path: entry.into_path(),
+                depth,
+                is_dir,
+                size,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.path.cmp(&b.path)));
+    entries
+}
+
+/// First lines of a file, or a note if it cannot be shown as text.
+fn preview_lines(path: &Path) -> Vec<String> {
+    let mut bytes = Vec::new();
+    let read =
+        fs::File::open(path).and_then(|f| f.take(PREVIEW_BYTES as u64).read_to_end(&mut bytes));
+    if read.is_err() {
+        return vec!["(cannot read file)".to_string()];
+    }
+    if bytes.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|l| l.replace('\t', "    "))
+        .collect()
+}
+
+/// Human-readable file size.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl FileTree {
+    /// Open the tree on `root` with its top level shown.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let mut tree = Self {
+            root: root.into(),
+            entries: Vec::new(),
+            expanded: HashSet::new(),
+            selected: 0,
+            marked: BTreeSet::new(),
+            preview: Vec::new(),
+        };
+        tree.rebuild();
+        tree
+    }
+
+    /// Recompute the visible rows from the expanded directories.
+    fn rebuild(&mut self) {
+        let mut entries = Vec::new();
+        let mut stack = children(&self.root, 0);
+        stack.reverse();
+        while let Some(entry) = stack.pop() {
+            if entry.is_dir && self.expanded.contains(&entry.path) {
+                let mut nested = children(&entry.path, entry.depth + 1);
+                nested.reverse();
+                stack.extend(nested);
+            }
+            entries.push(entry);
+        }
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.refresh_preview();
+    }
+
+    fn refresh_preview(&mut self) {
+        self.preview = match self.entries.get(self.selected) {
+            Some(entry) if !entry.is_dir => preview_lines(&entry.path),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Path shown to the user and used in chips: relative to the root.
+    pub fn display_path<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> FileTreeAction {
+        let Some(entry) = self.entries.get(self.selected).cloned() else {
+            return match key {
+                KeyCode::Esc => FileTreeAction::Close,
+                _ => FileTreeAction::Continue,
+            };
+        };
+        match key {
+            KeyCode::Esc => return FileTreeAction::Close,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                self.refresh_preview();
+            }
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.entries.len() - 1);
+                self.refresh_preview();
+            }
+            KeyCode::Right if entry.is_dir => {
+                self.expanded.insert(entry.path);
+                self.rebuild();
+            }
+            KeyCode::Left => {
+                if entry.is_dir && self.expanded.remove(&entry.path) {
+                    self.rebuild();
+                } else if let Some(parent) = self
+                    .entries
+                    .iter()
+                    .position(|e| e.is_dir && entry.path.parent() == Some(e.path.as_path()))
+                {
+                    // Collapse the enclosing directory and move onto it
+                    let parent_path = self.entries[parent].path.clone();
+                    self.expanded.remove(&parent_path);
+                    self.selected = parent;
+                    self.rebuild();
+                }
+            }
+            KeyCode::Char(' ') if !entry.is_dir => {
+                if !self.marked.remove(&entry.path) {
+                    self.marked.insert(entry.path);
+                }
+                self.selected = (self.selected + 1).min(self.entries.len() - 1);
+                self.refresh_preview();
+            }
+            KeyCode::Enter if entry.is_dir => {
+                if !self.expanded.remove(&entry.path) {
+                    self.expanded.insert(entry.path);
+                }
+                self.rebuild();
+            }
+            KeyCode::Enter => {
+                let files: Vec<PathBuf> = if self.marked.is_empty() {
+                    vec![entry.path]
+                } else {
+                    self.marked.iter().cloned().collect()
+                };
+                return FileTreeAction::Attach(
+                    files
+                        .iter()
+                        .map(|p| self.display_path(p).to_path_buf())
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+        FileTreeAction::Continue
+    }
+}
+
+/// Render the file tree and preview pane.
+pub fn render_file_tree(f: &mut Frame, tree: &FileTree) {
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let title = if tree.marked.is_empty() {
+        " Files ".to_string()
+    } else {
+        format!(" Files ({} marked) ", tree.marked.len())
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Tree and preview
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(rows[0]);
+
+    // Keep the selection visible in large directories
+    let visible = columns[0].height as usize;
+    let first = (tree.selected + 1).saturating_sub(visible);
+    let width = columns[0].width as usize;
+
+    let lines: Vec<Line> = if tree.entries.is_empty() {
+        vec![Line::from(Span::styled(
+            "  (no files)",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        tree.entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, entry)| {
+                let selected = i == tree.selected;
+                let name = entry
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let (icon, size) = if entry.is_dir {
+                    let open = tree.expanded.contains(&entry.path);
+                    (if open { "▾ " } else { "▸ " }, String::new())
+                } else if tree.marked.contains(&entry.path) {
+                    ("● ", format_size(entry.size))
+                } else {
+                    ("  ", format_size(entry.size))
+                };
+                let label = format!(
+                    "{}{}{}{}",
+                    if selected { ">" } else { " " },
+                    "  ".repeat(entry.depth),
+                    icon,
+                    if entry.is_dir {
+                        format!("{}/", name)
+                    } else {
+                        name
+                    }
+                );
+                let pad = width.saturating_sub(label.chars().count() + size.len() + 1);
+                let style = if selected {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else if entry.is_dir {
+                    Style::default().fg(Color::Blue)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                Line::from(vec![
+                    Span::styled(label, style),
+                    Span::raw(" ".repeat(pad)),
+                    Span::styled(size, Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect()
+    };
+    f.render_widget(Paragraph::new(lines), columns[0]);
+
+    let preview: Vec<Line> = tree
+        .preview
+        .iter()
+        .take(columns[1].height as usize)
+        .map(|l| Line::from(Span::styled(l.as_str(), Style::default().fg(Color::Gray))))
+        .collect();
+    f.render_widget(
+        Paragraph::new(preview).block(
+            Block::default()
+                .borders(Borders::LEFT)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        ),
+        columns[1],
+    );
+
+    let hints = Paragraph::new("↑↓ move · →← open/close · Space mark · Enter attach · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, rows[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tree: &FileTree) -> Vec<String> {
+        tree.entries
+            .iter()
+            .map(|e| tree.display_path(&e.path).display().to_string())
+            .collect()
+    }
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Demo\nhello").unwrap();
+        fs::write(dir.path().join("debug.log"), "noise").unwrap();
+        fs::write(dir.path().join("src").join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("target").join("out"), "built").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_ignored_and_hidden_files_are_skipped() {
+        let dir = workspace();
+        let tree = FileTree::new(dir.path());
+        assert_eq!(names(&tree), vec!["src", "README.md"]);
+    }
+
+    #[test]
+    fn test_expand_mark_and_attach() {
+        let dir = workspace();
+        let mut tree = FileTree::new(dir.path());
+
+        tree.handle_key(KeyCode::Right);
+        assert_eq!(names(&tree), vec!["src", "src/main.rs", "README.md"]);
+
+        // Space marks and moves on; Enter attaches every marked file
+        tree.handle_key(KeyCode::Down);
+        assert_eq!(tree.preview, vec!["fn main() {}"]);
+        tree.handle_key(KeyCode::Char(' '));
+        tree.handle_key(KeyCode::Char(' '));
+        assert_eq!(
+            tree.handle_key(KeyCode::Enter),
+            FileTreeAction::Attach(vec![
+                PathBuf::from("README.md"),
+                PathBuf::from("src/main.rs")
+            ])
+        );
+
+        // Left on a nested file collapses its directory
+        tree.selected = 1;
+        tree.handle_key(KeyCode::Left);
+        assert_eq!(names(&tree), vec!["src", "README.md"]);
+        assert_eq!(tree.selected, 0);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,247 @@
+//! Search across all saved sessions (Ctrl+Shift+F).
+//!
+//! Enter runs the query against the session store (its full-text index with
+//! the SQLite backend) and lists every matching message with a snippet of
+//! the text around the match. Picking a hit opens that session scrolled to
+//! the message, with the query in the Ctrl+F search bar so the match is
+//! highlighted. Editing the query and pressing Enter again searches anew.
+
+use chrono::Local;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::render::centered_rect;
+use super::search::MATCH_BG;
+use crate::session::SearchHit;
+
+/// Result of handling a key event in the global search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalSearchAction {
+    /// Keep the search open.
+    Continue,
+    /// Close the search.
+    Close,
+    /// Run the query.
+    Search,
+    /// Open the hit at this index and close.
+    Open(usize),
+}
+
+/// State of the global search.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalSearch {
+    /// What to look for
+    pub query: String,
+    /// Query the hits were found for, once one has run
+    pub searched: Option<String>,
+    /// Hits, most recently updated session first
+    pub hits: Vec<SearchHit>,
+    /// Index of the highlighted hit
+    pub selected: usize,
+}
+
+impl GlobalSearch {
+    /// Open with an empty query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show the hits found for `query`, highlighting the first.
+    pub fn set_hits(&mut self, query: String, hits: Vec<SearchHit>) {
+        self.searched = Some(query);
+        self.hits = hits;
+        self.selected = 0;
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> GlobalSearchAction {
+        match key {
+            KeyCode::Esc => GlobalSearchAction::Close,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                GlobalSearchAction::Continue
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < self.hits.len() {
+                    self.selected += 1;
+                }
+                GlobalSearchAction::Continue
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                GlobalSearchAction::Continue
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                GlobalSearchAction::Continue
+            }
+            KeyCode::Enter if self.query.trim().is_empty() => GlobalSearchAction::Continue,
+            KeyCode::Enter if self.searched.as_deref() != Some(self.query.trim()) => {
+                GlobalSearchAction::Search
+            }
+            KeyCode::Enter if self.selected < self.hits.len() => {
+                GlobalSearchAction::Open(self.selected)
+            }
+            _ => GlobalSearchAction::Continue,
+        }
+    }
+}
+
+/// Render the global search.
+pub fn render_global_search(f: &mut Frame, search: &GlobalSearch) {
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Search Sessions ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), // Query
+            Constraint::Min(1),    // Hits
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    let query = Line::from(vec![
+        Span::styled("Search: ", Style::default().fg(Color::Yellow)),
+        Span::styled(
+            search.query.clone(),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("▎", Style::default().fg(Color::Cyan)),
+    ]);
+    f.render_widget(Paragraph::new(query), chunks[0]);
+
+    match &search.searched {
+        None => {}
+        Some(query) if search.hits.is_empty() => {
+            let none = Paragraph::new(format!("No saved session mentions “{}”", query))
+                .style(Style::default().fg(Color::DarkGray));
+            f.render_widget(none, chunks[1]);
+        }
+        Some(_) => {
+            // Two lines per hit; keep the selection visible
+            let visible = (chunks[1].height as usize / 2).max(1);
+            let first = (search.selected + 1).saturating_sub(visible);
+            let mut lines = Vec::new();
+            for (i, hit) in search.hits.iter().enumerate().skip(first).take(visible) {
+                let selected = i == search.selected;
+                let style = if selected {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let title = if hit.session.title.is_empty() {
+                    "(untitled)"
+                } else {
+                    &hit.session.title
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{} {}", if selected { ">" } else { " " }, title), style),
+                    Span::styled(
+                        format!(
+                            " · {}",
+                            hit.session
+                                .updated_at
+                                .with_timezone(&Local)
+                                .format("%Y-%m-%d %H:%M")
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]));
+                lines.push(snippet_line(hit));
+            }
+            f.render_widget(Paragraph::new(lines), chunks[1]);
+        }
+    }
+
+    let hints = if search.searched.as_deref() == Some(search.query.trim()) {
+        "↑↓ select · Enter open · type to change the query · Esc close"
+    } else {
+        "Enter search · Esc close"
+    };
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::DarkGray)),
+        chunks[2],
+    );
+}
+
+/// A hit's snippet, indented, with the match highlighted.
+fn snippet_line(hit: &SearchHit) -> Line<'static> {
+    let (start, len) = hit.highlight;
+    let chars: Vec<char> = hit.snippet.chars().collect();
+    let part = |from: usize, to: usize| -> String {
+        chars[from.min(chars.len())..to.min(chars.len())].iter().collect()
+    };
+    let text = Style::default().fg(Color::Gray);
+    Line::from(vec![
+        Span::styled(format!("    {}", part(0, start)), text),
+        Span::styled(part(start, start + len), text.bg(MATCH_BG)),
+        Span::styled(part(start + len, chars.len()), text),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+
+    fn hits() -> Vec<SearchHit> {
+        let mut session = Session::new("ollama", "llama3");
+        session.title = "Tuning postgres".to_string();
+        session.messages = ["Raise shared_buffers", "and work_mem"]
+            .iter()
+            .map(|text| crate::session::SessionMessage {
+                role: crate::message::Role::User,
+                content: text.to_string(),
+                interrupted: false,
+                pinned: false,
+            })
+            .collect();
+        session.hits("r")
+    }
+
+    #[test]
+    fn test_enter_searches_then_opens() {
+        let mut search = GlobalSearch::new();
+        assert_eq!(search.handle_key(KeyCode::Enter), GlobalSearchAction::Continue);
+        search.handle_key(KeyCode::Char('r'));
+        assert_eq!(search.handle_key(KeyCode::Enter), GlobalSearchAction::Search);
+
+        search.set_hits("r".to_string(), hits());
+        assert_eq!(search.hits.len(), 2);
+        search.handle_key(KeyCode::Down);
+        search.handle_key(KeyCode::Down);
+        assert_eq!(search.handle_key(KeyCode::Enter), GlobalSearchAction::Open(1));
+
+        // A changed query searches again
+        search.handle_key(KeyCode::Char('k'));
+        assert_eq!(search.handle_key(KeyCode::Enter), GlobalSearchAction::Search);
+        assert_eq!(search.handle_key(KeyCode::Esc), GlobalSearchAction::Close);
+    }
+
+    #[test]
+    fn test_snippet_highlights_the_match() {
+        let hit = &hits()[1];
+        let line = snippet_line(hit);
+        assert_eq!(line.spans[1].content, "r");
+        assert_eq!(line.spans[1].style.bg, Some(MATCH_BG));
+        assert_eq!(line.spans[2].content, "k_mem");
+    }
+}
@@ -0,0 +1,164 @@
+//! Syntax highlighting for fenced code blocks.
+//!
+//! Uses syntect's bundled grammars and themes. Highlighting is cached per
+//! block, since the chat is redrawn every frame; a block still being
+//! streamed is highlighted again only when it changes.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use ratatui::style::{Color, Modifier, Style};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+/// Background of code blocks.
+pub const CODE_BG: Color = Color::Rgb(40, 44, 52);
+
+/// Bundled syntect theme used for code.
+const THEME: &str = "base16-ocean.dark";
+
+/// Highlighted blocks kept before the cache is emptied.
+const MAX_CACHED: usize = 256;
+
+/// Cache lookups that found, or did not find, a highlighted block.
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// A run of code with one style.
+pub type Piece = (String, Style);
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME_CELL: OnceLock<Theme> = OnceLock::new();
+    THEME_CELL.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults().themes;
+        themes.remove(THEME).unwrap_or_default()
+    })
+}
+
+/// Cache hits and misses so far, for the profiling overlay.
+pub fn cache_stats() -> (u64, u64) {
+    (HITS.load(Ordering::Relaxed), MISSES.load(Ordering::Relaxed))
+}
+
+fn cache() -> &'static Mutex<HashMap<u64, Vec<Vec<Piece>>>> {
+    static CACHE: OnceLock<Mutex<HashMap<u64, Vec<Vec<Piece>>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Style for code in a language without a grammar.
+pub fn plain_style() -> Style {
+    Style::default().fg(Color::Yellow).bg(CODE_BG)
+}
+
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut out = Style::default()
+        .fg(Color::Rgb(fg.r, fg.g, fg.b))
+        .bg(CODE_BG);
+    if style.font_style.contains(FontStyle::BOLD) {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+    out
+}
+
+/// Highlight `lines` of code written in `lang` (a fence tag such as `rust`
+/// or `py`), returning the styled pieces of each line.
+///
+/// Unknown or missing languages get [`plain_style`].
+pub fn highlight(lines: &[String], lang: &str) -> Vec<Vec<Piece>> {
+    let plain = || {
+        lines
+            .iter()
+            .map(|line| vec![(line.clone(), plain_style())])
+            .collect()
+    };
+    let Some(syntax) = (!lang.is_empty())
+        .then(|| syntaxes().find_syntax_by_token(lang))
+        .flatten()
+    else {
+        return plain();
+    };
+
+    let mut hasher = DefaultHasher::new();
+    (lang, lines).hash(&mut hasher);
+    let key = hasher.finish();
+    if let Some(cached) = cache().lock().ok().and_then(|c| c.get(&key).cloned()) {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        return cached;
+    }
+    MISSES.fetch_add(1, Ordering::Relaxed);
+
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    let mut out = Vec::with_capacity(lines.len());
+    for line in lines {
+        // The newline-aware grammars expect each line to end with one
+        let line = format!("{}\n", line);
+        let Ok(ranges) = highlighter.highlight_line(&line, syntaxes()) else {
+            return plain();
+        };
+        out.push(
+            ranges
+                .into_iter()
+                .map(|(style, text)| (text.trim_end_matches('\n').to_string(), to_style(style)))
+                .filter(|(text, _)| !text.is_empty())
+                .collect(),
+        );
+    }
+
+    if let Ok(mut cache) = cache().lock() {
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(key, out.clone());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(pieces: &[Piece]) -> String {
+        pieces.iter().map(|(t, _)| t.as_str()).collect()
+    }
+
+    #[test]
+    fn test_known_language_is_colored() {
+        let lines = vec!["fn main() {".to_string(), "}".to_string()];
+        let highlighted = highlight(&lines, "rust");
+        assert_eq!(highlighted.len(), 2);
+        assert_eq!(text(&highlighted[0]), "fn main() {");
+        let keyword = &highlighted[0][0];
+        assert_eq!(keyword.0, "fn");
+        assert_ne!(keyword.1.fg, plain_style().fg);
+        assert!(highlighted
+            .iter()
+            .flatten()
+            .all(|(_, s)| s.bg == Some(CODE_BG)));
+    }
+
+    #[test]
+    fn test_unknown_language_is_plain() {
+        let lines = vec!["some text".to_string()];
+        assert_eq!(
+            highlight(&lines, "no-such-language"),
+            vec![vec![("some text".to_string(), plain_style())]]
+        );
+        assert_eq!(highlight(&lines, "")[0][0].1, plain_style());
+    }
+}
@@ -0,0 +1,119 @@
+//! Input box layout and viewport.
+//!
+//! The draft is laid out into rows of the box's width cell by cell, with the
+//! cursor taking a cell of its own, so the cursor stays on the same character
+//! however the text reflows when the terminal is resized. Newlines start a
+//! new row. The box shows a few rows; the viewport scrolls to keep the row
+//! with the cursor in view.
+
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthChar;
+
+/// Which rows of the laid out input are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputViewport {
+    /// First visible row
+    pub scroll: usize,
+}
+
+impl InputViewport {
+    /// Scroll as little as possible to show `row` of `rows` in `height` rows.
+    pub fn follow(&mut self, row: usize, rows: usize, height: usize) {
+        let height = height.max(1);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + height {
+            self.scroll = row + 1 - height;
+        }
+        // Don't leave empty rows below the text when it shrinks
+        self.scroll = self.scroll.min(rows.saturating_sub(height));
+    }
+}
+
+/// Lay `spans` out into rows at most `width` cells wide. Returns the rows and
+/// the row holding the span at index `marked` (the cursor).
+pub fn wrap_spans<'a>(spans: &[Span<'a>], width: usize, marked: usize) -> (Vec<Line<'a>>, usize) {
+    let width = width.max(1);
+    let mut rows: Vec<Line<'a>> = Vec::new();
+    let mut row: Vec<Span<'a>> = Vec::new();
+    let mut col = 0;
+    let mut marked_row = 0;
+
+    for (i, span) in spans.iter().enumerate() {
+        let mut run = String::new();
+        let mut placed = false;
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if c == '\n' || col + w > width {
+                if !run.is_empty() {
+                    row.push(Span::styled(std::mem::take(&mut run), span.style));
+                }
+                rows.push(Line::from(std::mem::take(&mut row)));
+                col = 0;
+                if c == '\n' {
+                    continue;
+                }
+            }
+            if i == marked && !placed {
+                marked_row = rows.len();
+                placed = true;
+            }
+            run.push(c);
+            col += w;
+        }
+        if i == marked && !placed {
+            marked_row = rows.len();
+        }
+        if !run.is_empty() {
+            row.push(Span::styled(run, span.style));
+        }
+    }
+    rows.push(Line::from(row));
+    (rows, marked_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_wrap_keeps_cursor_on_its_character() {
+        let spans = [Span::raw("hello wor"), Span::raw("|"), Span::raw("ld")];
+        let (rows, cursor) = wrap_spans(&spans, 4, 1);
+        let texts: Vec<String> = rows.iter().map(text).collect();
+        assert_eq!(texts, ["hell", "o wo", "r|ld"]);
+        assert_eq!(cursor, 2);
+
+        // Wider: same character, different row
+        let (rows, cursor) = wrap_spans(&spans, 6, 1);
+        assert_eq!(text(&rows[cursor]), "wor|ld");
+    }
+
+    #[test]
+    fn test_newlines_and_wide_characters_break_rows() {
+        let spans = [Span::raw("a\n你好吗"), Span::raw("|")];
+        let (rows, cursor) = wrap_spans(&spans, 4, 1);
+        let texts: Vec<String> = rows.iter().map(text).collect();
+        assert_eq!(texts, ["a", "你好", "吗|"]);
+        assert_eq!(cursor, 2);
+    }
+
+    #[test]
+    fn test_viewport_follows_cursor() {
+        let mut view = InputViewport::default();
+        view.follow(4, 6, 3);
+        assert_eq!(view.scroll, 2);
+        view.follow(3, 6, 3);
+        assert_eq!(view.scroll, 2);
+        view.follow(0, 6, 3);
+        assert_eq!(view.scroll, 0);
+        // The text shrank to one row
+        view.scroll = 2;
+        view.follow(0, 1, 3);
+        assert_eq!(view.scroll, 0);
+    }
+}
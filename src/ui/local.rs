@@ -0,0 +1,270 @@
+//! Local model status panel (`/local`).
+//!
+//! Shows the models Ollama currently holds in memory, how much of each sits
+//! in VRAM and when it will be unloaded, with an action to unload a model
+//! right away to free GPU memory.
+
+use chrono::{DateTime, FixedOffset, Utc};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use super::render::centered_rect;
+use crate::llm::RunningModel;
+
+/// Result of handling a key event in the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalModelsAction {
+    /// Keep the panel open.
+    Continue,
+    /// Close the panel.
+    Close,
+    /// Query `/api/ps` again.
+    Refresh,
+    /// Unload the named model.
+    Unload(String),
+}
+
+/// State of the local models panel.
+#[derive(Debug, Clone, Default)]
+pub struct LocalModelsPanel {
+    /// Models reported by the last successful query
+    pub models: Vec<RunningModel>,
+    /// Index of the highlighted model
+    pub selected: usize,
+    /// Whether a query or unload is in flight
+    pub loading: bool,
+    /// Error from the last query, if it failed
+    pub error: Option<String>,
+}
+
+impl LocalModelsPanel {
+    /// Create a panel waiting for its first query.
+    pub fn new() -> Self {
+        Self {
+            loading: true,
+            ..Self::default()
+        }
+    }
+
+    /// Apply the result of a query.
+    pub fn update(&mut self, result: Result<Vec<RunningModel>, String>) {
+        self.loading = false;
+        match result {
+            Ok(models) => {
+                self.models = models;
+                self.selected = self.selected.min(self.models.len().saturating_sub(1));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> LocalModelsAction {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => LocalModelsAction::Close,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                LocalModelsAction::Continue
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < self.models.len() {
+                    self.selected += 1;
+                }
+                LocalModelsAction::Continue
+            }
+            KeyCode::Char('r') if !self.loading => LocalModelsAction::Refresh,
+            KeyCode::Char('u') | KeyCode::Delete if !self.loading => {
+                match self.models.get(self.selected) {
+                    Some(model) => LocalModelsAction::Unload(model.name.clone()),
+                    None => LocalModelsAction::Continue,
+                }
+            }
+            _ => LocalModelsAction::Continue,
+        }
+    }
+}
+
+/// Format a byte count as a human-readable size.
+pub(super) fn format_size(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else {
+        format!("{:.0} MB", bytes / MB)
+    }
+}
+
+/// Describe when a model will be unloaded.
+fn format_expiry(expires_at: Option<DateTime<FixedOffset>>, now: DateTime<Utc>) -> String {
+    let Some(expires_at) = expires_at else {
+        return "-".to_string();
+    };
+    let remaining = expires_at.with_timezone(&Utc) - now;
+    if remaining.num_days() > 365 {
+        // keep_alive = -1 keeps the model loaded indefinitely
+        "never".to_string()
+    } else if remaining.num_seconds() <= 0 {
+        "now".to_string()
+    } else if remaining.num_minutes() < 1 {
+        format!("in {}s", remaining.num_seconds())
+    } else if remaining.num_hours() < 1 {
+        format!("in {}m", remaining.num_minutes())
+    } else {
+        format!(
+            "in {}h {}m",
+            remaining.num_hours(),
+            remaining.num_minutes() % 60
+        )
+    }
+}
+
+/// Render the local models panel.
+pub fn render_local_models(f: &mut Frame, panel: &LocalModelsPanel) {
+    let area = centered_rect(70, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let title = if panel.loading {
+        " Local Models (loading…) "
+    } else {
+        " Local Models "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Model list
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    let mut lines = Vec::new();
+    if let Some(error) = &panel.error {
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    if panel.models.is_empty() && !panel.loading {
+        lines.push(Line::from(Span::styled(
+            "No models loaded",
+            Style::default().fg(Color::Gray),
+        )));
+    } else if !panel.models.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {:<28} {:>9} {:>9}  {}",
+                "MODEL", "SIZE", "GPU", "UNLOADS"
+            ),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    let now = Utc::now();
+    for (i, model) in panel.models.iter().enumerate() {
+        let selected = i == panel.selected;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{} {:<28} {:>9} {:>8}%  {}",
+                if selected { ">" } else { " " },
+                model.name,
+                format_size(model.size),
+                model.gpu_percent(),
+                format_expiry(model.expires_at, now)
+            ),
+            style,
+        )));
+    }
+
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), chunks[0]);
+
+    let hints = Paragraph::new("↑↓ select · u unload · r refresh · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str) -> RunningModel {
+        RunningModel {
+            name: name.to_string(),
+            size: 4 * 1024 * 1024 * 1024,
+            size_vram: 4 * 1024 * 1024 * 1024,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_unload_selected_model() {
+        let mut panel = LocalModelsPanel::new();
+        assert_eq!(
+            panel.handle_key(KeyCode::Char('u')),
+            LocalModelsAction::Continue
+        );
+
+        panel.update(Ok(vec![model("a"), model("b")]));
+        panel.handle_key(KeyCode::Down);
+        assert_eq!(
+            panel.handle_key(KeyCode::Char('u')),
+            LocalModelsAction::Unload("b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_update_clamps_selection_and_keeps_models_on_error() {
+        let mut panel = LocalModelsPanel::new();
+        panel.update(Ok(vec![model("a"), model("b")]));
+        panel.selected = 1;
+
+        panel.update(Ok(vec![model("a")]));
+        assert_eq!(panel.selected, 0);
+
+        panel.update(Err("connection refused".to_string()));
+        assert_eq!(panel.models.len(), 1);
+        assert_eq!(panel.error.as_deref(), Some("connection refused"));
+    }
+
+    #[test]
+    fn test_format_expiry() {
+        let now = Utc::now();
+        let at = |secs: i64| Some((now + chrono::Duration::seconds(secs)).fixed_offset());
+        assert_eq!(format_expiry(None, now), "-");
+        assert_eq!(format_expiry(at(30), now), "in 30s");
+        assert_eq!(format_expiry(at(4 * 60 + 5), now), "in 4m");
+        assert_eq!(format_expiry(at(-5), now), "now");
+        assert_eq!(format_expiry(at(400 * 24 * 3600), now), "never");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024 / 2), "2.5 GB");
+        assert_eq!(format_size(300 * 1024 * 1024), "300 MB");
+    }
+}
@@ -0,0 +1,248 @@
+//! Lightweight markdown rendering for assistant messages.
+//!
+//! Covers what chat replies mostly use: headings, bullet lists, block
+//! quotes, fenced code blocks, inline code and bold. Markers are hidden and
+//! replaced by styling; code blocks are syntax highlighted on a shaded
+//! background, kept verbatim and never wrapped. Anything else passes through
+//! as text.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_width::UnicodeWidthStr;
+
+use super::highlight::{self, Piece};
+
+/// Split a line into styled pieces for `code` and **bold** spans.
+fn inline_pieces(text: &str, base: Style) -> Vec<Piece> {
+    let code = Style::default().fg(Color::Yellow);
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let next_code = rest.find('`');
+        let next_bold = rest.find("**");
+        let (start, marker, style) = match (next_code, next_bold) {
+            (Some(c), Some(b)) if b < c => (b, "**", base.add_modifier(Modifier::BOLD)),
+            (Some(c), _) => (c, "`", code),
+            (None, Some(b)) => (b, "**", base.add_modifier(Modifier::BOLD)),
+            (None, None) => break,
+        };
+        let after = &rest[start + marker.len()..];
+        let Some(end) = after.find(marker) else {
+            break;
+        };
+        if start > 0 {
+            pieces.push((rest[..start].to_string(), base));
+        }
+        pieces.push((after[..end].to_string(), style));
+        rest = &after[end + marker.len()..];
+    }
+    if !rest.is_empty() {
+        pieces.push((rest.to_string(), base));
+    }
+    pieces
+}
+
+/// Word-wrap styled pieces to `width` columns, continuing lines with `indent`.
+fn wrap_pieces(pieces: &[Piece], width: usize, indent: &str) -> Vec<Vec<Piece>> {
+    // Words are runs of non-whitespace that may span several styles
+    let mut words: Vec<Vec<Piece>> = vec![Vec::new()];
+    for (text, style) in pieces {
+        for (i, part) in text.split(char::is_whitespace).enumerate() {
+            if i > 0 && !words.last().is_some_and(Vec::is_empty) {
+                words.push(Vec::new());
+            }
+            if !part.is_empty() {
+                words.last_mut().unwrap().push((part.to_string(), *style));
+            }
+        }
+    }
+
+    let mut lines = vec![Vec::new()];
+    let mut used = 0;
+    for word in words.into_iter().filter(|w| !w.is_empty()) {
+        let word_width: usize = word.iter().map(|(t, _)| t.width()).sum();
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && used + 1 + word_width > width {
+            lines.push(vec![(indent.to_string(), Style::default())]);
+            used = indent.width();
+        } else if !line.is_empty() {
+            line.push((" ".to_string(), Style::default()));
+            used += 1;
+        }
+        lines.last_mut().unwrap().extend(word);
+        used += word_width;
+    }
+    lines
+}
+
+/// Render markdown source into lines at most `width` columns wide (except code).
+pub fn render(content: &str, width: usize, base: Style) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    // Language and lines of the open code block, if any
+    let mut code: Option<(String, Vec<String>)> = None;
+
+    for raw in content.lines() {
+        let trimmed = raw.trim_start();
+        if trimmed.starts_with("```") {
+            match code.take() {
+                Some((lang, block)) => lines.extend(code_lines(&block, &lang, width)),
+                None => {
+                    // Keep the language tag as a dim label, drop the fences themselves
+                    let lang = trimmed.trim_start_matches('`').trim();
+                    if !lang.is_empty() {
+                        lines.push(Line::from(Span::styled(
+                            lang.to_string(),
+                            Style::default().fg(Color::DarkGray),
+                        )));
+                    }
+                    code = Some((lang.to_string(), Vec::new()));
+                }
+            }
+            continue;
+        }
+        if let Some((_, block)) = &mut code {
+            block.push(raw.replace('\t', "    "));
+            continue;
+        }
+
+        let (lead, text, style) = if let Some(heading) = trimmed
+            .strip_prefix('#')
+            .map(|h| h.trim_start_matches('#'))
+            .filter(|h| h.starts_with(' '))
+        {
+            (
+                String::new(),
+                heading.trim(),
+                base.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            )
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            let depth = raw.len() - trimmed.len();
+            (format!("{}• ", " ".repeat(depth)), item, base)
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            (
+                "│ ".to_string(),
+                quote.trim_start(),
+                base.add_modifier(Modifier::ITALIC),
+            )
+        } else {
+            (String::new(), raw, base)
+        };
+
+        let mut pieces = vec![(lead.clone(), base)];
+        pieces.extend(inline_pieces(text, style));
+        let indent = " ".repeat(lead.width());
+        for line in wrap_pieces(&pieces, width, &indent) {
+            lines.push(Line::from(
+                line.into_iter()
+                    .map(|(t, s)| Span::styled(t, s))
+                    .collect::<Vec<_>>(),
+            ));
+        }
+    }
+
+    // A block still being streamed has no closing fence yet
+    if let Some((lang, block)) = code {
+        lines.extend(code_lines(&block, &lang, width));
+    }
+
+    if lines.is_empty() {
+        lines.push(Line::from(""));
+    }
+    lines
+}
+
+/// Highlight a code block, padding short lines to `width` so the background
+/// forms a block. Long lines are left unwrapped.
+fn code_lines(block: &[String], lang: &str, width: usize) -> Vec<Line<'static>> {
+    highlight::highlight(block, lang)
+        .into_iter()
+        .map(|pieces| {
+            let used: usize = pieces.iter().map(|(t, _)| t.width()).sum();
+            let mut spans: Vec<Span> = pieces
+                .into_iter()
+                .map(|(t, s)| Span::styled(t, s))
+                .collect();
+            if used < width {
+                spans.push(Span::styled(
+                    " ".repeat(width - used),
+                    Style::default().bg(highlight::CODE_BG),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    fn render_text(content: &str, width: usize) -> Vec<String> {
+        render(content, width, Style::default())
+            .iter()
+            .map(text)
+            .collect()
+    }
+
+    #[test]
+    fn test_markers_are_hidden() {
+        assert_eq!(
+            render_text("# Title\nUse `cargo` and **care**.\n- item\n> quoted", 80),
+            vec!["Title", "Use cargo and care.", "• item", "│ quoted"]
+        );
+    }
+
+    #[test]
+    fn test_code_blocks_are_verbatim() {
+        let rendered = render_text("```rust\nfn main() {    let very_long = 1; }\n```", 10);
+        assert_eq!(
+            rendered,
+            vec!["rust", "fn main() {    let very_long = 1; }"]
+        );
+    }
+
+    #[test]
+    fn test_code_blocks_are_highlighted_blocks() {
+        let lines = render("Before\n```rust\nlet x = 1;\n```\nAfter", 20, Style::default());
+        assert_eq!(lines.len(), 4);
+        // Short code lines are padded so the background forms a block
+        assert_eq!(text(&lines[2]), format!("{:<20}", "let x = 1;"));
+        assert!(lines[2]
+            .spans
+            .iter()
+            .all(|s| s.style.bg == Some(highlight::CODE_BG)));
+        assert_eq!(text(&lines[3]), "After");
+    }
+
+    #[test]
+    fn test_unclosed_code_block_is_still_rendered() {
+        let rendered = render_text("```\nstreaming...", 12);
+        assert_eq!(rendered, vec!["streaming...".to_string()]);
+    }
+
+    #[test]
+    fn test_wrapping_keeps_styles_and_list_indent() {
+        let lines = render("- alpha **beta** gamma", 14, Style::default());
+        assert_eq!(text(&lines[0]), "• alpha beta");
+        assert_eq!(text(&lines[1]), "  gamma");
+        let beta = lines[0].spans.iter().find(|s| s.content == "beta").unwrap();
+        assert!(beta.style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_unclosed_markers_stay_literal() {
+        assert_eq!(
+            render_text("2 ** 3 and a `tick", 80),
+            vec!["2 ** 3 and a `tick"]
+        );
+    }
+}
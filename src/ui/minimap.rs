@@ -0,0 +1,179 @@
+//! Conversation minimap (Ctrl+B).
+//!
+//! A narrow column beside the chat that compresses the whole conversation
+//! into one row per bucket of messages: colored ticks show who spoke, rules
+//! mark section boundaries and a shaded band shows the part in view. Large
+//! sessions stay navigable at a glance; Shift+Up/Down jump between prompts.
+
+use std::ops::Range;
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::message::{Message, Role};
+use crate::sections::Section;
+
+/// Width of the minimap column, including its left gap.
+pub const MINIMAP_WIDTH: u16 = 3;
+
+/// What a minimap row stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tick {
+    /// No messages map to this row
+    Empty,
+    /// Contains a user prompt
+    User,
+    /// Assistant replies only
+    Assistant,
+    /// Contains a failed request
+    Error,
+    /// Banners and notes only
+    Note,
+}
+
+/// One row of the minimap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimapRow {
+    pub tick: Tick,
+    /// A section starts within this row
+    pub boundary: bool,
+    /// The row overlaps the visible messages
+    pub in_view: bool,
+}
+
+/// Compress `messages` into `height` rows.
+///
+/// Each row covers an equal share of the messages; short conversations use
+/// one row per message. `viewport` is the range of visible message indices.
+pub fn minimap_rows(
+    messages: &[Message],
+    sections: &[Section],
+    viewport: Range<usize>,
+    height: usize,
+) -> Vec<MinimapRow> {
+    let total = messages.len();
+    if total == 0 || height == 0 {
+        return Vec::new();
+    }
+    let rows = height.min(total);
+
+    (0..rows)
+        .map(|row| {
+            let start = row * total / rows;
+            let end = ((row + 1) * total / rows).max(start + 1);
+            let bucket = &messages[start..end];
+
+            let tick = if bucket.iter().any(|m| m.is_error()) {
+                Tick::Error
+            } else if bucket.iter().any(|m| m.is_chat() && m.role == Role::User) {
+                Tick::User
+            } else if bucket.iter().any(|m| m.is_chat()) {
+                Tick::Assistant
+            } else {
+                Tick::Note
+            };
+            MinimapRow {
+                tick,
+                // The first section always starts at 0, so it needs no rule
+                boundary: sections
+                    .iter()
+                    .skip(1)
+                    .any(|s| (start..end).contains(&s.start)),
+                in_view: start < viewport.end && viewport.start < end,
+            }
+        })
+        .chain(std::iter::repeat(MinimapRow {
+            tick: Tick::Empty,
+            boundary: false,
+            in_view: false,
+        }))
+        .take(height)
+        .collect()
+}
+
+/// Render minimap rows into `area`.
+pub fn render_minimap(f: &mut Frame, area: Rect, rows: &[MinimapRow]) {
+    let lines: Vec<Line> = rows
+        .iter()
+        .map(|row| {
+            let (symbol, color) = match (row.boundary, row.tick) {
+                (true, _) => ("━━", Color::Magenta),
+                (false, Tick::User) => ("▐█", Color::Cyan),
+                (false, Tick::Assistant) => ("▐█", Color::Green),
+                (false, Tick::Error) => ("▐█", Color::Red),
+                (false, Tick::Note) => ("▐▌", Color::DarkGray),
+                (false, Tick::Empty) => ("  ", Color::Reset),
+            };
+            let mut style = Style::default().fg(color);
+            if row.in_view {
+                style = style.bg(Color::DarkGray);
+            }
+            Line::from(vec![Span::raw(" "), Span::styled(symbol, style)])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(turns: usize) -> Vec<Message> {
+        (0..turns)
+            .flat_map(|i| {
+                [
+                    Message::user(format!("question {}", i)),
+                    Message::assistant(format!("answer {}", i)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_short_conversation_uses_one_row_per_message() {
+        let messages = conversation(2);
+        let rows = minimap_rows(&messages, &[], 2..4, 6);
+        assert_eq!(rows.len(), 6);
+        let ticks: Vec<Tick> = rows.iter().map(|r| r.tick).collect();
+        assert_eq!(
+            ticks,
+            vec![
+                Tick::User,
+                Tick::Assistant,
+                Tick::User,
+                Tick::Assistant,
+                Tick::Empty,
+                Tick::Empty
+            ]
+        );
+        assert!(!rows[1].in_view && rows[2].in_view && rows[3].in_view);
+    }
+
+    #[test]
+    fn test_long_conversation_is_bucketed() {
+        let messages = conversation(500);
+        let sections = vec![
+            Section {
+                start: 0,
+                end: 500,
+                title: "a".to_string(),
+            },
+            Section {
+                start: 500,
+                end: 1000,
+                title: "b".to_string(),
+            },
+        ];
+        let rows = minimap_rows(&messages, &sections, 990..1000, 20);
+        assert_eq!(rows.len(), 20);
+        assert!(rows.iter().all(|r| r.tick == Tick::User));
+        assert!(rows[10].boundary);
+        assert_eq!(rows.iter().filter(|r| r.boundary).count(), 1);
+        assert!(rows[19].in_view && !rows[18].in_view);
+    }
+}
@@ -2,23 +2,71 @@
 //!
 //! This module contains all UI rendering logic including:
 //! - Main UI layout and rendering
+//! - Input box layout and scrolling
+//! - Chat message layout (plain or bubbles)
+//! - Markdown rendering for replies, with syntax highlighted code
 //! - Menu overlay
 //! - Modal dialogs
+//! - Status bar
+//! - Diff review for file rewrites
+//! - Local model status panel
+//! - Section picker
+//! - Workspace file tree
+//! - Attachment manager
+//! - Tool call confirmation
+//! - Conversation minimap
+//! - Profiling overlay
+//! - Full-screen message viewer
 //! - Auth dialogs for OAuth
 //! - Toast notifications
 //! - Gradient utilities
 //! - Text processing
 
 pub mod anthropic_dialogs;
+mod attachments;
 mod auth_dialog;
+mod bubble;
 mod dialog;
+mod diff;
+mod drag;
+mod file_tree;
+mod global_search;
 mod gradient;
+mod highlight;
+mod input_view;
+mod local;
+mod markdown;
 mod menu;
+mod minimap;
+mod profile;
+mod profile_picker;
+mod pull;
 mod render;
+mod search;
+mod sections;
+mod selection;
+mod session_picker;
+mod status_bar;
 pub mod text;
 mod toast;
+mod tool_confirm;
+mod viewer;
 
+pub use attachments::{render_attachments, Attachment, AttachmentsAction, AttachmentsPanel};
 pub use auth_dialog::{AuthDialog, AuthDialogResult, AuthDialogState};
 pub use dialog::{Dialog, DialogAction, DialogContent, DialogResult, DialogState};
+pub use local::{render_local_models, LocalModelsAction, LocalModelsPanel};
+pub use drag::{DragSelection, DRAG_BG};
+pub use diff::{hunk_lines, render_diff_review, DiffReview, ReviewAction};
+pub use input_view::InputViewport;
+pub use file_tree::{format_size, render_file_tree, FileTree, FileTreeAction};
+pub use global_search::{render_global_search, GlobalSearch, GlobalSearchAction};
+pub use profile_picker::{render_profile_picker, ProfilePicker, ProfilePickerAction};
 pub use render::ui;
+pub use search::{render_search_bar, ConversationSearch, SearchAction, SearchMatch};
+pub use sections::{render_section_picker, SectionPicker, SectionPickerAction};
+pub use selection::{MessageSelection, SelectionAction, SELECTION_HINTS};
+pub use session_picker::{render_session_picker, SessionPicker, SessionPickerAction};
 pub use toast::{render_toasts, Toast, ToastLevel, ToastState};
+pub use tool_confirm::{render_tool_confirmation, ToolConfirmAction, ToolConfirmation};
+pub use viewer::{render_message_viewer, MessageViewer, ViewerAction};
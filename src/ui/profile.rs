@@ -0,0 +1,147 @@
+//! Profiling overlay (F12).
+//!
+//! A small box in the top-right corner with frames per second, where the
+//! last frame's time went (layout, wrapping, rendering), how often the code
+//! highlighting cache is hit, allocations per frame, and how full the reply
+//! stream's channel has run. Meant for checking performance changes while
+//! using the app.
+
+use std::time::Duration;
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::highlight;
+use crate::llm::StreamStatsSnapshot;
+use crate::profiling;
+use crate::scheduler::FrameStats;
+
+/// Width of the overlay, borders included.
+const WIDTH: u16 = 30;
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Lines shown in the overlay, given highlight cache `hits` and `misses`,
+/// whether allocations are being `counted`, and the connected client's
+/// `stream` channel statistics.
+pub fn profile_lines(
+    stats: &FrameStats,
+    hits: u64,
+    misses: u64,
+    counted: bool,
+    stream: Option<StreamStatsSnapshot>,
+) -> Vec<Line<'static>> {
+    let last = &stats.last;
+    let hit_rate = match hits + misses {
+        0 => "-".to_string(),
+        lookups => format!("{:.0}%", hits as f64 * 100.0 / lookups as f64),
+    };
+    let allocations = if counted {
+        last.allocations.to_string()
+    } else {
+        "-".to_string()
+    };
+    let stream_stat = |value: fn(&StreamStatsSnapshot) -> String| {
+        stream.as_ref().map_or_else(|| "-".to_string(), value)
+    };
+    [
+        ("fps", stats.fps().to_string()),
+        ("layout", millis(last.layout)),
+        ("wrap", millis(last.wrap)),
+        ("render", millis(last.render)),
+        ("draw avg", millis(stats.average_draw())),
+        ("cache hits", hit_rate),
+        ("allocs/frame", allocations),
+        ("stream events", stream_stat(|s| s.events.to_string())),
+        ("channel full", stream_stat(|s| s.full.to_string())),
+        ("coalesced", stream_stat(|s| s.coalesced.to_string())),
+        ("queue depth", stream_stat(|s| s.max_depth.to_string())),
+    ]
+    .into_iter()
+    .map(|(label, value)| {
+        Line::from(vec![
+            Span::styled(
+                format!("{:<13}", label),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(value, Style::default().fg(Color::Cyan)),
+        ])
+    })
+    .collect()
+}
+
+/// Draw the overlay in the top-right corner of `area`.
+pub fn render_profile_overlay(
+    f: &mut Frame,
+    area: Rect,
+    stats: &FrameStats,
+    stream: Option<StreamStatsSnapshot>,
+) {
+    let (hits, misses) = highlight::cache_stats();
+    let lines = profile_lines(stats, hits, misses, profiling::counting(), stream);
+    let width = WIDTH.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let overlay = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+    f.render_widget(Clear, overlay);
+
+    let block = Block::default()
+        .title(" Profile ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta))
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(Paragraph::new(lines).block(block), overlay);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_profile_lines() {
+        let mut stats = FrameStats::default();
+        stats.record_frame(Duration::from_millis(3));
+        stats.last.wrap = Duration::from_micros(1500);
+        stats.last.allocations = 42;
+
+        let stream = StreamStatsSnapshot {
+            events: 120,
+            full: 3,
+            coalesced: 7,
+            max_depth: 64,
+        };
+
+        let lines: Vec<String> = profile_lines(&stats, 3, 1, true, Some(stream))
+            .iter()
+            .map(text)
+            .collect();
+        assert!(lines[0].ends_with('1'));
+        assert!(lines[2].ends_with("1.50ms"));
+        assert!(lines[5].ends_with("75%"));
+        assert!(lines[6].ends_with("42"));
+        assert!(lines[7].ends_with("120"));
+        assert!(lines[8].ends_with('3'));
+        assert!(lines[9].ends_with('7'));
+        assert!(lines[10].ends_with("64"));
+
+        // Nothing to report before the first lookup, without the counting
+        // allocator or without a connection
+        let lines: Vec<String> = profile_lines(&stats, 0, 0, false, None)
+            .iter()
+            .map(text)
+            .collect();
+        assert!(lines[5].ends_with('-'));
+        assert!(lines[6].ends_with('-'));
+        assert!(lines[7..].iter().all(|line| line.ends_with('-')));
+    }
+}
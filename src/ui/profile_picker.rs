@@ -0,0 +1,151 @@
+//! Profile picker (Switch Profile in the Ctrl+P menu).
+//!
+//! Lists the main config and every named profile, marking the one in use.
+//! Picking another switches the connection to that profile's provider, model,
+//! system prompt and credentials.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::render::centered_rect;
+
+/// Result of handling a key event in the picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfilePickerAction {
+    /// Keep the picker open.
+    Continue,
+    /// Close the picker.
+    Close,
+    /// Switch to this profile (`None` for the main config) and close.
+    Switch(Option<String>),
+}
+
+/// State of the profile picker.
+#[derive(Debug, Clone, Default)]
+pub struct ProfilePicker {
+    /// Profiles to choose from; `None` is the main config
+    pub profiles: Vec<Option<String>>,
+    /// Profile in use
+    pub current: Option<String>,
+    /// Index of the highlighted profile
+    pub selected: usize,
+}
+
+impl ProfilePicker {
+    /// Create a picker over the main config and `names`, with `current` highlighted.
+    pub fn new(names: Vec<String>, current: Option<String>) -> Self {
+        let profiles: Vec<Option<String>> =
+            std::iter::once(None).chain(names.into_iter().map(Some)).collect();
+        let selected = profiles.iter().position(|p| *p == current).unwrap_or(0);
+        Self {
+            profiles,
+            current,
+            selected,
+        }
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> ProfilePickerAction {
+        match key {
+            KeyCode::Esc => ProfilePickerAction::Close,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                ProfilePickerAction::Continue
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < self.profiles.len() {
+                    self.selected += 1;
+                }
+                ProfilePickerAction::Continue
+            }
+            KeyCode::Enter => match self.profiles.get(self.selected) {
+                Some(profile) if *profile == self.current => ProfilePickerAction::Close,
+                Some(profile) => ProfilePickerAction::Switch(profile.clone()),
+                None => ProfilePickerAction::Continue,
+            },
+            _ => ProfilePickerAction::Continue,
+        }
+    }
+}
+
+/// Render the profile picker.
+pub fn render_profile_picker(f: &mut Frame, picker: &ProfilePicker) {
+    let area = centered_rect(50, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Profiles ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Profile list
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    let visible = (chunks[0].height as usize).max(1);
+    let first = (picker.selected + 1).saturating_sub(visible);
+
+    let mut lines = Vec::new();
+    for (i, profile) in picker.profiles.iter().enumerate().skip(first).take(visible) {
+        let selected = i == picker.selected;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let mut line = vec![Span::styled(
+            format!(
+                "{} {}",
+                if selected { ">" } else { " " },
+                profile.as_deref().unwrap_or("(main config)")
+            ),
+            style,
+        )];
+        if *profile == picker.current {
+            line.push(Span::styled(" · in use", Style::default().fg(Color::DarkGray)));
+        }
+        lines.push(Line::from(line));
+    }
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Paragraph::new("↑↓ select · Enter switch · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starts_on_the_current_profile_and_switches() {
+        let names = vec!["personal".to_string(), "work".to_string()];
+        let mut picker = ProfilePicker::new(names, Some("work".to_string()));
+        assert_eq!(picker.selected, 2);
+        assert_eq!(picker.handle_key(KeyCode::Enter), ProfilePickerAction::Close);
+
+        picker.handle_key(KeyCode::Up);
+        picker.handle_key(KeyCode::Up);
+        picker.handle_key(KeyCode::Up);
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter),
+            ProfilePickerAction::Switch(None)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Ollama model pull progress overlay.
+//!
+//! Shown while `/api/pull` downloads a missing model: the current stage
+//! (manifest, download, verification) and a progress bar for the layer
+//! being downloaded.
+
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Gauge, Paragraph},
+    Frame,
+};
+
+use super::local::format_size;
+use super::render::centered_rect;
+use crate::llm::PullStatus;
+
+/// Human-readable name of a pull stage.
+fn stage(status: &str) -> &str {
+    if status.is_empty() {
+        "Starting"
+    } else if status == "pulling manifest" {
+        "Fetching manifest"
+    } else if status.starts_with("pulling") {
+        "Downloading"
+    } else if status.starts_with("verifying") {
+        "Verifying"
+    } else if status.starts_with("writing") {
+        "Writing manifest"
+    } else if status == "success" {
+        "Done"
+    } else {
+        status
+    }
+}
+
+/// Progress bar label, such as `1.2 GB / 2.0 GB`.
+fn progress_label(progress: &PullStatus) -> String {
+    match (progress.completed, progress.total) {
+        (Some(completed), Some(total)) if total > 0 => {
+            format!("{} / {}", format_size(completed), format_size(total))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Render the pull progress overlay.
+pub fn render_ollama_pull(f: &mut Frame, model: &str, progress: &PullStatus) {
+    let area = centered_rect(60, 30, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Pulling {} ", model))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(1), // Stage
+        Constraint::Length(1), // Spacer
+        Constraint::Length(1), // Progress bar
+        Constraint::Min(0),    // Spacer
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let stage_line = Line::from(vec![
+        Span::styled(stage(&progress.status), Style::default().fg(Color::White)),
+        Span::styled(
+            format!("  {}", progress.status),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]);
+    f.render_widget(Paragraph::new(stage_line), chunks[0]);
+
+    // Stages without a size (manifest, verification) show an empty bar
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray))
+        .ratio(progress.fraction().unwrap_or(0.0))
+        .label(progress_label(progress));
+    f.render_widget(gauge, chunks[2]);
+
+    let hints = Line::from(vec![
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[4],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_names() {
+        assert_eq!(stage(""), "Starting");
+        assert_eq!(stage("pulling manifest"), "Fetching manifest");
+        assert_eq!(stage("pulling 6a0746a1ec1a"), "Downloading");
+        assert_eq!(stage("verifying sha256 digest"), "Verifying");
+        assert_eq!(stage("writing manifest"), "Writing manifest");
+        assert_eq!(stage("removing unused layers"), "removing unused layers");
+    }
+
+    #[test]
+    fn test_progress_label() {
+        let progress = PullStatus {
+            status: "pulling 6a0746a1ec1a".to_string(),
+            total: Some(2 * 1024 * 1024 * 1024),
+            completed: Some(512 * 1024 * 1024),
+        };
+        assert_eq!(progress_label(&progress), "512 MB / 2.0 GB");
+        assert_eq!(progress_label(&PullStatus::default()), "");
+    }
+}
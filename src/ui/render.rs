@@ -1,26 +1,58 @@
+use std::ops::Range;
+use std::time::Instant;
+
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Margin, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, Wrap},
+    widgets::{
+        block::{Position, Title}, Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar,
+        ScrollbarOrientation, Wrap,
+    },
     Frame,
 };
 
-use crate::app::{App, ConnectState};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, ConnectState, ModelMismatch, PastedFiles, SendConfirmation};
+use crate::commands;
 use crate::config::Config;
-use crate::llm::{Provider, ANTHROPIC_MODELS, COPILOT_MODELS};
-use crate::message::Role;
+use crate::image::Image;
+use crate::llm::{FinishReason, InstalledModel, ModelInfo, Provider};
+use crate::message::{ErrorCard, Message, ReplyMeta};
+use crate::paste::chip_ranges;
+use crate::routing::Tier;
+use crate::usage::{format_cost, SpendCheck};
+use crate::variables::{placeholder_ranges, Expansion};
 
+use super::attachments::render_attachments;
 use super::anthropic_dialogs::{
     render_anthropic_method_dialog, render_auth_code_entry_dialog, render_exchanging_code_dialog,
 };
+use super::bubble::message_lines;
 use super::gradient::gradient_color;
+use super::input_view::wrap_spans;
+use super::local::{format_size, render_local_models};
 use super::menu::render_menu;
+use super::minimap::{minimap_rows, render_minimap, MINIMAP_WIDTH};
+use super::profile::render_profile_overlay;
+use super::pull::render_ollama_pull;
+use super::file_tree::render_file_tree;
+use super::global_search::render_global_search;
+use super::sections::render_section_picker;
+use super::selection::SELECTION_BG;
+use super::profile_picker::render_profile_picker;
+use super::search::render_search_bar;
+use super::session_picker::render_session_picker;
+use super::status_bar::{format_tokens, render_status_bar};
 use super::text::{apply_miami_gradient_to_line, wrap_text};
 use super::toast::render_toasts;
+use super::tool_confirm::render_tool_confirmation;
+use super::viewer::render_message_viewer;
 
 /// Main UI rendering function.
 pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
+    let started = Instant::now();
     let colors = &config.colors;
     let behavior = &config.behavior;
     let theme = &config.theme;
@@ -47,12 +79,13 @@ pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
         .style(Style::default().bg(bg_color));
     f.render_widget(inner_bg, inner_area);
 
-    // Create layout: chat area (top) and input area (bottom)
+    // Create layout: chat area (top), input area and status bar (bottom)
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(3),    // Chat messages
             Constraint::Length(3), // Input box
+            Constraint::Length(1), // Status bar
         ])
         .split(inner_area);
 
@@ -70,55 +103,151 @@ pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
         }
     }
 
-    // Render chat messages (skip based on scroll offset)
+    // Optional minimap column to the right of the chat
+    let (chat_area, minimap_area) = if app.scroll.minimap {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(10), Constraint::Length(MINIMAP_WIDTH)])
+            .split(chunks[0]);
+        (columns[0], Some(columns[1]))
+    } else {
+        (chunks[0], None)
+    };
+
+    let layout_done = Instant::now();
+    app.frames.last.layout = layout_done - started;
+
+    // Fold markers only make sense once the conversation has moved on
+    let sections = app.sections();
+    let show_sections = sections.len() > 1;
+
+    // Render chat messages (skip based on scroll offset), laying out only
+    // as many as can be seen
+    let height = chat_area.height.saturating_sub(2) as usize;
+    let mut used = 0;
+    let mut visible_end = app.scroll.offset;
+    let selected = app.selection.as_ref().map(|s| s.selected);
     let messages: Vec<ListItem> = app
         .chat
         .messages
         .iter()
         .enumerate()
         .skip(app.scroll.offset)
-        .flat_map(|(_msg_idx, msg)| {
+        .map(|(msg_idx, msg)| {
             let is_banner = msg.is_system_banner();
 
+            let mut items = Vec::new();
+            if show_sections {
+                if let Some(section) = sections.iter().find(|s| s.start == msg_idx) {
+                    let collapsed = app.sections.is_collapsed(section.start);
+                    items.push(ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("{} {}", if collapsed { "▸" } else { "▾" }, section.title),
+                            Style::default()
+                                .fg(Color::Magenta)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            format!("  ({} messages)", section.len()),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ])));
+                    if collapsed {
+                        items.push(ListItem::new(Line::from("")));
+                    }
+                }
+                if app.sections.is_hidden(&sections, msg_idx) {
+                    return items;
+                }
+            }
+
+            // Where the unread messages start in a resumed session
+            if app.chat.first_unread == Some(msg_idx) {
+                items.push(ListItem::new(Line::from(Span::styled(
+                    "── unread ──",
+                    Style::default().fg(Color::Yellow),
+                ))));
+            }
+
+            if let Some(card) = &msg.error {
+                let is_last = msg_idx + 1 == app.chat.messages.len();
+                items.extend(
+                    error_card_lines(card, is_last, chat_area.width.saturating_sub(4) as usize)
+                        .into_iter()
+                        .map(ListItem::new),
+                );
+                items.push(ListItem::new(Line::from("")));
+                return items;
+            }
+
             // Apply Miami gradient to banner, regular colors to other messages
-            let message_content = if is_banner && !app.animation.banner_complete {
+            let message_content: &str = if is_banner && !app.animation.banner_complete {
                 // Animated reveal: only show characters up to current frame
-                msg.content
-                    .chars()
-                    .take(app.animation.banner_frame)
-                    .collect::<String>()
+                let end = msg
+                    .content
+                    .char_indices()
+                    .nth(app.animation.banner_frame)
+                    .map_or(msg.content.len(), |(i, _)| i);
+                &msg.content[..end]
             } else {
-                msg.content.clone()
+                &msg.content
             };
 
-            let role_prefix = msg.role.prefix();
-
-            // Wrap long messages
-            let wrapped_lines =
-                wrap_text(&message_content, chunks[0].width.saturating_sub(4) as usize);
-
-            let mut items = Vec::new();
-            for (i, line) in wrapped_lines.iter().enumerate() {
-                if is_banner {
-                    // Apply Miami gradient to banner (no role prefix)
-                    let miami_line = apply_miami_gradient_to_line(line, i, &miami);
-                    items.push(ListItem::new(miami_line));
-                } else {
-                    // Regular message styling
-                    let style = match msg.role {
-                        Role::User => Style::default().fg(Color::Cyan),
-                        Role::Assistant => Style::default().fg(Color::Green),
+            let width = chat_area.width.saturating_sub(4) as usize;
+            if msg.is_chat() {
+                items.extend(
+                    thinking_lines(msg, app.chat.show_thinking, width)
+                        .into_iter()
+                        .map(ListItem::new),
+                );
+                items.extend(
+                    message_lines(
+                        theme,
+                        msg.role,
+                        msg.author.as_deref(),
+                        message_content,
+                        width,
+                        app.chat.is_raw(msg_idx),
+                    )
+                        .into_iter()
+                        .map(ListItem::new),
+                );
+                items.extend(image_lines(&msg.images).into_iter().map(ListItem::new));
+                if let Some(line) = meta_line(msg) {
+                    items.push(ListItem::new(line));
+                }
+                if let Some(meta) = msg.meta.as_ref().filter(|_| app.chat.show_info) {
+                    items.push(ListItem::new(info_line(meta)));
+                }
+                if let Some(line) = msg.finish.as_ref().and_then(finish_line) {
+                    items.push(ListItem::new(line));
+                }
+                if msg.queued {
+                    let note = match app.llm.rate_limits.held_until {
+                        Some(until) => format!(
+                            "⏸ Rate limit nearly used up — sending in {}s",
+                            until.saturating_duration_since(Instant::now()).as_secs_f64().ceil()
+                        ),
+                        None => "⏸ Offline — will send when the connection returns".to_string(),
                     };
-
-                    if i == 0 {
-                        items.push(ListItem::new(Line::from(vec![
-                            Span::styled(role_prefix, style.add_modifier(Modifier::BOLD)),
-                            Span::styled(line.clone(), style),
-                        ])));
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        note,
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC),
+                    ))));
+                }
+            } else {
+                for (i, line) in wrap_text(message_content, width).iter().enumerate() {
+                    if is_banner {
+                        // Apply Miami gradient to banner (no role prefix)
+                        let miami_line = apply_miami_gradient_to_line(line, i, &miami);
+                        items.push(ListItem::new(miami_line));
                     } else {
+                        // Notes about the conversation: dim, no role prefix
                         items.push(ListItem::new(Line::from(Span::styled(
-                            format!("         {}", line),
-                            style,
+                            format!("{} {}", if i == 0 { "•" } else { " " }, line),
+                            Style::default()
+                                .fg(Color::DarkGray)
+                                .add_modifier(Modifier::ITALIC),
                         ))));
                     }
                 }
@@ -130,17 +259,160 @@ pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
             }
             items
         })
+        .enumerate()
+        .map(|(i, items)| {
+            // Highlight the selected message in selection mode
+            if selected != Some(app.scroll.offset + i) {
+                return items;
+            }
+            items
+                .into_iter()
+                .map(|item| item.style(Style::default().bg(SELECTION_BG)))
+                .collect()
+        })
+        // Messages that at least partly fit in the chat area
+        .take_while(|items| {
+            let fits = used < height;
+            used += items.len();
+            fits
+        })
+        .inspect(|_| visible_end += 1)
+        .flatten()
         .collect();
+    app.chat.mark_read(visible_end);
+    app.scroll.visible_end = visible_end;
+    app.frames.last.wrap = layout_done.elapsed();
 
     // Purple to Blue gradient for chat area
     let mid_color = gradient_color(chat_start, chat_end, 0.5);
-    let messages_list = List::new(messages).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(mid_color))
-    );
+    let mut messages_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(mid_color));
+    if app.playground.is_some() {
+        messages_block = messages_block.title(Span::styled(
+            " ⚗ Playground · not saved · Ctrl+K keeps it ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if app.llm.dry_run {
+        messages_block = messages_block.title(Span::styled(
+            " ◌ Dry run · nothing is sent ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some(batch) = &app.batch {
+        messages_block = messages_block.title(Span::styled(
+            format!(" ⧗ Batch {}/{} · /batch stop ", batch.done(), batch.total),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let share_badge = match (&app.share, &app.guest) {
+        (Some(host), _) => Some(format!(
+            " ⇄ Sharing ({}) · {} guest(s) ",
+            host.mode.label(),
+            host.guests.len()
+        )),
+        (_, Some(guest)) if guest.closed => Some(format!(" ⇄ {}'s session ended ", guest.host)),
+        (_, Some(guest)) => Some(format!(" ⇄ {}'s session ({}) ", guest.host, guest.mode.label())),
+        _ => None,
+    };
+    if let Some(badge) = share_badge {
+        messages_block = messages_block.title(Span::styled(
+            badge,
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if app.llm.is_warming_up() {
+        messages_block = messages_block.title(Span::styled(
+            format!(" ⟳ Loading {}… ", app.llm.config.model),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some((done, total)) = app.indexer.as_ref().and_then(|i| i.progress) {
+        messages_block = messages_block.title(Span::styled(
+            format!(" ⟳ Indexing {}/{} ", done, total),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(effort) = app.llm.config.reasoning {
+        messages_block = messages_block.title(
+            Title::from(Span::styled(
+                format!(" ✦ think: {} ", effort.label()),
+                Style::default().fg(Color::Magenta),
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
+    // Explain unavailable commands right above the input while typing
+    if let Some(hint) = app.input_hint() {
+        messages_block = messages_block.title_bottom(Span::styled(
+            format!(" ⓘ {} ", hint),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    // Token count of the saved conversation, from the session indexer
+    if let Some(indexed) = app
+        .indexer
+        .as_ref()
+        .and_then(|i| i.last.as_ref())
+        .filter(|last| app.session.as_ref().is_some_and(|s| s.id == last.id))
+    {
+        messages_block = messages_block.title(
+            Title::from(Span::styled(
+                format!(" ≈{} tokens ", indexed.tokens),
+                Style::default().fg(Color::DarkGray),
+            ))
+            .position(Position::Bottom)
+            .alignment(Alignment::Right),
+        );
+    }
+    let messages_list = List::new(messages).block(messages_block);
+
+    f.render_widget(messages_list, chat_area);
+
+    // Search matches, then the mouse selection over them (read back once
+    // released)
+    let text_area = chat_area.inner(&Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    if let Some(search) = &app.search {
+        search.highlight(f.buffer_mut(), text_area);
+    }
+    app.mouse.chat_area = text_area;
+    if let Some(drag) = &mut app.mouse.drag {
+        if drag.copy {
+            drag.copy = false;
+            app.mouse.copied = Some(drag.text(f.buffer_mut(), text_area));
+        }
+        drag.highlight(f.buffer_mut(), text_area);
+    }
 
-    f.render_widget(messages_list, chunks[0]);
+    if let Some(area) = minimap_area {
+        let area = area.inner(&Margin {
+            vertical: 1,
+            horizontal: 0,
+        });
+        let rows = minimap_rows(
+            &app.chat.messages,
+            &sections,
+            app.scroll.offset..visible_end,
+            area.height as usize,
+        );
+        render_minimap(f, area, &rows);
+    }
 
     // Render scrollbar with smooth Unicode characters and gradient
     let scroll_position = if total_messages > 0 {
@@ -158,7 +430,7 @@ pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
 
     f.render_stateful_widget(
         scrollbar,
-        chunks[0].inner(&Margin {
+        chat_area.inner(&Margin {
             vertical: 1,
             horizontal: 0,
         }),
@@ -168,48 +440,157 @@ pub fn ui(f: &mut Frame, app: &mut App, config: &Config) {
     // Render input box with left border only, dark grey background, blinking cursor
     let cursor_char = if app.animation.cursor_visible { "▎" } else { " " };
     
-    let input_text = if app.chat.cursor_position < app.chat.input.len() {
-        Line::from(vec![
-            Span::raw(&app.chat.input[..app.chat.cursor_position]),
-            Span::styled(cursor_char, Style::default().fg(Color::Cyan).add_modifier(Modifier::SLOW_BLINK)),
-            Span::raw(&app.chat.input[app.chat.cursor_position..]),
-        ])
-    } else {
-        Line::from(vec![
-            Span::raw(&app.chat.input),
-            Span::styled(cursor_char, Style::default().fg(Color::Cyan).add_modifier(Modifier::SLOW_BLINK)),
-        ])
-    };
+    let cursor = Span::styled(cursor_char, Style::default().fg(Color::Cyan).add_modifier(Modifier::SLOW_BLINK));
+    let (spans, cursor_index) = input_spans(&app.chat.input, app.chat.cursor_position, cursor);
 
     // Dark grey background, left border only with gradient color
-    let input_block = Block::default()
+    let mut input_block = Block::default()
         .borders(Borders::LEFT)
         .border_style(Style::default().fg(gradient_color(input_start, input_end, 0.5)))
         .style(Style::default().bg(theme.bg_secondary()));
+    if !app.chat.images.is_empty() {
+        let names: Vec<&str> = app.chat.images.iter().map(|i| i.name.as_str()).collect();
+        input_block = input_block.title(Span::styled(
+            format!(" {} {} · /attach clear ", IMAGE_ICON, names.join(", ")),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
 
-    let input = Paragraph::new(input_text)
-        .style(Style::default().fg(Color::White))
-        .block(input_block)
-        .wrap(Wrap { trim: false });
+    // Reflow the draft to the box and scroll to the cursor's row
+    let input_inner = input_block.inner(chunks[1]);
+    let (rows, cursor_row) = wrap_spans(&spans, input_inner.width as usize, cursor_index);
+    let height = input_inner.height as usize;
+    app.input_view.follow(cursor_row, rows.len(), height);
+    let visible: Vec<Line> = rows
+        .into_iter()
+        .skip(app.input_view.scroll)
+        .take(height)
+        .collect();
+
+    let input = Paragraph::new(visible)
+        .style(Style::default().fg(theme.fg_primary()))
+        .block(input_block);
 
     f.render_widget(input, chunks[1]);
+    let context = app.context_usage();
+    match &app.search {
+        Some(search) => render_search_bar(f, chunks[2], search),
+        None => render_status_bar(f, chunks[2], app, context, theme),
+    }
+
+    // Slash command or emoji shortcode completions just above the input
+    let commands = app.chat.command_completions();
+    let completions = app.chat.shortcode_completions();
+    if !commands.is_empty() {
+        let items: Vec<String> = commands
+            .iter()
+            .map(|spec| format!("{:<10} {}", spec.name, spec.description))
+            .collect();
+        render_completion_popup(f, chunks[1], &items, 48);
+    } else if !completions.is_empty() {
+        let items: Vec<String> = completions
+            .iter()
+            .map(|(code, emoji)| format!("{} :{}:", emoji, code))
+            .collect();
+        render_completion_popup(f, chunks[1], &items, 28);
+    }
 
     // Render menu overlay if visible
     if app.menu.visible {
         render_menu(f, app, &miami, config);
     }
 
-    // Render toast notifications (above main content, but below dialogs)
-    render_toasts(f, &app.toasts);
+    // Render local models panel
+    if let Some(panel) = &app.local_models {
+        render_local_models(f, panel);
+    }
+
+    // Render jump-to-section picker
+    if let Some(picker) = &app.section_picker {
+        render_section_picker(f, picker, &sections, &app.sections);
+    }
+
+    // Render saved session browser
+    if let Some(picker) = &app.session_picker {
+        render_session_picker(f, picker);
+    }
+
+    // Render search across sessions
+    if let Some(search) = &app.global_search {
+        render_global_search(f, search);
+    }
+
+    // Render profile picker
+    if let Some(picker) = &app.profile_picker {
+        render_profile_picker(f, picker);
+    }
+
+    // Render workspace file tree
+    if let Some(tree) = &app.file_tree {
+        render_file_tree(f, tree);
+    }
+
+    // Render attachment manager
+    if let Some(panel) = &app.attachments {
+        render_attachments(f, panel);
+    }
+
+    // Render variable expansion preview
+    if let Some(expansion) = &app.submit_guard.expansion {
+        render_expansion_preview(f, expansion);
+    }
+
+    // Render pasted file paths prompt
+    if let Some(pasted) = &app.pasted_files {
+        render_paste_confirmation(f, pasted);
+    }
+
+    // Render large prompt confirmation
+    if let Some(pending) = &app.submit_guard.pending {
+        render_send_confirmation(f, app, pending);
+    }
+
+    // Render spending cap confirmation
+    if let Some(check) = &app.submit_guard.spend {
+        render_spend_confirmation(f, check, app.submit_guard.held.is_some());
+    }
+
+    // Render resumed session model prompt
+    if let Some(mismatch) = &app.model_mismatch {
+        render_model_mismatch_dialog(f, app, mismatch);
+    }
+
+    // Render tool permission prompt
+    if let Some(confirmation) = &app.tool_confirmation {
+        render_tool_confirmation(f, confirmation);
+    }
+
+    // Render message viewer over the whole screen
+    if let Some(viewer) = &app.viewer {
+        render_message_viewer(f, viewer);
+    }
+
+    // Render toast notifications around the input, the status bar and any
+    // dialog (the largest dialog's area, whichever is open)
+    let mut exclusions = vec![chunks[1], chunks[2]];
+    if app.overlay_open() {
+        exclusions.push(centered_rect(80, 70, f.size()));
+    }
+    render_toasts(f, &app.toasts, &exclusions);
 
     // Render connection dialog if active (on top of everything)
     if app.connect.is_active() {
         render_connect_dialog(f, app);
     }
+
+    if app.show_profile {
+        let stream = app.llm.client.as_ref().map(|client| client.stream_stats());
+        render_profile_overlay(f, f.size(), &app.frames, stream);
+    }
 }
 
 /// Calculate a centered rectangle within an area.
-fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+pub(super) fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let width = (area.width as u32 * percent_x as u32 / 100) as u16;
     let height = (area.height as u32 * percent_y as u32 / 100) as u16;
     let x = area.x + (area.width.saturating_sub(width)) / 2;
@@ -225,6 +606,7 @@ pub fn render_connect_dialog(f: &mut Frame, app: &App) {
             provider,
             masked_key,
             current_model,
+            scope,
             selected,
         } => {
             render_existing_credential_dialog(
@@ -232,6 +614,7 @@ pub fn render_connect_dialog(f: &mut Frame, app: &App) {
                 *provider,
                 masked_key,
                 current_model.as_deref(),
+                scope.as_deref(),
                 *selected,
             );
         }
@@ -277,9 +660,48 @@ pub fn render_connect_dialog(f: &mut Frame, app: &App) {
         ConnectState::SelectingModel {
             provider,
             selected,
+            models,
+            ..
+        } => {
+            render_model_selection_dialog(f, *provider, models.as_deref(), *selected);
+        }
+        ConnectState::EnteringServerUrl {
+            input,
+            cursor,
+            error,
+        } => {
+            render_server_url_dialog(f, input, *cursor, error.as_deref());
+        }
+        ConnectState::FetchingServerModels { api_base } => {
+            render_fetching_models_dialog(f, &format!("{}/models", api_base));
+        }
+        ConnectState::FetchingOllamaModels { api_base } => {
+            render_fetching_models_dialog(f, &format!("{}/api/tags", api_base));
+        }
+        ConnectState::SelectingOllamaModel {
+            models, selected, ..
+        } => {
+            render_ollama_model_dialog(f, models, *selected);
+        }
+        ConnectState::OfferingOllamaPull {
+            model,
+            installed,
+            selected,
             ..
         } => {
-            render_model_selection_dialog(f, *provider, *selected);
+            render_ollama_pull_offer_dialog(f, model, !installed.is_empty(), *selected);
+        }
+        ConnectState::PullingOllamaModel {
+            model, progress, ..
+        } => {
+            render_ollama_pull(f, model, progress);
+        }
+        ConnectState::SelectingServerModel {
+            api_base,
+            models,
+            selected,
+        } => {
+            render_server_model_dialog(f, api_base, models, *selected);
         }
     }
 }
@@ -290,6 +712,7 @@ fn render_existing_credential_dialog(
     provider: Provider,
     masked_key: &str,
     current_model: Option<&str>,
+    scope: Option<&str>,
     selected: usize,
 ) {
     let area = centered_rect(50, 40, f.size());
@@ -307,10 +730,10 @@ fn render_existing_credential_dialog(
     // Determine if we should show "Change model" option
     let has_saved_model = current_model.is_some();
 
-    // Layout: info lines (key + optional model), spacer, options, hints
-    let info_lines = if has_saved_model { 3 } else { 2 };
+    // Layout: info lines (key + optional model and scope), spacer, options, hints
+    let info_lines = 2 + u16::from(has_saved_model) + u16::from(scope.is_some());
     let chunks = Layout::vertical([
-        Constraint::Length(info_lines), // Current key info + optional model
+        Constraint::Length(info_lines), // Current key info + optional model and scope
         Constraint::Length(1),           // Spacer
         Constraint::Min(3),              // Options
         Constraint::Length(1),           // Hints
@@ -320,7 +743,10 @@ fn render_existing_credential_dialog(
     // Current key and model info
     let mut info_text = format!("Current key: {}\n", masked_key);
     if let Some(model) = current_model {
-        info_text.push_str(&format!("Current model: {}", model));
+        info_text.push_str(&format!("Current model: {}\n", model));
+    }
+    if let Some(scope) = scope {
+        info_text.push_str(&format!("{}: {}", provider.scope_label(), scope));
     }
     let info = Paragraph::new(info_text)
         .style(Style::default().fg(Color::Gray));
@@ -371,108 +797,640 @@ fn render_existing_credential_dialog(
     f.render_widget(hints_widget, chunks[3]);
 }
 
-/// Render the "selecting method" dialog.
-fn render_selecting_method_dialog(f: &mut Frame, provider_name: &str, selected: usize) {
-    let area = centered_rect(50, 40, f.size());
+/// A dim note under a reply naming the model `auto` or `/retry` picked and
+/// the reasoning effort it was asked for, or None if neither applies.
+fn meta_line(msg: &Message) -> Option<Line<'static>> {
+    let dim = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::ITALIC);
+    let mut spans = Vec::new();
+    if let Some(route) = &msg.route {
+        let model = Span::styled(route.model.clone(), Style::default().fg(Color::Cyan));
+        if route.tier == Tier::Chosen {
+            spans.push(Span::styled("↳ retried on ", dim));
+            spans.push(model);
+        } else {
+            spans.push(Span::styled("↳ auto → ", dim));
+            spans.push(model);
+            spans.push(Span::styled(format!(" ({})", route.reason), dim));
+        }
+    }
+    if let Some(effort) = msg.reasoning {
+        let sep = if spans.is_empty() { "↳ " } else { " · " };
+        spans.push(Span::styled(format!("{}thinking: {}", sep, effort.label()), dim));
+    }
+    if msg.pinned {
+        let sep = if spans.is_empty() { "" } else { " · " };
+        spans.push(Span::styled(format!("{}📌 pinned", sep), dim));
+    }
+    (!spans.is_empty()).then(|| Line::from(spans))
+}
+
+/// The details of a reply: model, time to the first token, total time and
+/// the tokens the provider reported.
+fn info_line(meta: &ReplyMeta) -> Line<'static> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut parts = Vec::new();
+    if let Some(first) = meta.first_token {
+        parts.push(format!("first token {:.1}s", first.as_secs_f64()));
+    }
+    if let Some(total) = meta.duration {
+        parts.push(format!("total {:.1}s", total.as_secs_f64()));
+    }
+    parts.push(match meta.usage {
+        Some(usage) => format!(
+            "{} in · {} out tokens",
+            format_tokens(usage.prompt_tokens),
+            format_tokens(usage.completion_tokens)
+        ),
+        None => "tokens not reported".to_string(),
+    });
+    if let Some(cost) = meta.cost {
+        parts.push(format_cost(cost));
+    }
+    Line::from(vec![
+        Span::styled("ⓘ ", dim),
+        Span::styled(meta.model.clone(), Style::default().fg(Color::Cyan)),
+        Span::styled(format!(" · {}", parts.join(" · ")), dim),
+    ])
+}
+
+/// The reasoning streamed before a reply as a dimmed, collapsible block:
+/// a header, then the reasoning set off with a bar if `show` is set. The
+/// header says "Thinking…" until the answer starts, so a long think doesn't
+/// look like a stall.
+fn thinking_lines(msg: &Message, show: bool, width: usize) -> Vec<Line<'static>> {
+    if msg.thinking.is_empty() {
+        return Vec::new();
+    }
+    let dim = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::ITALIC);
+    let status = if msg.content.is_empty() {
+        "Thinking…".to_string()
+    } else {
+        format!("Thought ({} words)", msg.thinking.split_whitespace().count())
+    };
+    let (marker, hint) = if show { ("▾", "hide") } else { ("▸", "show") };
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!("{} 💭 {}", marker, status), dim),
+        Span::styled(format!(" · Ctrl+Y to {}", hint), Style::default().fg(Color::DarkGray)),
+    ])];
+    if show {
+        lines.extend(
+            wrap_text(&msg.thinking, width.saturating_sub(2))
+                .into_iter()
+                .map(|text| Line::from(vec![Span::styled("│ ", dim), Span::styled(text, dim)])),
+        );
+    }
+    lines
+}
+
+/// Stands in for an image in the chat.
+const IMAGE_ICON: &str = "▧";
+
+/// A placeholder thumbnail for each image sent with a prompt: a small frame
+/// with the file name, format and size beside it.
+fn image_lines(images: &[Image]) -> Vec<Line<'static>> {
+    let frame = Style::default().fg(Color::DarkGray);
+    images
+        .iter()
+        .flat_map(|image| {
+            [
+                Line::from(Span::styled("╭─────╮", frame)),
+                Line::from(vec![
+                    Span::styled(format!("│ {} │ ", IMAGE_ICON.repeat(3)), frame),
+                    Span::styled(image.name.clone(), Style::default().fg(Color::Cyan)),
+                ]),
+                Line::from(vec![
+                    Span::styled("╰─────╯ ", frame),
+                    Span::styled(image.describe(), frame),
+                ]),
+            ]
+        })
+        .collect()
+}
+
+/// A dim note under a reply that stopped early, or None if it finished normally.
+fn finish_line(reason: &FinishReason) -> Option<Line<'static>> {
+    let (note, action) = match reason {
+        FinishReason::Length => ("⋯ Cut off at the token limit", " to extend"),
+        FinishReason::ContentFilter => ("⊘ Stopped by the provider's content filter", " to extend"),
+        FinishReason::Interrupted => ("⚡ Stream interrupted", " to resume"),
+        _ => return None,
+    };
+    let dim = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::ITALIC);
+    Some(Line::from(vec![
+        Span::styled(note, dim),
+        Span::styled(" · ", dim),
+        Span::styled("/continue", Style::default().fg(Color::Yellow)),
+        Span::styled(action, dim),
+    ]))
+}
+
+/// Lines of an error card: class and provider, the message, and the retry hint.
+fn error_card_lines(card: &ErrorCard, retryable: bool, width: usize) -> Vec<Line<'static>> {
+    let border = Style::default().fg(Color::Red);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("╭ ✖ ", border),
+        Span::styled(
+            card.error.title(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!(" · {}", card.provider), Style::default().fg(Color::Gray)),
+    ])];
+    for line in wrap_text(card.error.message(), width.saturating_sub(2)) {
+        lines.push(Line::from(vec![
+            Span::styled("│ ", border),
+            Span::styled(line, Style::default().fg(Color::White)),
+        ]));
+    }
+    let footer = if retryable {
+        Line::from(vec![
+            Span::styled("╰ ", border),
+            Span::styled("[Ctrl+R]", Style::default().fg(Color::Yellow)),
+            Span::styled(" Retry", Style::default().fg(Color::Gray)),
+        ])
+    } else {
+        Line::from(Span::styled("╰", border))
+    };
+    lines.push(footer);
+    lines
+}
+
+/// Parts of the input that submitting interprets, with their styles: the
+/// slash command (whose arguments are taken as is), or else `${...}`
+/// variables. `@path` chips are marked either way.
+fn input_highlights(input: &str) -> Vec<(Range<usize>, Style)> {
+    let mut highlights = Vec::new();
+    if let Some(range) = commands::command_range(input) {
+        let style = Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD);
+        highlights.push((range, style));
+    } else {
+        let style = Style::default().fg(Color::Yellow);
+        highlights.extend(placeholder_ranges(input).into_iter().map(|r| (r, style)));
+    }
+    let chip_style = Style::default()
+        .fg(Color::Black)
+        .bg(Color::Cyan);
+    highlights.extend(chip_ranges(input).into_iter().map(|r| (r, chip_style)));
+    highlights
+}
+
+/// Split the input into spans, highlighting what submitting interprets and
+/// placing the cursor. Returns the spans and the index of the cursor span.
+fn input_spans<'a>(input: &'a str, cursor_pos: usize, cursor: Span<'a>) -> (Vec<Span<'a>>, usize) {
+    // Segment boundaries: highlight edges plus the cursor
+    let highlights = input_highlights(input);
+    let mut cuts = vec![0, cursor_pos, input.len()];
+    cuts.extend(highlights.iter().flat_map(|(r, _)| [r.start, r.end]));
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut spans = Vec::new();
+    let mut cursor_index = 0;
+    for pair in cuts.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        if start == cursor_pos {
+            cursor_index = spans.len();
+            spans.push(cursor.clone());
+        }
+        let text = &input[start..end];
+        // Variables come first, so one inside a chip keeps its own color
+        match highlights
+            .iter()
+            .find(|(r, _)| r.start <= start && end <= r.end)
+        {
+            Some((_, style)) => spans.push(Span::styled(text, *style)),
+            None => spans.push(Span::raw(text)),
+        }
+    }
+    if cursor_pos == input.len() {
+        cursor_index = spans.len();
+        spans.push(cursor);
+    }
+    (spans, cursor_index)
+}
+
+/// Render completions above the input box; the first one is what Tab inserts.
+fn render_completion_popup(f: &mut Frame, input_area: Rect, completions: &[String], width: u16) {
+    let height = (completions.len() as u16 + 2).min(input_area.y);
+    let width = width.min(input_area.width);
+    let area = Rect::new(input_area.x, input_area.y - height, width, height);
+    f.render_widget(Clear, area);
+
+    let lines: Vec<Line> = completions
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let style = if i == 0 {
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Line::from(Span::styled(item.as_str(), style))
+        })
+        .collect();
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .title_bottom(Span::styled(" Tab ", Style::default().fg(Color::DarkGray)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().bg(Color::Black)),
+    );
+    f.render_widget(popup, area);
+}
+
+/// Render the confirmation shown before sending an oversized prompt.
+fn render_send_confirmation(f: &mut Frame, app: &App, pending: &SendConfirmation) {
+    let area = centered_rect(50, 30, f.size());
     f.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(format!(" Connect to {} ", provider_name))
+        .title(" Send Large Prompt? ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan))
+        .border_style(Style::default().fg(Color::Yellow))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    // Layout: options, hints
     let chunks = Layout::vertical([
-        Constraint::Min(3),    // Options
+        Constraint::Min(3),    // Summary
         Constraint::Length(1), // Hints
     ])
     .split(inner);
 
-    // Options
-    let options = vec![
-        "Enter API Key manually",
-        "Create API Key (opens browser)",
-        "Cancel",
-    ];
-    let lines: Vec<Line> = options
-        .iter()
-        .enumerate()
-        .map(|(i, opt)| {
-            let style = if i == selected {
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(Color::White)
-            };
-            let prefix = if i == selected { "> " } else { "  " };
-            Line::from(Span::styled(format!("{}{}", prefix, opt), style))
-        })
-        .collect();
-    let options_widget = Paragraph::new(lines);
-    f.render_widget(options_widget, chunks[0]);
+    let cost = if matches!(app.llm.config.provider, Provider::Ollama | Provider::LocalServer) {
+        "free (runs locally)".to_string()
+    } else {
+        format!("billed by {} at its per-token rate", app.llm.config.provider.display_name())
+    };
+    let summary = Paragraph::new(vec![
+        Line::from(format!(
+            "This request sends {:.1} KB (~{} tokens) to {}.",
+            pending.bytes as f64 / 1024.0,
+            pending.tokens,
+            app.llm.config.model
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Cost: {}", cost),
+            Style::default().fg(Color::Gray),
+        )),
+    ])
+    .style(Style::default().fg(Color::White))
+    .wrap(Wrap { trim: true });
+    f.render_widget(summary, chunks[0]);
 
-    // Hints
     let hints = Line::from(vec![
-        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
-        Span::raw(" Navigate  "),
         Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
-        Span::raw(" Select  "),
+        Span::raw(" Send  "),
         Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
-        Span::raw(" Cancel"),
+        Span::raw(" Keep editing"),
     ]);
-    let hints_widget = Paragraph::new(hints).style(Style::default().fg(Color::Gray));
-    f.render_widget(hints_widget, chunks[1]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[1],
+    );
 }
 
-/// Render the "entering API key" dialog.
-fn render_entering_api_key_dialog(
-    f: &mut Frame,
-    provider_name: &str,
-    input: &str,
-    cursor: usize,
-    error: Option<&str>,
-) {
-    let height_percent = if error.is_some() { 45 } else { 35 };
-    let area = centered_rect(60, height_percent, f.size());
+/// Render the confirmation shown before sending past a spending cap.
+fn render_spend_confirmation(f: &mut Frame, check: &SpendCheck, held: bool) {
+    let area = centered_rect(50, 30, f.size());
     f.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(format!(" Enter {} API Key ", provider_name))
+        .title(" Spending Cap Reached ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan))
+        .border_style(Style::default().fg(Color::Red))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    // Layout with optional error
-    let constraints = if error.is_some() {
-        vec![
-            Constraint::Length(1), // Input label
-            Constraint::Length(1), // Input field
-            Constraint::Length(1), // Spacer
-            Constraint::Length(1), // Hints
-            Constraint::Length(1), // Spacer
-            Constraint::Min(1),    // Error
-        ]
-    } else {
-        vec![
-            Constraint::Length(1), // Input label
-            Constraint::Length(1), // Input field
-            Constraint::Length(1), // Spacer
-            Constraint::Length(1), // Hints
-            Constraint::Min(0),    // Filler
-        ]
-    };
-    let chunks = Layout::vertical(constraints).split(inner);
-
-    // Input label
+    let chunks = Layout::vertical([
+        Constraint::Min(3),    // Summary
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let summary = Paragraph::new(vec![
+        Line::from(format!("{}.", check.message())),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "Sending anyway stops asking about the {} cap until scry restarts.",
+                check.period
+            ),
+            Style::default().fg(Color::Gray),
+        )),
+    ])
+    .style(Style::default().fg(Color::White))
+    .wrap(Wrap { trim: true });
+    f.render_widget(summary, chunks[0]);
+
+    let hints = Line::from(vec![
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Send anyway  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        // A held retry or batch prompt has no draft to go back to
+        Span::raw(if held { " Don't send" } else { " Keep editing" }),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[1],
+    );
+}
+
+/// Render the prompt asking whether pasted file paths are attachments.
+fn render_paste_confirmation(f: &mut Frame, pasted: &PastedFiles) {
+    let area = centered_rect(60, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let title = if pasted.paths.len() == 1 {
+        " Attach Pasted File? ".to_string()
+    } else {
+        format!(" Attach {} Pasted Files? ", pasted.paths.len())
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(2), // Explanation
+        Constraint::Min(1),    // Files
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    f.render_widget(
+        Paragraph::new("The paste names files that exist. Attach them instead of pasting the path?")
+            .style(Style::default().fg(Color::White))
+            .wrap(Wrap { trim: true }),
+        chunks[0],
+    );
+
+    let files: Vec<Line> = pasted
+        .paths
+        .iter()
+        .map(|path| {
+            let size = std::fs::metadata(path).map_or(0, |m| m.len());
+            Line::from(vec![
+                Span::styled(format!("  {}", path.display()), Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("  {}", format_size(size)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(files), chunks[1]);
+
+    let hints = Line::from(vec![
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Attach  "),
+        Span::styled("[t]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Paste as text  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[2],
+    );
+}
+
+/// Render the preview of a prompt whose variables were expanded.
+fn render_expansion_preview(f: &mut Frame, expansion: &Expansion) {
+    let area = centered_rect(60, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Send With Variables? ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(expansion.values.len() as u16 + 1), // Expanded values
+        Constraint::Min(1),                                    // Prompt preview
+        Constraint::Length(1),                                 // Hints
+    ])
+    .split(inner);
+
+    let values: Vec<Line> = expansion
+        .values
+        .iter()
+        .map(|v| {
+            Line::from(vec![
+                Span::styled(v.placeholder.clone(), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("  → {}", v.summary), Style::default().fg(Color::Gray)),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(values), chunks[0]);
+
+    let preview = Paragraph::new(expansion.text.as_str())
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false });
+    f.render_widget(preview, chunks[1]);
+
+    let hints = Line::from(vec![
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Send  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Keep editing"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[2],
+    );
+}
+
+/// Render the prompt shown when a resumed session used a different model.
+fn render_model_mismatch_dialog(f: &mut Frame, app: &App, mismatch: &ModelMismatch) {
+    let area = centered_rect(50, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Session Model Changed ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(3), // Explanation
+        Constraint::Length(1), // Spacer
+        Constraint::Min(2),    // Options
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let info = Paragraph::new(format!(
+        "This session was started with {} ({}).\nYou are now using {} ({}).",
+        mismatch.session_model,
+        mismatch.session_provider.display_name(),
+        app.llm.config.model,
+        app.llm.config.provider.display_name()
+    ))
+    .style(Style::default().fg(Color::Gray))
+    .wrap(Wrap { trim: true });
+    f.render_widget(info, chunks[0]);
+
+    let options = [
+        format!("Switch back to {}", mismatch.session_model),
+        format!("Continue with {}", app.llm.config.model),
+    ];
+    let lines: Vec<Line> = options
+        .iter()
+        .enumerate()
+        .map(|(i, opt)| {
+            let style = if i == mismatch.selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let prefix = if i == mismatch.selected { "> " } else { "  " };
+            Line::from(Span::styled(format!("{}{}", prefix, opt), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[2]);
+
+    let hints = Line::from(vec![
+        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Continue"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[3],
+    );
+}
+
+/// Render the "selecting method" dialog.
+fn render_selecting_method_dialog(f: &mut Frame, provider_name: &str, selected: usize) {
+    let area = centered_rect(50, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Connect to {} ", provider_name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Layout: options, hints
+    let chunks = Layout::vertical([
+        Constraint::Min(3),    // Options
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    // Options
+    let options = [
+        "Enter API Key manually",
+        "Create API Key (opens browser)",
+        "Cancel",
+    ];
+    let lines: Vec<Line> = options
+        .iter()
+        .enumerate()
+        .map(|(i, opt)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let prefix = if i == selected { "> " } else { "  " };
+            Line::from(Span::styled(format!("{}{}", prefix, opt), style))
+        })
+        .collect();
+    let options_widget = Paragraph::new(lines);
+    f.render_widget(options_widget, chunks[0]);
+
+    // Hints
+    let hints = Line::from(vec![
+        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    let hints_widget = Paragraph::new(hints).style(Style::default().fg(Color::Gray));
+    f.render_widget(hints_widget, chunks[1]);
+}
+
+/// Render the "entering API key" dialog.
+fn render_entering_api_key_dialog(
+    f: &mut Frame,
+    provider_name: &str,
+    input: &str,
+    cursor: usize,
+    error: Option<&str>,
+) {
+    let height_percent = if error.is_some() { 45 } else { 35 };
+    let area = centered_rect(60, height_percent, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Enter {} API Key ", provider_name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Layout with optional error
+    let constraints = if error.is_some() {
+        vec![
+            Constraint::Length(1), // Input label
+            Constraint::Length(1), // Input field
+            Constraint::Length(1), // Spacer
+            Constraint::Length(1), // Hints
+            Constraint::Length(1), // Spacer
+            Constraint::Min(1),    // Error
+        ]
+    } else {
+        vec![
+            Constraint::Length(1), // Input label
+            Constraint::Length(1), // Input field
+            Constraint::Length(1), // Spacer
+            Constraint::Length(1), // Hints
+            Constraint::Min(0),    // Filler
+        ]
+    };
+    let chunks = Layout::vertical(constraints).split(inner);
+
+    // Input label
     let label = Paragraph::new("API Key:").style(Style::default().fg(Color::Gray));
     f.render_widget(label, chunks[0]);
 
@@ -534,7 +1492,12 @@ fn render_validating_dialog(f: &mut Frame, provider_name: &str) {
 }
 
 /// Render the model selection dialog.
-fn render_model_selection_dialog(f: &mut Frame, provider: Provider, selected: usize) {
+fn render_model_selection_dialog(
+    f: &mut Frame,
+    provider: Provider,
+    models: Option<&[ModelInfo]>,
+    selected: usize,
+) {
     let area = centered_rect(50, 50, f.size());
     f.render_widget(Clear, area);
 
@@ -555,18 +1518,36 @@ fn render_model_selection_dialog(f: &mut Frame, provider: Provider, selected: us
     ])
     .split(inner);
 
-    // Get the appropriate model list for the provider
-    let models: &[(&str, &str)] = match provider {
-        Provider::Anthropic => ANTHROPIC_MODELS,
-        Provider::GitHubCopilot => COPILOT_MODELS,
-        _ => &[], // Other providers don't use model selection
+    // Hints
+    let hints = Line::from(vec![
+        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    let hints_widget = Paragraph::new(hints).style(Style::default().fg(Color::Gray));
+    f.render_widget(hints_widget, chunks[1]);
+
+    let Some(models) = models else {
+        let loading = Line::from(Span::styled(
+            "  Loading models...",
+            Style::default().fg(Color::Gray),
+        ));
+        f.render_widget(Paragraph::new(loading), chunks[0]);
+        return;
     };
 
-    // Model options
+    // Model options, scrolled to keep the selection in view
+    let visible = (chunks[0].height as usize).max(1);
+    let start = (selected + 1).saturating_sub(visible);
     let lines: Vec<Line> = models
         .iter()
         .enumerate()
-        .map(|(i, (display_name, _api_id))| {
+        .skip(start)
+        .take(visible)
+        .map(|(i, model)| {
             let style = if i == selected {
                 Style::default()
                     .fg(Color::Black)
@@ -576,13 +1557,146 @@ fn render_model_selection_dialog(f: &mut Frame, provider: Provider, selected: us
                 Style::default().fg(Color::White)
             };
             let prefix = if i == selected { "> " } else { "  " };
-            Line::from(Span::styled(format!("{}{}", prefix, display_name), style))
+            Line::from(Span::styled(format!("{}{}", prefix, model.name), style))
         })
         .collect();
     let options_widget = Paragraph::new(lines);
     f.render_widget(options_widget, chunks[0]);
+}
+
+/// Render the dialog asking for a local server's URL.
+fn render_server_url_dialog(f: &mut Frame, input: &str, cursor: usize, error: Option<&str>) {
+    let height_percent = if error.is_some() { 45 } else { 35 };
+    let area = centered_rect(60, height_percent, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Connect to a Local Server ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(1), // Input label
+        Constraint::Length(1), // Input field
+        Constraint::Length(1), // Spacer
+        Constraint::Length(1), // Hints
+        Constraint::Length(1), // Spacer
+        Constraint::Min(0),    // Error
+    ])
+    .split(inner);
+
+    let label = Paragraph::new("Server URL (LM Studio, vLLM, llama.cpp, LocalAI):")
+        .style(Style::default().fg(Color::Gray));
+    f.render_widget(label, chunks[0]);
+
+    let input_line = Line::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Cyan)),
+        Span::raw(&input[..cursor]),
+        Span::styled("▎", Style::default().fg(Color::Cyan).add_modifier(Modifier::SLOW_BLINK)),
+        Span::raw(&input[cursor..]),
+    ]);
+    f.render_widget(
+        Paragraph::new(input_line).style(Style::default().fg(Color::White)),
+        chunks[1],
+    );
+
+    let hints = Line::from(vec![
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Fetch models  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[3],
+    );
+
+    if let Some(err) = error {
+        let error_widget = Paragraph::new(err)
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        f.render_widget(error_widget, chunks[5]);
+    }
+}
+
+/// Render the "fetching models" dialog.
+fn render_fetching_models_dialog(f: &mut Frame, url: &str) {
+    let area = centered_rect(50, 25, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Fetching Models... ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let text = Paragraph::new(format!("Asking {}", url))
+        .style(Style::default().fg(Color::Gray))
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, inner);
+}
+
+/// Render the dialog offering to pull an Ollama model that isn't installed.
+fn render_ollama_pull_offer_dialog(
+    f: &mut Frame,
+    model: &str,
+    has_installed: bool,
+    selected: usize,
+) {
+    let area = centered_rect(50, 30, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Model Not Installed ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Length(1), // Message
+        Constraint::Length(1), // Spacer
+        Constraint::Min(2),    // Options
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let message = Paragraph::new(format!("{} isn't installed in Ollama.", model))
+        .style(Style::default().fg(Color::Gray));
+    f.render_widget(message, chunks[0]);
+
+    let pull = format!("Pull {}", model);
+    let mut options = vec![pull.as_str()];
+    if has_installed {
+        options.push("Choose an installed model");
+    }
+    let lines: Vec<Line> = options
+        .iter()
+        .enumerate()
+        .map(|(i, opt)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let prefix = if i == selected { "> " } else { "  " };
+            Line::from(Span::styled(format!("{}{}", prefix, opt), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[2]);
 
-    // Hints
     let hints = Line::from(vec![
         Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
         Span::raw(" Navigate  "),
@@ -591,6 +1705,133 @@ fn render_model_selection_dialog(f: &mut Frame, provider: Provider, selected: us
         Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
         Span::raw(" Cancel"),
     ]);
-    let hints_widget = Paragraph::new(hints).style(Style::default().fg(Color::Gray));
-    f.render_widget(hints_widget, chunks[1]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[3],
+    );
+}
+
+/// Render the list of installed Ollama models with their sizes and dates.
+fn render_ollama_model_dialog(f: &mut Frame, models: &[InstalledModel], selected: usize) {
+    let area = centered_rect(60, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Select Ollama Model ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Min(3),    // Models
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let name_width = models.iter().map(|m| m.name.width()).max().unwrap_or(0);
+    let visible = (chunks[0].height as usize).max(1);
+    let start = (selected + 1).saturating_sub(visible);
+    let lines: Vec<Line> = models
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(visible)
+        .map(|(i, model)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let prefix = if i == selected { "> " } else { "  " };
+            let modified = model
+                .modified_at
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            let padding = " ".repeat(name_width - model.name.width());
+            Line::from(vec![
+                Span::styled(format!("{}{}{}", prefix, model.name, padding), style),
+                Span::styled(
+                    format!("  {:>8}  {}", format_size(model.size), modified),
+                    Style::default().fg(Color::Gray),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Line::from(vec![
+        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[1],
+    );
+}
+
+/// Render the list of models a local server offers.
+fn render_server_model_dialog(f: &mut Frame, api_base: &str, models: &[String], selected: usize) {
+    let area = centered_rect(50, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Select Model ({}) ", api_base))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Min(3),    // Models
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    // Servers can offer many models; keep the selection in view
+    let visible = (chunks[0].height as usize).max(1);
+    let start = (selected + 1).saturating_sub(visible);
+    let lines: Vec<Line> = models
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(visible)
+        .map(|(i, model)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let prefix = if i == selected { "> " } else { "  " };
+            Line::from(Span::styled(format!("{}{}", prefix, model), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Line::from(vec![
+        Span::styled("[↑↓]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Cancel"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[1],
+    );
 }
@@ -0,0 +1,297 @@
+//! Search within the conversation (Ctrl+F).
+//!
+//! Typing in the search bar finds matches in every message as you go and
+//! jumps to the first one from the top of the view; Enter stops editing so
+//! `n` and `N` step to the next and previous match. Matching ignores case
+//! unless the query has capitals. On screen, every visible occurrence is
+//! highlighted in the cells the renderer drew.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::message::Message;
+
+/// Background of matches on screen.
+pub const MATCH_BG: Color = Color::Rgb(120, 100, 20);
+
+/// Key hints shown while typing the query.
+const EDIT_HINTS: &str = "Enter done · ↑↓ previous/next · Esc close";
+
+/// Key hints shown once the query is entered.
+const BROWSE_HINTS: &str = "n next · N previous · / edit · Esc close";
+
+/// Result of handling a key event in the search bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchAction {
+    /// Nothing to do beyond redrawing.
+    Continue,
+    /// The query changed; find the matches again.
+    Changed,
+    /// Go to the next match.
+    Next,
+    /// Go to the previous match.
+    Previous,
+    /// Close the search bar.
+    Close,
+}
+
+/// A match: message index and byte offset in its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub message: usize,
+    pub start: usize,
+}
+
+/// State of the search bar.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationSearch {
+    /// What to look for
+    pub query: String,
+    /// Whether keys edit the query (before Enter)
+    pub editing: bool,
+    /// Matches in conversation order
+    pub matches: Vec<SearchMatch>,
+    /// Index into `matches` of the current match
+    pub current: usize,
+}
+
+impl ConversationSearch {
+    /// Open an empty search bar.
+    pub fn new() -> Self {
+        Self {
+            editing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> SearchAction {
+        if self.editing {
+            return match key {
+                KeyCode::Esc => SearchAction::Close,
+                KeyCode::Enter => {
+                    self.editing = false;
+                    SearchAction::Continue
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    SearchAction::Changed
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    SearchAction::Changed
+                }
+                KeyCode::Down => SearchAction::Next,
+                KeyCode::Up => SearchAction::Previous,
+                _ => SearchAction::Continue,
+            };
+        }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => SearchAction::Close,
+            KeyCode::Char('n') | KeyCode::Enter | KeyCode::Down => SearchAction::Next,
+            KeyCode::Char('N') | KeyCode::Up => SearchAction::Previous,
+            KeyCode::Char('/') => {
+                self.editing = true;
+                SearchAction::Continue
+            }
+            _ => SearchAction::Continue,
+        }
+    }
+
+    /// Whether matching ignores case: unless the query has capitals.
+    fn ignore_case(&self) -> bool {
+        !self.query.chars().any(char::is_uppercase)
+    }
+
+    /// Find the query in `messages` again, keeping the current match on the
+    /// first one at or after message `from`.
+    pub fn find(&mut self, messages: &[Message], from: usize) {
+        self.matches.clear();
+        self.current = 0;
+        if self.query.is_empty() {
+            return;
+        }
+        let ignore_case = self.ignore_case();
+        let needle = fold(&self.query, ignore_case);
+        for (index, message) in messages.iter().enumerate() {
+            if message.is_system_banner() {
+                continue;
+            }
+            let haystack = fold(&message.content, ignore_case);
+            self.matches.extend(
+                haystack
+                    .match_indices(needle.as_str())
+                    .map(|(start, _)| SearchMatch {
+                        message: index,
+                        start,
+                    }),
+            );
+        }
+        self.current = self
+            .matches
+            .iter()
+            .position(|m| m.message >= from)
+            .unwrap_or(0);
+    }
+
+    /// Step to the next (or previous) match, wrapping around.
+    pub fn step(&mut self, forward: bool) -> Option<SearchMatch> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+        self.current = if forward {
+            (self.current + 1) % count
+        } else {
+            (self.current + count - 1) % count
+        };
+        self.current_match()
+    }
+
+    /// The current match, if there is one.
+    pub fn current_match(&self) -> Option<SearchMatch> {
+        self.matches.get(self.current).copied()
+    }
+
+    /// Highlight the occurrences of the query drawn in `area` of `buf`.
+    pub fn highlight(&self, buf: &mut Buffer, area: Rect) {
+        let ignore_case = self.ignore_case();
+        let needle: Vec<char> = fold(&self.query, ignore_case).chars().collect();
+        if needle.is_empty() {
+            return;
+        }
+        for row in area.top()..area.bottom() {
+            // One character per drawn cell; wide characters leave empty cells
+            let cells: Vec<(u16, char)> = (area.left()..area.right())
+                .filter_map(|column| {
+                    let c = buf.get(column, row).symbol().chars().next()?;
+                    Some((column, fold_char(c, ignore_case)))
+                })
+                .collect();
+            let mut i = 0;
+            while i + needle.len() <= cells.len() {
+                if cells[i..i + needle.len()]
+                    .iter()
+                    .map(|(_, c)| c)
+                    .eq(needle.iter())
+                {
+                    for &(column, _) in &cells[i..i + needle.len()] {
+                        buf.get_mut(column, row).set_bg(MATCH_BG);
+                    }
+                    i += needle.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+/// `text` lowercased when matching ignores case. Lowercasing keeps byte
+/// offsets only for text whose lowercase is the same length, which holds
+/// for nearly everything people search for; offsets only pick the message.
+fn fold(text: &str, ignore_case: bool) -> String {
+    if ignore_case {
+        text.chars().map(|c| fold_char(c, true)).collect()
+    } else {
+        text.to_string()
+    }
+}
+
+/// `c` lowercased when matching ignores case.
+fn fold_char(c: char, ignore_case: bool) -> char {
+    if ignore_case {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// Render the search bar into `area` (the status bar's row).
+pub fn render_search_bar(f: &mut Frame, area: Rect, search: &ConversationSearch) {
+    let mut spans = vec![
+        Span::styled(" Search: ", Style::default().fg(Color::Yellow)),
+        Span::styled(
+            search.query.clone(),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ),
+    ];
+    if search.editing {
+        spans.push(Span::styled("▎", Style::default().fg(Color::Cyan)));
+    }
+    let count = if search.query.is_empty() {
+        String::new()
+    } else if search.matches.is_empty() {
+        "  no matches".to_string()
+    } else {
+        format!("  {} of {}", search.current + 1, search.matches.len())
+    };
+    spans.push(Span::styled(count, Style::default().fg(Color::Yellow)));
+    let hints = if search.editing {
+        EDIT_HINTS
+    } else {
+        BROWSE_HINTS
+    };
+    spans.push(Span::styled(
+        format!("  {}", hints),
+        Style::default().fg(Color::DarkGray),
+    ));
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::user("How do I parse TOML?".to_string()),
+            Message::assistant("Use the toml crate: toml::from_str.".to_string()),
+            Message::user("And JSON?".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_finds_matches_incrementally_and_steps_between_them() {
+        let mut search = ConversationSearch::new();
+        for c in "toml".chars() {
+            assert_eq!(search.handle_key(KeyCode::Char(c)), SearchAction::Changed);
+        }
+        search.find(&messages(), 1);
+        assert_eq!(search.matches.len(), 3);
+        assert_eq!(search.current_match().map(|m| m.message), Some(1));
+
+        search.handle_key(KeyCode::Enter);
+        assert_eq!(search.handle_key(KeyCode::Char('n')), SearchAction::Next);
+        search.step(true);
+        assert_eq!(search.step(true).map(|m| m.message), Some(0));
+        assert_eq!(search.step(false).map(|m| m.message), Some(1));
+
+        // Capitals make the search match case
+        search.query = "TOML".to_string();
+        search.find(&messages(), 0);
+        assert_eq!(search.matches, [SearchMatch { message: 0, start: 15 }]);
+    }
+
+    #[test]
+    fn test_highlights_occurrences_on_screen() {
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "a Toml b toml", Style::default());
+        let search = ConversationSearch {
+            query: "toml".to_string(),
+            ..ConversationSearch::new()
+        };
+        search.highlight(&mut buf, area);
+        assert_eq!(buf.get(2, 0).bg, MATCH_BG);
+        assert_eq!(buf.get(9, 0).bg, MATCH_BG);
+        assert_eq!(buf.get(7, 0).bg, Color::Reset);
+    }
+}
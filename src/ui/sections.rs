@@ -0,0 +1,176 @@
+//! Jump-to-section picker (Ctrl+G).
+//!
+//! Lists the topic sections of the conversation so the user can jump to one
+//! or fold it without scrolling through the whole history.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::render::centered_rect;
+use crate::sections::{Section, SectionState};
+
+/// Result of handling a key event in the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionPickerAction {
+    /// Keep the picker open.
+    Continue,
+    /// Close the picker.
+    Close,
+    /// Scroll to the section at this index and close.
+    Jump(usize),
+    /// Fold or unfold the section at this index.
+    Toggle(usize),
+}
+
+/// State of the section picker.
+#[derive(Debug, Clone, Default)]
+pub struct SectionPicker {
+    /// Index of the highlighted section
+    pub selected: usize,
+}
+
+impl SectionPicker {
+    /// Create a picker with `selected` highlighted.
+    pub fn new(selected: usize) -> Self {
+        Self { selected }
+    }
+
+    /// Handle a key press, given the number of sections.
+    pub fn handle_key(&mut self, key: KeyCode, count: usize) -> SectionPickerAction {
+        match key {
+            KeyCode::Esc => SectionPickerAction::Close,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                SectionPickerAction::Continue
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < count {
+                    self.selected += 1;
+                }
+                SectionPickerAction::Continue
+            }
+            KeyCode::Enter if self.selected < count => SectionPickerAction::Jump(self.selected),
+            KeyCode::Char(' ') if self.selected < count => {
+                SectionPickerAction::Toggle(self.selected)
+            }
+            _ => SectionPickerAction::Continue,
+        }
+    }
+}
+
+/// Render the section picker.
+pub fn render_section_picker(
+    f: &mut Frame,
+    picker: &SectionPicker,
+    sections: &[Section],
+    state: &SectionState,
+) {
+    let area = centered_rect(60, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Sections ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Section list
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    // Keep the selection visible in long sessions
+    let visible = chunks[0].height as usize;
+    let first = (picker.selected + 1).saturating_sub(visible);
+
+    let lines: Vec<Line> = sections
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, section)| {
+            let selected = i == picker.selected;
+            let style = if selected {
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let fold = if state.is_collapsed(section.start) {
+                "▸"
+            } else {
+                "▾"
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!(
+                        "{} {} {}",
+                        if selected { ">" } else { " " },
+                        fold,
+                        section.title
+                    ),
+                    style,
+                ),
+                Span::styled(
+                    format!("  ({} messages)", section.len()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Paragraph::new("↑↓ select · Enter jump · Space fold · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation_stays_in_bounds() {
+        let mut picker = SectionPicker::new(0);
+        picker.handle_key(KeyCode::Up, 3);
+        assert_eq!(picker.selected, 0);
+        picker.handle_key(KeyCode::Down, 3);
+        picker.handle_key(KeyCode::Down, 3);
+        picker.handle_key(KeyCode::Down, 3);
+        assert_eq!(picker.selected, 2);
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter, 3),
+            SectionPickerAction::Jump(2)
+        );
+        assert_eq!(
+            picker.handle_key(KeyCode::Char(' '), 3),
+            SectionPickerAction::Toggle(2)
+        );
+    }
+
+    #[test]
+    fn test_no_sections_cannot_jump() {
+        let mut picker = SectionPicker::new(0);
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter, 0),
+            SectionPickerAction::Continue
+        );
+        assert_eq!(
+            picker.handle_key(KeyCode::Esc, 0),
+            SectionPickerAction::Close
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Message selection mode (Ctrl+V).
+//!
+//! Up/Down move a highlight from message to message instead of scrolling,
+//! and single keys act on the highlighted message: copy, delete, edit, pin
+//! or rerun it. `i` shows or hides the model, timing and token usage under
+//! every reply. The chat keeps the highlighted message in view.
+
+use crossterm::event::KeyCode;
+use ratatui::style::Color;
+
+/// Background of the selected message.
+pub const SELECTION_BG: Color = Color::Rgb(45, 45, 70);
+
+/// Key hints shown in the status bar while selecting.
+pub const SELECTION_HINTS: &str =
+    "↑↓ move · y copy · d delete · e edit · p pin · r rerun · i info · Enter open · Esc done";
+
+/// Result of handling a key event in selection mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionAction {
+    /// Keep selecting.
+    Continue,
+    /// Leave selection mode.
+    Close,
+    /// Open the message at this index full-screen.
+    Open(usize),
+    /// Copy the message at this index to the clipboard.
+    Copy(usize),
+    /// Delete the message at this index.
+    Delete(usize),
+    /// Edit the prompt at this index (or before it) and resend it.
+    Edit(usize),
+    /// Pin or unpin the message at this index.
+    Pin(usize),
+    /// Send the prompt at this index (or before it) again.
+    Rerun(usize),
+    /// Show or hide the details under replies.
+    Info,
+}
+
+/// State of selection mode.
+#[derive(Debug, Clone, Default)]
+pub struct MessageSelection {
+    /// Index of the highlighted message
+    pub selected: usize,
+}
+
+impl MessageSelection {
+    /// Start selecting with the message at `selected` highlighted.
+    pub fn new(selected: usize) -> Self {
+        Self { selected }
+    }
+
+    /// Handle a key press, given the number of messages.
+    pub fn handle_key(&mut self, key: KeyCode, count: usize) -> SelectionAction {
+        if count == 0 {
+            return SelectionAction::Close;
+        }
+        self.selected = self.selected.min(count - 1);
+        let selected = self.selected;
+        match key {
+            KeyCode::Esc | KeyCode::Char('v') | KeyCode::Char('q') => SelectionAction::Close,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = selected.saturating_sub(1);
+                SelectionAction::Continue
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (selected + 1).min(count - 1);
+                SelectionAction::Continue
+            }
+            KeyCode::Home | KeyCode::Char('g') => {
+                self.selected = 0;
+                SelectionAction::Continue
+            }
+            KeyCode::End | KeyCode::Char('G') => {
+                self.selected = count - 1;
+                SelectionAction::Continue
+            }
+            KeyCode::Enter => SelectionAction::Open(selected),
+            KeyCode::Char('y') | KeyCode::Char('c') => SelectionAction::Copy(selected),
+            KeyCode::Char('d') | KeyCode::Delete => SelectionAction::Delete(selected),
+            KeyCode::Char('e') => SelectionAction::Edit(selected),
+            KeyCode::Char('p') => SelectionAction::Pin(selected),
+            KeyCode::Char('r') => SelectionAction::Rerun(selected),
+            KeyCode::Char('i') => SelectionAction::Info,
+            _ => SelectionAction::Continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves_between_messages_and_acts_on_the_highlighted_one() {
+        let mut selection = MessageSelection::new(2);
+        selection.handle_key(KeyCode::Down, 3);
+        assert_eq!(selection.selected, 2);
+        selection.handle_key(KeyCode::Char('k'), 3);
+        assert_eq!(selection.selected, 1);
+        assert_eq!(
+            selection.handle_key(KeyCode::Char('y'), 3),
+            SelectionAction::Copy(1)
+        );
+        selection.handle_key(KeyCode::Home, 3);
+        assert_eq!(
+            selection.handle_key(KeyCode::Char('d'), 3),
+            SelectionAction::Delete(0)
+        );
+        assert_eq!(selection.handle_key(KeyCode::Char('i'), 3), SelectionAction::Info);
+        assert_eq!(selection.handle_key(KeyCode::Esc, 3), SelectionAction::Close);
+    }
+
+    #[test]
+    fn test_selection_follows_a_shrinking_conversation() {
+        let mut selection = MessageSelection::new(5);
+        assert_eq!(
+            selection.handle_key(KeyCode::Char('p'), 2),
+            SelectionAction::Pin(1)
+        );
+        assert_eq!(selection.handle_key(KeyCode::Down, 0), SelectionAction::Close);
+    }
+}
@@ -0,0 +1,188 @@
+//! Saved session browser (Resume Session in the Ctrl+P menu).
+//!
+//! Lists saved conversations, most recently updated first, so one can be
+//! reopened in place of the current chat. Sessions with messages that were
+//! never scrolled into view show an unread count.
+
+use chrono::Local;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::render::centered_rect;
+use crate::session::SessionSummary;
+
+/// Result of handling a key event in the picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionPickerAction {
+    /// Keep the picker open.
+    Continue,
+    /// Close the picker.
+    Close,
+    /// Open the session with this id and close.
+    Open(String),
+}
+
+/// State of the session picker.
+#[derive(Debug, Clone, Default)]
+pub struct SessionPicker {
+    /// Sessions to choose from, newest first
+    pub sessions: Vec<SessionSummary>,
+    /// Index of the highlighted session
+    pub selected: usize,
+}
+
+impl SessionPicker {
+    /// Create a picker over `sessions` with the first one highlighted.
+    pub fn new(sessions: Vec<SessionSummary>) -> Self {
+        Self {
+            sessions,
+            selected: 0,
+        }
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> SessionPickerAction {
+        match key {
+            KeyCode::Esc => SessionPickerAction::Close,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                SessionPickerAction::Continue
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < self.sessions.len() {
+                    self.selected += 1;
+                }
+                SessionPickerAction::Continue
+            }
+            KeyCode::Enter => match self.sessions.get(self.selected) {
+                Some(session) => SessionPickerAction::Open(session.id.clone()),
+                None => SessionPickerAction::Continue,
+            },
+            _ => SessionPickerAction::Continue,
+        }
+    }
+}
+
+/// Render the session picker.
+pub fn render_session_picker(f: &mut Frame, picker: &SessionPicker) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Saved Sessions ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Session list
+            Constraint::Length(1), // Key hints
+        ])
+        .split(inner);
+
+    // Two lines per session; keep the selection visible
+    let visible = (chunks[0].height as usize / 2).max(1);
+    let first = (picker.selected + 1).saturating_sub(visible);
+
+    let mut lines = Vec::new();
+    for (i, session) in picker.sessions.iter().enumerate().skip(first).take(visible) {
+        let selected = i == picker.selected;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let title = if session.title.is_empty() {
+            "(untitled)"
+        } else {
+            &session.title
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{} {}", if selected { ">" } else { " " }, title),
+            style,
+        )));
+        let mut meta = vec![Span::styled(
+            format!(
+                "    {}/{} · {} · {} messages",
+                session.provider,
+                session.model,
+                session
+                    .updated_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M"),
+                session.message_count
+            ),
+            Style::default().fg(Color::DarkGray),
+        )];
+        if session.unread > 0 {
+            meta.push(Span::styled(
+                format!(" · {} unread", session.unread),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        lines.push(Line::from(meta));
+    }
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let hints = Paragraph::new("↑↓ select · Enter open · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hints, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn summary(id: &str) -> SessionSummary {
+        SessionSummary {
+            id: id.to_string(),
+            title: format!("Session {}", id),
+            provider: "ollama".to_string(),
+            model: "llama3".to_string(),
+            updated_at: Utc::now(),
+            message_count: 2,
+            unread: 0,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_navigation_and_open() {
+        let mut picker = SessionPicker::new(vec![summary("a"), summary("b")]);
+        picker.handle_key(KeyCode::Up);
+        assert_eq!(picker.selected, 0);
+        picker.handle_key(KeyCode::Down);
+        picker.handle_key(KeyCode::Down);
+        assert_eq!(picker.selected, 1);
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter),
+            SessionPickerAction::Open("b".to_string())
+        );
+        assert_eq!(picker.handle_key(KeyCode::Esc), SessionPickerAction::Close);
+    }
+
+    #[test]
+    fn test_empty_picker_cannot_open() {
+        let mut picker = SessionPicker::new(Vec::new());
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter),
+            SessionPickerAction::Continue
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! One-line status bar under the input.
+//!
+//! Left: connection status (with a spinner while a reply streams), the
+//! retry a failed request is waiting on, the profile in use and the active
+//! provider and model. Right: how much of the
+//! model's context window the conversation fills, the requests and tokens
+//! the provider's rate limits have left, then tokens sent and received since
+//! scry started and what they cost where the price is known.
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, ConnectionStatus, RetryNotice};
+use crate::config::ThemeConfig;
+use crate::llm::{ProviderError, RateLimits};
+use crate::profiles;
+use crate::tokens::ContextUsage;
+
+use super::selection::SELECTION_HINTS;
+
+/// Spinner frames shown while a reply streams.
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// How often the spinner advances.
+const SPINNER_FRAME_MS: u128 = 100;
+
+/// Cells in the context meter.
+const METER_WIDTH: usize = 8;
+
+/// Label and color for the connection status.
+fn status_label(app: &App, theme: &ThemeConfig) -> (&'static str, Color) {
+    if app.llm.offline.offline {
+        return ("offline", theme.status_error());
+    }
+    match app.llm.status {
+        ConnectionStatus::NotConfigured => ("not connected", theme.status_not_configured()),
+        ConnectionStatus::Ready => ("ready", theme.status_ready()),
+        ConnectionStatus::WarmingUp => ("loading", theme.status_streaming()),
+        ConnectionStatus::Streaming => ("streaming", theme.status_streaming()),
+        ConnectionStatus::Error(_) => ("error", theme.status_error()),
+    }
+}
+
+/// The retry a request is waiting on, e.g. "↻ Rate limited · retry 1/3 in 4s".
+fn retry_label(retry: &RetryNotice, now: Instant) -> String {
+    let wait = retry.at.saturating_duration_since(now).as_secs_f64().ceil();
+    format!(
+        "↻ {} · retry {}/{} in {}s",
+        ProviderError::from_message(&retry.reason).title(),
+        retry.attempt,
+        retry.max_retries,
+        wait
+    )
+}
+
+/// What the provider's quotas have left, e.g. "⏱ 45 req · 38.2k tok left".
+fn quota_label(limits: &RateLimits) -> String {
+    let requests = limits.requests.map(|q| format!("{} req", q.remaining));
+    let tokens = limits
+        .tokens
+        .map(|q| format!("{} tok", format_tokens(q.remaining)));
+    let parts: Vec<String> = [requests, tokens].into_iter().flatten().collect();
+    format!("⏱ {} left", parts.join(" · "))
+}
+
+/// Token count in a few characters: `950`, `12.3k`, `4.1M`.
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+/// Context meter cells: `▰` for the filled share of the window, `▱` for the rest.
+pub fn context_meter(usage: ContextUsage) -> String {
+    let filled = ((usage.fraction() * METER_WIDTH as f64).ceil() as usize).min(METER_WIDTH);
+    format!("{}{}", "▰".repeat(filled), "▱".repeat(METER_WIDTH - filled))
+}
+
+/// Draw the status bar into `area`.
+pub fn render_status_bar(
+    f: &mut Frame,
+    area: Rect,
+    app: &App,
+    context: ContextUsage,
+    theme: &ThemeConfig,
+) {
+    let (label, color) = status_label(app, theme);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    // Advances with each redraw, which a streaming reply triggers per token
+    let marker = if app.llm.status == ConnectionStatus::Streaming {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        SPINNER[(millis / SPINNER_FRAME_MS) as usize % SPINNER.len()]
+    } else {
+        "●"
+    };
+    let mut left = vec![
+        Span::styled(format!(" {} ", marker), Style::default().fg(color)),
+        Span::styled(label, Style::default().fg(color)),
+    ];
+    if let Some(retry) = &app.llm.retrying {
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(
+            retry_label(retry, Instant::now()),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(profile) = profiles::active() {
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(profile, Style::default().fg(Color::Magenta)));
+    }
+    if app.llm.status != ConnectionStatus::NotConfigured {
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(
+            app.llm.config.provider.display_name(),
+            Style::default().fg(theme.fg_primary()),
+        ));
+        left.push(Span::styled(" · ", dim));
+        left.push(Span::styled(
+            app.llm.config.model.clone(),
+            Style::default()
+                .fg(theme.fg_primary())
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(selection) = &app.selection {
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(
+            format!(
+                "▌ message {} of {} · {}",
+                selection.selected + 1,
+                app.chat.messages.len(),
+                SELECTION_HINTS
+            ),
+            Style::default().fg(Color::Cyan),
+        ));
+    } else if app.chat.editing.is_some() {
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(
+            "✎ editing a prompt · Esc cancels",
+            Style::default().fg(Color::Yellow),
+        ));
+    } else if let Some(expiring) = app.llm.token_refresh.expiring.first() {
+        let now = chrono::Utc::now();
+        let color = if expiring.expires_at <= now {
+            theme.status_error()
+        } else {
+            Color::Yellow
+        };
+        left.push(Span::styled(" │ ", dim));
+        left.push(Span::styled(
+            format!(
+                "⚠ {} sign-in {} · Ctrl+L",
+                expiring.provider.display_name(),
+                expiring.describe(now)
+            ),
+            Style::default().fg(color),
+        ));
+    }
+
+    let usage = &app.session_usage;
+    let right = format!(
+        "↑{} ↓{} tokens · {} ",
+        format_tokens(usage.input_tokens),
+        format_tokens(usage.output_tokens),
+        usage.cost_label()
+    );
+
+    // Yellow once a quota is down to its last tenth
+    let quota = app.llm.rate_limits.limits.map(|limits| {
+        let low = [limits.requests, limits.tokens]
+            .into_iter()
+            .flatten()
+            .any(|q| q.remaining.saturating_mul(10) <= q.limit);
+        Span::styled(
+            format!("{} │ ", quota_label(&limits)),
+            if low { Style::default().fg(Color::Yellow) } else { dim },
+        )
+    });
+
+    let meter_color = match context.fraction() {
+        f if f > 0.9 => theme.status_error(),
+        f if f > 0.75 => Color::Yellow,
+        _ => Color::DarkGray,
+    };
+    let meter = vec![
+        Span::styled(
+            format!("{} ", context_meter(context)),
+            Style::default().fg(meter_color),
+        ),
+        Span::styled(
+            format!(
+                "{}% of {}",
+                context.percent(),
+                format_tokens(context.window as u64)
+            ),
+            Style::default().fg(meter_color),
+        ),
+        Span::styled(" │ ", dim),
+    ];
+
+    // Usage drops out first when the terminal is narrow, then the meter
+    let left_width: usize = left.iter().map(|s| s.content.width()).sum();
+    let meter_width: usize = meter.iter().map(|s| s.content.width()).sum();
+    let quota_width = quota.as_ref().map_or(0, |s| s.content.width());
+    let gap = (area.width as usize).saturating_sub(left_width + quota_width + right.width());
+    if gap > meter_width {
+        left.push(Span::raw(" ".repeat(gap - meter_width)));
+        left.extend(meter);
+        left.extend(quota);
+        left.push(Span::styled(right, dim));
+    } else if gap > 0 {
+        left.push(Span::raw(" ".repeat(gap)));
+        left.extend(quota);
+        left.push(Span::styled(right, dim));
+    }
+
+    f.render_widget(
+        Paragraph::new(Line::from(left)).style(Style::default().bg(theme.bg_secondary())),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_label() {
+        let now = Instant::now();
+        let retry = RetryNotice {
+            attempt: 2,
+            max_retries: 3,
+            at: now + std::time::Duration::from_millis(3200),
+            reason: "OpenAI API error (429): Rate limit reached".to_string(),
+        };
+        assert_eq!(retry_label(&retry, now), "↻ Rate limited · retry 2/3 in 4s");
+    }
+
+    #[test]
+    fn test_quota_label() {
+        use crate::llm::Quota;
+        let quota = |remaining| Quota {
+            limit: 50_000,
+            remaining,
+            reset: None,
+        };
+        let limits = RateLimits {
+            requests: Some(quota(45)),
+            tokens: Some(quota(38_200)),
+        };
+        assert_eq!(quota_label(&limits), "⏱ 45 req · 38.2k tok left");
+        let limits = RateLimits {
+            requests: None,
+            ..limits
+        };
+        assert_eq!(quota_label(&limits), "⏱ 38.2k tok left");
+    }
+
+    #[test]
+    fn test_format_tokens() {
+        assert_eq!(format_tokens(0), "0");
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(12_345), "12.3k");
+        assert_eq!(format_tokens(4_100_000), "4.1M");
+    }
+
+    #[test]
+    fn test_context_meter() {
+        let usage = |tokens| ContextUsage {
+            tokens,
+            window: 8_000,
+        };
+        assert_eq!(context_meter(usage(0)), "▱▱▱▱▱▱▱▱");
+        assert_eq!(context_meter(usage(1)), "▰▱▱▱▱▱▱▱");
+        assert_eq!(context_meter(usage(4_000)), "▰▰▰▰▱▱▱▱");
+        assert_eq!(context_meter(usage(20_000)), "▰▰▰▰▰▰▰▰");
+    }
+
+    #[test]
+    fn test_status_label() {
+        let theme = ThemeConfig::default();
+        let mut app = App::new_without_banner();
+        app.llm.status = ConnectionStatus::Error("boom".to_string());
+        assert_eq!(status_label(&app, &theme).0, "error");
+        app.llm.offline.offline = true;
+        assert_eq!(status_label(&app, &theme), ("offline", theme.status_error()));
+    }
+}
@@ -1,6 +1,8 @@
 //! Toast notification system for displaying transient status messages.
 //!
-//! Toasts appear in the top-right corner and auto-dismiss after a configurable duration.
+//! Toasts stack in a corner (`[toasts]`, top-right by default) and
+//! auto-dismiss after a configurable duration. They are placed around
+//! exclusion zones, so they never cover the input line or an open dialog.
 
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
@@ -13,6 +15,14 @@ use ratatui::{
     Frame,
 };
 
+use crate::config::{ToastAnimation, ToastConfig, ToastPosition};
+
+/// Rows a toast takes: border, message, border.
+const TOAST_HEIGHT: u16 = 3;
+
+/// How long toasts take to slide or fade in, and out again.
+const ANIMATION: Duration = Duration::from_millis(250);
+
 /// Toast notification level determining color and behavior.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ToastLevel {
@@ -102,6 +112,14 @@ impl Toast {
         self.created_at.elapsed() >= self.duration
     }
 
+    /// How far in the toast is `elapsed` after it was created: rising from
+    /// 0.0 to 1.0 while it animates in, falling back to 0.0 as it leaves.
+    pub fn presence(&self, elapsed: Duration) -> f32 {
+        let remaining = self.duration.saturating_sub(elapsed);
+        let progress = |d: Duration| d.as_secs_f32() / ANIMATION.as_secs_f32();
+        progress(elapsed).min(progress(remaining)).min(1.0)
+    }
+
     /// Get the remaining time as a fraction (1.0 = full, 0.0 = expired).
     pub fn time_remaining_fraction(&self) -> f32 {
         let elapsed = self.created_at.elapsed().as_secs_f32();
@@ -122,15 +140,17 @@ pub struct ToastState {
     next_id: u64,
     /// Maximum number of toasts to display at once.
     pub max_visible: usize,
+    /// Corner the toasts stack in.
+    pub position: ToastPosition,
+    /// Toast width in columns.
+    pub width: u16,
+    /// How toasts appear and leave.
+    pub animation: ToastAnimation,
 }
 
 impl Default for ToastState {
     fn default() -> Self {
-        Self {
-            toasts: VecDeque::new(),
-            next_id: 0,
-            max_visible: 3,
-        }
+        Self::with_config(&ToastConfig::default())
     }
 }
 
@@ -142,10 +162,21 @@ impl ToastState {
 
     /// Create a new ToastState with a custom max visible count.
     pub fn with_max_visible(max_visible: usize) -> Self {
+        Self {
+            max_visible,
+            ..Self::default()
+        }
+    }
+
+    /// Create a new ToastState placed and animated as configured.
+    pub fn with_config(config: &ToastConfig) -> Self {
         Self {
             toasts: VecDeque::new(),
             next_id: 0,
-            max_visible,
+            max_visible: config.max_visible.max(1),
+            position: config.position,
+            width: config.width,
+            animation: config.animation,
         }
     }
 
@@ -227,61 +258,129 @@ impl ToastState {
     }
 }
 
-/// Render toasts in the top-right corner of the frame.
-pub fn render_toasts(f: &mut Frame, toast_state: &ToastState) {
+/// Areas for up to `count` toasts stacked from `position`, skipping past
+/// the `exclusions` (input, status bar, dialogs). Fewer areas are returned
+/// when the rest would not fit.
+pub fn toast_areas(
+    frame: Rect,
+    count: usize,
+    position: ToastPosition,
+    width: u16,
+    exclusions: &[Rect],
+) -> Vec<Rect> {
+    let width = width.min(frame.width.saturating_sub(4));
+    let x = match position {
+        ToastPosition::TopRight | ToastPosition::BottomRight => {
+            frame.width.saturating_sub(width + 2)
+        }
+        ToastPosition::BottomCenter => frame.width.saturating_sub(width) / 2,
+    };
+    let from_top = position == ToastPosition::TopRight;
+    let mut y = if from_top {
+        frame.y + 1
+    } else {
+        frame.bottom().saturating_sub(TOAST_HEIGHT + 1)
+    };
+
+    let mut areas = Vec::new();
+    while areas.len() < count {
+        if y < frame.y || y + TOAST_HEIGHT > frame.bottom() {
+            break;
+        }
+        let area = Rect::new(x, y, width, TOAST_HEIGHT);
+        match exclusions.iter().find(|zone| zone.intersects(area)) {
+            // Move past the zone and try again
+            Some(zone) if from_top => y = zone.bottom(),
+            Some(zone) => match zone.y.checked_sub(TOAST_HEIGHT) {
+                Some(above) => y = above,
+                None => break,
+            },
+            None => {
+                areas.push(area);
+                if from_top {
+                    y += TOAST_HEIGHT;
+                } else if let Some(above) = y.checked_sub(TOAST_HEIGHT) {
+                    y = above;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+    areas
+}
+
+/// The first `chars` characters of `text`.
+fn take_chars(text: &str, chars: usize) -> &str {
+    text.char_indices().nth(chars).map_or(text, |(i, _)| &text[..i])
+}
+
+/// Render toasts in their corner, around the `exclusions`.
+pub fn render_toasts(f: &mut Frame, toast_state: &ToastState, exclusions: &[Rect]) {
     if toast_state.is_empty() {
         return;
     }
 
-    let frame_area = f.size();
-    
-    // Toast dimensions
-    let toast_width = 40u16.min(frame_area.width.saturating_sub(4));
-    let toast_height = 3u16; // Border + content + border
-    let spacing = 0u16; // Vertical spacing between toasts
-    
-    // Starting position (top-right corner)
-    let start_x = frame_area.width.saturating_sub(toast_width + 2);
-    let start_y = 1u16;
-    
-    for (i, toast) in toast_state.toasts.iter().enumerate() {
-        let y = start_y + (i as u16) * (toast_height + spacing);
-        
-        // Don't render if it would go off screen
-        if y + toast_height > frame_area.height {
-            break;
+    let areas = toast_areas(
+        f.size(),
+        toast_state.toasts.len(),
+        toast_state.position,
+        toast_state.width,
+        exclusions,
+    );
+    for (toast, mut toast_area) in toast_state.toasts.iter().zip(areas) {
+        let presence = match toast_state.animation {
+            ToastAnimation::None => 1.0,
+            _ => toast.presence(toast.created_at.elapsed()),
+        };
+
+        // Sliding toasts come in from the right edge, or open out from the middle
+        if toast_state.animation == ToastAnimation::Slide {
+            let hidden = ((1.0 - presence) * toast_area.width as f32) as u16;
+            if toast_state.position == ToastPosition::BottomCenter {
+                toast_area.x += hidden / 2;
+            } else {
+                toast_area.x += hidden;
+            }
+            toast_area.width -= hidden;
+            if toast_area.width < 3 {
+                continue;
+            }
         }
-        
-        let toast_area = Rect::new(start_x, y, toast_width, toast_height);
-        
+
         // Clear the area behind the toast
         f.render_widget(Clear, toast_area);
-        
+
         // Build toast content
         let color = toast.level.color();
         let prefix = toast.level.prefix();
-        
+
         // Truncate message if too long
-        let max_msg_len = (toast_width as usize).saturating_sub(prefix.len() + 5);
-        let display_msg = if toast.message.len() > max_msg_len {
-            format!("{}...", &toast.message[..max_msg_len.saturating_sub(3)])
+        let max_msg_len = (toast_area.width as usize).saturating_sub(prefix.len() + 5);
+        let mut display_msg = if toast.message.chars().count() > max_msg_len {
+            format!("{}...", take_chars(&toast.message, max_msg_len.saturating_sub(3)))
         } else {
             toast.message.clone()
         };
-        
+        // Fading toasts type their message in and erase it again
+        if toast_state.animation == ToastAnimation::Fade {
+            let shown = (display_msg.chars().count() as f32 * presence).ceil() as usize;
+            display_msg = take_chars(&display_msg, shown).to_string();
+        }
+
         let content = Line::from(vec![
             Span::styled(prefix, Style::default().fg(color)),
             Span::raw(" "),
             Span::styled(display_msg, Style::default().fg(Color::White)),
         ]);
-        
+
         let block = Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(color))
             .style(Style::default().bg(Color::Black));
-        
+
         let paragraph = Paragraph::new(content).block(block);
-        
+
         f.render_widget(paragraph, toast_area);
     }
 }
@@ -441,4 +540,43 @@ mod tests {
         assert_eq!(toast.level, ToastLevel::Error);
         assert_eq!(toast.duration, Duration::from_secs(8));
     }
+
+    #[test]
+    fn test_toast_presence_animates_in_and_out() {
+        let toast = Toast::with_duration(1, "Hi", ToastLevel::Info, Duration::from_secs(3));
+        assert_eq!(toast.presence(Duration::ZERO), 0.0);
+        assert_eq!(toast.presence(ANIMATION / 2), 0.5);
+        assert_eq!(toast.presence(Duration::from_secs(1)), 1.0);
+        assert_eq!(toast.presence(Duration::from_secs(3)), 0.0);
+    }
+
+    #[test]
+    fn test_toast_areas_stack_from_the_corner() {
+        let frame = Rect::new(0, 0, 100, 30);
+        let areas = toast_areas(frame, 2, ToastPosition::TopRight, 40, &[]);
+        assert_eq!(areas, [Rect::new(58, 1, 40, 3), Rect::new(58, 4, 40, 3)]);
+
+        let areas = toast_areas(frame, 1, ToastPosition::BottomCenter, 40, &[]);
+        assert_eq!(areas, [Rect::new(30, 26, 40, 3)]);
+    }
+
+    #[test]
+    fn test_toast_areas_skip_exclusions() {
+        let frame = Rect::new(0, 0, 100, 30);
+        // Input and status bar at the bottom, a dialog in the middle
+        let input = Rect::new(0, 25, 100, 5);
+        let dialog = Rect::new(10, 3, 80, 18);
+
+        let areas = toast_areas(frame, 3, ToastPosition::BottomRight, 40, &[input, dialog]);
+        assert_eq!(areas, [Rect::new(58, 22, 40, 3), Rect::new(58, 0, 40, 3)]);
+
+        let areas = toast_areas(frame, 3, ToastPosition::TopRight, 40, &[dialog, input]);
+        assert_eq!(areas, [Rect::new(58, 21, 40, 3)]);
+    }
+
+    #[test]
+    fn test_long_multibyte_message_is_cut_on_a_character() {
+        assert_eq!(take_chars("héllo", 2), "hé");
+        assert_eq!(take_chars("hé", 5), "hé");
+    }
 }
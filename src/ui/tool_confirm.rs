@@ -0,0 +1,188 @@
+//! Confirmation dialog for tool calls set to `ask`.
+//!
+//! Shows what the call will do and lets the user allow or deny it, optionally
+//! remembering the answer for the current project.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use super::render::centered_rect;
+use crate::llm::ToolCall;
+
+/// Result of handling a key event in the dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolConfirmAction {
+    /// Keep the dialog open.
+    Continue,
+    /// Close with an answer.
+    Answer { allow: bool, remember: bool },
+}
+
+/// A tool call waiting for permission.
+#[derive(Debug, Clone)]
+pub struct ToolConfirmation {
+    /// The call to run once allowed
+    pub call: ToolCall,
+    /// What the call will do
+    pub summary: String,
+    /// Whether "Allow" (rather than "Deny") is highlighted
+    pub allow: bool,
+    /// Remember the answer for this project
+    pub remember: bool,
+}
+
+impl ToolConfirmation {
+    /// Ask about `call`, with "Deny" highlighted so Enter is the safe choice.
+    pub fn new(call: ToolCall, summary: String) -> Self {
+        Self {
+            call,
+            summary,
+            allow: false,
+            remember: false,
+        }
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> ToolConfirmAction {
+        let answer = |allow| ToolConfirmAction::Answer {
+            allow,
+            remember: self.remember,
+        };
+        match key {
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Tab => {
+                self.allow = !self.allow;
+                ToolConfirmAction::Continue
+            }
+            KeyCode::Char(' ') | KeyCode::Char('r') => {
+                self.remember = !self.remember;
+                ToolConfirmAction::Continue
+            }
+            KeyCode::Char('y') => answer(true),
+            KeyCode::Char('n') | KeyCode::Esc => answer(false),
+            KeyCode::Enter => answer(self.allow),
+            _ => ToolConfirmAction::Continue,
+        }
+    }
+}
+
+/// Render the tool confirmation dialog.
+pub fn render_tool_confirmation(f: &mut Frame, confirmation: &ToolConfirmation) {
+    let area = centered_rect(60, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Run {}? ", confirmation.call.name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::vertical([
+        Constraint::Min(2),    // What the call does
+        Constraint::Length(1), // Allow / Deny
+        Constraint::Length(1), // Remember
+        Constraint::Length(1), // Spacer
+        Constraint::Length(1), // Hints
+    ])
+    .split(inner);
+
+    let summary = Paragraph::new(confirmation.summary.as_str())
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false });
+    f.render_widget(summary, chunks[0]);
+
+    let button = |label: &'static str, selected: bool| {
+        let style = if selected {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        Span::styled(format!(" {} ", label), style)
+    };
+    let buttons = Line::from(vec![
+        button("Allow", confirmation.allow),
+        Span::raw("  "),
+        button("Deny", !confirmation.allow),
+    ]);
+    f.render_widget(Paragraph::new(buttons), chunks[1]);
+
+    let checkbox = if confirmation.remember { "[x]" } else { "[ ]" };
+    f.render_widget(
+        Paragraph::new(format!("{} Remember my choice for this project", checkbox))
+            .style(Style::default().fg(Color::Gray)),
+        chunks[2],
+    );
+
+    let hints = Line::from(vec![
+        Span::styled("[←→]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Choose  "),
+        Span::styled("[Space]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Remember  "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Confirm  "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
+        Span::raw(" Deny"),
+    ]);
+    f.render_widget(
+        Paragraph::new(hints).style(Style::default().fg(Color::Gray)),
+        chunks[4],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn confirmation() -> ToolConfirmation {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "write_file".to_string(),
+            arguments: serde_json::json!({}),
+        };
+        ToolConfirmation::new(call, "write src/main.rs".to_string())
+    }
+
+    #[test]
+    fn test_enter_denies_by_default() {
+        let mut dialog = confirmation();
+        assert_eq!(
+            dialog.handle_key(KeyCode::Enter),
+            ToolConfirmAction::Answer {
+                allow: false,
+                remember: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_allow_and_remember() {
+        let mut dialog = confirmation();
+        dialog.handle_key(KeyCode::Left);
+        dialog.handle_key(KeyCode::Char(' '));
+        assert_eq!(
+            dialog.handle_key(KeyCode::Enter),
+            ToolConfirmAction::Answer {
+                allow: true,
+                remember: true
+            }
+        );
+        assert_eq!(
+            dialog.handle_key(KeyCode::Esc),
+            ToolConfirmAction::Answer {
+                allow: false,
+                remember: true
+            }
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Full-screen message viewer.
+//!
+//! Shows one message exactly as received, without wrapping, with line
+//! numbers and horizontal scrolling, so wide code and tables stay readable.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Columns moved per Left/Right press.
+const H_STEP: usize = 8;
+
+/// Lines moved per Page Up/Down press.
+const PAGE: usize = 20;
+
+/// Result of handling a key event in the viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewerAction {
+    /// Keep the viewer open.
+    Continue,
+    /// Close the viewer.
+    Close,
+}
+
+/// State of the message viewer.
+#[derive(Debug, Clone)]
+pub struct MessageViewer {
+    /// Title shown in the border, e.g. the role
+    pub title: String,
+    /// Raw lines of the message
+    pub lines: Vec<String>,
+    /// First visible line
+    pub scroll_y: usize,
+    /// First visible column
+    pub scroll_x: usize,
+}
+
+impl MessageViewer {
+    /// Open a viewer on `content`.
+    pub fn new(title: impl Into<String>, content: &str) -> Self {
+        Self {
+            title: title.into(),
+            lines: content.lines().map(|l| l.replace('\t', "    ")).collect(),
+            scroll_y: 0,
+            scroll_x: 0,
+        }
+    }
+
+    /// Width of the widest line.
+    fn max_width(&self) -> usize {
+        self.lines.iter().map(|l| l.width()).max().unwrap_or(0)
+    }
+
+    /// Handle a key press.
+    pub fn handle_key(&mut self, key: KeyCode) -> ViewerAction {
+        let last_line = self.lines.len().saturating_sub(1);
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => return ViewerAction::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_y = self.scroll_y.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.scroll_y = (self.scroll_y + 1).min(last_line)
+            }
+            KeyCode::PageUp => self.scroll_y = self.scroll_y.saturating_sub(PAGE),
+            KeyCode::PageDown => self.scroll_y = (self.scroll_y + PAGE).min(last_line),
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.scroll_x = self.scroll_x.saturating_sub(H_STEP)
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.scroll_x = (self.scroll_x + H_STEP).min(self.max_width().saturating_sub(1))
+            }
+            KeyCode::Home => {
+                self.scroll_x = 0;
+                self.scroll_y = 0;
+            }
+            KeyCode::End => self.scroll_y = last_line,
+            _ => {}
+        }
+        ViewerAction::Continue
+    }
+}
+
+/// Render the viewer over the whole screen.
+pub fn render_message_viewer(f: &mut Frame, viewer: &MessageViewer) {
+    let area = f.size();
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", viewer.title))
+        .title_bottom(Span::styled(
+            " ↑↓ PgUp/PgDn scroll · ←→ pan · Esc close ",
+            Style::default().fg(Color::DarkGray),
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let gutter = viewer.lines.len().max(1).to_string().len() as u16 + 1;
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(gutter + 1), Constraint::Min(1)])
+        .split(inner);
+
+    let numbers: Vec<Line> = (1..=viewer.lines.len())
+        .map(|n| {
+            Line::from(Span::styled(
+                format!("{:>width$} ", n, width = gutter as usize),
+                Style::default().fg(Color::DarkGray),
+            ))
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(numbers).scroll((viewer.scroll_y as u16, 0)),
+        columns[0],
+    );
+
+    let text: Vec<Line> = viewer
+        .lines
+        .iter()
+        .map(|l| Line::from(l.as_str()))
+        .collect();
+    f.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::White))
+            .scroll((viewer.scroll_y as u16, viewer.scroll_x as u16)),
+        columns[1],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrolling_is_clamped_to_content() {
+        let mut viewer = MessageViewer::new("Assistant", "short\na much longer line here\nend");
+        viewer.handle_key(KeyCode::Up);
+        viewer.handle_key(KeyCode::Left);
+        assert_eq!((viewer.scroll_y, viewer.scroll_x), (0, 0));
+
+        viewer.handle_key(KeyCode::PageDown);
+        assert_eq!(viewer.scroll_y, 2);
+
+        for _ in 0..10 {
+            viewer.handle_key(KeyCode::Right);
+        }
+        assert_eq!(viewer.scroll_x, "a much longer line here".len() - 1);
+
+        viewer.handle_key(KeyCode::Home);
+        assert_eq!((viewer.scroll_y, viewer.scroll_x), (0, 0));
+        assert_eq!(viewer.handle_key(KeyCode::Esc), ViewerAction::Close);
+    }
+
+    #[test]
+    fn test_tabs_are_expanded() {
+        let viewer = MessageViewer::new("You", "\tindented");
+        assert_eq!(viewer.lines, vec!["    indented"]);
+    }
+}
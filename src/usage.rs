@@ -0,0 +1,545 @@
+//! Usage ledger: one row per completed request, kept in SQLite.
+//!
+//! Stored in `~/.local/share/scry-cli/usage.db`. Rows record when a request
+//! was made, to which provider and model, its token counts and cost, and the
+//! session it belonged to. Monthly reports (`scry usage`) and spending checks
+//! are queries over this table.
+//!
+//! `[budget]` caps are checked against the same rows: what a provider has
+//! spent today and this month, in UTC.
+//!
+//! Token counts are the ones the provider reported, or estimated from text
+//! length when it reported none. Costs come from the [`Pricing`] table and
+//! are NULL for models it has no price for.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::app::estimate_tokens;
+use crate::config::BudgetConfig;
+use crate::llm::{Provider, TokenUsage};
+use crate::pricing::Pricing;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS usage (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        input_tokens INTEGER NOT NULL,
+        output_tokens INTEGER NOT NULL,
+        cost REAL,
+        session_id TEXT
+    );
+    CREATE INDEX IF NOT EXISTS usage_timestamp ON usage(timestamp);
+";
+
+/// One completed request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Provider storage key, e.g. `anthropic`
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost in USD, if known
+    pub cost: Option<f64>,
+    /// Saved session the request belonged to
+    pub session_id: Option<String>,
+}
+
+impl UsageRecord {
+    /// A record for a request made now, with tokens estimated from text
+    /// sizes and no cost until [`UsageRecord::priced`].
+    pub fn estimated(
+        provider: Provider,
+        model: &str,
+        input_bytes: usize,
+        output_bytes: usize,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            provider: provider.storage_key().to_string(),
+            model: model.to_string(),
+            input_tokens: estimate_tokens(input_bytes) as u64,
+            output_tokens: estimate_tokens(output_bytes) as u64,
+            cost: None,
+            session_id: None,
+        }
+    }
+
+    /// Replace the estimated token counts with the ones the provider reported.
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.input_tokens = usage.prompt_tokens;
+        self.output_tokens = usage.completion_tokens;
+        self
+    }
+
+    /// Set the cost from the token counts and `pricing`.
+    pub fn priced(mut self, pricing: &Pricing) -> Self {
+        let usage = TokenUsage {
+            prompt_tokens: self.input_tokens,
+            completion_tokens: self.output_tokens,
+        };
+        self.cost = self
+            .provider
+            .parse()
+            .ok()
+            .and_then(|provider| pricing.cost(provider, &self.model, usage));
+        self
+    }
+}
+
+/// Usage summed over one provider and model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Sum of the known costs
+    pub cost: f64,
+    /// Requests whose cost is unknown
+    pub unpriced: u64,
+}
+
+impl UsageTotals {
+    /// Count one more request.
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        match record.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced += 1,
+        }
+    }
+
+    /// The known cost, `+` if some requests are unpriced, `-` if none are priced.
+    pub fn cost_label(&self) -> String {
+        if self.unpriced == self.requests {
+            "-".to_string()
+        } else if self.unpriced > 0 {
+            format!("{}+", format_cost(self.cost))
+        } else {
+            format_cost(self.cost)
+        }
+    }
+}
+
+/// A provider's spending measured against one of its `[budget]` caps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendCheck {
+    /// Provider storage key
+    pub provider: String,
+    /// `daily` or `monthly`
+    pub period: &'static str,
+    /// Known cost so far in the period, USD
+    pub spent: f64,
+    /// The cap, USD
+    pub limit: f64,
+}
+
+impl SpendCheck {
+    /// Whether the cap is reached.
+    pub fn exceeded(&self) -> bool {
+        self.spent >= self.limit
+    }
+
+    /// Which cap this is, e.g. "anthropic daily".
+    pub fn cap(&self) -> String {
+        format!("{} {}", self.provider, self.period)
+    }
+
+    /// One line for a toast or dialog.
+    pub fn message(&self) -> String {
+        if self.exceeded() {
+            format!(
+                "{} {} spending cap reached: {} of {}",
+                self.provider,
+                self.period,
+                format_cost(self.spent),
+                format_cost(self.limit)
+            )
+        } else {
+            format!(
+                "{} has spent {} of its {} {} cap",
+                self.provider,
+                format_cost(self.spent),
+                self.period,
+                format_cost(self.limit)
+            )
+        }
+    }
+}
+
+/// The usage database.
+pub struct UsageLedger {
+    conn: Connection,
+}
+
+impl UsageLedger {
+    /// Returns `~/.local/share/scry-cli/usage.db` on Linux/macOS.
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir =
+            dirs::data_local_dir().context("Could not determine local data directory")?;
+        Ok(data_dir.join("scry-cli").join("usage.db"))
+    }
+
+    /// Open the ledger at the default path.
+    pub fn open_default() -> Result<Self> {
+        Self::open(Self::default_path()?)
+    }
+
+    /// Open or create the ledger at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open usage database: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// A ledger that lives only in memory, for tests.
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create usage table")?;
+        Ok(Self { conn })
+    }
+
+    /// Append a row.
+    pub fn record(&self, record: &UsageRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage
+                (timestamp, provider, model, input_tokens, output_tokens, cost, session_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                timestamp_text(record.timestamp),
+                record.provider,
+                record.model,
+                record.input_tokens as i64,
+                record.output_tokens as i64,
+                record.cost,
+                record.session_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Totals per provider and model for requests in `[from, to)`, most used first.
+    pub fn totals_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<UsageTotals>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT provider, model, COUNT(*), SUM(input_tokens), SUM(output_tokens),
+                    COALESCE(SUM(cost), 0), COUNT(*) - COUNT(cost)
+             FROM usage WHERE timestamp >= ?1 AND timestamp < ?2
+             GROUP BY provider, model
+             ORDER BY SUM(input_tokens) + SUM(output_tokens) DESC, provider, model",
+        )?;
+        let rows = stmt.query_map(params![timestamp_text(from), timestamp_text(to)], |row| {
+            Ok(UsageTotals {
+                provider: row.get(0)?,
+                model: row.get(1)?,
+                requests: row.get::<_, i64>(2)? as u64,
+                input_tokens: row.get::<_, i64>(3)? as u64,
+                output_tokens: row.get::<_, i64>(4)? as u64,
+                cost: row.get(5)?,
+                unpriced: row.get::<_, i64>(6)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Totals per provider and model over every recorded request.
+    pub fn totals(&self) -> Result<Vec<UsageTotals>> {
+        let from = Utc.timestamp_opt(0, 0).single().context("Epoch out of range")?;
+        let to = Utc
+            .with_ymd_and_hms(9999, 12, 31, 0, 0, 0)
+            .single()
+            .context("Year 9999 out of range")?;
+        self.totals_between(from, to)
+    }
+
+    /// Total known cost of requests in `[from, to)`, for budget checks.
+    pub fn cost_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<f64> {
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(SUM(cost), 0) FROM usage WHERE timestamp >= ?1 AND timestamp < ?2",
+            params![timestamp_text(from), timestamp_text(to)],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Known cost of `provider`'s requests in `[from, to)`.
+    pub fn provider_cost_between(
+        &self,
+        provider: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<f64> {
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(SUM(cost), 0) FROM usage
+             WHERE provider = ?1 AND timestamp >= ?2 AND timestamp < ?3",
+            params![provider, timestamp_text(from), timestamp_text(to)],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// The cap `provider` has used the largest share of at `now`, once
+    /// spending reaches `warn_at` of it; `None` while well under every cap.
+    pub fn check_budget(
+        &self,
+        provider: &str,
+        budget: &BudgetConfig,
+        now: DateTime<Utc>,
+    ) -> Result<Option<SpendCheck>> {
+        let Some(limit) = budget.providers.get(provider) else {
+            return Ok(None);
+        };
+        let mut worst: Option<SpendCheck> = None;
+        let periods = [
+            ("daily", limit.daily, day_range(now)),
+            ("monthly", limit.monthly, month_range(&now.format("%Y-%m").to_string())?),
+        ];
+        for (period, cap, (from, to)) in periods {
+            let Some(cap) = cap else { continue };
+            let check = SpendCheck {
+                provider: provider.to_string(),
+                period,
+                spent: self.provider_cost_between(provider, from, to)?,
+                limit: cap,
+            };
+            let share = |c: &SpendCheck| c.spent / c.limit.max(f64::MIN_POSITIVE);
+            if share(&check) >= budget.warn_at
+                && worst.as_ref().is_none_or(|w| share(&check) > share(w))
+            {
+                worst = Some(check);
+            }
+        }
+        Ok(worst)
+    }
+}
+
+/// A cost in USD, with more places for amounts under a cent.
+pub fn format_cost(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        format!("${:.4}", cost)
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+/// Fixed-width UTC timestamps, so text order is time order.
+fn timestamp_text(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Start and end (exclusive) of a `YYYY-MM` month in UTC.
+pub fn month_range(month: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid month '{}', expected YYYY-MM", month))?;
+    let end = if start.month() == 12 {
+        NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+    }
+    .context("Month out of range")?;
+    let midnight =
+        |d: NaiveDate| Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap_or_default());
+    Ok((midnight(start), midnight(end)))
+}
+
+/// Start and end (exclusive) of the UTC day holding `time`.
+pub fn day_range(time: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let midnight = time.date_naive().and_hms_opt(0, 0, 0).unwrap_or_default();
+    let start = Utc.from_utc_datetime(&midnight);
+    (start, start + Duration::days(1))
+}
+
+/// The current month as `YYYY-MM`.
+pub fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// Render totals as a plain-text table with a total row.
+pub fn format_table(month: &str, totals: &[UsageTotals]) -> String {
+    if totals.is_empty() {
+        return format!("No usage recorded in {}\n", month);
+    }
+    let sum = UsageTotals {
+        provider: "Total".to_string(),
+        model: String::new(),
+        requests: totals.iter().map(|t| t.requests).sum(),
+        input_tokens: totals.iter().map(|t| t.input_tokens).sum(),
+        output_tokens: totals.iter().map(|t| t.output_tokens).sum(),
+        cost: totals.iter().map(|t| t.cost).sum(),
+        unpriced: totals.iter().map(|t| t.unpriced).sum(),
+    };
+    let label = |t: &UsageTotals| {
+        if t.model.is_empty() {
+            t.provider.clone()
+        } else {
+            format!("{}/{}", t.provider, t.model)
+        }
+    };
+    let width = totals
+        .iter()
+        .map(|t| label(t).len())
+        .max()
+        .unwrap_or(0)
+        .max("Model".len());
+
+    let mut out = format!("Usage for {}\n\n", month);
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}",
+        "Model", "Requests", "Input tok", "Output tok", "Cost"
+    );
+    for t in totals.iter().chain(std::iter::once(&sum)) {
+        if t.model.is_empty() {
+            let _ = writeln!(out, "{}", "-".repeat(width + 52));
+        }
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}",
+            label(t),
+            t.requests,
+            t.input_tokens,
+            t.output_tokens,
+            t.cost_label()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        day: u32,
+        provider: &str,
+        model: &str,
+        tokens: u64,
+        cost: Option<f64>,
+    ) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc.with_ymd_and_hms(2025, 1, day, 12, 0, 0).unwrap(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            input_tokens: tokens,
+            output_tokens: tokens / 2,
+            cost,
+            session_id: Some("s1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_month_range() {
+        let (start, end) = month_range("2024-12").unwrap();
+        assert_eq!(timestamp_text(start), "2024-12-01T00:00:00Z");
+        assert_eq!(timestamp_text(end), "2025-01-01T00:00:00Z");
+        assert!(month_range("2024-13").is_err());
+        assert!(month_range("january").is_err());
+    }
+
+    #[test]
+    fn test_totals_group_by_model_within_month() {
+        let ledger = UsageLedger::in_memory().unwrap();
+        ledger
+            .record(&record(3, "ollama", "qwen3:4b", 100, Some(0.0)))
+            .unwrap();
+        ledger
+            .record(&record(5, "anthropic", "claude", 1000, Some(0.5)))
+            .unwrap();
+        ledger
+            .record(&record(9, "anthropic", "claude", 1000, None))
+            .unwrap();
+        ledger
+            .record(&record(31, "anthropic", "claude", 10, Some(1.0)))
+            .unwrap();
+        // Outside the month
+        let mut february = record(1, "anthropic", "claude", 5000, Some(9.0));
+        february.timestamp = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        ledger.record(&february).unwrap();
+
+        let (from, to) = month_range("2025-01").unwrap();
+        let totals = ledger.totals_between(from, to).unwrap();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(ledger.totals().unwrap()[0].requests, 4);
+        assert_eq!(totals[0].model, "claude");
+        assert_eq!(totals[0].requests, 3);
+        assert_eq!(totals[0].input_tokens, 2010);
+        assert_eq!(totals[0].unpriced, 1);
+        assert_eq!(totals[0].cost, 1.5);
+        assert_eq!(ledger.cost_between(from, to).unwrap(), 1.5);
+
+        let table = format_table("2025-01", &totals);
+        assert!(table.contains("anthropic/claude"));
+        assert!(table.contains("$1.50+"));
+        assert!(table.lines().last().unwrap().starts_with("Total"));
+    }
+
+    #[test]
+    fn test_budget_checks_the_cap_closest_to_its_limit() {
+        let ledger = UsageLedger::in_memory().unwrap();
+        ledger
+            .record(&record(3, "anthropic", "claude", 1000, Some(4.0)))
+            .unwrap();
+        ledger
+            .record(&record(9, "anthropic", "claude", 1000, Some(0.5)))
+            .unwrap();
+        ledger
+            .record(&record(9, "openai", "gpt-4o", 1000, Some(7.0)))
+            .unwrap();
+
+        let mut budget = BudgetConfig::default();
+        budget.providers.insert(
+            "anthropic".to_string(),
+            crate::config::SpendLimit {
+                daily: Some(2.0),
+                monthly: Some(5.0),
+            },
+        );
+        let now = Utc.with_ymd_and_hms(2025, 1, 9, 18, 0, 0).unwrap();
+        // 0.5 of 2.0 today, 4.5 of 5.0 this month
+        let check = ledger.check_budget("anthropic", &budget, now).unwrap().unwrap();
+        assert_eq!((check.period, check.spent), ("monthly", 4.5));
+        assert!(!check.exceeded());
+        assert_eq!(check.message(), "anthropic has spent $4.50 of its monthly $5.00 cap");
+
+        ledger
+            .record(&record(9, "anthropic", "claude", 1000, Some(2.0)))
+            .unwrap();
+        let check = ledger.check_budget("anthropic", &budget, now).unwrap().unwrap();
+        assert!(check.exceeded());
+        assert_eq!(check.cap(), "anthropic monthly");
+
+        // Under the warning share, and providers without caps
+        budget.warn_at = 1.5;
+        assert_eq!(ledger.check_budget("anthropic", &budget, now).unwrap(), None);
+        assert_eq!(ledger.check_budget("openai", &budget, now).unwrap(), None);
+    }
+
+    #[test]
+    fn test_empty_month() {
+        assert_eq!(
+            format_table("2025-03", &[]),
+            "No usage recorded in 2025-03\n"
+        );
+    }
+}
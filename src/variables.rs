@@ -0,0 +1,198 @@
+//! Prompt variables: `${env:VAR}` and `${file:path}` interpolation.
+//!
+//! Placeholders are expanded at send time, and only for names listed in the
+//! `[variables]` allowlist, so a prompt can never read an arbitrary secret or
+//! file by accident. `$${...}` is left as a literal `${...}`.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::config::VariablesConfig;
+
+/// One expanded placeholder, for the preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expanded {
+    /// The placeholder as written, e.g. `${env:USER}`
+    pub placeholder: String,
+    /// Short description of the value
+    pub summary: String,
+}
+
+/// Text with its placeholders replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    pub values: Vec<Expanded>,
+}
+
+/// Whether `text` contains any `${env:...}` or `${file:...}` placeholder.
+pub fn has_placeholders(text: &str) -> bool {
+    placeholders(text).next().is_some()
+}
+
+/// Byte ranges of the placeholders [`expand`] would replace, for highlighting.
+pub fn placeholder_ranges(text: &str) -> Vec<Range<usize>> {
+    placeholders(text).map(|(start, end, ..)| start..end).collect()
+}
+
+/// Byte ranges of the placeholders in `text`, with their kind and argument.
+fn placeholders(text: &str) -> impl Iterator<Item = (usize, usize, &str, &str)> {
+    let mut rest = 0;
+    std::iter::from_fn(move || loop {
+        let start = rest + text[rest..].find("${")?;
+        let escaped = text[..start].ends_with('$');
+        let len = text[start..].find('}')?;
+        let end = start + len + 1;
+        rest = start + 2;
+        if escaped {
+            continue;
+        }
+        let inner = &text[start + 2..end - 1];
+        if let Some((kind, arg)) = inner.split_once(':') {
+            if matches!(kind, "env" | "file") && !arg.is_empty() {
+                rest = end;
+                return Some((start, end, kind, arg));
+            }
+        }
+    })
+}
+
+impl VariablesConfig {
+    /// Whether the environment variable `name` may be read.
+    pub fn allows_env(&self, name: &str) -> bool {
+        self.env
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => pattern == name,
+            })
+    }
+
+    /// Whether `path` (already canonical) is inside an allowed file or directory.
+    pub fn allows_file(&self, path: &Path) -> bool {
+        self.files
+            .iter()
+            .filter_map(|allowed| resolve(allowed).canonicalize().ok())
+            .any(|allowed| path.starts_with(allowed))
+    }
+}
+
+/// Expand a leading `~` to the home directory. Other paths are returned as
+/// they are, so relative ones resolve against the working directory when used.
+pub(crate) fn resolve(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir().map_or_else(|| path.to_path_buf(), |home| home.join(rest)),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn expand_env(name: &str, config: &VariablesConfig) -> Result<(String, String)> {
+    if !config.allows_env(name) {
+        bail!(
+            "${{env:{}}} is not allowed; add it to [variables] env",
+            name
+        );
+    }
+    let value = std::env::var(name).with_context(|| format!("${{env:{}}} is not set", name))?;
+    let summary = format!("{} chars", value.chars().count());
+    Ok((value, summary))
+}
+
+fn expand_file(path: &str, config: &VariablesConfig) -> Result<(String, String)> {
+    let canonical = resolve(Path::new(path))
+        .canonicalize()
+        .with_context(|| format!("${{file:{}}}: file not found", path))?;
+    if !config.allows_file(&canonical) {
+        bail!(
+            "${{file:{}}} is not allowed; add it to [variables] files",
+            path
+        );
+    }
+    let contents = fs::read_to_string(&canonical)
+        .with_context(|| format!("${{file:{}}}: cannot read file as text", path))?;
+    let summary = format!(
+        "{} lines, {} bytes",
+        contents.lines().count(),
+        contents.len()
+    );
+    Ok((contents, summary))
+}
+
+/// Replace every placeholder in `text`, failing on the first one that is not
+/// allowed or cannot be read.
+pub fn expand(text: &str, config: &VariablesConfig) -> Result<Expansion> {
+    let mut out = String::with_capacity(text.len());
+    let mut values = Vec::new();
+    let mut copied = 0;
+    for (start, end, kind, arg) in placeholders(text) {
+        let (value, summary) = match kind {
+            "env" => expand_env(arg, config)?,
+            _ => expand_file(arg, config)?,
+        };
+        out.push_str(&text[copied..start].replace("$${", "${"));
+        out.push_str(&value);
+        copied = end;
+        values.push(Expanded {
+            placeholder: text[start..end].to_string(),
+            summary,
+        });
+    }
+    out.push_str(&text[copied..].replace("$${", "${"));
+    Ok(Expansion { text: out, values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(env: &[&str], files: Vec<PathBuf>) -> VariablesConfig {
+        VariablesConfig {
+            env: env.iter().map(|s| s.to_string()).collect(),
+            files,
+        }
+    }
+
+    #[test]
+    fn test_env_requires_allowlist() {
+        std::env::set_var("SCRY_TEST_PROJECT", "scry");
+        let allowed = config(&["SCRY_TEST_*"], Vec::new());
+        let expansion = expand("Project: ${env:SCRY_TEST_PROJECT}.", &allowed).unwrap();
+        assert_eq!(expansion.text, "Project: scry.");
+        assert_eq!(expansion.values[0].placeholder, "${env:SCRY_TEST_PROJECT}");
+
+        let denied = config(&["OTHER"], Vec::new());
+        assert!(expand("${env:SCRY_TEST_PROJECT}", &denied).is_err());
+        assert!(expand("${env:SCRY_TEST_UNSET_VAR}", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_files_must_be_inside_allowed_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        fs::write(notes.join("todo.md"), "- ship it\n").unwrap();
+        fs::write(dir.path().join("secret.txt"), "hunter2").unwrap();
+        let cfg = config(&[], vec![notes.clone()]);
+
+        let prompt = format!("Review:\n${{file:{}}}", notes.join("todo.md").display());
+        let expansion = expand(&prompt, &cfg).unwrap();
+        assert_eq!(expansion.text, "Review:\n- ship it\n");
+        assert_eq!(expansion.values[0].summary, "1 lines, 10 bytes");
+
+        // `..` cannot climb out of an allowed directory
+        let escape = format!("${{file:{}/../secret.txt}}", notes.display());
+        assert!(expand(&escape, &cfg).is_err());
+    }
+
+    #[test]
+    fn test_other_text_is_untouched() {
+        let cfg = VariablesConfig::default();
+        assert!(!has_placeholders("cost is ${price} or $${env:HOME}"));
+        assert_eq!(placeholder_ranges("a ${env:HOME} ${price}"), vec![2..13]);
+        let expansion = expand("cost is ${price} or $${env:HOME}", &cfg).unwrap();
+        assert_eq!(expansion.text, "cost is ${price} or ${env:HOME}");
+        assert!(expansion.values.is_empty());
+    }
+}
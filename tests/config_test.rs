@@ -1,6 +1,9 @@
+use scry_cli::appearance::Appearance;
 use scry_cli::config::{
-    BehaviorConfig, ColorConfig, Config, LlmConfigFile, ThemeConfig, WelcomeConfig,
+    AppearanceMode, BackpressurePolicy, BehaviorConfig, ClipboardBackend, ColorConfig, Config, ContextStrategy, FsyncPolicy, LlmConfigFile, MessageAlign, PROJECT_FILE,
+    MessageStyle, Permission, PreprocessStep, RetryConfig, SigningConfig, StorageBackend, StreamConfig, SyncBackend, ThemeConfig, TimeoutConfig, ToastAnimation, ToastPosition, WelcomeConfig,
 };
+use scry_cli::llm::{Provider, ReasoningEffort};
 use std::fs;
 use tempfile::TempDir;
 
@@ -61,6 +64,13 @@ fn test_behavior_config_default() {
     assert_eq!(behavior.animation_chars_per_frame, 3);
     assert_eq!(behavior.animation_frame_ms, 16);
     assert_eq!(behavior.idle_poll_ms, 100);
+    assert!(!behavior.minimap);
+    assert_eq!(behavior.submit_debounce_ms, 300);
+    assert_eq!(behavior.confirm_send_tokens, 32_000);
+    assert!(behavior.emoji_shortcodes);
+    assert_eq!(behavior.auto_continue, 2);
+    assert_eq!(behavior.max_reply_tokens, 16_000);
+    assert!(!behavior.show_thinking);
 }
 
 #[test]
@@ -104,6 +114,7 @@ fn test_theme_config_default() {
     // Background colors
     assert_eq!(theme.bg_primary, [20, 20, 25]);
     assert_eq!(theme.bg_secondary, [30, 30, 35]);
+    assert_eq!(theme.fg_primary, [255, 255, 255]);
 }
 
 #[test]
@@ -167,6 +178,27 @@ effect = "decrypt"
     assert_eq!(config.welcome.effect, "decrypt");
 }
 
+#[test]
+fn test_theme_message_layout_from_toml() {
+    let config: Config = toml::from_str(
+        r#"
+[theme]
+user_prefix = "me> "
+user_color = [255, 200, 0]
+user_align = "right"
+message_style = "bubbles"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.theme.user_prefix, "me> ");
+    assert_eq!(config.theme.assistant_prefix, "Assistant: ");
+    assert_eq!(config.theme.user_color, Some([255, 200, 0]));
+    assert_eq!(config.theme.assistant_color, None);
+    assert_eq!(config.theme.user_align, MessageAlign::Right);
+    assert_eq!(config.theme.message_style, MessageStyle::Bubbles);
+}
+
 #[test]
 fn test_config_load_from_path_missing_file() {
     let result = Config::load_from_path("/nonexistent/path/config.toml");
@@ -241,3 +273,628 @@ fn test_theme_config_color_methods() {
     assert_eq!(theme.bg_primary(), Color::Rgb(20, 20, 25));
     assert_eq!(theme.bg_secondary(), Color::Rgb(30, 30, 35));
 }
+
+#[test]
+fn test_config_load_default_provider_and_models() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm]
+provider = "copilot"
+
+[llm.models]
+github_copilot = "gpt-4.1"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.llm.default_provider(), Some(Provider::GitHubCopilot));
+    assert_eq!(config.llm.models["github_copilot"], "gpt-4.1");
+    assert_eq!(Config::default().llm.default_provider(), None);
+}
+
+#[test]
+fn test_config_load_layered_profile() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let main_path = temp_dir.path().join("config.toml");
+    let profile_path = temp_dir.path().join("profiles/work/config.toml");
+
+    let main = r#"
+[llm]
+model = "claude-sonnet-4-5"
+system_prompt = "Be brief."
+
+[behavior]
+minimap = true
+"#;
+    let profile = r#"
+[llm]
+provider = "openai"
+system_prompt = "You review code for Acme."
+
+[llm.models]
+openai = "gpt-4o"
+"#;
+
+    fs::write(&main_path, main).expect("Failed to write test config");
+    fs::create_dir_all(profile_path.parent().unwrap()).expect("Failed to create profile dir");
+    fs::write(&profile_path, profile).expect("Failed to write profile config");
+
+    let config = Config::load_layered(&main_path, Some(&profile_path), None).expect("Failed to load config");
+    assert_eq!(config.llm.default_provider(), Some(Provider::OpenAI));
+    assert_eq!(config.llm.models["openai"], "gpt-4o");
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("You review code for Acme."));
+    // Settings the profile leaves out come from the main file
+    assert_eq!(config.llm.model, "claude-sonnet-4-5");
+    assert!(config.behavior.minimap);
+
+    // A profile without a config file is the main config
+    let missing = temp_dir.path().join("profiles/home/config.toml");
+    let config = Config::load_layered(&main_path, Some(&missing), None).expect("Failed to load config");
+    assert_eq!(config.llm.default_provider(), None);
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("Be brief."));
+}
+
+#[test]
+fn test_config_project_file_overrides_model_choice() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let main_path = temp_dir.path().join("config.toml");
+    let repo = temp_dir.path().join("repo");
+    let nested = repo.join("src/deep");
+    fs::create_dir_all(&nested).expect("Failed to create repo dirs");
+    fs::write(&main_path, "[llm]\nsystem_prompt = \"Be brief.\"\ntemperature = 0.2\n")
+        .expect("Failed to write test config");
+    let project_toml = r#"
+[llm]
+provider = "ollama"
+system_prompt = "This repo uses Rust 2021."
+
+[llm.models]
+ollama = "qwen3:4b"
+"#;
+    fs::write(repo.join(PROJECT_FILE), project_toml).expect("Failed to write project file");
+
+    let path = Config::find_project_file(&nested).expect("project file not found");
+    assert_eq!(path, repo.join(PROJECT_FILE));
+    assert_eq!(Config::find_project_file(temp_dir.path()), None);
+
+    let project = Config::load_project(&path).expect("Failed to load project file");
+    let config = Config::load_layered(&main_path, None, Some(project)).expect("Failed to load config");
+    assert_eq!(config.llm.default_provider(), Some(Provider::Ollama));
+    assert_eq!(config.llm.models["ollama"], "qwen3:4b");
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("This repo uses Rust 2021."));
+    assert_eq!(config.llm.temperature, Some(0.2));
+}
+
+#[test]
+fn test_config_project_file_cannot_redirect_requests() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let path = temp_dir.path().join(PROJECT_FILE);
+
+    for contents in [
+        "[llm]\napi_base = \"https://attacker.example\"\n",
+        "[tools]\nenabled = true\n",
+        "[llm]\ntemperature = \"hot\"\n",
+    ] {
+        fs::write(&path, contents).expect("Failed to write project file");
+        assert!(Config::load_project(&path).is_err(), "accepted {}", contents);
+    }
+}
+
+#[test]
+fn test_config_load_provider_scopes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm.scopes.github_copilot]
+organization = "acme"
+
+[llm.scopes.openrouter]
+organization = "org-123"
+project = "proj-456"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    let copilot = &config.llm.scopes["github_copilot"];
+    assert_eq!(copilot.summary().as_deref(), Some("acme"));
+    assert_eq!(copilot.openai_headers().len(), 1);
+
+    let openrouter = &config.llm.scopes["openrouter"];
+    assert_eq!(openrouter.summary().as_deref(), Some("org-123 / proj-456"));
+    assert_eq!(
+        openrouter.openai_headers(),
+        vec![
+            ("OpenAI-Organization", "org-123".to_string()),
+            ("OpenAI-Project", "proj-456".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_config_load_signing() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm.signing]
+type = "hmac"
+secret_env = "GATEWAY_SECRET"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(
+        config.llm.signing,
+        Some(SigningConfig::Hmac {
+            secret_env: "GATEWAY_SECRET".to_string(),
+            header: "X-Signature".to_string(),
+            timestamp_header: "X-Signature-Timestamp".to_string(),
+        })
+    );
+    assert!(LlmConfigFile::default().signing.is_none());
+}
+
+#[test]
+fn test_config_load_stream() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm.stream]
+backpressure = "coalesce"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.llm.stream.backpressure, BackpressurePolicy::Coalesce);
+    assert_eq!(config.llm.stream.channel_size, 100);
+    assert_eq!(
+        LlmConfigFile::default().stream,
+        StreamConfig {
+            channel_size: 100,
+            backpressure: BackpressurePolicy::Block,
+        }
+    );
+}
+
+#[test]
+fn test_config_load_retry() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm.retry]
+max_retries = 5
+max_delay_ms = 10000
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(
+        config.llm.retry,
+        RetryConfig {
+            max_retries: 5,
+            base_delay_ms: 1000,
+            max_delay_ms: 10_000,
+        }
+    );
+    assert_eq!(LlmConfigFile::default().retry.max_retries, 3);
+}
+
+#[test]
+fn test_config_load_timeouts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm.timeouts]
+connect_secs = 5
+stall_secs = 0
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(
+        config.llm.timeouts,
+        TimeoutConfig {
+            connect_secs: 5,
+            read_secs: 300,
+            stall_secs: 0,
+        }
+    );
+    assert_eq!(LlmConfigFile::default().timeouts.stall_secs, 180);
+}
+
+#[test]
+fn test_config_load_warm_up() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm]
+warm_up = false
+keep_alive = "30m"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert!(!config.llm.warm_up);
+    assert_eq!(config.llm.keep_alive.as_deref(), Some("30m"));
+    assert!(LlmConfigFile::default().warm_up);
+}
+
+#[test]
+fn test_config_load_auto_model() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[llm]
+model = "auto"
+
+[llm.auto]
+cheap = "qwen3:0.6b"
+simple_max_tokens = 50
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.llm.model, "auto");
+    assert_eq!(config.llm.auto.cheap.as_deref(), Some("qwen3:0.6b"));
+    assert_eq!(config.llm.auto.strong, None);
+    assert_eq!(config.llm.auto.simple_max_tokens, 50);
+    assert_eq!(LlmConfigFile::default().auto.simple_max_tokens, 300);
+}
+
+#[test]
+fn test_config_load_reasoning() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, "[llm]\nreasoning = \"med\"\n").expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.llm.reasoning, Some(ReasoningEffort::Medium));
+    assert_eq!(LlmConfigFile::default().reasoning, None);
+}
+
+#[test]
+fn test_config_load_system_prompt() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, "[llm]\nsystem_prompt = \"Be brief.\"\n")
+        .expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("Be brief."));
+    assert_eq!(LlmConfigFile::default().system_prompt, None);
+}
+
+#[test]
+fn test_config_load_storage() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[storage]
+backend = "sqlite"
+path = "/tmp/scry/sessions.db"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.storage.backend, StorageBackend::Sqlite);
+    assert_eq!(
+        config.storage.resolved_path().unwrap(),
+        std::path::PathBuf::from("/tmp/scry/sessions.db")
+    );
+    assert_eq!(Config::default().storage.backend, StorageBackend::Json);
+    assert_eq!(config.storage.autosave_secs, 60);
+    assert_eq!(config.storage.checkpoint_secs, 10);
+    assert!(config.storage.atomic_writes);
+    assert_eq!(config.storage.fsync, FsyncPolicy::Critical);
+}
+
+#[test]
+fn test_config_load_sync() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[sync]
+backend = "webdav"
+remote = "https://dav.example.com/scry"
+username = "me"
+on_exit = true
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.sync.backend, Some(SyncBackend::Webdav));
+    assert_eq!(config.sync.remote, "https://dav.example.com/scry");
+    assert_eq!(config.sync.username.as_deref(), Some("me"));
+    assert!(config.sync.on_exit);
+    assert_eq!(config.sync.branch, "main");
+    assert_eq!(config.sync.password_env, "SCRY_SYNC_PASSWORD");
+    assert_eq!(Config::default().sync.backend, None);
+    assert!(!Config::default().sync.on_exit);
+}
+
+#[test]
+fn test_config_load_share() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[share]
+name = "ada"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.share.name.as_deref(), Some("ada"));
+    assert_eq!(config.share.bind, "0.0.0.0:8766");
+    assert_eq!(Config::default().share.name, None);
+}
+
+#[test]
+fn test_config_load_clipboard() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[clipboard]
+backend = "osc52"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.clipboard.backend, ClipboardBackend::Osc52);
+    assert_eq!(Config::default().clipboard.backend, ClipboardBackend::Auto);
+}
+
+#[test]
+fn test_config_load_appearance() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[appearance]
+mode = "schedule"
+light_from = "07:30"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.appearance.mode, AppearanceMode::Schedule);
+    assert_eq!(config.appearance.light_from, "07:30");
+    assert_eq!(config.appearance.light_until, "19:00");
+    assert_eq!(config.appearance.check_interval_secs, 60);
+    assert_eq!(Config::default().appearance.mode, AppearanceMode::Dark);
+
+    // The light palette keeps the user's prefixes
+    let light = config.with_appearance(Appearance::Light);
+    assert_eq!(light.theme.bg_primary, [250, 250, 252]);
+    assert_eq!(light.theme.user_prefix, config.theme.user_prefix);
+    assert_eq!(config.with_appearance(Appearance::Dark).theme.bg_primary, [20, 20, 25]);
+}
+
+#[test]
+fn test_config_load_durability() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[storage]
+autosave_secs = 0
+checkpoint_secs = 0
+atomic_writes = false
+fsync = "always"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.storage.autosave_secs, 0);
+    assert_eq!(config.storage.checkpoint_secs, 0);
+    let options = config.storage.write_options();
+    assert!(!options.atomic);
+    assert!(options.fsync);
+}
+
+#[test]
+fn test_config_load_variables() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[variables]
+env = ["USER", "PROJECT_*"]
+files = ["~/notes"]
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert!(config.variables.allows_env("USER"));
+    assert!(config.variables.allows_env("PROJECT_NAME"));
+    assert!(!config.variables.allows_env("AWS_SECRET_ACCESS_KEY"));
+    assert_eq!(config.variables.files.len(), 1);
+    assert!(Config::default().variables.env.is_empty());
+}
+
+#[test]
+fn test_config_load_preprocess() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[preprocess]
+steps = ["trim", "redact_secrets", "append_signature"]
+signature = "-- scry"
+
+[preprocess.templates]
+review = "Review this:"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(
+        config.preprocess.steps,
+        vec![
+            PreprocessStep::Trim,
+            PreprocessStep::RedactSecrets,
+            PreprocessStep::AppendSignature
+        ]
+    );
+    assert_eq!(config.preprocess.signature, "-- scry");
+    assert_eq!(config.preprocess.templates["review"], "Review this:");
+    assert_eq!(Config::default().preprocess.steps, vec![PreprocessStep::Trim]);
+}
+
+#[test]
+fn test_config_load_context_strategy() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[context]
+strategy = "sliding_window"
+window_messages = 8
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.context.strategy, ContextStrategy::SlidingWindow);
+    assert_eq!(config.context.window_messages, 8);
+    assert_eq!(config.context.reserve_tokens, 1024);
+    assert_eq!(Config::default().context.strategy, ContextStrategy::Truncate);
+}
+
+#[test]
+fn test_config_load_toasts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[toasts]
+position = "bottom_center"
+animation = "fade"
+max_visible = 5
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.toasts.position, ToastPosition::BottomCenter);
+    assert_eq!(config.toasts.animation, ToastAnimation::Fade);
+    assert_eq!(config.toasts.max_visible, 5);
+    assert_eq!(config.toasts.width, 40);
+    assert_eq!(Config::default().toasts.position, ToastPosition::TopRight);
+}
+
+#[test]
+fn test_config_load_tool_permissions() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[tools]
+default = "deny"
+root = "/work/app"
+paths = ["src", "README.md"]
+
+[tools.permissions]
+read_file = "allow"
+write_file = "ask"
+
+[tools.projects."/work/scratch"]
+write_file = "allow"
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    let tools = &config.tools;
+    assert_eq!(tools.default, Permission::Deny);
+    assert_eq!(tools.root.as_deref(), Some(std::path::Path::new("/work/app")));
+    assert_eq!(tools.paths.len(), 2);
+    assert_eq!(tools.permissions["read_file"], Permission::Allow);
+    assert_eq!(
+        tools.level("write_file", std::path::Path::new("/work/scratch/src")),
+        Permission::Allow
+    );
+    assert_eq!(tools.level("shell", std::path::Path::new("/work")), Permission::Deny);
+    assert_eq!(Config::default().tools.default, Permission::Ask);
+}
+
+#[test]
+fn test_config_load_pricing() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[pricing]
+"mistral-large" = { input = 2.0, output = 6.0 }
+
+[pricing."gpt-4o"]
+input = 1.5
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    assert_eq!(config.pricing.len(), 2);
+    assert_eq!(config.pricing["mistral-large"].output, 6.0);
+    assert_eq!(config.pricing["gpt-4o"].input, 1.5);
+    assert_eq!(config.pricing["gpt-4o"].output, 0.0);
+    assert!(Config::default().pricing.is_empty());
+}
+
+#[test]
+fn test_config_load_budget() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[budget]
+block = true
+
+[budget.providers.anthropic]
+daily = 2.0
+monthly = 20.0
+
+[budget.providers.openai]
+monthly = 5.0
+"#;
+
+    fs::write(&config_path, toml_content).expect("Failed to write test config");
+
+    let config = Config::load_from_path(&config_path).expect("Failed to load config");
+    let budget = &config.budget;
+    assert!(budget.block);
+    assert_eq!(budget.warn_at, 0.8);
+    assert_eq!(budget.providers["anthropic"].daily, Some(2.0));
+    assert_eq!(budget.providers["openai"].daily, None);
+    assert_eq!(budget.providers["openai"].monthly, Some(5.0));
+    assert!(!Config::default().budget.block);
+}